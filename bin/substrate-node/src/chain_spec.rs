@@ -17,7 +17,7 @@
 use sp_core::{Pair, Public, sr25519};
 use secretstore_runtime::{
 	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig,
-	SecretStoreConfig, SudoConfig, SystemConfig, WASM_BINARY, Signature
+	SecretStoreConfig, SudoConfig, SystemConfig, WASM_BINARY, Signature, HOURS,
 };
 use sp_consensus_aura::sr25519::{AuthorityId as AuraId};
 use sp_finality_grandpa::{AuthorityId as GrandpaId};
@@ -161,8 +161,12 @@ fn testnet_genesis(
 			],
 			server_key_generation_fee: 0,
 			server_key_retrieval_fee: 0,
+			server_key_deletion_fee: 0,
 			document_key_store_fee: 0,
 			document_key_shadow_retrieval_fee: 0,
+			schnorr_signing_fee: 0,
+			ecdsa_signing_fee: 0,
+			request_lifetime: HOURS,
 		})
 	}
 }
@@ -18,7 +18,7 @@ use sp_consensus_aura::sr25519::{AuthorityPair as AuraPair};
 use sc_cli::VersionInfo;
 use crate::service;
 use crate::chain_spec;
-use crate::cli::Cli;
+use crate::cli::{Cli, Subcommand};
 
 /// Parse and run command line arguments
 pub fn run(version: VersionInfo) -> sc_cli::Result<()> {
@@ -27,7 +27,7 @@ pub fn run(version: VersionInfo) -> sc_cli::Result<()> {
 	let mut config = sc_service::Configuration::from_version(&version);
 
 	match opt.subcommand {
-		Some(subcommand) => {
+		Some(Subcommand::Base(subcommand)) => {
 			subcommand.init(&version)?;
 			subcommand.update_config(&mut config, chain_spec::load_spec, &version)?;
 			subcommand.run(
@@ -35,6 +35,11 @@ pub fn run(version: VersionInfo) -> sc_cli::Result<()> {
 				|config: _| Ok(new_full_start!(config).0),
 			)
 		},
+		Some(Subcommand::Benchmark(cmd)) => {
+			cmd.init(&version)?;
+			cmd.update_config(&mut config, chain_spec::load_spec, &version)?;
+			cmd.run::<secretstore_runtime::opaque::Block, service::Executor>(config)
+		},
 		None => {
 			opt.run.init(&version)?;
 			opt.run.update_config(&mut config, chain_spec::load_spec, &version)?;
@@ -31,6 +31,7 @@ native_executor_instance!(
 	pub Executor,
 	secretstore_runtime::api::dispatch,
 	secretstore_runtime::native_version,
+	frame_benchmarking::benchmarking::HostFunctions,
 );
 
 /// Starts a `ServiceBuilder` for a full service.
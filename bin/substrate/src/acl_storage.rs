@@ -15,9 +15,10 @@
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
 use codec::Encode;
+use futures::future::{BoxFuture, FutureExt};
 use primitives::{
 	Address, ServerKeyId,
-	acl_storage::AclStorage,
+	acl_storage::{AsyncAclStorage, KeyAccessLevel},
 	error::Error,
 };
 use crate::substrate_client::{BlockRef, Client};
@@ -35,15 +36,27 @@ impl OnChainAclStorage {
 	}
 }
 
-impl AclStorage for OnChainAclStorage {
-	fn check(&self, requester_address: Address, server_key_id: &ServerKeyId) -> Result<bool, Error> {
+impl AsyncAclStorage for OnChainAclStorage {
+	fn check(&self, requester_address: Address, server_key_id: &ServerKeyId) -> BoxFuture<'static, Result<bool, Error>> {
+		self.check_access(requester_address, server_key_id, KeyAccessLevel::Decrypt)
+	}
+
+	fn check_access(
+		&self,
+		requester_address: Address,
+		server_key_id: &ServerKeyId,
+		level: KeyAccessLevel,
+	) -> BoxFuture<'static, Result<bool, Error>> {
+		let level = match level {
+			KeyAccessLevel::Read => runtime_primitives::acl_storage::KeyAccessLevel::Read,
+			KeyAccessLevel::Decrypt => runtime_primitives::acl_storage::KeyAccessLevel::Decrypt,
+		};
+
 		// we always check at best block - there's no need to use deprecated ACLs
-		futures::executor::block_on(async {
-			self.client.call_runtime_method(
-				BlockRef::LocalBest,
-				"SecretStoreAclApi_check",
-				(requester_address, server_key_id).encode(),
-			).await.map_err(|err| Error::Internal(format!("{:?}", err)))
-		})
+		self.client.call_runtime_method(
+			BlockRef::LocalBest,
+			"SecretStoreAclApi_check_access",
+			(requester_address, server_key_id, level).encode(),
+		).map(|result| result.map_err(|err| Error::Internal(format!("{:?}", err)))).boxed()
 	}
 }
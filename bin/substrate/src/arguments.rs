@@ -14,7 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::str::FromStr;
+use std::{
+	net::IpAddr,
+	str::FromStr,
+	time::Duration,
+};
 use clap::ArgMatches;
 use serde::Deserialize;
 use parity_crypto::publickey::Secret;
@@ -25,8 +29,20 @@ use parity_crypto::publickey::Secret;
 pub struct Arguments {
 	pub self_secret: Secret,
 	pub db_path: String,
+	pub db_passphrase: Option<String>,
 	pub net_host: String,
 	pub net_port: u16,
+	pub net_public_host: String,
+	pub net_public_port: u16,
+	pub session_timeout_interval: Duration,
+	pub session_keep_alive_interval: Duration,
+	pub net_maintain_interval: Duration,
+	pub net_keep_alive_send_interval: Duration,
+	pub net_keep_alive_disconnect_interval: Duration,
+	pub key_versions_to_keep: usize,
+	pub http_host: String,
+	pub http_port: Option<u16>,
+	pub http_cors: String,
 	pub sub_host: String,
 	pub sub_port: u16,
 	pub sub_signer: String,
@@ -51,10 +67,34 @@ struct TomlArguments {
 	self_secret: Option<Secret>,
 	#[serde(default, rename = "db-path")]
 	db_path: Option<String>,
+	#[serde(default, rename = "db-passphrase")]
+	db_passphrase: Option<String>,
 	#[serde(default, rename = "net-host")]
 	net_host: Option<String>,
 	#[serde(default, rename = "net-port")]
 	net_port: Option<u16>,
+	#[serde(default, rename = "net-public-host")]
+	net_public_host: Option<String>,
+	#[serde(default, rename = "net-public-port")]
+	net_public_port: Option<u16>,
+	#[serde(default, rename = "session-timeout-interval")]
+	session_timeout_interval: Option<u64>,
+	#[serde(default, rename = "session-keep-alive-interval")]
+	session_keep_alive_interval: Option<u64>,
+	#[serde(default, rename = "net-maintain-interval")]
+	net_maintain_interval: Option<u64>,
+	#[serde(default, rename = "net-keep-alive-send-interval")]
+	net_keep_alive_send_interval: Option<u64>,
+	#[serde(default, rename = "net-keep-alive-disconnect-interval")]
+	net_keep_alive_disconnect_interval: Option<u64>,
+	#[serde(default, rename = "key-versions-to-keep")]
+	key_versions_to_keep: Option<usize>,
+	#[serde(default, rename = "http-host")]
+	http_host: Option<String>,
+	#[serde(default, rename = "http-port")]
+	http_port: Option<u16>,
+	#[serde(default, rename = "http-cors")]
+	http_cors: Option<String>,
 	#[serde(default, rename = "sub-host")]
 	sub_host: Option<String>,
 	#[serde(default, rename = "sub-port")]
@@ -95,6 +135,63 @@ pub fn parse_arguments<'a>(
 			)?,
 		None => Default::default(),
 	};
+	let default_cluster_config = key_server::ClusterConfiguration::default();
+	let net_host = matches.value_of("net-host")
+		.map(str::to_owned)
+		.or_else(|| toml_arguments.net_host.clone())
+		.unwrap_or_else(|| "0.0.0.0".into());
+	let net_port = matches.value_of("net-port")
+		.map(|net_port| u16::from_str(net_port).map_err(|err| format!("{}", err)))
+		.or_else(|| toml_arguments.net_port.clone().map(Ok))
+		.unwrap_or_else(|| Ok(8083))?;
+	let session_timeout_interval = parse_interval(
+		matches,
+		"session-timeout-interval",
+		toml_arguments.session_timeout_interval,
+		default_cluster_config.session_timeout_interval,
+	)?;
+	let session_keep_alive_interval = parse_interval(
+		matches,
+		"session-keep-alive-interval",
+		toml_arguments.session_keep_alive_interval,
+		default_cluster_config.session_keep_alive_interval,
+	)?;
+	let net_maintain_interval = parse_interval(
+		matches,
+		"net-maintain-interval",
+		toml_arguments.net_maintain_interval,
+		default_cluster_config.maintain_interval,
+	)?;
+	let net_keep_alive_send_interval = parse_interval(
+		matches,
+		"net-keep-alive-send-interval",
+		toml_arguments.net_keep_alive_send_interval,
+		default_cluster_config.keep_alive_send_interval,
+	)?;
+	let net_keep_alive_disconnect_interval = parse_interval(
+		matches,
+		"net-keep-alive-disconnect-interval",
+		toml_arguments.net_keep_alive_disconnect_interval,
+		default_cluster_config.keep_alive_disconnect_interval,
+	)?;
+	if session_keep_alive_interval >= session_timeout_interval {
+		return Err("session-keep-alive-interval must be less than session-timeout-interval".into());
+	}
+	if net_keep_alive_disconnect_interval <= net_keep_alive_send_interval {
+		return Err("net-keep-alive-disconnect-interval must be greater than net-keep-alive-send-interval".into());
+	}
+	let net_public_host = match matches.value_of("net-public-host")
+		.map(str::to_owned)
+		.or_else(|| toml_arguments.net_public_host.clone())
+	{
+		Some(net_public_host) => net_public_host,
+		None if is_unspecified_address(&net_host) =>
+			return Err("net-public-host must be specified when net-host is an unspecified address".into()),
+		None => net_host.clone(),
+	};
+	if is_unspecified_address(&net_public_host) {
+		return Err("net-public-host must not be an unspecified address".into());
+	}
 
 	Ok(Arguments {
 		self_secret: matches.value_of("self-secret")
@@ -105,14 +202,37 @@ pub fn parse_arguments<'a>(
 			.map(str::to_owned)
 			.or_else(|| toml_arguments.db_path.clone())
 			.unwrap_or_else(|| "db".into()),
-		net_host: matches.value_of("net-host")
+		db_passphrase: matches.value_of("db-passphrase")
 			.map(str::to_owned)
-			.or_else(|| toml_arguments.net_host.clone())
-			.unwrap_or_else(|| "0.0.0.0".into()),
-		net_port: matches.value_of("net-port")
-			.map(|net_port| u16::from_str(net_port).map_err(|err| format!("{}", err)))
-			.or_else(|| toml_arguments.net_port.clone().map(Ok))
-			.unwrap_or_else(|| Ok(8083))?,
+			.or_else(|| toml_arguments.db_passphrase.clone()),
+		net_host,
+		net_port,
+		net_public_host,
+		net_public_port: matches.value_of("net-public-port")
+			.map(|net_public_port| u16::from_str(net_public_port).map_err(|err| format!("{}", err)))
+			.or_else(|| toml_arguments.net_public_port.clone().map(Ok))
+			.unwrap_or_else(|| Ok(net_port))?,
+		session_timeout_interval,
+		session_keep_alive_interval,
+		net_maintain_interval,
+		net_keep_alive_send_interval,
+		net_keep_alive_disconnect_interval,
+		key_versions_to_keep: matches.value_of("key-versions-to-keep")
+			.map(|key_versions_to_keep| usize::from_str(key_versions_to_keep).map_err(|err| format!("{}", err)))
+			.or_else(|| toml_arguments.key_versions_to_keep.clone().map(Ok))
+			.unwrap_or_else(|| Ok(default_cluster_config.key_versions_to_keep))?,
+		http_host: matches.value_of("http-host")
+			.map(str::to_owned)
+			.or_else(|| toml_arguments.http_host.clone())
+			.unwrap_or_else(|| "127.0.0.1".into()),
+		http_port: matches.value_of("http-port")
+			.map(|http_port| u16::from_str(http_port).map_err(|err| format!("{}", err)))
+			.or_else(|| toml_arguments.http_port.clone().map(Ok))
+			.transpose()?,
+		http_cors: matches.value_of("http-cors")
+			.map(str::to_owned)
+			.or_else(|| toml_arguments.http_cors.clone())
+			.unwrap_or_else(|| "none".into()),
 		sub_host: substrate_arguments.sub_host,
 		sub_port: substrate_arguments.sub_port,
 		sub_signer: substrate_arguments.sub_signer,
@@ -152,6 +272,32 @@ pub fn parse_substrate_arguments<'a>(
 	})
 }
 
+/// Parse interval (in seconds) argument. Zero intervals are rejected.
+fn parse_interval(
+	matches: &ArgMatches,
+	name: &str,
+	toml_value: Option<u64>,
+	default_value: Duration,
+) -> Result<Duration, String> {
+	let interval = matches.value_of(name)
+		.map(|interval| u64::from_str(interval).map_err(|err| format!("{}", err)))
+		.or_else(|| toml_value.map(Ok))
+		.map(|interval| interval.map(Duration::from_secs))
+		.unwrap_or_else(|| Ok(default_value))?;
+	if interval == Duration::from_secs(0) {
+		return Err(format!("{} must be positive", name));
+	}
+
+	Ok(interval)
+}
+
+/// Returns true if host is an unspecified address (like 0.0.0.0).
+fn is_unspecified_address(host: &str) -> bool {
+	IpAddr::from_str(host)
+		.map(|ip| ip.is_unspecified())
+		.unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
 	use std::io::Write;
@@ -172,8 +318,20 @@ mod tests {
 			Ok(Arguments {
 				self_secret: Secret::from([1u8; 32]),
 				db_path: "db".into(),
+				db_passphrase: None,
 				net_host: "nethost.com".into(),
 				net_port: 8083,
+				net_public_host: "nethost.com".into(),
+				net_public_port: 8083,
+				session_timeout_interval: Duration::from_secs(60),
+				session_keep_alive_interval: Duration::from_secs(30),
+				net_maintain_interval: Duration::from_secs(10),
+				net_keep_alive_send_interval: Duration::from_secs(30),
+				net_keep_alive_disconnect_interval: Duration::from_secs(60),
+				key_versions_to_keep: 2,
+				http_host: "127.0.0.1".into(),
+				http_port: None,
+				http_cors: "none".into(),
 				sub_host: "localhost".into(),
 				sub_port: 4242,
 				sub_signer: "//Bob".into(),
@@ -191,8 +349,20 @@ mod tests {
 				"parity-secretstore-substrate",
 				"--self-secret=0101010101010101010101010101010101010101010101010101010101010101",
 				"--db-path=mydb",
+				"--db-passphrase=dbpassword",
 				"--net-host=nethost.com",
 				"--net-port=42",
+				"--net-public-host=publichost.com",
+				"--net-public-port=4343",
+				"--session-timeout-interval=600",
+				"--session-keep-alive-interval=300",
+				"--net-maintain-interval=100",
+				"--net-keep-alive-send-interval=300",
+				"--net-keep-alive-disconnect-interval=600",
+				"--key-versions-to-keep=3",
+				"--http-host=httphost.com",
+				"--http-port=8082",
+				"--http-cors=all",
 				"--sub-host=subhost.com",
 				"--sub-port=4242",
 				"--sub-signer=//Bob",
@@ -201,8 +371,20 @@ mod tests {
 			Ok(Arguments {
 				self_secret: Secret::from([1u8; 32]),
 				db_path: "mydb".into(),
+				db_passphrase: Some("dbpassword".into()),
 				net_host: "nethost.com".into(),
 				net_port: 42,
+				net_public_host: "publichost.com".into(),
+				net_public_port: 4343,
+				session_timeout_interval: Duration::from_secs(600),
+				session_keep_alive_interval: Duration::from_secs(300),
+				net_maintain_interval: Duration::from_secs(100),
+				net_keep_alive_send_interval: Duration::from_secs(300),
+				net_keep_alive_disconnect_interval: Duration::from_secs(600),
+				key_versions_to_keep: 3,
+				http_host: "httphost.com".into(),
+				http_port: Some(8082),
+				http_cors: "all".into(),
 				sub_host: "subhost.com".into(),
 				sub_port: 4242,
 				sub_signer: "//Bob".into(),
@@ -234,8 +416,20 @@ sub-signer = "//Bob"
 			Ok(Arguments {
 				self_secret: Secret::from([1u8; 32]),
 				db_path: "db".into(),
+				db_passphrase: None,
 				net_host: "nethost.com".into(),
 				net_port: 8083,
+				net_public_host: "nethost.com".into(),
+				net_public_port: 8083,
+				session_timeout_interval: Duration::from_secs(60),
+				session_keep_alive_interval: Duration::from_secs(30),
+				net_maintain_interval: Duration::from_secs(10),
+				net_keep_alive_send_interval: Duration::from_secs(30),
+				net_keep_alive_disconnect_interval: Duration::from_secs(60),
+				key_versions_to_keep: 2,
+				http_host: "127.0.0.1".into(),
+				http_port: None,
+				http_cors: "none".into(),
 				sub_host: "localhost".into(),
 				sub_port: 4242,
 				sub_signer: "//Bob".into(),
@@ -253,8 +447,20 @@ sub-signer = "//Bob"
 		std::fs::File::create(temp_file_path.clone()).unwrap().write_all(r#"
 self-secret = "0101010101010101010101010101010101010101010101010101010101010101"
 db-path = "mydb"
+db-passphrase = "dbpassword"
 net-host = "nethost.com"
 net-port = 42
+net-public-host = "publichost.com"
+net-public-port = 4343
+session-timeout-interval = 600
+session-keep-alive-interval = 300
+net-maintain-interval = 100
+net-keep-alive-send-interval = 300
+net-keep-alive-disconnect-interval = 600
+key-versions-to-keep = 3
+http-host = "httphost.com"
+http-port = 8082
+http-cors = "all"
 sub-host = "subhost.com"
 sub-port = 4242
 sub-signer = "//Bob"
@@ -270,8 +476,20 @@ sub-signer-password = "password"
 			Ok(Arguments {
 				self_secret: Secret::from([1u8; 32]),
 				db_path: "mydb".into(),
+				db_passphrase: Some("dbpassword".into()),
 				net_host: "nethost.com".into(),
 				net_port: 42,
+				net_public_host: "publichost.com".into(),
+				net_public_port: 4343,
+				session_timeout_interval: Duration::from_secs(600),
+				session_keep_alive_interval: Duration::from_secs(300),
+				net_maintain_interval: Duration::from_secs(100),
+				net_keep_alive_send_interval: Duration::from_secs(300),
+				net_keep_alive_disconnect_interval: Duration::from_secs(600),
+				key_versions_to_keep: 3,
+				http_host: "httphost.com".into(),
+				http_port: Some(8082),
+				http_cors: "all".into(),
 				sub_host: "subhost.com".into(),
 				sub_port: 4242,
 				sub_signer: "//Bob".into(),
@@ -300,8 +518,20 @@ self-secret = "0202020202020202020202020202020202020202020202020202020202020202"
 			Ok(Arguments {
 				self_secret: Secret::from([1u8; 32]),
 				db_path: "db".into(),
+				db_passphrase: None,
 				net_host: "0.0.0.0".into(),
 				net_port: 8083,
+				net_public_host: "0.0.0.0".into(),
+				net_public_port: 8083,
+				session_timeout_interval: Duration::from_secs(60),
+				session_keep_alive_interval: Duration::from_secs(30),
+				net_maintain_interval: Duration::from_secs(10),
+				net_keep_alive_send_interval: Duration::from_secs(30),
+				net_keep_alive_disconnect_interval: Duration::from_secs(60),
+				key_versions_to_keep: 2,
+				http_host: "127.0.0.1".into(),
+				http_port: None,
+				http_cors: "none".into(),
 				sub_host: "localhost".into(),
 				sub_port: 9944,
 				sub_signer: "//Alice".into(),
@@ -309,4 +539,87 @@ self-secret = "0202020202020202020202020202020202020202020202020202020202020202"
 			}),
 		);
 	}
+
+	#[test]
+	fn arguments_reject_zero_interval() {
+		let yaml = clap::load_yaml!("cli.yml");
+		let clap_app = clap::App::from_yaml(yaml);
+
+		assert_eq!(
+			parse_arguments(&clap_app.get_matches_from(vec![
+				"parity-secretstore-substrate",
+				"--self-secret",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+				"--net-maintain-interval=0",
+			])),
+			Err("net-maintain-interval must be positive".into()),
+		);
+	}
+
+	#[test]
+	fn arguments_reject_session_keep_alive_interval_not_less_than_timeout() {
+		let yaml = clap::load_yaml!("cli.yml");
+		let clap_app = clap::App::from_yaml(yaml);
+
+		assert_eq!(
+			parse_arguments(&clap_app.get_matches_from(vec![
+				"parity-secretstore-substrate",
+				"--self-secret",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+				"--session-timeout-interval=30",
+				"--session-keep-alive-interval=30",
+			])),
+			Err("session-keep-alive-interval must be less than session-timeout-interval".into()),
+		);
+	}
+
+	#[test]
+	fn arguments_reject_keep_alive_disconnect_interval_not_greater_than_send() {
+		let yaml = clap::load_yaml!("cli.yml");
+		let clap_app = clap::App::from_yaml(yaml);
+
+		assert_eq!(
+			parse_arguments(&clap_app.get_matches_from(vec![
+				"parity-secretstore-substrate",
+				"--self-secret",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+				"--net-keep-alive-send-interval=60",
+				"--net-keep-alive-disconnect-interval=60",
+			])),
+			Err("net-keep-alive-disconnect-interval must be greater than net-keep-alive-send-interval".into()),
+		);
+	}
+
+	#[test]
+	fn arguments_require_public_host_when_listening_on_unspecified_address() {
+		let yaml = clap::load_yaml!("cli.yml");
+		let clap_app = clap::App::from_yaml(yaml);
+
+		assert_eq!(
+			parse_arguments(&clap_app.get_matches_from(vec![
+				"parity-secretstore-substrate",
+				"--self-secret",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+			])),
+			Err("net-public-host must be specified when net-host is an unspecified address".into()),
+		);
+		assert_eq!(
+			parse_arguments(&clap_app.get_matches_from(vec![
+				"parity-secretstore-substrate",
+				"--self-secret",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+				"--net-public-host=::",
+			])),
+			Err("net-public-host must not be an unspecified address".into()),
+		);
+		assert_eq!(
+			parse_arguments(&clap_app.get_matches_from(vec![
+				"parity-secretstore-substrate",
+				"--self-secret",
+				"0101010101010101010101010101010101010101010101010101010101010101",
+				"--net-public-host=publichost.com",
+			])).map(|arguments| (arguments.net_host, arguments.net_public_host)),
+			Ok(("0.0.0.0".into(), "publichost.com".into())),
+		);
+	}
 }
@@ -28,7 +28,7 @@ use futures::{
 use log::error;
 use codec::Encode;
 use primitives::{
-	Address, KeyServerId, ServerKeyId,
+	Address, H256, KeyServerId, ServerKeyId,
 	key_server_set::KeyServerSet,
 	requester::Requester,
 	service::ServiceTask,
@@ -191,6 +191,29 @@ impl Blockchain for SecretStoreBlockchain {
 		).boxed()
 	}
 
+	fn server_key_deletion_tasks(
+		&self,
+		block_hash: Self::BlockHash,
+		range: Range<usize>,
+	) -> Self::PendingEventsStream {
+		self.pending_tasks(
+			block_hash,
+			"SecretStoreServiceApi_server_key_deletion_tasks",
+			range,
+		).boxed()
+	}
+
+	fn is_server_key_deletion_response_required(
+		&self,
+		key_id: ServerKeyId,
+		key_server_id: KeyServerId,
+	) -> Self::ResponseRequiredFuture {
+		self.is_response_required(
+			"SecretStoreServiceApi_is_server_key_deletion_response_required",
+			(key_server_id, key_id).encode(),
+		).boxed()
+	}
+
 	fn document_key_store_tasks(
 		&self,
 		block_hash: Self::BlockHash,
@@ -237,6 +260,56 @@ impl Blockchain for SecretStoreBlockchain {
 			(key_server_id, key_id, requester).encode(),
 		).boxed()
 	}
+
+	fn schnorr_signing_tasks(
+		&self,
+		block_hash: Self::BlockHash,
+		range: Range<usize>,
+	) -> Self::PendingEventsStream {
+		self.pending_tasks(
+			block_hash,
+			"SecretStoreServiceApi_schnorr_signing_tasks",
+			range,
+		).boxed()
+	}
+
+	fn is_schnorr_signing_response_required(
+		&self,
+		key_id: ServerKeyId,
+		requester: Address,
+		message: H256,
+		key_server_id: KeyServerId,
+	) -> Self::ResponseRequiredFuture {
+		self.is_response_required(
+			"SecretStoreServiceApi_is_schnorr_signing_response_required",
+			(key_server_id, key_id, requester, message).encode(),
+		).boxed()
+	}
+
+	fn ecdsa_signing_tasks(
+		&self,
+		block_hash: Self::BlockHash,
+		range: Range<usize>,
+	) -> Self::PendingEventsStream {
+		self.pending_tasks(
+			block_hash,
+			"SecretStoreServiceApi_ecdsa_signing_tasks",
+			range,
+		).boxed()
+	}
+
+	fn is_ecdsa_signing_response_required(
+		&self,
+		key_id: ServerKeyId,
+		requester: Address,
+		message: H256,
+		key_server_id: KeyServerId,
+	) -> Self::ResponseRequiredFuture {
+		self.is_response_required(
+			"SecretStoreServiceApi_is_ecdsa_signing_response_required",
+			(key_server_id, key_id, requester, message).encode(),
+		).boxed()
+	}
 }
 
 impl MaybeSecretStoreEvent for SubstrateServiceTaskWrapper {
@@ -288,6 +361,28 @@ impl MaybeSecretStoreEvent for SubstrateServiceTaskWrapper {
 					key_id, None,
 				)
 			)),
+			SubstrateServiceTaskWrapper::Event(
+				crate::runtime::Event::secretstore_runtime_module(
+					runtime_module::Event::ServerKeyDeletionRequested(
+						key_id, author,
+					),
+				)
+			) => Some(BlockchainServiceTask::Regular(
+				origin,
+				ServiceTask::DeleteServerKey(
+					key_id, Requester::Address(author),
+				)
+			)),
+			SubstrateServiceTaskWrapper::Task(
+				runtime_primitives::service::ServiceTask::DeleteServerKey(
+					key_id, author,
+				)
+			) => Some(BlockchainServiceTask::Regular(
+				origin,
+				ServiceTask::DeleteServerKey(
+					key_id, Requester::Address(author),
+				)
+			)),
 			SubstrateServiceTaskWrapper::Event(
 				crate::runtime::Event::secretstore_runtime_module(
 					runtime_module::Event::DocumentKeyStoreRequested(
@@ -350,6 +445,50 @@ impl MaybeSecretStoreEvent for SubstrateServiceTaskWrapper {
 				key_id,
 				Requester::Public(requester_public),
 			)),
+			SubstrateServiceTaskWrapper::Event(
+				crate::runtime::Event::secretstore_runtime_module(
+					runtime_module::Event::SchnorrSigningRequested(
+						key_id, requester_address, message,
+					),
+				)
+			) => Some(BlockchainServiceTask::Regular(
+				origin,
+				ServiceTask::SchnorrSignMessage(
+					key_id, Requester::Address(requester_address), message,
+				)
+			)),
+			SubstrateServiceTaskWrapper::Task(
+				runtime_primitives::service::ServiceTask::SchnorrSignMessage(
+					key_id, requester_address, message,
+				)
+			) => Some(BlockchainServiceTask::Regular(
+				origin,
+				ServiceTask::SchnorrSignMessage(
+					key_id, Requester::Address(requester_address), message,
+				)
+			)),
+			SubstrateServiceTaskWrapper::Event(
+				crate::runtime::Event::secretstore_runtime_module(
+					runtime_module::Event::EcdsaSigningRequested(
+						key_id, requester_address, message,
+					),
+				)
+			) => Some(BlockchainServiceTask::Regular(
+				origin,
+				ServiceTask::EcdsaSignMessage(
+					key_id, Requester::Address(requester_address), message,
+				)
+			)),
+			SubstrateServiceTaskWrapper::Task(
+				runtime_primitives::service::ServiceTask::EcdsaSignMessage(
+					key_id, requester_address, message,
+				)
+			) => Some(BlockchainServiceTask::Regular(
+				origin,
+				ServiceTask::EcdsaSignMessage(
+					key_id, Requester::Address(requester_address), message,
+				)
+			)),
 			_ => None,
 		}
 	}
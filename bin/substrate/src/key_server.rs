@@ -20,17 +20,35 @@ use primitives::{
 	executor::TokioHandle,
 	key_server_key_pair::KeyServerKeyPair,
 };
-use key_server::{ClusterConfiguration, KeyServerImpl, db_key_storage::PersistentKeyStorage};
+use key_server::{
+	ClusterConfiguration, KeyServerImpl,
+	db_key_storage::{KeyShareSealingKey, PersistentKeyStorage},
+};
 use crate::{
 	acl_storage::OnChainAclStorage,
+	arguments::Arguments,
 	key_server_set::OnChainKeyServerSet,
 };
 
+/// Open key server database. Key shares are sealed with the key derived from given passphrase
+/// or, if it is not specified, from the key server key pair.
+pub fn open_key_storage(
+	db_path: &str,
+	db_passphrase: Option<&str>,
+	key_server_key_pair: &dyn KeyServerKeyPair,
+) -> Result<PersistentKeyStorage, Error> {
+	let sealing_key = match db_passphrase {
+		Some(db_passphrase) => KeyShareSealingKey::from_passphrase(db_passphrase)?,
+		None => KeyShareSealingKey::from_key_server_key_pair(key_server_key_pair)?,
+	};
+	PersistentKeyStorage::new(std::path::Path::new(db_path), sealing_key)
+}
+
 /// Start Secret Store key server.
 pub fn start(
 	executor: TokioHandle,
 	key_server_key_pair: Arc<dyn KeyServerKeyPair>,
-	listen_port: u16,
+	arguments: &Arguments,
 	key_storage: Arc<PersistentKeyStorage>,
 	acl_storage: Arc<OnChainAclStorage>,
 	key_server_set: Arc<OnChainKeyServerSet>,
@@ -38,17 +56,24 @@ pub fn start(
 	let key_server_config = ClusterConfiguration {
 		admin_address: None,
 		auto_migrate_enabled: true,
+		session_timeout_interval: arguments.session_timeout_interval,
+		session_keep_alive_interval: arguments.session_keep_alive_interval,
+		maintain_interval: arguments.net_maintain_interval,
+		keep_alive_send_interval: arguments.net_keep_alive_send_interval,
+		keep_alive_disconnect_interval: arguments.net_keep_alive_disconnect_interval,
+		key_versions_to_keep: arguments.key_versions_to_keep,
+		..Default::default()
 	};
 	key_server::Builder::new()
 		.with_self_key_pair(key_server_key_pair)
-		.with_acl_storage(acl_storage)
+		.with_async_acl_storage(acl_storage)
 		.with_key_storage(key_storage)
 		.with_config(key_server_config)
 		.build_for_tcp(
 			executor,
 			key_server::network::tcp::NodeAddress {
-				address: "127.0.0.1".into(),
-				port: listen_port,
+				address: arguments.net_host.clone(),
+				port: arguments.net_port,
 			},
 			key_server_set,
 		)
@@ -16,7 +16,7 @@
 
 use std::{
 	collections::BTreeMap,
-	net::SocketAddr,
+	net::{SocketAddr, ToSocketAddrs},
 	str::FromStr,
 	sync::Arc,
 };
@@ -117,6 +117,42 @@ impl OnChainKeyServerSet {
 			})
 		);
 	}
+
+	/// Check that the address of this key server in the on-chain key server set (if it is there)
+	/// matches the address that is advertised to other key servers.
+	///
+	/// Public host may be either IP address or host name. Host name is resolved and the
+	/// registered address must match one of resolved addresses.
+	pub async fn ensure_self_address(&self, public_host: &str, public_port: u16) -> Result<(), String> {
+		let public_addresses = (public_host, public_port).to_socket_addrs()
+			.map_err(|err| format!("Invalid public address {}:{}: {}", public_host, public_port, err))?
+			.collect::<Vec<_>>();
+		let snapshot: runtime_primitives::key_server_set::KeyServerSetSnapshot = self.client.call_runtime_method(
+			BlockRef::LocalBest,
+			"SecretStoreKeyServerSetApi_snapshot",
+			self.self_id.encode(),
+		).await.map_err(|err| format!("Failed to read key server set snapshot: {:?}", err))?;
+
+		let self_addresses = snapshot.current_set.into_iter()
+			.chain(snapshot.new_set)
+			.chain(snapshot.migration.into_iter().flat_map(|migration| migration.set))
+			.filter(|(server_id, _)| *server_id == self.self_id)
+			.map(|(_, server_address)| parse_socket_addr(server_address));
+		for self_address in self_addresses {
+			let self_address = self_address?;
+			if !public_addresses.contains(&self_address) {
+				return Err(format!(
+					"Key server {} is registered with address {}, but advertises address {}:{}",
+					self.self_id,
+					self_address,
+					public_host,
+					public_port,
+				));
+			}
+		}
+
+		Ok(())
+	}
 }
 
 impl KeyServerSet for OnChainKeyServerSet {
@@ -139,29 +175,32 @@ impl KeyServerSet for OnChainKeyServerSet {
 			}
 		}
 
-		let submit_result = futures::executor::block_on(async {
-			self.client.submit_transaction(crate::runtime::Call::SecretStore(
+		// transaction is retried until it is included into block => do not block caller
+		let client = self.client.clone();
+		let self_id = self.self_id.clone();
+		self.sync_futures_pool.spawn_ok(async move {
+			let submit_result = client.submit_transaction(crate::runtime::Call::SecretStore(
 				crate::runtime::SecretStoreCall::start_migration(
 					migration_id,
 				),
-			)).await
-		});
+			)).await;
 
-		match submit_result {
-			Ok(tx_hash) => trace!(
-				target: "secretstore_net",
-				"{}: Start migration({}) transaction submitted: {:?}",
-				self.self_id,
-				migration_id,
-				tx_hash,
-			),
-			Err(error) => error!(
-				target: "secretstore_net",
-				"{}: Error submitting start migration transaction: {:?}",
-				self.self_id,
-				error,
-			),
-		}
+			match submit_result {
+				Ok(tx_hash) => trace!(
+					target: "secretstore_net",
+					"{}: Start migration({}) transaction submitted: {:?}",
+					self_id,
+					migration_id,
+					tx_hash,
+				),
+				Err(error) => error!(
+					target: "secretstore_net",
+					"{}: Error submitting start migration transaction: {:?}",
+					self_id,
+					error,
+				),
+			}
+		});
 	}
 
 	fn confirm_migration(&self, migration_id: MigrationId) {
@@ -173,29 +212,32 @@ impl KeyServerSet for OnChainKeyServerSet {
 			}
 		}
 
-		let submit_result = futures::executor::block_on(async {
-			self.client.submit_transaction(crate::runtime::Call::SecretStore(
+		// transaction is retried until it is included into block => do not block caller
+		let client = self.client.clone();
+		let self_id = self.self_id.clone();
+		self.sync_futures_pool.spawn_ok(async move {
+			let submit_result = client.submit_transaction(crate::runtime::Call::SecretStore(
 				crate::runtime::SecretStoreCall::confirm_migration(
 					migration_id,
 				),
-			)).await
-		});
+			)).await;
 
-		match submit_result {
-			Ok(tx_hash) => trace!(
-				target: "secretstore_net",
-				"{}: Migration({}) confirmation transaction submitted: {:?}",
-				self.self_id,
-				migration_id,
-				tx_hash,
-			),
-			Err(error) => error!(
-				target: "secretstore_net",
-				"{}: Error submitting confirm migration transaction: {:?}",
-				self.self_id,
-				error,
-			),
-		}
+			match submit_result {
+				Ok(tx_hash) => trace!(
+					target: "secretstore_net",
+					"{}: Migration({}) confirmation transaction submitted: {:?}",
+					self_id,
+					migration_id,
+					tx_hash,
+				),
+				Err(error) => error!(
+					target: "secretstore_net",
+					"{}: Error submitting confirm migration transaction: {:?}",
+					self_id,
+					error,
+				),
+			}
+		});
 	}
 }
 
@@ -21,6 +21,7 @@ mod arguments;
 mod blockchain;
 mod key_server;
 mod key_server_set;
+mod nonce_manager;
 mod runtime;
 mod service;
 mod subcommands;
@@ -31,8 +32,8 @@ use std::{
 	io::Write,
 	sync::Arc,
 };
-use futures::{FutureExt, TryFutureExt};
-use log::error;
+use futures::{FutureExt, TryFutureExt, future::{BoxFuture, RemoteHandle, Shared}};
+use log::{error, info};
 use parity_crypto::publickey::{KeyPair, public_to_address};
 use primitives::{
 	executor::{TokioRuntime, tokio_runtime},
@@ -59,6 +60,10 @@ fn main() {
 			return subcommands::shadow_decrypt_message::run(shadow_decrypt_message_matches),
 		("submit-transaction", Some(submit_transaction_matches)) =>
 			return subcommands::submit_transaction::run(submit_transaction_matches),
+		("backup-keys", Some(backup_keys_matches)) =>
+			return subcommands::backup_keys::run(backup_keys_matches),
+		("restore-keys", Some(restore_keys_matches)) =>
+			return subcommands::restore_keys::run(restore_keys_matches),
 		_ => (),
 	}
 
@@ -88,6 +93,9 @@ fn main() {
 		);
 }
 
+/// Shutdown signal, shared by the blockchain loop and the HTTP service.
+type ShutdownSignal = Shared<BoxFuture<'static, ()>>;
+
 /// Run key server ad blockchain service.
 async fn run_key_server(arguments: arguments::Arguments) -> Result<(), String> {
 	// we still need tokio 0.1 runtime to run SS :/
@@ -97,17 +105,20 @@ async fn run_key_server(arguments: arguments::Arguments) -> Result<(), String> {
 	// futures executor that we'll use to run futures in sync functions
 	let thread_pool = futures::executor::ThreadPool::new()
 		.map_err(|err| format!("Error creating thread pool: {}", err))?;
+	let shutdown_signal = shutdown_signal(&tokio_runtime);
 
 	// start key server and services
-	let (client, key_server_set, best_sender) = start_key_server(
+	let (client, key_server_set, best_sender, http_service_handle) = start_key_server(
 		arguments,
 		&tokio_runtime,
 		thread_pool,
+		shutdown_signal.clone(),
 	).await?;
 
 	let mut fut_finalized_headers = client.subscribe_finalized_heads().await
 		.map_err(|err| format!("Failed to subscribe to finalized blocks: {:?}", err))?;
 
+	let mut shutdown_signal = shutdown_signal.fuse();
 	loop {
 		futures::select! {
 			finalized_header = fut_finalized_headers.next().fuse() => {
@@ -122,8 +133,18 @@ async fn run_key_server(arguments: arguments::Arguments) -> Result<(), String> {
 					);
 				}
 			},
+			_ = shutdown_signal => break,
 		}
 	}
+
+	info!(target: "secretstore", "Shutting down");
+
+	// wait until HTTP service is stopped
+	if let Some(http_service_handle) = http_service_handle {
+		http_service_handle.await;
+	}
+
+	Ok(())
 }
 
 /// Start key server and blockchain service.
@@ -131,10 +152,12 @@ async fn start_key_server(
 	arguments: arguments::Arguments,
 	tokio_runtime: &TokioRuntime,
 	thread_pool: futures::executor::ThreadPool,
+	shutdown_signal: ShutdownSignal,
 ) -> Result<(
 	substrate_client::Client,
 	Arc<key_server_set::OnChainKeyServerSet>,
 	futures::channel::mpsc::UnboundedSender<runtime::BlockHash>,
+	Option<RemoteHandle<()>>,
 ), String> {
 	// let's connect to Substrate node first
 	let client = substrate_client::Client::new(
@@ -146,7 +169,7 @@ async fn start_key_server(
 	).await.map_err(|error| format!("Failed to start substrate client: {:?}", error))?;
 
 	// start key server
-	let self_key_pair = KeyPair::from_secret(arguments.self_secret)
+	let self_key_pair = KeyPair::from_secret(arguments.self_secret.clone())
 		.map_err(|error| format!("{}", error))?;
 	let self_id = public_to_address(self_key_pair.public());
 	let key_server_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(self_key_pair));
@@ -156,18 +179,49 @@ async fn start_key_server(
 		self_id.clone(),
 		thread_pool,
 	));
-	let key_storage = Arc::new(::key_server::db_key_storage::PersistentKeyStorage::new(
-		&std::path::Path::new(&arguments.db_path),
+	key_server_set.ensure_self_address(
+		&arguments.net_public_host,
+		arguments.net_public_port,
+	).await?;
+	let key_storage = Arc::new(key_server::open_key_storage(
+		&arguments.db_path,
+		arguments.db_passphrase.as_deref(),
+		&*key_server_key_pair,
 	).map_err(|error| format!("{:?}", error))?);
 	let key_server = key_server::start(
 		tokio_runtime.executor(),
 		key_server_key_pair.clone(),
-		arguments.net_port,
+		&arguments,
 		key_storage.clone(),
 		acl_storage.clone(),
 		key_server_set.clone(),
 	).map_err(|error| format!("{:?}", error))?;
 
+	// start HTTP service (if required)
+	let http_service_handle = arguments.http_port.map(|http_port| {
+		let http_host = arguments.http_host.clone();
+		let http_cors = http_service::parse_cors_domains(&arguments.http_cors);
+		let key_server = key_server.clone();
+		let (http_future, http_handle) = async move {
+			let start_result = http_service::start_service(
+				&http_host,
+				http_port,
+				key_server,
+				http_cors,
+				shutdown_signal,
+			).await;
+			if let Err(error) = start_result {
+				error!(
+					target: "secretstore",
+					"HTTP service has failed: {:?}",
+					error,
+				);
+			}
+		}.remote_handle();
+		tokio_runtime.executor().spawn_std(http_future);
+		http_handle
+	});
+
 	// start substrate service
 	let (best_sender, best_receiver) = futures::channel::mpsc::unbounded();
 	let blockchain = Arc::new(
@@ -179,6 +233,7 @@ async fn start_key_server(
 	let transaction_pool = Arc::new(
 		transaction_pool::SecretStoreTransactionPool::new(
 			client.clone(),
+			self_id,
 		)
 	);
 	service::start(
@@ -191,7 +246,32 @@ async fn start_key_server(
 		best_receiver,
 	).map_err(|error| format!("{:?}", error))?;
 
-	Ok((client, key_server_set, best_sender))
+	Ok((client, key_server_set, best_sender, http_service_handle))
+}
+
+/// Create shutdown signal that fires when Ctrl-C is received.
+fn shutdown_signal(tokio_runtime: &TokioRuntime) -> ShutdownSignal {
+	let (shutdown_sender, shutdown_receiver) = futures::channel::oneshot::channel();
+	tokio_runtime.executor().spawn_std(async move {
+		match tokio::signal::ctrl_c().await {
+			Ok(()) => {
+				let _ = shutdown_sender.send(());
+			},
+			Err(error) => {
+				error!(
+					target: "secretstore",
+					"Failed to listen for shutdown signal: {:?}",
+					error,
+				);
+
+				// never fire shutdown signal
+				let _shutdown_sender = shutdown_sender;
+				futures::future::pending::<()>().await;
+			},
+		}
+	});
+
+	shutdown_receiver.map(|_| ()).boxed().shared()
 }
 
 fn initialize() {
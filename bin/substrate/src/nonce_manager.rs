@@ -0,0 +1,106 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+use futures::lock::Mutex;
+use crate::runtime::Index;
+
+/// Local manager of transactions signer nonces.
+///
+/// Substrate node is only aware of transactions that it has already seen. So when
+/// several transactions are submitted at once, they could all get the same nonce
+/// from the node. Nonce manager reads nonce from the node once and then allocates
+/// subsequent nonces locally, until it is reset.
+pub struct NonceManager {
+	/// Nonce that will be used by the next transaction. None if it needs to be read from the node.
+	next_index: Mutex<Option<Index>>,
+}
+
+impl NonceManager {
+	/// Create new nonce manager.
+	pub fn new() -> Self {
+		NonceManager {
+			next_index: Mutex::new(None),
+		}
+	}
+
+	/// Allocate nonce for the new transaction. If next nonce is unknown, it is read
+	/// using `read_index` future.
+	pub async fn next<E>(&self, read_index: impl Future<Output = Result<Index, E>>) -> Result<Index, E> {
+		// the lock is held while we're reading nonce from the node, so concurrent
+		// transactions will never get the same nonce
+		let mut next_index = self.next_index.lock().await;
+		let index = match *next_index {
+			Some(index) => index,
+			None => read_index.await?,
+		};
+		*next_index = Some(index + 1);
+		Ok(index)
+	}
+
+	/// Forget about locally allocated nonces. Next nonce will be read from the node.
+	///
+	/// Should be called when one of transactions has been rejected or dropped from
+	/// the pool, because nonces of subsequent transactions are now invalid.
+	pub async fn reset(&self) {
+		*self.next_index.lock().await = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use futures::future::ready;
+	use super::*;
+
+	#[test]
+	fn should_only_read_nonce_once() {
+		let reads = AtomicUsize::new(0);
+		let read_index = || async {
+			reads.fetch_add(1, Ordering::SeqCst);
+			Ok::<_, ()>(10)
+		};
+
+		let nonce_manager = NonceManager::new();
+		futures::executor::block_on(async {
+			assert_eq!(nonce_manager.next(read_index()).await, Ok(10));
+			assert_eq!(nonce_manager.next(read_index()).await, Ok(11));
+			assert_eq!(nonce_manager.next(read_index()).await, Ok(12));
+		});
+		assert_eq!(reads.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn should_read_nonce_again_after_reset() {
+		let nonce_manager = NonceManager::new();
+		futures::executor::block_on(async {
+			assert_eq!(nonce_manager.next(ready(Ok::<_, ()>(10))).await, Ok(10));
+			assert_eq!(nonce_manager.next(ready(Ok::<_, ()>(20))).await, Ok(11));
+
+			nonce_manager.reset().await;
+			assert_eq!(nonce_manager.next(ready(Ok::<_, ()>(20))).await, Ok(20));
+		});
+	}
+
+	#[test]
+	fn should_not_allocate_nonce_if_read_has_failed() {
+		let nonce_manager = NonceManager::new();
+		futures::executor::block_on(async {
+			assert_eq!(nonce_manager.next(ready(Err(()))).await, Err(()));
+			assert_eq!(nonce_manager.next(ready(Ok::<_, ()>(10))).await, Ok(10));
+		});
+	}
+}
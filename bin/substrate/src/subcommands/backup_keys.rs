@@ -0,0 +1,65 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use clap::ArgMatches;
+use log::{error, info};
+use parity_crypto::publickey::KeyPair;
+use key_server::db_key_storage::export_key_shares;
+use primitives::key_server_key_pair::{InMemoryKeyServerKeyPair, KeyServerKeyPair};
+use crate::{
+	arguments::Arguments,
+	key_server::open_key_storage,
+	subcommands::utils::require_string_arg,
+};
+
+/// Export all key shares of the key server to the encrypted archive.
+pub fn run(matches: &ArgMatches) {
+	let parse_arguments = || {
+		let arguments = crate::arguments::parse_arguments(matches)?;
+		let output = require_string_arg(matches, "output")?;
+		Ok((arguments, output))
+	};
+
+	let backup_keys = move |(arguments, output): (Arguments, &str)| -> Result<(), String> {
+		let key_server_key_pair = KeyPair::from_secret(arguments.self_secret)
+			.map(InMemoryKeyServerKeyPair::new)
+			.map_err(|err| format!("{}", err))?;
+		let key_storage = open_key_storage(
+			&arguments.db_path,
+			arguments.db_passphrase.as_deref(),
+			&key_server_key_pair,
+		).map_err(|err| format!("Error opening key server database: {}", err))?;
+
+		let archive = export_key_shares(&key_storage, &key_server_key_pair)
+			.map_err(|err| format!("Error exporting key shares: {}", err))?;
+		std::fs::write(output, archive)
+			.map_err(|err| format!("Error writing key shares archive: {}", err))?;
+
+		info!(
+			target: "secretstore",
+			"Key shares of key server {:?} have been exported to {}",
+			key_server_key_pair.address(),
+			output,
+		);
+
+		Ok(())
+	};
+
+	let result = parse_arguments().and_then(backup_keys);
+	if let Err(error) = result {
+		error!(target: "secretstore", "Failed to backup keys: {}", error);
+	}
+}
@@ -14,10 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod backup_keys;
 pub mod decrypt_message;
 pub mod encrypt_message;
 pub mod generate_document_key;
 pub mod generate_key_pair;
+pub mod restore_keys;
 pub mod shadow_decrypt_message;
 pub mod submit_transaction;
 mod utils;
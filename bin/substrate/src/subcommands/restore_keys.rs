@@ -0,0 +1,69 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use clap::ArgMatches;
+use log::{error, info};
+use parity_crypto::publickey::KeyPair;
+use key_server::db_key_storage::import_key_shares;
+use primitives::key_server_key_pair::InMemoryKeyServerKeyPair;
+use crate::{
+	arguments::Arguments,
+	key_server::open_key_storage,
+	subcommands::utils::require_string_arg,
+};
+
+/// Import key shares of the key server from the encrypted archive.
+pub fn run(matches: &ArgMatches) {
+	let parse_arguments = || {
+		let arguments = crate::arguments::parse_arguments(matches)?;
+		let input = require_string_arg(matches, "input")?;
+		Ok((arguments, input))
+	};
+
+	let restore_keys = move |(arguments, input): (Arguments, &str)| -> Result<(), String> {
+		let archive = std::fs::read(input)
+			.map_err(|err| format!("Error reading key shares archive: {}", err))?;
+
+		let key_pair = KeyPair::from_secret(arguments.self_secret)
+			.map_err(|err| format!("{}", err))?;
+		let key_server_key_pair = InMemoryKeyServerKeyPair::new(key_pair);
+		let key_storage = open_key_storage(
+			&arguments.db_path,
+			arguments.db_passphrase.as_deref(),
+			&key_server_key_pair,
+		).map_err(|err| format!("Error opening key server database: {}", err))?;
+
+		// archive is only imported if it has been exported by the same key server
+		let report = import_key_shares(&key_storage, key_server_key_pair.key_pair(), &archive)
+			.map_err(|err| format!("Error importing key shares: {}", err))?;
+
+		info!(
+			target: "secretstore",
+			"Key shares have been restored from {}: {} imported, {} skipped (already in the database), {} skipped (deleted)",
+			input,
+			report.imported,
+			report.skipped,
+			report.deleted,
+		);
+
+		Ok(())
+	};
+
+	let result = parse_arguments().and_then(restore_keys);
+	if let Err(error) = result {
+		error!(target: "secretstore", "Failed to restore keys: {}", error);
+	}
+}
@@ -14,17 +14,30 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{future::Future, sync::Arc};
+use std::{future::Future, sync::Arc, time::Duration};
 use codec::{Decode, Encode};
+use futures::future::{Either, select};
+use log::{trace, warn};
 use parking_lot::RwLock;
 use sp_core::crypto::Pair as _;
-use crate::runtime::{
-	SYSTEM_EVENTS_KEY,
-	AccountId, BlockHash, Call, Event, Header,
-	Index, Pair, TransactionHash,
-	create_transaction,
+use crate::{
+	nonce_manager::NonceManager,
+	runtime::{
+		SYSTEM_EVENTS_KEY,
+		AccountId, BlockHash, Call, Event, Header,
+		Index, Pair, TransactionHash, TransactionStatus,
+		create_transaction,
+	},
 };
 
+/// Max number of attempts to submit single transaction.
+const MAX_SUBMIT_ATTEMPTS: usize = 5;
+/// Delay before transaction is resubmitted for the first time. The delay is doubled
+/// on every subsequent attempt.
+const INITIAL_RESUBMIT_DELAY: Duration = Duration::from_secs(1);
+/// Max time we're waiting for transaction to be included into block.
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// All possible errors that can occur during interacting with Substrate node.
 #[derive(Debug)]
 pub enum Error {
@@ -36,6 +49,10 @@ pub enum Error {
 	DecodeFailed(codec::Error),
 	/// Failed to get best finalized header.
 	MissingBestFinalizedHeader,
+	/// Transaction has been dropped from the pool or invalidated.
+	TransactionDropped,
+	/// Transaction has not been included into block in time.
+	TransactionInclusionTimeout,
 }
 
 /// Block reference.
@@ -64,6 +81,8 @@ pub struct Client {
 	runtime_version: u32,
 	/// Best local (finalized) block.
 	best_block: Arc<RwLock<(crate::runtime::BlockNumber, crate::runtime::BlockHash)>>,
+	/// Transactions signer nonces.
+	nonce_manager: Arc<NonceManager>,
 }
 
 impl Client {
@@ -101,6 +120,7 @@ impl Client {
 			genesis_hash,
 			runtime_version: runtime_version.spec_version,
 			best_block: Arc::new(RwLock::new((finalized_header.number, finalized_hash))),
+			nonce_manager: Arc::new(NonceManager::new()),
 		})
 	}
 
@@ -192,9 +212,91 @@ impl Client {
 		}
 	}
 
-	/// Submit runtime transaction.
+	/// Submit runtime transaction and wait until it is included into block.
+	///
+	/// If transaction is rejected or dropped from the pool, it is resubmitted with
+	/// renewed nonce. Delay between attempts is doubled on every attempt.
 	pub async fn submit_transaction(&self, call: Call) -> Result<TransactionHash, Error> {
-		let index = self.next_account_index().await?;
+		self.submit_transaction_while_required(call, None).await
+	}
+
+	/// Submit key server response transaction and wait until it is included into block.
+	///
+	/// Works like `submit_transaction`, but before resubmitting the transaction, asks
+	/// the runtime (by calling `is_response_required` method) whether the response is
+	/// still required. If it isn't, the transaction is not resubmitted.
+	pub async fn submit_response_transaction(
+		&self,
+		is_response_required: (&'static str, Vec<u8>),
+		call: Call,
+	) -> Result<TransactionHash, Error> {
+		self.submit_transaction_while_required(call, Some(is_response_required)).await
+	}
+
+	/// Submit runtime transaction and resubmit it while it is required.
+	///
+	/// The transaction is only resubmitted if the node has reported that it has been
+	/// dropped from the pool, invalidated or usurped. After other errors (like inclusion
+	/// timeout) the transaction may still be included into block, so resubmitting it
+	/// could lead to duplicate transactions.
+	async fn submit_transaction_while_required(
+		&self,
+		call: Call,
+		is_response_required: Option<(&'static str, Vec<u8>)>,
+	) -> Result<TransactionHash, Error> {
+		let mut attempt = 1;
+		let mut resubmit_delay = INITIAL_RESUBMIT_DELAY;
+		loop {
+			let error = match self.submit_and_wait_included(call.clone()).await {
+				Ok(transaction_hash) => return Ok(transaction_hash),
+				Err(error) => error,
+			};
+
+			// nonces of all transactions that have been submitted after this one are
+			// now invalid => let's read actual nonce from the node
+			self.nonce_manager.reset().await;
+
+			match error {
+				Error::TransactionDropped if attempt < MAX_SUBMIT_ATTEMPTS => (),
+				error => return Err(error),
+			}
+
+			warn!(
+				target: "secretstore",
+				"Transaction submission attempt {} has failed: {:?}. Retrying in {:?}",
+				attempt,
+				error,
+				resubmit_delay,
+			);
+
+			futures_timer::Delay::new(resubmit_delay).await;
+			attempt += 1;
+			resubmit_delay *= 2;
+
+			if let Some((method, ref arguments)) = is_response_required {
+				let is_required: bool = self.call_runtime_method(
+					BlockRef::RemoteBest,
+					method,
+					arguments.clone(),
+				).await?;
+				if !is_required {
+					trace!(
+						target: "secretstore",
+						"Not resubmitting transaction, because response is not required anymore",
+					);
+
+					return Err(error);
+				}
+			}
+		}
+	}
+
+	/// Submit and watch runtime transaction.
+	pub async fn submit_and_watch_transaction(
+		&self,
+		call: Call,
+	) -> Result<jsonrpsee::client::Subscription<TransactionStatus>, Error> {
+		let index = self.nonce_manager.next(self.next_account_index()).await?;
 		let transaction = create_transaction(
 			call,
 			&self.signer,
@@ -202,29 +304,47 @@ impl Client {
 			self.genesis_hash,
 			self.runtime_version,
 		);
-		let hex_transaction = format!("0x{}", hex::encode(transaction.encode()));
-		self.rpc_client.request(
-			"author_submitExtrinsic",
-			jsonrpsee::common::Params::Array(vec![
-				serde_json::to_value(hex_transaction).unwrap(),
-			]),
-		).await.map_err(Error::RequestFailed)
+		self.watch_transaction(transaction.encode()).await
 	}
 
-	/// Submit and watch runtime transaction.
-	pub async fn submit_and_watch_transaction(
-		&self,
-		call: Call,
-	) -> Result<jsonrpsee::client::Subscription<crate::runtime::TransactionStatus>, Error> {
-		let index = self.next_account_index().await?;
+	/// Submit runtime transaction and wait until it is included into block.
+	async fn submit_and_wait_included(&self, call: Call) -> Result<TransactionHash, Error> {
+		let index = self.nonce_manager.next(self.next_account_index()).await?;
 		let transaction = create_transaction(
 			call,
 			&self.signer,
 			index,
 			self.genesis_hash,
 			self.runtime_version,
-		);
-		let hex_transaction = format!("0x{}", hex::encode(transaction.encode()));
+		).encode();
+		let transaction_hash = sp_core::hashing::blake2_256(&transaction).into();
+		let mut subscription = self.watch_transaction(transaction).await?;
+
+		let wait_included = Box::pin(async move {
+			loop {
+				match subscription.next().await {
+					TransactionStatus::InBlock(_) => return Ok(transaction_hash),
+					TransactionStatus::Usurped(_)
+						| TransactionStatus::Dropped
+						| TransactionStatus::Invalid
+						=> return Err(Error::TransactionDropped),
+					_ => (),
+				}
+			}
+		});
+
+		match select(wait_included, futures_timer::Delay::new(INCLUSION_TIMEOUT)).await {
+			Either::Left((result, _)) => result,
+			Either::Right(_) => Err(Error::TransactionInclusionTimeout),
+		}
+	}
+
+	/// Submit encoded transaction and subscribe to its status updates.
+	async fn watch_transaction(
+		&self,
+		transaction: Vec<u8>,
+	) -> Result<jsonrpsee::client::Subscription<TransactionStatus>, Error> {
+		let hex_transaction = format!("0x{}", hex::encode(transaction));
 		self.rpc_client.subscribe(
 			"author_submitAndWatchExtrinsic",
 			jsonrpsee::common::Params::Array(vec![
@@ -247,3 +367,174 @@ impl Client {
 		).await.map_err(Error::RequestFailed)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		net::SocketAddr,
+		sync::{Arc, atomic::{AtomicUsize, Ordering}},
+		time::Duration,
+	};
+	use futures::executor::{ThreadPool, block_on};
+	use jsonrpsee::common::JsonValue;
+	use sp_runtime::traits::Header as _;
+	use super::*;
+
+	/// Mock Substrate node that answers all requests made by the client.
+	struct MockNode {
+		/// Port the node is listening on.
+		port: u16,
+		/// Number of `system_accountNextIndex` requests received by the node.
+		nonce_requests: Arc<AtomicUsize>,
+		/// Thread pool where node tasks are running.
+		_pool: ThreadPool,
+	}
+
+	/// Start mock node that is listening on some free port. Every submitted transaction
+	/// is getting the next status from `statuses`. The node reports that responses are
+	/// never required.
+	fn start_mock_node(statuses: Vec<TransactionStatus>) -> MockNode {
+		let pool = ThreadPool::new().unwrap();
+		let nonce_requests = Arc::new(AtomicUsize::new(0));
+		// let OS pick free port for us
+		let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+		let address: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+		let server = block_on(jsonrpsee::ws_server(&address)).unwrap();
+
+		let genesis_header = Header::new(
+			0,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		);
+		let constant_responses = vec![
+			("chain_getBlockHash", serde_json::to_value(BlockHash::default()).unwrap()),
+			("chain_getFinalizedHead", serde_json::to_value(BlockHash::default()).unwrap()),
+			("chain_getHeader", serde_json::to_value(genesis_header).unwrap()),
+			("state_getRuntimeVersion", serde_json::to_value(substrate_runtime::VERSION).unwrap()),
+			("state_call", serde_json::to_value(sp_core::Bytes(false.encode())).unwrap()),
+		];
+		for (method, response) in constant_responses {
+			let mut method = server.register_method(method.into()).unwrap();
+			pool.spawn_ok(async move {
+				loop {
+					let request = method.next().await;
+					request.respond(Ok(response.clone())).await;
+				}
+			});
+		}
+
+		// node is always reporting the same nonce, because transactions are never
+		// actually included into block
+		let mut nonce_method = server.register_method("system_accountNextIndex".into()).unwrap();
+		let mut watch_subscription = server.register_subscription(
+			"author_submitAndWatchExtrinsic".into(),
+			"author_unwatchExtrinsic".into(),
+		).unwrap();
+		let nonce_requests_counter = nonce_requests.clone();
+		pool.spawn_ok(async move {
+			let mut statuses = statuses.into_iter();
+			let mut request = nonce_method.next().await;
+			loop {
+				nonce_requests_counter.fetch_add(1, Ordering::SeqCst);
+				request.respond(Ok(JsonValue::from(0))).await;
+
+				// client subscribes to transaction status updates after it has received
+				// the nonce => keep sending the status until client asks for the next nonce
+				let status = statuses.next().map(|status| serde_json::to_value(status).unwrap());
+				let mut next_request = Box::pin(nonce_method.next());
+				request = loop {
+					if let Some(ref status) = status {
+						watch_subscription.send(status.clone()).await;
+					}
+
+					match select(next_request, futures_timer::Delay::new(Duration::from_millis(10))).await {
+						Either::Left((request, _)) => break request,
+						Either::Right((_, request)) => next_request = request,
+					}
+				};
+			}
+		});
+
+		// keep server alive while the node is running
+		pool.spawn_ok(async move {
+			let _server = server;
+			futures::future::pending::<()>().await;
+		});
+
+		MockNode {
+			port,
+			nonce_requests,
+			_pool: pool,
+		}
+	}
+
+	fn client(port: u16) -> Client {
+		block_on(Client::new(
+			&format!("ws://127.0.0.1:{}", port),
+			Pair::from_string("//Alice", None).unwrap(),
+		)).unwrap()
+	}
+
+	#[test]
+	fn should_wait_until_transaction_is_included_into_block() {
+		let node = start_mock_node(vec![
+			TransactionStatus::InBlock(Default::default()),
+		]);
+		let client = client(node.port);
+
+		block_on(client.submit_transaction(Call::SecretStore(
+			crate::runtime::SecretStoreCall::claim_key(Default::default()),
+		))).unwrap();
+		assert_eq!(node.nonce_requests.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn should_resubmit_dropped_transaction_with_renewed_nonce() {
+		let node = start_mock_node(vec![
+			TransactionStatus::Dropped,
+			TransactionStatus::InBlock(Default::default()),
+		]);
+		let client = client(node.port);
+
+		block_on(client.submit_transaction(Call::SecretStore(
+			crate::runtime::SecretStoreCall::claim_key(Default::default()),
+		))).unwrap();
+		assert_eq!(node.nonce_requests.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn should_allocate_nonces_locally() {
+		let node = start_mock_node(vec![]);
+		let client = client(node.port);
+
+		block_on(async {
+			client.submit_and_watch_transaction(Call::SecretStore(
+				crate::runtime::SecretStoreCall::claim_key(Default::default()),
+			)).await.unwrap();
+			client.submit_and_watch_transaction(Call::SecretStore(
+				crate::runtime::SecretStoreCall::claim_key(Default::default()),
+			)).await.unwrap();
+		});
+		assert_eq!(node.nonce_requests.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn should_not_resubmit_dropped_transaction_if_response_is_not_required() {
+		let node = start_mock_node(vec![
+			TransactionStatus::Dropped,
+			TransactionStatus::InBlock(Default::default()),
+		]);
+		let client = client(node.port);
+
+		match block_on(client.submit_response_transaction(
+			("SecretStoreServiceApi_is_server_key_generation_response_required", Vec::new()),
+			Call::SecretStore(crate::runtime::SecretStoreCall::claim_key(Default::default())),
+		)) {
+			Err(Error::TransactionDropped) => (),
+			result => panic!("unexpected result: {:?}", result),
+		}
+		assert_eq!(node.nonce_requests.load(Ordering::SeqCst), 1);
+	}
+}
@@ -19,6 +19,7 @@ use std::{
 	future::Future,
 	pin::Pin,
 };
+use codec::Encode;
 use futures::{FutureExt, TryFutureExt};
 use primitives::KeyServerId;
 use substrate_service::{TransactionPool, SecretStoreCall};
@@ -30,12 +31,13 @@ use crate::{
 /// Transaction pool of Substrate node that runs blockchain with Secret Store module.
 pub struct SecretStoreTransactionPool {
 	client: Client,
+	key_server_id: KeyServerId,
 }
 
 impl SecretStoreTransactionPool {
 	/// Create new transaction pool.
-	pub fn new(client: Client) -> SecretStoreTransactionPool {
-		SecretStoreTransactionPool { client }
+	pub fn new(client: Client, key_server_id: KeyServerId) -> SecretStoreTransactionPool {
+		SecretStoreTransactionPool { client, key_server_id }
 	}
 }
 
@@ -45,8 +47,9 @@ impl TransactionPool for SecretStoreTransactionPool {
 
 	fn submit_transaction(&self, call: SecretStoreCall) -> Self::SubmitTransactionFuture {
 		let client = self.client.clone();
+		let is_response_required = is_response_required_call(self.key_server_id, &call);
 		async move {
-			client.submit_transaction(crate::runtime::Call::SecretStore(
+			client.submit_response_transaction(is_response_required, crate::runtime::Call::SecretStore(
 				match call {
 					SecretStoreCall::ServerKeyGenerated(key_id, key) =>
 						crate::runtime::SecretStoreCall::server_key_generated(
@@ -67,6 +70,14 @@ impl TransactionPool for SecretStoreTransactionPool {
 						crate::runtime::SecretStoreCall::server_key_retrieval_error(
 							key_id,
 						),
+					SecretStoreCall::ServerKeyDeleted(key_id) =>
+						crate::runtime::SecretStoreCall::server_key_deleted(
+							key_id,
+						),
+					SecretStoreCall::ServerKeyDeletionError(key_id) =>
+						crate::runtime::SecretStoreCall::server_key_deletion_error(
+							key_id,
+						),
 					SecretStoreCall::DocumentKeyStored(key_id) =>
 						crate::runtime::SecretStoreCall::document_key_stored(
 							key_id,
@@ -113,8 +124,80 @@ impl TransactionPool for SecretStoreTransactionPool {
 							key_id,
 							requester,
 						),
+					SecretStoreCall::SchnorrSignatureGenerated(key_id, requester, message, signature_c, signature_s, threshold) =>
+						crate::runtime::SecretStoreCall::schnorr_signature_generated(
+							key_id,
+							requester,
+							message,
+							signature_c,
+							signature_s,
+							threshold,
+						),
+					SecretStoreCall::SchnorrSigningError(key_id, requester, message) =>
+						crate::runtime::SecretStoreCall::schnorr_signing_error(
+							key_id,
+							requester,
+							message,
+						),
+					SecretStoreCall::EcdsaSignatureGenerated(key_id, requester, message, signature, threshold) =>
+						crate::runtime::SecretStoreCall::ecdsa_signature_generated(
+							key_id,
+							requester,
+							message,
+							sp_core::ecdsa::Signature::from_raw(*signature),
+							threshold,
+						),
+					SecretStoreCall::EcdsaSigningError(key_id, requester, message) =>
+						crate::runtime::SecretStoreCall::ecdsa_signing_error(
+							key_id,
+							requester,
+							message,
+						),
 				}
 			)).map_err(|err| format!("{:?}", err)).await
 		}.boxed()
 	}
 }
+
+/// Returns runtime method (and its arguments) that checks if response of this key server
+/// is still required by the module.
+fn is_response_required_call(key_server_id: KeyServerId, call: &SecretStoreCall) -> (&'static str, Vec<u8>) {
+	match *call {
+		SecretStoreCall::ServerKeyGenerated(key_id, _)
+			| SecretStoreCall::ServerKeyGenerationError(key_id) => (
+				"SecretStoreServiceApi_is_server_key_generation_response_required",
+				(key_server_id, key_id).encode(),
+			),
+		SecretStoreCall::ServerKeyRetrieved(key_id, _, _)
+			| SecretStoreCall::ServerKeyRetrievalError(key_id) => (
+				"SecretStoreServiceApi_is_server_key_retrieval_response_required",
+				(key_server_id, key_id).encode(),
+			),
+		SecretStoreCall::ServerKeyDeleted(key_id)
+			| SecretStoreCall::ServerKeyDeletionError(key_id) => (
+				"SecretStoreServiceApi_is_server_key_deletion_response_required",
+				(key_server_id, key_id).encode(),
+			),
+		SecretStoreCall::DocumentKeyStored(key_id)
+			| SecretStoreCall::DocumentKeyStoreError(key_id) => (
+				"SecretStoreServiceApi_is_document_key_store_response_required",
+				(key_server_id, key_id).encode(),
+			),
+		SecretStoreCall::DocumentKeyCommonRetrieved(key_id, requester, _, _)
+			| SecretStoreCall::DocumentKeyPersonalRetrieved(key_id, requester, _, _, _)
+			| SecretStoreCall::DocumentKeyShadowRetrievalError(key_id, requester) => (
+				"SecretStoreServiceApi_is_document_key_shadow_retrieval_response_required",
+				(key_server_id, key_id, requester).encode(),
+			),
+		SecretStoreCall::SchnorrSignatureGenerated(key_id, requester, message, _, _, _)
+			| SecretStoreCall::SchnorrSigningError(key_id, requester, message) => (
+				"SecretStoreServiceApi_is_schnorr_signing_response_required",
+				(key_server_id, key_id, requester, message).encode(),
+			),
+		SecretStoreCall::EcdsaSignatureGenerated(key_id, requester, message, _, _)
+			| SecretStoreCall::EcdsaSigningError(key_id, requester, message) => (
+				"SecretStoreServiceApi_is_ecdsa_signing_response_required",
+				(key_server_id, key_id, requester, message).encode(),
+			),
+	}
+}
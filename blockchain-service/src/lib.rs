@@ -23,7 +23,7 @@ use std::{
 use futures::{future::{ready, Either}, FutureExt, Stream, StreamExt};
 use log::{error, info, trace, warn};
 use parking_lot::RwLock;
-use ethereum_types::{U256, BigEndianHash};
+use ethereum_types::{H256, U256, BigEndianHash};
 
 use primitives::{
 	KeyServerId, ServerKeyId,
@@ -32,7 +32,8 @@ use primitives::{
 	key_server::{
 		Origin, KeyServer, ServerKeyGenerationArtifacts, ServerKeyRetrievalArtifacts,
 		DocumentKeyCommonRetrievalArtifacts, DocumentKeyShadowRetrievalArtifacts,
-		ServerKeyGenerationResult, DocumentKeyShadowRetrievalResult,
+		SchnorrSigningArtifacts, EcdsaSigningArtifacts,
+		ServerKeyGenerationResult, ServerKeyDeletionResult, DocumentKeyShadowRetrievalResult,
 	},
 	key_storage::KeyStorage,
 	requester::Requester,
@@ -91,6 +92,10 @@ pub trait TransactionPool: Send + Sync + 'static {
 	);
 	/// Publish server key retrieval error.
 	fn publish_server_key_retrieval_error(&self, origin: Origin, key_id: ServerKeyId);
+	/// Publish delete server key result (success).
+	fn publish_deleted_server_key(&self, origin: Origin, key_id: ServerKeyId);
+	/// Publish server key deletion error.
+	fn publish_server_key_deletion_error(&self, origin: Origin, key_id: ServerKeyId);
 	/// Publish store document key result (success).
 	fn publish_stored_document_key(&self, origin: Origin, key_id: ServerKeyId);
 	/// Publish document key store error.
@@ -125,6 +130,40 @@ pub trait TransactionPool: Send + Sync + 'static {
 		key_id: ServerKeyId,
 		requester: Requester,
 	);
+	/// Publish generated Schnorr signature of the message.
+	fn publish_schnorr_signature(
+		&self,
+		origin: Origin,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+		artifacts: SchnorrSigningArtifacts,
+	);
+	/// Publish error that has occured during Schnorr signing of the message.
+	fn publish_schnorr_signing_error(
+		&self,
+		origin: Origin,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+	);
+	/// Publish generated ECDSA signature of the message.
+	fn publish_ecdsa_signature(
+		&self,
+		origin: Origin,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+		artifacts: EcdsaSigningArtifacts,
+	);
+	/// Publish error that has occured during ECDSA signing of the message.
+	fn publish_ecdsa_signing_error(
+		&self,
+		origin: Origin,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+	);
 }
 
 /// Service configuration.
@@ -168,6 +207,11 @@ struct ServiceData {
 	/// Recently completed (with or without error) server key retrieval sessions,
 	/// started by this service.
 	pub recent_server_key_retrieval_sessions: HashSet<ServerKeyId>,
+	/// Active server key deletion sessions started by this service.
+	pub server_key_deletion_sessions: HashSet<ServerKeyId>,
+	/// Recently completed (with or without error) server key deletion sessions,
+	/// started by this service.
+	pub recent_server_key_deletion_sessions: HashSet<ServerKeyId>,
 	/// Active document key store sessions started by this service.
 	pub document_key_store_sessions: HashSet<ServerKeyId>,
 	/// Recently completed (with or without error) document key store sessions,
@@ -183,6 +227,25 @@ struct ServiceData {
 	/// Recently completed (with or without error) personal document key part retrieval sessions,
 	/// started by this service.
 	pub recent_document_key_personal_retrieval_sessions: HashSet<(ServerKeyId, Requester)>,
+	/// Active Schnorr signing sessions started by this service.
+	pub schnorr_signing_sessions: HashSet<(ServerKeyId, Requester, H256)>,
+	/// Recently completed (with or without error) Schnorr signing sessions,
+	/// started by this service.
+	pub recent_schnorr_signing_sessions: HashSet<(ServerKeyId, Requester, H256)>,
+	/// Active ECDSA signing sessions started by this service.
+	pub ecdsa_signing_sessions: HashSet<(ServerKeyId, Requester, H256)>,
+	/// Recently completed (with or without error) ECDSA signing sessions,
+	/// started by this service.
+	pub recent_ecdsa_signing_sessions: HashSet<(ServerKeyId, Requester, H256)>,
+	/// Service metrics.
+	pub metrics: ServiceMetrics,
+}
+
+/// Service metrics.
+#[derive(Debug, Default)]
+struct ServiceMetrics {
+	/// Number of tasks that have been ignored, because they're not supported by blockchain services.
+	pub unsupported_tasks: usize,
 }
 
 /// Service tasks listener.
@@ -329,9 +392,12 @@ async fn process_new_block<B, E, TP, KSrv, KStr>(
 			service_data.last_restart_time = Instant::now();
 			service_data.recent_server_key_generation_sessions.clear();
 			service_data.recent_server_key_retrieval_sessions.clear();
+			service_data.recent_server_key_deletion_sessions.clear();
 			service_data.recent_document_key_store_sessions.clear();
 			service_data.recent_document_key_common_retrieval_sessions.clear();
 			service_data.recent_document_key_personal_retrieval_sessions.clear();
+			service_data.recent_schnorr_signing_sessions.clear();
+			service_data.recent_ecdsa_signing_sessions.clear();
 		}
 	}
 }
@@ -689,7 +755,7 @@ fn process_task<E, TP, KSrv, KStr>(
 
 			let future_environment = environment.clone();
 			let future_service_data = service_data.clone();
-			Some(Either::Right(Either::Right(Either::Right(Either::Right(
+			Some(Either::Right(Either::Right(Either::Right(Either::Right(Either::Left(
 				future_environment
 					.key_server
 					.restore_document_key_shadow(Some(origin), key_id, requester.clone())
@@ -698,29 +764,260 @@ fn process_task<E, TP, KSrv, KStr>(
 							&(key_id, requester.clone()),
 						);
 					})
-			)))))
+			))))))
 		},
-		BlockchainServiceTask::Regular(_, ServiceTask::GenerateDocumentKey(_, _, _)) => {
-			unimplemented!("GenerateDocumentKey requests are not implemented on blockchain services");
-		},
-		BlockchainServiceTask::Regular(_, ServiceTask::RetrieveDocumentKey(_, _)) => {
-			unimplemented!("RetrieveDocumentKey requests are not implemented on blockchain services");
-		},
-		BlockchainServiceTask::Regular(_, ServiceTask::RetrieveShadowDocumentKey(_, _)) => {
-			unimplemented!("RetrieveShadowDocumentKey requests are not implemented on blockchain services");
+		BlockchainServiceTask::Regular(origin, ServiceTask::SchnorrSignMessage(key_id, requester, message)) => {
+			let mut service_data_lock = service_data.write();
+			let locked_service_data = &mut *service_data_lock;
+			if let Err(error) = filter_signing_task(
+				locked_service_data.active_sessions(),
+				max_active_sessions,
+				&current_set,
+				None,
+				&key_id,
+				&requester,
+				&message,
+				Some(&mut locked_service_data.schnorr_signing_sessions),
+				&mut locked_service_data.recent_schnorr_signing_sessions,
+			) {
+				info!(
+					target: "secretstore",
+					"Ignoring task SchnorrSignMessage({}, {}, {:?}) at block {} because: {:?}",
+					key_id,
+					requester,
+					message,
+					block,
+					error,
+				);
+				return None;
+			}
+
+			info!(
+				target: "secretstore",
+				"Starting task SchnorrSignMessage({}, {}, {:?}) at block {}",
+				key_id,
+				requester,
+				message,
+				block,
+			);
+
+			let future_environment = environment.clone();
+			let future_service_data = service_data.clone();
+			Some(Either::Right(Either::Right(Either::Right(Either::Right(Either::Right(Either::Left(
+				future_environment
+					.key_server
+					.sign_message_schnorr(Some(origin), key_id, requester.clone(), message)
+					.map(move |result| {
+						future_service_data.write().schnorr_signing_sessions.remove(
+							&(key_id, requester.clone(), message),
+						);
+
+						match result.result {
+							Ok(artifacts) => future_environment
+								.transaction_pool
+								.publish_schnorr_signature(
+									origin,
+									result.params.key_id,
+									result.params.requester,
+									message,
+									artifacts,
+								),
+							Err(error) if error.is_non_fatal() => {
+								log_nonfatal_secret_store_error(
+									&format!(
+										"SchnorrSignMessage({}, {}, {:?})",
+										result.params.key_id,
+										result.params.requester,
+										message,
+									),
+									error,
+								);
+							},
+							Err(error) => {
+								log_fatal_secret_store_error(
+									&format!(
+										"SchnorrSignMessage({}, {}, {:?})",
+										result.params.key_id,
+										result.params.requester,
+										message,
+									),
+									error,
+								);
+								future_environment.transaction_pool.publish_schnorr_signing_error(
+									origin,
+									result.params.key_id,
+									result.params.requester,
+									message,
+								);
+							}
+						}
+					})
+			)))))))
 		},
-		BlockchainServiceTask::Regular(_, ServiceTask::SchnorrSignMessage(_, _, _)) => {
-			unimplemented!("SchnorrSignMessage requests are not implemented on blockchain services");
+		BlockchainServiceTask::Regular(origin, ServiceTask::EcdsaSignMessage(key_id, requester, message)) => {
+			let mut service_data_lock = service_data.write();
+			let locked_service_data = &mut *service_data_lock;
+			if let Err(error) = filter_signing_task(
+				locked_service_data.active_sessions(),
+				max_active_sessions,
+				&current_set,
+				None,
+				&key_id,
+				&requester,
+				&message,
+				Some(&mut locked_service_data.ecdsa_signing_sessions),
+				&mut locked_service_data.recent_ecdsa_signing_sessions,
+			) {
+				info!(
+					target: "secretstore",
+					"Ignoring task EcdsaSignMessage({}, {}, {:?}) at block {} because: {:?}",
+					key_id,
+					requester,
+					message,
+					block,
+					error,
+				);
+				return None;
+			}
+
+			info!(
+				target: "secretstore",
+				"Starting task EcdsaSignMessage({}, {}, {:?}) at block {}",
+				key_id,
+				requester,
+				message,
+				block,
+			);
+
+			let future_environment = environment.clone();
+			let future_service_data = service_data.clone();
+			Some(Either::Right(Either::Right(Either::Right(Either::Right(Either::Right(Either::Right(Either::Left(
+				future_environment
+					.key_server
+					.sign_message_ecdsa(Some(origin), key_id, requester.clone(), message)
+					.map(move |result| {
+						future_service_data.write().ecdsa_signing_sessions.remove(
+							&(key_id, requester.clone(), message),
+						);
+
+						match result.result {
+							Ok(artifacts) => future_environment
+								.transaction_pool
+								.publish_ecdsa_signature(
+									origin,
+									result.params.key_id,
+									result.params.requester,
+									message,
+									artifacts,
+								),
+							Err(error) if error.is_non_fatal() => {
+								log_nonfatal_secret_store_error(
+									&format!(
+										"EcdsaSignMessage({}, {}, {:?})",
+										result.params.key_id,
+										result.params.requester,
+										message,
+									),
+									error,
+								);
+							},
+							Err(error) => {
+								log_fatal_secret_store_error(
+									&format!(
+										"EcdsaSignMessage({}, {}, {:?})",
+										result.params.key_id,
+										result.params.requester,
+										message,
+									),
+									error,
+								);
+								future_environment.transaction_pool.publish_ecdsa_signing_error(
+									origin,
+									result.params.key_id,
+									result.params.requester,
+									message,
+								);
+							}
+						}
+					})
+			))))))))
 		},
-		BlockchainServiceTask::Regular(_, ServiceTask::EcdsaSignMessage(_, _, _)) => {
-			unimplemented!("EcdsaSignMessage requests are not implemented on blockchain services");
+		BlockchainServiceTask::Regular(origin, ServiceTask::DeleteServerKey(key_id, author)) => {
+			let mut service_data_lock = service_data.write();
+			let locked_service_data = &mut *service_data_lock;
+			if let Err(error) = filter_task(
+				locked_service_data.active_sessions(),
+				max_active_sessions,
+				&current_set,
+				Some(&environment.self_id),
+				&key_id,
+				Some(&mut locked_service_data.server_key_deletion_sessions),
+				&mut locked_service_data.recent_server_key_deletion_sessions,
+			) {
+				info!(
+					target: "secretstore",
+					"Ignoring task DeleteServerKey({}, {}) at block {} because: {:?}",
+					key_id,
+					author,
+					block,
+					error,
+				);
+				return None;
+			}
+
+			info!(
+				target: "secretstore",
+				"Starting task DeleteServerKey({}, {}) at block {}",
+				key_id,
+				author,
+				block,
+			);
+
+			let future_environment = environment.clone();
+			let future_service_data = service_data.clone();
+			Some(Either::Right(Either::Right(Either::Right(Either::Right(Either::Right(Either::Right(Either::Right(
+				future_environment
+					.key_server
+					.delete_key(Some(origin), key_id, author)
+					.map(move |_| {
+						future_service_data.write().server_key_deletion_sessions.remove(&key_id);
+					})
+			))))))))
 		},
-		BlockchainServiceTask::Regular(_, ServiceTask::ChangeServersSet(_, _, _)) => {
-			unimplemented!("ChangeServersSet requests are not implemented on blockchain services");
+		BlockchainServiceTask::Regular(_, task @ ServiceTask::GenerateDocumentKey(..))
+			| BlockchainServiceTask::Regular(_, task @ ServiceTask::RetrieveDocumentKey(..))
+			| BlockchainServiceTask::Regular(_, task @ ServiceTask::RetrieveShadowDocumentKey(..))
+			| BlockchainServiceTask::Regular(_, task @ ServiceTask::ChangeServersSet(..))
+			| BlockchainServiceTask::Regular(_, task @ ServiceTask::RefreshKeyShares(..))
+			| BlockchainServiceTask::Regular(_, task @ ServiceTask::PruneKeyVersions(..)) => {
+			ignore_unsupported_task(service_data, block, task);
+			None
 		},
 	}
 }
 
+/// Ignore task that is not supported by blockchain services. None of blockchain services
+/// is able to accept responses to such tasks, so the task is only logged and counted.
+fn ignore_unsupported_task(
+	service_data: &Arc<RwLock<ServiceData>>,
+	block: &str,
+	task: ServiceTask,
+) {
+	let unsupported_tasks = {
+		let mut service_data = service_data.write();
+		service_data.metrics.unsupported_tasks += 1;
+		service_data.metrics.unsupported_tasks
+	};
+
+	warn!(
+		target: "secretstore",
+		"Ignoring unsupported task {:?} at block {}. Total unsupported tasks: {}",
+		task,
+		block,
+		unsupported_tasks,
+	);
+}
+
 /// Log nonfatal session error.
 fn log_nonfatal_secret_store_error(request_type: &str, error: Error) {
 	warn!(
@@ -823,6 +1120,42 @@ fn filter_document_task(
 	Ok(())
 }
 
+/// Returns true when signing session, related to `server_key_id`, `requester` and `message` could be started now.
+fn filter_signing_task(
+	total_active_sessions: usize,
+	max_active_sessions: usize,
+	current_set: &BTreeSet<KeyServerId>,
+	self_id: Option<&KeyServerId>,
+	server_key_id: &ServerKeyId,
+	requester: &Requester,
+	message: &H256,
+	active_sessions: Option<&mut HashSet<(ServerKeyId, Requester, H256)>>,
+	recent_sessions: &mut HashSet<(ServerKeyId, Requester, H256)>,
+) -> Result<(), SkipReason> {
+	// ignore if there's already too many session started by this service
+	if total_active_sessions >= max_active_sessions {
+		return Err(SkipReason::TooMuchActiveSessions);
+	}
+	// check if task must be procesed by another node
+	if let Some(self_id) = self_id {
+		if !is_processed_by_this_key_server(current_set, self_id, server_key_id) {
+			return Err(SkipReason::NotMyTask);
+		}
+	}
+	// check if task has been completed recently
+	if !recent_sessions.insert((*server_key_id, requester.clone(), *message)) {
+		return Err(SkipReason::HasBeenProcessedRecently);
+	}
+	// check if task is currently processed
+	if let Some(active_sessions) = active_sessions {
+		if !active_sessions.insert((*server_key_id, requester.clone(), *message)) {
+			return Err(SkipReason::IsActive);
+		}
+	}
+
+	Ok(())
+}
+
 /// Returns true when session, related to `server_key_id` must be started by this node.
 fn is_processed_by_this_key_server(
 	current_set: &BTreeSet<KeyServerId>,
@@ -858,9 +1191,12 @@ impl ServiceData {
 	fn active_sessions(&self) -> usize {
 		self.server_key_generation_sessions.len()
 			+ self.server_key_retrieval_sessions.len()
+			+ self.server_key_deletion_sessions.len()
 			+ self.document_key_store_sessions.len()
 			+ self.document_key_common_retrieval_sessions.len()
 			+ self.document_key_personal_retrieval_sessions.len()
+			+ self.schnorr_signing_sessions.len()
+			+ self.ecdsa_signing_sessions.len()
 	}
 }
 
@@ -901,6 +1237,28 @@ where
 		}
 	}
 
+	fn server_key_deleted(&self, result: ServerKeyDeletionResult) {
+		if let Some(origin) = result.origin {
+			match result.result {
+				Ok(_) => self.environment.transaction_pool.publish_deleted_server_key(
+					origin,
+					result.params.key_id,
+				),
+				Err(error) if error.is_non_fatal() => log_nonfatal_secret_store_error(
+					&format!("DeleteServerKey({})", result.params.key_id),
+					error,
+				),
+				Err(error) => {
+					log_fatal_secret_store_error(&format!("DeleteServerKey({})", result.params.key_id), error);
+					self.environment.transaction_pool.publish_server_key_deletion_error(
+						origin,
+						result.params.key_id,
+					);
+				},
+			}
+		}
+	}
+
 	fn document_key_shadow_retrieved(&self, result: DocumentKeyShadowRetrievalResult) {
 		if let Some(origin) = result.origin {
 			match result.result {
@@ -952,12 +1310,19 @@ fn empty_service_data() -> ServiceData {
 		recent_server_key_generation_sessions: HashSet::new(),
 		server_key_retrieval_sessions: HashSet::new(),
 		recent_server_key_retrieval_sessions: HashSet::new(),
+		server_key_deletion_sessions: HashSet::new(),
+		recent_server_key_deletion_sessions: HashSet::new(),
 		document_key_store_sessions: HashSet::new(),
 		recent_document_key_store_sessions: HashSet::new(),
 		document_key_common_retrieval_sessions: HashSet::new(),
 		recent_document_key_common_retrieval_sessions: HashSet::new(),
 		document_key_personal_retrieval_sessions: HashSet::new(),
 		recent_document_key_personal_retrieval_sessions: HashSet::new(),
+		schnorr_signing_sessions: HashSet::new(),
+		recent_schnorr_signing_sessions: HashSet::new(),
+		ecdsa_signing_sessions: HashSet::new(),
+		recent_ecdsa_signing_sessions: HashSet::new(),
+		metrics: ServiceMetrics::default(),
 	}
 }
 
@@ -979,6 +1344,8 @@ mod tests {
 	const KEY2_ID: [u8; 32] = [2u8; 32];
 	const KEY3_ID: [u8; 32] = [3u8; 32];
 
+	const MESSAGE_HASH: [u8; 32] = [42u8; 32];
+
 	fn new_task() -> ServiceTask {
 		ServiceTask::GenerateServerKey(
 			KEY1_ID.into(),
@@ -1002,6 +1369,13 @@ mod tests {
 		)
 	}
 
+	fn server_key_deletion_task() -> ServiceTask {
+		ServiceTask::DeleteServerKey(
+			KEY1_ID.into(),
+			Requester::Address(REQUESTER1_ID.into()),
+		)
+	}
+
 	fn document_key_store_task() -> ServiceTask {
 		ServiceTask::StoreDocumentKey(
 			KEY1_ID.into(),
@@ -1018,6 +1392,26 @@ mod tests {
 		)
 	}
 
+	fn schnorr_signing_task() -> ServiceTask {
+		ServiceTask::SchnorrSignMessage(
+			KEY1_ID.into(),
+			Requester::Address(REQUESTER1_ID.into()),
+			MESSAGE_HASH.into(),
+		)
+	}
+
+	fn ecdsa_signing_task() -> ServiceTask {
+		ServiceTask::EcdsaSignMessage(
+			KEY1_ID.into(),
+			Requester::Address(REQUESTER1_ID.into()),
+			MESSAGE_HASH.into(),
+		)
+	}
+
+	fn signing_task_id() -> (ServerKeyId, Requester, H256) {
+		(KEY1_ID.into(), Requester::Address(REQUESTER1_ID.into()), MESSAGE_HASH.into())
+	}
+
 	#[derive(Default)]
 	struct TestListenerRegistrar(RwLock<usize>);
 
@@ -1088,6 +1482,10 @@ mod tests {
 			self.failed_retrieved_server_keys.write().push(key_id)
 		}
 
+		fn publish_deleted_server_key(&self, _origin: Origin, _key_id: ServerKeyId) { }
+
+		fn publish_server_key_deletion_error(&self, _origin: Origin, _key_id: ServerKeyId) { }
+
 		fn publish_stored_document_key(&self, _origin: Origin, key_id: ServerKeyId) {
 			self.stored_document_keys.write().push(key_id);
 		}
@@ -1125,6 +1523,40 @@ mod tests {
 			_key_id: ServerKeyId,
 			_requester: Requester,
 		) { }
+
+		fn publish_schnorr_signature(
+			&self,
+			_origin: Origin,
+			_key_id: ServerKeyId,
+			_requester: Requester,
+			_message: H256,
+			_artifacts: SchnorrSigningArtifacts,
+		) { }
+
+		fn publish_schnorr_signing_error(
+			&self,
+			_origin: Origin,
+			_key_id: ServerKeyId,
+			_requester: Requester,
+			_message: H256,
+		) { }
+
+		fn publish_ecdsa_signature(
+			&self,
+			_origin: Origin,
+			_key_id: ServerKeyId,
+			_requester: Requester,
+			_message: H256,
+			_artifacts: EcdsaSigningArtifacts,
+		) { }
+
+		fn publish_ecdsa_signing_error(
+			&self,
+			_origin: Origin,
+			_key_id: ServerKeyId,
+			_requester: Requester,
+			_message: H256,
+		) { }
 	}
 
 	const KEY_SERVER1_ID: [u8; 20] = REQUESTER1_ID;
@@ -1262,6 +1694,20 @@ mod tests {
 		)
 	}
 
+	fn run_at_key_server_signing(
+		key_server_id: KeyServerId,
+		service_data: Arc<RwLock<ServiceData>>,
+		task: ServiceTask,
+	) -> (Arc<AccumulatingKeyServer>, Arc<TestTransactionPool>) {
+		run_tasks_at_key_server_with_data(
+			key_server_id,
+			service_data,
+			default_key_storage(),
+			vec![BlockchainServiceTask::Regular(Default::default(), task)],
+			vec![],
+		)
+	}
+
 	#[test]
 	fn new_tasks_are_ignored_by_isolated_key_server() {
 		assert_eq!(
@@ -1399,6 +1845,52 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn process_tasks_ignores_recent_server_key_deletion_task() {
+		let mut service_data = empty_service_data();
+		service_data.recent_server_key_deletion_sessions.insert(KEY1_ID.into());
+		assert_eq!(
+			run_tasks_at_key_server_with_data(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(service_data)),
+				default_key_storage(),
+				vec![BlockchainServiceTask::Regular(Default::default(), server_key_deletion_task())],
+				vec![],
+			).0.accumulated_tasks(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn process_tasks_ignores_active_server_key_deletion_task() {
+		let mut service_data = empty_service_data();
+		service_data.server_key_deletion_sessions.insert(KEY1_ID.into());
+		assert_eq!(
+			run_tasks_at_key_server_with_data(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(service_data)),
+				default_key_storage(),
+				vec![BlockchainServiceTask::Regular(Default::default(), server_key_deletion_task())],
+				vec![],
+			).0.accumulated_tasks(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn process_tasks_spawns_filtered_server_key_deletion_task() {
+		assert_eq!(
+			run_tasks_at_key_server_with_data(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(empty_service_data())),
+				default_key_storage(),
+				vec![BlockchainServiceTask::Regular(Default::default(), server_key_deletion_task())],
+				vec![],
+			).0.accumulated_tasks(),
+			vec![server_key_deletion_task()],
+		);
+	}
+
 	#[test]
 	fn process_tasks_ignores_recent_document_key_store_task() {
 		let mut service_data = empty_service_data();
@@ -1568,4 +2060,145 @@ mod tests {
 			vec![document_key_shadow_retrieval_task()],
 		);
 	}
+
+	#[test]
+	fn process_tasks_spawns_schnorr_signing_task_at_every_key_server() {
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER2_ID.into(),
+				Arc::new(RwLock::new(empty_service_data())),
+				schnorr_signing_task(),
+			).0.accumulated_tasks(),
+			vec![schnorr_signing_task()],
+		);
+	}
+
+	#[test]
+	fn process_tasks_ignores_recent_schnorr_signing_task() {
+		let mut service_data = empty_service_data();
+		service_data.recent_schnorr_signing_sessions.insert(signing_task_id());
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(service_data)),
+				schnorr_signing_task(),
+			).0.accumulated_tasks(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn process_tasks_ignores_active_schnorr_signing_task() {
+		let mut service_data = empty_service_data();
+		service_data.schnorr_signing_sessions.insert(signing_task_id());
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(service_data)),
+				schnorr_signing_task(),
+			).0.accumulated_tasks(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn process_tasks_spawns_filtered_schnorr_signing_task() {
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(empty_service_data())),
+				schnorr_signing_task(),
+			).0.accumulated_tasks(),
+			vec![schnorr_signing_task()],
+		);
+	}
+
+	#[test]
+	fn process_tasks_spawns_ecdsa_signing_task_at_every_key_server() {
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER2_ID.into(),
+				Arc::new(RwLock::new(empty_service_data())),
+				ecdsa_signing_task(),
+			).0.accumulated_tasks(),
+			vec![ecdsa_signing_task()],
+		);
+	}
+
+	#[test]
+	fn process_tasks_ignores_recent_ecdsa_signing_task() {
+		let mut service_data = empty_service_data();
+		service_data.recent_ecdsa_signing_sessions.insert(signing_task_id());
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(service_data)),
+				ecdsa_signing_task(),
+			).0.accumulated_tasks(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn process_tasks_spawns_filtered_ecdsa_signing_task() {
+		assert_eq!(
+			run_at_key_server_signing(
+				KEY_SERVER1_ID.into(),
+				Arc::new(RwLock::new(empty_service_data())),
+				ecdsa_signing_task(),
+			).0.accumulated_tasks(),
+			vec![ecdsa_signing_task()],
+		);
+	}
+
+	#[test]
+	fn service_forgets_recent_signing_tasks_when_pending_tasks_are_restarted() {
+		let mut service_data = empty_service_data();
+		service_data.recent_schnorr_signing_sessions.insert(signing_task_id());
+		service_data.recent_ecdsa_signing_sessions.insert(signing_task_id());
+		service_data.last_restart_time = Instant::now() - Duration::from_secs(100 * 1_000);
+
+		let service_data = Arc::new(RwLock::new(service_data));
+		run_tasks_at_key_server_with_data(
+			KEY_SERVER1_ID.into(),
+			service_data.clone(),
+			default_key_storage(),
+			vec![],
+			vec![
+				BlockchainServiceTask::Regular(Default::default(), schnorr_signing_task()),
+				BlockchainServiceTask::Regular(Default::default(), ecdsa_signing_task()),
+			],
+		);
+		assert!(service_data.read().recent_schnorr_signing_sessions.is_empty());
+		assert!(service_data.read().recent_ecdsa_signing_sessions.is_empty());
+	}
+
+	#[test]
+	fn process_tasks_ignores_unsupported_tasks() {
+		let unsupported_tasks = vec![
+			ServiceTask::GenerateDocumentKey(KEY1_ID.into(), Requester::Address(REQUESTER1_ID.into()), 8),
+			ServiceTask::RetrieveDocumentKey(KEY1_ID.into(), Requester::Address(REQUESTER1_ID.into())),
+			document_key_shadow_retrieval_task(),
+			ServiceTask::ChangeServersSet(
+				[1u8; 65].into(),
+				[2u8; 65].into(),
+				vec![KEY_SERVER1_ID.into()].into_iter().collect(),
+			),
+		];
+
+		for unsupported_task in unsupported_tasks {
+			let service_data = Arc::new(RwLock::new(empty_service_data()));
+			let (key_server, _) = run_tasks_at_key_server_with_data(
+				KEY_SERVER1_ID.into(),
+				service_data.clone(),
+				default_key_storage(),
+				vec![BlockchainServiceTask::Regular(Default::default(), unsupported_task)],
+				vec![],
+			);
+
+			assert_eq!(key_server.accumulated_tasks(), vec![]);
+			assert_eq!(service_data.read().metrics.unsupported_tasks, 1);
+			assert_eq!(service_data.read().active_sessions(), 0);
+		}
+	}
 }
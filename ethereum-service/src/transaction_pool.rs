@@ -17,7 +17,7 @@
 use std::sync::Arc;
 use futures::future::{Future, FutureExt, TryFutureExt, lazy, ready};
 use log::{error, trace};
-use ethereum_types::Address;
+use ethereum_types::{Address, H256};
 use parity_bytes::Bytes;
 use primitives::{
 	ServerKeyId,
@@ -25,6 +25,7 @@ use primitives::{
 	key_server::{
 		ServerKeyGenerationArtifacts, ServerKeyRetrievalArtifacts,
 		DocumentKeyCommonRetrievalArtifacts, DocumentKeyShadowRetrievalArtifacts,
+		SchnorrSigningArtifacts, EcdsaSigningArtifacts,
 	},
 	requester::Requester,
 };
@@ -119,6 +120,16 @@ impl<E, B, P> EthereumTransactionPool<E, B, P>
 
 		self.executor.spawn(submit_transaction_future.boxed());
 	}
+
+	/// Report response that can't be submitted, because there's no corresponding service contract.
+	fn reject_unsupported_response(&self, contract_address: Address, formatted_request: String) {
+		error!(
+			target: "secretstore",
+			"Failed to submit response {} at {}: requests of this type are not supported by Ethereum service contracts",
+			formatted_request,
+			contract_address,
+		);
+	}
 }
 
 impl<E, B, P> blockchain_service::TransactionPool
@@ -208,6 +219,20 @@ impl<E, B, P> blockchain_service::TransactionPool
 		)
 	}
 
+	fn publish_deleted_server_key(&self, contract_address: Address, key_id: ServerKeyId) {
+		self.reject_unsupported_response(
+			contract_address,
+			format!("ServerKeyDeletionSuccess({})", key_id),
+		)
+	}
+
+	fn publish_server_key_deletion_error(&self, contract_address: Address, key_id: ServerKeyId) {
+		self.reject_unsupported_response(
+			contract_address,
+			format!("ServerKeyDeletionFailure({})", key_id),
+		)
+	}
+
 	fn publish_stored_document_key(&self, contract_address: Address, key_id: ServerKeyId) {
 		self.submit_response_transaction(
 			contract_address,
@@ -377,4 +402,58 @@ impl<E, B, P> blockchain_service::TransactionPool
 			),
 		)
 	}
+
+	fn publish_schnorr_signature(
+		&self,
+		contract_address: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+		_artifacts: SchnorrSigningArtifacts,
+	) {
+		self.reject_unsupported_response(
+			contract_address,
+			format!("SchnorrSigningSuccess({}, {}, {:?})", key_id, requester, message),
+		)
+	}
+
+	fn publish_schnorr_signing_error(
+		&self,
+		contract_address: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+	) {
+		self.reject_unsupported_response(
+			contract_address,
+			format!("SchnorrSigningFailure({}, {}, {:?})", key_id, requester, message),
+		)
+	}
+
+	fn publish_ecdsa_signature(
+		&self,
+		contract_address: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+		_artifacts: EcdsaSigningArtifacts,
+	) {
+		self.reject_unsupported_response(
+			contract_address,
+			format!("EcdsaSigningSuccess({}, {}, {:?})", key_id, requester, message),
+		)
+	}
+
+	fn publish_ecdsa_signing_error(
+		&self,
+		contract_address: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+	) {
+		self.reject_unsupported_response(
+			contract_address,
+			format!("EcdsaSigningFailure({}, {}, {:?})", key_id, requester, message),
+		)
+	}
 }
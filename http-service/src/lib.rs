@@ -27,14 +27,27 @@ use serde::Serialize;
 use primitives::{
 	Public, ecies_encrypt,
 	error::Error as SecretStoreError,
-	key_server::{DocumentKeyStoreArtifacts, DocumentKeyShadowRetrievalArtifacts, KeyServer},
+	key_server::{
+		ServerKeyDeletionArtifacts, DocumentKeyStoreArtifacts, DocumentKeyShadowRetrievalArtifacts, KeyServer,
+	},
 	serialization::{SerializableBytes, SerializablePublic, SerializableEncryptedDocumentKeyShadow},
 	service::ServiceTask,
 };
 
 mod parse;
+mod parse_v2;
 
-type CorsDomains = Option<Vec<AccessControlAllowOrigin>>;
+/// Allowed CORS domains. None means that requests from any origin are allowed.
+pub type CorsDomains = Option<Vec<AccessControlAllowOrigin>>;
+
+/// Error object, returned by v2 API.
+#[derive(Serialize)]
+struct SerializableErrorObject {
+	/// Name of the error variant.
+	error: String,
+	/// Human-readable error description.
+	message: String,
+}
 
 /// All possible errors.
 #[derive(Debug)]
@@ -72,6 +85,7 @@ pub async fn start_service<KS: KeyServer>(
 	listen_port: u16,
 	key_server: Arc<KS>,
 	cors: CorsDomains,
+	shutdown_signal: impl Future<Output = ()>,
 ) -> Result<(), Error> {
 	let cors = Arc::new(cors);
 	let http_address = format!("{}:{}", listen_address, listen_port)
@@ -92,10 +106,22 @@ pub async fn start_service<KS: KeyServer>(
 			))
 		}
 	});
-	let http_service = http_server.serve(http_service_fn);
+	let http_service = http_server
+		.serve(http_service_fn)
+		.with_graceful_shutdown(shutdown_signal);
 	http_service.await.map_err(Error::Hyper)
 }
 
+/// Parse comma-separated list of allowed CORS domains. "all" (or "*") allows
+/// requests from any origin, "none" rejects all cross-origin requests.
+pub fn parse_cors_domains(cors: &str) -> CorsDomains {
+	match cors {
+		"all" | "*" => None,
+		"none" => Some(Vec::new()),
+		_ => Some(cors.split(',').map(AccessControlAllowOrigin::from).collect()),
+	}
+}
+
 /// Serve single HTTP request.
 async fn serve_http_request<KS: KeyServer>(
 	http_request: Request<Body>,
@@ -120,12 +146,20 @@ async fn serve_decomposed_http_request<KS: KeyServer>(
 ) -> Result<Response<Body>, hyper::Error> {
 	let allow_cors = match ensure_cors(&decomposed_request, cors_domains) {
 		Ok(allow_cors) => allow_cors,
-		Err(error) => return Ok(return_error(error)),
+		Err(error) => return Ok(return_request_error(&decomposed_request, error)),
 	};
 
-	let service_task = match crate::parse::parse_http_request(&decomposed_request) {
+	if crate::parse_v2::is_openapi_request(&decomposed_request) {
+		return Ok(return_openapi_description(allow_cors));
+	}
+
+	let service_task = match crate::parse_v2::is_v2_request(&decomposed_request) {
+		true => crate::parse_v2::parse_http_request(&decomposed_request),
+		false => crate::parse::parse_http_request(&decomposed_request),
+	};
+	let service_task = match service_task {
 		Ok(service_task) => service_task,
-		Err(error) => return Ok(return_error(error)),
+		Err(error) => return Ok(return_request_error(&decomposed_request, error)),
 	};
 
 	serve_service_task(
@@ -180,6 +214,17 @@ async fn serve_service_task<KS: KeyServer>(
 					.map(|artifacts| artifacts.key)
 					.map_err(log_secret_store_error),
 			)),
+		ServiceTask::DeleteServerKey(key_id, requester) =>
+			Ok(return_empty(
+				&decomposed_request,
+				allow_cors,
+				key_server
+					.delete_key(None, key_id, requester)
+					.await
+					.map(Into::into)
+					.map(|_: ServerKeyDeletionArtifacts| ())
+					.map_err(log_secret_store_error),
+			)),
 		ServiceTask::GenerateDocumentKey(key_id, requester, threshold) =>
 			Ok(return_encrypted_document_key(
 				&decomposed_request,
@@ -281,6 +326,26 @@ async fn serve_service_task<KS: KeyServer>(
 					.map(Into::into)
 					.map_err(log_secret_store_error),
 			)),
+		ServiceTask::RefreshKeyShares(key_id, servers_set_signature) =>
+			Ok(return_empty(
+				&decomposed_request,
+				allow_cors,
+				key_server
+					.refresh_key_shares(None, key_id, servers_set_signature)
+					.await
+					.map(Into::into)
+					.map_err(log_secret_store_error),
+			)),
+		ServiceTask::PruneKeyVersions(key_id, admin_signature) =>
+			Ok(return_empty(
+				&decomposed_request,
+				allow_cors,
+				key_server
+					.prune_key_versions(None, key_id, admin_signature)
+					.await
+					.map(Into::into)
+					.map_err(log_secret_store_error),
+			)),
 	}
 }
 
@@ -445,12 +510,68 @@ fn return_bytes<T: Serialize>(
 			}
 			builder.body(Body::empty()).expect("Nothing to parse, cannot fail; qed")
 		},
-		Err(err) => return_error(err),
+		Err(err) => return_request_error(request, err),
+	}
+}
+
+fn return_openapi_description(allow_cors: AllowCors<AccessControlAllowOrigin>) -> Response<Body> {
+	let mut builder = Response::builder();
+	builder = builder.header(
+		header::CONTENT_TYPE,
+		HeaderValue::from_static("application/json; charset=utf-8"),
+	);
+	if let AllowCors::Ok(AccessControlAllowOrigin::Value(origin)) = allow_cors {
+		builder = builder.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.to_string());
+	}
+	builder
+		.body(crate::parse_v2::OPENAPI_DESCRIPTION.into())
+		.expect("OpenAPI description is a static string, parsing cannot fail; qed")
+}
+
+fn return_request_error(request: &DecomposedRequest, err: Error) -> Response<Body> {
+	match crate::parse_v2::is_v2_request(request) {
+		true => return_error_object(err),
+		false => return_error(err),
+	}
+}
+
+fn return_error_object(err: Error) -> Response<Body> {
+	let mut res = Response::builder();
+	res = res.status(error_status(&err));
+
+	// return error object. ignore errors when returning error
+	let error_object = SerializableErrorObject {
+		error: err.kind().into(),
+		message: err.to_string(),
+	};
+	if let Ok(error_object) = serde_json::to_vec(&error_object) {
+		res = res.header(header::CONTENT_TYPE, HeaderValue::from_static("application/json; charset=utf-8"));
+		res.body(error_object.into())
+			.expect("`error_object` is a serialized struct, parsing cannot fail; qed")
+	} else {
+		res.body(Body::empty())
+			.expect("Nothing to parse, cannot fail; qed")
 	}
 }
 
 fn return_error(err: Error) -> Response<Body> {
-	let status = match err {
+	let mut res = Response::builder();
+	res = res.status(error_status(&err));
+
+	// return error text. ignore errors when returning error
+	let error_text = format!("\"{}\"", err);
+	if let Ok(error_text) = serde_json::to_vec(&error_text) {
+		res = res.header(header::CONTENT_TYPE, HeaderValue::from_static("application/json; charset=utf-8"));
+		res.body(error_text.into())
+			.expect("`error_text` is a formatted string, parsing cannot fail; qed")
+	} else {
+		res.body(Body::empty())
+			.expect("Nothing to parse, cannot fail; qed")
+	}
+}
+
+fn error_status(err: &Error) -> StatusCode {
+	match *err {
 		Error::SecretStore(SecretStoreError::AccessDenied)
 		| Error::SecretStore(SecretStoreError::ConsensusUnreachable)
 		| Error::SecretStore(SecretStoreError::ConsensusTemporaryUnreachable) =>
@@ -465,23 +586,56 @@ fn return_error(err: Error) -> Response<Body> {
 		| Error::SecretStore(SecretStoreError::Hyper(_))
 		| Error::SecretStore(SecretStoreError::Serde(_))
 		| Error::SecretStore(SecretStoreError::DocumentKeyAlreadyStored)
-		| Error::SecretStore(SecretStoreError::ServerKeyAlreadyGenerated) =>
+		| Error::SecretStore(SecretStoreError::ServerKeyAlreadyGenerated)
+		| Error::SecretStore(SecretStoreError::ServerKeyIsDeleted) =>
 			StatusCode::BAD_REQUEST,
 		_ => StatusCode::INTERNAL_SERVER_ERROR,
-	};
-
-	let mut res = Response::builder();
-	res = res.status(status);
+	}
+}
 
-	// return error text. ignore errors when returning error
-	let error_text = format!("\"{}\"", err);
-	if let Ok(error_text) = serde_json::to_vec(&error_text) {
-		res = res.header(header::CONTENT_TYPE, HeaderValue::from_static("application/json; charset=utf-8"));
-		res.body(error_text.into())
-			.expect("`error_text` is a formatted string, parsing cannot fail; qed")
-	} else {
-		res.body(Body::empty())
-			.expect("Nothing to parse, cannot fail; qed")
+impl Error {
+	/// Returns name of the error variant. For Secret Store errors, this is the name of
+	/// `primitives::error::Error` variant.
+	pub fn kind(&self) -> &'static str {
+		match *self {
+			Error::InvalidListenAddress(_) => "InvalidListenAddress",
+			Error::InvalidCors => "InvalidCors",
+			Error::InvalidRequest => "InvalidRequest",
+			Error::Hyper(_) => "Hyper",
+			Error::SecretStore(ref error) => match *error {
+				SecretStoreError::InvalidNodeAddress => "InvalidNodeAddress",
+				SecretStoreError::InvalidNodeId(_) => "InvalidNodeId",
+				SecretStoreError::DuplicateSessionId => "DuplicateSessionId",
+				SecretStoreError::NoActiveSessionWithId => "NoActiveSessionWithId",
+				SecretStoreError::NotEnoughNodesForThreshold => "NotEnoughNodesForThreshold",
+				SecretStoreError::TooEarlyForRequest => "TooEarlyForRequest",
+				SecretStoreError::InvalidStateForRequest => "InvalidStateForRequest",
+				SecretStoreError::InvalidNodeForRequest => "InvalidNodeForRequest",
+				SecretStoreError::InvalidMessage => "InvalidMessage",
+				SecretStoreError::InvalidMessageVersion => "InvalidMessageVersion",
+				SecretStoreError::ReplayProtection => "ReplayProtection",
+				SecretStoreError::NodeDisconnected => "NodeDisconnected",
+				SecretStoreError::ServerKeyAlreadyGenerated => "ServerKeyAlreadyGenerated",
+				SecretStoreError::ServerKeyIsNotFound => "ServerKeyIsNotFound",
+				SecretStoreError::DocumentKeyAlreadyStored => "DocumentKeyAlreadyStored",
+				SecretStoreError::DocumentKeyIsNotFound => "DocumentKeyIsNotFound",
+				SecretStoreError::ConsensusTemporaryUnreachable => "ConsensusTemporaryUnreachable",
+				SecretStoreError::ConsensusUnreachable => "ConsensusUnreachable",
+				SecretStoreError::AccessDenied => "AccessDenied",
+				SecretStoreError::ExclusiveSessionActive => "ExclusiveSessionActive",
+				SecretStoreError::HasActiveSessions => "HasActiveSessions",
+				SecretStoreError::InsufficientRequesterData(_) => "InsufficientRequesterData",
+				SecretStoreError::EthKey(_) => "EthKey",
+				SecretStoreError::Io(_) => "Io",
+				SecretStoreError::Serde(_) => "Serde",
+				SecretStoreError::Hyper(_) => "Hyper",
+				SecretStoreError::Database(_) => "Database",
+				SecretStoreError::Internal(_) => "Internal",
+				SecretStoreError::InvalidSignatureShare(_) => "InvalidSignatureShare",
+				SecretStoreError::InvalidSecretSubshare(_) => "InvalidSecretSubshare",
+				SecretStoreError::ServerKeyIsDeleted => "ServerKeyIsDeleted",
+			},
+		}
 	}
 }
 
@@ -582,6 +736,20 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn parse_cors_domains_works() {
+		assert_eq!(parse_cors_domains("all"), None);
+		assert_eq!(parse_cors_domains("*"), None);
+		assert_eq!(parse_cors_domains("none"), Some(vec![]));
+		assert_eq!(
+			parse_cors_domains("null,http://parity.io"),
+			Some(vec![
+				AccessControlAllowOrigin::Null,
+				AccessControlAllowOrigin::Value("http://parity.io".into()),
+			]),
+		);
+	}
+
 	#[test]
 	fn return_empty_ok_works() {
 		let response = return_empty(
@@ -605,6 +773,70 @@ mod tests {
 		));
 	}
 
+	#[test]
+	fn return_empty_err_works_with_v2_request() {
+		let mut request = default_decomposed_request();
+		request.uri = "http://some-uri/v2/document_key/store".parse().unwrap();
+		let response = return_empty(
+			&request,
+			AllowCors::NotRequired,
+			Err(Error::SecretStore(SecretStoreError::InsufficientRequesterData("test".into()))),
+		);
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		let response: serde_json::Value = serde_json::from_slice(
+			&futures::executor::block_on(hyper::body::to_bytes(response.into_body())).unwrap(),
+		).unwrap();
+		assert_eq!(response["error"], "InsufficientRequesterData");
+		assert_eq!(
+			response["message"],
+			Error::SecretStore(SecretStoreError::InsufficientRequesterData("test".into())).to_string(),
+		);
+	}
+
+	#[test]
+	fn error_kind_is_name_of_error_variant() {
+		assert_eq!(Error::InvalidCors.kind(), "InvalidCors");
+		assert_eq!(Error::SecretStore(SecretStoreError::AccessDenied).kind(), "AccessDenied");
+		assert_eq!(Error::SecretStore(SecretStoreError::Internal("test".into())).kind(), "Internal");
+		assert_eq!(
+			Error::SecretStore(SecretStoreError::InvalidSignatureShare(Default::default())).kind(),
+			"InvalidSignatureShare",
+		);
+	}
+
+	#[test]
+	fn serve_decomposed_http_request_returns_v2_error_object() {
+		let mut request = default_decomposed_request();
+		request.uri = "http://some-uri/v2/server_key/generate".parse().unwrap();
+		let response = futures::executor::block_on(serve_decomposed_http_request(
+			request,
+			Arc::new(AccumulatingKeyServer::default()),
+			Arc::new(None),
+		)).unwrap();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		let response: serde_json::Value = serde_json::from_slice(
+			&futures::executor::block_on(hyper::body::to_bytes(response.into_body())).unwrap(),
+		).unwrap();
+		assert_eq!(response["error"], "InvalidRequest");
+	}
+
+	#[test]
+	fn serve_decomposed_http_request_returns_openapi_description() {
+		let mut request = default_decomposed_request();
+		request.method = Method::GET;
+		request.uri = "http://some-uri/v2/openapi.json".parse().unwrap();
+		let response = futures::executor::block_on(serve_decomposed_http_request(
+			request,
+			Arc::new(AccumulatingKeyServer::default()),
+			Arc::new(None),
+		)).unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			futures::executor::block_on(hyper::body::to_bytes(response.into_body())).unwrap(),
+			crate::parse_v2::OPENAPI_DESCRIPTION,
+		);
+	}
+
 	#[test]
 	fn return_unencrypted_server_key_ok_works() {
 		let response = return_unencrypted_server_key(
@@ -750,6 +982,22 @@ mod tests {
 		assert_eq!(key_server.accumulated_tasks(), vec![service_task]);
 	}
 
+	#[test]
+	fn serve_decomposed_http_request_schedules_delete_server_key_request() {
+		let key_server = Arc::new(AccumulatingKeyServer::default());
+		let service_task = ServiceTask::DeleteServerKey(
+			[1u8; 32].into(),
+			Requester::Address([2u8; 20].into()),
+		);
+		futures::executor::block_on(serve_service_task(
+			default_decomposed_request(),
+			key_server.clone(),
+			AllowCors::NotRequired,
+			service_task.clone(),
+		)).unwrap();
+		assert_eq!(key_server.accumulated_tasks(), vec![service_task]);
+	}
+
 	#[test]
 	fn serve_decomposed_http_request_schedules_generate_document_key_request() {
 		let key_server = Arc::new(AccumulatingKeyServer::default());
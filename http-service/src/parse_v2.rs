@@ -0,0 +1,427 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+use hyper::Method;
+use serde::{Deserialize, de::DeserializeOwned};
+use primitives::{
+	service::ServiceTask,
+	requester::Requester,
+	serialization::{SerializableAddress, SerializableH256, SerializablePublic, SerializableSignature},
+};
+use crate::{DecomposedRequest, Error};
+
+/// Path prefix of all v2 API requests.
+pub const V2_PATH_PREFIX: &'static str = "/v2/";
+
+/// Path of OpenAPI description of v2 API.
+pub const OPENAPI_PATH: &'static str = "/v2/openapi.json";
+
+/// OpenAPI description of v2 API.
+pub const OPENAPI_DESCRIPTION: &'static str = include_str!("../openapi.json");
+
+/// Body of server key generation and document key generation requests.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GenerateKeyRequest {
+	/// Server key id.
+	key_id: SerializableH256,
+	/// Signature of server key id, made by requester.
+	signature: SerializableSignature,
+	/// Key threshold.
+	threshold: usize,
+}
+
+/// Body of requests that only require key id and requester signature.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyRequest {
+	/// Server key id.
+	key_id: SerializableH256,
+	/// Signature of server key id, made by requester.
+	signature: SerializableSignature,
+}
+
+/// Body of document key store request.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StoreDocumentKeyRequest {
+	/// Server key id.
+	key_id: SerializableH256,
+	/// Signature of server key id, made by requester.
+	signature: SerializableSignature,
+	/// Common point of encrypted document key.
+	common_point: SerializablePublic,
+	/// Encrypted point of encrypted document key.
+	encrypted_point: SerializablePublic,
+}
+
+/// Body of message signing request.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignMessageRequest {
+	/// Server key id.
+	key_id: SerializableH256,
+	/// Signature of server key id, made by requester.
+	signature: SerializableSignature,
+	/// Hash of the message to sign.
+	message_hash: SerializableH256,
+}
+
+/// Body of servers set change request.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChangeServersSetRequest {
+	/// Signature of old servers set, made by administrator.
+	old_set_signature: SerializableSignature,
+	/// Signature of new servers set, made by administrator.
+	new_set_signature: SerializableSignature,
+	/// New servers set.
+	new_servers_set: BTreeSet<SerializableAddress>,
+}
+
+/// Body of key shares refresh request.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RefreshKeySharesRequest {
+	/// Id of the key to refresh shares of. Shares of all keys are refreshed if omitted.
+	#[serde(default)]
+	key_id: Option<SerializableH256>,
+	/// Signature of current servers set, made by administrator.
+	servers_set_signature: SerializableSignature,
+}
+
+/// Body of key versions pruning request.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PruneKeyVersionsRequest {
+	/// Id of the key to prune versions of.
+	key_id: SerializableH256,
+	/// Signature of keccak("prune" + key_id), made by administrator.
+	signature: SerializableSignature,
+}
+
+/// Returns true if request should be served by v2 API.
+pub fn is_v2_request(request: &DecomposedRequest) -> bool {
+	request.uri.path().starts_with(V2_PATH_PREFIX)
+}
+
+/// Returns true if request asks for OpenAPI description of v2 API.
+pub fn is_openapi_request(request: &DecomposedRequest) -> bool {
+	request.method == Method::GET && request.uri.path() == OPENAPI_PATH
+}
+
+pub fn parse_http_request(request: &DecomposedRequest) -> Result<ServiceTask, Error> {
+	if request.method != Method::POST {
+		return Err(Error::InvalidRequest);
+	}
+
+	match request.uri.path().trim_start_matches(V2_PATH_PREFIX) {
+		"server_key/generate" => parse_body(request).map(|body: GenerateKeyRequest|
+			ServiceTask::GenerateServerKey(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+				body.threshold,
+			)),
+		"server_key/retrieve" => parse_body(request).map(|body: KeyRequest|
+			ServiceTask::RetrieveServerKey(
+				body.key_id.into(),
+				Some(Requester::Signature(body.signature.into())),
+			)),
+		"server_key/delete" => parse_body(request).map(|body: KeyRequest|
+			ServiceTask::DeleteServerKey(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+			)),
+		"document_key/generate" => parse_body(request).map(|body: GenerateKeyRequest|
+			ServiceTask::GenerateDocumentKey(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+				body.threshold,
+			)),
+		"document_key/store" => parse_body(request).map(|body: StoreDocumentKeyRequest|
+			ServiceTask::StoreDocumentKey(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+				body.common_point.into(),
+				body.encrypted_point.into(),
+			)),
+		"document_key/retrieve" => parse_body(request).map(|body: KeyRequest|
+			ServiceTask::RetrieveDocumentKey(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+			)),
+		"document_key/retrieve_shadow" => parse_body(request).map(|body: KeyRequest|
+			ServiceTask::RetrieveShadowDocumentKey(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+			)),
+		"signature/schnorr" => parse_body(request).map(|body: SignMessageRequest|
+			ServiceTask::SchnorrSignMessage(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+				body.message_hash.into(),
+			)),
+		"signature/ecdsa" => parse_body(request).map(|body: SignMessageRequest|
+			ServiceTask::EcdsaSignMessage(
+				body.key_id.into(),
+				Requester::Signature(body.signature.into()),
+				body.message_hash.into(),
+			)),
+		"admin/servers_set_change" => parse_body(request).map(|body: ChangeServersSetRequest|
+			ServiceTask::ChangeServersSet(
+				body.old_set_signature.into(),
+				body.new_set_signature.into(),
+				body.new_servers_set.into_iter().map(Into::into).collect(),
+			)),
+		"admin/key_shares_refresh" => parse_body(request).map(|body: RefreshKeySharesRequest|
+			ServiceTask::RefreshKeyShares(
+				body.key_id.map(Into::into),
+				body.servers_set_signature.into(),
+			)),
+		"admin/key_versions_pruning" => parse_body(request).map(|body: PruneKeyVersionsRequest|
+			ServiceTask::PruneKeyVersions(
+				body.key_id.into(),
+				body.signature.into(),
+			)),
+		_ => Err(Error::InvalidRequest),
+	}
+}
+
+fn parse_body<T: DeserializeOwned>(request: &DecomposedRequest) -> Result<T, Error> {
+	serde_json::from_slice(&request.body).map_err(|_| Error::InvalidRequest)
+}
+
+#[cfg(test)]
+mod tests {
+	use assert_matches::assert_matches;
+	use hyper::Uri;
+	use primitives::ServerKeyId;
+	use super::*;
+
+	const KEY_ID: &'static str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+	const SIGNATURE: &'static str = "0xa199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e\
+		1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01";
+	const COMMON_POINT: &'static str = "0xb486d3840218837b035c66196ecb15e6b067ca20101e11bd5e626288ab6806\
+		ecc70b8307012626bd512bad1559112d11d21025cef48cc7a1d2f3976da08f36c8";
+	const ENCRYPTED_POINT: &'static str = "0x1395568277679f7f583ab7c0992da35f26cde57149ee70e524e49bdae62d\
+		b3e18eb96122501e7cbb798b784395d7bb5a499edead0706638ad056d886e56cf8fb";
+	const MESSAGE_HASH: &'static str = "0x281b6bf43cb86d0dc7b98e1b7def4a80f3ce16d28d2308f934f116767306f06c";
+	const NODE1_ADDRESS: &'static str = "0x9aa83d4e5ae7a548e34f3b54a713a4f28d876bb8";
+	const NODE2_ADDRESS: &'static str = "0xde925758b13aa7ea104d233888d970feb73b7dad";
+
+	fn prepare_request(method: Method, uri_path: &str, body: serde_json::Value) -> DecomposedRequest {
+		DecomposedRequest {
+			uri: Uri::builder().path_and_query(uri_path).build().unwrap(),
+			method,
+			header_origin: None,
+			header_host: None,
+			body: serde_json::to_vec(&body).unwrap(),
+		}
+	}
+
+	fn key_id() -> ServerKeyId {
+		KEY_ID[2..].parse().unwrap()
+	}
+
+	fn requester() -> Requester {
+		Requester::Signature(SIGNATURE[2..].parse().unwrap())
+	}
+
+	#[test]
+	fn parse_http_request_successful() {
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/server_key/generate",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE, "threshold": 2 }),
+			)).unwrap(),
+			ServiceTask::GenerateServerKey(key_id(), requester(), 2),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/server_key/retrieve",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::RetrieveServerKey(key_id(), Some(requester())),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/server_key/delete",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::DeleteServerKey(key_id(), requester()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/document_key/generate",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE, "threshold": 2 }),
+			)).unwrap(),
+			ServiceTask::GenerateDocumentKey(key_id(), requester(), 2),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/document_key/store",
+				serde_json::json!({
+					"key_id": KEY_ID,
+					"signature": SIGNATURE,
+					"common_point": COMMON_POINT,
+					"encrypted_point": ENCRYPTED_POINT,
+				}),
+			)).unwrap(),
+			ServiceTask::StoreDocumentKey(
+				key_id(),
+				requester(),
+				COMMON_POINT[2..].parse().unwrap(),
+				ENCRYPTED_POINT[2..].parse().unwrap(),
+			),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/document_key/retrieve",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::RetrieveDocumentKey(key_id(), requester()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/document_key/retrieve_shadow",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::RetrieveShadowDocumentKey(key_id(), requester()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/signature/schnorr",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE, "message_hash": MESSAGE_HASH }),
+			)).unwrap(),
+			ServiceTask::SchnorrSignMessage(key_id(), requester(), MESSAGE_HASH[2..].parse().unwrap()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/signature/ecdsa",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE, "message_hash": MESSAGE_HASH }),
+			)).unwrap(),
+			ServiceTask::EcdsaSignMessage(key_id(), requester(), MESSAGE_HASH[2..].parse().unwrap()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/admin/servers_set_change",
+				serde_json::json!({
+					"old_set_signature": SIGNATURE,
+					"new_set_signature": SIGNATURE,
+					"new_servers_set": [NODE1_ADDRESS, NODE2_ADDRESS],
+				}),
+			)).unwrap(),
+			ServiceTask::ChangeServersSet(
+				SIGNATURE[2..].parse().unwrap(),
+				SIGNATURE[2..].parse().unwrap(),
+				vec![
+					NODE1_ADDRESS[2..].parse().unwrap(),
+					NODE2_ADDRESS[2..].parse().unwrap(),
+				].into_iter().collect(),
+			),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/admin/key_shares_refresh",
+				serde_json::json!({ "key_id": KEY_ID, "servers_set_signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::RefreshKeyShares(Some(key_id()), SIGNATURE[2..].parse().unwrap()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/admin/key_shares_refresh",
+				serde_json::json!({ "servers_set_signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::RefreshKeyShares(None, SIGNATURE[2..].parse().unwrap()),
+		);
+		assert_eq!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/admin/key_versions_pruning",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap(),
+			ServiceTask::PruneKeyVersions(key_id(), SIGNATURE[2..].parse().unwrap()),
+		);
+	}
+
+	#[test]
+	fn parse_request_failed() {
+		// unknown path
+		assert_matches!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/server_key/unknown",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap_err(),
+			Error::InvalidRequest
+		);
+		// wrong method
+		assert_matches!(
+			parse_http_request(&prepare_request(
+				Method::GET,
+				"/v2/server_key/retrieve",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap_err(),
+			Error::InvalidRequest
+		);
+		// missing field
+		assert_matches!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/server_key/generate",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE }),
+			)).unwrap_err(),
+			Error::InvalidRequest
+		);
+		// unknown field
+		assert_matches!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/server_key/retrieve",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE, "threshold": 2 }),
+			)).unwrap_err(),
+			Error::InvalidRequest
+		);
+		// invalid field value
+		assert_matches!(
+			parse_http_request(&prepare_request(
+				Method::POST,
+				"/v2/signature/ecdsa",
+				serde_json::json!({ "key_id": KEY_ID, "signature": SIGNATURE, "message_hash": "0x01" }),
+			)).unwrap_err(),
+			Error::InvalidRequest
+		);
+	}
+
+	#[test]
+	fn openapi_description_is_valid_json() {
+		let description: serde_json::Value = serde_json::from_str(OPENAPI_DESCRIPTION).unwrap();
+		assert!(description["paths"]["/v2/server_key/generate"].is_object());
+	}
+}
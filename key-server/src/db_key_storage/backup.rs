@@ -0,0 +1,267 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use keccak_hash::keccak;
+use serde::{Serialize, Deserialize};
+use parity_crypto::publickey::{KeyPair, ecies, public_to_address, recover};
+use primitives::{
+	error::Error, ServerKeyId,
+	key_server_key_pair::KeyServerKeyPair,
+	key_storage::KeyStorage,
+	serialization::{SerializableAddress, SerializableBytes, SerializableH256, SerializableSignature},
+};
+use super::{COL_KEY_SHARES, PersistentKeyStorage, SerializableKeyShareV3, unseal_key_share};
+
+/// Current version of key shares archive.
+pub const CURRENT_ARCHIVE_VERSION: u32 = 1;
+
+/// Key shares archive header. Used to read version of the archive.
+#[derive(Serialize, Deserialize)]
+struct SerializableKeySharesArchiveHeader {
+	/// Archive version.
+	pub version: u32,
+}
+
+/// V1 of key shares archive.
+#[derive(Serialize, Deserialize)]
+struct SerializableKeySharesArchiveV1 {
+	/// Archive version.
+	pub version: u32,
+	/// Address of the key server that has exported key shares.
+	pub node: SerializableAddress,
+	/// JSON-serialized key shares, encrypted with key server public.
+	pub key_shares: SerializableBytes,
+	/// Signature of encrypted key shares hash, made by the key server.
+	pub signature: SerializableSignature,
+}
+
+/// Key share, as it is stored in the archive.
+#[derive(Serialize, Deserialize)]
+struct SerializableArchivedKeyShareV1 {
+	/// Server key id.
+	pub key_id: SerializableH256,
+	/// Key share.
+	pub key_share: SerializableKeyShareV3,
+}
+
+/// Result of key shares import.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KeySharesImportReport {
+	/// Number of key shares that have been imported.
+	pub imported: usize,
+	/// Number of key shares that have been skipped, because key storage already has shares of these keys.
+	pub skipped: usize,
+	/// Number of key shares that have been skipped, because these keys have been deleted.
+	pub deleted: usize,
+}
+
+/// Export all key shares from key storage to the archive, that could only be imported
+/// by the same key server.
+///
+/// Fails if any key share can't be unsealed or deserialized, so that the archive never
+/// silently misses key shares.
+pub fn export_key_shares(
+	key_storage: &PersistentKeyStorage,
+	key_server_key_pair: &dyn KeyServerKeyPair,
+) -> Result<Vec<u8>, Error> {
+	let key_shares = key_storage.db.iter(COL_KEY_SHARES)
+		.map(|(db_key, db_val)| {
+			let key_id = ServerKeyId::from_slice(&*db_key);
+			unseal_key_share(&key_storage.sealing_key, &key_id, &db_val)
+				.map(|key_share| SerializableArchivedKeyShareV1 {
+					key_id: key_id.into(),
+					key_share,
+				})
+				.map_err(|e| Error::Database(format!("Error exporting key share {:?}: {}", key_id, e)))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	let key_shares = serde_json::to_vec(&key_shares).map_err(|e| Error::Serde(e.to_string()))?;
+
+	let node = key_server_key_pair.address();
+	let key_shares = ecies::encrypt(key_server_key_pair.public(), node.as_bytes(), &key_shares)?;
+	let signature = key_server_key_pair.sign(&keccak(&key_shares))?;
+
+	serde_json::to_vec(&SerializableKeySharesArchiveV1 {
+		version: CURRENT_ARCHIVE_VERSION,
+		node: node.into(),
+		key_shares: key_shares.into(),
+		signature: signature.into(),
+	}).map_err(|e| Error::Serde(e.to_string()))
+}
+
+/// Import key shares from the archive to the key storage. Archive must be exported by the
+/// same key server. Key shares that are already in the key storage are left untouched. Key shares
+/// of keys that have been deleted after the archive has been exported are not restored.
+pub fn import_key_shares(
+	key_storage: &dyn KeyStorage,
+	key_server_key_pair: &KeyPair,
+	archive: &[u8],
+) -> Result<KeySharesImportReport, Error> {
+	let header: SerializableKeySharesArchiveHeader = serde_json::from_slice(archive)
+		.map_err(|e| Error::Serde(e.to_string()))?;
+	if header.version != CURRENT_ARCHIVE_VERSION {
+		return Err(Error::Internal(format!("Unsupported key shares archive version: {}", header.version)));
+	}
+
+	let archive: SerializableKeySharesArchiveV1 = serde_json::from_slice(archive)
+		.map_err(|e| Error::Serde(e.to_string()))?;
+	let self_address = public_to_address(key_server_key_pair.public());
+	if *archive.node != self_address {
+		return Err(Error::Internal(format!(
+			"Key shares archive has been exported by key server {:?}, but this key server is {:?}",
+			*archive.node,
+			self_address,
+		)));
+	}
+	let signer = recover(&archive.signature, &keccak(&*archive.key_shares))?;
+	if public_to_address(&signer) != self_address {
+		return Err(Error::Internal("Key shares archive has invalid signature".into()));
+	}
+
+	let key_shares = ecies::decrypt(key_server_key_pair.secret(), self_address.as_bytes(), &archive.key_shares)?;
+	let key_shares: Vec<SerializableArchivedKeyShareV1> = serde_json::from_slice(&key_shares)
+		.map_err(|e| Error::Serde(e.to_string()))?;
+
+	let mut report = KeySharesImportReport::default();
+	for key_share in key_shares {
+		let key_id = key_share.key_id.into();
+		if key_storage.contains(&key_id) {
+			report.skipped += 1;
+			continue;
+		}
+		if key_storage.is_deleted(&key_id)? {
+			report.deleted += 1;
+			continue;
+		}
+
+		key_storage.insert(key_id, key_share.key_share.into())?;
+		report.imported += 1;
+	}
+
+	Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+	use tempdir::TempDir;
+	use kvdb::KeyValueDB;
+	use parity_crypto::publickey::{Generator, Random};
+	use primitives::{
+		ServerKeyId,
+		key_server_key_pair::InMemoryKeyServerKeyPair,
+		key_storage::{InMemoryKeyStorage, KeyStorage},
+	};
+	use crate::db_key_storage::{COL_KEY_SHARES, PersistentKeyStorage, tests::{key_share, sealing_key}};
+	use super::{KeySharesImportReport, export_key_shares, import_key_shares};
+
+	fn key_storage(tempdir: &TempDir) -> PersistentKeyStorage {
+		PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap()
+	}
+
+	#[test]
+	fn key_shares_are_exported_and_imported() {
+		let tempdir = TempDir::new("").unwrap();
+		let key_pair = Random.generate();
+		let key_server_key_pair = InMemoryKeyServerKeyPair::new(key_pair.clone());
+		let key_storage = key_storage(&tempdir);
+		key_storage.insert(ServerKeyId::from_low_u64_be(1), key_share()).unwrap();
+		key_storage.insert(ServerKeyId::from_low_u64_be(2), key_share()).unwrap();
+
+		let archive = export_key_shares(&key_storage, &key_server_key_pair).unwrap();
+
+		let restored_key_storage = InMemoryKeyStorage::default();
+		restored_key_storage.insert(ServerKeyId::from_low_u64_be(2), key_share()).unwrap();
+		assert_eq!(
+			import_key_shares(&restored_key_storage, &key_pair, &archive),
+			Ok(KeySharesImportReport { imported: 1, skipped: 1, deleted: 0 }),
+		);
+		assert_eq!(
+			restored_key_storage.get(&ServerKeyId::from_low_u64_be(1)),
+			key_storage.get(&ServerKeyId::from_low_u64_be(1)),
+		);
+	}
+
+	#[test]
+	fn deleted_key_shares_are_not_imported() {
+		let tempdir = TempDir::new("").unwrap();
+		let key_pair = Random.generate();
+		let key_server_key_pair = InMemoryKeyServerKeyPair::new(key_pair.clone());
+		let key_storage = key_storage(&tempdir);
+		key_storage.insert(ServerKeyId::from_low_u64_be(1), key_share()).unwrap();
+		key_storage.insert(ServerKeyId::from_low_u64_be(2), key_share()).unwrap();
+
+		let archive = export_key_shares(&key_storage, &key_server_key_pair).unwrap();
+
+		key_storage.delete(&ServerKeyId::from_low_u64_be(1)).unwrap();
+		assert_eq!(
+			import_key_shares(&key_storage, &key_pair, &archive),
+			Ok(KeySharesImportReport { imported: 0, skipped: 1, deleted: 1 }),
+		);
+		assert_eq!(key_storage.get(&ServerKeyId::from_low_u64_be(1)), Ok(None));
+	}
+
+	#[test]
+	fn key_shares_are_not_exported_when_some_key_share_is_unreadable() {
+		let tempdir = TempDir::new("").unwrap();
+		let key_server_key_pair = InMemoryKeyServerKeyPair::new(Random.generate());
+		let key_storage = key_storage(&tempdir);
+		key_storage.insert(ServerKeyId::from_low_u64_be(1), key_share()).unwrap();
+		key_storage.insert(ServerKeyId::from_low_u64_be(2), key_share()).unwrap();
+
+		// corrupt the second key share
+		let mut batch = key_storage.db.transaction();
+		batch.put(COL_KEY_SHARES, ServerKeyId::from_low_u64_be(2).as_bytes(), b"corrupted");
+		key_storage.db.write(batch).unwrap();
+
+		assert!(export_key_shares(&key_storage, &key_server_key_pair).is_err());
+	}
+
+	#[test]
+	fn key_shares_archive_of_other_node_is_rejected() {
+		let tempdir = TempDir::new("").unwrap();
+		let key_server_key_pair = InMemoryKeyServerKeyPair::new(Random.generate());
+		let key_storage = key_storage(&tempdir);
+		key_storage.insert(ServerKeyId::from_low_u64_be(1), key_share()).unwrap();
+
+		let archive = export_key_shares(&key_storage, &key_server_key_pair).unwrap();
+
+		let restored_key_storage = InMemoryKeyStorage::default();
+		assert!(import_key_shares(&restored_key_storage, &Random.generate(), &archive).is_err());
+		assert_eq!(restored_key_storage.iter().count(), 0);
+	}
+
+	#[test]
+	fn key_shares_archive_with_invalid_signature_is_rejected() {
+		let (tempdir1, tempdir2) = (TempDir::new("").unwrap(), TempDir::new("").unwrap());
+		let key_pair = Random.generate();
+		let key_server_key_pair = InMemoryKeyServerKeyPair::new(key_pair.clone());
+		let key_storage1 = key_storage(&tempdir1);
+		key_storage1.insert(ServerKeyId::from_low_u64_be(1), key_share()).unwrap();
+		let key_storage2 = key_storage(&tempdir2);
+		key_storage2.insert(ServerKeyId::from_low_u64_be(2), key_share()).unwrap();
+
+		let archive1 = export_key_shares(&key_storage1, &key_server_key_pair).unwrap();
+		let archive2 = export_key_shares(&key_storage2, &key_server_key_pair).unwrap();
+		let mut archive: serde_json::Value = serde_json::from_slice(&archive1).unwrap();
+		let archive2: serde_json::Value = serde_json::from_slice(&archive2).unwrap();
+		archive["key_shares"] = archive2["key_shares"].clone();
+		let archive = serde_json::to_vec(&archive).unwrap();
+
+		let restored_key_storage = InMemoryKeyStorage::default();
+		assert!(import_key_shares(&restored_key_storage, &key_pair, &archive).is_err());
+		assert_eq!(restored_key_storage.iter().count(), 0);
+	}
+}
@@ -14,25 +14,58 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
+mod backup;
+mod sealing;
+mod upgrade;
+
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 use serde_json;
-use kvdb::KeyValueDB;
+use kvdb::{DBTransaction, KeyValueDB};
 use primitives::{
 	error::Error, ServerKeyId,
 	key_storage::{KeyStorage, KeyShare, KeyShareVersion},
-	serialization::{SerializablePublic, SerializableSecret, SerializableH256, SerializableAddress},
+	serialization::{SerializablePublic, SerializableSecret, SerializableH256, SerializableAddress, SerializableBytes},
 };
 
+pub use self::backup::{CURRENT_ARCHIVE_VERSION, KeySharesImportReport, export_key_shares, import_key_shares};
+pub use self::sealing::KeyShareSealingKey;
+pub use self::upgrade::{CURRENT_SCHEMA_VERSION, UpgradeOptions, UpgradeReport, UpgradeStepReport};
+
+/// Column where key shares are stored.
+const COL_KEY_SHARES: u32 = 0;
+/// Column where storage metadata (like schema version) is stored.
+const COL_METADATA: u32 = 1;
+/// Number of database columns.
+const NUM_COLUMNS: u32 = 2;
+/// Prefix of metadata keys, where tombstones of deleted keys are stored.
+const DELETED_KEY_PREFIX: &[u8] = b"deleted_key_";
+/// Prefix of metadata keys, where staged (prepared, but not yet committed) key shares are stored.
+const PREPARED_KEY_PREFIX: &[u8] = b"prepared_key_";
+/// Metadata key, where sealed `SEALING_KEY_CHECK_VALUE` is stored.
+const SEALING_KEY_CHECK_KEY: &[u8] = b"sealing_key_check";
+/// Value that is sealed with the sealing key to check that the same key is used after restart.
+const SEALING_KEY_CHECK_VALUE: &[u8] = b"parity-secretstore-key-storage-sealing-key-check";
+
 /// Persistent document encryption keys storage
 pub struct PersistentKeyStorage {
 	db: Arc<dyn KeyValueDB>,
+	sealing_key: Arc<KeyShareSealingKey>,
 }
 
 /// Persistent document encryption keys storage iterator
 pub struct PersistentKeyStorageIterator<'a> {
 	iter: Box<dyn Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a>,
+	sealing_key: &'a KeyShareSealingKey,
+}
+
+/// V4 of encrypted key share, as it is stored by key storage on the single key server.
+/// It is the V3 key share, sealed with the key server sealing key.
+#[derive(Serialize, Deserialize)]
+struct SerializableKeyShareV4 {
+	/// Sealed JSON-serialized `SerializableKeyShareV3`.
+	pub sealed: SerializableBytes,
 }
 
 /// V3 of encrypted key share, as it is stored by key storage on the single key server.
@@ -61,30 +94,74 @@ struct SerializableKeyShareVersionV3 {
 	pub id_numbers: BTreeMap<SerializableAddress, SerializableSecret>,
 	/// Node secret share.
 	pub secret_share: SerializableSecret,
+	/// Public key shares of all nodes. Missing in key shares, stored by previous versions.
+	#[serde(default)]
+	pub public_shares: BTreeMap<SerializableAddress, SerializablePublic>,
 }
 
 impl PersistentKeyStorage {
 	/// Crate new persistent keys storage at given path.
-	pub fn new(db_path: &std::path::Path) -> Result<Self, Error> {
+	///
+	/// All key shares are sealed with given sealing key. Database schema is upgraded
+	/// to the current version when storage is opened.
+	pub fn new(db_path: &std::path::Path, sealing_key: KeyShareSealingKey) -> Result<Self, Error> {
+		Self::with_upgrade_options(db_path, sealing_key, &Default::default())
+	}
+
+	/// Crate new persistent keys storage at given path, using given schema upgrade options.
+	///
+	/// Fails if database schema needs to be upgraded and dry run is requested. Also fails
+	/// if key shares have been sealed with other sealing key.
+	pub fn with_upgrade_options(
+		db_path: &std::path::Path,
+		sealing_key: KeyShareSealingKey,
+		upgrade_options: &UpgradeOptions,
+	) -> Result<Self, Error> {
 		let db_path = db_path
 			.to_str()
 			.ok_or_else(|| Error::Database("Invalid secretstore path".to_string()))?;
 
-		let config = kvdb_rocksdb::DatabaseConfig::with_columns(1);
-		let db = kvdb_rocksdb::Database::open(&config, &db_path)
-			.map_err(|e| Error::Database(format!("Error opening database: {:?}", e)))?;
+		let db = upgrade::open_database(db_path, upgrade_options.dry_run)?;
+		let upgrade_report = upgrade::upgrade_database(&db, &sealing_key, upgrade_options)?;
+		if upgrade_options.dry_run && upgrade_report.is_upgrade_required() {
+			return Err(Error::Database(format!(
+				"Database schema needs to be upgraded from version {} to version {}",
+				upgrade_report.from_version,
+				upgrade_report.to_version,
+			)));
+		}
+		check_sealing_key(&db, &sealing_key, upgrade_options.dry_run)?;
+
 		Ok(PersistentKeyStorage {
 			db: Arc::new(db),
+			sealing_key: Arc::new(sealing_key),
 		})
 	}
+
+	/// Upgrade database schema at given path to the current version without opening the storage.
+	pub fn upgrade(
+		db_path: &std::path::Path,
+		sealing_key: &KeyShareSealingKey,
+		upgrade_options: &UpgradeOptions,
+	) -> Result<UpgradeReport, Error> {
+		let db_path = db_path
+			.to_str()
+			.ok_or_else(|| Error::Database("Invalid secretstore path".to_string()))?;
+
+		let db = upgrade::open_database(db_path, upgrade_options.dry_run)?;
+		upgrade::upgrade_database(&db, sealing_key, upgrade_options)
+	}
 }
 
 impl KeyStorage for PersistentKeyStorage {
 	fn insert(&self, document: ServerKeyId, key: KeyShare) -> Result<(), Error> {
-		let key: SerializableKeyShareV3 = key.into();
-		let key = serde_json::to_vec(&key).map_err(|e| Error::Database(e.to_string()))?;
+		if self.is_deleted(&document)? {
+			return Err(Error::ServerKeyIsDeleted);
+		}
+
+		let key = seal_key_share(&self.sealing_key, &document, key.into())?;
 		let mut batch = self.db.transaction();
-		batch.put(0, document.as_bytes(), &key);
+		batch.put(COL_KEY_SHARES, document.as_bytes(), &key);
 		self.db.write(batch).map_err(Into::into)
 	}
 
@@ -93,12 +170,11 @@ impl KeyStorage for PersistentKeyStorage {
 	}
 
 	fn get(&self, document: &ServerKeyId) -> Result<Option<KeyShare>, Error> {
-		self.db.get(0, document.as_bytes())
+		self.db.get(COL_KEY_SHARES, document.as_bytes())
 			.map_err(|e| Error::Database(e.to_string()))
 			.and_then(|key| match key {
 				None => Ok(None),
-				Some(key) => serde_json::from_slice::<SerializableKeyShareV3>(&key)
-					.map_err(|e| Error::Database(e.to_string()))
+				Some(key) => unseal_key_share(&self.sealing_key, document, &key)
 					.map(Into::into)
 					.map(Some),
 			})
@@ -106,43 +182,185 @@ impl KeyStorage for PersistentKeyStorage {
 
 	fn remove(&self, document: &ServerKeyId) -> Result<(), Error> {
 		let mut batch = self.db.transaction();
-		batch.delete(0, document.as_bytes());
+		batch.delete(COL_KEY_SHARES, document.as_bytes());
+		self.db.write(batch).map_err(Into::into)
+	}
+
+	fn delete(&self, document: &ServerKeyId) -> Result<(), Error> {
+		let mut batch = self.db.transaction();
+		batch.delete(COL_KEY_SHARES, document.as_bytes());
+		batch.put(COL_METADATA, &deleted_key_tombstone(document), &[]);
+		self.db.write(batch).map_err(Into::into)
+	}
+
+	fn is_deleted(&self, document: &ServerKeyId) -> Result<bool, Error> {
+		self.db.get(COL_METADATA, &deleted_key_tombstone(document))
+			.map(|k| k.is_some())
+			.map_err(|e| Error::Database(e.to_string()))
+	}
+
+	fn prepare(&self, document: ServerKeyId, key: KeyShare) -> Result<(), Error> {
+		let key = seal_key_share(&self.sealing_key, &document, key.into())?;
+		let mut batch = self.db.transaction();
+		batch.put(COL_METADATA, &prepared_key_share(&document), &key);
+		self.db.write(batch).map_err(Into::into)
+	}
+
+	fn commit_prepared(&self, document: &ServerKeyId) -> Result<(), Error> {
+		if self.is_deleted(document)? {
+			return Err(Error::ServerKeyIsDeleted);
+		}
+
+		let key = self.db.get(COL_METADATA, &prepared_key_share(document))
+			.map_err(|e| Error::Database(e.to_string()))?
+			.ok_or(Error::InvalidStateForRequest)?;
+		let mut batch = self.db.transaction();
+		batch.put(COL_KEY_SHARES, document.as_bytes(), &key);
+		batch.delete(COL_METADATA, &prepared_key_share(document));
+		self.db.write(batch).map_err(Into::into)
+	}
+
+	fn discard_prepared(&self, document: &ServerKeyId) -> Result<(), Error> {
+		let mut batch = self.db.transaction();
+		batch.delete(COL_METADATA, &prepared_key_share(document));
 		self.db.write(batch).map_err(Into::into)
 	}
 
 	fn clear(&self) -> Result<(), Error> {
 		let mut batch = self.db.transaction();
-		for (key, _) in self.iter() {
-			batch.delete(0, key.as_bytes());
+		for (key, _) in self.db.iter(COL_KEY_SHARES) {
+			batch.delete(COL_KEY_SHARES, &key);
 		}
 		self.db.write(batch)
 			.map_err(|e| Error::Database(e.to_string()))
 	}
 
 	fn contains(&self, document: &ServerKeyId) -> bool {
-		self.db.get(0, document.as_bytes())
+		self.db.get(COL_KEY_SHARES, document.as_bytes())
 			.map(|k| k.is_some())
 			.unwrap_or(false)
 	}
 
-	fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=(ServerKeyId, KeyShare)> + 'a> {
+	fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Result<(ServerKeyId, KeyShare), Error>> + 'a> {
 		Box::new(PersistentKeyStorageIterator {
-			iter: self.db.iter(0),
+			iter: self.db.iter(COL_KEY_SHARES),
+			sealing_key: &self.sealing_key,
 		})
 	}
 }
 
 impl<'a> Iterator for PersistentKeyStorageIterator<'a> {
-	type Item = (ServerKeyId, KeyShare);
+	type Item = Result<(ServerKeyId, KeyShare), Error>;
 
-	fn next(&mut self) -> Option<(ServerKeyId, KeyShare)> {
+	fn next(&mut self) -> Option<Self::Item> {
+		let sealing_key = self.sealing_key;
 		self.iter.as_mut().next()
-			.and_then(|(db_key, db_val)| serde_json::from_slice::<SerializableKeyShareV3>(&db_val)
-					  .ok()
-					  .map(|key| (ServerKeyId::from_slice(&*db_key), key.into())))
+			.map(|(db_key, db_val)| {
+				let key_id = ServerKeyId::from_slice(&*db_key);
+				unseal_key_share(sealing_key, &key_id, &db_val)
+					.map_err(|e| Error::Database(format!("Error reading key share {:?}: {}", key_id, e)))
+					.map(|key| (key_id, key.into()))
+			})
 	}
 }
 
+/// Metadata key of the deleted key tombstone.
+fn deleted_key_tombstone(key_id: &ServerKeyId) -> Vec<u8> {
+	let mut tombstone = DELETED_KEY_PREFIX.to_vec();
+	tombstone.extend_from_slice(key_id.as_bytes());
+	tombstone
+}
+
+/// Metadata key of the staged key share.
+fn prepared_key_share(key_id: &ServerKeyId) -> Vec<u8> {
+	let mut prepared_key = PREPARED_KEY_PREFIX.to_vec();
+	prepared_key.extend_from_slice(key_id.as_bytes());
+	prepared_key
+}
+
+/// Check that database is opened with the sealing key that has been used to seal key shares.
+///
+/// Databases that have been created before the check has been introduced have no sealed check
+/// value. For these databases the first key share is unsealed instead and the check value is
+/// written (unless dry run is requested).
+fn check_sealing_key(
+	db: &dyn KeyValueDB,
+	sealing_key: &KeyShareSealingKey,
+	dry_run: bool,
+) -> Result<(), Error> {
+	let check_key_id = ServerKeyId::zero();
+	let sealed_check_value = db.get(COL_METADATA, SEALING_KEY_CHECK_KEY)
+		.map_err(|e| Error::Database(e.to_string()))?;
+	if let Some(sealed_check_value) = sealed_check_value {
+		return match sealing_key.unseal(&check_key_id, &sealed_check_value) {
+			Ok(ref check_value) if check_value.as_slice() == SEALING_KEY_CHECK_VALUE => Ok(()),
+			_ => Err(Error::Database("Key shares have been sealed with other sealing key".into())),
+		};
+	}
+
+	if let Some((db_key, db_val)) = db.iter(COL_KEY_SHARES).next() {
+		let key_id = ServerKeyId::from_slice(&*db_key);
+		unseal_key_share(sealing_key, &key_id, &db_val)
+			.map_err(|e| Error::Database(format!("Key shares have been sealed with other sealing key: {}", e)))?;
+	}
+
+	if !dry_run {
+		let mut batch = db.transaction();
+		batch.put(COL_METADATA, SEALING_KEY_CHECK_KEY, &sealing_key.seal(&check_key_id, SEALING_KEY_CHECK_VALUE)?);
+		db.write(batch).map_err(|e| Error::Database(e.to_string()))?;
+	}
+
+	Ok(())
+}
+
+/// Seal all plain (V3) key shares that are stored in the database.
+fn seal_plain_key_shares(
+	db: &dyn KeyValueDB,
+	sealing_key: &KeyShareSealingKey,
+	batch: &mut DBTransaction,
+) -> Result<usize, Error> {
+	let mut sealed_key_shares = 0;
+	for (db_key, db_val) in db.iter(COL_KEY_SHARES) {
+		if serde_json::from_slice::<SerializableKeyShareV4>(&db_val).is_ok() {
+			continue;
+		}
+
+		let key_id = ServerKeyId::from_slice(&*db_key);
+		let key = serde_json::from_slice::<SerializableKeyShareV3>(&db_val)
+			.map_err(|e| Error::Database(format!("Error reading key share {:?}: {}", key_id, e)))?;
+		batch.put(COL_KEY_SHARES, &db_key, &seal_key_share(sealing_key, &key_id, key)?);
+		sealed_key_shares += 1;
+	}
+
+	Ok(sealed_key_shares)
+}
+
+/// Seal key share and serialize it for writing to the database.
+fn seal_key_share(
+	sealing_key: &KeyShareSealingKey,
+	key_id: &ServerKeyId,
+	key: SerializableKeyShareV3,
+) -> Result<Vec<u8>, Error> {
+	let key = serde_json::to_vec(&key).map_err(|e| Error::Database(e.to_string()))?;
+	let key = SerializableKeyShareV4 {
+		sealed: sealing_key.seal(key_id, &key)?.into(),
+	};
+	serde_json::to_vec(&key).map_err(|e| Error::Database(e.to_string()))
+}
+
+/// Deserialize key share, read from the database, and unseal it.
+fn unseal_key_share(
+	sealing_key: &KeyShareSealingKey,
+	key_id: &ServerKeyId,
+	key: &[u8],
+) -> Result<SerializableKeyShareV3, Error> {
+	let key = serde_json::from_slice::<SerializableKeyShareV4>(key)
+		.map_err(|e| Error::Database(e.to_string()))?;
+	let key = sealing_key.unseal(key_id, &key.sealed)?;
+	serde_json::from_slice::<SerializableKeyShareV3>(&key)
+		.map_err(|e| Error::Database(e.to_string()))
+}
+
 impl From<KeyShare> for SerializableKeyShareV3 {
 	fn from(key: KeyShare) -> Self {
 		SerializableKeyShareV3 {
@@ -162,6 +380,7 @@ impl From<KeyShareVersion> for SerializableKeyShareVersionV3 {
 			hash: version.hash.into(),
 			id_numbers: version.id_numbers.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
 			secret_share: version.secret_share.into(),
+			public_shares: version.public_shares.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
 		}
 	}
 }
@@ -179,6 +398,7 @@ impl From<SerializableKeyShareV3> for KeyShare {
 					hash: v.hash.into(),
 					id_numbers: v.id_numbers.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
 					secret_share: v.secret_share.into(),
+					public_shares: v.public_shares.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
 				})
 				.collect(),
 		}
@@ -187,21 +407,29 @@ impl From<SerializableKeyShareV3> for KeyShare {
 
 #[cfg(test)]
 pub mod tests {
-	use std::collections::HashMap;
+	use std::collections::{HashMap, HashSet};
 	use parking_lot::RwLock;
 	use tempdir::TempDir;
+	use kvdb::KeyValueDB;
 	use parity_crypto::publickey::{Random, Generator, Public, public_to_address};
 	use primitives::{error::Error, ServerKeyId};
-	use super::{KeyStorage, PersistentKeyStorage, KeyShare, KeyShareVersion};
+	use super::{KeyStorage, PersistentKeyStorage, KeyShare, KeyShareVersion, KeyShareSealingKey,
+		SerializableKeyShareV3, COL_KEY_SHARES, COL_METADATA, SEALING_KEY_CHECK_KEY};
 
 	/// In-memory document encryption keys storage
 	#[derive(Default)]
 	pub struct DummyKeyStorage {
 		keys: RwLock<HashMap<ServerKeyId, KeyShare>>,
+		deleted_keys: RwLock<HashSet<ServerKeyId>>,
+		prepared_keys: RwLock<HashMap<ServerKeyId, KeyShare>>,
 	}
 
 	impl KeyStorage for DummyKeyStorage {
 		fn insert(&self, document: ServerKeyId, key: KeyShare) -> Result<(), Error> {
+			if self.deleted_keys.read().contains(&document) {
+				return Err(Error::ServerKeyIsDeleted);
+			}
+
 			self.keys.write().insert(document, key);
 			Ok(())
 		}
@@ -220,6 +448,36 @@ pub mod tests {
 			Ok(())
 		}
 
+		fn delete(&self, document: &ServerKeyId) -> Result<(), Error> {
+			self.deleted_keys.write().insert(document.clone());
+			self.keys.write().remove(document);
+			Ok(())
+		}
+
+		fn is_deleted(&self, document: &ServerKeyId) -> Result<bool, Error> {
+			Ok(self.deleted_keys.read().contains(document))
+		}
+
+		fn prepare(&self, document: ServerKeyId, key: KeyShare) -> Result<(), Error> {
+			self.prepared_keys.write().insert(document, key);
+			Ok(())
+		}
+
+		fn commit_prepared(&self, document: &ServerKeyId) -> Result<(), Error> {
+			if self.deleted_keys.read().contains(document) {
+				return Err(Error::ServerKeyIsDeleted);
+			}
+
+			let key = self.prepared_keys.write().remove(document).ok_or(Error::InvalidStateForRequest)?;
+			self.keys.write().insert(document.clone(), key);
+			Ok(())
+		}
+
+		fn discard_prepared(&self, document: &ServerKeyId) -> Result<(), Error> {
+			self.prepared_keys.write().remove(document);
+			Ok(())
+		}
+
 		fn clear(&self) -> Result<(), Error> {
 			self.keys.write().clear();
 			Ok(())
@@ -229,8 +487,8 @@ pub mod tests {
 			self.keys.read().contains_key(document)
 		}
 
-		fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=(ServerKeyId, KeyShare)> + 'a> {
-			Box::new(self.keys.read().clone().into_iter())
+		fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Result<(ServerKeyId, KeyShare), Error>> + 'a> {
+			Box::new(self.keys.read().clone().into_iter().map(Ok))
 		}
 	}
 
@@ -253,6 +511,7 @@ pub mod tests {
 					)
 				].into_iter().collect(),
 				secret_share: Random.generate().secret().clone(),
+				public_shares: Default::default(),
 			}],
 		};
 		let key2 = ServerKeyId::from_low_u64_be(2);
@@ -271,11 +530,12 @@ pub mod tests {
 					)
 				].into_iter().collect(),
 				secret_share: Random.generate().secret().clone(),
+				public_shares: Default::default(),
 			}],
 		};
 		let key3 = ServerKeyId::from_low_u64_be(3);
 
-		let key_storage = PersistentKeyStorage::new(tempdir.path()).unwrap();
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
 		key_storage.insert(key1.clone(), value1.clone()).unwrap();
 		key_storage.insert(key2.clone(), value2.clone()).unwrap();
 		assert_eq!(key_storage.get(&key1), Ok(Some(value1.clone())));
@@ -283,9 +543,214 @@ pub mod tests {
 		assert_eq!(key_storage.get(&key3), Ok(None));
 		drop(key_storage);
 
-		let key_storage = PersistentKeyStorage::new(tempdir.path()).unwrap();
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
 		assert_eq!(key_storage.get(&key1), Ok(Some(value1)));
 		assert_eq!(key_storage.get(&key2), Ok(Some(value2)));
 		assert_eq!(key_storage.get(&key3), Ok(None));
 	}
+
+	#[test]
+	fn persistent_key_storage_does_not_store_plain_secrets() {
+		let tempdir = TempDir::new("").unwrap();
+		let key = ServerKeyId::from_low_u64_be(1);
+		let value = key_share();
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		key_storage.insert(key.clone(), value.clone()).unwrap();
+		drop(key_storage);
+
+		assert_no_plain_secrets(tempdir.path(), &value);
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		assert_eq!(key_storage.get(&key), Ok(Some(value)));
+	}
+
+	#[test]
+	fn persistent_key_storage_is_not_opened_with_other_sealing_key() {
+		let tempdir = TempDir::new("").unwrap();
+
+		// even empty storage remembers its sealing key
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		drop(key_storage);
+
+		let other_sealing_key = KeyShareSealingKey::from_passphrase("other passphrase").unwrap();
+		assert!(PersistentKeyStorage::new(tempdir.path(), other_sealing_key).is_err());
+		PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+	}
+
+	#[test]
+	fn persistent_key_storage_without_sealing_key_check_is_not_opened_with_other_sealing_key() {
+		let tempdir = TempDir::new("").unwrap();
+		let key = ServerKeyId::from_low_u64_be(1);
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		key_storage.insert(key.clone(), key_share()).unwrap();
+		drop(key_storage);
+
+		// storage that has been created before the check has been introduced
+		let db = super::upgrade::open_database(tempdir.path().to_str().unwrap(), false).unwrap();
+		let mut batch = db.transaction();
+		batch.delete(COL_METADATA, SEALING_KEY_CHECK_KEY);
+		db.write(batch).unwrap();
+		drop(db);
+
+		let other_sealing_key = KeyShareSealingKey::from_passphrase("other passphrase").unwrap();
+		assert!(PersistentKeyStorage::new(tempdir.path(), other_sealing_key).is_err());
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		assert!(key_storage.get(&key).unwrap().is_some());
+	}
+
+	#[test]
+	fn persistent_key_storage_iterator_returns_unreadable_key_shares_errors() {
+		let tempdir = TempDir::new("").unwrap();
+		let key1 = ServerKeyId::from_low_u64_be(1);
+		let key2 = ServerKeyId::from_low_u64_be(2);
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		key_storage.insert(key1.clone(), key_share()).unwrap();
+		key_storage.insert(key2.clone(), key_share()).unwrap();
+
+		// corrupt the second key share
+		let mut batch = key_storage.db.transaction();
+		batch.put(COL_KEY_SHARES, key2.as_bytes(), b"corrupted");
+		key_storage.db.write(batch).unwrap();
+
+		let key_shares = key_storage.iter().collect::<Vec<_>>();
+		assert_eq!(key_shares.len(), 2);
+		assert!(key_shares[0].is_ok());
+		assert!(key_shares[1].is_err());
+	}
+
+	#[test]
+	fn persistent_key_storage_seals_plain_key_shares_on_open() {
+		let tempdir = TempDir::new("").unwrap();
+		let key = ServerKeyId::from_low_u64_be(1);
+		let value = key_share();
+
+		let db = open_raw_db(tempdir.path());
+		let plain_value: SerializableKeyShareV3 = value.clone().into();
+		let mut batch = db.transaction();
+		batch.put(COL_KEY_SHARES, key.as_bytes(), &serde_json::to_vec(&plain_value).unwrap());
+		db.write(batch).unwrap();
+		drop(db);
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		assert_eq!(key_storage.get(&key), Ok(Some(value.clone())));
+		drop(key_storage);
+
+		assert_no_plain_secrets(tempdir.path(), &value);
+	}
+
+	#[test]
+	fn persistent_key_storage_remembers_deleted_keys() {
+		let tempdir = TempDir::new("").unwrap();
+		let key1 = ServerKeyId::from_low_u64_be(1);
+		let key2 = ServerKeyId::from_low_u64_be(2);
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		key_storage.insert(key1.clone(), key_share()).unwrap();
+		key_storage.insert(key2.clone(), key_share()).unwrap();
+		key_storage.delete(&key1).unwrap();
+		key_storage.remove(&key2).unwrap();
+		drop(key_storage);
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		assert_eq!(key_storage.get(&key1), Ok(None));
+		assert_eq!(key_storage.get(&key2), Ok(None));
+		assert_eq!(key_storage.is_deleted(&key1), Ok(true));
+		assert_eq!(key_storage.is_deleted(&key2), Ok(false));
+		assert_eq!(key_storage.iter().count(), 0);
+
+		// deleted key can't be inserted again
+		assert_eq!(key_storage.insert(key1.clone(), key_share()), Err(Error::ServerKeyIsDeleted));
+		key_storage.insert(key2.clone(), key_share()).unwrap();
+	}
+
+	#[test]
+	fn persistent_key_storage_keeps_prepared_key_share_until_commit() {
+		let tempdir = TempDir::new("").unwrap();
+		let key1 = ServerKeyId::from_low_u64_be(1);
+		let key2 = ServerKeyId::from_low_u64_be(2);
+		let (value1, value2) = (key_share(), key_share());
+		let (prepared_value1, prepared_value2) = (key_share(), key_share());
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		key_storage.insert(key1.clone(), value1.clone()).unwrap();
+		key_storage.insert(key2.clone(), value2.clone()).unwrap();
+		key_storage.prepare(key1.clone(), prepared_value1.clone()).unwrap();
+		key_storage.prepare(key2.clone(), prepared_value2).unwrap();
+		assert_eq!(key_storage.get(&key1), Ok(Some(value1)));
+		drop(key_storage);
+
+		// prepared key shares survive restart
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		key_storage.commit_prepared(&key1).unwrap();
+		key_storage.discard_prepared(&key2).unwrap();
+		assert_eq!(key_storage.get(&key1), Ok(Some(prepared_value1)));
+		assert_eq!(key_storage.get(&key2), Ok(Some(value2)));
+		assert_eq!(key_storage.iter().count(), 2);
+
+		// nothing to commit
+		assert_eq!(key_storage.commit_prepared(&key1), Err(Error::InvalidStateForRequest));
+		assert_eq!(key_storage.commit_prepared(&key2), Err(Error::InvalidStateForRequest));
+	}
+
+	#[test]
+	fn key_share_without_public_shares_is_deserialized() {
+		let mut value = key_share();
+		value.versions[0].public_shares.clear();
+
+		let plain_value: SerializableKeyShareV3 = value.clone().into();
+		let mut json_value = serde_json::to_value(&plain_value).unwrap();
+		json_value["versions"][0].as_object_mut().unwrap().remove("public_shares");
+		let plain_value: SerializableKeyShareV3 = serde_json::from_value(json_value).unwrap();
+		assert_eq!(KeyShare::from(plain_value), value);
+	}
+
+	pub fn sealing_key() -> KeyShareSealingKey {
+		KeyShareSealingKey::from_passphrase("passphrase").unwrap()
+	}
+
+	pub fn key_share() -> KeyShare {
+		KeyShare {
+			author: Default::default(),
+			threshold: 1,
+			public: Random.generate().public().clone(),
+			common_point: Some(Random.generate().public().clone()),
+			encrypted_point: Some(Random.generate().public().clone()),
+			versions: vec![KeyShareVersion {
+				hash: Default::default(),
+				id_numbers: vec![
+					(
+						public_to_address(Random.generate().public()),
+						Random.generate().secret().clone(),
+					)
+				].into_iter().collect(),
+				secret_share: Random.generate().secret().clone(),
+				public_shares: vec![
+					(
+						public_to_address(Random.generate().public()),
+						Random.generate().public().clone(),
+					)
+				].into_iter().collect(),
+			}],
+		}
+	}
+
+	fn open_raw_db(path: &std::path::Path) -> kvdb_rocksdb::Database {
+		let config = kvdb_rocksdb::DatabaseConfig::with_columns(1);
+		kvdb_rocksdb::Database::open(&config, path.to_str().unwrap()).unwrap()
+	}
+
+	fn assert_no_plain_secrets(path: &std::path::Path, key: &KeyShare) {
+		let secrets = key.versions.iter()
+			.flat_map(|version| version.id_numbers.values().chain(std::iter::once(&version.secret_share)))
+			.collect::<Vec<_>>();
+		let db = super::upgrade::open_database(path.to_str().unwrap(), false).unwrap();
+		for (_, db_val) in db.iter(COL_KEY_SHARES) {
+			let db_val = String::from_utf8_lossy(&db_val).to_lowercase();
+			for secret in &secrets {
+				assert!(!db_val.contains(&secret.to_hex()));
+			}
+		}
+	}
 }
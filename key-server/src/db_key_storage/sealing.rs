@@ -0,0 +1,75 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use keccak_hash::keccak;
+use parity_crypto::publickey::{KeyPair, ecies};
+use primitives::{
+	error::Error, ServerKeyId,
+	key_server_key_pair::KeyServerKeyPair,
+};
+
+/// Message that is signed by the key server key pair to derive sealing key.
+const SEALING_KEY_DERIVATION_MESSAGE: &[u8] = b"parity-secretstore-key-storage-sealing-key";
+/// Salt that is used to derive sealing key from the passphrase.
+const SEALING_KEY_PASSPHRASE_SALT: &[u8] = b"parity-secretstore-key-storage-sealing-salt";
+/// Number of PBKDF2 iterations that are used to derive sealing key from the passphrase.
+const SEALING_KEY_PASSPHRASE_ITERATIONS: u32 = 10240;
+
+/// Key that is used to seal key shares before they are written to the database.
+pub struct KeyShareSealingKey {
+	key_pair: KeyPair,
+}
+
+impl KeyShareSealingKey {
+	/// Derive sealing key from the key server key pair.
+	///
+	/// The key pair must produce deterministic signatures (RFC6979), otherwise sealed
+	/// key shares won't be readable after restart.
+	pub fn from_key_server_key_pair(key_server_key_pair: &dyn KeyServerKeyPair) -> Result<Self, Error> {
+		let signature = key_server_key_pair.sign(&keccak(SEALING_KEY_DERIVATION_MESSAGE))?;
+		Self::from_seed(keccak(&*signature).as_bytes())
+	}
+
+	/// Derive sealing key from the passphrase.
+	pub fn from_passphrase(passphrase: &str) -> Result<Self, Error> {
+		let mut seed = [0u8; 32];
+		parity_crypto::pbkdf2::sha256(
+			SEALING_KEY_PASSPHRASE_ITERATIONS,
+			parity_crypto::pbkdf2::Salt(SEALING_KEY_PASSPHRASE_SALT),
+			parity_crypto::pbkdf2::Secret(passphrase.as_bytes()),
+			&mut seed,
+		);
+		Self::from_seed(&seed)
+	}
+
+	/// Seal key share of given key.
+	pub fn seal(&self, key_id: &ServerKeyId, data: &[u8]) -> Result<Vec<u8>, Error> {
+		ecies::encrypt(self.key_pair.public(), key_id.as_bytes(), data)
+			.map_err(|error| Error::Database(format!("Error sealing key share: {}", error)))
+	}
+
+	/// Unseal key share of given key.
+	pub fn unseal(&self, key_id: &ServerKeyId, data: &[u8]) -> Result<Vec<u8>, Error> {
+		ecies::decrypt(self.key_pair.secret(), key_id.as_bytes(), data)
+			.map_err(|error| Error::Database(format!("Error unsealing key share: {}", error)))
+	}
+
+	fn from_seed(seed: &[u8]) -> Result<Self, Error> {
+		Ok(KeyShareSealingKey {
+			key_pair: KeyPair::from_secret_slice(seed)?,
+		})
+	}
+}
@@ -0,0 +1,346 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+use log::info;
+use kvdb::{DBTransaction, KeyValueDB};
+use primitives::error::Error;
+use super::{COL_KEY_SHARES, COL_METADATA, NUM_COLUMNS, KeyShareSealingKey, seal_plain_key_shares};
+
+/// Current version of the key storage schema.
+pub const CURRENT_SCHEMA_VERSION: u32 = 4;
+/// Version of the key storage schema that has been used before versioning was introduced.
+const LEGACY_SCHEMA_VERSION: u32 = 3;
+/// Key of schema version in the metadata column.
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+/// Single step of the key storage schema upgrade.
+struct UpgradeStep {
+	/// Schema version that is upgraded by this step. The step upgrades it to the next version.
+	from_version: u32,
+	/// Human-readable step description.
+	description: &'static str,
+	/// Upgrade function. Returns number of records that are updated by the step.
+	upgrade: fn(&dyn KeyValueDB, &KeyShareSealingKey, &mut DBTransaction) -> Result<usize, Error>,
+}
+
+/// Registry of all known upgrade steps, ordered by schema version.
+const UPGRADE_STEPS: &[UpgradeStep] = &[
+	UpgradeStep {
+		from_version: 3,
+		description: "seal plain key shares",
+		upgrade: seal_plain_key_shares,
+	},
+];
+
+/// Key storage upgrade options.
+#[derive(Debug, Default, Clone)]
+pub struct UpgradeOptions {
+	/// If true, upgrade steps are computed, but changes are not written to the database.
+	pub dry_run: bool,
+	/// If set, database is copied to this path before changes are written.
+	pub backup_path: Option<PathBuf>,
+}
+
+/// Key storage upgrade report.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UpgradeReport {
+	/// Schema version before upgrade.
+	pub from_version: u32,
+	/// Schema version after upgrade.
+	pub to_version: u32,
+	/// Applied (or planned if dry run has been requested) upgrade steps.
+	pub steps: Vec<UpgradeStepReport>,
+}
+
+/// Report of single upgrade step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeStepReport {
+	/// Schema version that is upgraded by this step.
+	pub from_version: u32,
+	/// Human-readable step description.
+	pub description: &'static str,
+	/// Number of records, updated by this step.
+	pub updated_records: usize,
+}
+
+impl UpgradeReport {
+	/// Returns true if database schema has been (or needs to be) upgraded.
+	pub fn is_upgrade_required(&self) -> bool {
+		self.from_version != self.to_version
+	}
+}
+
+/// Open key storage database, adding metadata column if required.
+///
+/// If dry run is requested, database is never created or modified. So the legacy database
+/// is opened without metadata column.
+pub fn open_database(db_path: &str, dry_run: bool) -> Result<kvdb_rocksdb::Database, Error> {
+	if dry_run && !Path::new(db_path).exists() {
+		return Err(Error::Database(format!("Database at {} does not exist", db_path)));
+	}
+
+	let config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	let open_error = match kvdb_rocksdb::Database::open(&config, db_path) {
+		Ok(db) => return Ok(db),
+		Err(error) => error,
+	};
+
+	// databases that have been created before schema versioning was introduced have no metadata column
+	// => fall back to the legacy config only if database is actually opened with single column
+	let legacy_config = kvdb_rocksdb::DatabaseConfig::with_columns(1);
+	let db = match kvdb_rocksdb::Database::open(&legacy_config, db_path) {
+		Ok(db) if db.num_columns() == 1 => db,
+		_ => return Err(Error::Database(format!("Error opening database: {:?}", open_error))),
+	};
+	if !dry_run {
+		db.add_column()
+			.map_err(|e| Error::Database(format!("Error adding metadata column: {:?}", e)))?;
+	}
+	Ok(db)
+}
+
+/// Upgrade key storage database schema to the current version.
+///
+/// Every upgrade step is applied to the database (along with schema version bump) before
+/// the next step is computed. If dry run is requested, all steps are computed using the
+/// original database.
+pub fn upgrade_database(
+	db: &kvdb_rocksdb::Database,
+	sealing_key: &KeyShareSealingKey,
+	options: &UpgradeOptions,
+) -> Result<UpgradeReport, Error> {
+	let from_version = read_database_schema_version(db)?;
+	if from_version > CURRENT_SCHEMA_VERSION {
+		return Err(Error::Database(format!(
+			"Database schema version {} is newer than supported version {}",
+			from_version,
+			CURRENT_SCHEMA_VERSION,
+		)));
+	}
+
+	let mut report = UpgradeReport {
+		from_version,
+		to_version: CURRENT_SCHEMA_VERSION,
+		steps: Vec::new(),
+	};
+	if !options.dry_run && report.is_upgrade_required() {
+		if let Some(ref backup_path) = options.backup_path {
+			backup_database(db, backup_path)?;
+		}
+	}
+
+	for step in UPGRADE_STEPS.iter().filter(|step| step.from_version >= from_version) {
+		let mut transaction = db.transaction();
+		let updated_records = (step.upgrade)(db, sealing_key, &mut transaction)?;
+		transaction.put(COL_METADATA, SCHEMA_VERSION_KEY, &(step.from_version + 1).to_le_bytes());
+		if !options.dry_run {
+			db.write(transaction).map_err(|e| Error::Database(e.to_string()))?;
+
+			info!(
+				target: "secretstore",
+				"Upgraded key storage from version {}: {} ({} records updated)",
+				step.from_version,
+				step.description,
+				updated_records,
+			);
+		}
+
+		report.steps.push(UpgradeStepReport {
+			from_version: step.from_version,
+			description: step.description,
+			updated_records,
+		});
+	}
+
+	// new database has no schema version yet
+	let has_schema_version = db.get(COL_METADATA, SCHEMA_VERSION_KEY)
+		.map_err(|e| Error::Database(e.to_string()))?
+		.is_some();
+	if !options.dry_run && !has_schema_version {
+		let mut transaction = db.transaction();
+		transaction.put(COL_METADATA, SCHEMA_VERSION_KEY, &CURRENT_SCHEMA_VERSION.to_le_bytes());
+		db.write(transaction).map_err(|e| Error::Database(e.to_string()))?;
+	}
+
+	Ok(report)
+}
+
+/// Read schema version of the database that could have been opened without metadata column.
+fn read_database_schema_version(db: &kvdb_rocksdb::Database) -> Result<u32, Error> {
+	if db.num_columns() <= COL_METADATA {
+		return Ok(LEGACY_SCHEMA_VERSION);
+	}
+
+	read_schema_version(db)
+}
+
+/// Read schema version from the metadata column.
+fn read_schema_version(db: &dyn KeyValueDB) -> Result<u32, Error> {
+	let version = db.get(COL_METADATA, SCHEMA_VERSION_KEY)
+		.map_err(|e| Error::Database(e.to_string()))?;
+	match version {
+		Some(version) if version.len() == 4 => {
+			let mut version_bytes = [0u8; 4];
+			version_bytes.copy_from_slice(&version);
+			Ok(u32::from_le_bytes(version_bytes))
+		},
+		Some(_) => Err(Error::Database("Invalid database schema version".into())),
+		// empty database is created using the current schema
+		None if db.iter(COL_KEY_SHARES).next().is_none() => Ok(CURRENT_SCHEMA_VERSION),
+		None => Ok(LEGACY_SCHEMA_VERSION),
+	}
+}
+
+/// Copy all database columns to the new database at given path.
+fn backup_database(db: &kvdb_rocksdb::Database, backup_path: &Path) -> Result<(), Error> {
+	let backup_path = backup_path
+		.to_str()
+		.ok_or_else(|| Error::Database("Invalid backup path".to_string()))?;
+	let config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	let backup_db = kvdb_rocksdb::Database::open(&config, backup_path)
+		.map_err(|e| Error::Database(format!("Error opening backup database: {:?}", e)))?;
+	if backup_db.iter(COL_KEY_SHARES).next().is_some() || backup_db.iter(COL_METADATA).next().is_some() {
+		return Err(Error::Database(format!("Backup database at {} is not empty", backup_path)));
+	}
+
+	let mut transaction = backup_db.transaction();
+	for column in 0..NUM_COLUMNS {
+		for (key, value) in db.iter(column) {
+			transaction.put(column, &key, &value);
+		}
+	}
+	backup_db.write(transaction)
+		.map_err(|e| Error::Database(format!("Error writing backup database: {:?}", e)))?;
+
+	info!(target: "secretstore", "Key storage has been backed up to {}", backup_path);
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use tempdir::TempDir;
+	use kvdb::KeyValueDB;
+	use primitives::ServerKeyId;
+	use crate::db_key_storage::{
+		PersistentKeyStorage, SerializableKeyShareV3, COL_KEY_SHARES,
+		tests::{key_share, sealing_key},
+	};
+	use super::{
+		CURRENT_SCHEMA_VERSION, LEGACY_SCHEMA_VERSION, UpgradeOptions, open_database,
+		read_database_schema_version, read_schema_version, upgrade_database,
+	};
+
+	fn create_legacy_database(path: &std::path::Path) -> (ServerKeyId, Vec<u8>) {
+		let key = ServerKeyId::from_low_u64_be(1);
+		let value: SerializableKeyShareV3 = key_share().into();
+		let value = serde_json::to_vec(&value).unwrap();
+
+		let config = kvdb_rocksdb::DatabaseConfig::with_columns(1);
+		let db = kvdb_rocksdb::Database::open(&config, path.to_str().unwrap()).unwrap();
+		let mut transaction = db.transaction();
+		transaction.put(COL_KEY_SHARES, key.as_bytes(), &value);
+		db.write(transaction).unwrap();
+
+		(key, value)
+	}
+
+	#[test]
+	fn new_database_is_created_with_current_schema_version() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open_database(tempdir.path().to_str().unwrap(), false).unwrap();
+		let report = upgrade_database(&db, &sealing_key(), &Default::default()).unwrap();
+		assert!(!report.is_upgrade_required());
+		assert!(report.steps.is_empty());
+		assert_eq!(read_schema_version(&db), Ok(CURRENT_SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn legacy_database_is_upgraded() {
+		let tempdir = TempDir::new("").unwrap();
+		let (key, _) = create_legacy_database(tempdir.path());
+
+		let db = open_database(tempdir.path().to_str().unwrap(), false).unwrap();
+		assert_eq!(read_schema_version(&db), Ok(LEGACY_SCHEMA_VERSION));
+		let report = upgrade_database(&db, &sealing_key(), &Default::default()).unwrap();
+		assert_eq!(report.from_version, LEGACY_SCHEMA_VERSION);
+		assert_eq!(report.to_version, CURRENT_SCHEMA_VERSION);
+		assert_eq!(report.steps.len(), 1);
+		assert_eq!(report.steps[0].updated_records, 1);
+		assert_eq!(read_schema_version(&db), Ok(CURRENT_SCHEMA_VERSION));
+		drop(db);
+
+		let key_storage = PersistentKeyStorage::new(tempdir.path(), sealing_key()).unwrap();
+		assert!(key_storage.get(&key).unwrap().is_some());
+	}
+
+	#[test]
+	fn dry_run_does_not_change_database() {
+		let tempdir = TempDir::new("").unwrap();
+		let (key, value) = create_legacy_database(tempdir.path());
+
+		let db = open_database(tempdir.path().to_str().unwrap(), true).unwrap();
+		let report = upgrade_database(&db, &sealing_key(), &UpgradeOptions {
+			dry_run: true,
+			backup_path: None,
+		}).unwrap();
+		assert!(report.is_upgrade_required());
+		assert_eq!(report.steps[0].updated_records, 1);
+		assert_eq!(db.num_columns(), 1);
+		assert_eq!(read_database_schema_version(&db), Ok(LEGACY_SCHEMA_VERSION));
+		assert_eq!(db.get(COL_KEY_SHARES, key.as_bytes()).unwrap(), Some(value));
+	}
+
+	#[test]
+	fn dry_run_does_not_create_database() {
+		let tempdir = TempDir::new("").unwrap();
+		let db_path = tempdir.path().join("db");
+		assert!(open_database(db_path.to_str().unwrap(), true).is_err());
+		assert!(!db_path.exists());
+	}
+
+	#[test]
+	fn database_is_backed_up_before_upgrade() {
+		let tempdir = TempDir::new("").unwrap();
+		let backup_tempdir = TempDir::new("").unwrap();
+		let (key, value) = create_legacy_database(tempdir.path());
+
+		let db = open_database(tempdir.path().to_str().unwrap(), false).unwrap();
+		upgrade_database(&db, &sealing_key(), &UpgradeOptions {
+			dry_run: false,
+			backup_path: Some(backup_tempdir.path().into()),
+		}).unwrap();
+		assert_ne!(db.get(COL_KEY_SHARES, key.as_bytes()).unwrap(), Some(value.clone()));
+
+		let backup_db = open_database(backup_tempdir.path().to_str().unwrap(), false).unwrap();
+		assert_eq!(read_schema_version(&backup_db), Ok(LEGACY_SCHEMA_VERSION));
+		assert_eq!(backup_db.get(COL_KEY_SHARES, key.as_bytes()).unwrap(), Some(value));
+	}
+
+	#[test]
+	fn database_of_newer_version_is_rejected() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = open_database(tempdir.path().to_str().unwrap(), false).unwrap();
+		let mut transaction = db.transaction();
+		transaction.put(super::COL_METADATA, super::SCHEMA_VERSION_KEY, &(CURRENT_SCHEMA_VERSION + 1).to_le_bytes());
+		db.write(transaction).unwrap();
+
+		assert!(upgrade_database(&db, &sealing_key(), &Default::default()).is_err());
+		drop(db);
+		assert!(PersistentKeyStorage::new(tempdir.path(), sealing_key()).is_err());
+	}
+}
@@ -489,6 +489,7 @@ fn start_key_server(
 		.with_config(ClusterConfiguration {
 			admin_address,
 			auto_migrate_enabled,
+			..Default::default()
 		})
 		.build_for_tcp(
 			executor,
@@ -18,11 +18,11 @@ use std::collections::BTreeSet;
 use std::sync::Arc;
 use futures03::{
 	compat::Future01CompatExt,
-	future::{ready, FutureExt},
+	future::FutureExt,
 };
 use parking_lot::Mutex;
 use parity_crypto::publickey::public_to_address;
-use primitives::acl_storage::AclStorage;
+use primitives::acl_storage::{AsyncAclStorage, KeyAccessLevel};
 use primitives::key_storage::KeyStorage;
 use crate::key_server_cluster::math;
 use crate::types::{Error, Public, Requester, ServerKeyId};
@@ -36,7 +36,7 @@ pub struct KeyServerImpl {
 /// Secret store key server data.
 pub struct KeyServerCore {
 	cluster: Arc<dyn ClusterClient>,
-	acl_storage: Arc<dyn AclStorage>,
+	acl_storage: Arc<dyn AsyncAclStorage>,
 	key_storage: Arc<dyn KeyStorage>,
 }
 
@@ -44,7 +44,7 @@ impl KeyServerImpl {
 	/// Create new key server instance
 	pub fn new(
 		cluster: Arc<dyn ClusterClient>,
-		acl_storage: Arc<dyn AclStorage>,
+		acl_storage: Arc<dyn AsyncAclStorage>,
 		key_storage: Arc<dyn KeyStorage>,
 	) -> Result<Self, Error> {
 		Ok(KeyServerImpl {
@@ -61,7 +61,7 @@ impl KeyServerImpl {
 impl KeyServerCore {
 	pub fn new(
 		cluster: Arc<dyn ClusterClient>,
-		acl_storage: Arc<dyn AclStorage>,
+		acl_storage: Arc<dyn AsyncAclStorage>,
 		key_storage: Arc<dyn KeyStorage>,
 	) -> Result<Self, Error> {
 		Ok(KeyServerCore {
@@ -76,6 +76,15 @@ impl KeyServerCore {
 impl primitives::key_server::KeyServer for KeyServerImpl {
 }
 
+/// Check that requester has access to private data of the key before starting session.
+///
+/// Sessions are unable to wait for the ACL check, so the check is awaited here and
+/// its result is passed to the session.
+async fn check_key_access(acl_storage: &dyn AsyncAclStorage, key_id: ServerKeyId, requester: &Requester) -> Result<bool, Error> {
+	let requester_address = requester.address(&key_id)?;
+	acl_storage.check_access(requester_address, &key_id, KeyAccessLevel::Decrypt).await
+}
+
 impl primitives::key_server::ServerKeyGenerator for KeyServerImpl {
 	type GenerateKeyFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::ServerKeyGenerationResult> + Send>>;
 	type RestoreKeyFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::ServerKeyRetrievalResult> + Send>>;
@@ -119,6 +128,7 @@ impl primitives::key_server::ServerKeyGenerator for KeyServerImpl {
 		requester: Option<Requester>,
 	) -> Self::RestoreKeyFuture {
 		let key_server_core = self.data.clone();
+		let acl_storage = key_server_core.lock().acl_storage.clone();
 		async move {
 			let session_result = async move {
 				let requester_address = match requester {
@@ -134,18 +144,18 @@ impl primitives::key_server::ServerKeyGenerator for KeyServerImpl {
 					.into_wait_future()
 					.compat()
 					.await?;
-				session_core
-					.common_key_data()
-					.and_then(|key_share| {
-						let requester_is_author = requester_address
-							.map(|requester_address| requester_address == key_share.author)
-							.unwrap_or(true);
-						if requester_is_author {
-							Ok(key_share)
-						} else {
-							Err(Error::AccessDenied)
-						}
-					})
+				let key_share = session_core.common_key_data()?;
+
+				// key author and entities with read access are allowed to retrieve the key
+				if let Some(requester_address) = requester_address {
+					let is_allowed = requester_address == key_share.author
+						|| acl_storage.check_access(requester_address, &key_id, KeyAccessLevel::Read).await?;
+					if !is_allowed {
+						return Err(Error::AccessDenied);
+					}
+				}
+
+				Ok(key_share)
 			}.await;
 
 			primitives::key_server::SessionResult {
@@ -163,6 +173,39 @@ impl primitives::key_server::ServerKeyGenerator for KeyServerImpl {
 	}
 }
 
+impl primitives::key_server::ServerKeyDeleter for KeyServerImpl {
+	type DeleteKeyFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::ServerKeyDeletionResult> + Send>>;
+
+	fn delete_key(
+		&self,
+		origin: Option<primitives::key_server::Origin>,
+		key_id: ServerKeyId,
+		author: Requester,
+	) -> Self::DeleteKeyFuture {
+		let key_server_core = self.data.clone();
+		async move {
+			let session_result = async move {
+				let session = key_server_core
+					.lock()
+					.cluster
+					.new_key_deletion_session(key_id, origin, author)?;
+				session
+					.into_wait_future()
+					.compat()
+					.await
+			}.await;
+
+			primitives::key_server::SessionResult {
+				origin,
+				params: primitives::key_server::ServerKeyDeletionParams {
+					key_id,
+				},
+				result: session_result.map(|_| primitives::key_server::ServerKeyDeletionArtifacts)
+			}
+		}.boxed()
+	}
+}
+
 impl primitives::key_server::DocumentKeyServer for KeyServerImpl {
 	type StoreDocumentKeyFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::DocumentKeyStoreResult> + Send>>;
 	type GenerateDocumentKeyFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::DocumentKeyGenerationResult> + Send>>;
@@ -265,13 +308,15 @@ impl primitives::key_server::DocumentKeyServer for KeyServerImpl {
 		requester: Requester,
 	) -> Self::RestoreDocumentKeyFuture {
 		let key_server_core = self.data.clone();
+		let acl_storage = key_server_core.lock().acl_storage.clone();
 		async move {
+			let key_access = check_key_access(&*acl_storage, key_id, &requester).await;
 			let requester_copy = requester.clone();
 			let session_result = async move {
 				let session = key_server_core
 					.lock()
 					.cluster
-					.new_decryption_session(key_id, origin, requester, None, false, false)?;
+					.new_decryption_session(key_id, origin, requester, key_access, None, false, false)?;
 				session
 					.into_wait_future()
 					.compat()
@@ -299,32 +344,34 @@ impl primitives::key_server::DocumentKeyServer for KeyServerImpl {
 	) -> Self::RestoreDocumentKeyCommonFuture {
 		let acl_storage = self.data.lock().acl_storage.clone();
 		let key_storage = self.data.lock().key_storage.clone();
-		let prepare_result = || {
-			let requester_address = requester.address(&key_id)?;
-			let is_allowed = acl_storage.check(requester_address, &key_id)?;
-			if !is_allowed {
-				return Err(Error::AccessDenied);
-			}
+		async move {
+			let prepare_result = async {
+				let requester_address = requester.address(&key_id)?;
+				let is_allowed = acl_storage.check_access(requester_address, &key_id, KeyAccessLevel::Decrypt).await?;
+				if !is_allowed {
+					return Err(Error::AccessDenied);
+				}
+
+				let key_share = key_storage.get(&key_id)
+					.and_then(|key_share| key_share.ok_or(Error::ServerKeyIsNotFound))?;
+				let common_point = key_share.common_point.ok_or(Error::DocumentKeyIsNotFound)?;
+				let common_point = math::make_common_shadow_point(key_share.threshold, common_point)?;
+				Ok((key_share.threshold, common_point))
+			};
+			let session_result = prepare_result.await;
 
-			let key_share = key_storage.get(&key_id)
-				.and_then(|key_share| key_share.ok_or(Error::ServerKeyIsNotFound))?;
-			let common_point = key_share.common_point.ok_or(Error::DocumentKeyIsNotFound)?;
-			let common_point = math::make_common_shadow_point(key_share.threshold, common_point)?;
-			Ok((key_share.threshold, common_point))
-		};
-		let session_result = prepare_result();
-
-		ready(primitives::key_server::SessionResult {
-			origin,
-			params: primitives::key_server::DocumentKeyCommonRetrievalParams {
-				key_id,
-				requester,
-			},
-			result: session_result.map(|(threshold, common_point)| primitives::key_server::DocumentKeyCommonRetrievalArtifacts {
-				common_point,
-				threshold,
-			})
-		}).boxed()
+			primitives::key_server::SessionResult {
+				origin,
+				params: primitives::key_server::DocumentKeyCommonRetrievalParams {
+					key_id,
+					requester,
+				},
+				result: session_result.map(|(threshold, common_point)| primitives::key_server::DocumentKeyCommonRetrievalArtifacts {
+					common_point,
+					threshold,
+				})
+			}
+		}.boxed()
 	}
 
 	fn restore_document_key_shadow(
@@ -334,7 +381,9 @@ impl primitives::key_server::DocumentKeyServer for KeyServerImpl {
 		requester: Requester,
 	) -> Self::RestoreDocumentKeyShadowFuture {
 		let key_server_core = self.data.clone();
+		let acl_storage = key_server_core.lock().acl_storage.clone();
 		async move {
+			let key_access = check_key_access(&*acl_storage, key_id, &requester).await;
 			let requester_copy = requester.clone();
 			let session_result = async move {
 				// TODO: second true means that all key servers will have encrypted shadows
@@ -343,7 +392,7 @@ impl primitives::key_server::DocumentKeyServer for KeyServerImpl {
 				let session = key_server_core
 					.lock()
 					.cluster
-					.new_decryption_session(key_id, origin, requester, None, true, true)?;
+					.new_decryption_session(key_id, origin, requester, key_access, None, true, true)?;
 				let session_core = session.session.clone();
 				let document_key = session
 					.into_wait_future()
@@ -389,17 +438,25 @@ impl primitives::key_server::MessageSigner for KeyServerImpl {
 		debug_assert_eq!(origin, None, "Not supported");
 
 		let key_server_core = self.data.clone();
+		let acl_storage = key_server_core.lock().acl_storage.clone();
+		let key_storage = key_server_core.lock().key_storage.clone();
 		async move {
+			let key_access = check_key_access(&*acl_storage, key_id, &requester).await;
 			let requester_copy = requester.clone();
 			let session_result = async move {
+				// key threshold is reported along with signature
+				let threshold = key_storage.get(&key_id)
+					.and_then(|key_share| key_share.ok_or(Error::ServerKeyIsNotFound))?
+					.threshold;
 				let session = key_server_core
 					.lock()
 					.cluster
-					.new_schnorr_signing_session(key_id, requester, None, message)?;
+					.new_schnorr_signing_session(key_id, requester, key_access, None, message)?;
 				session
 					.into_wait_future()
 					.compat()
 					.await
+					.map(|signature| (threshold, signature))
 			}.await;
 
 			primitives::key_server::SessionResult {
@@ -408,9 +465,10 @@ impl primitives::key_server::MessageSigner for KeyServerImpl {
 					key_id,
 					requester: requester_copy,
 				},
-				result: session_result.map(|(signature_c, signature_s)| primitives::key_server::SchnorrSigningArtifacts {
+				result: session_result.map(|(threshold, (signature_c, signature_s))| primitives::key_server::SchnorrSigningArtifacts {
 					signature_c: *signature_c,
 					signature_s: *signature_s,
+					threshold,
 				})
 			}
 		}.boxed()
@@ -426,17 +484,25 @@ impl primitives::key_server::MessageSigner for KeyServerImpl {
 		debug_assert_eq!(origin, None, "Not supported");
 
 		let key_server_core = self.data.clone();
+		let acl_storage = key_server_core.lock().acl_storage.clone();
+		let key_storage = key_server_core.lock().key_storage.clone();
 		async move {
+			let key_access = check_key_access(&*acl_storage, key_id, &requester).await;
 			let requester_copy = requester.clone();
 			let session_result = async move {
+				// key threshold is reported along with signature
+				let threshold = key_storage.get(&key_id)
+					.and_then(|key_share| key_share.ok_or(Error::ServerKeyIsNotFound))?
+					.threshold;
 				let session = key_server_core
 					.lock()
 					.cluster
-					.new_ecdsa_signing_session(key_id, requester, None, message)?;
+					.new_ecdsa_signing_session(key_id, requester, key_access, None, message)?;
 				session
 					.into_wait_future()
 					.compat()
 					.await
+					.map(|signature| (threshold, signature))
 			}.await;
 
 			primitives::key_server::SessionResult {
@@ -445,8 +511,9 @@ impl primitives::key_server::MessageSigner for KeyServerImpl {
 					key_id,
 					requester: requester_copy,
 				},
-				result: session_result.map(|signature| primitives::key_server::EcdsaSigningArtifacts {
+				result: session_result.map(|(threshold, signature)| primitives::key_server::EcdsaSigningArtifacts {
 					signature,
+					threshold,
 				})
 			}
 		}.boxed()
@@ -491,15 +558,86 @@ impl primitives::key_server::AdminSessionsServer for KeyServerImpl {
 			}
 		}.boxed()
 	}
+
+	type RefreshKeySharesFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::SessionResult<(), ()>> + Send>>;
+
+	fn refresh_key_shares(
+		&self,
+		origin: Option<primitives::key_server::Origin>,
+		key_id: Option<ServerKeyId>,
+		servers_set_signature: primitives::Signature,
+	) -> Self::RefreshKeySharesFuture {
+		debug_assert_eq!(origin, None, "Not supported");
+
+		let key_server_core = self.data.clone();
+		async move {
+			let session_result = async move {
+				let session = key_server_core
+					.lock()
+					.cluster
+					.new_key_shares_refresh_session(
+						key_id,
+						servers_set_signature,
+					)?;
+				session
+					.into_wait_future()
+					.compat()
+					.await
+			}.await;
+
+			primitives::key_server::SessionResult {
+				origin,
+				params: (),
+				result: session_result,
+			}
+		}.boxed()
+	}
+
+	type PruneKeyVersionsFuture = std::pin::Pin<Box<dyn std::future::Future<Output = primitives::key_server::SessionResult<(), ()>> + Send>>;
+
+	fn prune_key_versions(
+		&self,
+		origin: Option<primitives::key_server::Origin>,
+		key_id: ServerKeyId,
+		admin_signature: primitives::Signature,
+	) -> Self::PruneKeyVersionsFuture {
+		debug_assert_eq!(origin, None, "Not supported");
+
+		let key_server_core = self.data.clone();
+		async move {
+			let session_result = async move {
+				let session = key_server_core
+					.lock()
+					.cluster
+					.new_key_versions_pruning_session(
+						key_id,
+						admin_signature,
+					)?;
+				session
+					.into_wait_future()
+					.compat()
+					.await
+			}.await;
+
+			primitives::key_server::SessionResult {
+				origin,
+				params: (),
+				result: session_result,
+			}
+		}.boxed()
+	}
 }
 
 #[cfg(test)]
 pub mod tests {
+	use std::sync::Arc;
 	use ethereum_types::H256;
 	use parity_crypto::publickey::{Random, Generator, verify_public};
+	use primitives::acl_storage::SyncAclStorageAdapter;
 	use primitives::key_storage::KeyStorage;
+	use primitives::requester::key_deletion_message_hash;
 	use crate::types::Requester;
-	use crate::traits::{ServerKeyGenerator, DocumentKeyServer, MessageSigner};
+	use crate::traits::{ServerKeyGenerator, ServerKeyDeleter, DocumentKeyServer, MessageSigner};
 	use super::KeyServerImpl;
 	use crate::key_server_cluster::{
 		math,
@@ -509,7 +647,7 @@ pub mod tests {
 	fn make_key_server(ml: &MessageLoop, index: usize) -> KeyServerImpl {
 		KeyServerImpl::new(
 			ml.cluster(index).client(),
-			ml.acl_storage(index).clone(),
+			Arc::new(SyncAclStorageAdapter::new(ml.acl_storage(index).clone())),
 			ml.key_storage(index).clone(),
 		).unwrap()
 	}
@@ -632,6 +770,69 @@ pub mod tests {
 		}
 	}
 
+	#[test]
+	fn server_key_generation_and_deletion_works_over_network_with_3_nodes() {
+		let _ = ::env_logger::try_init();
+		let ml = make_clusters(3);
+
+		// generate server key
+		let server_key_id = Random.generate().secret().clone();
+		let requestor_secret = Random.generate().secret().clone();
+		let signature: Requester = parity_crypto::publickey::sign(&requestor_secret, &server_key_id).unwrap().into();
+		ml.loop_until_future_completed(
+			make_key_server(&ml, 0).generate_key(
+				None,
+				*server_key_id,
+				signature.clone(),
+				1,
+			)
+		).result.unwrap();
+
+		// generation signature can't be used to delete server key
+		assert_eq!(
+			ml.loop_until_future_completed(
+				make_key_server(&ml, 1).delete_key(
+					None,
+					*server_key_id,
+					signature.clone(),
+				)
+			).result.unwrap_err(),
+			crate::types::Error::AccessDenied,
+		);
+
+		// delete server key
+		let deletion_signature: Requester = parity_crypto::publickey::sign(
+			&requestor_secret,
+			&key_deletion_message_hash(&server_key_id),
+		).unwrap().into();
+		ml.loop_until_future_completed(
+			make_key_server(&ml, 1).delete_key(
+				None,
+				*server_key_id,
+				deletion_signature,
+			)
+		).result.unwrap();
+
+		// key is deleted on all nodes
+		for i in 0..3 {
+			assert!(ml.key_storage(i).get(&server_key_id).unwrap().is_none());
+			assert!(ml.key_storage(i).is_deleted(&server_key_id).unwrap());
+		}
+
+		// and can't be generated again
+		assert_eq!(
+			ml.loop_until_future_completed(
+				make_key_server(&ml, 0).generate_key(
+					None,
+					*server_key_id,
+					signature.clone(),
+					1,
+				)
+			).result.unwrap_err(),
+			crate::types::Error::ServerKeyIsDeleted,
+		);
+	}
+
 	#[test]
 	fn server_key_generation_and_message_signing_works_over_network_with_3_nodes() {
 		let _ = ::env_logger::try_init();
@@ -910,6 +910,7 @@ mod tests {
 				hash: version_id,
 				id_numbers: vec![(nodes.keys().cloned().nth(0).unwrap(), math::generate_random_scalar().unwrap())].into_iter().collect(),
 				secret_share: math::generate_random_scalar().unwrap(),
+				public_shares: Default::default(),
 			}],
 		}).unwrap();
 		let ml = MessageLoop::new(nodes);
@@ -0,0 +1,778 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Formatter, Error as FmtError};
+use std::sync::Arc;
+use futures::Oneshot;
+use log::warn;
+use parking_lot::Mutex;
+use ethereum_types::H256;
+use parity_crypto::publickey::{Address, Signature, verify_address};
+use tiny_keccak::Keccak;
+use primitives::key_storage::KeyStorage;
+use crate::key_server_cluster::{Error, NodeId, SessionId};
+use crate::key_server_cluster::cluster::Cluster;
+use crate::key_server_cluster::cluster_sessions::{ClusterSession, CompletionSignal};
+use crate::key_server_cluster::message::{Message, KeyVersionsPruningMessage, InitializeKeyVersionsPruning,
+	HeldKeyVersions, PruneKeyVersions, ConfirmKeyVersionsPruning, KeyVersionsPruningError};
+
+/// Prefix of the message that administrator signs to prune versions of the key.
+const KEY_VERSIONS_PRUNING_MESSAGE_PREFIX: &[u8] = b"prune";
+
+/// Key versions pruning session.
+/// Removes versions of the key, that are no longer required, from key storages of all cluster nodes.
+/// Brief overview:
+/// 1) master node checks administrator signature && asks all other nodes for versions of the key they're holding
+/// 2) every node checks administrator signature && reports versions of the key it is holding
+/// 3) when all nodes have responded, master sends versions held by every node to all other nodes
+/// 4) every node computes versions to prune on its own. Version is pruned if it can't be restored by any
+///    threshold-sized group of nodes, or if it is older than `versions_to_keep` (configured on this node) most
+///    recent restorable versions. Nothing is pruned if the latest version of the key can't be restored
+/// 5) every node removes these versions from its key storage (never removing its own latest version) && confirms pruning
+/// 6) session is completed when all nodes have confirmed pruning
+pub struct SessionImpl {
+	/// Unique session id (same as key id).
+	id: SessionId,
+	/// Public identifier of this node.
+	self_node_id: NodeId,
+	/// Key storage.
+	key_storage: Arc<dyn KeyStorage>,
+	/// Cluster which allows this node to send messages to other nodes in the cluster.
+	cluster: Arc<dyn Cluster>,
+	/// Administrator address.
+	admin_address: Address,
+	/// Number of most recent restorable versions to keep.
+	versions_to_keep: usize,
+	/// Session nonce.
+	nonce: u64,
+	/// Session completion signal.
+	completed: CompletionSignal<()>,
+	/// Mutable session data.
+	data: Mutex<SessionData>,
+}
+
+/// SessionImpl creation parameters
+pub struct SessionParams {
+	/// SessionImpl identifier.
+	pub id: SessionId,
+	/// Id of node, on which this session is running.
+	pub self_node_id: NodeId,
+	/// Key storage.
+	pub key_storage: Arc<dyn KeyStorage>,
+	/// Cluster
+	pub cluster: Arc<dyn Cluster>,
+	/// Administrator address.
+	pub admin_address: Address,
+	/// Number of most recent restorable versions to keep.
+	pub versions_to_keep: usize,
+	/// Session nonce.
+	pub nonce: u64,
+}
+
+/// Mutable data of key versions pruning session.
+#[derive(Debug)]
+struct SessionData {
+	/// Current state of the session.
+	state: SessionState,
+	/// Master node id.
+	master: Option<NodeId>,
+	/// Versions of the key, reported to master node (on slave nodes only).
+	reported_versions: Option<Vec<H256>>,
+	/// Nodes-specific data (on master node only).
+	nodes: BTreeMap<NodeId, NodeData>,
+	/// Key versions pruning session result.
+	result: Option<Result<(), Error>>,
+}
+
+/// Mutable node-specific data.
+#[derive(Debug, Clone, Default)]
+struct NodeData {
+	/// Versions of the key, held by the node (in the order they have been created).
+	pub versions: Option<Vec<H256>>,
+	/// Flag marking that node has confirmed versions pruning.
+	pub pruning_confirmed: bool,
+}
+
+/// Key versions pruning session state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionState {
+	/// Every node starts in this state.
+	WaitingForInitialization,
+	/// Master node waits for every other node to report versions of the key.
+	WaitingForKeyVersions,
+	/// Slave node waits for master node to send versions to prune.
+	WaitingForVersionsToPrune,
+	/// Master node waits for every other node to confirm versions pruning.
+	WaitingForPruningConfirmations,
+
+	/// Versions are pruned.
+	Finished,
+	/// Failed to prune versions.
+	Failed,
+}
+
+impl SessionImpl {
+	/// Create new key versions pruning session.
+	pub fn new(params: SessionParams) -> Result<(Self, Oneshot<Result<(), Error>>), Error> {
+		let (completed, oneshot) = CompletionSignal::new();
+		Ok((SessionImpl {
+			id: params.id,
+			self_node_id: params.self_node_id,
+			key_storage: params.key_storage,
+			cluster: params.cluster,
+			admin_address: params.admin_address,
+			versions_to_keep: params.versions_to_keep,
+			nonce: params.nonce,
+			completed,
+			data: Mutex::new(SessionData {
+				state: SessionState::WaitingForInitialization,
+				master: None,
+				reported_versions: None,
+				nodes: BTreeMap::new(),
+				result: None,
+			}),
+		}, oneshot))
+	}
+
+	/// Get this node Id.
+	pub fn node(&self) -> &NodeId {
+		&self.self_node_id
+	}
+
+	/// Start new session initialization. This must be called on master node.
+	/// The signature is the `key_versions_pruning_hash` of the key id, signed by administrator.
+	pub fn initialize(&self, admin_signature: Signature) -> Result<(), Error> {
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForInitialization {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// only administrator could prune key versions
+		self.check_admin_signature(&admin_signature)?;
+
+		// master node must have the key to know its threshold && order of versions
+		let key_share = self.key_storage.get(&self.id)?.ok_or(Error::ServerKeyIsNotFound)?;
+
+		// update state
+		data.state = SessionState::WaitingForKeyVersions;
+		data.master = Some(self.node().clone());
+		data.nodes.extend(self.cluster.nodes().into_iter().map(|n| (n, NodeData::default())));
+		data.nodes.entry(self.node().clone()).or_insert_with(Default::default).versions =
+			Some(key_share.versions.iter().map(|v| v.hash.clone()).collect());
+
+		// start initialization
+		if data.nodes.len() > 1 {
+			self.cluster.broadcast(Message::KeyVersionsPruning(KeyVersionsPruningMessage::InitializeKeyVersionsPruning(InitializeKeyVersionsPruning {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				admin_signature: admin_signature.into(),
+			})))
+		} else {
+			self.start_pruning(&mut *data)
+		}
+	}
+
+	/// When session initialization message is received.
+	pub fn on_initialize_session(&self, sender: NodeId, message: &InitializeKeyVersionsPruning) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForInitialization {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// only administrator could prune key versions
+		self.check_admin_signature(&message.admin_signature)?;
+
+		// update state
+		let versions: Vec<H256> = self.key_storage.get(&self.id)?
+			.map(|key_share| key_share.versions.into_iter().map(|v| v.hash).collect())
+			.unwrap_or_default();
+		data.state = SessionState::WaitingForVersionsToPrune;
+		data.master = Some(sender.clone());
+		data.reported_versions = Some(versions.clone());
+
+		// send versions we're holding back to master node
+		self.cluster.send(&sender, Message::KeyVersionsPruning(KeyVersionsPruningMessage::HeldKeyVersions(HeldKeyVersions {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+			versions: versions.into_iter().map(Into::into).collect(),
+		})))
+	}
+
+	/// When versions of the key are received from other node.
+	pub fn on_held_key_versions(&self, sender: NodeId, message: &HeldKeyVersions) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForKeyVersions {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// remember node versions
+		{
+			let node_data = data.nodes.get_mut(&sender).ok_or(Error::InvalidMessage)?;
+			if node_data.versions.is_some() {
+				return Err(Error::InvalidMessage);
+			}
+			node_data.versions = Some(message.versions.iter().cloned().map(Into::into).collect());
+		}
+
+		// wait for other nodes
+		if !data.nodes.values().all(|n| n.versions.is_some()) {
+			return Ok(());
+		}
+
+		self.start_pruning(&mut *data)
+	}
+
+	/// When versions held by all nodes are received from master node.
+	pub fn on_prune_key_versions(&self, sender: NodeId, message: &PruneKeyVersions) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForVersionsToPrune {
+			return Err(Error::InvalidStateForRequest);
+		}
+		if data.master.as_ref() != Some(&sender) {
+			return Err(Error::InvalidMessage);
+		}
+
+		// master must pass exactly the same versions that we have reported
+		let held_versions: BTreeMap<NodeId, Vec<H256>> = message.held_versions.iter()
+			.map(|(node, versions)| (node.clone().into(), versions.iter().cloned().map(Into::into).collect()))
+			.collect();
+		if held_versions.get(self.node()) != data.reported_versions.as_ref() {
+			return Err(Error::InvalidMessage);
+		}
+
+		// compute versions to prune on our own && prune them && confirm pruning
+		if let Some(key_share) = self.key_storage.get(&self.id)? {
+			let master_versions = held_versions.get(&sender).ok_or(Error::InvalidMessage)?;
+			let versions_to_prune = compute_versions_to_prune(key_share.threshold, self.versions_to_keep,
+				master_versions, &compute_holders(&held_versions))?;
+			self.prune(&versions_to_prune)?;
+		}
+		self.cluster.send(&sender, Message::KeyVersionsPruning(KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(ConfirmKeyVersionsPruning {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+		})))?;
+
+		self.complete(&mut *data);
+		Ok(())
+	}
+
+	/// When versions pruning confirmation is received.
+	pub fn on_confirm_pruning(&self, sender: NodeId, message: &ConfirmKeyVersionsPruning) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForPruningConfirmations {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// check if all nodes have confirmed pruning
+		data.nodes.get_mut(&sender).ok_or(Error::InvalidMessage)?
+			.pruning_confirmed = true;
+		if !data.nodes.values().all(|n| n.pruning_confirmed) {
+			return Ok(());
+		}
+
+		self.complete(&mut *data);
+		Ok(())
+	}
+
+	/// Compute versions to prune, prune them on this node && ask other nodes to do the same.
+	fn start_pruning(&self, data: &mut SessionData) -> Result<(), Error> {
+		let key_share = self.key_storage.get(&self.id)?.ok_or(Error::ServerKeyIsNotFound)?;
+		let held_versions: BTreeMap<NodeId, Vec<H256>> = data.nodes.iter()
+			.map(|(node, node_data)| (node.clone(), node_data.versions.clone()
+				.expect("pruning is started when versions are received from all nodes; qed")))
+			.collect();
+
+		let master_versions: Vec<_> = key_share.versions.iter().map(|v| v.hash.clone()).collect();
+		let versions_to_prune = compute_versions_to_prune(key_share.threshold, self.versions_to_keep,
+			&master_versions, &compute_holders(&held_versions))?;
+		self.prune(&versions_to_prune)?;
+
+		data.state = SessionState::WaitingForPruningConfirmations;
+		data.nodes.get_mut(self.node()).expect("master node is always inserted in initialize; qed")
+			.pruning_confirmed = true;
+		if data.nodes.len() == 1 {
+			self.complete(data);
+			return Ok(());
+		}
+
+		self.cluster.broadcast(Message::KeyVersionsPruning(KeyVersionsPruningMessage::PruneKeyVersions(PruneKeyVersions {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+			held_versions: held_versions.into_iter()
+				.map(|(node, versions)| (node.into(), versions.into_iter().map(Into::into).collect()))
+				.collect(),
+		})))
+	}
+
+	/// Check that the session has been requested by administrator.
+	fn check_admin_signature(&self, admin_signature: &Signature) -> Result<(), Error> {
+		match verify_address(&self.admin_address, admin_signature, &key_versions_pruning_hash(&self.id))? {
+			true => Ok(()),
+			false => Err(Error::AccessDenied),
+		}
+	}
+
+	/// Remove given versions from the key storage. The latest version is never removed.
+	fn prune(&self, versions_to_prune: &BTreeSet<H256>) -> Result<(), Error> {
+		let mut key_share = match self.key_storage.get(&self.id)? {
+			Some(key_share) => key_share,
+			None => return Ok(()),
+		};
+
+		let latest_version = key_share.last_version()?.hash.clone();
+		let versions_count = key_share.versions.len();
+		key_share.versions.retain(|v| v.hash == latest_version || !versions_to_prune.contains(&v.hash));
+		if key_share.versions.len() != versions_count {
+			self.key_storage.update(self.id.clone(), key_share)?;
+		}
+
+		Ok(())
+	}
+
+	/// Complete session successfully.
+	fn complete(&self, data: &mut SessionData) {
+		data.state = SessionState::Finished;
+		data.result = Some(Ok(()));
+		self.completed.send(Ok(()));
+	}
+
+	/// Mark session as failed.
+	fn fail(&self, data: &mut SessionData, error: Error) {
+		// versions are already pruned => nothing to do
+		if data.state == SessionState::Finished || data.state == SessionState::Failed {
+			return;
+		}
+
+		// master node is responsible for notifying other nodes
+		if data.master.as_ref() == Some(self.node()) && data.nodes.len() > 1 {
+			// do not bother processing send error, as we already processing error
+			let _ = self.cluster.broadcast(Message::KeyVersionsPruning(KeyVersionsPruningMessage::KeyVersionsPruningError(KeyVersionsPruningError {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				error: error.clone().into(),
+			})));
+		}
+
+		data.state = SessionState::Failed;
+		data.result = Some(Err(error.clone()));
+		self.completed.send(Err(error));
+	}
+}
+
+impl ClusterSession for SessionImpl {
+	type Id = SessionId;
+	type CreationData = ();
+	type SuccessfulResult = ();
+
+	fn type_name() -> &'static str {
+		"key versions pruning"
+	}
+
+	fn id(&self) -> SessionId {
+		self.id.clone()
+	}
+
+	fn is_finished(&self) -> bool {
+		let data = self.data.lock();
+		data.state == SessionState::Failed
+			|| data.state == SessionState::Finished
+	}
+
+	fn on_node_timeout(&self, node: &NodeId) {
+		let mut data = self.data.lock();
+
+		warn!("{}: key versions pruning session failed because {} connection has timeouted", self.node(), node);
+
+		self.fail(&mut *data, Error::NodeDisconnected);
+	}
+
+	fn on_session_timeout(&self) {
+		let mut data = self.data.lock();
+
+		warn!("{}: key versions pruning session failed with timeout", self.node());
+
+		self.fail(&mut *data, Error::NodeDisconnected);
+	}
+
+	fn on_session_error(&self, node: &NodeId, error: Error) {
+		let mut data = self.data.lock();
+
+		// error in key versions pruning session is considered fatal
+		// => broadcast error if error occured on this node (master node broadcasts error in fail)
+		if *node == self.self_node_id && data.master.as_ref() != Some(self.node()) {
+			// do not bother processing send error, as we already processing error
+			let _ = self.cluster.broadcast(Message::KeyVersionsPruning(KeyVersionsPruningMessage::KeyVersionsPruningError(KeyVersionsPruningError {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				error: error.clone().into(),
+			})));
+		}
+
+		warn!("{}: key versions pruning session failed with error: {} from {}", self.node(), error, node);
+
+		self.fail(&mut *data, error);
+	}
+
+	fn on_message(&self, sender: &NodeId, message: &Message) -> Result<(), Error> {
+		if Some(self.nonce) != message.session_nonce() {
+			return Err(Error::ReplayProtection);
+		}
+
+		match message {
+			&Message::KeyVersionsPruning(ref message) => match message {
+				&KeyVersionsPruningMessage::InitializeKeyVersionsPruning(ref message) =>
+					self.on_initialize_session(sender.clone(), message),
+				&KeyVersionsPruningMessage::HeldKeyVersions(ref message) =>
+					self.on_held_key_versions(sender.clone(), message),
+				&KeyVersionsPruningMessage::PruneKeyVersions(ref message) =>
+					self.on_prune_key_versions(sender.clone(), message),
+				&KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(ref message) =>
+					self.on_confirm_pruning(sender.clone(), message),
+				&KeyVersionsPruningMessage::KeyVersionsPruningError(ref message) => {
+					self.on_session_error(sender, message.error.clone());
+					Ok(())
+				},
+			},
+			_ => unreachable!("cluster checks message to be correct before passing; qed"),
+		}
+	}
+}
+
+impl Debug for SessionImpl {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+		write!(f, "Key versions pruning session {} on {}", self.id, self.self_node_id)
+	}
+}
+
+/// Hash of the message that administrator signs to prune versions of the key.
+pub fn key_versions_pruning_hash(key_id: &SessionId) -> H256 {
+	let mut message_keccak = Keccak::new_keccak256();
+	message_keccak.update(KEY_VERSIONS_PRUNING_MESSAGE_PREFIX);
+	message_keccak.update(key_id.as_bytes());
+
+	let mut message_keccak_value = [0u8; 32];
+	message_keccak.finalize(&mut message_keccak_value);
+
+	message_keccak_value.into()
+}
+
+/// Compute nodes holding every version of the key.
+fn compute_holders(held_versions: &BTreeMap<NodeId, Vec<H256>>) -> BTreeMap<H256, BTreeSet<NodeId>> {
+	let mut holders: BTreeMap<H256, BTreeSet<NodeId>> = BTreeMap::new();
+	for (node, versions) in held_versions {
+		for version in versions {
+			holders.entry(version.clone()).or_insert_with(Default::default).insert(node.clone());
+		}
+	}
+	holders
+}
+
+/// Compute versions of the key that could be pruned.
+/// `ordered_versions` are versions of the key, known to the master node, in the order they have been created.
+/// `holders` are all nodes holding every version of the key.
+pub fn compute_versions_to_prune(
+	threshold: usize,
+	versions_to_keep: usize,
+	ordered_versions: &[H256],
+	holders: &BTreeMap<H256, BTreeSet<NodeId>>,
+) -> Result<BTreeSet<H256>, Error> {
+	// version could be restored if there are at least threshold + 1 nodes holding it
+	let is_restorable = |version: &H256| holders.get(version).map(|h| h.len() > threshold).unwrap_or(false);
+
+	// never prune anything if the latest version can't be restored
+	let latest_version = ordered_versions.last().ok_or(Error::ServerKeyIsNotFound)?;
+	if !is_restorable(latest_version) {
+		return Err(Error::NotEnoughNodesForThreshold);
+	}
+
+	// keep the latest version && a few previous restorable versions
+	let versions_to_keep: BTreeSet<_> = ::std::iter::once(latest_version)
+		.chain(ordered_versions.iter().rev().skip(1).filter(|v| is_restorable(*v)))
+		.take(::std::cmp::max(versions_to_keep, 1))
+		.cloned()
+		.collect();
+
+	// versions that are unknown to master could be newer than its latest version => only prune them if
+	// they can't be restored
+	Ok(holders.keys()
+		.filter(|v| !versions_to_keep.contains(*v))
+		.filter(|v| ordered_versions.contains(*v) || !is_restorable(*v))
+		.cloned()
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::collections::{BTreeMap, BTreeSet};
+	use ethereum_types::H256;
+	use parity_crypto::publickey::{Random, Generator, sign};
+	use primitives::key_storage::KeyStorage;
+	use crate::key_server_cluster::{Error, NodeId, SessionId, ServerKeyId};
+	use crate::key_server_cluster::cluster::tests::MessageLoop as ClusterMessageLoop;
+	use crate::key_server_cluster::cluster_sessions::AdminSession;
+	use crate::key_server_cluster::message::{Message, KeyVersionsPruningMessage};
+	use crate::key_server_cluster::generation_session::tests::MessageLoop as GenerationMessageLoop;
+	use super::{SessionImpl, SessionState, compute_versions_to_prune, key_versions_pruning_hash};
+
+	const DUMMY_SESSION_ID: [u8; 32] = [1u8; 32];
+
+	struct MessageLoop(ClusterMessageLoop);
+
+	impl MessageLoop {
+		pub fn new(num_nodes: usize, threshold: usize) -> Self {
+			let ml = GenerationMessageLoop::new(num_nodes).init(threshold).unwrap();
+			ml.0.loop_until(|| ml.0.is_empty()); // complete generation session
+			MessageLoop(ml.0)
+		}
+
+		pub fn init(self, versions_to_keep: usize) -> Result<Self, Error> {
+			for idx in 0..self.0.nodes().len() {
+				self.set_versions_to_keep_at(idx, versions_to_keep);
+			}
+			self.init_with_configured_versions()
+		}
+
+		pub fn init_with_configured_versions(self) -> Result<Self, Error> {
+			let admin_signature = sign(self.0.admin_key_pair().secret(),
+				&key_versions_pruning_hash(&SessionId::from(DUMMY_SESSION_ID))).unwrap();
+			self.0.cluster(0).client().new_key_versions_pruning_session(
+				ServerKeyId::from(DUMMY_SESSION_ID),
+				admin_signature,
+			).map(|_| self)
+		}
+
+		pub fn set_versions_to_keep_at(&self, idx: usize, versions_to_keep: usize) {
+			self.0.sessions(idx).set_key_versions_to_keep(versions_to_keep);
+		}
+
+		pub fn session_at(&self, idx: usize) -> Arc<AdminSession> {
+			self.0.sessions(idx).admin_sessions.first().unwrap()
+		}
+
+		pub fn versions_at(&self, idx: usize) -> Vec<H256> {
+			self.0.key_storage(idx).get(&SessionId::from(DUMMY_SESSION_ID)).unwrap().unwrap()
+				.versions.into_iter().map(|v| v.hash).collect()
+		}
+
+		/// Insert fake version of the key at given position on given node.
+		pub fn insert_version_at(&self, idx: usize, position: usize, version: H256) {
+			let key_storage = self.0.key_storage(idx);
+			let mut key_share = key_storage.get(&SessionId::from(DUMMY_SESSION_ID)).unwrap().unwrap();
+			let mut key_version = key_share.last_version().unwrap().clone();
+			key_version.hash = version;
+			key_share.versions.insert(position, key_version);
+			key_storage.update(SessionId::from(DUMMY_SESSION_ID), key_share).unwrap();
+		}
+
+		pub fn loop_until_empty(&self) {
+			self.0.loop_until(|| self.0.is_empty());
+		}
+	}
+
+	fn session_state(session: &AdminSession) -> SessionState {
+		match *session {
+			AdminSession::KeyVersionsPruning(ref session) => session.data.lock().state.clone(),
+			_ => unreachable!("only key versions pruning sessions are started in these tests; qed"),
+		}
+	}
+
+	fn session_result(session: &AdminSession) -> Option<Result<(), Error>> {
+		match *session {
+			AdminSession::KeyVersionsPruning(ref session) => session.data.lock().result.clone(),
+			_ => unreachable!("only key versions pruning sessions are started in these tests; qed"),
+		}
+	}
+
+	fn holders(nodes: &[(H256, &[NodeId])]) -> BTreeMap<H256, BTreeSet<NodeId>> {
+		nodes.iter().map(|(v, n)| (v.clone(), n.iter().cloned().collect())).collect()
+	}
+
+	#[test]
+	fn versions_to_prune_are_computed_correctly() {
+		let (v1, v2, v3, v4) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2), H256::from_low_u64_be(3), H256::from_low_u64_be(4));
+		let (n1, n2, n3) = (NodeId::from_low_u64_be(1), NodeId::from_low_u64_be(2), NodeId::from_low_u64_be(3));
+		let holders = holders(&[(v1, &[n1, n2]), (v2, &[n1]), (v3, &[n1, n2, n3]), (v4, &[n3])]);
+
+		// unrestorable v2 is always pruned, unknown v4 is pruned because it is unrestorable
+		assert_eq!(compute_versions_to_prune(1, 2, &[v1, v2, v3], &holders),
+			Ok(vec![v2, v4].into_iter().collect()));
+		// restorable v1 is pruned when only the latest version is kept
+		assert_eq!(compute_versions_to_prune(1, 1, &[v1, v2, v3], &holders),
+			Ok(vec![v1, v2, v4].into_iter().collect()));
+		// the latest version is kept even if zero versions are required
+		assert_eq!(compute_versions_to_prune(1, 0, &[v1, v2, v3], &holders),
+			Ok(vec![v1, v2, v4].into_iter().collect()));
+		// unknown restorable version is never pruned
+		assert_eq!(compute_versions_to_prune(0, 1, &[v1, v2, v3], &holders),
+			Ok(vec![v1, v2].into_iter().collect()));
+		// nothing is pruned if the latest version can't be restored
+		assert_eq!(compute_versions_to_prune(3, 1, &[v1, v2, v3], &holders),
+			Err(Error::NotEnoughNodesForThreshold));
+	}
+
+	#[test]
+	fn key_versions_pruning_session_removes_obsolete_versions_on_all_nodes() {
+		let ml = MessageLoop::new(3, 1);
+		let latest_version = ml.versions_at(0)[0];
+		let (restorable_version, unrestorable_version) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+		ml.insert_version_at(0, 0, restorable_version);
+		ml.insert_version_at(1, 0, restorable_version);
+		ml.insert_version_at(0, 1, unrestorable_version);
+
+		// when two versions are kept, only unrestorable version is pruned
+		let ml = ml.init(2).unwrap();
+		ml.loop_until_empty();
+		assert_eq!(session_result(&ml.session_at(0)), Some(Ok(())));
+		for idx in 0..3 {
+			assert_eq!(session_state(&ml.session_at(idx)), SessionState::Finished);
+		}
+		assert_eq!(ml.versions_at(0), vec![restorable_version, latest_version]);
+		assert_eq!(ml.versions_at(1), vec![restorable_version, latest_version]);
+		assert_eq!(ml.versions_at(2), vec![latest_version]);
+	}
+
+	#[test]
+	fn key_versions_pruning_session_keeps_configured_number_of_versions() {
+		let ml = MessageLoop::new(3, 1);
+		let latest_version = ml.versions_at(0)[0];
+		let restorable_version = H256::from_low_u64_be(1);
+		ml.insert_version_at(0, 0, restorable_version);
+		ml.insert_version_at(1, 0, restorable_version);
+
+		// when only the latest version is kept, restorable version is pruned too
+		let ml = ml.init(1).unwrap();
+		ml.loop_until_empty();
+		assert_eq!(session_result(&ml.session_at(0)), Some(Ok(())));
+		for idx in 0..3 {
+			assert_eq!(ml.versions_at(idx), vec![latest_version]);
+		}
+	}
+
+	#[test]
+	fn key_versions_pruning_session_never_removes_latest_version_of_node() {
+		let ml = MessageLoop::new(3, 1);
+		let latest_version = ml.versions_at(0)[0];
+		let stale_version = H256::from_low_u64_be(1);
+		ml.insert_version_at(0, 0, stale_version);
+		ml.insert_version_at(1, 0, stale_version);
+		ml.insert_version_at(2, 1, stale_version);
+
+		// node2 has missed the latest version => stale version remains its latest version
+		let ml = ml.init(1).unwrap();
+		ml.loop_until_empty();
+		assert_eq!(session_result(&ml.session_at(0)), Some(Ok(())));
+		assert_eq!(ml.versions_at(0), vec![latest_version]);
+		assert_eq!(ml.versions_at(1), vec![latest_version]);
+		assert_eq!(ml.versions_at(2), vec![latest_version, stale_version]);
+	}
+
+	#[test]
+	fn key_versions_pruning_session_fails_if_latest_version_is_not_restorable() {
+		let ml = MessageLoop::new(3, 1);
+		let unrestorable_version = H256::from_low_u64_be(1);
+		ml.insert_version_at(0, 1, unrestorable_version);
+		let versions_before: Vec<_> = (0..3).map(|idx| ml.versions_at(idx)).collect();
+
+		// latest version of master is only held by master itself => nothing is pruned
+		let ml = ml.init(1).unwrap();
+		ml.loop_until_empty();
+		assert_eq!(session_result(&ml.session_at(0)), Some(Err(Error::NotEnoughNodesForThreshold)));
+		for idx in 0..3 {
+			assert_eq!(session_state(&ml.session_at(idx)), SessionState::Failed);
+			assert_eq!(ml.versions_at(idx), versions_before[idx]);
+		}
+	}
+
+	#[test]
+	fn key_versions_pruning_session_is_not_started_without_admin_signature() {
+		let ml = MessageLoop::new(3, 1);
+		let not_admin_signature = sign(Random.generate().secret(),
+			&key_versions_pruning_hash(&SessionId::from(DUMMY_SESSION_ID))).unwrap();
+		assert_eq!(ml.0.cluster(0).client().new_key_versions_pruning_session(
+			ServerKeyId::from(DUMMY_SESSION_ID),
+			not_admin_signature,
+		).map(drop), Err(Error::AccessDenied));
+	}
+
+	#[test]
+	fn key_versions_pruning_session_fails_on_slave_if_admin_signature_is_wrong() {
+		let ml = MessageLoop::new(3, 1);
+		let latest_version = ml.versions_at(0)[0];
+		let restorable_version = H256::from_low_u64_be(1);
+		ml.insert_version_at(0, 0, restorable_version);
+		ml.insert_version_at(1, 0, restorable_version);
+		let ml = ml.init(1).unwrap();
+
+		// node1 receives initialization request with signature of other key
+		let (from, to, mut message) = ml.0.take_message().unwrap();
+		match message {
+			Message::KeyVersionsPruning(KeyVersionsPruningMessage::InitializeKeyVersionsPruning(ref mut message)) =>
+				message.admin_signature = sign(ml.0.admin_key_pair().secret(),
+					&key_versions_pruning_hash(&SessionId::from([2u8; 32]))).unwrap().into(),
+			_ => unreachable!("initialization message is the first message of the session; qed"),
+		}
+		ml.0.process_message(from, to, message);
+		ml.loop_until_empty();
+
+		assert_eq!(session_result(&ml.session_at(0)), Some(Err(Error::AccessDenied)));
+		assert_eq!(ml.versions_at(0), vec![restorable_version, latest_version]);
+		assert_eq!(ml.versions_at(1), vec![restorable_version, latest_version]);
+	}
+
+	#[test]
+	fn key_versions_pruning_session_uses_versions_to_keep_configured_on_every_node() {
+		let ml = MessageLoop::new(3, 1);
+		let latest_version = ml.versions_at(0)[0];
+		let restorable_version = H256::from_low_u64_be(1);
+		ml.insert_version_at(0, 0, restorable_version);
+		ml.insert_version_at(1, 0, restorable_version);
+
+		// master is configured to keep the latest version only, but node1 keeps two versions
+		ml.set_versions_to_keep_at(0, 1);
+		ml.set_versions_to_keep_at(1, 2);
+		ml.set_versions_to_keep_at(2, 1);
+		let ml = ml.init_with_configured_versions().unwrap();
+		ml.loop_until_empty();
+		assert_eq!(session_result(&ml.session_at(0)), Some(Ok(())));
+		assert_eq!(ml.versions_at(0), vec![latest_version]);
+		assert_eq!(ml.versions_at(1), vec![restorable_version, latest_version]);
+		assert_eq!(ml.versions_at(2), vec![latest_version]);
+	}
+}
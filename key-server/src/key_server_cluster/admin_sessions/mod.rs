@@ -15,6 +15,7 @@
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
 pub mod key_version_negotiation_session;
+pub mod key_versions_pruning_session;
 pub mod servers_set_change_session;
 pub mod share_add_session;
 pub mod share_change_session;
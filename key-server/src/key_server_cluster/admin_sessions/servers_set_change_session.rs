@@ -23,7 +23,7 @@ use parking_lot::Mutex;
 use ethereum_types::H256;
 use parity_crypto::publickey::{Address, Signature};
 use primitives::key_storage::KeyStorage;
-use crate::key_server_cluster::{Error, NodeId, SessionId};
+use crate::key_server_cluster::{Error, NodeId, SessionId, ServerKeyId};
 use crate::key_server_cluster::math;
 use crate::key_server_cluster::cluster::Cluster;
 use crate::key_server_cluster::cluster_sessions::{ClusterSession, CompletionSignal};
@@ -34,7 +34,7 @@ use crate::key_server_cluster::message::{Message, ServersSetChangeMessage,
 	ServersSetChangeDelegate, ServersSetChangeDelegateResponse, InitializeShareChangeSession,
 	ConfirmShareChangeSessionInitialization, KeyVersionNegotiationMessage, ShareChangeKeyVersionNegotiation};
 use crate::key_server_cluster::share_change_session::{ShareChangeSession, ShareChangeSessionParams, ShareChangeSessionPlan,
-	prepare_share_change_session_plan};
+	prepare_share_change_session_plan, prepare_share_refresh_session_plan};
 use crate::key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	SessionParams as KeyVersionNegotiationSessionParams, LargestSupportResultComputer,
 	SessionTransport as KeyVersionNegotiationTransport};
@@ -62,6 +62,10 @@ const MAX_ACTIVE_KEY_SESSIONS: usize = 64;
 /// 5.3) share change session is executed by this sub_master
 /// 5.4) share change confirm is sent from sub_master to master
 /// 6) upon completing all known share change sessions && receiving confirmations for all unknown share change sessions, session completion signal is sent to all slave nodes && session is completed
+///
+/// The same session is used to refresh key shares without changing servers set. Then new nodes set must be equal
+/// to the current set, ShareAdd session is started for every (or single) key with all version holders being the
+/// 'new' nodes && previous versions of refreshed keys are removed upon session completion.
 pub struct SessionImpl {
 	/// Session core.
 	core: SessionCore,
@@ -69,6 +73,15 @@ pub struct SessionImpl {
 	data: Mutex<SessionData>,
 }
 
+/// Keys, which shares are refreshed by the session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeySharesRefresh {
+	/// Refresh shares of all keys.
+	AllKeys,
+	/// Refresh shares of single key.
+	Key(ServerKeyId),
+}
+
 /// Session state.
 #[derive(Debug, PartialEq)]
 enum SessionState {
@@ -96,6 +109,8 @@ struct SessionCore {
 	pub admin_address: Address,
 	/// Migration id (if this session is a part of auto-migration process).
 	pub migration_id: Option<H256>,
+	/// Keys to refresh shares of (if this session is a key shares refresh session).
+	pub shares_refresh: Option<KeySharesRefresh>,
 	/// Session completion signal.
 	pub completed: CompletionSignal<()>,
 }
@@ -121,6 +136,8 @@ struct SessionData {
 	pub delegated_key_sessions: BTreeMap<SessionId, NodeId>,
 	/// Active share change sessions.
 	pub active_key_sessions: BTreeMap<SessionId, ShareChangeSession>,
+	/// Keys, which shares have been refreshed on this node.
+	pub refreshed_keys: BTreeSet<SessionId>,
 	/// Servers set change result.
 	pub result: Option<Result<(), Error>>,
 }
@@ -149,6 +166,8 @@ pub struct SessionParams {
 	pub admin_address: Address,
 	/// Migration id (if this session is a part of auto-migration process).
 	pub migration_id: Option<H256>,
+	/// Keys to refresh shares of (if this session is a key shares refresh session).
+	pub shares_refresh: Option<KeySharesRefresh>,
 }
 
 /// Servers set change consensus transport.
@@ -159,6 +178,8 @@ struct ServersSetChangeConsensusTransport {
 	nonce: u64,
 	/// Migration id (if part of auto-migration process).
 	migration_id: Option<H256>,
+	/// Keys to refresh shares of (if this is a key shares refresh session).
+	shares_refresh: Option<KeySharesRefresh>,
 	/// Cluster.
 	cluster: Arc<dyn Cluster>,
 }
@@ -196,6 +217,7 @@ impl SessionImpl {
 				all_nodes_set: params.all_nodes_set,
 				admin_address: params.admin_address,
 				migration_id: params.migration_id,
+				shares_refresh: params.shares_refresh,
 				completed,
 			},
 			data: Mutex::new(SessionData {
@@ -207,6 +229,7 @@ impl SessionImpl {
 				sessions_initialization_state: BTreeMap::new(),
 				delegated_key_sessions: BTreeMap::new(),
 				active_key_sessions: BTreeMap::new(),
+				refreshed_keys: BTreeSet::new(),
 				result: None,
 			}),
 		}, oneshot))
@@ -230,6 +253,7 @@ impl SessionImpl {
 	/// Initialize servers set change session on master node.
 	pub fn initialize(&self, new_nodes_set: BTreeSet<NodeId>, all_set_signature: Signature, new_set_signature: Signature) -> Result<(), Error> {
 		check_nodes_set(&self.core.all_nodes_set, &new_nodes_set)?;
+		check_shares_refresh_nodes_set(&self.core, &new_nodes_set)?;
 
 		let mut data = self.data.lock();
 		if data.state != SessionState::EstablishingConsensus || data.consensus_session.is_some() {
@@ -247,6 +271,7 @@ impl SessionImpl {
 				id: self.core.meta.id.clone(),
 				nonce: self.core.nonce,
 				migration_id: self.core.migration_id.clone(),
+				shares_refresh: self.core.shares_refresh.clone(),
 				cluster: self.core.cluster.clone(),
 			},
 		})?;
@@ -321,6 +346,7 @@ impl SessionImpl {
 								id: self.core.meta.id.clone(),
 								nonce: self.core.nonce,
 								migration_id: self.core.migration_id.clone(),
+								shares_refresh: self.core.shares_refresh.clone(),
 								cluster: self.core.cluster.clone(),
 							},
 						})?);
@@ -370,6 +396,7 @@ impl SessionImpl {
 				.expect("consensus session is now completed; new_servers_set is intermediate result of consensus session; qed")
 				.clone()
 		};
+		check_shares_refresh_nodes_set(&self.core, &new_nodes_set)?;
 
 		// update state
 		data.state = SessionState::RunningShareChangeSessions;
@@ -404,7 +431,10 @@ impl SessionImpl {
 
 		// initialize sessions queue
 		data.state = SessionState::RunningShareChangeSessions;
-		data.sessions_queue = Some(SessionsQueue::new(&self.core.key_storage, unknown_sessions.keys().cloned().collect()));
+		data.sessions_queue = Some(match self.core.shares_refresh {
+			Some(KeySharesRefresh::Key(ref key_id)) => SessionsQueue::single(key_id.clone()),
+			_ => SessionsQueue::new(&self.core.key_storage, unknown_sessions.keys().cloned().collect()),
+		});
 
 		// and disseminate session initialization requests
 		Self::disseminate_session_initialization_requests(&self.core, &mut *data)
@@ -509,8 +539,8 @@ impl SessionImpl {
 					let key_share_owners = message.version_holders.iter().cloned().map(Into::into).collect();
 					let new_nodes_set = data.new_nodes_set.as_ref()
 						.expect("new_nodes_set is filled during consensus establishing; change sessions are running after this; qed");
-					let local_plan = prepare_share_change_session_plan(
-						&self.core.all_nodes_set,
+					let local_plan = Self::prepare_session_plan(
+						&self.core,
 						key_share.threshold,
 						&key_id,
 						version,
@@ -673,6 +703,9 @@ impl SessionImpl {
 			self.core.key_storage.clear()?;
 		}
 
+		// refreshed shares are now confirmed by all nodes => remove previous versions
+		Self::prune_refreshed_keys_versions(&self.core, &mut *data)?;
+
 		data.state = SessionState::Finished;
 		self.core.completed.send(Ok(()));
 
@@ -810,7 +843,7 @@ impl SessionImpl {
 		let old_nodes_set = selected_version_holders;
 		let new_nodes_set = data.new_nodes_set.as_ref()
 			.expect("this method is called after consensus estabished; new_nodes_set is a result of consensus session; qed");
-		let session_plan = prepare_share_change_session_plan(&core.all_nodes_set,
+		let session_plan = Self::prepare_session_plan(core,
 			selected_version_threshold,
 			&key_id,
 			selected_version.clone(),
@@ -863,6 +896,27 @@ impl SessionImpl {
 		Ok(true)
 	}
 
+	/// Prepare share change (or share refresh) plan for given key.
+	fn prepare_session_plan(core: &SessionCore, threshold: usize, key_id: &SessionId, key_version: H256, master: &NodeId, old_nodes_set: &BTreeSet<NodeId>, new_nodes_set: &BTreeSet<NodeId>) -> Result<ShareChangeSessionPlan, Error> {
+		match core.shares_refresh {
+			Some(_) => prepare_share_refresh_session_plan(&core.all_nodes_set, threshold, key_id, key_version, master, old_nodes_set),
+			None => prepare_share_change_session_plan(&core.all_nodes_set, threshold, key_id, key_version, master, old_nodes_set, new_nodes_set),
+		}
+	}
+
+	/// Remove all versions of refreshed keys, except the last (refreshed) version.
+	fn prune_refreshed_keys_versions(core: &SessionCore, data: &mut SessionData) -> Result<(), Error> {
+		for key_id in ::std::mem::replace(&mut data.refreshed_keys, BTreeSet::new()) {
+			if let Some(mut key_share) = core.key_storage.get(&key_id)? {
+				let versions_to_remove = key_share.versions.len().saturating_sub(1);
+				key_share.versions.drain(..versions_to_remove);
+				core.key_storage.update(key_id, key_share)?;
+			}
+		}
+
+		Ok(())
+	}
+
 	/// Return delegated session to master.
 	fn return_delegated_session(core: &SessionCore, key_id: &SessionId) -> Result<(), Error> {
 		assert!(core.meta.self_node_id != core.meta.master_node_id);
@@ -876,6 +930,9 @@ impl SessionImpl {
 	/// Complete key session.
 	fn complete_key_session(core: &SessionCore, data: &mut SessionData, is_master: bool, session_id: SessionId) -> Result<(), Error> {
 		data.active_key_sessions.remove(&session_id);
+		if core.shares_refresh.is_some() {
+			data.refreshed_keys.insert(session_id.clone());
+		}
 		let is_general_master = core.meta.self_node_id == core.meta.master_node_id;
 		if is_master && !is_general_master {
 			Self::return_delegated_session(core, &session_id)?;
@@ -885,6 +942,7 @@ impl SessionImpl {
 		}
 
 		if data.result.is_some() && data.active_key_sessions.len() == 0 {
+			Self::prune_refreshed_keys_versions(core, data)?;
 			data.state = SessionState::Finished;
 			core.completed.send(Ok(()));
 		}
@@ -909,6 +967,9 @@ impl SessionImpl {
 			core.key_storage.clear()?;
 		}
 
+		// refreshed shares are now confirmed by all nodes => remove previous versions
+		Self::prune_refreshed_keys_versions(core, data)?;
+
 		data.state = SessionState::Finished;
 		data.result = Some(Ok(()));
 		core.completed.send(Ok(()));
@@ -982,6 +1043,11 @@ impl JobTransport for ServersSetChangeConsensusTransport {
 			session_nonce: self.nonce,
 			message: ConsensusMessageWithServersSet::InitializeConsensusSession(InitializeConsensusSessionWithServersSet {
 				migration_id: self.migration_id.clone().map(Into::into),
+				refresh_key_shares: self.shares_refresh.is_some(),
+				refreshed_key_id: match self.shares_refresh {
+					Some(KeySharesRefresh::Key(ref key_id)) => Some(key_id.clone().into()),
+					_ => None,
+				},
 				old_nodes_set: request.old_servers_set.into_iter().map(Into::into).collect(),
 				new_nodes_set: request.new_servers_set.into_iter().map(Into::into).collect(),
 				old_set_signature: request.old_set_signature.into(),
@@ -1047,6 +1113,14 @@ fn check_nodes_set(all_nodes_set: &BTreeSet<NodeId>, new_nodes_set: &BTreeSet<No
 	}
 }
 
+fn check_shares_refresh_nodes_set(core: &SessionCore, new_nodes_set: &BTreeSet<NodeId>) -> Result<(), Error> {
+	// servers set is not changed when key shares are refreshed
+	match core.shares_refresh.is_some() && *new_nodes_set != core.all_nodes_set {
+		true => Err(Error::InvalidMessage),
+		false => Ok(()),
+	}
+}
+
 #[cfg(test)]
 pub mod tests {
 	use std::sync::Arc;
@@ -1064,7 +1138,7 @@ pub mod tests {
 	use crate::key_server_cluster::message::Message;
 	use crate::key_server_cluster::admin_sessions::ShareChangeSessionMeta;
 	use crate::key_server_cluster::jobs::servers_set_change_access_job::ordered_nodes_hash;
-	use super::{SessionImpl, SessionParams};
+	use super::{SessionImpl, SessionParams, KeySharesRefresh};
 
 	pub trait AdminSessionAdapter<S> {
 		const SIGN_NEW_NODES: bool;
@@ -1118,6 +1192,33 @@ pub mod tests {
 				nonce: 1,
 				admin_address: admin_address,
 				migration_id: None,
+				shares_refresh: None,
+			}).unwrap().0
+		}
+	}
+
+	struct RefreshAdapter;
+
+	impl AdminSessionAdapter<SessionImpl> for RefreshAdapter {
+		const SIGN_NEW_NODES: bool = true;
+
+		fn create(
+			mut meta: ShareChangeSessionMeta,
+			admin_address: Address,
+			all_nodes_set: BTreeSet<NodeId>,
+			ml: &ClusterMessageLoop,
+			idx: usize
+		) -> SessionImpl {
+			meta.self_node_id = ml.node_key_pair(idx).address();
+			SessionImpl::new(SessionParams {
+				meta: meta,
+				all_nodes_set: all_nodes_set,
+				cluster: ml.cluster(idx).view().unwrap(),
+				key_storage: ml.key_storage(idx).clone(),
+				nonce: 1,
+				admin_address: admin_address,
+				migration_id: None,
+				shares_refresh: Some(KeySharesRefresh::AllKeys),
 			}).unwrap().0
 		}
 	}
@@ -1457,4 +1558,50 @@ pub mod tests {
 		// try to recover secret for every possible combination of nodes && check that secret is the same
 		ml.check_secret_is_preserved(ml.sessions.keys());
 	}
+
+	#[test]
+	fn key_shares_are_refreshed_using_servers_set_change() {
+		// initial 2-of-3 session
+		let gml = generate_key(3, 1);
+		let key_id = SessionId::from([1u8; 32]);
+		let old_key_shares: BTreeMap<_, _> = gml.0.nodes().into_iter()
+			.map(|n| (n, gml.0.key_storage_of(&n).get(&key_id).unwrap().unwrap()))
+			.collect();
+
+		// refresh shares without changing servers set
+		let master = gml.0.node(0);
+		let ml = MessageLoop::with_gml::<RefreshAdapter>(gml, master, None, None, None).run_at(master);
+
+		// try to recover secret for every possible combination of nodes && check that secret is the same
+		ml.check_secret_is_preserved(ml.sessions.keys());
+
+		// check that every node has single refreshed version of the key
+		for (node, old_key_share) in old_key_shares {
+			let old_key_version = old_key_share.last_version().unwrap();
+			let key_share = ml.ml.key_storage_of(&node).get(&key_id).unwrap().unwrap();
+			assert_eq!(key_share.public, old_key_share.public);
+			assert_eq!(key_share.versions.len(), 1);
+
+			let key_version = key_share.last_version().unwrap();
+			assert!(key_version.hash != old_key_version.hash);
+			assert!(key_version.secret_share != old_key_version.secret_share);
+			assert_eq!(key_version.id_numbers, old_key_version.id_numbers);
+			assert_eq!(key_version.public_shares[&node], math::compute_public_share(&key_version.secret_share).unwrap());
+		}
+	}
+
+	#[test]
+	fn key_shares_refresh_fails_if_servers_set_is_changed() {
+		// initial 2-of-3 session
+		let gml = generate_key(3, 1);
+
+		// try to remove node when refreshing shares
+		let master = gml.0.node(0);
+		let remove: BTreeSet<_> = ::std::iter::once(gml.0.node(1)).collect();
+		let ml = MessageLoop::with_gml::<RefreshAdapter>(gml, master, None, Some(remove), None);
+		assert_eq!(ml.sessions[&master].initialize(
+			ml.new_nodes_set.clone(),
+			ml.all_set_signature.clone(),
+			ml.new_set_signature.clone()), Err(Error::InvalidMessage));
+	}
 }
@@ -21,8 +21,8 @@ use crate::key_server_cluster::{Error, SessionId};
 
 /// Queue of share change sessions.
 pub struct SessionsQueue {
-	/// Sessions, known on this node.
-	known_sessions: VecDeque<SessionId>,
+	/// Sessions, known on this node. Key shares that can't be read are queued as errors.
+	known_sessions: VecDeque<Result<SessionId, Error>>,
 	/// Unknown sessions.
 	unknown_sessions: VecDeque<SessionId>,
 }
@@ -34,10 +34,18 @@ impl SessionsQueue {
 		// 1) known sessions - change to iter
 		// 2) unknown sesions - request chunk-by-chunk
 		SessionsQueue {
-			known_sessions: key_storage.iter().map(|(k, _)| k).collect(),
+			known_sessions: key_storage.iter().map(|key| key.map(|(k, _)| k)).collect(),
 			unknown_sessions: unknown_sessions.into_iter().collect(),
 		}
 	}
+
+	/// Create new sessions queue with single session.
+	pub fn single(session: SessionId) -> Self {
+		SessionsQueue {
+			known_sessions: ::std::iter::once(Ok(session)).collect(),
+			unknown_sessions: VecDeque::new(),
+		}
+	}
 }
 
 impl Iterator for SessionsQueue {
@@ -45,7 +53,7 @@ impl Iterator for SessionsQueue {
 
 	fn next(&mut self) -> Option<Self::Item> {
 		if let Some(known_session) = self.known_sessions.pop_front() {
-			return Some(Ok(known_session));
+			return Some(known_session);
 		}
 
 		if let Some(unknown_session) = self.unknown_sessions.pop_front() {
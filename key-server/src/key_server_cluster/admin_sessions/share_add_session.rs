@@ -25,10 +25,11 @@ use primitives::key_storage::{KeyShare, KeyShareVersion, KeyStorage};
 use crate::key_server_cluster::{Error, SessionId, NodeId};
 use crate::key_server_cluster::cluster::Cluster;
 use crate::key_server_cluster::cluster_sessions::{ClusterSession, CompletionSignal};
+use crate::key_server_cluster::io::VERIFIABLE_SHARES_HEADER_VERSION;
 use crate::key_server_cluster::math;
 use crate::key_server_cluster::message::{Message, ShareAddMessage, ShareAddConsensusMessage, ConsensusMessageOfShareAdd,
 	InitializeConsensusSessionOfShareAdd, KeyShareCommon, NewKeysDissemination, ShareAddError,
-	ConfirmConsensusInitialization, CommonKeyData};
+	ConfirmConsensusInitialization, CommonKeyData, NewKeysProofsFootprint};
 use crate::key_server_cluster::jobs::job_session::JobTransport;
 use crate::key_server_cluster::jobs::dummy_job::{DummyJob, DummyJobTransport};
 use crate::key_server_cluster::jobs::servers_set_change_access_job::{ServersSetChangeAccessJob, ServersSetChangeAccessRequest};
@@ -39,6 +40,8 @@ use crate::key_server_cluster::admin_sessions::ShareChangeSessionMeta;
 pub trait SessionTransport: Clone + JobTransport<PartialJobRequest=ServersSetChangeAccessRequest, PartialJobResponse=bool> {
 	/// Get all connected nodes. Since ShareAdd session requires all cluster nodes to be connected, this set equals to all known cluster nodes set.
 	fn nodes(&self) -> BTreeSet<NodeId>;
+	/// Get maximal header version that is supported by all given nodes.
+	fn protocol_version(&self, nodes: &BTreeSet<NodeId>) -> u64;
 	/// Send message to given node.
 	fn send(&self, node: &NodeId, message: ShareAddMessage) -> Result<(), Error>;
 	/// Set data for master node (sent to slave nodes in consensus session initialization message).
@@ -51,8 +54,9 @@ pub trait SessionTransport: Clone + JobTransport<PartialJobRequest=ServersSetCha
 /// Brief overview:
 /// 1) initialization: master node (which has received request for shares addition the message) asks all other nodes to support addition
 /// 2) key refreshing distribution (KRD): node generates new random polynom && sends required data to all other nodes
-/// 3) key refreshing verification (KRV): node verifies received data
-/// 4) node updates its own key share using generated (&& received) data
+/// 3) key refreshing verification (KRV): node verifies received data against public commitments to the sender' polynom
+/// 4) node broadcasts footprint of all received commitments to be sure that all nodes have received the same commitments
+/// 5) node updates its own key share using generated (&& received) data
 pub struct SessionImpl<T: SessionTransport> {
 	/// Session core.
 	core: SessionCore<T>,
@@ -97,6 +101,14 @@ struct SessionData<T: SessionTransport> {
 	pub id_numbers: Option<BTreeMap<NodeId, Option<Secret>>>,
 	/// Secret subshares received from nodes.
 	pub secret_subshares: Option<BTreeMap<NodeId, Option<Secret>>>,
+	/// Share proofs, received from nodes.
+	pub share_proofs: BTreeMap<NodeId, Vec<Public>>,
+	/// Share proofs of secret subshares (see `math::compute_subshare_proof`), received from nodes.
+	pub subshare_proofs: BTreeMap<NodeId, Vec<Public>>,
+	/// Footprint of all share proofs, received by this node.
+	pub share_proofs_footprint: Option<H256>,
+	/// Footprints of all share proofs, received by other nodes.
+	pub share_proofs_footprints: BTreeMap<NodeId, H256>,
 	/// Share add change result.
 	pub result: Option<Result<(), Error>>,
 }
@@ -113,6 +125,8 @@ struct NewKeyShare {
 	pub common_point: Option<Public>,
 	/// NewKeyShare: Encrypted point.
 	pub encrypted_point: Option<Public>,
+	/// NewKeyShare: public key shares of version holders. Empty if unknown.
+	pub public_shares: BTreeMap<NodeId, Public>,
 }
 
 /// Session state.
@@ -182,6 +196,10 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 				new_key_share: None,
 				id_numbers: None,
 				secret_subshares: None,
+				share_proofs: BTreeMap::new(),
+				subshare_proofs: BTreeMap::new(),
+				share_proofs_footprint: None,
+				share_proofs_footprints: BTreeMap::new(),
 				result: None,
 			}),
 		}, oneshot))
@@ -237,7 +255,8 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		}
 
 		// check passed consensus data
-		Self::check_nodes_map(&self.core, version, &consensus_group, &version_holders, &new_nodes_map)?;
+		// (pre-established consensus is also used to refresh shares of version holders => no new nodes are required)
+		Self::check_nodes_map(&self.core, version, &consensus_group, &version_holders, &new_nodes_map, false)?;
 
 		// update data
 		data.version = Some(version.clone());
@@ -261,6 +280,11 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			return Err(Error::InvalidStateForRequest);
 		}
 
+		// refreshed key shares are verified using proofs, which older nodes can't provide
+		if self.core.transport.protocol_version(&self.core.transport.nodes()) < VERIFIABLE_SHARES_HEADER_VERSION {
+			return Err(Error::InvalidMessageVersion);
+		}
+
 		// if consensus is pre-established => start sending ShareAdd-specific messages
 		let is_consensus_pre_established = data.id_numbers.is_some();
 		if is_consensus_pre_established {
@@ -305,7 +329,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		let version_holders = &old_nodes_set;
 
 		// now check nodes map
-		Self::check_nodes_map(&self.core, &version, &consensus_group, version_holders, &new_nodes_map)?;
+		Self::check_nodes_map(&self.core, &version, &consensus_group, version_holders, &new_nodes_map, true)?;
 
 		// prepare consensus session transport
 		let mut consensus_transport = self.core.transport.clone();
@@ -347,6 +371,8 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 				self.on_common_key_share_data(sender, message),
 			&ShareAddMessage::NewKeysDissemination(ref message) =>
 				self.on_new_keys_dissemination(sender, message),
+			&ShareAddMessage::NewKeysProofsFootprint(ref message) =>
+				self.on_new_keys_proofs_footprint(sender, message),
 			&ShareAddMessage::ShareAddError(ref message) => {
 				self.on_session_error(sender, message.error.clone());
 				Ok(())
@@ -396,7 +422,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 					}
 
 					// check old set of nodes
-					Self::check_nodes_map(&self.core, &version, &consensus_group, &version_holders, &new_nodes_map)?;
+					Self::check_nodes_map(&self.core, &version, &consensus_group, &version_holders, &new_nodes_map, true)?;
 
 					(Some(version), Some(new_nodes_map), Some(consensus_group), Some(version_holders))
 				},
@@ -477,6 +503,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			joint_public: message.key_common.public.clone().into(),
 			common_point: message.common_point.clone().map(Into::into),
 			encrypted_point: message.encrypted_point.clone().map(Into::into),
+			public_shares: message.public_shares.iter().map(|(k, v)| (k.clone().into(), v.clone().into())).collect(),
 		});
 
 		let id_numbers = data.id_numbers.as_mut()
@@ -532,10 +559,17 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 				Some(&None) => (),
 			};
 
-			let secret_subshare = Self::compute_secret_subshare(&self.core, &mut *data, sender, &message.secret_subshare.clone().into())?;
+			let secret_value = message.secret_subshare.clone().into();
+			let share_proof: Vec<Public> = message.share_proof.iter().cloned().map(Into::into).collect();
+			Self::check_share_proof(&self.core, &*data, sender, &secret_value, &share_proof)?;
+
+			let secret_subshare = Self::compute_secret_subshare(&self.core, &mut *data, sender, &secret_value)?;
+			let subshare_proof = Self::compute_subshare_proof(&self.core, &*data, sender, &share_proof)?;
 			*data.secret_subshares.as_mut().expect(explanation)
 				.get_mut(sender)
 				.expect("checked couple of lines above; qed") = Some(secret_subshare);
+			data.share_proofs.insert(sender.clone(), share_proof);
+			data.subshare_proofs.insert(sender.clone(), subshare_proof);
 		}
 
 		// if we have received subshare from master node, it means that we should start dissemination
@@ -548,12 +582,36 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			return Ok(())
 		}
 
-		// TODO [Trust]: find a way to verificate keys
-		Self::complete_session(&self.core, &mut *data)
+		Self::on_keys_disseminated(&self.core, &mut *data)
+	}
+
+	/// When share proofs footprint is received.
+	pub fn on_new_keys_proofs_footprint(&self, sender: &NodeId, message: &NewKeysProofsFootprint) -> Result<(), Error> {
+		debug_assert!(self.core.meta.id == *message.session);
+		debug_assert!(sender != &self.core.meta.self_node_id);
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state == SessionState::Finished {
+			return Err(Error::InvalidStateForRequest);
+		}
+		match data.id_numbers.as_ref() {
+			None => return Err(Error::TooEarlyForRequest),
+			Some(id_numbers) if !id_numbers.contains_key(sender) => return Err(Error::InvalidMessage),
+			Some(_) => (),
+		}
+
+		// we only expect this message once
+		if data.share_proofs_footprints.insert(sender.clone(), message.share_proofs_footprint.clone().into()).is_some() {
+			return Err(Error::InvalidMessage);
+		}
+
+		Self::try_complete_session(&self.core, &mut *data)
 	}
 
 	/// Check nodes map.
-	fn check_nodes_map(core: &SessionCore<T>, version: &H256, consensus_group: &BTreeSet<NodeId>, version_holders: &BTreeSet<NodeId>, new_nodes_map: &BTreeMap<NodeId, Option<Secret>>) -> Result<(), Error> {
+	fn check_nodes_map(core: &SessionCore<T>, version: &H256, consensus_group: &BTreeSet<NodeId>, version_holders: &BTreeSet<NodeId>, new_nodes_map: &BTreeMap<NodeId, Option<Secret>>, require_new_nodes: bool) -> Result<(), Error> {
 		// check if this node has given version
 		let has_this_version = match core.key_share.as_ref() {
 			Some(key_share) => key_share.version(version).is_ok(),
@@ -580,7 +638,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 				}
 
 				// there must be at least one new node in new_nodes_map
-				if key_version.id_numbers.keys().filter(|n| non_isolated_nodes.contains(n) && version_holders.contains(n)).count() >= new_nodes_map.len() {
+				if require_new_nodes && key_version.id_numbers.keys().filter(|n| non_isolated_nodes.contains(n) && version_holders.contains(n)).count() >= new_nodes_map.len() {
 					return Err(Error::ConsensusUnreachable);
 				}
 			},
@@ -632,8 +690,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			return Ok(())
 		}
 
-		// TODO [Trust]: find a way to verificate keys
-		Self::complete_session(core, data)
+		Self::on_keys_disseminated(core, data)
 	}
 
 	/// Send common share data to evey new node.
@@ -661,6 +718,9 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 				id_numbers: old_key_version.id_numbers.iter()
 					.filter(|&(k, _)| version_holders.contains(k))
 					.map(|(k, v)| (k.clone().into(), v.clone().into())).collect(),
+				public_shares: old_key_version.public_shares.iter()
+					.filter(|&(k, _)| version_holders.contains(k))
+					.map(|(k, v)| (k.clone().into(), v.clone().into())).collect(),
 			}))?;
 		}
 
@@ -675,6 +735,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		let key_version = key_share.version(data.version.as_ref().expect(explanation)).expect(explanation);
 		let mut secret_share_polynom = math::generate_random_polynom(key_share.threshold)?;
 		secret_share_polynom[0] = key_version.secret_share.clone();
+		let share_proof = math::prepare_share_proof(&secret_share_polynom)?;
 
 		// calculate secret subshare for every new node (including this node)
 		let explanation = "disseminate_keys is called after initialization has completed; this field is filled during initialization; qed";
@@ -686,13 +747,62 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 					session: core.meta.id.clone().into(),
 					session_nonce: core.nonce,
 					secret_subshare: secret_subshare.into(),
+					share_proof: share_proof.iter().cloned().map(Into::into).collect(),
 				}))?;
 			} else {
 				let secret_subshare = Self::compute_secret_subshare(core, data, new_node, &secret_subshare)?;
+				let subshare_proof = Self::compute_subshare_proof(core, data, new_node, &share_proof)?;
 				*data.secret_subshares.as_mut().expect(explanation)
 					.get_mut(&core.meta.self_node_id)
 					.expect("disseminate_keys is only calle on consensus group nodes; there's entry for every consensus node in secret_subshares; qed")
 						= Some(secret_subshare);
+				data.share_proofs.insert(new_node.clone(), share_proof.clone());
+				data.subshare_proofs.insert(new_node.clone(), subshare_proof);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Get threshold of the key.
+	fn threshold(core: &SessionCore<T>, data: &SessionData<T>) -> usize {
+		core.key_share.as_ref().map(|ks| ks.threshold)
+			.unwrap_or_else(|| data.new_key_share.as_ref()
+				.expect("computation occurs after receiving key share threshold if not having one already; qed")
+				.threshold)
+	}
+
+	/// Get public key shares of version holders (if known).
+	fn public_shares<'a>(core: &'a SessionCore<T>, data: &'a SessionData<T>) -> Option<&'a BTreeMap<NodeId, Public>> {
+		data.version.as_ref()
+			.and_then(|version| core.key_share.as_ref().and_then(|ks| ks.version(version).ok()))
+			.map(|key_version| &key_version.public_shares)
+			.or_else(|| data.new_key_share.as_ref().map(|new_key_share| &new_key_share.public_shares))
+	}
+
+	/// Check that secret value, received from sender, matches commitments to the sender' polynom.
+	fn check_share_proof(core: &SessionCore<T>, data: &SessionData<T>, sender: &NodeId, secret_value: &Secret, share_proof: &[Public]) -> Result<(), Error> {
+		let threshold = Self::threshold(core, data);
+		if share_proof.len() != threshold + 1 {
+			return Err(Error::InvalidSecretSubshare(sender.clone()));
+		}
+
+		let self_id_number = data.id_numbers.as_ref()
+			.expect("this field is a result of consensus job; check_share_proof is called after consensus is established")
+			.get(&core.meta.self_node_id)
+			.and_then(|id_number| id_number.as_ref())
+			.ok_or(Error::InvalidMessage)?;
+		if !math::share_proof_verification(threshold, self_id_number, secret_value, share_proof)? {
+			return Err(Error::InvalidSecretSubshare(sender.clone()));
+		}
+
+		// absolute term of sender' polynom must be its secret share
+		// => if we know public key share of sender, check that it matches the commitment
+		let sender_public_share = Self::public_shares(core, data)
+			.and_then(|public_shares| public_shares.get(sender));
+		if let Some(sender_public_share) = sender_public_share {
+			if *sender_public_share != share_proof[0] {
+				return Err(Error::InvalidSecretSubshare(sender.clone()));
 			}
 		}
 
@@ -704,10 +814,7 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		let explanation = "this field is a result of consensus job; compute_secret_subshare is called after consensus is established";
 		let id_numbers = data.id_numbers.as_ref().expect(explanation);
 		let secret_subshares = data.secret_subshares.as_ref().expect(explanation);
-		let threshold = core.key_share.as_ref().map(|ks| ks.threshold)
-			.unwrap_or_else(|| data.new_key_share.as_ref()
-				.expect("computation occurs after receiving key share threshold if not having one already; qed")
-				.threshold);
+		let threshold = Self::threshold(core, data);
 
 		let explanation = "id_numbers are checked to have Some value for every consensus group node when consensus is establishe; qed";
 		let sender_id_number = id_numbers[sender].as_ref().expect(explanation);
@@ -715,6 +822,59 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 		math::compute_secret_subshare(threshold, secret_value, sender_id_number, other_id_numbers)
 	}
 
+	/// Compute share proof of secret subshare from passed share proof.
+	fn compute_subshare_proof(core: &SessionCore<T>, data: &SessionData<T>, sender: &NodeId, share_proof: &[Public]) -> Result<Vec<Public>, Error> {
+		let explanation = "this field is a result of consensus job; compute_subshare_proof is called after consensus is established";
+		let id_numbers = data.id_numbers.as_ref().expect(explanation);
+		let secret_subshares = data.secret_subshares.as_ref().expect(explanation);
+		let threshold = Self::threshold(core, data);
+
+		let explanation = "id_numbers are checked to have Some value for every consensus group node when consensus is establishe; qed";
+		let sender_id_number = id_numbers[sender].as_ref().expect(explanation);
+		let other_id_numbers = secret_subshares.keys().filter(|k| *k != sender).map(|n| id_numbers[n].as_ref().expect(explanation));
+		math::compute_subshare_proof(threshold, share_proof, sender_id_number, other_id_numbers)
+	}
+
+	/// When subshares from all nodes are received.
+	fn on_keys_disseminated(core: &SessionCore<T>, data: &mut SessionData<T>) -> Result<(), Error> {
+		// share proofs are sent directly to every node => sender could send different proofs to different nodes
+		// => broadcast footprint of all received share proofs to be sure that all nodes have received the same proofs
+		let share_proofs_footprint = math::compute_publics_footprint(data.share_proofs.clone())?;
+		data.share_proofs_footprint = Some(share_proofs_footprint);
+
+		let explanation = "this field is a result of consensus job; on_keys_disseminated is called after consensus is established";
+		for node in data.id_numbers.as_ref().expect(explanation).keys().filter(|n| **n != core.meta.self_node_id) {
+			core.transport.send(node, ShareAddMessage::NewKeysProofsFootprint(NewKeysProofsFootprint {
+				session: core.meta.id.clone().into(),
+				session_nonce: core.nonce,
+				share_proofs_footprint: share_proofs_footprint.into(),
+			}))?;
+		}
+
+		Self::try_complete_session(core, data)
+	}
+
+	/// Complete session if share proofs footprints from all nodes are received.
+	fn try_complete_session(core: &SessionCore<T>, data: &mut SessionData<T>) -> Result<(), Error> {
+		let share_proofs_footprint = match data.share_proofs_footprint.as_ref() {
+			Some(share_proofs_footprint) => share_proofs_footprint,
+			None => return Ok(()),
+		};
+
+		let explanation = "this field is a result of consensus job; try_complete_session is called after consensus is established";
+		let other_nodes_count = data.id_numbers.as_ref().expect(explanation).len() - 1;
+		if data.share_proofs_footprints.len() != other_nodes_count {
+			return Ok(());
+		}
+
+		// if footprints differ, then some node has sent different share proofs to different nodes
+		if data.share_proofs_footprints.values().any(|footprint| footprint != share_proofs_footprint) {
+			return Err(Error::InvalidMessage);
+		}
+
+		Self::complete_session(core, data)
+	}
+
 	/// Complete session.
 	fn complete_session(core: &SessionCore<T>, data: &mut SessionData<T>) -> Result<(), Error> {
 		// if already completed, do nothing
@@ -729,10 +889,42 @@ impl<T> SessionImpl<T> where T: SessionTransport {
 			.expect("nodes are filled during consensus establishing; session is completed after consensus is established; qed");
 		let secret_share = math::compute_secret_share(secret_subshares.values().map(|ss| ss.as_ref()
 			.expect("complete_session is only called when subshares from all nodes are received; qed")))?;
+		let id_numbers: BTreeMap<_, _> = id_numbers.clone().into_iter().map(|(k, v)| (k.clone(),
+			v.expect("id_numbers are checked to have Some value for every consensus group node when consensus is establishe; qed"))).collect();
+
+		// all consensus group nodes must have shared the same (joint) secret
+		let joint_public = core.key_share.as_ref().map(|ks| &ks.public)
+			.unwrap_or_else(|| &data.new_key_share.as_ref()
+				.expect("this is new node; on new nodes this field is filled before KRD; session is completed after KRD; qed")
+				.joint_public);
+		if math::compute_public_sum(data.subshare_proofs.values().map(|p| &p[0]))? != *joint_public {
+			// absolute term of every sender' polynom must be its secret share
+			// => if we know public key shares, find the node that has committed to the wrong value
+			let faulty_node = Self::public_shares(core, data)
+				.and_then(|public_shares| data.share_proofs.iter()
+					.find(|&(node, share_proof)| public_shares.get(node).map(|public_share| *public_share != share_proof[0]).unwrap_or(false))
+					.map(|(node, _)| node.clone()));
+			return Err(faulty_node.map(Error::InvalidSecretSubshare).unwrap_or(Error::InvalidMessage));
+		}
+
+		// public key shares of all nodes are computed from share proofs
+		let mut public_shares = BTreeMap::new();
+		for (node, id_number) in &id_numbers {
+			public_shares.insert(node.clone(), math::compute_public_key_share(id_number,
+				data.subshare_proofs.values().map(|p| &p[..]))?);
+		}
 
-		let refreshed_key_version = KeyShareVersion::new(id_numbers.clone().into_iter().map(|(k, v)| (k.clone(),
-			v.expect("id_numbers are checked to have Some value for every consensus group node when consensus is establishe; qed"))).collect(),
-			secret_share);
+		// if id numbers are the same as in the original version, then shares are refreshed
+		// => version hash must be different from the original version hash
+		let version = data.version.as_ref().expect(explanation);
+		let is_shares_refresh = core.key_share.as_ref()
+			.and_then(|ks| ks.version(version).ok())
+			.map(|key_version| key_version.id_numbers == id_numbers)
+			.unwrap_or(false);
+		let mut refreshed_key_version = KeyShareVersion::new(id_numbers, secret_share, public_shares);
+		if is_shares_refresh {
+			refreshed_key_version.hash = KeyShareVersion::refreshed_hash(version);
+		}
 		let mut refreshed_key_share = core.key_share.as_ref().cloned().unwrap_or_else(|| {
 			let new_key_share = data.new_key_share.as_ref()
 				.expect("this is new node; on new nodes this field is filled before KRD; session is completed after KRD; qed");
@@ -879,6 +1071,10 @@ impl SessionTransport for IsolatedSessionTransport {
 		self.cluster.nodes()
 	}
 
+	fn protocol_version(&self, nodes: &BTreeSet<NodeId>) -> u64 {
+		self.cluster.protocol_version(nodes)
+	}
+
 	fn set_master_data(&mut self, consensus_group: BTreeSet<NodeId>, version_holders: BTreeSet<NodeId>, id_numbers: BTreeMap<NodeId, Option<Secret>>) {
 		self.version_holders = Some(version_holders);
 		self.consensus_group = Some(consensus_group);
@@ -893,10 +1089,13 @@ impl SessionTransport for IsolatedSessionTransport {
 #[cfg(test)]
 pub mod tests {
 	use std::collections::BTreeSet;
-	use parity_crypto::publickey::{Address, Random, Generator};
+	use ethereum_types::H256;
+	use parity_crypto::publickey::{Address, Random, Generator, Secret};
 	use primitives::key_storage::KeyStorage;
 	use primitives::key_server_key_pair::KeyServerKeyPair;
 	use crate::key_server_cluster::{NodeId, Error};
+	use crate::key_server_cluster::math;
+	use crate::key_server_cluster::message::{Message, ShareAddMessage};
 	use crate::key_server_cluster::cluster::tests::MessageLoop as ClusterMessageLoop;
 	use crate::key_server_cluster::servers_set_change_session::tests::{MessageLoop, AdminSessionAdapter, generate_key};
 	use crate::key_server_cluster::admin_sessions::ShareChangeSessionMeta;
@@ -1117,4 +1316,104 @@ pub mod tests {
 		assert_eq!(MessageLoop::with_gml::<Adapter>(gml, master, Some(add), None, Some(isolate))
 			.run_at(master).unwrap_err(), Error::ConsensusUnreachable);
 	}
+
+	#[test]
+	fn nodes_added_using_share_add_have_public_key_shares_of_all_nodes() {
+		let (n, add) = (3, 2);
+
+		// generate key
+		let gml = generate_key(n, 1);
+
+		// run share add session
+		let master = gml.0.node(0);
+		let add = (0..add).map(|_| Random.generate()).collect();
+		let ml = MessageLoop::with_gml::<Adapter>(gml, master, Some(add), None, None)
+			.run_at(master).unwrap();
+
+		// check that every node knows public key shares of all nodes
+		let dummy_doc = [1u8; 32].into();
+		let nodes: Vec<_> = ml.sessions.keys().cloned().collect();
+		let key_versions: Vec<_> = nodes.iter()
+			.map(|n| ml.ml.key_storage_of(n).get(&dummy_doc).unwrap().unwrap().last_version().unwrap().clone())
+			.collect();
+		for key_version in &key_versions {
+			assert_eq!(key_version.public_shares.len(), nodes.len());
+			for (node, other_key_version) in nodes.iter().zip(key_versions.iter()) {
+				assert_eq!(
+					key_version.public_shares[node],
+					math::compute_public_share(&other_key_version.secret_share).unwrap(),
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn node_add_fails_when_consensus_node_sends_invalid_secret_subshare() {
+		let (n, add) = (3, 1);
+
+		// generate key
+		let gml = generate_key(n, 1);
+
+		// start share add session
+		let master = gml.0.node(0);
+		let add: Vec<_> = (0..add).map(|_| Random.generate()).collect();
+		let new_node = add[0].address();
+		let mut ml = MessageLoop::with_gml::<Adapter>(gml, master, Some(add), None, None)
+			.init_at(master).unwrap();
+
+		// process messages until consensus node sends secret subshare to the new node
+		// => corrupt this subshare
+		let (from, to, mut message) = loop {
+			let message = ml.take_message().unwrap();
+			match message.2 {
+				Message::ShareAdd(ShareAddMessage::NewKeysDissemination(_)) if message.1 == new_node => break message,
+				_ => ml.process_message(message).unwrap(),
+			}
+		};
+		match message {
+			Message::ShareAdd(ShareAddMessage::NewKeysDissemination(ref mut message)) => {
+				let mut secret_subshare: Secret = message.secret_subshare.clone().into();
+				secret_subshare.add(&math::generate_random_scalar().unwrap()).unwrap();
+				message.secret_subshare = secret_subshare.into();
+			},
+			_ => unreachable!("checked above; qed"),
+		}
+
+		// new node must detect that subshare doesn't match commitments of the sender
+		assert_eq!(ml.process_message((from, to, message)), Err(Error::InvalidSecretSubshare(from)));
+	}
+
+	#[test]
+	fn node_add_fails_when_nodes_have_received_different_share_proofs() {
+		let (n, add) = (3, 1);
+
+		// generate key
+		let gml = generate_key(n, 1);
+
+		// start share add session
+		let master = gml.0.node(0);
+		let add: Vec<_> = (0..add).map(|_| Random.generate()).collect();
+		let new_node = add[0].address();
+		let mut ml = MessageLoop::with_gml::<Adapter>(gml, master, Some(add), None, None)
+			.init_at(master).unwrap();
+
+		// new node reports footprint of share proofs that differs from footprints of other nodes
+		// (that's what happens when some node sends different share proofs to different nodes)
+		let mut result = Ok(());
+		while let Some((from, to, mut message)) = ml.take_message() {
+			if from == new_node {
+				if let Message::ShareAdd(ShareAddMessage::NewKeysProofsFootprint(ref mut message)) = message {
+					message.share_proofs_footprint = H256::from([1u8; 32]).into();
+				}
+			}
+
+			result = ml.process_message((from, to, message));
+			if result.is_err() {
+				break;
+			}
+		}
+
+		// other nodes must detect that share proofs footprints are different
+		assert_eq!(result, Err(Error::InvalidMessage));
+	}
 }
@@ -228,6 +228,10 @@ impl ShareAddSessionTransport for ShareChangeTransport {
 		self.cluster.nodes()
 	}
 
+	fn protocol_version(&self, nodes: &BTreeSet<NodeId>) -> u64 {
+		self.cluster.protocol_version(nodes)
+	}
+
 	fn set_master_data(&mut self, _consensus_group: BTreeSet<NodeId>, _version_holders: BTreeSet<NodeId>, _id_numbers: BTreeMap<NodeId, Option<Secret>>) {
 		unreachable!("only called when establishing consensus; this transport is never used for establishing consensus; qed")
 	}
@@ -293,6 +297,42 @@ pub fn prepare_share_change_session_plan(cluster_nodes: &BTreeSet<NodeId>, thres
 	})
 }
 
+/// Prepare share change plan for refreshing shares of `key_version_owners` without changing the set of owners.
+pub fn prepare_share_refresh_session_plan(cluster_nodes: &BTreeSet<NodeId>, threshold: usize, key_id: &ServerKeyId, key_version: H256, master: &NodeId, key_version_owners: &BTreeSet<NodeId>) -> Result<ShareChangeSessionPlan, Error> {
+	// we can't do anything if there are no enought shares
+	if key_version_owners.len() < threshold + 1 {
+		warn!("cannot refresh shares of key {} with threshold {}: only {} shares owners are available",
+			key_id, threshold, key_version_owners.len());
+		return Ok(ShareChangeSessionPlan {
+			key_version: key_version,
+			version_holders: Default::default(),
+			consensus_group: Default::default(),
+			new_nodes_map: Default::default(),
+		});
+	}
+
+	// all owners are receiving new shares, but id numbers are preserved
+	let new_nodes_map = key_version_owners.iter()
+		.filter(|n| cluster_nodes.contains(n))
+		.map(|n| (n.clone(), None))
+		.collect();
+
+	// select consensus group
+	let consensus_group = ::std::iter::once(master.clone())
+		.chain(key_version_owners.iter()
+			.filter(|n| *n != master && cluster_nodes.contains(*n))
+			.take(threshold)
+			.cloned())
+		.collect();
+
+	Ok(ShareChangeSessionPlan {
+		key_version: key_version,
+		version_holders: key_version_owners.clone(),
+		consensus_group: consensus_group,
+		new_nodes_map: new_nodes_map,
+	})
+}
+
 impl ShareChangeSessionPlan {
 	/// Is empty (nothing-to-do) plan?
 	pub fn is_empty(&self) -> bool {
@@ -303,7 +343,7 @@ impl ShareChangeSessionPlan {
 #[cfg(test)]
 mod tests {
 	use crate::key_server_cluster::math;
-	use super::prepare_share_change_session_plan;
+	use super::{prepare_share_change_session_plan, prepare_share_refresh_session_plan};
 
 	#[test]
 	fn share_change_plan_creates_empty_plan() {
@@ -317,6 +357,21 @@ mod tests {
 		assert!(plan.is_empty());
 	}
 
+	#[test]
+	fn share_refresh_plan_refreshes_shares_of_all_owners() {
+		let cluster_nodes: Vec<_> = (0..3).map(|_| math::generate_random_address().unwrap()).collect();
+		let master = cluster_nodes[0].clone();
+		let key_version_owners = cluster_nodes.iter().cloned().collect();
+		let plan = prepare_share_refresh_session_plan(&cluster_nodes.iter().cloned().collect(),
+			1, &Default::default(), Default::default(), &master, &key_version_owners).unwrap();
+
+		assert!(!plan.is_empty());
+		assert_eq!(plan.consensus_group.len(), 2);
+		assert!(plan.consensus_group.contains(&master));
+		assert_eq!(key_version_owners, plan.new_nodes_map.keys().cloned().collect());
+		assert!(plan.new_nodes_map.values().all(Option::is_none));
+	}
+
 	#[test]
 	fn share_change_plan_adds_new_nodes() {
 		let cluster_nodes: Vec<_> = (0..3).map(|_| math::generate_random_address().unwrap()).collect();
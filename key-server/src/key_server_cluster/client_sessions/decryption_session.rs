@@ -21,7 +21,7 @@ use parking_lot::Mutex;
 use ethereum_types::{Address, H256};
 use log::warn;
 use parity_crypto::publickey::Secret;
-use primitives::{acl_storage::AclStorage, key_storage::KeyShare};
+use primitives::key_storage::KeyShare;
 use crate::key_server_cluster::{Error, NodeId, SessionId, Requester,
 	EncryptedDocumentKeyShadow, SessionMeta};
 use crate::key_server_cluster::cluster::Cluster;
@@ -99,8 +99,6 @@ pub struct SessionParams {
 	pub access_key: Secret,
 	/// Key share.
 	pub key_share: Option<KeyShare>,
-	/// ACL storage.
-	pub acl_storage: Arc<dyn AclStorage>,
 	/// Cluster.
 	pub cluster: Arc<dyn Cluster>,
 	/// Session nonce.
@@ -174,8 +172,8 @@ impl SessionImpl {
 		let consensus_session = ConsensusSession::new(ConsensusSessionParams {
 			meta: params.meta.clone(),
 			consensus_executor: match requester {
-				Some(requester) => KeyAccessJob::new_on_master(params.meta.id.clone(), params.acl_storage.clone(), requester),
-				None => KeyAccessJob::new_on_slave(params.meta.id.clone(), params.acl_storage.clone()),
+				Some(requester) => KeyAccessJob::new_on_master(params.meta.id.clone(), requester),
+				None => KeyAccessJob::new_on_slave(params.meta.id.clone()),
 			},
 			consensus_transport: consensus_transport,
 		})?;
@@ -271,6 +269,13 @@ impl SessionImpl {
 		})
 	}
 
+	/// Set result of the requester access check, which has been awaited before message from
+	/// the requester (or the requester itself) has reached the session.
+	pub fn set_key_access_check_result(&self, requester_address: Address, result: Result<bool, Error>) {
+		self.data.lock().consensus_session.consensus_job_mut().executor_mut()
+			.set_access_check_result(requester_address, result);
+	}
+
 	/// Delegate session to other node.
 	pub fn delegate(&self, master: NodeId, origin: Option<Address>, version: H256, is_shadow_decryption: bool, is_broadcast_session: bool) -> Result<(), Error> {
 		if self.core.meta.master_node_id != self.core.meta.self_node_id {
@@ -831,7 +836,6 @@ impl JobTransport for DecryptionJobTransport {
 
 #[cfg(test)]
 pub fn create_default_decryption_session() -> Arc<SessionImpl> {
-	use primitives::acl_storage::InMemoryPermissiveAclStorage;
 	use crate::key_server_cluster::cluster::tests::DummyCluster;
 	use ethereum_types::H512;
 
@@ -846,7 +850,6 @@ pub fn create_default_decryption_session() -> Arc<SessionImpl> {
 		},
 		access_key: Secret::zero(),
 		key_share: Default::default(),
-		acl_storage: Arc::new(InMemoryPermissiveAclStorage::default()),
 		cluster: Arc::new(DummyCluster::new(Default::default())),
 		nonce: 0,
 	}, Some(Requester::Public(H512::from_low_u64_be(2)))).unwrap().0)
@@ -857,7 +860,7 @@ mod tests {
 	use std::sync::Arc;
 	use std::collections::{BTreeMap, VecDeque};
 	use primitives::{
-		acl_storage::InMemoryPermissiveAclStorage,
+		acl_storage::{AclStorage, InMemoryPermissiveAclStorage},
 		key_storage::{KeyShare, KeyShareVersion},
 	};
 	use parity_crypto::publickey::{KeyPair, Random, Generator, Public, Secret, public_to_address};
@@ -909,6 +912,7 @@ mod tests {
 				hash: Default::default(),
 				id_numbers: id_numbers.clone().into_iter().collect(),
 				secret_share: secret_shares[i].clone(),
+				public_shares: Default::default(),
 			}],
 		}).collect();
 		let acl_storages: Vec<_> = (0..5).map(|_| Arc::new(InMemoryPermissiveAclStorage::default())).collect();
@@ -932,19 +936,24 @@ mod tests {
 			},
 			access_key: access_key.clone(),
 			key_share: Some(encrypted_datas[i].clone()),
-			acl_storage: acl_storages[i].clone(),
 			cluster: clusters[i].clone(),
 			nonce: 0,
 		}, if i == 0 { signature.clone().map(Into::into) } else { None }).unwrap().0).collect();
+		check_key_access(&acl_storages[0], &sessions[0], &session_id, requester.public());
 
 		(requester, clusters, acl_storages, sessions)
 	}
 
-	fn do_messages_exchange(clusters: &[Arc<DummyCluster>], sessions: &[SessionImpl]) -> Result<(), Error> {
-		do_messages_exchange_until(clusters, sessions, |_, _, _| false)
+	fn check_key_access(acl_storage: &InMemoryPermissiveAclStorage, session: &SessionImpl, key_id: &SessionId, requester: &Public) {
+		let requester_address = public_to_address(requester);
+		session.set_key_access_check_result(requester_address, acl_storage.check(requester_address, key_id));
 	}
 
-	fn do_messages_exchange_until<F>(clusters: &[Arc<DummyCluster>], sessions: &[SessionImpl], mut cond: F) -> Result<(), Error> where F: FnMut(&NodeId, &NodeId, &Message) -> bool {
+	fn do_messages_exchange(clusters: &[Arc<DummyCluster>], acl_storages: &[Arc<InMemoryPermissiveAclStorage>], sessions: &[SessionImpl]) -> Result<(), Error> {
+		do_messages_exchange_until(clusters, acl_storages, sessions, |_, _, _| false)
+	}
+
+	fn do_messages_exchange_until<F>(clusters: &[Arc<DummyCluster>], acl_storages: &[Arc<InMemoryPermissiveAclStorage>], sessions: &[SessionImpl], mut cond: F) -> Result<(), Error> where F: FnMut(&NodeId, &NodeId, &Message) -> bool {
 		let mut queue: VecDeque<(NodeId, NodeId, Message)> = VecDeque::new();
 		while let Some((mut from, mut to, mut message)) = clusters.iter().filter_map(|c| c.take_message().map(|(to, msg)| (c.node(), to, msg))).next() {
 			if cond(&from, &to, &message) {
@@ -953,7 +962,14 @@ mod tests {
 
 			let mut is_queued_message = false;
 			loop {
-				let session = &sessions[sessions.iter().position(|s| s.node() == &to).unwrap()];
+				let session_index = sessions.iter().position(|s| s.node() == &to).unwrap();
+				let session = &sessions[session_index];
+				// requester access is checked by the message processor in real cluster
+				if let Some((key_id, requester)) = message.key_access_request() {
+					if let Ok(requester_address) = requester.address(&key_id) {
+						session.set_key_access_check_result(requester_address, acl_storages[session_index].check(requester_address, &key_id));
+					}
+				}
 				match session.on_message(&from, &message) {
 					Ok(_) => {
 						if let Some(qmessage) = queue.pop_front() {
@@ -1008,9 +1024,9 @@ mod tests {
 					hash: Default::default(),
 					id_numbers: nodes,
 					secret_share: Random.generate().secret().clone(),
+					public_shares: Default::default(),
 				}],
 			}),
-			acl_storage: Arc::new(InMemoryPermissiveAclStorage::default()),
 			cluster: Arc::new(DummyCluster::new(self_node_id.clone())),
 			nonce: 0,
 		};
@@ -1034,7 +1050,6 @@ mod tests {
 			},
 			access_key: Random.generate().secret().clone(),
 			key_share: None,
-			acl_storage: Arc::new(InMemoryPermissiveAclStorage::default()),
 			cluster: Arc::new(DummyCluster::new(self_node_id.clone())),
 			nonce: 0,
 		}, Some(Requester::Signature(
@@ -1068,9 +1083,9 @@ mod tests {
 					hash: Default::default(),
 					id_numbers: nodes,
 					secret_share: Random.generate().secret().clone(),
+					public_shares: Default::default(),
 				}],
 			}),
-			acl_storage: Arc::new(InMemoryPermissiveAclStorage::default()),
 			cluster: Arc::new(DummyCluster::new(self_node_id.clone())),
 			nonce: 0,
 		}, Some(Requester::Signature(
@@ -1106,13 +1121,15 @@ mod tests {
 	#[test]
 	fn fails_to_partial_decrypt_if_requested_by_slave() {
 		let (_, _, _, sessions) = prepare_decryption_sessions();
+		let requester = Random.generate();
+		sessions[1].set_key_access_check_result(public_to_address(requester.public()), Ok(true));
 		assert_eq!(sessions[1].on_consensus_message(sessions[0].node(), &message::DecryptionConsensusMessage {
 				session: SessionId::from(DUMMY_SESSION_ID).into(),
 				sub_session: sessions[0].access_key().clone().into(),
 				session_nonce: 0,
 				origin: None,
 				message: message::ConsensusMessage::InitializeConsensusSession(message::InitializeConsensusSession {
-					requester: Requester::Signature(parity_crypto::publickey::sign(Random.generate().secret(),
+					requester: Requester::Signature(parity_crypto::publickey::sign(requester.secret(),
 						&SessionId::from(DUMMY_SESSION_ID)).unwrap()).into(),
 					version: Default::default(),
 				}),
@@ -1131,13 +1148,15 @@ mod tests {
 	#[test]
 	fn fails_to_partial_decrypt_if_wrong_number_of_nodes_participating() {
 		let (_, _, _, sessions) = prepare_decryption_sessions();
+		let requester = Random.generate();
+		sessions[1].set_key_access_check_result(public_to_address(requester.public()), Ok(true));
 		assert_eq!(sessions[1].on_consensus_message(sessions[0].node(), &message::DecryptionConsensusMessage {
 				session: SessionId::from(DUMMY_SESSION_ID).into(),
 				sub_session: sessions[0].access_key().clone().into(),
 				session_nonce: 0,
 				origin: None,
 				message: message::ConsensusMessage::InitializeConsensusSession(message::InitializeConsensusSession {
-					requester: Requester::Signature(parity_crypto::publickey::sign(Random.generate().secret(),
+					requester: Requester::Signature(parity_crypto::publickey::sign(requester.secret(),
 						&SessionId::from(DUMMY_SESSION_ID)).unwrap()).into(),
 					version: Default::default(),
 				}),
@@ -1168,12 +1187,12 @@ mod tests {
 
 	#[test]
 	fn fails_to_accept_partial_decrypt_twice() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
 		let mut pd_from = None;
 		let mut pd_msg = None;
-		do_messages_exchange_until(&clusters, &sessions, |from, _, msg| match msg {
+		do_messages_exchange_until(&clusters, &acl_storages, &sessions, |from, _, msg| match msg {
 			&Message::Decryption(DecryptionMessage::PartialDecryption(ref msg)) => {
 				pd_from = Some(from.clone());
 				pd_msg = Some(msg.clone());
@@ -1217,7 +1236,7 @@ mod tests {
 		acl_storages[1].forbid(public_to_address(key_pair.public()), SessionId::from(DUMMY_SESSION_ID));
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange_until(&clusters, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
+		do_messages_exchange_until(&clusters, &acl_storages, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
 
 		// 1st node disconnects => ignore this
 		sessions[0].on_node_timeout(sessions[1].node());
@@ -1226,10 +1245,10 @@ mod tests {
 
 	#[test]
 	fn session_does_not_fail_if_requested_node_disconnects() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange_until(&clusters, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
+		do_messages_exchange_until(&clusters, &acl_storages, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
 
 		// 1 node disconnects => we still can recover secret
 		sessions[0].on_node_timeout(sessions[1].node());
@@ -1242,10 +1261,10 @@ mod tests {
 
 	#[test]
 	fn session_does_not_fail_if_node_with_shadow_point_disconnects() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange_until(&clusters, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults
+		do_messages_exchange_until(&clusters, &acl_storages, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults
 			&& sessions[0].data.lock().consensus_session.computation_job().responses().len() == 2).unwrap();
 
 		// disconnects from the node which has already sent us its own shadow point
@@ -1259,10 +1278,10 @@ mod tests {
 
 	#[test]
 	fn session_restarts_if_confirmed_node_disconnects() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange_until(&clusters, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
+		do_messages_exchange_until(&clusters, &acl_storages, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
 
 		// disconnects from the node which has already confirmed its participation
 		let disconnected = sessions[0].data.lock().consensus_session.computation_job().requests().iter().cloned().nth(0).unwrap();
@@ -1274,10 +1293,10 @@ mod tests {
 
 	#[test]
 	fn session_does_not_fail_if_non_master_node_disconnects_from_non_master_node() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange_until(&clusters, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
+		do_messages_exchange_until(&clusters, &acl_storages, &sessions, |_, _, _| sessions[0].state() == ConsensusSessionState::WaitingForPartialResults).unwrap();
 
 		// disconnects from the node which has already confirmed its participation
 		sessions[1].on_node_timeout(sessions[2].node());
@@ -1287,12 +1306,12 @@ mod tests {
 
 	#[test]
 	fn complete_dec_session() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 
 		// now let's try to do a decryption
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// now check that:
 		// 1) 5 of 5 sessions are in Finished state
@@ -1309,12 +1328,12 @@ mod tests {
 
 	#[test]
 	fn complete_shadow_dec_session() {
-		let (key_pair, clusters, _, sessions) = prepare_decryption_sessions();
+		let (key_pair, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 
 		// now let's try to do a decryption
 		sessions[0].initialize(Default::default(), Default::default(), true, false).unwrap();
 
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// now check that:
 		// 1) 5 of 5 sessions are in Finished state
@@ -1351,7 +1370,7 @@ mod tests {
 		acl_storages[1].forbid(public_to_address(key_pair.public()), document);
 		acl_storages[2].forbid(public_to_address(key_pair.public()), document);
 
-		assert_eq!(do_messages_exchange(&clusters, &sessions).unwrap_err(), Error::ConsensusUnreachable);
+		assert_eq!(do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap_err(), Error::ConsensusUnreachable);
 
 		// check that 3 nodes have failed state
 		assert_eq!(sessions[0].state(), ConsensusSessionState::Failed);
@@ -1365,11 +1384,12 @@ mod tests {
 		// we need 4 out of 5 nodes to agree to do a decryption
 		// let's say that 1 of these nodes (master) is disagree
 		acl_storages[0].forbid(public_to_address(key_pair.public()), SessionId::from(DUMMY_SESSION_ID));
+		check_key_access(&acl_storages[0], &sessions[0], &SessionId::from(DUMMY_SESSION_ID), key_pair.public());
 
 		// now let's try to do a decryption
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
 
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// now check that:
 		// 1) 4 of 5 sessions are in Finished state
@@ -1397,7 +1417,7 @@ mod tests {
 
 	#[test]
 	fn decryption_works_when_delegated_to_other_node() {
-		let (_, clusters, _, mut sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, mut sessions) = prepare_decryption_sessions();
 
 		// let's say node1 doesn't have a share && delegates decryption request to node0
 		// initially session is created on node1 => node1 is master for itself, but for other nodes node0 is still master
@@ -1408,7 +1428,7 @@ mod tests {
 
 		// now let's try to do a decryption
 		sessions[1].delegate(sessions[0].core.meta.self_node_id.clone(), Default::default(), Default::default(), false, false).unwrap();
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// now check that:
 		// 1) 4 of 5 sessions are in Finished state
@@ -1423,7 +1443,7 @@ mod tests {
 
 	#[test]
 	fn decryption_works_when_share_owners_are_isolated() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 
 		// we need 4 out of 5 nodes to agree to do a decryption
 		// let's say that 1 of these nodes (master) is isolated
@@ -1434,7 +1454,7 @@ mod tests {
 
 		// now let's try to do a decryption
 		sessions[0].initialize(Default::default(), Default::default(), false, false).unwrap();
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		assert_eq!(sessions[0].decrypted_secret().unwrap().unwrap(), EncryptedDocumentKeyShadow {
 			decrypted_secret: H512::from_str(SECRET_PLAIN).unwrap(),
@@ -1445,9 +1465,9 @@ mod tests {
 
 	#[test]
 	fn decryption_result_restored_on_all_nodes_if_broadcast_session_is_completed() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), false, true).unwrap();
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// decryption result must be the same and available on 4 nodes
 		let result = sessions[0].decrypted_secret();
@@ -1463,9 +1483,9 @@ mod tests {
 
 	#[test]
 	fn decryption_shadows_restored_on_all_nodes_if_shadow_broadcast_session_is_completed() {
-		let (key_pair, clusters, _, sessions) = prepare_decryption_sessions();
+		let (key_pair, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Default::default(), Default::default(), true, true).unwrap();
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// decryption shadows must be the same and available on 4 nodes
 		let broadcast_shadows = sessions[0].broadcast_shadows();
@@ -1487,9 +1507,9 @@ mod tests {
 
 	#[test]
 	fn decryption_session_origin_is_known_to_all_initialized_nodes() {
-		let (_, clusters, _, sessions) = prepare_decryption_sessions();
+		let (_, clusters, acl_storages, sessions) = prepare_decryption_sessions();
 		sessions[0].initialize(Some(Address::from_low_u64_be(1)), Default::default(), true, true).unwrap();
-		do_messages_exchange(&clusters, &sessions).unwrap();
+		do_messages_exchange(&clusters, &acl_storages, &sessions).unwrap();
 
 		// all session must have origin set
 		assert_eq!(5, sessions.iter().filter(|s| s.origin() == Some(Address::from_low_u64_be(1))).count());
@@ -23,11 +23,11 @@ use parking_lot::Mutex;
 use ethereum_types::Address;
 use parity_crypto::publickey::Public;
 use primitives::key_storage::{KeyStorage, KeyShare};
-use crate::key_server_cluster::{Error, NodeId, SessionId, Requester, ServerKeyId};
+use crate::key_server_cluster::{Error, NodeId, SessionId, Requester};
 use crate::key_server_cluster::cluster::Cluster;
 use crate::key_server_cluster::cluster_sessions::{ClusterSession, CompletionSignal};
 use crate::key_server_cluster::message::{Message, EncryptionMessage, InitializeEncryptionSession,
-	ConfirmEncryptionInitialization, EncryptionSessionError};
+	ConfirmEncryptionInitialization, CommitEncryptionSession, ConfirmEncryptionCommit, EncryptionSessionError};
 
 /// Encryption (distributed key generation) session.
 /// Based on "ECDKG: A Distributed Key Generation Protocol Based on Elliptic Curve Discrete Logarithm" paper:
@@ -35,8 +35,11 @@ use crate::key_server_cluster::message::{Message, EncryptionMessage, InitializeE
 /// Brief overview:
 /// 1) initialization: master node (which has received request for storing the secret) initializes the session on all other nodes
 /// 2) master node sends common_point + encrypted_point to all other nodes
-/// 3) common_point + encrypted_point are saved on all nodes
-/// 4) in case of error, previous values are restored
+/// 3) every node checks common_point + encrypted_point and durably stages (but not yet commits) them, then confirms initialization
+/// 4) when all nodes have confirmed initialization, master node commits common_point + encrypted_point and asks other nodes to commit
+/// 5) common_point + encrypted_point are committed on all other nodes && every node confirms commit
+/// 6) session is completed on master node when all nodes have confirmed commit
+/// 7) in case of error before commit, staged values are dropped and previous values are left untouched
 pub struct SessionImpl {
 	/// Unique session id.
 	id: SessionId,
@@ -77,6 +80,10 @@ pub struct SessionParams {
 struct SessionData {
 	/// Current state of the session.
 	state: SessionState,
+	/// Master node id.
+	master: Option<NodeId>,
+	/// True if encrypted data is staged in the key storage && is waiting for commit.
+	is_prepared: bool,
 	/// Nodes-specific data.
 	nodes: BTreeMap<NodeId, NodeData>,
 	/// Encryption session result.
@@ -89,6 +96,10 @@ struct NodeData {
 	// === Values, filled during initialization phase ===
 	/// Flags marking that node has confirmed session initialization.
 	pub initialization_confirmed: bool,
+
+	// === Values, filled during commit phase ===
+	/// Flags marking that node has confirmed commit.
+	pub commit_confirmed: bool,
 }
 
 /// Encryption (distributed key generation) session state.
@@ -99,6 +110,10 @@ pub enum SessionState {
 	WaitingForInitialization,
 	/// Master node waits for every other node to confirm initialization.
 	WaitingForInitializationConfirm,
+	/// Slave node waits for master node to commit prepared data.
+	WaitingForCommit,
+	/// Master node has committed data and waits for every other node to confirm commit.
+	WaitingForCommitConfirm,
 
 	// === Final states of the session ===
 	/// Encryption data is saved.
@@ -123,6 +138,8 @@ impl SessionImpl {
 			completed,
 			data: Mutex::new(SessionData {
 				state: SessionState::WaitingForInitialization,
+				master: None,
+				is_prepared: false,
 				nodes: BTreeMap::new(),
 				result: None,
 			}),
@@ -143,22 +160,22 @@ impl SessionImpl {
 			return Err(Error::InvalidStateForRequest);
 		}
 
+		// TODO [Sec]: id signature is not enough here, as it was already used in key generation
+		// prepare encryption data
+		if let Some(encrypted_data) = self.encrypted_data.clone() {
+			let requester_address = requester.address(&self.id)?;
+			self.prepare(&mut *data, prepare_encrypted_data(encrypted_data, requester_address,
+				common_point.clone(), encrypted_point.clone())?)?;
+		}
+
 		// update state
 		data.state = SessionState::WaitingForInitializationConfirm;
+		data.master = Some(self.node().clone());
 		data.nodes.extend(self.cluster.nodes().into_iter().map(|n| (n, NodeData {
 			initialization_confirmed: &n == self.node(),
+			commit_confirmed: &n == self.node(),
 		})));
 
-		// TODO [Sec]: id signature is not enough here, as it was already used in key generation
-		// TODO [Reliability]: there could be situation when some nodes have failed to store encrypted data
-		// => potential problems during restore. some confirmation step is needed (2pc)?
-		// save encryption data
-		if let Some(encrypted_data) = self.encrypted_data.clone() {
-			let requester_address = requester.address(&self.id)?;
-			update_encrypted_data(&self.key_storage, self.id.clone(),
-				encrypted_data, requester_address, common_point.clone(), encrypted_point.clone())?;
-		}
-
 		// start initialization
 		if data.nodes.len() > 1 {
 			self.cluster.broadcast(Message::Encryption(EncryptionMessage::InitializeEncryptionSession(InitializeEncryptionSession {
@@ -169,9 +186,8 @@ impl SessionImpl {
 				encrypted_point: encrypted_point.into(),
 			})))
 		} else {
-			data.state = SessionState::Finished;
-			data.result = Some(Ok(()));
-			self.completed.send(Ok(()));
+			self.commit(&mut *data)?;
+			self.complete(&mut *data);
 
 			Ok(())
 		}
@@ -189,16 +205,17 @@ impl SessionImpl {
 			return Err(Error::InvalidStateForRequest);
 		}
 
-		// check that the requester is the author of the encrypted data
+		// check that the requester is the author of the encrypted data and prepare encryption data
 		if let Some(encrypted_data) = self.encrypted_data.clone() {
 			let requester: Requester = message.requester.clone().into();
 			let requester_address = requester.address(&self.id)?;
-			update_encrypted_data(&self.key_storage, self.id.clone(),
-				encrypted_data, requester_address, message.common_point.clone().into(), message.encrypted_point.clone().into())?;
+			self.prepare(&mut *data, prepare_encrypted_data(encrypted_data, requester_address,
+				message.common_point.clone().into(), message.encrypted_point.clone().into())?)?;
 		}
 
 		// update state
-		data.state = SessionState::Finished;
+		data.state = SessionState::WaitingForCommit;
+		data.master = Some(sender.clone());
 
 		// send confirmation back to master node
 		self.cluster.send(&sender, Message::Encryption(EncryptionMessage::ConfirmEncryptionInitialization(ConfirmEncryptionInitialization {
@@ -215,6 +232,11 @@ impl SessionImpl {
 		let mut data = self.data.lock();
 		debug_assert!(data.nodes.contains_key(&sender));
 
+		// check state
+		if data.state != SessionState::WaitingForInitializationConfirm {
+			return Err(Error::InvalidStateForRequest);
+		}
+
 		// check if all nodes have confirmed initialization
 		data.nodes.get_mut(&sender)
 			.expect("message is received from cluster; nodes contains all cluster nodes; qed")
@@ -223,12 +245,116 @@ impl SessionImpl {
 			return Ok(());
 		}
 
-		// update state
+		// all nodes have prepared encryption data => commit it on this node and ask other nodes to do the same
+		self.commit(&mut *data)?;
+		data.state = SessionState::WaitingForCommitConfirm;
+
+		self.cluster.broadcast(Message::Encryption(EncryptionMessage::CommitEncryptionSession(CommitEncryptionSession {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+		})))
+	}
+
+	/// When commit message is received.
+	pub fn on_commit(&self, sender: NodeId, message: &CommitEncryptionSession) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForCommit {
+			return Err(Error::InvalidStateForRequest);
+		}
+		if data.master.as_ref() != Some(&sender) {
+			return Err(Error::InvalidMessage);
+		}
+
+		// commit prepared data && confirm commit
+		self.commit(&mut *data)?;
+		self.complete(&mut *data);
+
+		self.cluster.send(&sender, Message::Encryption(EncryptionMessage::ConfirmEncryptionCommit(ConfirmEncryptionCommit {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+		})))
+	}
+
+	/// When commit confirmation message is received.
+	pub fn on_confirm_commit(&self, sender: NodeId, message: &ConfirmEncryptionCommit) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForCommitConfirm {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// check if all nodes have confirmed commit
+		data.nodes.get_mut(&sender).ok_or(Error::InvalidMessage)?
+			.commit_confirmed = true;
+		if !data.nodes.values().all(|n| n.commit_confirmed) {
+			return Ok(());
+		}
+
+		self.complete(&mut *data);
+		Ok(())
+	}
+
+	/// Durably stage encryption data in the key storage.
+	fn prepare(&self, data: &mut SessionData, prepared_data: KeyShare) -> Result<(), Error> {
+		self.key_storage.prepare(self.id.clone(), prepared_data)?;
+		data.is_prepared = true;
+		Ok(())
+	}
+
+	/// Commit prepared encryption data.
+	fn commit(&self, data: &mut SessionData) -> Result<(), Error> {
+		if data.is_prepared {
+			self.key_storage.commit_prepared(&self.id)?;
+			data.is_prepared = false;
+		}
+
+		Ok(())
+	}
+
+	/// Complete session successfully.
+	fn complete(&self, data: &mut SessionData) {
 		data.state = SessionState::Finished;
 		data.result = Some(Ok(()));
 		self.completed.send(Ok(()));
+	}
 
-		Ok(())
+	/// Drop prepared encryption data && mark session as failed.
+	fn rollback(&self, data: &mut SessionData, error: Error) {
+		// data is already saved or dropped => nothing to rollback
+		if data.state == SessionState::Finished || data.state == SessionState::Failed {
+			return;
+		}
+
+		// master node is responsible for asking other nodes to drop prepared data
+		if data.master.as_ref() == Some(self.node()) && data.nodes.len() > 1 {
+			// do not bother processing send error, as we already processing error
+			let _ = self.cluster.broadcast(Message::Encryption(EncryptionMessage::EncryptionSessionError(EncryptionSessionError {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				error: error.clone().into(),
+			})));
+		}
+
+		// drop staged data. If master node has already committed data, it can't be rolled back, but
+		// session still fails, because some nodes have not confirmed that they have committed data
+		if data.is_prepared {
+			// do not bother processing storage error, as we already processing error
+			let _ = self.key_storage.discard_prepared(&self.id);
+			data.is_prepared = false;
+		}
+
+		data.state = SessionState::Failed;
+		data.result = Some(Err(error.clone()));
+		self.completed.send(Err(error));
 	}
 }
 
@@ -256,9 +382,7 @@ impl ClusterSession for SessionImpl {
 
 		warn!("{}: encryption session failed because {} connection has timeouted", self.node(), node);
 
-		data.state = SessionState::Failed;
-		data.result = Some(Err(Error::NodeDisconnected));
-		self.completed.send(Err(Error::NodeDisconnected));
+		self.rollback(&mut *data, Error::NodeDisconnected);
 	}
 
 	fn on_session_timeout(&self) {
@@ -266,15 +390,15 @@ impl ClusterSession for SessionImpl {
 
 		warn!("{}: encryption session failed with timeout", self.node());
 
-		data.state = SessionState::Failed;
-		data.result = Some(Err(Error::NodeDisconnected));
-		self.completed.send(Err(Error::NodeDisconnected));
+		self.rollback(&mut *data, Error::NodeDisconnected);
 	}
 
 	fn on_session_error(&self, node: &NodeId, error: Error) {
+		let mut data = self.data.lock();
+
 		// error in encryption session is considered fatal
-		// => broadcast error if error occured on this node
-		if *node == self.self_node_id {
+		// => broadcast error if error occured on this node (master node broadcasts error on rollback)
+		if *node == self.self_node_id && data.master.as_ref() != Some(self.node()) {
 			// do not bother processing send error, as we already processing error
 			let _ = self.cluster.broadcast(Message::Encryption(EncryptionMessage::EncryptionSessionError(EncryptionSessionError {
 				session: self.id.clone().into(),
@@ -283,13 +407,9 @@ impl ClusterSession for SessionImpl {
 			})));
 		}
 
-		let mut data = self.data.lock();
-
 		warn!("{}: encryption session failed with error: {} from {}", self.node(), error, node);
 
-		data.state = SessionState::Failed;
-		data.result = Some(Err(error.clone()));
-		self.completed.send(Err(error));
+		self.rollback(&mut *data, error);
 	}
 
 	fn on_message(&self, sender: &NodeId, message: &Message) -> Result<(), Error> {
@@ -303,6 +423,10 @@ impl ClusterSession for SessionImpl {
 					self.on_initialize_session(sender.clone(), message),
 				&EncryptionMessage::ConfirmEncryptionInitialization(ref message) =>
 					self.on_confirm_initialization(sender.clone(), message),
+				&EncryptionMessage::CommitEncryptionSession(ref message) =>
+					self.on_commit(sender.clone(), message),
+				&EncryptionMessage::ConfirmEncryptionCommit(ref message) =>
+					self.on_confirm_commit(sender.clone(), message),
 				&EncryptionMessage::EncryptionSessionError(ref message) => {
 					self.on_session_error(sender, message.error.clone());
 					Ok(())
@@ -331,15 +455,194 @@ pub fn check_encrypted_data(key_share: Option<&KeyShare>) -> Result<(), Error> {
 	Ok(())
 }
 
-/// Update key share with encrypted document key.
-pub fn update_encrypted_data(key_storage: &Arc<dyn KeyStorage>, key_id: ServerKeyId, mut key_share: KeyShare, author: Address, common_point: Public, encrypted_point: Public) -> Result<(), Error> {
+/// Prepare key share with encrypted document key for saving.
+pub fn prepare_encrypted_data(mut key_share: KeyShare, author: Address, common_point: Public, encrypted_point: Public) -> Result<KeyShare, Error> {
 	// author must be the same
 	if key_share.author != author {
 		return Err(Error::AccessDenied);
 	}
 
-	// save encryption data
+	// prepare encryption data
 	key_share.common_point = Some(common_point);
 	key_share.encrypted_point = Some(encrypted_point);
-	key_storage.update(key_id, key_share)
+	Ok(key_share)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use parity_crypto::publickey::{Random, Generator};
+	use primitives::key_storage::KeyStorage;
+	use crate::key_server_cluster::{Error, NodeId, SessionId, ServerKeyId, Requester};
+	use crate::key_server_cluster::cluster::tests::MessageLoop as ClusterMessageLoop;
+	use crate::key_server_cluster::cluster_sessions::ClusterSession;
+	use crate::key_server_cluster::generation_session::tests::MessageLoop as GenerationMessageLoop;
+	use super::{SessionImpl, SessionState};
+
+	const DUMMY_SESSION_ID: [u8; 32] = [1u8; 32];
+
+	struct MessageLoop(ClusterMessageLoop);
+
+	impl MessageLoop {
+		pub fn new(num_nodes: usize) -> Self {
+			let ml = GenerationMessageLoop::new(num_nodes).init(0).unwrap();
+			ml.0.loop_until(|| ml.0.is_empty()); // complete generation session
+			MessageLoop(ml.0)
+		}
+
+		pub fn init(self) -> Result<Self, Error> {
+			self.0.cluster(0).client().new_encryption_session(
+				SessionId::from(DUMMY_SESSION_ID),
+				Requester::Address(Default::default()),
+				Random.generate().public().clone(),
+				Random.generate().public().clone(),
+			).map(|_| self)
+		}
+
+		pub fn session_at(&self, idx: usize) -> Arc<SessionImpl> {
+			self.0.sessions(idx).encryption_sessions.first().unwrap()
+		}
+
+		pub fn node(&self, idx: usize) -> NodeId {
+			self.0.node(idx)
+		}
+
+		pub fn is_document_key_stored(&self, idx: usize) -> bool {
+			let key_share = self.0.key_storage(idx).get(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap().unwrap();
+			key_share.common_point.is_some() && key_share.encrypted_point.is_some()
+		}
+
+		pub fn loop_until_empty(&self) {
+			self.0.loop_until(|| self.0.is_empty());
+		}
+
+		pub fn process_next_message(&self) {
+			assert!(self.0.take_and_process_message());
+		}
+	}
+
+	#[test]
+	fn encryption_session_saves_data_on_all_nodes() {
+		let ml = MessageLoop::new(3).init().unwrap();
+		ml.loop_until_empty();
+
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Ok(())));
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Finished);
+			assert!(ml.is_document_key_stored(idx));
+		}
+	}
+
+	#[test]
+	fn encryption_session_saves_data_only_when_all_nodes_have_confirmed() {
+		let ml = MessageLoop::new(3).init().unwrap();
+
+		// deliver initialization messages to node1 && node2 and confirmation from node1 to master
+		ml.process_next_message();
+		ml.process_next_message();
+		ml.process_next_message();
+
+		// data is prepared, but not yet saved on any node
+		assert_eq!(ml.session_at(1).data.lock().state, SessionState::WaitingForCommit);
+		assert_eq!(ml.session_at(2).data.lock().state, SessionState::WaitingForCommit);
+		for idx in 0..3 {
+			assert!(!ml.is_document_key_stored(idx));
+		}
+
+		// deliver confirmation from node2 && commit messages
+		ml.loop_until_empty();
+		for idx in 0..3 {
+			assert!(ml.is_document_key_stored(idx));
+		}
+	}
+
+	#[test]
+	fn encryption_session_rolls_back_when_node_disconnects_before_confirmation() {
+		let ml = MessageLoop::new(3).init().unwrap();
+
+		// deliver initialization messages to node1 && node2 and confirmation from node1 to master
+		ml.process_next_message();
+		ml.process_next_message();
+		ml.process_next_message();
+
+		// node2 disconnects before its confirmation is delivered to master
+		ml.0.take_message().unwrap();
+		ml.session_at(0).on_node_timeout(&ml.node(2));
+		ml.loop_until_empty();
+
+		// => prepared data is dropped on all nodes
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Err(Error::NodeDisconnected)));
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Failed);
+			assert!(!ml.session_at(idx).data.lock().is_prepared);
+			assert!(!ml.is_document_key_stored(idx));
+		}
+	}
+
+	#[test]
+	fn encryption_session_rolls_back_when_node_fails_to_prepare_data() {
+		let ml = MessageLoop::new(3);
+
+		// document key is already stored on node2 => it fails to prepare data
+		let mut key_share = ml.0.key_storage(2).get(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap().unwrap();
+		key_share.common_point = Some(Random.generate().public().clone());
+		key_share.encrypted_point = Some(Random.generate().public().clone());
+		ml.0.key_storage(2).update(ServerKeyId::from(DUMMY_SESSION_ID), key_share).unwrap();
+
+		let ml = ml.init().unwrap();
+		ml.loop_until_empty();
+
+		// => prepared data is dropped on all other nodes
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Err(Error::DocumentKeyAlreadyStored)));
+		for idx in 0..2 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Failed);
+			assert!(!ml.is_document_key_stored(idx));
+		}
+	}
+
+	#[test]
+	fn encryption_session_completes_only_when_all_nodes_have_confirmed_commit() {
+		let ml = MessageLoop::new(3).init().unwrap();
+
+		// deliver initialization messages to node1 && node2 and confirmations to master
+		for _ in 0..4 {
+			ml.process_next_message();
+		}
+
+		// data is committed on master, but session is not yet completed
+		assert_eq!(ml.session_at(0).data.lock().state, SessionState::WaitingForCommitConfirm);
+		assert_eq!(ml.session_at(0).data.lock().result, None);
+		assert!(ml.is_document_key_stored(0));
+		assert!(!ml.is_document_key_stored(1));
+		assert!(!ml.is_document_key_stored(2));
+
+		// deliver commit messages && commit confirmations
+		ml.loop_until_empty();
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Ok(())));
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Finished);
+			assert!(ml.is_document_key_stored(idx));
+		}
+	}
+
+	#[test]
+	fn encryption_session_fails_when_node_disconnects_before_commit_confirmation() {
+		let ml = MessageLoop::new(3).init().unwrap();
+
+		// deliver initialization messages to node1 && node2 and confirmations to master
+		for _ in 0..4 {
+			ml.process_next_message();
+		}
+
+		// node1 disconnects before commit message is delivered
+		ml.0.take_message().unwrap();
+		ml.session_at(0).on_node_timeout(&ml.node(1));
+		ml.loop_until_empty();
+
+		// => data is committed on master && node2, but session has failed
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Err(Error::NodeDisconnected)));
+		assert!(ml.is_document_key_stored(0));
+		assert!(!ml.is_document_key_stored(1));
+		assert!(ml.is_document_key_stored(2));
+	}
 }
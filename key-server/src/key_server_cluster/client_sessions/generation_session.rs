@@ -26,6 +26,7 @@ use primitives::key_storage::{KeyStorage, KeyShare, KeyShareVersion};
 use crate::key_server_cluster::{Error, NodeId, SessionId};
 use crate::key_server_cluster::math;
 use crate::key_server_cluster::cluster::Cluster;
+use crate::key_server_cluster::io::VERIFIABLE_SHARES_HEADER_VERSION;
 use crate::key_server_cluster::cluster_sessions::{ClusterSession, CompletionSignal};
 use crate::key_server_cluster::random_point_generation_session::{SessionImpl as RandomPointGenerationSession, SessionTransport as RandomPointGenerationSessionTransport};
 use crate::key_server_cluster::message::{Message, GenerationMessage, InitializeSession, ConfirmInitialization,
@@ -139,6 +140,8 @@ struct NodeData {
 	// === Values, filled during KG phase ===
 	/// Public share, which has been received from this node.
 	pub public_share: Option<Public>,
+	/// Public share proof, which has been received from this node.
+	pub share_proof: Option<Vec<Public>>,
 
 	// === Values, filled during completion phase ===
 	/// Flags marking that node has confirmed key joint public compution.
@@ -300,6 +303,26 @@ impl SessionImpl {
 		self.data.lock().joint_public_and_secret.clone()
 	}
 
+	/// Get public shares (publics of secret coefficients) of all nodes (if key is generated).
+	pub fn nodes_public_shares(&self) -> Option<BTreeMap<NodeId, Public>> {
+		let data = self.data.lock();
+		match data.joint_public_and_secret {
+			Some(Ok(_)) => data.nodes.iter()
+				.map(|(node, node_data)| node_data.public_share.clone().map(|public_share| (node.clone(), public_share)))
+				.collect(),
+			_ => None,
+		}
+	}
+
+	/// Get publics of secret shares of all nodes (if key is generated).
+	pub fn nodes_public_key_shares(&self) -> Option<BTreeMap<NodeId, Public>> {
+		let data = self.data.lock();
+		match data.joint_public_and_secret {
+			Some(Ok(_)) => compute_public_key_shares(&*data).ok(),
+			_ => None,
+		}
+	}
+
 	/// Start new session initialization. This must be called on master node.
 	pub fn initialize(&self, origin: Option<Address>, author: Address, is_zero: bool, threshold: usize, nodes: InitializationNodes) -> Result<(), Error> {
 		check_cluster_nodes(self.node(), &nodes.set())?;
@@ -594,6 +617,7 @@ impl SessionImpl {
 			}
 
 			node_data.public_share = Some(message.public_share_proof[0].clone().into());
+			node_data.share_proof = Some(message.public_share_proof.iter().cloned().map(Into::into).collect());
 		}
 
 		// if there's also nodes, which has not sent us their public shares - do nothing
@@ -683,6 +707,7 @@ impl SessionImpl {
 				versions: vec![KeyShareVersion::new(
 					data.nodes.iter().map(|(node_id, node_data)| (node_id.clone(), node_data.id_number.clone())).collect(),
 					data.secret_share.as_ref().expect("secret_share is filled in KG phase; we are at the end of KG phase; qed").clone(),
+					compute_public_key_shares(&data)?,
 				)],
 			};
 
@@ -843,6 +868,7 @@ impl SessionImpl {
 		data.publics_footprint = Some(publics_footprint);
 		let self_node = data.nodes.get_mut(self.node()).expect("node is always qualified by himself; qed");
 		self_node.public_share = Some(self_public_share.clone());
+		self_node.share_proof = Some(public_share_proof.clone());
 
 		// broadcast self public key share
 		self.cluster.broadcast(Message::Generation(GenerationMessage::PublicKeyShare(PublicKeyShare {
@@ -866,18 +892,27 @@ impl SessionImpl {
 			Default::default()
 		};
 
-		// compute joint public footprint
+		// compute joint public footprint (share proofs are included, because they're used to compute public key shares)
+		// nodes that don't support verifiable shares are only using public shares to compute the footprint
+		let nodes = data.nodes.keys().cloned().collect();
+		let include_share_proofs = self.cluster.protocol_version(&nodes) >= VERIFIABLE_SHARES_HEADER_VERSION;
 		let joint_public_footprint = math::compute_publics_footprint(
 			data.nodes
 				.iter()
 				.map(|(id, node_data)| (
 					id.clone(),
-					vec![
-						node_data
-							.public_share
+					match include_share_proofs {
+						true => node_data
+							.share_proof
 							.clone()
 							.expect("keys received on KD phase; KG phase follows KD phase; qed"),
-						]
+						false => vec![
+							node_data
+								.public_share
+								.clone()
+								.expect("keys received on KD phase; KG phase follows KD phase; qed"),
+						],
+					},
 				))
 				.collect(),
 		)?;
@@ -913,6 +948,7 @@ impl SessionImpl {
 			versions: vec![KeyShareVersion::new(
 				data.nodes.iter().map(|(node_id, node_data)| (node_id.clone(), node_data.id_number.clone())).collect(),
 				secret_share.clone(),
+				compute_public_key_shares(&data)?,
 			)],
 		};
 
@@ -1028,6 +1064,7 @@ impl NodeData {
 			secret2: None,
 			publics: None,
 			public_share: None,
+			share_proof: None,
 			joint_computed: false,
 			completion_confirmed: false,
 		}
@@ -1054,6 +1091,26 @@ fn check_threshold(threshold: usize, nodes: &BTreeSet<NodeId>) -> Result<(), Err
 	Ok(())
 }
 
+/// Compute public key shares of all nodes, using share proofs, received during KG phase.
+fn compute_public_key_shares(data: &SessionData) -> Result<BTreeMap<NodeId, Public>, Error> {
+	// there are no share proofs when zero secret is generated
+	let is_zero = data.is_zero.expect("is_zero is filled in initialization phase; KG phase follows initialization phase; qed");
+	if is_zero {
+		return Ok(BTreeMap::new());
+	}
+
+	let share_proofs = data.nodes.values()
+		.map(|node_data| node_data.share_proof.as_ref()
+			.expect("share proofs are received in KG phase; we are at the end of KG phase; qed"))
+		.collect::<Vec<_>>();
+	data.nodes.iter()
+		.map(|(node, node_data)| math::compute_public_key_share(
+			&node_data.id_number,
+			share_proofs.iter().map(|share_proof| &share_proof[..]),
+		).map(|public_key_share| (node.clone(), public_key_share)))
+		.collect()
+}
+
 impl RandomPointGenerationSessionTransport for DerivedPointGenerationTransport {
 	fn send(&self, node: &NodeId, message: RandomPointGenerationMessage) -> Result<(), Error> {
 		self.cluster.send(node, Message::Generation(GenerationMessage::DerivedPointGeneration(
@@ -1076,6 +1133,7 @@ pub mod tests {
 	use crate::key_server_cluster::message::{self, JointPublicKey, KeysDissemination, PublicKeyShare, ConfirmInitialization};
 	use crate::key_server_cluster::cluster::tests::{MessageLoop as ClusterMessageLoop, make_clusters_and_preserve_sessions};
 	use crate::key_server_cluster::cluster_sessions::ClusterSession;
+	use crate::key_server_cluster::io::SESSION_KEYS_HEADER_VERSION;
 	use crate::key_server_cluster::generation_session::{SessionImpl, SessionState};
 	use crate::key_server_cluster::math;
 	use crate::key_server_cluster::math::tests::do_encryption_and_decryption;
@@ -1528,6 +1586,39 @@ pub mod tests {
 		}
 	}
 
+	#[test]
+	fn public_key_shares_of_all_nodes_are_stored() {
+		let ml = MessageLoop::new(3).init(1).unwrap();
+		ml.0.loop_until(|| ml.0.is_empty());
+
+		let server_key_id = ServerKeyId::from([1u8; 32]);
+		let secret_shares = ml.nodes_secret_shares();
+		for i in 0..3 {
+			let key_share = ml.0.key_storage(i).get(&server_key_id).unwrap().unwrap();
+			let public_shares = &key_share.versions[0].public_shares;
+			assert_eq!(public_shares.len(), 3);
+			for j in 0..3 {
+				assert_eq!(public_shares[&ml.0.node(j)], math::compute_public_share(&secret_shares[j]).unwrap());
+			}
+		}
+	}
+
+	#[test]
+	fn share_proofs_are_not_included_in_footprint_when_nodes_do_not_support_verifiable_shares() {
+		let ml = MessageLoop::new(3);
+		ml.0.set_header_version(SESSION_KEYS_HEADER_VERSION);
+		let ml = ml.init(1).unwrap();
+		ml.0.loop_until(|| ml.0.is_empty());
+
+		let session = ml.session_at(0);
+		let data = session.data.lock();
+		let expected_footprint = math::compute_publics_footprint(data.nodes.iter()
+			.map(|(node, node_data)| (node.clone(), vec![node_data.public_share.clone().unwrap()]))
+			.collect()).unwrap();
+		assert_eq!(data.joint_public_footprint, Some(expected_footprint));
+		assert!(data.joint_public_and_secret.clone().unwrap().is_ok());
+	}
+
 	#[test]
 	fn generation_message_fails_when_nonce_is_wrong() {
 		let ml = MessageLoop::new(2).init(0).unwrap();
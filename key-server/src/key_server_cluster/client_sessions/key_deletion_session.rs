@@ -0,0 +1,549 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter, Error as FmtError};
+use std::sync::Arc;
+use futures::Oneshot;
+use log::warn;
+use parking_lot::Mutex;
+use ethereum_types::Address;
+use primitives::key_storage::KeyStorage;
+use primitives::requester::key_deletion_message_hash;
+use crate::key_server_cluster::{Error, NodeId, SessionId, Requester};
+use crate::key_server_cluster::cluster::Cluster;
+use crate::key_server_cluster::cluster_sessions::{ClusterSession, CompletionSignal};
+use crate::key_server_cluster::message::{Message, KeyDeletionMessage, InitializeKeyDeletionSession,
+	ConfirmKeyDeletionInitialization, CommitKeyDeletionSession, KeyDeletionSessionError};
+
+/// Key deletion session.
+/// Removes server key (along with associated document key) from key storages of all cluster nodes
+/// and leaves tombstones, so that key with the same id can't be generated again.
+/// Brief overview:
+/// 1) initialization: master node (which has received request for deleting the key) initializes the session on all other nodes
+/// 2) every node checks that the requester is the author of the key, then confirms initialization
+/// 3) when all nodes have confirmed initialization, master node deletes the key and asks other nodes to commit
+/// 4) key is deleted on all other nodes
+/// 5) in case of error before commit, the key is left untouched
+pub struct SessionImpl {
+	/// Unique session id (same as key id).
+	id: SessionId,
+	/// Public identifier of this node.
+	self_node_id: NodeId,
+	/// Key storage.
+	key_storage: Arc<dyn KeyStorage>,
+	/// Cluster which allows this node to send messages to other nodes in the cluster.
+	cluster: Arc<dyn Cluster>,
+	/// Session nonce.
+	nonce: u64,
+	/// Session completion signal.
+	completed: CompletionSignal<()>,
+	/// Mutable session data.
+	data: Mutex<SessionData>,
+}
+
+/// SessionImpl creation parameters
+pub struct SessionParams {
+	/// SessionImpl identifier.
+	pub id: SessionId,
+	/// Id of node, on which this session is running.
+	pub self_node_id: NodeId,
+	/// Key storage.
+	pub key_storage: Arc<dyn KeyStorage>,
+	/// Cluster
+	pub cluster: Arc<dyn Cluster>,
+	/// Session nonce.
+	pub nonce: u64,
+}
+
+/// Mutable data of key deletion session.
+#[derive(Debug)]
+struct SessionData {
+	/// Current state of the session.
+	state: SessionState,
+	/// Session origin (if any).
+	origin: Option<Address>,
+	/// Master node id.
+	master: Option<NodeId>,
+	/// Nodes-specific data.
+	nodes: BTreeMap<NodeId, NodeData>,
+	/// Key deletion session result.
+	result: Option<Result<(), Error>>,
+}
+
+/// Mutable node-specific data.
+#[derive(Debug, Clone)]
+struct NodeData {
+	/// Flags marking that node has confirmed session initialization.
+	pub initialization_confirmed: bool,
+}
+
+/// Key deletion session state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionState {
+	// === Initialization states ===
+	/// Every node starts in this state.
+	WaitingForInitialization,
+	/// Master node waits for every other node to confirm initialization.
+	WaitingForInitializationConfirm,
+	/// Slave node waits for master node to commit deletion.
+	WaitingForCommit,
+
+	// === Final states of the session ===
+	/// Key is deleted.
+	Finished,
+	/// Failed to delete key.
+	Failed,
+}
+
+impl SessionImpl {
+	/// Create new key deletion session.
+	pub fn new(params: SessionParams) -> Result<(Self, Oneshot<Result<(), Error>>), Error> {
+		let (completed, oneshot) = CompletionSignal::new();
+		Ok((SessionImpl {
+			id: params.id,
+			self_node_id: params.self_node_id,
+			key_storage: params.key_storage,
+			cluster: params.cluster,
+			nonce: params.nonce,
+			completed,
+			data: Mutex::new(SessionData {
+				state: SessionState::WaitingForInitialization,
+				origin: None,
+				master: None,
+				nodes: BTreeMap::new(),
+				result: None,
+			}),
+		}, oneshot))
+	}
+
+	/// Get this node Id.
+	pub fn node(&self) -> &NodeId {
+		&self.self_node_id
+	}
+
+	/// Get session origin.
+	pub fn origin(&self) -> Option<Address> {
+		self.data.lock().origin.clone()
+	}
+
+	/// Get session result.
+	pub fn result(&self) -> Option<Result<(), Error>> {
+		self.data.lock().result.clone()
+	}
+
+	/// Start new session initialization. This must be called on master node.
+	pub fn initialize(&self, origin: Option<Address>, requester: Requester) -> Result<(), Error> {
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForInitialization {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// master node must have the key to check that the requester is its author
+		if !self.key_storage.contains(&self.id) {
+			return Err(Error::ServerKeyIsNotFound);
+		}
+		self.check_author(&requester)?;
+
+		// update state
+		data.state = SessionState::WaitingForInitializationConfirm;
+		data.origin = origin;
+		data.master = Some(self.node().clone());
+		data.nodes.extend(self.cluster.nodes().into_iter().map(|n| (n, NodeData {
+			initialization_confirmed: &n == self.node(),
+		})));
+
+		// start initialization
+		if data.nodes.len() > 1 {
+			self.cluster.broadcast(Message::KeyDeletion(KeyDeletionMessage::InitializeKeyDeletionSession(InitializeKeyDeletionSession {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				origin: origin.map(Into::into),
+				requester: requester.into(),
+			})))
+		} else {
+			self.commit(&mut *data)?;
+			data.result = Some(Ok(()));
+			self.completed.send(Ok(()));
+
+			Ok(())
+		}
+	}
+
+	/// When session initialization message is received.
+	pub fn on_initialize_session(&self, sender: NodeId, message: &InitializeKeyDeletionSession) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForInitialization {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// check that the requester is the author of the key
+		self.check_author(&message.requester.clone().into())?;
+
+		// update state
+		data.state = SessionState::WaitingForCommit;
+		data.origin = message.origin.clone().map(Into::into);
+		data.master = Some(sender.clone());
+
+		// send confirmation back to master node
+		self.cluster.send(&sender, Message::KeyDeletion(KeyDeletionMessage::ConfirmKeyDeletionInitialization(ConfirmKeyDeletionInitialization {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+		})))
+	}
+
+	/// When session initialization confirmation message is received.
+	pub fn on_confirm_initialization(&self, sender: NodeId, message: &ConfirmKeyDeletionInitialization) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+		debug_assert!(data.nodes.contains_key(&sender));
+
+		// check state
+		if data.state != SessionState::WaitingForInitializationConfirm {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// check if all nodes have confirmed initialization
+		data.nodes.get_mut(&sender)
+			.expect("message is received from cluster; nodes contains all cluster nodes; qed")
+			.initialization_confirmed = true;
+		if !data.nodes.values().all(|n| n.initialization_confirmed) {
+			return Ok(());
+		}
+
+		// all nodes have agreed to delete the key => delete it on this node and ask other nodes to do the same
+		self.commit(&mut *data)?;
+		data.result = Some(Ok(()));
+		self.completed.send(Ok(()));
+
+		self.cluster.broadcast(Message::KeyDeletion(KeyDeletionMessage::CommitKeyDeletionSession(CommitKeyDeletionSession {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+		})))
+	}
+
+	/// When commit message is received.
+	pub fn on_commit(&self, sender: NodeId, message: &CommitKeyDeletionSession) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForCommit {
+			return Err(Error::InvalidStateForRequest);
+		}
+		if data.master.as_ref() != Some(&sender) {
+			return Err(Error::InvalidMessage);
+		}
+
+		self.commit(&mut *data)?;
+		data.result = Some(Ok(()));
+		self.completed.send(Ok(()));
+
+		Ok(())
+	}
+
+	/// Check that the requester is the author of the key. Nodes that are not holding the key share are
+	/// not able to check this, so they're relying on other nodes. Signed requests must be signed with
+	/// deletion-specific message (see `key_deletion_message_hash`).
+	fn check_author(&self, requester: &Requester) -> Result<(), Error> {
+		if let Some(key_share) = self.key_storage.get(&self.id)? {
+			if key_share.author != requester.address(&key_deletion_message_hash(&self.id))? {
+				return Err(Error::AccessDenied);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Delete the key.
+	fn commit(&self, data: &mut SessionData) -> Result<(), Error> {
+		self.key_storage.delete(&self.id)?;
+
+		data.state = SessionState::Finished;
+		Ok(())
+	}
+
+	/// Mark session as failed.
+	fn rollback(&self, data: &mut SessionData, error: Error) {
+		// key is already deleted => nothing to rollback
+		if data.state == SessionState::Finished || data.state == SessionState::Failed {
+			return;
+		}
+
+		// master node is responsible for notifying other nodes
+		if data.master.as_ref() == Some(self.node()) && data.nodes.len() > 1 {
+			// do not bother processing send error, as we already processing error
+			let _ = self.cluster.broadcast(Message::KeyDeletion(KeyDeletionMessage::KeyDeletionSessionError(KeyDeletionSessionError {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				error: error.clone().into(),
+			})));
+		}
+
+		data.state = SessionState::Failed;
+		data.result = Some(Err(error.clone()));
+		self.completed.send(Err(error));
+	}
+}
+
+impl ClusterSession for SessionImpl {
+	type Id = SessionId;
+	type CreationData = ();
+	type SuccessfulResult = ();
+
+	fn type_name() -> &'static str {
+		"key deletion"
+	}
+
+	fn id(&self) -> SessionId {
+		self.id.clone()
+	}
+
+	fn is_finished(&self) -> bool {
+		let data = self.data.lock();
+		data.state == SessionState::Failed
+			|| data.state == SessionState::Finished
+	}
+
+	fn on_node_timeout(&self, node: &NodeId) {
+		let mut data = self.data.lock();
+
+		warn!("{}: key deletion session failed because {} connection has timeouted", self.node(), node);
+
+		self.rollback(&mut *data, Error::NodeDisconnected);
+	}
+
+	fn on_session_timeout(&self) {
+		let mut data = self.data.lock();
+
+		warn!("{}: key deletion session failed with timeout", self.node());
+
+		self.rollback(&mut *data, Error::NodeDisconnected);
+	}
+
+	fn on_session_error(&self, node: &NodeId, error: Error) {
+		let mut data = self.data.lock();
+
+		// error in key deletion session is considered fatal
+		// => broadcast error if error occured on this node (master node broadcasts error on rollback)
+		if *node == self.self_node_id && data.master.as_ref() != Some(self.node()) {
+			// do not bother processing send error, as we already processing error
+			let _ = self.cluster.broadcast(Message::KeyDeletion(KeyDeletionMessage::KeyDeletionSessionError(KeyDeletionSessionError {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				error: error.clone().into(),
+			})));
+		}
+
+		warn!("{}: key deletion session failed with error: {} from {}", self.node(), error, node);
+
+		self.rollback(&mut *data, error);
+	}
+
+	fn on_message(&self, sender: &NodeId, message: &Message) -> Result<(), Error> {
+		if Some(self.nonce) != message.session_nonce() {
+			return Err(Error::ReplayProtection);
+		}
+
+		match message {
+			&Message::KeyDeletion(ref message) => match message {
+				&KeyDeletionMessage::InitializeKeyDeletionSession(ref message) =>
+					self.on_initialize_session(sender.clone(), message),
+				&KeyDeletionMessage::ConfirmKeyDeletionInitialization(ref message) =>
+					self.on_confirm_initialization(sender.clone(), message),
+				&KeyDeletionMessage::CommitKeyDeletionSession(ref message) =>
+					self.on_commit(sender.clone(), message),
+				&KeyDeletionMessage::KeyDeletionSessionError(ref message) => {
+					self.on_session_error(sender, message.error.clone());
+					Ok(())
+				},
+			},
+			_ => unreachable!("cluster checks message to be correct before passing; qed"),
+		}
+	}
+}
+
+impl Debug for SessionImpl {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+		write!(f, "Key deletion session {} on {}", self.id, self.self_node_id)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use parity_crypto::publickey::{Random, Generator, public_to_address, sign};
+	use primitives::key_storage::KeyStorage;
+	use primitives::requester::key_deletion_message_hash;
+	use crate::key_server_cluster::{Error, NodeId, SessionId, ServerKeyId, Requester};
+	use crate::key_server_cluster::cluster::tests::MessageLoop as ClusterMessageLoop;
+	use crate::key_server_cluster::cluster_sessions::ClusterSession;
+	use crate::key_server_cluster::generation_session::tests::MessageLoop as GenerationMessageLoop;
+	use super::{SessionImpl, SessionState};
+
+	const DUMMY_SESSION_ID: [u8; 32] = [1u8; 32];
+
+	struct MessageLoop(ClusterMessageLoop);
+
+	impl MessageLoop {
+		pub fn new(num_nodes: usize) -> Self {
+			let ml = GenerationMessageLoop::new(num_nodes).init(0).unwrap();
+			ml.0.loop_until(|| ml.0.is_empty()); // complete generation session
+			MessageLoop(ml.0)
+		}
+
+		pub fn init(self, requester: Requester) -> Result<Self, Error> {
+			self.0.cluster(0).client().new_key_deletion_session(
+				SessionId::from(DUMMY_SESSION_ID),
+				None,
+				requester,
+			).map(|_| self)
+		}
+
+		pub fn session_at(&self, idx: usize) -> Arc<SessionImpl> {
+			self.0.sessions(idx).key_deletion_sessions.first().unwrap()
+		}
+
+		pub fn node(&self, idx: usize) -> NodeId {
+			self.0.node(idx)
+		}
+
+		pub fn is_key_stored(&self, idx: usize) -> bool {
+			self.0.key_storage(idx).contains(&ServerKeyId::from(DUMMY_SESSION_ID))
+		}
+
+		pub fn is_key_deleted(&self, idx: usize) -> bool {
+			self.0.key_storage(idx).is_deleted(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap()
+		}
+
+		pub fn loop_until_empty(&self) {
+			self.0.loop_until(|| self.0.is_empty());
+		}
+
+		pub fn process_next_message(&self) {
+			assert!(self.0.take_and_process_message());
+		}
+	}
+
+	#[test]
+	fn key_deletion_session_deletes_key_on_all_nodes() {
+		let ml = MessageLoop::new(3).init(Requester::Address(Default::default())).unwrap();
+		ml.loop_until_empty();
+
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Finished);
+			assert_eq!(ml.session_at(idx).result(), Some(Ok(())));
+			assert!(!ml.is_key_stored(idx));
+			assert!(ml.is_key_deleted(idx));
+		}
+	}
+
+	#[test]
+	fn key_deletion_session_fails_if_requester_is_not_author() {
+		let requester = Requester::Address(public_to_address(Random.generate().public()));
+		let ml = MessageLoop::new(3);
+		assert_eq!(ml.init(requester).err(), Some(Error::AccessDenied));
+	}
+
+	#[test]
+	fn key_deletion_session_requires_deletion_specific_signature() {
+		let author = Random.generate();
+		let key_id = ServerKeyId::from(DUMMY_SESSION_ID);
+		let make_ml = || {
+			let ml = MessageLoop::new(3);
+			for idx in 0..3 {
+				let mut key_share = ml.0.key_storage(idx).get(&key_id).unwrap().unwrap();
+				key_share.author = author.address();
+				ml.0.key_storage(idx).update(key_id, key_share).unwrap();
+			}
+			ml
+		};
+
+		// signature of the key id (that is used to generate the key) can't be used to delete the key
+		let requester = Requester::Signature(sign(author.secret(), &key_id).unwrap());
+		assert_eq!(make_ml().init(requester).err(), Some(Error::AccessDenied));
+
+		// => the key is deleted with signature of deletion message
+		let requester = Requester::Signature(sign(author.secret(), &key_deletion_message_hash(&key_id)).unwrap());
+		let ml = make_ml().init(requester).unwrap();
+		ml.loop_until_empty();
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).result(), Some(Ok(())));
+			assert!(ml.is_key_deleted(idx));
+		}
+	}
+
+	#[test]
+	fn key_deletion_session_rolls_back_when_node_rejects_requester() {
+		let ml = MessageLoop::new(3);
+
+		// node2 believes that the key has other author => it rejects deletion
+		let mut key_share = ml.0.key_storage(2).get(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap().unwrap();
+		key_share.author = public_to_address(Random.generate().public());
+		ml.0.key_storage(2).update(ServerKeyId::from(DUMMY_SESSION_ID), key_share).unwrap();
+
+		let ml = ml.init(Requester::Address(Default::default())).unwrap();
+		ml.loop_until_empty();
+
+		// => key is not deleted on any node
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Err(Error::AccessDenied)));
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Failed);
+			assert!(ml.is_key_stored(idx));
+			assert!(!ml.is_key_deleted(idx));
+		}
+	}
+
+	#[test]
+	fn key_deletion_session_rolls_back_when_node_disconnects_before_confirmation() {
+		let ml = MessageLoop::new(3).init(Requester::Address(Default::default())).unwrap();
+
+		// deliver initialization messages to node1 && node2 and confirmation from node1 to master
+		ml.process_next_message();
+		ml.process_next_message();
+		ml.process_next_message();
+
+		// node2 disconnects before its confirmation is delivered to master
+		ml.0.take_message().unwrap();
+		ml.session_at(0).on_node_timeout(&ml.node(2));
+		ml.loop_until_empty();
+
+		// => key is not deleted on any node
+		assert_eq!(ml.session_at(0).data.lock().result, Some(Err(Error::NodeDisconnected)));
+		for idx in 0..3 {
+			assert_eq!(ml.session_at(idx).data.lock().state, SessionState::Failed);
+			assert!(ml.is_key_stored(idx));
+		}
+	}
+
+	#[test]
+	fn deleted_key_can_not_be_generated_again() {
+		let ml = GenerationMessageLoop::new(3);
+		ml.0.key_storage(0).delete(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap();
+		assert_eq!(ml.init(0).err(), Some(Error::ServerKeyIsDeleted));
+	}
+}
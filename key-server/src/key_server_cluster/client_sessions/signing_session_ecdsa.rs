@@ -20,9 +20,9 @@ use std::sync::Arc;
 use futures::Oneshot;
 use parking_lot::Mutex;
 use parity_crypto::publickey::{Public, Secret, Signature, sign};
-use ethereum_types::H256;
+use ethereum_types::{Address, H256};
 use log::warn;
-use primitives::{acl_storage::AclStorage, key_storage::KeyShare};
+use primitives::key_storage::KeyShare;
 use crate::key_server_cluster::{Error, NodeId, SessionId, SessionMeta, Requester};
 use crate::key_server_cluster::cluster::{Cluster};
 use crate::key_server_cluster::cluster_sessions::{SessionIdWithSubSession, ClusterSession, CompletionSignal};
@@ -33,7 +33,7 @@ use crate::key_server_cluster::message::{Message, EcdsaSigningMessage, EcdsaSign
 	EcdsaInversionNonceGenerationMessage, EcdsaInversionZeroGenerationMessage, EcdsaSigningInversedNonceCoeffShare,
 	EcdsaRequestPartialSignature, EcdsaPartialSignature, EcdsaSigningSessionCompleted, GenerationMessage,
 	ConsensusMessage, EcdsaSigningSessionError, InitializeConsensusSession, ConfirmConsensusInitialization,
-	EcdsaSigningSessionDelegation, EcdsaSigningSessionDelegationCompleted};
+	EcdsaSigningSessionDelegation, EcdsaSigningSessionDelegationCompleted, EcdsaPartialSignatureProof};
 use crate::key_server_cluster::jobs::job_session::JobTransport;
 use crate::key_server_cluster::jobs::key_access_job::KeyAccessJob;
 use crate::key_server_cluster::jobs::signing_job_ecdsa::{EcdsaPartialSigningRequest, EcdsaPartialSigningResponse, EcdsaSigningJob};
@@ -113,8 +113,6 @@ pub struct SessionParams {
 	pub access_key: Secret,
 	/// Key share.
 	pub key_share: Option<KeyShare>,
-	/// ACL storage.
-	pub acl_storage: Arc<dyn AclStorage>,
 	/// Cluster
 	pub cluster: Arc<dyn Cluster>,
 	/// Session nonce.
@@ -197,8 +195,8 @@ impl SessionImpl {
 				connected_nodes_count: params.meta.connected_nodes_count,
 			},
 			consensus_executor: match requester {
-				Some(requester) => KeyAccessJob::new_on_master(params.meta.id.clone(), params.acl_storage.clone(), requester),
-				None => KeyAccessJob::new_on_slave(params.meta.id.clone(), params.acl_storage.clone()),
+				Some(requester) => KeyAccessJob::new_on_master(params.meta.id.clone(), requester),
+				None => KeyAccessJob::new_on_slave(params.meta.id.clone()),
 			},
 			consensus_transport: consensus_transport,
 		})?;
@@ -235,6 +233,13 @@ impl SessionImpl {
 			.expect("wait_session returns Some if called without timeout; qed")
 	}
 
+	/// Set result of the requester access check, which has been awaited before message from
+	/// the requester (or the requester itself) has reached the session.
+	pub fn set_key_access_check_result(&self, requester_address: Address, result: Result<bool, Error>) {
+		self.data.lock().consensus_session.consensus_job_mut().executor_mut()
+			.set_access_check_result(requester_address, result);
+	}
+
 	/// Delegate session to other node.
 	pub fn delegate(&self, master: NodeId, version: H256, message_hash: H256) -> Result<(), Error> {
 		if self.core.meta.master_node_id != self.core.meta.self_node_id {
@@ -393,55 +398,7 @@ impl SessionImpl {
 			return Ok(());
 		}
 
-		let key_share = self.core.key_share.as_ref()
-			.expect("this is master node; master node is selected so that it has key version; qed");
-		let key_version = key_share.version(data.version.as_ref()
-			.expect("this is master node; master node is selected so that it has key version; qed"))?;
-
-		let consensus_group = data.consensus_session.select_consensus_group()?.clone();
-		let mut other_consensus_group_nodes = consensus_group.clone();
-		other_consensus_group_nodes.remove(&self.core.meta.self_node_id);
-		let consensus_group_map: BTreeMap<_, _> = consensus_group.iter().map(|n| (n.clone(), key_version.id_numbers[n].clone())).collect();
-
-		// start generation of signature nonce
-		let sig_nonce_generation_session = Self::start_generation_session(&self.core, &other_consensus_group_nodes,
-			|s, k, n, m| EcdsaSigningMessage::EcdsaSignatureNonceGenerationMessage(
-				EcdsaSignatureNonceGenerationMessage {
-					session: s.into(),
-					sub_session: k.into(),
-					session_nonce: n,
-					message: m,
-				}));
-		sig_nonce_generation_session.initialize(Default::default(), Default::default(), false, key_share.threshold, consensus_group_map.clone().into())?;
-		data.sig_nonce_generation_session = Some(sig_nonce_generation_session);
-
-		// start generation of inversed nonce computation session
-		let inv_nonce_generation_session = Self::start_generation_session(&self.core, &other_consensus_group_nodes,
-			move |s, k, n, m| EcdsaSigningMessage::EcdsaInversionNonceGenerationMessage(
-				EcdsaInversionNonceGenerationMessage {
-					session: s.into(),
-					sub_session: k.into(),
-					session_nonce: n,
-					message: m,
-				}));
-		inv_nonce_generation_session.initialize(Default::default(), Default::default(), false, key_share.threshold, consensus_group_map.clone().into())?;
-		data.inv_nonce_generation_session = Some(inv_nonce_generation_session);
-
-		// start generation of zero-secret shares for inversed nonce computation session
-		let inv_zero_generation_session = Self::start_generation_session(&self.core, &other_consensus_group_nodes,
-			move |s, k, n, m| EcdsaSigningMessage::EcdsaInversionZeroGenerationMessage(
-				EcdsaInversionZeroGenerationMessage {
-					session: s.into(),
-					sub_session: k.into(),
-					session_nonce: n,
-					message: m,
-				}));
-		inv_zero_generation_session.initialize(Default::default(), Default::default(), true, key_share.threshold * 2, consensus_group_map.clone().into())?;
-		data.inv_zero_generation_session = Some(inv_zero_generation_session);
-
-		data.state = SessionState::NoncesGenerating;
-
-		Ok(())
+		Self::start_nonces_generation(&self.core, &mut *data)
 	}
 
 	/// When signature nonce generation message is received.
@@ -659,8 +616,10 @@ impl SessionImpl {
 		let nonce_exists_proof = "nonce is generated before signature is computed; we are in SignatureComputing state; qed";
 		let sig_nonce_public = data.sig_nonce_generation_session.as_ref().expect(nonce_exists_proof).joint_public_and_secret().expect(nonce_exists_proof)?.0;
 		let inv_nonce_share = data.inv_nonce_generation_session.as_ref().expect(nonce_exists_proof).joint_public_and_secret().expect(nonce_exists_proof)?.2;
+		let inv_nonce_public_shares = data.inv_nonce_generation_session.as_ref().expect(nonce_exists_proof).nodes_public_key_shares().expect(nonce_exists_proof);
 
-		self.core.disseminate_jobs(&mut data.consensus_session, &version, sig_nonce_public, inv_nonce_share, inversed_nonce_coeff, message_hash)
+		data.state = SessionState::SignatureComputing;
+		self.core.disseminate_jobs(&mut data.consensus_session, &version, sig_nonce_public, inv_nonce_share, inv_nonce_public_shares, inversed_nonce_coeff, message_hash)
 	}
 
 	/// When partial signature is requested.
@@ -707,10 +666,31 @@ impl SessionImpl {
 		debug_assert!(sender != &self.core.meta.self_node_id);
 
 		let mut data = self.data.lock();
-		data.consensus_session.on_job_response(sender, EcdsaPartialSigningResponse {
+
+		// partial signatures, computed during previous signing attempt could be received
+		// while nonces are regenerated => ignore them
+		if data.state != SessionState::SignatureComputing {
+			return Ok(());
+		}
+
+		match data.consensus_session.on_job_response(sender, EcdsaPartialSigningResponse {
 			request_id: message.request_id.clone().into(),
 			partial_signature_s: message.partial_signature_s.clone().into(),
-		})?;
+			partial_signature_s_proof: message.partial_signature_s_proof.as_ref().map(|proof| math::EcdsaSignatureShareProof {
+				inv_nonce_mul_secret_public: proof.inv_nonce_mul_secret_public.clone().into(),
+				challenge: proof.challenge.clone().into(),
+				response: proof.response.clone().into(),
+			}),
+		}) {
+			Ok(()) => (),
+			Err(Error::InvalidSignatureShare(node)) => {
+				// exclude misbehaving node and retry signing with other nodes (if possible)
+				warn!("{}: ECDSA signing session: node {} has computed invalid signature share", &self.core.meta.self_node_id, node);
+				drop(data);
+				return self.process_node_error(Some(&node), Error::InvalidSignatureShare(node.clone()));
+			},
+			Err(error) => return Err(error),
+		}
 
 		if data.consensus_session.state() != ConsensusSessionState::Finished {
 			return Ok(());
@@ -760,19 +740,9 @@ impl SessionImpl {
 				Ok(())
 			},
 			Ok(true) => {
-				let version = data.version.as_ref().ok_or(Error::InvalidMessage)?.clone();
-
-				let message_hash = data.message_hash.as_ref().cloned()
-					.expect("on_node_error returned true; this means that jobs must be REsent; this means that jobs already have been sent; jobs are sent when message_hash.is_some(); qed");
-
-				let nonce_exists_proof = "on_node_error returned true; this means that jobs must be REsent; this means that jobs already have been sent; jobs are sent when nonces generation has completed; qed";
-				let sig_nonce_public = data.sig_nonce_generation_session.as_ref().expect(nonce_exists_proof).joint_public_and_secret().expect(nonce_exists_proof)?.0;
-				let inv_nonce_share = data.inv_nonce_generation_session.as_ref().expect(nonce_exists_proof).joint_public_and_secret().expect(nonce_exists_proof)?.2;
-
-				let inversed_nonce_coeff = Self::compute_inversed_nonce_coeff(&self.core, &*data)?;
-
-				let disseminate_result = self.core.disseminate_jobs(&mut data.consensus_session, &version, sig_nonce_public, inv_nonce_share, inversed_nonce_coeff, message_hash);
-				match disseminate_result {
+				// consensus group has changed => nonces must be regenerated by the nodes of new consensus group
+				let restart_result = Self::start_nonces_generation(&self.core, &mut *data);
+				match restart_result {
 					Ok(()) => Ok(()),
 					Err(err) => {
 						warn!("{}: ECDSA signing session failed with error: {:?} from {:?}", &self.core.meta.self_node_id, error, node);
@@ -789,6 +759,59 @@ impl SessionImpl {
 		}
 	}
 
+	/// Start generation of nonces by the nodes of (re)established consensus group.
+	fn start_nonces_generation(core: &SessionCore, data: &mut SessionData) -> Result<(), Error> {
+		let key_share = core.key_share.as_ref()
+			.expect("this is master node; master node is selected so that it has key version; qed");
+		let key_version = key_share.version(data.version.as_ref()
+			.expect("this is master node; master node is selected so that it has key version; qed"))?;
+
+		let consensus_group = data.consensus_session.select_consensus_group()?.clone();
+		let mut other_consensus_group_nodes = consensus_group.clone();
+		other_consensus_group_nodes.remove(&core.meta.self_node_id);
+		let consensus_group_map: BTreeMap<_, _> = consensus_group.iter().map(|n| (n.clone(), key_version.id_numbers[n].clone())).collect();
+
+		// start generation of signature nonce
+		let sig_nonce_generation_session = Self::start_generation_session(core, &other_consensus_group_nodes,
+			|s, k, n, m| EcdsaSigningMessage::EcdsaSignatureNonceGenerationMessage(
+				EcdsaSignatureNonceGenerationMessage {
+					session: s.into(),
+					sub_session: k.into(),
+					session_nonce: n,
+					message: m,
+				}));
+		sig_nonce_generation_session.initialize(Default::default(), Default::default(), false, key_share.threshold, consensus_group_map.clone().into())?;
+		data.sig_nonce_generation_session = Some(sig_nonce_generation_session);
+
+		// start generation of inversed nonce computation session
+		let inv_nonce_generation_session = Self::start_generation_session(core, &other_consensus_group_nodes,
+			move |s, k, n, m| EcdsaSigningMessage::EcdsaInversionNonceGenerationMessage(
+				EcdsaInversionNonceGenerationMessage {
+					session: s.into(),
+					sub_session: k.into(),
+					session_nonce: n,
+					message: m,
+				}));
+		inv_nonce_generation_session.initialize(Default::default(), Default::default(), false, key_share.threshold, consensus_group_map.clone().into())?;
+		data.inv_nonce_generation_session = Some(inv_nonce_generation_session);
+
+		// start generation of zero-secret shares for inversed nonce computation session
+		let inv_zero_generation_session = Self::start_generation_session(core, &other_consensus_group_nodes,
+			move |s, k, n, m| EcdsaSigningMessage::EcdsaInversionZeroGenerationMessage(
+				EcdsaInversionZeroGenerationMessage {
+					session: s.into(),
+					sub_session: k.into(),
+					session_nonce: n,
+					message: m,
+				}));
+		inv_zero_generation_session.initialize(Default::default(), Default::default(), true, key_share.threshold * 2, consensus_group_map.clone().into())?;
+		data.inv_zero_generation_session = Some(inv_zero_generation_session);
+
+		data.state = SessionState::NoncesGenerating;
+
+		Ok(())
+	}
+
 	/// Start generation session.
 	fn start_generation_session<F>(core: &SessionCore, other_consensus_group_nodes: &BTreeSet<NodeId>, map_message: F) -> GenerationSession
 		where F: Fn(SessionId, Secret, u64, GenerationMessage) -> EcdsaSigningMessage + Send + Sync + 'static {
@@ -1006,14 +1029,16 @@ impl SessionCore {
 		}
 	}
 
-	pub fn disseminate_jobs(&self, consensus_session: &mut SigningConsensusSession, version: &H256, nonce_public: Public, inv_nonce_share: Secret, inversed_nonce_coeff: Secret, message_hash: H256) -> Result<(), Error> {
+	pub fn disseminate_jobs(&self, consensus_session: &mut SigningConsensusSession, version: &H256, nonce_public: Public, inv_nonce_share: Secret,
+		inv_nonce_public_shares: BTreeMap<NodeId, Public>, inversed_nonce_coeff: Secret, message_hash: H256) -> Result<(), Error> {
 		let key_share = match self.key_share.as_ref() {
 			None => return Err(Error::InvalidMessage),
 			Some(key_share) => key_share,
 		};
 
 		let key_version = key_share.version(version)?.hash.clone();
-		let signing_job = EcdsaSigningJob::new_on_master(key_share.clone(), key_version, nonce_public, inv_nonce_share, inversed_nonce_coeff, message_hash)?;
+		let signing_job = EcdsaSigningJob::new_on_master(key_share.clone(), key_version, nonce_public, inv_nonce_share,
+			inv_nonce_public_shares, inversed_nonce_coeff, message_hash)?;
 		consensus_session.disseminate_jobs(signing_job, self.signing_transport(), false).map(|_| ())
 	}
 }
@@ -1070,6 +1095,11 @@ impl JobTransport for SigningJobTransport {
 			session_nonce: self.nonce,
 			request_id: response.request_id.into(),
 			partial_signature_s: response.partial_signature_s.into(),
+			partial_signature_s_proof: response.partial_signature_s_proof.map(|proof| EcdsaPartialSignatureProof {
+				inv_nonce_mul_secret_public: proof.inv_nonce_mul_secret_public.into(),
+				challenge: proof.challenge.into(),
+				response: proof.response.into(),
+			}),
 		})))
 	}
 }
@@ -1079,8 +1109,9 @@ mod tests {
 	use std::sync::Arc;
 	use ethereum_types::H256;
 	use parity_crypto::publickey::{Random, Generator, Public, verify_public, public_to_address};
-	use primitives::key_storage::KeyStorage;
+	use primitives::{acl_storage::AclStorage, key_storage::KeyStorage};
 	use crate::key_server_cluster::{SessionId, Error, ServerKeyId};
+	use crate::key_server_cluster::math;
 	use crate::key_server_cluster::cluster::tests::{MessageLoop as ClusterMessageLoop};
 	use crate::key_server_cluster::signing_session_ecdsa::SessionImpl;
 	use crate::key_server_cluster::generation_session::tests::MessageLoop as GenerationMessageLoop;
@@ -1102,8 +1133,9 @@ mod tests {
 			let message_hash = H256::random();
 			let requester = Random.generate();
 			let signature = parity_crypto::publickey::sign(requester.secret(), &SessionId::from(DUMMY_SESSION_ID)).unwrap();
+			let key_access = self.0.acl_storage(0).check(public_to_address(requester.public()), &SessionId::from(DUMMY_SESSION_ID));
 			self.0.cluster(0).client()
-				.new_ecdsa_signing_session(SessionId::from(DUMMY_SESSION_ID), signature.into(), key_version, message_hash)
+				.new_ecdsa_signing_session(SessionId::from(DUMMY_SESSION_ID), signature.into(), key_access, key_version, message_hash)
 				.map(|_| (self, *requester.public(), message_hash))
 		}
 
@@ -1133,6 +1165,12 @@ mod tests {
 		}
 	}
 
+	fn corrupt_secret_share(ml: &MessageLoop, idx: usize) {
+		let mut key_share = ml.0.key_storage(idx).get(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap().unwrap();
+		key_share.versions[0].secret_share = math::generate_random_scalar().unwrap();
+		ml.0.key_storage(idx).update(ServerKeyId::from(DUMMY_SESSION_ID), key_share).unwrap();
+	}
+
 	#[test]
 	fn failed_gen_ecdsa_sign_session_when_threshold_is_too_low() {
 		let test_cases = [(1, 2), (2, 4), (3, 6), (4, 6)];
@@ -1188,4 +1226,30 @@ mod tests {
 	fn ecdsa_signing_works_when_share_owners_are_isolated() {
 		MessageLoop::new(6, 2).unwrap().init_with_isolated().unwrap().0.ensure_completed();
 	}
+
+	#[test]
+	fn ecdsa_signing_works_when_node_computes_invalid_signature_share() {
+		// node1 is one of first nodes to join consensus group, but it computes invalid signature share
+		let ml = MessageLoop::new(4, 1).unwrap();
+		corrupt_secret_share(&ml, 1);
+
+		// => it is excluded and signature is computed by node0, node2 and node3
+		let (ml, _, message) = ml.init().unwrap();
+		ml.0.loop_until(|| ml.0.is_empty());
+
+		let signer_public = ml.0.key_storage(0).get(&ServerKeyId::from(DUMMY_SESSION_ID)).unwrap().unwrap().public;
+		let signature = ml.session_at(0).wait().unwrap();
+		assert!(verify_public(&signer_public, &signature, &message).unwrap());
+		assert_eq!(ml.session_at(0).data.lock().consensus_session.consensus_job().rejects().get(&ml.0.node(1)), Some(&true));
+	}
+
+	#[test]
+	fn ecdsa_signing_fails_when_node_computes_invalid_signature_share_and_no_other_nodes_left() {
+		let ml = MessageLoop::new(3, 1).unwrap();
+		corrupt_secret_share(&ml, 1);
+
+		let (ml, _, _) = ml.init().unwrap();
+		ml.0.loop_until(|| ml.0.is_empty());
+		assert_eq!(ml.session_at(0).wait().unwrap_err(), Error::ConsensusUnreachable);
+	}
 }
@@ -18,10 +18,10 @@ use std::collections::BTreeSet;
 use std::sync::Arc;
 use futures::Oneshot;
 use parking_lot::Mutex;
-use parity_crypto::publickey::{Public, Secret};
-use ethereum_types::H256;
+use parity_crypto::publickey::Secret;
+use ethereum_types::{Address, H256};
 use log::warn;
-use primitives::{acl_storage::AclStorage, key_storage::KeyShare};
+use primitives::key_storage::KeyShare;
 use crate::key_server_cluster::{Error, NodeId, SessionId, Requester, SessionMeta};
 use crate::key_server_cluster::cluster::{Cluster};
 use crate::key_server_cluster::cluster_sessions::{SessionIdWithSubSession, ClusterSession, CompletionSignal};
@@ -42,7 +42,8 @@ use crate::key_server_cluster::jobs::consensus_session::{ConsensusSessionParams,
 /// 1) initialization: master node (which has received request for signing the message) requests all other nodes to sign the message
 /// 2) ACL check: all nodes which have received the request are querying ACL-contract to check if requestor has access to the private key
 /// 3) partial signing: every node which has succussfully checked access for the requestor do a partial signing
-/// 4) signing: master node receives all partial signatures of the secret and computes the signature
+/// 4) signing: master node receives all partial signatures of the secret, checks them and computes the signature
+/// 5) if some node has computed invalid partial signature, it is excluded and session key is regenerated by the new consensus group
 pub struct SessionImpl {
 	/// Session core.
 	core: SessionCore,
@@ -107,8 +108,6 @@ pub struct SessionParams {
 	pub access_key: Secret,
 	/// Key share.
 	pub key_share: Option<KeyShare>,
-	/// ACL storage.
-	pub acl_storage: Arc<dyn AclStorage>,
 	/// Cluster
 	pub cluster: Arc<dyn Cluster>,
 	/// Session nonce.
@@ -179,8 +178,8 @@ impl SessionImpl {
 		let consensus_session = ConsensusSession::new(ConsensusSessionParams {
 			meta: params.meta.clone(),
 			consensus_executor: match requester {
-				Some(requester) => KeyAccessJob::new_on_master(params.meta.id.clone(), params.acl_storage.clone(), requester),
-				None => KeyAccessJob::new_on_slave(params.meta.id.clone(), params.acl_storage.clone()),
+				Some(requester) => KeyAccessJob::new_on_master(params.meta.id.clone(), requester),
+				None => KeyAccessJob::new_on_slave(params.meta.id.clone()),
 			},
 			consensus_transport: consensus_transport,
 		})?;
@@ -220,6 +219,13 @@ impl SessionImpl {
 		self.data.lock().state
 	}
 
+	/// Set result of the requester access check, which has been awaited before message from
+	/// the requester (or the requester itself) has reached the session.
+	pub fn set_key_access_check_result(&self, requester_address: Address, result: Result<bool, Error>) {
+		self.data.lock().consensus_session.consensus_job_mut().executor_mut()
+			.set_access_check_result(requester_address, result);
+	}
+
 	/// Delegate session to other node.
 	pub fn delegate(&self, master: NodeId, version: H256, message_hash: H256) -> Result<(), Error> {
 		if self.core.meta.master_node_id != self.core.meta.self_node_id {
@@ -289,14 +295,10 @@ impl SessionImpl {
 			generation_session.initialize(Default::default(), Default::default(), false, 0, vec![self.core.meta.self_node_id.clone()].into_iter().collect::<BTreeSet<_>>().into())?;
 
 			debug_assert_eq!(generation_session.state(), GenerationSessionState::Finished);
-			let joint_public_and_secret = generation_session
-				.joint_public_and_secret()
-				.expect("session key is generated before signature is computed; we are in SignatureComputing state; qed")?;
+			self.core.disseminate_jobs(&mut data.consensus_session, &generation_session, &version, message_hash)?;
 			data.generation_session = Some(generation_session);
 			data.state = SessionState::SignatureComputing;
 
-			self.core.disseminate_jobs(&mut data.consensus_session, &version, joint_public_and_secret.0, joint_public_and_secret.1, message_hash)?;
-
 			debug_assert!(data.consensus_session.state() == ConsensusSessionState::Finished);
 			let result = data.consensus_session.result()?;
 			Self::set_signing_result(&self.core, &mut *data, Ok(result));
@@ -393,33 +395,7 @@ impl SessionImpl {
 			return Ok(());
 		}
 
-		let consensus_group = data.consensus_session.select_consensus_group()?.clone();
-		let mut other_consensus_group_nodes = consensus_group.clone();
-		other_consensus_group_nodes.remove(&self.core.meta.self_node_id);
-
-		let key_share = match self.core.key_share.as_ref() {
-			None => return Err(Error::InvalidMessage),
-			Some(key_share) => key_share,
-		};
-
-		let generation_session = GenerationSession::new(GenerationSessionParams {
-			id: self.core.meta.id.clone(),
-			self_node_id: self.core.meta.self_node_id.clone(),
-			key_storage: None,
-			cluster: Arc::new(SessionKeyGenerationTransport {
-				access_key: self.core.access_key.clone(),
-				cluster: self.core.cluster.clone(),
-				nonce: self.core.nonce,
-				other_nodes_ids: other_consensus_group_nodes,
-			}),
-			nonce: None,
-		}).0;
-
-		generation_session.initialize(Default::default(), Default::default(), false, key_share.threshold, consensus_group.into())?;
-		data.generation_session = Some(generation_session);
-		data.state = SessionState::SessionKeyGeneration;
-
-		Ok(())
+		Self::start_session_key_generation(&self.core, &mut *data)
 	}
 
 	/// When session key related message is received.
@@ -477,11 +453,10 @@ impl SessionImpl {
 		let version = data.version.as_ref().ok_or(Error::InvalidMessage)?.clone();
 		let message_hash = data.message_hash
 			.expect("we are on master node; on master node message_hash is filled in initialize(); on_generation_message follows initialize; qed");
-		let joint_public_and_secret = data.generation_session.as_ref()
-			.expect("session key is generated before signature is computed; we are in SignatureComputing state; qed")
-			.joint_public_and_secret()
-			.expect("session key is generated before signature is computed; we are in SignatureComputing state; qed")?;
-		self.core.disseminate_jobs(&mut data.consensus_session, &version, joint_public_and_secret.0, joint_public_and_secret.1, message_hash)
+		let data = &mut *data;
+		let generation_session = data.generation_session.as_ref()
+			.expect("session key is generated before signature is computed; we are in SignatureComputing state; qed");
+		self.core.disseminate_jobs(&mut data.consensus_session, generation_session, &version, message_hash)
 	}
 
 	/// When partial signature is requested.
@@ -526,10 +501,26 @@ impl SessionImpl {
 		debug_assert!(sender != &self.core.meta.self_node_id);
 
 		let mut data = self.data.lock();
-		data.consensus_session.on_job_response(sender, SchnorrPartialSigningResponse {
+
+		// partial signatures, computed during previous signing attempt could be received
+		// while session key is regenerated => ignore them
+		if data.state == SessionState::SessionKeyGeneration {
+			return Ok(());
+		}
+
+		match data.consensus_session.on_job_response(sender, SchnorrPartialSigningResponse {
 			request_id: message.request_id.clone().into(),
 			partial_signature: message.partial_signature.clone().into(),
-		})?;
+		}) {
+			Ok(()) => (),
+			Err(Error::InvalidSignatureShare(node)) => {
+				// exclude misbehaving node and retry signing with other nodes (if possible)
+				warn!("{}: signing session: node {} has computed invalid signature share", &self.core.meta.self_node_id, node);
+				drop(data);
+				return self.process_node_error(Some(&node), Error::InvalidSignatureShare(node.clone()));
+			},
+			Err(error) => return Err(error),
+		}
 
 		if data.consensus_session.state() != ConsensusSessionState::Finished {
 			return Ok(());
@@ -579,15 +570,9 @@ impl SessionImpl {
 				Ok(())
 			},
 			Ok(true) => {
-				let version = data.version.as_ref().ok_or(Error::InvalidMessage)?.clone();
-				let message_hash = data.message_hash.as_ref().cloned()
-					.expect("on_node_error returned true; this means that jobs must be REsent; this means that jobs already have been sent; jobs are sent when message_hash.is_some(); qed");
-				let joint_public_and_secret = data.generation_session.as_ref()
-					.expect("on_node_error returned true; this means that jobs must be REsent; this means that jobs already have been sent; jobs are sent when message_hash.is_some(); qed")
-					.joint_public_and_secret()
-					.expect("on_node_error returned true; this means that jobs must be REsent; this means that jobs already have been sent; jobs are sent when message_hash.is_some(); qed")?;
-				let disseminate_result = self.core.disseminate_jobs(&mut data.consensus_session, &version, joint_public_and_secret.0, joint_public_and_secret.1, message_hash);
-				match disseminate_result {
+				// consensus group has changed => session key must be regenerated by the nodes of new consensus group
+				let restart_result = Self::start_session_key_generation(&self.core, &mut *data);
+				match restart_result {
 					Ok(()) => Ok(()),
 					Err(err) => {
 						warn!("{}: signing session failed with error: {:?} from {:?}", &self.core.meta.self_node_id, error, node);
@@ -604,6 +589,37 @@ impl SessionImpl {
 		}
 	}
 
+	/// Start generation of session key by the nodes of (re)established consensus group.
+	fn start_session_key_generation(core: &SessionCore, data: &mut SessionData) -> Result<(), Error> {
+		let key_share = match core.key_share.as_ref() {
+			None => return Err(Error::InvalidMessage),
+			Some(key_share) => key_share,
+		};
+
+		let consensus_group = data.consensus_session.select_consensus_group()?.clone();
+		let mut other_consensus_group_nodes = consensus_group.clone();
+		other_consensus_group_nodes.remove(&core.meta.self_node_id);
+
+		let generation_session = GenerationSession::new(GenerationSessionParams {
+			id: core.meta.id.clone(),
+			self_node_id: core.meta.self_node_id.clone(),
+			key_storage: None,
+			cluster: Arc::new(SessionKeyGenerationTransport {
+				access_key: core.access_key.clone(),
+				cluster: core.cluster.clone(),
+				nonce: core.nonce,
+				other_nodes_ids: other_consensus_group_nodes,
+			}),
+			nonce: None,
+		}).0;
+
+		generation_session.initialize(Default::default(), Default::default(), false, key_share.threshold, consensus_group.into())?;
+		data.generation_session = Some(generation_session);
+		data.state = SessionState::SessionKeyGeneration;
+
+		Ok(())
+	}
+
 	/// Set signing session result.
 	fn set_signing_result(core: &SessionCore, data: &mut SessionData, result: Result<(Secret, Secret), Error>) {
 		if let Some(DelegationStatus::DelegatedFrom(master, nonce)) = data.delegation_status.take() {
@@ -734,6 +750,10 @@ impl Cluster for SessionKeyGenerationTransport {
 	fn connected_nodes_count(&self) -> usize {
 		self.cluster.connected_nodes_count()
 	}
+
+	fn protocol_version(&self, nodes: &BTreeSet<NodeId>) -> u64 {
+		self.cluster.protocol_version(nodes)
+	}
 }
 
 impl SessionCore {
@@ -746,15 +766,21 @@ impl SessionCore {
 		}
 	}
 
-	pub fn disseminate_jobs(&self, consensus_session: &mut SigningConsensusSession, version: &H256, session_public: Public, session_secret_share: Secret, message_hash: H256) -> Result<(), Error> {
+	pub fn disseminate_jobs(&self, consensus_session: &mut SigningConsensusSession, generation_session: &GenerationSession, version: &H256, message_hash: H256) -> Result<(), Error> {
 		let key_share = match self.key_share.as_ref() {
 			None => return Err(Error::InvalidMessage),
 			Some(key_share) => key_share,
 		};
 
+		let (session_public, session_secret_coeff, _) = generation_session
+			.joint_public_and_secret()
+			.expect("session key is generated before signature is computed; jobs are disseminated when signature is computed; qed")?;
+		let session_public_shares = generation_session
+			.nodes_public_shares()
+			.expect("session key is generated before signature is computed; jobs are disseminated when signature is computed; qed");
 		let key_version = key_share.version(version)?.hash.clone();
 		let signing_job = SchnorrSigningJob::new_on_master(self.meta.self_node_id.clone(), key_share.clone(), key_version,
-			session_public, session_secret_share, message_hash)?;
+			session_public, session_secret_coeff, session_public_shares, message_hash)?;
 		consensus_session.disseminate_jobs(signing_job, self.signing_transport(), false).map(|_| ())
 	}
 }
@@ -822,7 +848,7 @@ mod tests {
 	use std::collections::BTreeMap;
 	use ethereum_types::{Address, H256};
 	use parity_crypto::publickey::{Random, Generator, Public, Secret, public_to_address};
-	use primitives::{acl_storage::InMemoryPermissiveAclStorage, key_storage::KeyStorage};
+	use primitives::{acl_storage::AclStorage, key_storage::KeyStorage};
 	use crate::key_server_cluster::{SessionId, Requester, SessionMeta, Error};
 	use crate::key_server_cluster::cluster::tests::MessageLoop as ClusterMessageLoop;
 	use crate::key_server_cluster::generation_session::tests::MessageLoop as GenerationMessageLoop;
@@ -859,7 +885,6 @@ mod tests {
 				},
 				access_key: Random.generate().secret().clone(),
 				key_share: self.0.key_storage(at_node).get(&dummy_doc).unwrap(),
-				acl_storage: Arc::new(InMemoryPermissiveAclStorage::default()),
 				cluster: self.0.cluster(0).view().unwrap(),
 				nonce: 0,
 			}, requester).unwrap().0
@@ -869,9 +894,11 @@ mod tests {
 			let message_hash = H256::random();
 			let requester = Random.generate();
 			let signature = parity_crypto::publickey::sign(requester.secret(), &SessionId::from([1u8; 32])).unwrap();
+			let key_access = self.0.acl_storage(0).check(public_to_address(requester.public()), &SessionId::from([1u8; 32]));
 			self.0.cluster(0).client().new_schnorr_signing_session(
 				SessionId::from([1u8; 32]),
 				signature.into(),
+				key_access,
 				key_version,
 				message_hash).map(|_| (self, *requester.public(), message_hash)
 			)
@@ -930,6 +957,40 @@ mod tests {
 		}
 	}
 
+	fn corrupt_secret_share(ml: &MessageLoop, idx: usize) {
+		let doc = [1u8; 32].into();
+		let mut key_share = ml.0.key_storage(idx).get(&doc).unwrap().unwrap();
+		key_share.versions[0].secret_share = math::generate_random_scalar().unwrap();
+		ml.0.key_storage(idx).update(doc, key_share).unwrap();
+	}
+
+	#[test]
+	fn schnorr_signing_works_when_node_computes_invalid_signature_share() {
+		// node1 is the first node to join consensus group, but it computes invalid signature share
+		let ml = MessageLoop::new(3, 1).unwrap();
+		corrupt_secret_share(&ml, 1);
+
+		// => it is excluded and signature is computed by node0 and node2
+		let (ml, _, message) = ml.init().unwrap();
+		ml.0.loop_until(|| ml.0.is_empty());
+
+		let doc = [1u8; 32].into();
+		let signer_public = ml.0.key_storage(0).get(&doc).unwrap().unwrap().public;
+		let signature = ml.session_at(0).wait().unwrap();
+		assert!(math::verify_schnorr_signature(&signer_public, &signature, &message).unwrap());
+		assert_eq!(ml.session_at(0).data.lock().consensus_session.consensus_job().rejects().get(&ml.0.node(1)), Some(&true));
+	}
+
+	#[test]
+	fn schnorr_signing_fails_when_node_computes_invalid_signature_share_and_no_other_nodes_left() {
+		let ml = MessageLoop::new(2, 1).unwrap();
+		corrupt_secret_share(&ml, 1);
+
+		let (ml, _, _) = ml.init().unwrap();
+		ml.0.loop_until(|| ml.0.is_empty());
+		assert_eq!(ml.session_at(0).wait().unwrap_err(), Error::ConsensusUnreachable);
+	}
+
 	#[test]
 	fn schnorr_constructs_in_cluster_of_single_node() {
 		MessageLoop::new(1, 0).unwrap().init().unwrap();
@@ -16,10 +16,12 @@
 
 use std::sync::Arc;
 use std::collections::BTreeSet;
+use std::time::Duration;
 use parity_crypto::publickey::{Public, Signature, Random, Generator};
 use ethereum_types::{Address, H256};
 use log::trace;
-use primitives::acl_storage::AclStorage;
+use primitives::acl_storage::AsyncAclStorage;
+use primitives::executor::Executor;
 use primitives::key_storage::KeyStorage;
 use primitives::key_server_key_pair::KeyServerKeyPair;
 use primitives::service::{
@@ -27,22 +29,25 @@ use primitives::service::{
 	ServiceTasksListenerRegistrar,
 };
 use crate::network::{ConnectionProvider, ConnectionManager};
-use crate::key_server_cluster::{Error, NodeId, SessionId, Requester};
+use crate::key_server_cluster::{Error, NodeId, SessionId, ServerKeyId, Requester};
 use crate::key_server_cluster::cluster_sessions::{WaitableSession, ClusterSession, AdminSession, ClusterSessions,
 	SessionIdWithSubSession, ClusterSessionsContainer, SERVERS_SET_CHANGE_SESSION_ID, create_cluster_view,
 	AdminSessionCreationData, ClusterSessionsListener};
 use crate::key_server_cluster::cluster_sessions_creator::ClusterSessionCreator;
 use crate::key_server_cluster::cluster_message_processor::MessageProcessor;
 use crate::key_server_cluster::message::Message;
+use crate::key_server_cluster::io::CURRENT_HEADER_VERSION;
 use crate::key_server_cluster::generation_session::{SessionImpl as GenerationSession};
 use crate::key_server_cluster::decryption_session::{SessionImpl as DecryptionSession};
 use crate::key_server_cluster::encryption_session::{SessionImpl as EncryptionSession};
+use crate::key_server_cluster::key_deletion_session::{SessionImpl as KeyDeletionSession};
 use crate::key_server_cluster::cluster_message_processor::SessionsMessageProcessor;
 use crate::key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSession};
 use crate::key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSession};
 use crate::key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSession,
 	IsolatedSessionTransport as KeyVersionNegotiationSessionTransport, ContinueAction, FailedContinueAction};
 use crate::key_server_cluster::connection_trigger::ServersSetChangeSessionCreatorConnector;
+use crate::key_server_cluster::servers_set_change_session::KeySharesRefresh;
 
 /// Cluster interface for external clients.
 pub trait ClusterClient: Send + Sync {
@@ -62,29 +67,42 @@ pub trait ClusterClient: Send + Sync {
 		common_point: Public,
 		encrypted_point: Public,
 	) -> Result<WaitableSession<EncryptionSession>, Error>;
-	/// Start new decryption session.
+	/// Start new key deletion session.
+	fn new_key_deletion_session(
+		&self,
+		session_id: SessionId,
+		origin: Option<Address>,
+		requester: Requester,
+	) -> Result<WaitableSession<KeyDeletionSession>, Error>;
+	/// Start new decryption session. Requester access to the key must be checked by the caller
+	/// and the check result is passed to the session.
 	fn new_decryption_session(
 		&self,
 		session_id: SessionId,
 		origin: Option<Address>,
 		requester: Requester,
+		key_access_check_result: Result<bool, Error>,
 		version: Option<H256>,
 		is_shadow_decryption: bool,
 		is_broadcast_decryption: bool,
 	) -> Result<WaitableSession<DecryptionSession>, Error>;
-	/// Start new Schnorr signing session.
+	/// Start new Schnorr signing session. Requester access to the key must be checked by the caller
+	/// and the check result is passed to the session.
 	fn new_schnorr_signing_session(
 		&self,
 		session_id: SessionId,
 		requester: Requester,
+		key_access_check_result: Result<bool, Error>,
 		version: Option<H256>,
 		message_hash: H256,
 	) -> Result<WaitableSession<SchnorrSigningSession>, Error>;
-	/// Start new ECDSA session.
+	/// Start new ECDSA session. Requester access to the key must be checked by the caller
+	/// and the check result is passed to the session.
 	fn new_ecdsa_signing_session(
 		&self,
 		session_id: SessionId,
 		requester: Requester,
+		key_access_check_result: Result<bool, Error>,
 		version: Option<H256>,
 		message_hash: H256,
 	) -> Result<WaitableSession<EcdsaSigningSession>, Error>;
@@ -102,6 +120,21 @@ pub trait ClusterClient: Send + Sync {
 		old_set_signature: Signature,
 		new_set_signature: Signature,
 	) -> Result<WaitableSession<AdminSession>, Error>;
+	/// Start new key shares refresh session. Shares of given key (or of all keys if key is not specified)
+	/// are refreshed without changing servers set. The signature is the current servers set, signed by administrator.
+	fn new_key_shares_refresh_session(
+		&self,
+		key_id: Option<ServerKeyId>,
+		servers_set_signature: Signature,
+	) -> Result<WaitableSession<AdminSession>, Error>;
+	/// Start new key versions pruning session. Obsolete versions of given key are removed from all nodes,
+	/// except for (at most) configured number of most recent restorable versions. The latest version is always kept.
+	/// The signature is the key versions pruning hash of the key id, signed by administrator.
+	fn new_key_versions_pruning_session(
+		&self,
+		key_id: ServerKeyId,
+		admin_signature: Signature,
+	) -> Result<WaitableSession<AdminSession>, Error>;
 
 	/// Return cluster session listener registrar.
 	fn session_listener_registrar(&self) -> Arc<dyn ServiceTasksListenerRegistrar>;
@@ -135,6 +168,8 @@ pub trait Cluster: Send + Sync {
 	fn configured_nodes_count(&self) -> usize;
 	/// Get total count of connected key server nodes (valid at the time of ClusterView creation).
 	fn connected_nodes_count(&self) -> usize;
+	/// Get maximal protocol (message header) version, supported by all given nodes.
+	fn protocol_version(&self, nodes: &BTreeSet<NodeId>) -> u64;
 }
 
 /// Network cluster implementation.
@@ -170,7 +205,7 @@ pub struct ClusterData<C: ConnectionManager> {
 	/// Reference to key storage
 	pub key_storage: Arc<dyn KeyStorage>,
 	/// Reference to ACL storage
-	pub acl_storage: Arc<dyn AclStorage>,
+	pub acl_storage: Arc<dyn AsyncAclStorage>,
 	/// Administrator public key.
 	pub admin_address: Option<Address>,
 	/// Connections data.
@@ -188,23 +223,31 @@ pub fn create_cluster<CM: ConnectionManager>(
 	self_key_pair: Arc<dyn KeyServerKeyPair>,
 	admin_address: Option<Address>,
 	key_storage: Arc<dyn KeyStorage>,
-	acl_storage: Arc<dyn AclStorage>,
+	acl_storage: Arc<dyn AsyncAclStorage>,
 	servers_set_change_creator_connector: Arc<dyn ServersSetChangeSessionCreatorConnector>,
 	connection_provider: Arc<dyn ConnectionProvider>,
+	executor: Arc<dyn Executor>,
+	session_timeout_interval: Duration,
+	session_keep_alive_interval: Duration,
+	key_versions_to_keep: usize,
 	make_connections_manager: impl FnOnce(Arc<dyn MessageProcessor>) -> Result<Arc<CM>, Error>,
 ) -> Result<Arc<ClusterCore<CM>>, Error> {
 	let sessions = Arc::new(ClusterSessions::new(
 		self_key_pair.address(),
 		admin_address,
 		key_storage.clone(),
-		acl_storage.clone(),
 		servers_set_change_creator_connector.clone(),
+		session_timeout_interval,
+		session_keep_alive_interval,
+		key_versions_to_keep,
 	));
 	let message_processor = Arc::new(SessionsMessageProcessor::new(
 		self_key_pair.clone(),
 		servers_set_change_creator_connector.clone(),
 		sessions.clone(),
 		connection_provider,
+		acl_storage.clone(),
+		executor,
 	));
 	
 	let connections_manager = make_connections_manager(message_processor.clone())?;
@@ -303,6 +346,15 @@ impl Cluster for ClusterView {
 	fn connected_nodes_count(&self) -> usize {
 		self.connected_nodes.len()
 	}
+
+	fn protocol_version(&self, nodes: &BTreeSet<NodeId>) -> u64 {
+		nodes.iter()
+			.filter(|n| **n != self.self_key_pair.address())
+			.filter_map(|n| self.connections.connection(n))
+			.map(|connection| connection.header_version())
+			.min()
+			.unwrap_or(CURRENT_HEADER_VERSION)
+	}
 }
 
 impl<C: ConnectionManager> ClusterClientImpl<C> {
@@ -368,11 +420,25 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 			session, &self.data.sessions.encryption_sessions)
 	}
 
+	fn new_key_deletion_session(
+		&self,
+		session_id: SessionId,
+		origin: Option<Address>,
+		requester: Requester,
+	) -> Result<WaitableSession<KeyDeletionSession>, Error> {
+		let cluster = create_cluster_view(self.data.self_key_pair.clone(), self.data.connections.provider(), true)?;
+		let session = self.data.sessions.key_deletion_sessions.insert(cluster, self.data.self_key_pair.address(), session_id, None, false, None)?;
+		process_initialization_result(
+			session.session.initialize(origin, requester),
+			session, &self.data.sessions.key_deletion_sessions)
+	}
+
 	fn new_decryption_session(
 		&self,
 		session_id: SessionId,
 		origin: Option<Address>,
 		requester: Requester,
+		key_access_check_result: Result<bool, Error>,
 		version: Option<H256>,
 		is_shadow_decryption: bool,
 		is_broadcast_decryption: bool,
@@ -383,8 +449,12 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 		let access_key = Random.generate().secret().clone();
 		let session_id = SessionIdWithSubSession::new(session_id, access_key);
 		let cluster = create_cluster_view(self.data.self_key_pair.clone(), self.data.connections.provider(), false)?;
+		let requester_address = requester.address(&session_id.id);
 		let session = self.data.sessions.decryption_sessions.insert(cluster, self.data.self_key_pair.address().clone(),
 			session_id.clone(), None, false, Some(requester))?;
+		if let Ok(requester_address) = requester_address {
+			session.session.set_key_access_check_result(requester_address, key_access_check_result);
+		}
 
 		let initialization_result = match version {
 			Some(version) => session.session.initialize(origin, version, is_shadow_decryption, is_broadcast_decryption),
@@ -412,6 +482,7 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 		&self,
 		session_id: SessionId,
 		requester: Requester,
+		key_access_check_result: Result<bool, Error>,
 		version: Option<H256>,
 		message_hash: H256,
 	) -> Result<WaitableSession<SchnorrSigningSession>, Error> {
@@ -421,7 +492,11 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 		let access_key = Random.generate().secret().clone();
 		let session_id = SessionIdWithSubSession::new(session_id, access_key);
 		let cluster = create_cluster_view(self.data.self_key_pair.clone(), self.data.connections.provider(), false)?;
+		let requester_address = requester.address(&session_id.id);
 		let session = self.data.sessions.schnorr_signing_sessions.insert(cluster, self.data.self_key_pair.address(), session_id.clone(), None, false, Some(requester))?;
+		if let Ok(requester_address) = requester_address {
+			session.session.set_key_access_check_result(requester_address, key_access_check_result);
+		}
 
 		let initialization_result = match version {
 			Some(version) => session.session.initialize(version, message_hash),
@@ -444,6 +519,7 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 		&self,
 		session_id: SessionId,
 		requester: Requester,
+		key_access_check_result: Result<bool, Error>,
 		version: Option<H256>,
 		message_hash: H256,
 	) -> Result<WaitableSession<EcdsaSigningSession>, Error> {
@@ -453,7 +529,11 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 		let access_key = Random.generate().secret().clone();
 		let session_id = SessionIdWithSubSession::new(session_id, access_key);
 		let cluster = create_cluster_view(self.data.self_key_pair.clone(), self.data.connections.provider(), false)?;
+		let requester_address = requester.address(&session_id.id);
 		let session = self.data.sessions.ecdsa_signing_sessions.insert(cluster, self.data.self_key_pair.address(), session_id.clone(), None, false, Some(requester))?;
+		if let Ok(requester_address) = requester_address {
+			session.session.set_key_access_check_result(requester_address, key_access_check_result);
+		}
 
 		let initialization_result = match version {
 			Some(version) => session.session.initialize(version, message_hash),
@@ -498,9 +578,53 @@ impl<C: ConnectionManager> ClusterClient for ClusterClientImpl<C> {
 				new_nodes_set,
 				old_set_signature,
 				new_set_signature,
+				shares_refresh: None,
+			})
+	}
+
+	fn new_key_shares_refresh_session(
+		&self,
+		key_id: Option<ServerKeyId>,
+		servers_set_signature: Signature,
+	) -> Result<WaitableSession<AdminSession>, Error> {
+		let connections = self.data.connections.provider();
+		let mut servers_set = connections.connected_nodes()?;
+		servers_set.insert(self.data.self_key_pair.address());
+
+		new_servers_set_change_session(
+			self.data.self_key_pair.clone(),
+			&self.data.sessions,
+			connections,
+			self.data.servers_set_change_creator_connector.clone(),
+			ServersSetChangeParams {
+				session_id: None,
+				migration_id: None,
+				new_nodes_set: servers_set,
+				old_set_signature: servers_set_signature.clone(),
+				new_set_signature: servers_set_signature,
+				shares_refresh: Some(match key_id {
+					Some(key_id) => KeySharesRefresh::Key(key_id),
+					None => KeySharesRefresh::AllKeys,
+				}),
 			})
 	}
 
+	fn new_key_versions_pruning_session(
+		&self,
+		key_id: ServerKeyId,
+		admin_signature: Signature,
+	) -> Result<WaitableSession<AdminSession>, Error> {
+		let cluster = create_cluster_view(self.data.self_key_pair.clone(), self.data.connections.provider(), true)?;
+		let session = self.data.sessions.admin_sessions.insert(cluster, self.data.self_key_pair.address(), key_id,
+			None, false, Some(AdminSessionCreationData::KeyVersionsPruning))?;
+		let initialization_result = session.session.as_key_versions_pruning()
+			.expect("key versions pruning session is created; qed")
+			.initialize(admin_signature);
+		process_initialization_result(
+			initialization_result,
+			session, &self.data.sessions.admin_sessions)
+	}
+
 	/// Return cluster session listener registrar.
 	fn session_listener_registrar(&self) -> Arc<dyn ServiceTasksListenerRegistrar> {
 		Arc::new(ClusterSessionListenerRegistrar {
@@ -537,6 +661,9 @@ impl<C: ConnectionManager> ServiceTasksListenerRegistrar for ClusterSessionListe
 			ServerKeyGenerationResult,
 			ServerKeyGenerationParams,
 			ServerKeyGenerationArtifacts,
+			ServerKeyDeletionResult,
+			ServerKeyDeletionParams,
+			ServerKeyDeletionArtifacts,
 			DocumentKeyShadowRetrievalResult,
 			DocumentKeyShadowRetrievalParams,
 			DocumentKeyShadowRetrievalArtifacts,
@@ -562,6 +689,24 @@ impl<C: ConnectionManager> ServiceTasksListenerRegistrar for ClusterSessionListe
 			}
 		}
 
+		impl ClusterSessionsListener<KeyDeletionSession> for ListenerWrapper {
+			fn on_session_removed(&self, session: Arc<KeyDeletionSession>) {
+				// by this time sesion must already be completed - either successfully, or not
+				assert!(session.is_finished());
+
+				let key_id = session.id();
+				if let Some(session_result) = session.result() {
+					self.0.server_key_deleted(ServerKeyDeletionResult {
+						origin: session.origin(),
+						params: ServerKeyDeletionParams {
+							key_id,
+						},
+						result: session_result.map(|_| ServerKeyDeletionArtifacts),
+					})
+				}
+			}
+		}
+
 		impl ClusterSessionsListener<DecryptionSession> for ListenerWrapper {
 			fn on_session_removed(&self, session: Arc<DecryptionSession>) {
 				// by this time sesion must already be completed - either successfully, or not
@@ -627,6 +772,7 @@ impl<C: ConnectionManager> ServiceTasksListenerRegistrar for ClusterSessionListe
 		}
 
 		self.data.sessions.generation_sessions.add_listener(Arc::new(ListenerWrapper(listener.clone())));
+		self.data.sessions.key_deletion_sessions.add_listener(Arc::new(ListenerWrapper(listener.clone())));
 		self.data.sessions.decryption_sessions.add_listener(Arc::new(ListenerWrapper(listener.clone())));
 		self.data.sessions.negotiation_sessions.add_listener(Arc::new(ListenerWrapper(listener)));	
 	}
@@ -638,6 +784,7 @@ pub struct ServersSetChangeParams {
 	pub new_nodes_set: BTreeSet<NodeId>,
 	pub old_set_signature: Signature,
 	pub new_set_signature: Signature,
+	pub shares_refresh: Option<KeySharesRefresh>,
 }
 
 pub fn new_servers_set_change_session(
@@ -654,7 +801,7 @@ pub fn new_servers_set_change_session(
 	};
 
 	let cluster = create_cluster_view(self_key_pair.clone(), connections, true)?;
-	let creation_data = AdminSessionCreationData::ServersSetChange(params.migration_id, params.new_nodes_set.clone());
+	let creation_data = AdminSessionCreationData::ServersSetChange(params.migration_id, params.new_nodes_set.clone(), params.shares_refresh);
 	let session = sessions.admin_sessions
 		.insert(cluster, self_key_pair.address(), session_id, None, true, Some(creation_data))?;
 	let initialization_result = session.session.as_servers_set_change().expect("servers set change session is created; qed")
@@ -699,8 +846,10 @@ pub mod tests {
 	use futures::Future;
 	use parking_lot::{Mutex, RwLock};
 	use ethereum_types::{Address, H256};
-	use parity_crypto::publickey::{Random, Generator, Public, Signature, sign};
-	use primitives::acl_storage::{AclStorage, InMemoryPermissiveAclStorage};
+	use parity_crypto::publickey::{Random, Generator, KeyPair, Public, Signature, sign, public_to_address};
+	use futures03::{FutureExt, channel::oneshot, future::BoxFuture};
+	use primitives::acl_storage::{AclStorage, AsyncAclStorage, InMemoryPermissiveAclStorage, SyncAclStorageAdapter};
+	use primitives::executor::Executor;
 	use primitives::key_server_set::{KeyServerSet, InMemoryKeyServerSet};
 	use primitives::key_storage::{KeyStorage, InMemoryKeyStorage};
 	use primitives::key_server_key_pair::InMemoryKeyServerKeyPair;
@@ -708,24 +857,84 @@ pub mod tests {
 	use primitives::service::ServiceTasksListenerRegistrar;
 	use crate::network::ConnectionManager;
 	use crate::network::in_memory::{InMemoryMessagesQueue, InMemoryConnectionsManager, new_in_memory_connections};
-	use crate::key_server_cluster::{NodeId, SessionId, Requester, Error};
-	use crate::key_server_cluster::message::Message;
+	use crate::key_server_cluster::{NodeId, SessionId, ServerKeyId, Requester, Error};
+	use crate::key_server_cluster::message::{Message, SchnorrSigningMessage, SchnorrSigningConsensusMessage,
+		ConsensusMessage, InitializeConsensusSession, ConfirmConsensusInitialization};
+	use crate::key_server_cluster::cluster_message_processor::{MessageProcessor, SessionsMessageProcessor};
+	use crate::key_server_cluster::io::CURRENT_HEADER_VERSION;
 	use crate::key_server_cluster::cluster::{Cluster, ClusterCore, ClusterClient, create_cluster};
-	use crate::key_server_cluster::cluster_sessions::{WaitableSession, ClusterSession, ClusterSessions, AdminSession};
+	use crate::key_server_cluster::cluster_sessions::{WaitableSession, ClusterSession, ClusterSessions, AdminSession,
+		DEFAULT_SESSION_TIMEOUT_INTERVAL, DEFAULT_SESSION_KEEP_ALIVE_INTERVAL, DEFAULT_KEY_VERSIONS_TO_KEEP};
 	use crate::key_server_cluster::generation_session::{SessionImpl as GenerationSession,
 		SessionState as GenerationSessionState};
 	use crate::key_server_cluster::decryption_session::{SessionImpl as DecryptionSession};
 	use crate::key_server_cluster::encryption_session::{SessionImpl as EncryptionSession};
+	use crate::key_server_cluster::key_deletion_session::{SessionImpl as KeyDeletionSession};
 	use crate::key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSession};
 	use crate::key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSession};
 	use crate::key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSession,
 		IsolatedSessionTransport as KeyVersionNegotiationSessionTransport};
 
+	/// Executor that runs spawned futures in the current thread.
+	pub struct BlockingExecutor;
+
+	impl Executor for BlockingExecutor {
+		fn spawn(&self, future: BoxFuture<'static, ()>) {
+			futures03::executor::block_on(future)
+		}
+	}
+
+	/// Executor that runs spawned futures when asked to.
+	#[derive(Default)]
+	struct ManualExecutor {
+		futures: Mutex<Vec<BoxFuture<'static, ()>>>,
+	}
+
+	impl ManualExecutor {
+		fn run(&self) {
+			let futures = std::mem::replace(&mut *self.futures.lock(), Vec::new());
+			for future in futures {
+				futures03::executor::block_on(future);
+			}
+		}
+	}
+
+	impl Executor for ManualExecutor {
+		fn spawn(&self, future: BoxFuture<'static, ()>) {
+			self.futures.lock().push(future);
+		}
+	}
+
+	/// ACL storage, which checks are completed when asked to.
+	#[derive(Default)]
+	struct ManualAclStorage {
+		checks: Mutex<Vec<oneshot::Sender<Result<bool, Error>>>>,
+	}
+
+	impl ManualAclStorage {
+		fn complete_checks(&self, result: Result<bool, Error>) {
+			for check in self.checks.lock().drain(..) {
+				let _ = check.send(result.clone());
+			}
+		}
+	}
+
+	impl AsyncAclStorage for ManualAclStorage {
+		fn check(&self, _requester_address: Address, _key_id: &ServerKeyId) -> BoxFuture<'static, Result<bool, Error>> {
+			let (sender, receiver) = oneshot::channel();
+			self.checks.lock().push(sender);
+			receiver
+				.map(|result| result.unwrap_or_else(|_| Err(Error::Internal("ACL check has been cancelled".into()))))
+				.boxed()
+		}
+	}
+
 	/// Create new in-memory backed cluster.
 	pub fn new_test_cluster(
 		messages: InMemoryMessagesQueue,
 		key_server_set: Arc<dyn KeyServerSet<NetworkAddress=std::net::SocketAddr>>,
 		self_key_pair: Arc<dyn KeyServerKeyPair>,
+		admin_address: Option<Address>,
 		key_storage: Arc<dyn KeyStorage>,
 		acl_storage: Arc<dyn AclStorage>,
 		preserve_sessions: bool,
@@ -737,15 +946,19 @@ pub mod tests {
 		let nodes = key_server_set.snapshot().current_set;
 		let connections = Arc::new(new_in_memory_connections(messages, self_key_pair.address(), nodes.keys().cloned().collect()));
 		let connections_manager = connections.manager();
-		let connection_trigger = Box::new(SimpleConnectionTrigger::new(key_server_set, None));
+		let connection_trigger = Box::new(SimpleConnectionTrigger::new(key_server_set, admin_address));
 		let servers_set_change_creator_connector = connection_trigger.servers_set_change_creator_connector();
 		let cluster = create_cluster(
 			self_key_pair,
-			None,
+			admin_address,
 			key_storage,
-			acl_storage,
+			Arc::new(SyncAclStorageAdapter::new(acl_storage)),
 			servers_set_change_creator_connector.clone(),
 			connections_manager.provider(),
+			Arc::new(BlockingExecutor),
+			DEFAULT_SESSION_TIMEOUT_INTERVAL,
+			DEFAULT_SESSION_KEEP_ALIVE_INTERVAL,
+			DEFAULT_KEY_VERSIONS_TO_KEEP,
 			move |_message_processor| Ok(connections_manager),
 		)?;
 
@@ -793,11 +1006,20 @@ pub mod tests {
 		) -> Result<WaitableSession<EncryptionSession>, Error> {
 			unimplemented!("test-only")
 		}
+		fn new_key_deletion_session(
+			&self,
+			_session_id: SessionId,
+			_origin: Option<Address>,
+			_requester: Requester,
+		) -> Result<WaitableSession<KeyDeletionSession>, Error> {
+			unimplemented!("test-only")
+		}
 		fn new_decryption_session(
 			&self,
 			_session_id: SessionId,
 			_origin: Option<Address>,
 			_requester: Requester,
+			_key_access_check_result: Result<bool, Error>,
 			_version: Option<H256>,
 			_is_shadow_decryption: bool,
 			_is_broadcast_session: bool,
@@ -808,6 +1030,7 @@ pub mod tests {
 			&self,
 			_session_id: SessionId,
 			_requester: Requester,
+			_key_access_check_result: Result<bool, Error>,
 			_version: Option<H256>,
 			_message_hash: H256,
 		) -> Result<WaitableSession<SchnorrSigningSession>, Error> {
@@ -817,6 +1040,7 @@ pub mod tests {
 			&self,
 			_session_id: SessionId,
 			_requester: Requester,
+			_key_access_check_result: Result<bool, Error>,
 			_version: Option<H256>,
 			_message_hash: H256,
 		) -> Result<WaitableSession<EcdsaSigningSession>, Error> {
@@ -839,6 +1063,20 @@ pub mod tests {
 		) -> Result<WaitableSession<AdminSession>, Error> {
 			unimplemented!("test-only")
 		}
+		fn new_key_shares_refresh_session(
+			&self,
+			_key_id: Option<ServerKeyId>,
+			_servers_set_signature: Signature,
+		) -> Result<WaitableSession<AdminSession>, Error> {
+			unimplemented!("test-only")
+		}
+		fn new_key_versions_pruning_session(
+			&self,
+			_key_id: ServerKeyId,
+			_admin_signature: Signature,
+		) -> Result<WaitableSession<AdminSession>, Error> {
+			unimplemented!("test-only")
+		}
 
 		fn session_listener_registrar(&self) -> Arc<dyn ServiceTasksListenerRegistrar> {
 			unimplemented!("test-only")
@@ -912,12 +1150,17 @@ pub mod tests {
 		fn connected_nodes_count(&self) -> usize {
 			self.data.read().nodes.len()
 		}
+
+		fn protocol_version(&self, _nodes: &BTreeSet<NodeId>) -> u64 {
+			CURRENT_HEADER_VERSION
+		}
 	}
 
 	/// Test message loop.
 	pub struct MessageLoop {
 		messages: InMemoryMessagesQueue,
 		preserve_sessions: bool,
+		admin_key_pair: KeyPair,
 		key_pairs_map: BTreeMap<NodeId, Arc<InMemoryKeyServerKeyPair>>,
 		acl_storages_map: BTreeMap<NodeId, Arc<InMemoryPermissiveAclStorage>>,
 		key_storages_map: BTreeMap<NodeId, Arc<InMemoryKeyStorage>>,
@@ -941,6 +1184,11 @@ pub mod tests {
 			*self.clusters_map.keys().nth(idx).unwrap()
 		}
 
+		/// Returns key pair of the cluster administrator.
+		pub fn admin_key_pair(&self) -> &KeyPair {
+			&self.admin_key_pair
+		}
+
 		/// Returns key pair of the node by its idx.
 		pub fn node_key_pair(&self, idx: usize) -> &Arc<InMemoryKeyServerKeyPair> {
 			self.key_pairs_map.values().nth(idx).unwrap()
@@ -993,6 +1241,13 @@ pub mod tests {
 			}
 		}
 
+		/// Set message header version, negotiated by all nodes.
+		pub fn set_header_version(&self, header_version: u64) {
+			for cluster in self.clusters_map.values() {
+				cluster.data.connections.set_header_version(header_version);
+			}
+		}
+
 		/// Exclude node from cluster.
 		pub fn exclude(&mut self, idx: usize) {
 			let node = self.node(idx);
@@ -1022,6 +1277,7 @@ pub mod tests {
 						.collect())
 				),
 				node_key_pair.clone(),
+				Some(public_to_address(self.admin_key_pair.public())),
 				key_storage.clone(),
 				acl_storage.clone(),
 				self.preserve_sessions,
@@ -1080,8 +1336,6 @@ pub mod tests {
 			&self,
 			fut: impl std::future::Future<Output=T> + Send + 'static,
 		) -> T {
-			use futures03::FutureExt;
-			
 			let (sender, mut receiver) = futures03::channel::oneshot::channel();
 			let pool = futures03::executor::ThreadPool::new().unwrap();
 			pool.spawn_ok(fut.map(|result| { let _ = sender.send(result); }));
@@ -1106,6 +1360,7 @@ pub mod tests {
 	fn do_make_clusters(num_nodes: usize, preserve_sessions: bool) -> MessageLoop {
 		let ports_begin = 0;
 		let messages = Arc::new(Mutex::new(VecDeque::new()));
+		let admin_key_pair = Random.generate();
 		let key_pairs: Vec<_> = (0..num_nodes)
 			.map(|_| Arc::new(InMemoryKeyServerKeyPair::new(Random.generate()))).collect();
 		let key_storages: Vec<_> = (0..num_nodes).map(|_| Arc::new(InMemoryKeyStorage::default())).collect();
@@ -1123,6 +1378,7 @@ pub mod tests {
 						.collect()),
 					),
 					key_pairs[i].clone(),
+					Some(public_to_address(admin_key_pair.public())),
 					key_storages[i].clone(),
 					acl_storages[i].clone(),
 					preserve_sessions,
@@ -1136,7 +1392,7 @@ pub mod tests {
 			.map(|(c, ks)| (c.data.self_key_pair.address(), ks)).collect();
 		let acl_storages_map = clusters.iter().zip(acl_storages.into_iter())
 			.map(|(c, acls)| (c.data.self_key_pair.address(), acls)).collect();
-		MessageLoop { preserve_sessions, messages, key_pairs_map, acl_storages_map, key_storages_map, clusters_map }
+		MessageLoop { preserve_sessions, messages, admin_key_pair, key_pairs_map, acl_storages_map, key_storages_map, clusters_map }
 	}
 
 	#[test]
@@ -1257,14 +1513,14 @@ pub mod tests {
 			// try to start decryption session => fails in initialization
 			assert_eq!(
 				client.new_decryption_session(
-					Default::default(), Default::default(), Requester::Signature(Default::default()), Some(Default::default()), false, false
+					Default::default(), Default::default(), Requester::Signature(Default::default()), Ok(true), Some(Default::default()), false, false
 				).map(|_| ()),
 				Err(Error::InvalidMessage));
 
 			// try to start generation session => fails in initialization
 			assert_eq!(
 				client.new_decryption_session(
-					Default::default(), Default::default(), Requester::Signature(Default::default()), Some(Default::default()), false, false
+					Default::default(), Default::default(), Requester::Signature(Default::default()), Ok(true), Some(Default::default()), false, false
 				).map(|_| ()),
 				Err(Error::InvalidMessage));
 
@@ -1295,7 +1551,7 @@ pub mod tests {
 		let dummy_message = [1u8; 32].into();
 		let signature = sign(Random.generate().secret(), &dummy_message).unwrap();
 		let session0 = ml.cluster(0).client()
-			.new_schnorr_signing_session(dummy_session_id, signature.into(), None, Default::default()).unwrap();
+			.new_schnorr_signing_session(dummy_session_id, signature.into(), Ok(true), None, Default::default()).unwrap();
 		let session = ml.cluster(0).data.sessions.schnorr_signing_sessions.first().unwrap();
 
 		ml.loop_until(|| session.is_finished() && (0..3).all(|i|
@@ -1305,7 +1561,7 @@ pub mod tests {
 		// and try to sign message with generated key using node that has no key share
 		let signature = sign(Random.generate().secret(), &dummy_message).unwrap();
 		let session2 = ml.cluster(2).client()
-			.new_schnorr_signing_session(dummy_session_id, signature.into(), None, Default::default()).unwrap();
+			.new_schnorr_signing_session(dummy_session_id, signature.into(), Ok(true), None, Default::default()).unwrap();
 		let session = ml.cluster(2).data.sessions.schnorr_signing_sessions.first().unwrap();
 
 		ml.loop_until(|| session.is_finished()  && (0..3).all(|i|
@@ -1318,7 +1574,7 @@ pub mod tests {
 		// and try to sign message with generated key
 		let signature = sign(Random.generate().secret(), &dummy_message).unwrap();
 		let session1 = ml.cluster(0).client()
-			.new_schnorr_signing_session(dummy_session_id, signature.into(), None, Default::default()).unwrap();
+			.new_schnorr_signing_session(dummy_session_id, signature.into(), Ok(true), None, Default::default()).unwrap();
 		let session = ml.cluster(0).data.sessions.schnorr_signing_sessions.first().unwrap();
 
 		ml.loop_until(|| session.is_finished());
@@ -1347,7 +1603,7 @@ pub mod tests {
 		let dummy_message = [1u8; 32].into();
 		let signature = sign(Random.generate().secret(), &dummy_message).unwrap();
 		let session0 = ml.cluster(0).client()
-			.new_ecdsa_signing_session(dummy_session_id, signature.into(), None, H256::random()).unwrap();
+			.new_ecdsa_signing_session(dummy_session_id, signature.into(), Ok(true), None, H256::random()).unwrap();
 		let session = ml.cluster(0).data.sessions.ecdsa_signing_sessions.first().unwrap();
 
 		ml.loop_until(|| session.is_finished() && (0..3).all(|i|
@@ -1357,7 +1613,7 @@ pub mod tests {
 		// and try to sign message with generated key using node that has no key share
 		let signature = sign(Random.generate().secret(), &dummy_message).unwrap();
 		let session2 = ml.cluster(2).client()
-			.new_ecdsa_signing_session(dummy_session_id, signature.into(), None, H256::random()).unwrap();
+			.new_ecdsa_signing_session(dummy_session_id, signature.into(), Ok(true), None, H256::random()).unwrap();
 		let session = ml.cluster(2).data.sessions.ecdsa_signing_sessions.first().unwrap();
 		ml.loop_until(|| session.is_finished()  && (0..3).all(|i|
 			ml.cluster(i).data.sessions.ecdsa_signing_sessions.is_empty()));
@@ -1369,9 +1625,79 @@ pub mod tests {
 		// and try to sign message with generated key
 		let signature = sign(Random.generate().secret(), &dummy_message).unwrap();
 		let session1 = ml.cluster(0).client()
-			.new_ecdsa_signing_session(dummy_session_id, signature.into(), None, H256::random()).unwrap();
+			.new_ecdsa_signing_session(dummy_session_id, signature.into(), Ok(true), None, H256::random()).unwrap();
 		let session = ml.cluster(0).data.sessions.ecdsa_signing_sessions.first().unwrap();
 		ml.loop_until(|| session.is_finished());
 		session1.into_wait_future().wait().unwrap_err();
 	}
+
+	#[test]
+	fn client_session_messages_are_processed_in_order_when_acl_check_is_pending() {
+		let ml = make_clusters(3);
+		let dummy_session_id = SessionId::from([1u8; 32]);
+
+		// start && wait for generation session to complete
+		let session = ml.cluster(0).client().
+			new_generation_session(dummy_session_id, Default::default(), Default::default(), 1).unwrap().session;
+		ml.loop_until(|| (session.state() == GenerationSessionState::Finished
+			|| session.state() == GenerationSessionState::Failed)
+			&& ml.cluster(0).client().generation_session(&dummy_session_id).is_none());
+		assert!(session.joint_public_and_secret().unwrap().is_ok());
+
+		// messages to node1 are processed by processor that waits for ACL checks
+		let acl_storage = Arc::new(ManualAclStorage::default());
+		let executor = Arc::new(ManualExecutor::default());
+		let cluster1 = &ml.cluster(1).data;
+		let processor1 = SessionsMessageProcessor::new(
+			cluster1.self_key_pair.clone(),
+			cluster1.servers_set_change_creator_connector.clone(),
+			cluster1.sessions.clone(),
+			cluster1.connections.provider(),
+			acl_storage.clone(),
+			executor.clone(),
+		);
+		let connection = cluster1.connections.provider().connection(&ml.node(0)).unwrap();
+
+		// node0 asks node1 to join consensus and then sends another message of the same session
+		let key_version = ml.key_storage(1).get(&dummy_session_id).unwrap().unwrap().versions.last().unwrap().hash;
+		let sub_session = Random.generate().secret().clone();
+		let make_message = |message| Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningConsensusMessage(
+			SchnorrSigningConsensusMessage {
+				session: dummy_session_id.into(),
+				sub_session: sub_session.clone().into(),
+				session_nonce: 1000,
+				message,
+			}
+		));
+		let requester = Requester::Signature(sign(Random.generate().secret(), &dummy_session_id).unwrap());
+		processor1.process_connection_message(connection.clone(), make_message(
+			ConsensusMessage::InitializeConsensusSession(InitializeConsensusSession {
+				requester: requester.into(),
+				version: key_version.into(),
+			})
+		));
+		processor1.process_connection_message(connection, make_message(
+			ConsensusMessage::ConfirmConsensusInitialization(ConfirmConsensusInitialization {
+				is_confirmed: true,
+			})
+		));
+
+		// nothing is processed while ACL check is pending
+		assert!(cluster1.sessions.schnorr_signing_sessions.is_empty());
+		assert!(ml.is_empty());
+
+		// when check is completed, messages are processed in the order they have been received
+		acl_storage.complete_checks(Ok(true));
+		executor.run();
+		match ml.take_message() {
+			Some((from, _, Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningConsensusMessage(message)))) => {
+				assert_eq!(from, ml.node(1));
+				match message.message {
+					ConsensusMessage::ConfirmConsensusInitialization(message) => assert!(message.is_confirmed),
+					message => panic!("unexpected consensus message: {:?}", message),
+				}
+			},
+			message => panic!("unexpected message: {:?}", message),
+		}
+	}
 }
@@ -14,15 +14,22 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{BTreeMap, VecDeque};
+use std::collections::btree_map::Entry;
 use std::sync::Arc;
+use ethereum_types::Address;
+use futures03::FutureExt;
 use log::{info, trace, warn};
+use parking_lot::Mutex;
+use primitives::acl_storage::{AsyncAclStorage, KeyAccessLevel};
+use primitives::executor::Executor;
 use primitives::key_server_key_pair::KeyServerKeyPair;
 use crate::network::{ConnectionProvider, Connection};
 use crate::key_server_cluster::{Error, NodeId};
 use crate::key_server_cluster::cluster::{ServersSetChangeParams, new_servers_set_change_session};
 use crate::key_server_cluster::cluster_sessions::{AdminSession};
 use crate::key_server_cluster::cluster_sessions::{ClusterSession, ClusterSessions, ClusterSessionsContainer,
-	create_cluster_view};
+	SessionIdWithSubSession, create_cluster_view};
 use crate::key_server_cluster::cluster_sessions_creator::{ClusterSessionCreator, IntoSessionId};
 use crate::key_server_cluster::message::{self, Message, ClusterMessage};
 use crate::key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSession,
@@ -50,11 +57,17 @@ pub trait MessageProcessor: Send + Sync {
 }
 
 /// Bridge between ConnectionManager and ClusterSessions.
+#[derive(Clone)]
 pub struct SessionsMessageProcessor {
 	self_key_pair: Arc<dyn KeyServerKeyPair>,
 	servers_set_change_creator_connector: Arc<dyn ServersSetChangeSessionCreatorConnector>,
 	sessions: Arc<ClusterSessions>,
 	connections: Arc<dyn ConnectionProvider>,
+	acl_storage: Arc<dyn AsyncAclStorage>,
+	executor: Arc<dyn Executor>,
+	/// Messages of client sessions, received from given node. Messages are queued here while
+	/// requester access check is pending, so that they're processed in the order they have been received.
+	key_access_queues: Arc<Mutex<BTreeMap<(NodeId, SessionIdWithSubSession), VecDeque<(Arc<dyn Connection>, Message)>>>>,
 }
 
 impl SessionsMessageProcessor {
@@ -64,21 +77,151 @@ impl SessionsMessageProcessor {
 		servers_set_change_creator_connector: Arc<dyn ServersSetChangeSessionCreatorConnector>,
 		sessions: Arc<ClusterSessions>,
 		connections: Arc<dyn ConnectionProvider>,
+		acl_storage: Arc<dyn AsyncAclStorage>,
+		executor: Arc<dyn Executor>,
 	) -> Self {
 		SessionsMessageProcessor {
 			self_key_pair,
 			servers_set_change_creator_connector,
 			sessions,
 			connections,
+			acl_storage,
+			executor,
+			key_access_queues: Arc::new(Mutex::new(BTreeMap::new())),
+		}
+	}
+
+	/// Process client session message after requester access to the key has been checked.
+	///
+	/// Sessions are unable to wait for the ACL check, so it is awaited here and its result is
+	/// passed to the session along with the message. Messages that are received from the same
+	/// node for the same session while the check is pending are processed after the checked message.
+	fn process_message_with_key_access(&self, connection: Arc<dyn Connection>, message: Message) {
+		let queue_id = match IntoSessionId::<SessionIdWithSubSession>::into_session_id(&message) {
+			Ok(session_id) => (*connection.node_id(), session_id),
+			Err(_) => return self.process_checked_message(connection, message, None),
+		};
+
+		match self.key_access_queues.lock().entry(queue_id.clone()) {
+			// some message is being processed => it'll process this message when done
+			Entry::Occupied(mut queue) => {
+				queue.get_mut().push_back((connection, message));
+				return;
+			},
+			Entry::Vacant(queue) => {
+				queue.insert(vec![(connection, message)].into());
+			},
+		}
+
+		self.process_key_access_queue(queue_id);
+	}
+
+	/// Process queued client session messages until queue is empty, or until ACL check is pending.
+	fn process_key_access_queue(&self, queue_id: (NodeId, SessionIdWithSubSession)) {
+		loop {
+			let (connection, message) = {
+				let mut queues = self.key_access_queues.lock();
+				match queues.get_mut(&queue_id).and_then(|queue| queue.pop_front()) {
+					Some(queued_message) => queued_message,
+					None => {
+						queues.remove(&queue_id);
+						return;
+					},
+				}
+			};
+
+			let (requester_address, mut key_access_check) = match message.key_access_request() {
+				Some((key_id, requester)) => match requester.address(&key_id) {
+					Ok(requester_address) => (
+						requester_address,
+						self.acl_storage.check_access(requester_address, &key_id, KeyAccessLevel::Decrypt),
+					),
+					// invalid requester is rejected by the session itself
+					Err(_) => {
+						self.process_checked_message(connection, message, None);
+						continue;
+					},
+				},
+				None => {
+					self.process_checked_message(connection, message, None);
+					continue;
+				},
+			};
+
+			// do not reschedule message processing if result is already available
+			if let Some(key_access) = (&mut key_access_check).now_or_never() {
+				self.process_checked_message(connection, message, Some((requester_address, key_access)));
+				continue;
+			}
+
+			// queue is not removed => all later messages are queued until the check is completed
+			let processor = self.clone();
+			self.executor.spawn(async move {
+				let key_access = key_access_check.await;
+				processor.process_checked_message(connection, message, Some((requester_address, key_access)));
+				processor.process_key_access_queue(queue_id);
+			}.boxed());
+			return;
+		}
+	}
+
+	/// Process client session message, passing result of requester access check to the session.
+	fn process_checked_message(
+		&self,
+		connection: Arc<dyn Connection>,
+		message: Message,
+		key_access: Option<(Address, Result<bool, Error>)>,
+	) {
+		match message {
+			Message::Decryption(_) => self.process_message_with(
+				&self.sessions.decryption_sessions,
+				connection,
+				message,
+				|session| if let Some((requester_address, key_access)) = key_access {
+					session.set_key_access_check_result(requester_address, key_access);
+				},
+			).map(|_| ()).unwrap_or_default(),
+			Message::SchnorrSigning(_) => self.process_message_with(
+				&self.sessions.schnorr_signing_sessions,
+				connection,
+				message,
+				|session| if let Some((requester_address, key_access)) = key_access {
+					session.set_key_access_check_result(requester_address, key_access);
+				},
+			).map(|_| ()).unwrap_or_default(),
+			Message::EcdsaSigning(_) => self.process_message_with(
+				&self.sessions.ecdsa_signing_sessions,
+				connection,
+				message,
+				|session| if let Some((requester_address, key_access)) = key_access {
+					session.set_key_access_check_result(requester_address, key_access);
+				},
+			).map(|_| ()).unwrap_or_default(),
+			_ => unreachable!("only client sessions messages are passed to process_checked_message; qed"),
 		}
 	}
 
 	/// Process single session message from connection.
 	fn process_message<S: ClusterSession, SC: ClusterSessionCreator<S>>(
+		&self,
+		sessions: &ClusterSessionsContainer<S, SC>,
+		connection: Arc<dyn Connection>,
+		message: Message,
+	) -> Option<Arc<S>>
+		where
+			Message: IntoSessionId<S::Id>
+	{
+		self.process_message_with(sessions, connection, message, |_| ())
+	}
+
+	/// Process single session message from connection. The `on_session_prepared` callback is called
+	/// before the message is passed to the session.
+	fn process_message_with<S: ClusterSession, SC: ClusterSessionCreator<S>, F: FnOnce(&S)>(
 		&self,
 		sessions: &ClusterSessionsContainer<S, SC>,
 		connection: Arc<dyn Connection>,
 		mut message: Message,
+		on_session_prepared: F,
 	) -> Option<Arc<S>>
 		where
 			Message: IntoSessionId<S::Id>
@@ -107,6 +250,7 @@ impl SessionsMessageProcessor {
 				return None;
 			},
 		};
+		on_session_prepared(&session);
 
 		let session_id = session.id();
 		let mut is_queued_message = false;
@@ -168,6 +312,7 @@ impl SessionsMessageProcessor {
 				Message::Generation(_) => true,
 				Message::ShareAdd(_) => true,
 				Message::ServersSetChange(_) => true,
+				Message::KeyVersionsPruning(_) => true,
 				_ => false,
 			}
 		}
@@ -236,15 +381,15 @@ impl MessageProcessor for SessionsMessageProcessor {
 			Message::Encryption(message) => self
 				.process_message(&self.sessions.encryption_sessions, connection, Message::Encryption(message))
 				.map(|_| ()).unwrap_or_default(),
-			Message::Decryption(message) => self
-				.process_message(&self.sessions.decryption_sessions, connection, Message::Decryption(message))
+			Message::KeyDeletion(message) => self
+				.process_message(&self.sessions.key_deletion_sessions, connection, Message::KeyDeletion(message))
 				.map(|_| ()).unwrap_or_default(),
+			Message::Decryption(message) => self
+				.process_message_with_key_access(connection, Message::Decryption(message)),
 			Message::SchnorrSigning(message) => self
-				.process_message(&self.sessions.schnorr_signing_sessions, connection, Message::SchnorrSigning(message))
-				.map(|_| ()).unwrap_or_default(),
+				.process_message_with_key_access(connection, Message::SchnorrSigning(message)),
 			Message::EcdsaSigning(message) => self
-				.process_message(&self.sessions.ecdsa_signing_sessions, connection, Message::EcdsaSigning(message))
-				.map(|_| ()).unwrap_or_default(),
+				.process_message_with_key_access(connection, Message::EcdsaSigning(message)),
 			Message::ServersSetChange(message) => {
 				let message = Message::ServersSetChange(message);
 				let is_initialization_message = message.is_initialization_message();
@@ -264,6 +409,9 @@ impl MessageProcessor for SessionsMessageProcessor {
 			Message::ShareAdd(message) => self.process_message(
 				&self.sessions.admin_sessions, connection, Message::ShareAdd(message))
 				.map(|_| ()).unwrap_or_default(),
+			Message::KeyVersionsPruning(message) => self.process_message(
+				&self.sessions.admin_sessions, connection, Message::KeyVersionsPruning(message))
+				.map(|_| ()).unwrap_or_default(),
 			Message::Cluster(message) => self.process_cluster_message(connection, message),
 		}
 	}
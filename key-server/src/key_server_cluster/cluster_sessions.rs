@@ -16,14 +16,13 @@
 
 use std::time::{Duration, Instant};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::collections::{VecDeque, BTreeMap, BTreeSet};
 use futures::{oneshot, Oneshot, Complete, Future};
 use lazy_static::lazy_static;
 use parking_lot::{Mutex, RwLock, Condvar};
 use ethereum_types::{Address, H256};
 use parity_crypto::publickey::Secret;
-use primitives::acl_storage::AclStorage;
 use primitives::key_storage::KeyStorage;
 use primitives::key_server_key_pair::KeyServerKeyPair;
 use crate::network::ConnectionProvider;
@@ -34,24 +33,28 @@ use crate::key_server_cluster::message::{self, Message};
 use crate::key_server_cluster::generation_session::{SessionImpl as GenerationSessionImpl};
 use crate::key_server_cluster::decryption_session::{SessionImpl as DecryptionSessionImpl};
 use crate::key_server_cluster::encryption_session::{SessionImpl as EncryptionSessionImpl};
+use crate::key_server_cluster::key_deletion_session::{SessionImpl as KeyDeletionSessionImpl};
 use crate::key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSessionImpl};
 use crate::key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSessionImpl};
 use crate::key_server_cluster::share_add_session::{SessionImpl as ShareAddSessionImpl, IsolatedSessionTransport as ShareAddTransport};
-use crate::key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl};
+use crate::key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl, KeySharesRefresh};
 use crate::key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	IsolatedSessionTransport as VersionNegotiationTransport};
+use crate::key_server_cluster::key_versions_pruning_session::{SessionImpl as KeyVersionsPruningSessionImpl};
 
 use crate::key_server_cluster::cluster_sessions_creator::{GenerationSessionCreator, EncryptionSessionCreator, DecryptionSessionCreator,
 	SchnorrSigningSessionCreator, KeyVersionNegotiationSessionCreator, AdminSessionCreator, SessionCreatorCore,
-	EcdsaSigningSessionCreator, ClusterSessionCreator};
+	EcdsaSigningSessionCreator, KeyDeletionSessionCreator, ClusterSessionCreator};
 
-/// When there are no session-related messages for SESSION_TIMEOUT_INTERVAL seconds,
+/// Default session timeout. When there are no session-related messages for this interval,
 /// we must treat this session as stalled && finish it with an error.
 /// This timeout is for cases when node is responding to KeepAlive messages, but intentionally ignores
 /// session messages.
-const SESSION_TIMEOUT_INTERVAL: Duration = Duration::from_secs(60);
-/// Interval to send session-level KeepAlive-messages.
-const SESSION_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_SESSION_TIMEOUT_INTERVAL: Duration = Duration::from_secs(60);
+/// Default interval to send session-level KeepAlive-messages.
+pub const DEFAULT_SESSION_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+/// Default number of most recent restorable key versions, kept by key versions pruning session.
+pub const DEFAULT_KEY_VERSIONS_TO_KEEP: usize = 2;
 
 lazy_static! {
 	/// Servers set change session id (there could be at most 1 session => hardcoded id).
@@ -142,14 +145,18 @@ pub enum AdminSession {
 	ShareAdd(ShareAddSessionImpl<ShareAddTransport>),
 	/// Servers set change session.
 	ServersSetChange(ServersSetChangeSessionImpl),
+	/// Key versions pruning session.
+	KeyVersionsPruning(KeyVersionsPruningSessionImpl),
 }
 
 /// Administrative session creation data.
 pub enum AdminSessionCreationData {
 	/// Share add session (key id).
 	ShareAdd(H256),
-	/// Servers set change session (block id, new_server_set).
-	ServersSetChange(Option<H256>, BTreeSet<NodeId>),
+	/// Servers set change session (block id, new_server_set, keys to refresh shares of).
+	ServersSetChange(Option<H256>, BTreeSet<NodeId>, Option<KeySharesRefresh>),
+	/// Key versions pruning session.
+	KeyVersionsPruning,
 }
 
 /// Active sessions on this cluster.
@@ -158,6 +165,8 @@ pub struct ClusterSessions {
 	pub generation_sessions: ClusterSessionsContainer<GenerationSessionImpl, GenerationSessionCreator>,
 	/// Encryption sessions.
 	pub encryption_sessions: ClusterSessionsContainer<EncryptionSessionImpl, EncryptionSessionCreator>,
+	/// Key deletion sessions.
+	pub key_deletion_sessions: ClusterSessionsContainer<KeyDeletionSessionImpl, KeyDeletionSessionCreator>,
 	/// Decryption sessions.
 	pub decryption_sessions: ClusterSessionsContainer<DecryptionSessionImpl, DecryptionSessionCreator>,
 	/// Schnorr signing sessions.
@@ -175,6 +184,10 @@ pub struct ClusterSessions {
 	self_node_id: NodeId,
 	/// Creator core.
 	creator_core: Arc<SessionCreatorCore>,
+	/// Session is treated as stalled if there are no session-related messages within this interval.
+	session_timeout_interval: Duration,
+	/// Interval to send session-level KeepAlive-messages.
+	session_keep_alive_interval: Duration,
 }
 
 /// Active sessions container listener.
@@ -232,14 +245,15 @@ impl ClusterSessions {
 		self_node_id: NodeId,
 		admin_address: Option<Address>,
 		key_storage: Arc<dyn KeyStorage>,
-		acl_storage: Arc<dyn AclStorage>,
 		servers_set_change_session_creator_connector: Arc<dyn ServersSetChangeSessionCreatorConnector>,
+		session_timeout_interval: Duration,
+		session_keep_alive_interval: Duration,
+		key_versions_to_keep: usize,
 	) -> Self {
 		let container_state = Arc::new(Mutex::new(ClusterSessionsContainerState::Idle));
 		let creator_core = Arc::new(SessionCreatorCore::new(
 			self_node_id,
 			key_storage,
-			acl_storage,
 		));
 		ClusterSessions {
 			self_node_id,
@@ -250,6 +264,9 @@ impl ClusterSessions {
 			encryption_sessions: ClusterSessionsContainer::new(EncryptionSessionCreator {
 				core: creator_core.clone(),
 			}, container_state.clone()),
+			key_deletion_sessions: ClusterSessionsContainer::new(KeyDeletionSessionCreator {
+				core: creator_core.clone(),
+			}, container_state.clone()),
 			decryption_sessions: ClusterSessionsContainer::new(DecryptionSessionCreator {
 				core: creator_core.clone(),
 			}, container_state.clone()),
@@ -266,8 +283,11 @@ impl ClusterSessions {
 				core: creator_core.clone(),
 				servers_set_change_session_creator_connector: servers_set_change_session_creator_connector,
 				admin_address: admin_address,
+				key_versions_to_keep: AtomicUsize::new(key_versions_to_keep),
 			}, container_state),
 			creator_core: creator_core,
+			session_timeout_interval,
+			session_keep_alive_interval,
 		}
 	}
 
@@ -276,10 +296,16 @@ impl ClusterSessions {
 		self.generation_sessions.creator.make_faulty_generation_sessions();
 	}
 
+	#[cfg(test)]
+	pub fn set_key_versions_to_keep(&self, key_versions_to_keep: usize) {
+		self.admin_sessions.creator.set_key_versions_to_keep(key_versions_to_keep);
+	}
+
 	#[cfg(test)]
 	pub fn preserve_sessions(&self) {
 		self.generation_sessions.preserve_sessions.store(true, Ordering::Relaxed);
 		self.encryption_sessions.preserve_sessions.store(true, Ordering::Relaxed);
+		self.key_deletion_sessions.preserve_sessions.store(true, Ordering::Relaxed);
 		self.decryption_sessions.preserve_sessions.store(true, Ordering::Relaxed);
 		self.schnorr_signing_sessions.preserve_sessions.store(true, Ordering::Relaxed);
 		self.ecdsa_signing_sessions.preserve_sessions.store(true, Ordering::Relaxed);
@@ -297,7 +323,11 @@ impl ClusterSessions {
 
 	/// Send session-level keep-alive messages.
 	pub fn sessions_keep_alive(&self) {
-		self.admin_sessions.send_keep_alive(&*SERVERS_SET_CHANGE_SESSION_ID, &self.self_node_id);
+		self.admin_sessions.send_keep_alive(
+			&*SERVERS_SET_CHANGE_SESSION_ID,
+			&self.self_node_id,
+			self.session_keep_alive_interval,
+		);
 	}
 
 	/// When session-level keep-alive response is received.
@@ -309,19 +339,21 @@ impl ClusterSessions {
 
 	/// Stop sessions that are stalling.
 	pub fn stop_stalled_sessions(&self) {
-		self.generation_sessions.stop_stalled_sessions();
-		self.encryption_sessions.stop_stalled_sessions();
-		self.decryption_sessions.stop_stalled_sessions();
-		self.schnorr_signing_sessions.stop_stalled_sessions();
-		self.ecdsa_signing_sessions.stop_stalled_sessions();
-		self.negotiation_sessions.stop_stalled_sessions();
-		self.admin_sessions.stop_stalled_sessions();
+		self.generation_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.encryption_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.key_deletion_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.decryption_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.schnorr_signing_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.ecdsa_signing_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.negotiation_sessions.stop_stalled_sessions(self.session_timeout_interval);
+		self.admin_sessions.stop_stalled_sessions(self.session_timeout_interval);
 	}
 
 	/// When connection to node is lost.
 	pub fn on_connection_timeout(&self, node_id: &NodeId) {
 		self.generation_sessions.on_connection_timeout(node_id);
 		self.encryption_sessions.on_connection_timeout(node_id);
+		self.key_deletion_sessions.on_connection_timeout(node_id);
 		self.decryption_sessions.on_connection_timeout(node_id);
 		self.schnorr_signing_sessions.on_connection_timeout(node_id);
 		self.ecdsa_signing_sessions.on_connection_timeout(node_id);
@@ -418,12 +450,12 @@ impl<S, SC> ClusterSessionsContainer<S, SC> where S: ClusterSession, SC: Cluster
 			.and_then(|session| session.queue.pop_front())
 	}
 
-	pub fn stop_stalled_sessions(&self) {
+	pub fn stop_stalled_sessions(&self, session_timeout_interval: Duration) {
 		let mut sessions = self.sessions.write();
 		for sid in sessions.keys().cloned().collect::<Vec<_>>() {
 			let remove_session = {
 				let session = sessions.get(&sid).expect("enumerating only existing sessions; qed");
-				if Instant::now() - session.last_message_time > SESSION_TIMEOUT_INTERVAL {
+				if Instant::now() - session.last_message_time > session_timeout_interval {
 					session.session.on_session_timeout();
 					session.session.is_finished()
 				} else {
@@ -477,10 +509,10 @@ impl<S, SC> ClusterSessionsContainer<S, SC>
 		SC: ClusterSessionCreator<S>,
 		SessionId: From<S::Id>,
 {
-	pub fn send_keep_alive(&self, session_id: &S::Id, self_node_id: &NodeId) {
+	pub fn send_keep_alive(&self, session_id: &S::Id, self_node_id: &NodeId, session_keep_alive_interval: Duration) {
 		if let Some(session) = self.sessions.write().get_mut(session_id) {
 			let now = Instant::now();
-			if self_node_id == &session.master && now - session.last_keep_alive_time > SESSION_KEEP_ALIVE_INTERVAL {
+			if self_node_id == &session.master && now - session.last_keep_alive_time > session_keep_alive_interval {
 				session.last_keep_alive_time = now;
 				// since we send KeepAlive message to prevent nodes from disconnecting
 				// && worst thing that can happen if node is disconnected is that session is failed
@@ -574,6 +606,13 @@ impl AdminSession {
 			_ => None
 		}
 	}
+
+	pub fn as_key_versions_pruning(&self) -> Option<&KeyVersionsPruningSessionImpl> {
+		match *self {
+			AdminSession::KeyVersionsPruning(ref session) => Some(session),
+			_ => None
+		}
+	}
 }
 
 impl ClusterSession for AdminSession {
@@ -589,6 +628,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.id().clone(),
 			AdminSession::ServersSetChange(ref session) => session.id().clone(),
+			AdminSession::KeyVersionsPruning(ref session) => session.id(),
 		}
 	}
 
@@ -596,6 +636,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.is_finished(),
 			AdminSession::ServersSetChange(ref session) => session.is_finished(),
+			AdminSession::KeyVersionsPruning(ref session) => session.is_finished(),
 		}
 	}
 
@@ -603,6 +644,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_session_timeout(),
 			AdminSession::ServersSetChange(ref session) => session.on_session_timeout(),
+			AdminSession::KeyVersionsPruning(ref session) => session.on_session_timeout(),
 		}
 	}
 
@@ -610,6 +652,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_node_timeout(node_id),
 			AdminSession::ServersSetChange(ref session) => session.on_node_timeout(node_id),
+			AdminSession::KeyVersionsPruning(ref session) => session.on_node_timeout(node_id),
 		}
 	}
 
@@ -617,6 +660,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_session_error(node, error),
 			AdminSession::ServersSetChange(ref session) => session.on_session_error(node, error),
+			AdminSession::KeyVersionsPruning(ref session) => session.on_session_error(node, error),
 		}
 	}
 
@@ -624,6 +668,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_message(sender, message),
 			AdminSession::ServersSetChange(ref session) => session.on_message(sender, message),
+			AdminSession::KeyVersionsPruning(ref session) => session.on_message(sender, message),
 		}
 	}
 }
@@ -684,7 +729,6 @@ mod tests {
 	use std::sync::Arc;
 	use std::sync::atomic::{AtomicUsize, Ordering};
 	use parity_crypto::publickey::{Random, Generator};
-	use primitives::acl_storage::InMemoryPermissiveAclStorage;
 	use primitives::key_storage::InMemoryKeyStorage;
 	use primitives::key_server_key_pair::{KeyServerKeyPair, InMemoryKeyServerKeyPair};
 	use crate::key_server_cluster::{Error, math};
@@ -692,22 +736,24 @@ mod tests {
 	use crate::key_server_cluster::cluster::tests::DummyCluster;
 	use crate::key_server_cluster::generation_session::{SessionImpl as GenerationSession};
 	use super::{ClusterSessions, AdminSessionCreationData, ClusterSessionsListener,
-		ClusterSessionsContainerState, SESSION_TIMEOUT_INTERVAL};
+		ClusterSessionsContainerState, DEFAULT_SESSION_TIMEOUT_INTERVAL, DEFAULT_SESSION_KEEP_ALIVE_INTERVAL,
+		DEFAULT_KEY_VERSIONS_TO_KEEP};
 
 	pub fn make_cluster_sessions() -> ClusterSessions {
 		let key_pair = Random.generate();
 		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(key_pair.clone()));
 		let admin_address = Some(math::generate_random_address().unwrap());
 		let key_storage = Arc::new(InMemoryKeyStorage::default());
-		let acl_storage = Arc::new(InMemoryPermissiveAclStorage::default());
 		ClusterSessions::new(
 			self_key_pair.address(),
 			admin_address,
 			key_storage,
-			acl_storage,
 			Arc::new(SimpleServersSetChangeSessionCreatorConnector {
 				admin_address,
 			}),
+			DEFAULT_SESSION_TIMEOUT_INTERVAL,
+			DEFAULT_SESSION_KEEP_ALIVE_INTERVAL,
+			DEFAULT_KEY_VERSIONS_TO_KEEP,
 		)
 	}
 
@@ -783,9 +829,9 @@ mod tests {
 		sessions.generation_sessions.insert(Arc::new(DummyCluster::new(Default::default())), Default::default(), Default::default(), None, false, None).unwrap();
 		assert_eq!(*sessions.generation_sessions.container_state.lock(), ClusterSessionsContainerState::Active(1));
 
-		sessions.generation_sessions.sessions.write().get_mut(&Default::default()).unwrap().last_message_time -= SESSION_TIMEOUT_INTERVAL * 2;
+		sessions.generation_sessions.sessions.write().get_mut(&Default::default()).unwrap().last_message_time -= DEFAULT_SESSION_TIMEOUT_INTERVAL * 2;
 
-		sessions.generation_sessions.stop_stalled_sessions();
+		sessions.generation_sessions.stop_stalled_sessions(sessions.session_timeout_interval);
 		assert_eq!(sessions.generation_sessions.sessions.read().len(), 0);
 		assert_eq!(*sessions.generation_sessions.container_state.lock(), ClusterSessionsContainerState::Idle);
 	}
@@ -19,7 +19,6 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::collections::BTreeMap;
 use parking_lot::RwLock;
 use parity_crypto::publickey::Address;
-use primitives::acl_storage::AclStorage;
 use primitives::key_storage::{KeyStorage, KeyShare};
 use crate::key_server_cluster::{Error, NodeId, SessionId, Requester, SessionMeta};
 use crate::key_server_cluster::cluster::Cluster;
@@ -27,11 +26,13 @@ use crate::key_server_cluster::connection_trigger::ServersSetChangeSessionCreato
 use crate::key_server_cluster::cluster_sessions::{WaitableSession, ClusterSession, SessionIdWithSubSession,
 	AdminSession, AdminSessionCreationData};
 use crate::key_server_cluster::message::{self, Message, DecryptionMessage, SchnorrSigningMessage, ConsensusMessageOfShareAdd,
-	ShareAddMessage, ServersSetChangeMessage, ConsensusMessage, ConsensusMessageWithServersSet, EcdsaSigningMessage};
+	ShareAddMessage, ServersSetChangeMessage, ConsensusMessage, ConsensusMessageWithServersSet, EcdsaSigningMessage,
+	KeyVersionsPruningMessage, KeyDeletionMessage};
 use crate::key_server_cluster::generation_session::{SessionImpl as GenerationSessionImpl, SessionParams as GenerationSessionParams};
 use crate::key_server_cluster::decryption_session::{SessionImpl as DecryptionSessionImpl,
 	SessionParams as DecryptionSessionParams};
 use crate::key_server_cluster::encryption_session::{SessionImpl as EncryptionSessionImpl, SessionParams as EncryptionSessionParams};
+use crate::key_server_cluster::key_deletion_session::{SessionImpl as KeyDeletionSessionImpl, SessionParams as KeyDeletionSessionParams};
 use crate::key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSessionImpl,
 	SessionParams as EcdsaSigningSessionParams};
 use crate::key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSessionImpl,
@@ -39,10 +40,12 @@ use crate::key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrS
 use crate::key_server_cluster::share_add_session::{SessionImpl as ShareAddSessionImpl,
 	SessionParams as ShareAddSessionParams, IsolatedSessionTransport as ShareAddTransport};
 use crate::key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl,
-	SessionParams as ServersSetChangeSessionParams};
+	SessionParams as ServersSetChangeSessionParams, KeySharesRefresh};
 use crate::key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	SessionParams as KeyVersionNegotiationSessionParams, IsolatedSessionTransport as VersionNegotiationTransport,
 	FastestResultComputer as FastestResultKeyVersionsResultComputer};
+use crate::key_server_cluster::key_versions_pruning_session::{SessionImpl as KeyVersionsPruningSessionImpl,
+	SessionParams as KeyVersionsPruningSessionParams};
 use crate::key_server_cluster::admin_sessions::ShareChangeSessionMeta;
 
 /// Generic cluster session creator.
@@ -77,8 +80,6 @@ pub struct SessionCreatorCore {
 	self_node_id: NodeId,
 	/// Reference to key storage
 	key_storage: Arc<dyn KeyStorage>,
-	/// Reference to ACL storage
-	acl_storage: Arc<dyn AclStorage>,
 	/// Always-increasing sessions counter. Is used as session nonce to prevent replay attacks:
 	/// 1) during handshake, KeyServers generate new random key to encrypt messages
 	/// => there's no way to use messages from previous connections for replay attacks
@@ -98,11 +99,9 @@ impl SessionCreatorCore {
 	pub fn new(
 		self_node_id: NodeId,
 		key_storage: Arc<dyn KeyStorage>,
-		acl_storage: Arc<dyn AclStorage>,
 	) -> Self {
 		SessionCreatorCore {
 			self_node_id,
-			acl_storage,
 			key_storage,
 			session_counter: AtomicUsize::new(0),
 			max_nonce: RwLock::new(BTreeMap::new()),
@@ -169,6 +168,10 @@ impl ClusterSessionCreator<GenerationSessionImpl> for GenerationSessionCreator {
 		if self.core.key_storage.contains(&id) {
 			return Err(Error::ServerKeyAlreadyGenerated);
 		}
+		// check that the key with the same id has not been deleted
+		if self.core.key_storage.is_deleted(&id)? {
+			return Err(Error::ServerKeyIsDeleted);
+		}
 
 		let nonce = self.core.check_session_nonce(&master, nonce)?;
 		let (session, oneshot) = GenerationSessionImpl::new(GenerationSessionParams {
@@ -225,6 +228,42 @@ impl ClusterSessionCreator<EncryptionSessionImpl> for EncryptionSessionCreator {
 	}
 }
 
+/// Key deletion session creator.
+pub struct KeyDeletionSessionCreator {
+	/// Creator core.
+	pub core: Arc<SessionCreatorCore>,
+}
+
+impl ClusterSessionCreator<KeyDeletionSessionImpl> for KeyDeletionSessionCreator {
+	fn make_error_message(sid: SessionId, nonce: u64, err: Error) -> Message {
+		message::Message::KeyDeletion(message::KeyDeletionMessage::KeyDeletionSessionError(message::KeyDeletionSessionError {
+			session: sid.into(),
+			session_nonce: nonce,
+			error: err.into(),
+		}))
+	}
+
+	fn create(
+		&self,
+		cluster: Arc<dyn Cluster>,
+		master: NodeId,
+		nonce: Option<u64>,
+		id: SessionId,
+		_creation_data: Option<()>,
+	) -> Result<WaitableSession<KeyDeletionSessionImpl>, Error> {
+		let nonce = self.core.check_session_nonce(&master, nonce)?;
+		let (session, oneshot) = KeyDeletionSessionImpl::new(KeyDeletionSessionParams {
+			id: id,
+			self_node_id: self.core.self_node_id.clone(),
+			key_storage: self.core.key_storage.clone(),
+			cluster: cluster,
+			nonce: nonce,
+		})?;
+
+		Ok(WaitableSession::new(session, oneshot))
+	}
+}
+
 /// Decryption session creator.
 pub struct DecryptionSessionCreator {
 	/// Creator core.
@@ -273,7 +312,6 @@ impl ClusterSessionCreator<DecryptionSessionImpl> for DecryptionSessionCreator {
 			},
 			access_key: id.access_key,
 			key_share: encrypted_data,
-			acl_storage: self.core.acl_storage.clone(),
 			cluster: cluster,
 			nonce: nonce,
 		}, requester)?;
@@ -330,7 +368,6 @@ impl ClusterSessionCreator<SchnorrSigningSessionImpl> for SchnorrSigningSessionC
 			},
 			access_key: id.access_key,
 			key_share: encrypted_data,
-			acl_storage: self.core.acl_storage.clone(),
 			cluster: cluster,
 			nonce: nonce,
 		}, requester)?;
@@ -379,7 +416,6 @@ impl ClusterSessionCreator<EcdsaSigningSessionImpl> for EcdsaSigningSessionCreat
 			},
 			access_key: id.access_key,
 			key_share: encrypted_data,
-			acl_storage: self.core.acl_storage.clone(),
 			cluster: cluster,
 			nonce: nonce,
 		}, requester)?;
@@ -452,6 +488,15 @@ pub struct AdminSessionCreator {
 	pub admin_address: Option<Address>,
 	/// Servers set change sessions creator connector.
 	pub servers_set_change_session_creator_connector: Arc<dyn ServersSetChangeSessionCreatorConnector>,
+	/// Number of most recent restorable key versions, kept by key versions pruning sessions.
+	pub key_versions_to_keep: AtomicUsize,
+}
+
+impl AdminSessionCreator {
+	#[cfg(test)]
+	pub fn set_key_versions_to_keep(&self, key_versions_to_keep: usize) {
+		self.key_versions_to_keep.store(key_versions_to_keep, Ordering::Relaxed);
+	}
 }
 
 impl ClusterSessionCreator<AdminSession> for AdminSessionCreator {
@@ -460,7 +505,12 @@ impl ClusterSessionCreator<AdminSession> for AdminSessionCreator {
 			Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(ref message)) => match &message.message {
 				&ConsensusMessageWithServersSet::InitializeConsensusSession(ref message) => Ok(Some(AdminSessionCreationData::ServersSetChange(
 					message.migration_id.clone().map(Into::into),
-					message.new_nodes_set.clone().into_iter().map(Into::into).collect()
+					message.new_nodes_set.clone().into_iter().map(Into::into).collect(),
+					match (message.refresh_key_shares, message.refreshed_key_id.clone()) {
+						(false, _) => None,
+						(true, None) => Some(KeySharesRefresh::AllKeys),
+						(true, Some(key_id)) => Some(KeySharesRefresh::Key(key_id.into())),
+					},
 				))),
 				_ => Err(Error::InvalidMessage),
 			},
@@ -468,6 +518,8 @@ impl ClusterSessionCreator<AdminSession> for AdminSessionCreator {
 				&ConsensusMessageOfShareAdd::InitializeConsensusSession(ref message) => Ok(Some(AdminSessionCreationData::ShareAdd(message.version.clone().into()))),
 				_ => Err(Error::InvalidMessage),
 			},
+			Message::KeyVersionsPruning(KeyVersionsPruningMessage::InitializeKeyVersionsPruning(_)) =>
+				Ok(Some(AdminSessionCreationData::KeyVersionsPruning)),
 			_ => Err(Error::InvalidMessage),
 		}
 	}
@@ -506,7 +558,7 @@ impl ClusterSessionCreator<AdminSession> for AdminSessionCreator {
 				})?;
 				Ok(WaitableSession::new(AdminSession::ShareAdd(session), oneshot))
 			},
-			Some(AdminSessionCreationData::ServersSetChange(migration_id, new_nodes_set)) => {
+			Some(AdminSessionCreationData::ServersSetChange(migration_id, new_nodes_set, shares_refresh)) => {
 				let admin_address = self.servers_set_change_session_creator_connector.admin_address(migration_id.as_ref(), new_nodes_set)
 					.map_err(|_| Error::AccessDenied)?;
 
@@ -524,9 +576,22 @@ impl ClusterSessionCreator<AdminSession> for AdminSessionCreator {
 					all_nodes_set: cluster.nodes(),
 					admin_address: admin_address,
 					migration_id: migration_id,
+					shares_refresh: shares_refresh,
 				})?;
 				Ok(WaitableSession::new(AdminSession::ServersSetChange(session), oneshot))
 			},
+			Some(AdminSessionCreationData::KeyVersionsPruning) => {
+				let (session, oneshot) = KeyVersionsPruningSessionImpl::new(KeyVersionsPruningSessionParams {
+					id: id,
+					self_node_id: self.core.self_node_id.clone(),
+					key_storage: self.core.key_storage.clone(),
+					cluster: cluster,
+					admin_address: self.admin_address.clone().ok_or(Error::AccessDenied)?,
+					versions_to_keep: self.key_versions_to_keep.load(Ordering::Relaxed),
+					nonce: nonce,
+				})?;
+				Ok(WaitableSession::new(AdminSession::KeyVersionsPruning(session), oneshot))
+			},
 			None => unreachable!("expected to call with non-empty creation data; qed"),
 		}
 	}
@@ -537,12 +602,14 @@ impl IntoSessionId<SessionId> for Message {
 		match *self {
 			Message::Generation(ref message) => Ok(message.session_id().clone()),
 			Message::Encryption(ref message) => Ok(message.session_id().clone()),
+			Message::KeyDeletion(ref message) => Ok(message.session_id().clone()),
 			Message::Decryption(_) => Err(Error::InvalidMessage),
 			Message::SchnorrSigning(_) => Err(Error::InvalidMessage),
 			Message::EcdsaSigning(_) => Err(Error::InvalidMessage),
 			Message::ServersSetChange(ref message) => Ok(message.session_id().clone()),
 			Message::ShareAdd(ref message) => Ok(message.session_id().clone()),
 			Message::KeyVersionNegotiation(_) => Err(Error::InvalidMessage),
+			Message::KeyVersionsPruning(ref message) => Ok(message.session_id().clone()),
 			Message::Cluster(_) => Err(Error::InvalidMessage),
 		}
 	}
@@ -553,12 +620,14 @@ impl IntoSessionId<SessionIdWithSubSession> for Message {
 		match *self {
 			Message::Generation(_) => Err(Error::InvalidMessage),
 			Message::Encryption(_) => Err(Error::InvalidMessage),
+			Message::KeyDeletion(_) => Err(Error::InvalidMessage),
 			Message::Decryption(ref message) => Ok(SessionIdWithSubSession::new(message.session_id().clone(), message.sub_session_id().clone())),
 			Message::SchnorrSigning(ref message) => Ok(SessionIdWithSubSession::new(message.session_id().clone(), message.sub_session_id().clone())),
 			Message::EcdsaSigning(ref message) => Ok(SessionIdWithSubSession::new(message.session_id().clone(), message.sub_session_id().clone())),
 			Message::ServersSetChange(_) => Err(Error::InvalidMessage),
 			Message::ShareAdd(_) => Err(Error::InvalidMessage),
 			Message::KeyVersionNegotiation(ref message) => Ok(SessionIdWithSubSession::new(message.session_id().clone(), message.sub_session_id().clone())),
+			Message::KeyVersionsPruning(_) => Err(Error::InvalidMessage),
 			Message::Cluster(_) => Err(Error::InvalidMessage),
 		}
 	}
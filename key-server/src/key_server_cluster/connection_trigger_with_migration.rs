@@ -312,6 +312,7 @@ impl<NetworkAddress: Send + Sync> TriggerSession<NetworkAddress> {
 					new_nodes_set: new_set,
 					old_set_signature,
 					new_set_signature,
+					shares_refresh: None,
 				})
 			},
 			Err(err) => {
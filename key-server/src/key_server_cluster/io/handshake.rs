@@ -28,12 +28,28 @@
 ///! 9) nodes exchange with `NodePrivateKeySignature` messages, containing `confirmation_signed`
 ///! 10) both nodes are checking that `confirmation_signed` is actually signed with the owner of peer' `self_key_pair.secret`
 ///!
+///! `NodePublicKey` messages also carry maximal header version, supported by the node. All handshake messages
+///! are serialized using JSON codec, which is supported by all nodes. Following messages are serialized using
+///! codec that corresponds to the minimal of self and peer versions. Versions are sent unprotected, so
+///! `NodePrivateKeySignature` messages also carry `message.confirmation_plain` and both versions (as they
+///! are seen by the node), signed with `self_key_pair.private`. Both nodes are checking that peer has seen
+///! the same versions, so versions can't be downgraded by the third party. Nodes that only support JSON codec
+///! are not signing versions.
+///!
+///! If both nodes support session keys, both nodes are deriving symmetric keys (one set per direction)
+///! from the shared session key pair. All messages that follow the handshake are then encrypted and
+///! authenticated with these keys, instead of ECIES. Every message carries its own nonce, so replayed
+///! messages are rejected.
+///!
 ///! Result of handshake is:
 ///! 1) belief, that we are connected to the KS from our KS-set
 ///! 2) session key pair, which is used to enrypt all connection messages
+///! 3) header version, which is used to serialize all connection messages
+///! 4) optional symmetric session keys, which are used instead of session key pair
 
 use std::io;
 use std::sync::Arc;
+use keccak_hash::keccak;
 use std::collections::BTreeSet;
 use futures::{try_ready, Future, Poll, Async};
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -44,7 +60,8 @@ use primitives::key_server_key_pair::KeyServerKeyPair;
 use crate::key_server_cluster::{NodeId, Error};
 use crate::key_server_cluster::message::{Message, ClusterMessage, NodePublicKey, NodePrivateKeySignature};
 use crate::key_server_cluster::io::{write_message, write_encrypted_message, WriteMessage, ReadMessage,
-	read_message, read_encrypted_message, fix_shared_key};
+	read_message, read_encrypted_message, fix_shared_key, negotiate_header_version, ConnectionKey, SessionKeys,
+	CURRENT_HEADER_VERSION, HANDSHAKE_HEADER_VERSION, SESSION_KEYS_HEADER_VERSION};
 
 /// Start handshake procedure with another node from the cluster.
 pub fn handshake<A>(a: A, self_key_pair: Arc<dyn KeyServerKeyPair>, trusted_nodes: BTreeSet<NodeId>) -> Handshake<A> where A: AsyncWrite + AsyncRead {
@@ -77,6 +94,8 @@ pub fn handshake_with_init_data<A>(a: A, init_data: Result<(H256, KeyPair), Erro
 		peer_node_id: None,
 		peer_session_public: None,
 		peer_confirmation_plain: None,
+		peer_max_header_version: None,
+		header_version: None,
 		shared_key: None,
 	}
 }
@@ -97,6 +116,8 @@ pub fn accept_handshake<A>(a: A, self_key_pair: Arc<dyn KeyServerKeyPair>) -> Ha
 		peer_node_id: None,
 		peer_session_public: None,
 		peer_confirmation_plain: None,
+		peer_max_header_version: None,
+		header_version: None,
 		shared_key: None,
 	}
 }
@@ -108,6 +129,20 @@ pub struct HandshakeResult {
 	pub node_id: NodeId,
 	/// Shared key.
 	pub shared_key: KeyPair,
+	/// Header version that is used to serialize connection messages.
+	pub header_version: u64,
+	/// Symmetric session keys. None if peer doesn't support session keys.
+	pub session_keys: Option<SessionKeys>,
+}
+
+impl HandshakeResult {
+	/// Key that is used to protect connection messages.
+	pub fn connection_key(&self) -> ConnectionKey {
+		match self.session_keys {
+			Some(ref session_keys) => ConnectionKey::session(session_keys.clone()),
+			None => ConnectionKey::SharedKeyPair(self.shared_key.clone()),
+		}
+	}
 }
 
 /// Future handshake procedure.
@@ -122,6 +157,8 @@ pub struct Handshake<A> {
 	peer_node_id: Option<NodeId>,
 	peer_session_public: Option<Public>,
 	peer_confirmation_plain: Option<H256>,
+	peer_max_header_version: Option<u64>,
+	header_version: Option<u64>,
 	shared_key: Option<KeyPair>,
 }
 
@@ -150,12 +187,15 @@ impl<A> Handshake<A> where A: AsyncRead + AsyncWrite {
 			node_id: self_node_id.into(),
 			confirmation_plain: confirmation_plain.into(),
 			confirmation_signed_session: confirmation_signed_session.into(),
+			max_header_version: Some(CURRENT_HEADER_VERSION),
 		})))
 	}
 
-	fn make_private_key_signature_message(self_key_pair: &dyn KeyServerKeyPair, confirmation_plain: &H256) -> Result<Message, Error> {
+	fn make_private_key_signature_message(self_key_pair: &dyn KeyServerKeyPair, confirmation_plain: &H256, peer_max_header_version: Option<u64>) -> Result<Message, Error> {
+		let header_versions = header_versions_hash(confirmation_plain, Some(CURRENT_HEADER_VERSION), peer_max_header_version);
 		Ok(Message::Cluster(ClusterMessage::NodePrivateKeySignature(NodePrivateKeySignature {
 			confirmation_signed: self_key_pair.sign(confirmation_plain)?.into(),
+			header_versions_signed: Some(self_key_pair.sign(&header_versions)?.into()),
 		})))
 	}
 
@@ -198,13 +238,14 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 
 					let peer_confirmation_plain = self.peer_confirmation_plain.as_ref()
 						.expect("we are in passive mode; in passive mode SendPublicKey follows ReceivePublicKey; peer_confirmation_plain is filled in ReceivePublicKey; qed");
-					let message = match Handshake::<A>::make_private_key_signature_message(&*self.self_key_pair, peer_confirmation_plain) {
+					let message = match Handshake::<A>::make_private_key_signature_message(&*self.self_key_pair, peer_confirmation_plain, self.peer_max_header_version) {
 						Ok(message) => message,
 						Err(err) => return Ok((stream, Err(err)).into()),
 					};
 
 					(HandshakeState::SendPrivateKeySignature(write_encrypted_message(stream,
-						self.shared_key.as_ref().expect("filled couple of lines above; qed"),
+						&ConnectionKey::SharedKeyPair(self.shared_key.clone().expect("filled couple of lines above; qed")),
+						HANDSHAKE_HEADER_VERSION,
 					message)), Async::NotReady)
 				}
 			},
@@ -229,6 +270,8 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 					Err(err) => return Ok((stream, Err(err.into())).into()),
 				});
 				self.peer_confirmation_plain = Some(message.confirmation_plain.into());
+				self.peer_max_header_version = message.max_header_version;
+				self.header_version = Some(negotiate_header_version(message.max_header_version));
 				if self.is_active {
 					let shared_key = Self::compute_shared_key(
 						self.self_session_key_pair.as_ref().expect(
@@ -244,13 +287,14 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 
 					let peer_confirmation_plain = self.peer_confirmation_plain.as_ref()
 						.expect("filled couple of lines above; qed");
-					let message = match Handshake::<A>::make_private_key_signature_message(&*self.self_key_pair, peer_confirmation_plain) {
+					let message = match Handshake::<A>::make_private_key_signature_message(&*self.self_key_pair, peer_confirmation_plain, self.peer_max_header_version) {
 						Ok(message) => message,
 						Err(err) => return Ok((stream, Err(err)).into()),
 					};
 
 					(HandshakeState::SendPrivateKeySignature(write_encrypted_message(stream,
-						self.shared_key.as_ref().expect("filled couple of lines above; qed"),
+						&ConnectionKey::SharedKeyPair(self.shared_key.clone().expect("filled couple of lines above; qed")),
+						HANDSHAKE_HEADER_VERSION,
 					message)), Async::NotReady)
 				} else {
 					let self_session_key_pair = self.self_session_key_pair.as_ref()
@@ -271,9 +315,9 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 				let (stream, _) = try_ready!(future.poll());
 
 				(HandshakeState::ReceivePrivateKeySignature(
-					read_encrypted_message(stream,
-						self.shared_key.as_ref().expect("shared_key is filled in Send/ReceivePublicKey; SendPrivateKeySignature follows Send/ReceivePublicKey; qed").clone()
-					)
+					read_encrypted_message(stream, ConnectionKey::SharedKeyPair(
+						self.shared_key.clone().expect("shared_key is filled in Send/ReceivePublicKey; SendPrivateKeySignature follows Send/ReceivePublicKey; qed")
+					))
 				), Async::NotReady)
 			},
 			HandshakeState::ReceivePrivateKeySignature(ref mut future) => {
@@ -292,9 +336,32 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 					return Ok((stream, Err(Error::InvalidMessage)).into());
 				}
 
+				// nodes that only support JSON codec are not signing header versions
+				let are_header_versions_confirmed = match message.header_versions_signed {
+					Some(ref header_versions_signed) => {
+						let header_versions = header_versions_hash(&self.self_confirmation_plain, self.peer_max_header_version, Some(CURRENT_HEADER_VERSION));
+						verify_address(peer_address, &*header_versions_signed, &header_versions).unwrap_or(false)
+					},
+					None => self.peer_max_header_version.is_none(),
+				};
+				if !are_header_versions_confirmed {
+					return Ok((stream, Err(Error::InvalidMessage)).into());
+				}
+
+				let node_id = self.peer_node_id.expect("peer_node_id is filled in ReceivePublicKey; ReceivePrivateKeySignature follows ReceivePublicKey; qed");
+				let shared_key = self.shared_key.clone().expect("shared_key is filled in Send/ReceivePublicKey; ReceivePrivateKeySignature follows Send/ReceivePublicKey; qed");
+				let header_version = self.header_version.expect("header_version is filled in ReceivePublicKey; ReceivePrivateKeySignature follows ReceivePublicKey; qed");
+				let session_keys = if header_version >= SESSION_KEYS_HEADER_VERSION {
+					Some(SessionKeys::derive(&shared_key, &self.self_key_pair.address(), &node_id))
+				} else {
+					None
+				};
+
 				(HandshakeState::Finished, Async::Ready((stream, Ok(HandshakeResult {
-					node_id: self.peer_node_id.expect("peer_node_id is filled in ReceivePublicKey; ReceivePrivateKeySignature follows ReceivePublicKey; qed"),
-					shared_key: self.shared_key.clone().expect("shared_key is filled in Send/ReceivePublicKey; ReceivePrivateKeySignature follows Send/ReceivePublicKey; qed"),
+					node_id,
+					shared_key,
+					header_version,
+					session_keys,
 				}))))
 			},
 			HandshakeState::Finished => panic!("poll Handshake after it's done"),
@@ -309,6 +376,15 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 	}
 }
 
+/// Compute hash of header versions, seen by the node during handshake. Node that is signing the hash
+/// passes its own version as `signer_max_header_version`.
+fn header_versions_hash(confirmation_plain: &H256, signer_max_header_version: Option<u64>, peer_max_header_version: Option<u64>) -> H256 {
+	let mut data = confirmation_plain.as_bytes().to_vec();
+	data.extend_from_slice(&signer_max_header_version.unwrap_or_default().to_le_bytes());
+	data.extend_from_slice(&peer_max_header_version.unwrap_or_default().to_le_bytes());
+	keccak(data)
+}
+
 #[cfg(test)]
 mod tests {
 	use std::sync::Arc;
@@ -318,10 +394,21 @@ mod tests {
 	use ethereum_types::H256;
 	use primitives::key_server_key_pair::InMemoryKeyServerKeyPair;
 	use crate::key_server_cluster::io::message::tests::TestIo;
+	use crate::key_server_cluster::io::message::{CURRENT_HEADER_VERSION, JSON_HEADER_VERSION};
 	use crate::key_server_cluster::message::{Message, ClusterMessage, NodePublicKey, NodePrivateKeySignature};
-	use super::{handshake_with_init_data, accept_handshake, HandshakeResult};
+	use crate::key_server_cluster::Error;
+	use crate::key_server_cluster::io::SessionKeys;
+	use super::{handshake_with_init_data, accept_handshake, header_versions_hash, HandshakeResult};
 
 	fn prepare_test_io() -> (H256, TestIo) {
+		prepare_test_io_with_peer_version(Some(CURRENT_HEADER_VERSION))
+	}
+
+	fn prepare_test_io_with_peer_version(peer_max_header_version: Option<u64>) -> (H256, TestIo) {
+		prepare_test_io_with_peer_versions(peer_max_header_version, peer_max_header_version)
+	}
+
+	fn prepare_test_io_with_peer_versions(advertised_max_header_version: Option<u64>, signed_max_header_version: Option<u64>) -> (H256, TestIo) {
 		let mut io = TestIo::new();
 
 		let self_confirmation_plain = *Random.generate().secret().clone();
@@ -329,15 +416,21 @@ mod tests {
 
 		let self_confirmation_signed = sign(io.peer_key_pair().secret(), &self_confirmation_plain).unwrap();
 		let peer_confirmation_signed = sign(io.peer_session_key_pair().secret(), &peer_confirmation_plain).unwrap();
+		let header_versions_signed = signed_max_header_version.map(|signed_max_header_version| {
+			let header_versions = header_versions_hash(&self_confirmation_plain, Some(signed_max_header_version), Some(CURRENT_HEADER_VERSION));
+			sign(io.peer_key_pair().secret(), &header_versions).unwrap().into()
+		});
 
 		let peer_address = io.peer_key_pair().address();
 		io.add_input_message(Message::Cluster(ClusterMessage::NodePublicKey(NodePublicKey {
 			node_id: peer_address.into(),
 			confirmation_plain: peer_confirmation_plain.into(),
 			confirmation_signed_session: peer_confirmation_signed.into(),
+			max_header_version: advertised_max_header_version,
 		})));
 		io.add_encrypted_input_message(Message::Cluster(ClusterMessage::NodePrivateKeySignature(NodePrivateKeySignature {
 			confirmation_signed: self_confirmation_signed.into(),
+			header_versions_signed,
 		})));
 
 		(self_confirmation_plain, io)
@@ -350,12 +443,15 @@ mod tests {
 		let self_session_key_pair = io.self_session_key_pair().clone();
 		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(io.self_key_pair().clone()));
 		let shared_key = io.shared_key_pair().clone();
+		let session_keys = SessionKeys::derive(&shared_key, &io.self_key_pair().address(), &io.peer_key_pair().address());
 
 		let handshake = handshake_with_init_data(io, Ok((self_confirmation_plain, self_session_key_pair)), self_key_pair, trusted_nodes);
 		let handshake_result = handshake.wait().unwrap();
 		assert_eq!(handshake_result.1, Ok(HandshakeResult {
 			node_id: handshake_result.0.peer_key_pair().address(),
 			shared_key: shared_key,
+			header_version: CURRENT_HEADER_VERSION,
+			session_keys: Some(session_keys),
 		}));
 	}
 
@@ -365,6 +461,7 @@ mod tests {
 		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(io.self_key_pair().clone()));
 		let self_session_key_pair = io.self_session_key_pair().clone();
 		let shared_key = io.shared_key_pair().clone();
+		let session_keys = SessionKeys::derive(&shared_key, &io.self_key_pair().address(), &io.peer_key_pair().address());
 
 		let mut handshake = accept_handshake(io, self_key_pair);
 		handshake.set_self_confirmation_plain(self_confirmation_plain);
@@ -374,6 +471,59 @@ mod tests {
 		assert_eq!(handshake_result.1, Ok(HandshakeResult {
 			node_id: handshake_result.0.peer_key_pair().address(),
 			shared_key: shared_key,
+			header_version: CURRENT_HEADER_VERSION,
+			session_keys: Some(session_keys),
+		}));
+	}
+
+	#[test]
+	fn handshake_with_legacy_node_negotiates_json_codec() {
+		let (self_confirmation_plain, io) = prepare_test_io_with_peer_version(None);
+		let trusted_nodes: BTreeSet<_> = vec![io.peer_key_pair().address()].into_iter().collect();
+		let self_session_key_pair = io.self_session_key_pair().clone();
+		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(io.self_key_pair().clone()));
+		let shared_key = io.shared_key_pair().clone();
+
+		let handshake = handshake_with_init_data(io, Ok((self_confirmation_plain, self_session_key_pair)), self_key_pair, trusted_nodes);
+		let handshake_result = handshake.wait().unwrap();
+		assert_eq!(handshake_result.1, Ok(HandshakeResult {
+			node_id: handshake_result.0.peer_key_pair().address(),
+			shared_key: shared_key,
+			header_version: JSON_HEADER_VERSION,
+			session_keys: None,
 		}));
 	}
+
+	#[test]
+	fn handshake_fails_when_peer_header_version_is_downgraded() {
+		let (self_confirmation_plain, io) = prepare_test_io_with_peer_versions(Some(JSON_HEADER_VERSION), Some(CURRENT_HEADER_VERSION));
+		let trusted_nodes: BTreeSet<_> = vec![io.peer_key_pair().address()].into_iter().collect();
+		let self_session_key_pair = io.self_session_key_pair().clone();
+		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(io.self_key_pair().clone()));
+
+		let handshake = handshake_with_init_data(io, Ok((self_confirmation_plain, self_session_key_pair)), self_key_pair, trusted_nodes);
+		assert_eq!(handshake.wait().unwrap().1, Err(Error::InvalidMessage));
+	}
+
+	#[test]
+	fn handshake_fails_when_peer_header_version_is_removed() {
+		let (self_confirmation_plain, io) = prepare_test_io_with_peer_versions(None, Some(CURRENT_HEADER_VERSION));
+		let trusted_nodes: BTreeSet<_> = vec![io.peer_key_pair().address()].into_iter().collect();
+		let self_session_key_pair = io.self_session_key_pair().clone();
+		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(io.self_key_pair().clone()));
+
+		let handshake = handshake_with_init_data(io, Ok((self_confirmation_plain, self_session_key_pair)), self_key_pair, trusted_nodes);
+		assert_eq!(handshake.wait().unwrap().1, Err(Error::InvalidMessage));
+	}
+
+	#[test]
+	fn handshake_fails_when_peer_header_versions_are_not_signed() {
+		let (self_confirmation_plain, io) = prepare_test_io_with_peer_versions(Some(CURRENT_HEADER_VERSION), None);
+		let trusted_nodes: BTreeSet<_> = vec![io.peer_key_pair().address()].into_iter().collect();
+		let self_session_key_pair = io.self_session_key_pair().clone();
+		let self_key_pair = Arc::new(InMemoryKeyServerKeyPair::new(io.self_key_pair().clone()));
+
+		let handshake = handshake_with_init_data(io, Ok((self_confirmation_plain, self_session_key_pair)), self_key_pair, trusted_nodes);
+		assert_eq!(handshake.wait().unwrap().1, Err(Error::InvalidMessage));
+	}
 }
@@ -18,6 +18,7 @@ use std::io::Cursor;
 use std::u16;
 use std::ops::Deref;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{Serialize, de::DeserializeOwned};
 use serde_json;
 use parity_crypto::publickey::ecies;
 use parity_crypto::publickey::{Secret, KeyPair};
@@ -25,12 +26,29 @@ use parity_crypto::publickey::ec_math_utils::CURVE_ORDER;
 use ethereum_types::{H256, U256, BigEndianHash};
 use crate::key_server_cluster::Error;
 use crate::key_server_cluster::message::{Message, ClusterMessage, GenerationMessage, EncryptionMessage, DecryptionMessage,
-	SchnorrSigningMessage, EcdsaSigningMessage, ServersSetChangeMessage, ShareAddMessage, KeyVersionNegotiationMessage};
+	SchnorrSigningMessage, EcdsaSigningMessage, ServersSetChangeMessage, ShareAddMessage, KeyVersionNegotiationMessage,
+	KeyVersionsPruningMessage, KeyDeletionMessage, EcdsaPartialSignature, LegacyEcdsaPartialSignature,
+	KeyShareCommon, LegacyKeyShareCommon, ServersSetChangeConsensusMessage, LegacyServersSetChangeConsensusMessage,
+	ConsensusMessageWithServersSet, LegacyConsensusMessageWithServersSet, InitializeConsensusSessionWithServersSet,
+	LegacyInitializeConsensusSessionWithServersSet};
 
 /// Size of serialized header.
 pub const MESSAGE_HEADER_SIZE: usize = 18;
-/// Current header version.
-pub const CURRENT_HEADER_VERSION: u64 = 2;
+/// Header version of messages with JSON-serialized payload.
+pub const JSON_HEADER_VERSION: u64 = 2;
+/// Header version of messages with binary (bincode) serialized payload.
+pub const BINARY_HEADER_VERSION: u64 = 3;
+/// Header version of messages with binary (bincode) serialized payload, protected with symmetric
+/// session keys instead of ECIES.
+pub const SESSION_KEYS_HEADER_VERSION: u64 = 4;
+/// Header version of messages with binary (bincode) serialized payload, protected with symmetric
+/// session keys. Signature shares and refreshed key shares are accompanied by proofs of their correctness.
+pub const VERIFIABLE_SHARES_HEADER_VERSION: u64 = 5;
+/// Current header version. This is the maximal version that is supported by this node.
+pub const CURRENT_HEADER_VERSION: u64 = VERIFIABLE_SHARES_HEADER_VERSION;
+/// Header version of handshake messages. Handshake messages must be understood by all nodes,
+/// so they're always serialized using JSON.
+pub const HANDSHAKE_HEADER_VERSION: u64 = JSON_HEADER_VERSION;
 
 /// Message header.
 #[derive(Debug, PartialEq)]
@@ -61,184 +79,386 @@ impl Into<Vec<u8>> for SerializedMessage {
 	}
 }
 
-/// Serialize message.
-pub fn serialize_message(message: Message) -> Result<SerializedMessage, Error> {
+/// Serialize message using codec that corresponds to given header version.
+pub fn serialize_message(message: Message, version: u64) -> Result<SerializedMessage, Error> {
 	let (message_kind, payload) = match message {
-		Message::Cluster(ClusterMessage::NodePublicKey(payload))							=> (1, serde_json::to_vec(&payload)),
-		Message::Cluster(ClusterMessage::NodePrivateKeySignature(payload))					=> (2, serde_json::to_vec(&payload)),
-		Message::Cluster(ClusterMessage::KeepAlive(payload))								=> (3, serde_json::to_vec(&payload)),
-		Message::Cluster(ClusterMessage::KeepAliveResponse(payload))						=> (4, serde_json::to_vec(&payload)),
-
-		Message::Generation(GenerationMessage::InitializeSession(payload))					=> (50, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::ConfirmInitialization(payload))				=> (51, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::DerivedPointGeneration(payload))				=> (52, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::KeysDissemination(payload))					=> (53, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::PublicKeyShare(payload))						=> (54, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::JointPublicKey(payload))						=> (55, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::SessionError(payload))						=> (56, serde_json::to_vec(&payload)),
-		Message::Generation(GenerationMessage::SessionCompleted(payload))					=> (57, serde_json::to_vec(&payload)),
-
-		Message::Encryption(EncryptionMessage::InitializeEncryptionSession(payload))		=> (100, serde_json::to_vec(&payload)),
-		Message::Encryption(EncryptionMessage::ConfirmEncryptionInitialization(payload))	=> (101, serde_json::to_vec(&payload)),
-		Message::Encryption(EncryptionMessage::EncryptionSessionError(payload))				=> (102, serde_json::to_vec(&payload)),
-
-		Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(payload))			=> (150, serde_json::to_vec(&payload)),
-		Message::Decryption(DecryptionMessage::RequestPartialDecryption(payload))			=> (151, serde_json::to_vec(&payload)),
-		Message::Decryption(DecryptionMessage::PartialDecryption(payload))					=> (152, serde_json::to_vec(&payload)),
-		Message::Decryption(DecryptionMessage::DecryptionSessionError(payload))				=> (153, serde_json::to_vec(&payload)),
-		Message::Decryption(DecryptionMessage::DecryptionSessionCompleted(payload))			=> (154, serde_json::to_vec(&payload)),
-		Message::Decryption(DecryptionMessage::DecryptionSessionDelegation(payload))		=> (155, serde_json::to_vec(&payload)),
+		Message::Cluster(ClusterMessage::NodePublicKey(payload))							=> (1, serialize_payload(version, &payload)),
+		Message::Cluster(ClusterMessage::NodePrivateKeySignature(payload))					=> (2, serialize_payload(version, &payload)),
+		Message::Cluster(ClusterMessage::KeepAlive(payload))								=> (3, serialize_payload(version, &payload)),
+		Message::Cluster(ClusterMessage::KeepAliveResponse(payload))						=> (4, serialize_payload(version, &payload)),
+
+		Message::Generation(GenerationMessage::InitializeSession(payload))					=> (50, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::ConfirmInitialization(payload))				=> (51, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::DerivedPointGeneration(payload))				=> (52, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::KeysDissemination(payload))					=> (53, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::PublicKeyShare(payload))						=> (54, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::JointPublicKey(payload))						=> (55, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::SessionError(payload))						=> (56, serialize_payload(version, &payload)),
+		Message::Generation(GenerationMessage::SessionCompleted(payload))					=> (57, serialize_payload(version, &payload)),
+
+		Message::Encryption(EncryptionMessage::InitializeEncryptionSession(payload))		=> (100, serialize_payload(version, &payload)),
+		Message::Encryption(EncryptionMessage::ConfirmEncryptionInitialization(payload))	=> (101, serialize_payload(version, &payload)),
+		Message::Encryption(EncryptionMessage::EncryptionSessionError(payload))				=> (102, serialize_payload(version, &payload)),
+		Message::Encryption(EncryptionMessage::CommitEncryptionSession(payload))			=> (103, serialize_payload(version, &payload)),
+		Message::Encryption(EncryptionMessage::ConfirmEncryptionCommit(payload))			=> (104, serialize_payload(version, &payload)),
+
+		Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(payload))			=> (150, serialize_payload(version, &payload)),
+		Message::Decryption(DecryptionMessage::RequestPartialDecryption(payload))			=> (151, serialize_payload(version, &payload)),
+		Message::Decryption(DecryptionMessage::PartialDecryption(payload))					=> (152, serialize_payload(version, &payload)),
+		Message::Decryption(DecryptionMessage::DecryptionSessionError(payload))				=> (153, serialize_payload(version, &payload)),
+		Message::Decryption(DecryptionMessage::DecryptionSessionCompleted(payload))			=> (154, serialize_payload(version, &payload)),
+		Message::Decryption(DecryptionMessage::DecryptionSessionDelegation(payload))		=> (155, serialize_payload(version, &payload)),
 		Message::Decryption(DecryptionMessage::DecryptionSessionDelegationCompleted(payload))
-																							=> (156, serde_json::to_vec(&payload)),
+																							=> (156, serialize_payload(version, &payload)),
 
 		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningConsensusMessage(payload))
-																							=> (200, serde_json::to_vec(&payload)),
+																							=> (200, serialize_payload(version, &payload)),
 		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningGenerationMessage(payload))
-																							=> (201, serde_json::to_vec(&payload)),
+																							=> (201, serialize_payload(version, &payload)),
 		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrRequestPartialSignature(payload))
-																							=> (202, serde_json::to_vec(&payload)),
-		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrPartialSignature(payload))	=> (203, serde_json::to_vec(&payload)),
-		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionError(payload))	=> (204, serde_json::to_vec(&payload)),
+																							=> (202, serialize_payload(version, &payload)),
+		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrPartialSignature(payload))	=> (203, serialize_payload(version, &payload)),
+		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionError(payload))	=> (204, serialize_payload(version, &payload)),
 		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionCompleted(payload))
-																							=> (205, serde_json::to_vec(&payload)),
+																							=> (205, serialize_payload(version, &payload)),
 		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegation(payload))
-																							=> (206, serde_json::to_vec(&payload)),
+																							=> (206, serialize_payload(version, &payload)),
 		Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegationCompleted(payload))
-																							=> (207, serde_json::to_vec(&payload)),
+																							=> (207, serialize_payload(version, &payload)),
 
 		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(payload))
-																							=> (250, serde_json::to_vec(&payload)),
-		Message::ServersSetChange(ServersSetChangeMessage::UnknownSessionsRequest(payload)) => (251, serde_json::to_vec(&payload)),
-		Message::ServersSetChange(ServersSetChangeMessage::UnknownSessions(payload))		=> (252, serde_json::to_vec(&payload)),
+																							=> (250, serialize_servers_set_change_consensus_message(version, payload)),
+		Message::ServersSetChange(ServersSetChangeMessage::UnknownSessionsRequest(payload)) => (251, serialize_payload(version, &payload)),
+		Message::ServersSetChange(ServersSetChangeMessage::UnknownSessions(payload))		=> (252, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(payload))
-																							=> (253, serde_json::to_vec(&payload)),
+																							=> (253, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(payload))
-																							=> (254, serde_json::to_vec(&payload)),
+																							=> (254, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(payload))
-																							=> (255, serde_json::to_vec(&payload)),
+																							=> (255, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegate(payload))
-																							=> (256, serde_json::to_vec(&payload)),
+																							=> (256, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegateResponse(payload))
-																							=> (257, serde_json::to_vec(&payload)),
+																							=> (257, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeShareAddMessage(payload))
-																							=> (258, serde_json::to_vec(&payload)),
-		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(payload))	=> (261, serde_json::to_vec(&payload)),
+																							=> (258, serialize_payload(version, &payload)),
+		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(payload))	=> (261, serialize_payload(version, &payload)),
 		Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeCompleted(payload))
-																							=> (262, serde_json::to_vec(&payload)),
+																							=> (262, serialize_payload(version, &payload)),
 
-		Message::ShareAdd(ShareAddMessage::ShareAddConsensusMessage(payload))				=> (300, serde_json::to_vec(&payload)),
-		Message::ShareAdd(ShareAddMessage::KeyShareCommon(payload))							=> (301, serde_json::to_vec(&payload)),
-		Message::ShareAdd(ShareAddMessage::NewKeysDissemination(payload))					=> (302, serde_json::to_vec(&payload)),
-		Message::ShareAdd(ShareAddMessage::ShareAddError(payload))							=> (303, serde_json::to_vec(&payload)),
+		Message::ShareAdd(ShareAddMessage::ShareAddConsensusMessage(payload))				=> (300, serialize_payload(version, &payload)),
+		Message::ShareAdd(ShareAddMessage::KeyShareCommon(payload))							=> (301, serialize_key_share_common(version, payload)),
+		Message::ShareAdd(ShareAddMessage::NewKeysDissemination(payload))					=> (302, serialize_verifiable_shares_payload(version, &payload)),
+		Message::ShareAdd(ShareAddMessage::ShareAddError(payload))							=> (303, serialize_payload(version, &payload)),
+		Message::ShareAdd(ShareAddMessage::NewKeysProofsFootprint(payload))					=> (304, serialize_verifiable_shares_payload(version, &payload)),
 
 		Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::RequestKeyVersions(payload))
-																							=> (450, serde_json::to_vec(&payload)),
+																							=> (450, serialize_payload(version, &payload)),
 		Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersions(payload))
-																							=> (451, serde_json::to_vec(&payload)),
+																							=> (451, serialize_payload(version, &payload)),
 		Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersionsError(payload))
-																							=> (452, serde_json::to_vec(&payload)),
+																							=> (452, serialize_payload(version, &payload)),
 
-		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningConsensusMessage(payload))	=> (500, serde_json::to_vec(&payload)),
+		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningConsensusMessage(payload))	=> (500, serialize_payload(version, &payload)),
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSignatureNonceGenerationMessage(payload))
-																							=> (501, serde_json::to_vec(&payload)),
+																							=> (501, serialize_payload(version, &payload)),
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaInversionNonceGenerationMessage(payload))
-																							=> (502, serde_json::to_vec(&payload)),
+																							=> (502, serialize_payload(version, &payload)),
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaInversionZeroGenerationMessage(payload))
-																							=> (503, serde_json::to_vec(&payload)),
+																							=> (503, serialize_payload(version, &payload)),
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningInversedNonceCoeffShare(payload))
-																							=> (504, serde_json::to_vec(&payload)),
-		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaRequestPartialSignature(payload))	=> (505, serde_json::to_vec(&payload)),
-		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaPartialSignature(payload))			=> (506, serde_json::to_vec(&payload)),
-		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionError(payload))		=> (507, serde_json::to_vec(&payload)),
-		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionCompleted(payload))	=> (508, serde_json::to_vec(&payload)),
-		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(payload))	=> (509, serde_json::to_vec(&payload)),
+																							=> (504, serialize_payload(version, &payload)),
+		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaRequestPartialSignature(payload))	=> (505, serialize_payload(version, &payload)),
+		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaPartialSignature(payload))			=> (506, serialize_ecdsa_partial_signature(version, payload)),
+		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionError(payload))		=> (507, serialize_payload(version, &payload)),
+		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionCompleted(payload))	=> (508, serialize_payload(version, &payload)),
+		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(payload))	=> (509, serialize_payload(version, &payload)),
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegationCompleted(payload))
-																							=> (510, serde_json::to_vec(&payload)),
+																							=> (510, serialize_payload(version, &payload)),
+
+		Message::KeyVersionsPruning(KeyVersionsPruningMessage::InitializeKeyVersionsPruning(payload))
+																							=> (550, serialize_payload(version, &payload)),
+		Message::KeyVersionsPruning(KeyVersionsPruningMessage::HeldKeyVersions(payload))	=> (551, serialize_payload(version, &payload)),
+		Message::KeyVersionsPruning(KeyVersionsPruningMessage::PruneKeyVersions(payload))	=> (552, serialize_payload(version, &payload)),
+		Message::KeyVersionsPruning(KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(payload))
+																							=> (553, serialize_payload(version, &payload)),
+		Message::KeyVersionsPruning(KeyVersionsPruningMessage::KeyVersionsPruningError(payload))
+																							=> (554, serialize_payload(version, &payload)),
+
+		Message::KeyDeletion(KeyDeletionMessage::InitializeKeyDeletionSession(payload))	=> (600, serialize_payload(version, &payload)),
+		Message::KeyDeletion(KeyDeletionMessage::ConfirmKeyDeletionInitialization(payload))
+																							=> (601, serialize_payload(version, &payload)),
+		Message::KeyDeletion(KeyDeletionMessage::CommitKeyDeletionSession(payload))		=> (602, serialize_payload(version, &payload)),
+		Message::KeyDeletion(KeyDeletionMessage::KeyDeletionSessionError(payload))			=> (603, serialize_payload(version, &payload)),
 	};
 
-	let payload = payload.map_err(|err| Error::Serde(err.to_string()))?;
 	build_serialized_message(MessageHeader {
 		kind: message_kind,
-		version: CURRENT_HEADER_VERSION,
+		version: version,
 		size: 0,
-	}, payload)
+	}, payload?)
 }
 
 /// Deserialize message.
 pub fn deserialize_message(header: &MessageHeader, payload: Vec<u8>) -> Result<Message, Error> {
 	Ok(match header.kind {
-		1	=> Message::Cluster(ClusterMessage::NodePublicKey(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		2	=> Message::Cluster(ClusterMessage::NodePrivateKeySignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		3	=> Message::Cluster(ClusterMessage::KeepAlive(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		4	=> Message::Cluster(ClusterMessage::KeepAliveResponse(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		50	=> Message::Generation(GenerationMessage::InitializeSession(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		51	=> Message::Generation(GenerationMessage::ConfirmInitialization(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		52	=> Message::Generation(GenerationMessage::DerivedPointGeneration(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		53	=> Message::Generation(GenerationMessage::KeysDissemination(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		54	=> Message::Generation(GenerationMessage::PublicKeyShare(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		55	=> Message::Generation(GenerationMessage::JointPublicKey(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		56	=> Message::Generation(GenerationMessage::SessionError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		57	=> Message::Generation(GenerationMessage::SessionCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		100	=> Message::Encryption(EncryptionMessage::InitializeEncryptionSession(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		101	=> Message::Encryption(EncryptionMessage::ConfirmEncryptionInitialization(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		102	=> Message::Encryption(EncryptionMessage::EncryptionSessionError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		150	=> Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		151	=> Message::Decryption(DecryptionMessage::RequestPartialDecryption(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		152	=> Message::Decryption(DecryptionMessage::PartialDecryption(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		153	=> Message::Decryption(DecryptionMessage::DecryptionSessionError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		154	=> Message::Decryption(DecryptionMessage::DecryptionSessionCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		155	=> Message::Decryption(DecryptionMessage::DecryptionSessionDelegation(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		156	=> Message::Decryption(DecryptionMessage::DecryptionSessionDelegationCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		200	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningConsensusMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		201	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningGenerationMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		202	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrRequestPartialSignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		203	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrPartialSignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		204	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		205	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		206	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegation(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		207	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegationCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		250	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		251	=> Message::ServersSetChange(ServersSetChangeMessage::UnknownSessionsRequest(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		252	=> Message::ServersSetChange(ServersSetChangeMessage::UnknownSessions(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		253 => Message::ServersSetChange(ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		254 => Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		255 => Message::ServersSetChange(ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		256	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegate(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		257	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegateResponse(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		258	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeShareAddMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		261	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		262	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		300 => Message::ShareAdd(ShareAddMessage::ShareAddConsensusMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		301 => Message::ShareAdd(ShareAddMessage::KeyShareCommon(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		302 => Message::ShareAdd(ShareAddMessage::NewKeysDissemination(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		303 => Message::ShareAdd(ShareAddMessage::ShareAddError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		450 => Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::RequestKeyVersions(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		451 => Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersions(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		452 => Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersionsError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-
-		500	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningConsensusMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		501	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSignatureNonceGenerationMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		502	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaInversionNonceGenerationMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		503	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaInversionZeroGenerationMessage(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		504	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningInversedNonceCoeffShare(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		505	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaRequestPartialSignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		506	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaPartialSignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		507	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		508	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		509	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
-		510	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegationCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		1	=> Message::Cluster(ClusterMessage::NodePublicKey(deserialize_payload(header.version, &payload)?)),
+		2	=> Message::Cluster(ClusterMessage::NodePrivateKeySignature(deserialize_payload(header.version, &payload)?)),
+		3	=> Message::Cluster(ClusterMessage::KeepAlive(deserialize_payload(header.version, &payload)?)),
+		4	=> Message::Cluster(ClusterMessage::KeepAliveResponse(deserialize_payload(header.version, &payload)?)),
+
+		50	=> Message::Generation(GenerationMessage::InitializeSession(deserialize_payload(header.version, &payload)?)),
+		51	=> Message::Generation(GenerationMessage::ConfirmInitialization(deserialize_payload(header.version, &payload)?)),
+		52	=> Message::Generation(GenerationMessage::DerivedPointGeneration(deserialize_payload(header.version, &payload)?)),
+		53	=> Message::Generation(GenerationMessage::KeysDissemination(deserialize_payload(header.version, &payload)?)),
+		54	=> Message::Generation(GenerationMessage::PublicKeyShare(deserialize_payload(header.version, &payload)?)),
+		55	=> Message::Generation(GenerationMessage::JointPublicKey(deserialize_payload(header.version, &payload)?)),
+		56	=> Message::Generation(GenerationMessage::SessionError(deserialize_payload(header.version, &payload)?)),
+		57	=> Message::Generation(GenerationMessage::SessionCompleted(deserialize_payload(header.version, &payload)?)),
+
+		100	=> Message::Encryption(EncryptionMessage::InitializeEncryptionSession(deserialize_payload(header.version, &payload)?)),
+		101	=> Message::Encryption(EncryptionMessage::ConfirmEncryptionInitialization(deserialize_payload(header.version, &payload)?)),
+		102	=> Message::Encryption(EncryptionMessage::EncryptionSessionError(deserialize_payload(header.version, &payload)?)),
+		103	=> Message::Encryption(EncryptionMessage::CommitEncryptionSession(deserialize_payload(header.version, &payload)?)),
+		104	=> Message::Encryption(EncryptionMessage::ConfirmEncryptionCommit(deserialize_payload(header.version, &payload)?)),
+
+		150	=> Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(deserialize_payload(header.version, &payload)?)),
+		151	=> Message::Decryption(DecryptionMessage::RequestPartialDecryption(deserialize_payload(header.version, &payload)?)),
+		152	=> Message::Decryption(DecryptionMessage::PartialDecryption(deserialize_payload(header.version, &payload)?)),
+		153	=> Message::Decryption(DecryptionMessage::DecryptionSessionError(deserialize_payload(header.version, &payload)?)),
+		154	=> Message::Decryption(DecryptionMessage::DecryptionSessionCompleted(deserialize_payload(header.version, &payload)?)),
+		155	=> Message::Decryption(DecryptionMessage::DecryptionSessionDelegation(deserialize_payload(header.version, &payload)?)),
+		156	=> Message::Decryption(DecryptionMessage::DecryptionSessionDelegationCompleted(deserialize_payload(header.version, &payload)?)),
+
+		200	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningConsensusMessage(deserialize_payload(header.version, &payload)?)),
+		201	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningGenerationMessage(deserialize_payload(header.version, &payload)?)),
+		202	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrRequestPartialSignature(deserialize_payload(header.version, &payload)?)),
+		203	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrPartialSignature(deserialize_payload(header.version, &payload)?)),
+		204	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionError(deserialize_payload(header.version, &payload)?)),
+		205	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionCompleted(deserialize_payload(header.version, &payload)?)),
+		206	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegation(deserialize_payload(header.version, &payload)?)),
+		207	=> Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegationCompleted(deserialize_payload(header.version, &payload)?)),
+
+		250	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(deserialize_servers_set_change_consensus_message(header.version, &payload)?)),
+		251	=> Message::ServersSetChange(ServersSetChangeMessage::UnknownSessionsRequest(deserialize_payload(header.version, &payload)?)),
+		252	=> Message::ServersSetChange(ServersSetChangeMessage::UnknownSessions(deserialize_payload(header.version, &payload)?)),
+		253 => Message::ServersSetChange(ServersSetChangeMessage::ShareChangeKeyVersionNegotiation(deserialize_payload(header.version, &payload)?)),
+		254 => Message::ServersSetChange(ServersSetChangeMessage::InitializeShareChangeSession(deserialize_payload(header.version, &payload)?)),
+		255 => Message::ServersSetChange(ServersSetChangeMessage::ConfirmShareChangeSessionInitialization(deserialize_payload(header.version, &payload)?)),
+		256	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegate(deserialize_payload(header.version, &payload)?)),
+		257	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeDelegateResponse(deserialize_payload(header.version, &payload)?)),
+		258	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeShareAddMessage(deserialize_payload(header.version, &payload)?)),
+		261	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(deserialize_payload(header.version, &payload)?)),
+		262	=> Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeCompleted(deserialize_payload(header.version, &payload)?)),
+
+		300 => Message::ShareAdd(ShareAddMessage::ShareAddConsensusMessage(deserialize_payload(header.version, &payload)?)),
+		301 => Message::ShareAdd(ShareAddMessage::KeyShareCommon(deserialize_key_share_common(header.version, &payload)?)),
+		302 => Message::ShareAdd(ShareAddMessage::NewKeysDissemination(deserialize_verifiable_shares_payload(header.version, &payload)?)),
+		303 => Message::ShareAdd(ShareAddMessage::ShareAddError(deserialize_payload(header.version, &payload)?)),
+		304 => Message::ShareAdd(ShareAddMessage::NewKeysProofsFootprint(deserialize_verifiable_shares_payload(header.version, &payload)?)),
+
+		450 => Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::RequestKeyVersions(deserialize_payload(header.version, &payload)?)),
+		451 => Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersions(deserialize_payload(header.version, &payload)?)),
+		452 => Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersionsError(deserialize_payload(header.version, &payload)?)),
+
+		500	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningConsensusMessage(deserialize_payload(header.version, &payload)?)),
+		501	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSignatureNonceGenerationMessage(deserialize_payload(header.version, &payload)?)),
+		502	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaInversionNonceGenerationMessage(deserialize_payload(header.version, &payload)?)),
+		503	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaInversionZeroGenerationMessage(deserialize_payload(header.version, &payload)?)),
+		504	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningInversedNonceCoeffShare(deserialize_payload(header.version, &payload)?)),
+		505	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaRequestPartialSignature(deserialize_payload(header.version, &payload)?)),
+		506	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaPartialSignature(deserialize_ecdsa_partial_signature(header.version, &payload)?)),
+		507	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionError(deserialize_payload(header.version, &payload)?)),
+		508	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionCompleted(deserialize_payload(header.version, &payload)?)),
+		509	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(deserialize_payload(header.version, &payload)?)),
+		510	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegationCompleted(deserialize_payload(header.version, &payload)?)),
+
+		550	=> Message::KeyVersionsPruning(KeyVersionsPruningMessage::InitializeKeyVersionsPruning(deserialize_payload(header.version, &payload)?)),
+		551	=> Message::KeyVersionsPruning(KeyVersionsPruningMessage::HeldKeyVersions(deserialize_payload(header.version, &payload)?)),
+		552	=> Message::KeyVersionsPruning(KeyVersionsPruningMessage::PruneKeyVersions(deserialize_payload(header.version, &payload)?)),
+		553	=> Message::KeyVersionsPruning(KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(deserialize_payload(header.version, &payload)?)),
+		554	=> Message::KeyVersionsPruning(KeyVersionsPruningMessage::KeyVersionsPruningError(deserialize_payload(header.version, &payload)?)),
+
+		600	=> Message::KeyDeletion(KeyDeletionMessage::InitializeKeyDeletionSession(deserialize_payload(header.version, &payload)?)),
+		601	=> Message::KeyDeletion(KeyDeletionMessage::ConfirmKeyDeletionInitialization(deserialize_payload(header.version, &payload)?)),
+		602	=> Message::KeyDeletion(KeyDeletionMessage::CommitKeyDeletionSession(deserialize_payload(header.version, &payload)?)),
+		603	=> Message::KeyDeletion(KeyDeletionMessage::KeyDeletionSessionError(deserialize_payload(header.version, &payload)?)),
 
 		_ => return Err(Error::Serde(format!("unknown message type {}", header.kind))),
 	})
 }
 
+/// Negotiate header version that is used to communicate with the peer. Nodes that have been
+/// released before binary codec was introduced do not advertise their version.
+pub fn negotiate_header_version(peer_max_version: Option<u64>) -> u64 {
+	::std::cmp::min(CURRENT_HEADER_VERSION, peer_max_version.unwrap_or(JSON_HEADER_VERSION))
+}
+
+/// Serialize message payload using codec that corresponds to given header version.
+fn serialize_payload<T: Serialize>(version: u64, payload: &T) -> Result<Vec<u8>, Error> {
+	match version {
+		JSON_HEADER_VERSION => serde_json::to_vec(payload).map_err(|err| Error::Serde(err.to_string())),
+		BINARY_HEADER_VERSION | SESSION_KEYS_HEADER_VERSION | VERIFIABLE_SHARES_HEADER_VERSION => bincode::serialize(payload).map_err(|err| Error::Serde(err.to_string())),
+		_ => Err(Error::InvalidMessageVersion),
+	}
+}
+
+/// Deserialize message payload using codec that corresponds to given header version.
+fn deserialize_payload<T: DeserializeOwned>(version: u64, payload: &[u8]) -> Result<T, Error> {
+	match version {
+		JSON_HEADER_VERSION => serde_json::from_slice(payload).map_err(|err| Error::Serde(err.to_string())),
+		BINARY_HEADER_VERSION | SESSION_KEYS_HEADER_VERSION | VERIFIABLE_SHARES_HEADER_VERSION => bincode::deserialize(payload).map_err(|err| Error::Serde(err.to_string())),
+		_ => Err(Error::InvalidMessageVersion),
+	}
+}
+
+/// Serialize partial ECDSA signature. Nodes that do not support verifiable signature shares
+/// are not expecting the proof, so it is stripped.
+fn serialize_ecdsa_partial_signature(version: u64, payload: EcdsaPartialSignature) -> Result<Vec<u8>, Error> {
+	if version >= VERIFIABLE_SHARES_HEADER_VERSION {
+		return serialize_payload(version, &payload);
+	}
+
+	serialize_payload(version, &LegacyEcdsaPartialSignature {
+		session: payload.session,
+		sub_session: payload.sub_session,
+		session_nonce: payload.session_nonce,
+		request_id: payload.request_id,
+		partial_signature_s: payload.partial_signature_s,
+	})
+}
+
+/// Deserialize partial ECDSA signature. Signatures, received from nodes that do not support
+/// verifiable signature shares, come without proof.
+fn deserialize_ecdsa_partial_signature(version: u64, payload: &[u8]) -> Result<EcdsaPartialSignature, Error> {
+	if version >= VERIFIABLE_SHARES_HEADER_VERSION {
+		return deserialize_payload(version, payload);
+	}
+
+	let payload: LegacyEcdsaPartialSignature = deserialize_payload(version, payload)?;
+	Ok(EcdsaPartialSignature {
+		session: payload.session,
+		sub_session: payload.sub_session,
+		session_nonce: payload.session_nonce,
+		request_id: payload.request_id,
+		partial_signature_s: payload.partial_signature_s,
+		partial_signature_s_proof: None,
+	})
+}
+
+/// Serialize key share common data. Nodes that do not support verifiable shares are not
+/// expecting public key shares, so these are stripped.
+fn serialize_key_share_common(version: u64, payload: KeyShareCommon) -> Result<Vec<u8>, Error> {
+	if version >= VERIFIABLE_SHARES_HEADER_VERSION {
+		return serialize_payload(version, &payload);
+	}
+
+	serialize_payload(version, &LegacyKeyShareCommon {
+		session: payload.session,
+		session_nonce: payload.session_nonce,
+		key_common: payload.key_common,
+		common_point: payload.common_point,
+		encrypted_point: payload.encrypted_point,
+		id_numbers: payload.id_numbers,
+	})
+}
+
+/// Deserialize key share common data. Data, received from nodes that do not support verifiable
+/// shares, comes without public key shares.
+fn deserialize_key_share_common(version: u64, payload: &[u8]) -> Result<KeyShareCommon, Error> {
+	if version >= VERIFIABLE_SHARES_HEADER_VERSION {
+		return deserialize_payload(version, payload);
+	}
+
+	let payload: LegacyKeyShareCommon = deserialize_payload(version, payload)?;
+	Ok(KeyShareCommon {
+		session: payload.session,
+		session_nonce: payload.session_nonce,
+		key_common: payload.key_common,
+		common_point: payload.common_point,
+		encrypted_point: payload.encrypted_point,
+		id_numbers: payload.id_numbers,
+		public_shares: Default::default(),
+	})
+}
+
+/// Serialize servers set change consensus message. Nodes that do not support verifiable shares
+/// are also not supporting key shares refresh, so it can't be requested from these nodes.
+fn serialize_servers_set_change_consensus_message(version: u64, payload: ServersSetChangeConsensusMessage) -> Result<Vec<u8>, Error> {
+	if version >= VERIFIABLE_SHARES_HEADER_VERSION {
+		return serialize_payload(version, &payload);
+	}
+
+	serialize_payload(version, &LegacyServersSetChangeConsensusMessage {
+		session: payload.session,
+		session_nonce: payload.session_nonce,
+		message: match payload.message {
+			ConsensusMessageWithServersSet::InitializeConsensusSession(message) => {
+				if message.refresh_key_shares {
+					return Err(Error::InvalidMessageVersion);
+				}
+
+				LegacyConsensusMessageWithServersSet::InitializeConsensusSession(LegacyInitializeConsensusSessionWithServersSet {
+					migration_id: message.migration_id,
+					old_nodes_set: message.old_nodes_set,
+					new_nodes_set: message.new_nodes_set,
+					old_set_signature: message.old_set_signature,
+					new_set_signature: message.new_set_signature,
+				})
+			},
+			ConsensusMessageWithServersSet::ConfirmConsensusInitialization(message) =>
+				LegacyConsensusMessageWithServersSet::ConfirmConsensusInitialization(message),
+		},
+	})
+}
+
+/// Deserialize servers set change consensus message. Messages, received from nodes that do not support
+/// verifiable shares, never request key shares refresh.
+fn deserialize_servers_set_change_consensus_message(version: u64, payload: &[u8]) -> Result<ServersSetChangeConsensusMessage, Error> {
+	if version >= VERIFIABLE_SHARES_HEADER_VERSION {
+		return deserialize_payload(version, payload);
+	}
+
+	let payload: LegacyServersSetChangeConsensusMessage = deserialize_payload(version, payload)?;
+	Ok(ServersSetChangeConsensusMessage {
+		session: payload.session,
+		session_nonce: payload.session_nonce,
+		message: match payload.message {
+			LegacyConsensusMessageWithServersSet::InitializeConsensusSession(message) =>
+				ConsensusMessageWithServersSet::InitializeConsensusSession(InitializeConsensusSessionWithServersSet {
+					migration_id: message.migration_id,
+					refresh_key_shares: false,
+					refreshed_key_id: None,
+					old_nodes_set: message.old_nodes_set,
+					new_nodes_set: message.new_nodes_set,
+					old_set_signature: message.old_set_signature,
+					new_set_signature: message.new_set_signature,
+				}),
+			LegacyConsensusMessageWithServersSet::ConfirmConsensusInitialization(message) =>
+				ConsensusMessageWithServersSet::ConfirmConsensusInitialization(message),
+		},
+	})
+}
+
+/// Serialize payload of the message that only exists in verifiable shares protocol. Refreshed
+/// key shares can't be verified without proofs, so such messages are never sent to older nodes.
+fn serialize_verifiable_shares_payload<T: Serialize>(version: u64, payload: &T) -> Result<Vec<u8>, Error> {
+	if version < VERIFIABLE_SHARES_HEADER_VERSION {
+		return Err(Error::InvalidMessageVersion);
+	}
+
+	serialize_payload(version, payload)
+}
+
+/// Deserialize payload of the message that only exists in verifiable shares protocol.
+fn deserialize_verifiable_shares_payload<T: DeserializeOwned>(version: u64, payload: &[u8]) -> Result<T, Error> {
+	if version < VERIFIABLE_SHARES_HEADER_VERSION {
+		return Err(Error::InvalidMessageVersion);
+	}
+
+	deserialize_payload(version, payload)
+}
+
 /// Encrypt serialized message.
 pub fn encrypt_message(key: &KeyPair, message: SerializedMessage) -> Result<SerializedMessage, Error> {
 	let mut header: Vec<_> = message.into();
@@ -276,7 +496,7 @@ pub fn fix_shared_key(shared_secret: &Secret) -> Result<KeyPair, Error> {
 }
 
 /// Serialize message header.
-fn serialize_header(header: &MessageHeader) -> Result<Vec<u8>, Error> {
+pub fn serialize_header(header: &MessageHeader) -> Result<Vec<u8>, Error> {
 	let mut buffer = Vec::with_capacity(MESSAGE_HEADER_SIZE);
 	buffer.write_u64::<LittleEndian>(header.version)?;
 	buffer.write_u64::<LittleEndian>(header.kind)?;
@@ -288,7 +508,7 @@ fn serialize_header(header: &MessageHeader) -> Result<Vec<u8>, Error> {
 pub fn deserialize_header(data: &[u8]) -> Result<MessageHeader, Error> {
 	let mut reader = Cursor::new(data);
 	let version = reader.read_u64::<LittleEndian>()?;
-	if version != CURRENT_HEADER_VERSION {
+	if version < JSON_HEADER_VERSION || version > CURRENT_HEADER_VERSION {
 		return Err(Error::InvalidMessageVersion);
 	}
 
@@ -300,7 +520,7 @@ pub fn deserialize_header(data: &[u8]) -> Result<MessageHeader, Error> {
 }
 
 /// Build serialized message from header && payload
-fn build_serialized_message(mut header: MessageHeader, payload: Vec<u8>) -> Result<SerializedMessage, Error> {
+pub fn build_serialized_message(mut header: MessageHeader, payload: Vec<u8>) -> Result<SerializedMessage, Error> {
 	let payload_len = payload.len();
 	if payload_len > u16::MAX as usize {
 		return Err(Error::InvalidMessage);
@@ -320,9 +540,17 @@ pub mod tests {
 	use parity_crypto::publickey::{Random, Generator, KeyPair};
 	use parity_crypto::publickey::ecdh::agree;
 	use crate::key_server_cluster::Error;
-	use crate::key_server_cluster::message::Message;
-	use super::{MESSAGE_HEADER_SIZE, CURRENT_HEADER_VERSION, MessageHeader, fix_shared_key, encrypt_message,
-		serialize_message, serialize_header, deserialize_header};
+	use crate::key_server_cluster::message::{Message, ClusterMessage, GenerationMessage, EncryptionMessage,
+		DecryptionMessage, SchnorrSigningMessage, EcdsaSigningMessage, ConsensusMessage, KeyVersionNegotiationMessage,
+		NodePublicKey, KeepAlive, KeysDissemination, InitializeEncryptionSession, DecryptionConsensusMessage,
+		ConfirmConsensusInitialization, PartialDecryption, SchnorrPartialSignature, SchnorrSigningGenerationMessage,
+		EcdsaSigningSessionError, EcdsaSigningSessionDelegationCompleted, KeyVersionsError, EcdsaPartialSignature,
+		EcdsaPartialSignatureProof, ShareAddMessage, KeyShareCommon, CommonKeyData, NewKeysDissemination,
+		NewKeysProofsFootprint, ServersSetChangeMessage, ServersSetChangeConsensusMessage, ConsensusMessageWithServersSet,
+		InitializeConsensusSessionWithServersSet};
+	use super::{MESSAGE_HEADER_SIZE, CURRENT_HEADER_VERSION, JSON_HEADER_VERSION, BINARY_HEADER_VERSION,
+		SESSION_KEYS_HEADER_VERSION, VERIFIABLE_SHARES_HEADER_VERSION, HANDSHAKE_HEADER_VERSION, MessageHeader, fix_shared_key, encrypt_message, negotiate_header_version,
+		serialize_message, deserialize_message, serialize_header, deserialize_header};
 
 	pub struct TestIo {
 		self_key_pair: KeyPair,
@@ -371,7 +599,7 @@ pub mod tests {
 		}
 
 		pub fn add_input_message(&mut self, message: Message) {
-			let serialized_message = serialize_message(message).unwrap();
+			let serialized_message = serialize_message(message, HANDSHAKE_HEADER_VERSION).unwrap();
 			let serialized_message: Vec<_> = serialized_message.into();
 			let input_buffer = self.input_buffer.get_mut();
 			for b in serialized_message {
@@ -380,7 +608,7 @@ pub mod tests {
 		}
 
 		pub fn add_encrypted_input_message(&mut self, message: Message) {
-			let serialized_message = encrypt_message(&self.shared_key_pair, serialize_message(message).unwrap()).unwrap();
+			let serialized_message = encrypt_message(&self.shared_key_pair, serialize_message(message, HANDSHAKE_HEADER_VERSION).unwrap()).unwrap();
 			let serialized_message: Vec<_> = serialized_message.into();
 			let input_buffer = self.input_buffer.get_mut();
 			for b in serialized_message {
@@ -438,4 +666,277 @@ pub mod tests {
 
 		assert_eq!(deserialize_header(&serialize_header(&header).unwrap()).unwrap_err(), Error::InvalidMessageVersion);
 	}
+
+	fn test_messages() -> Vec<Message> {
+		let secret = || Random.generate().secret().clone().into();
+		let public = || Random.generate().public().clone().into();
+		let signature = || parity_crypto::publickey::sign(Random.generate().secret(), &Default::default()).unwrap().into();
+		let node = || Random.generate().address().into();
+		vec![
+			Message::Cluster(ClusterMessage::NodePublicKey(NodePublicKey {
+				node_id: node(),
+				confirmation_plain: Default::default(),
+				confirmation_signed_session: signature(),
+				max_header_version: Some(CURRENT_HEADER_VERSION),
+			})),
+			Message::Cluster(ClusterMessage::KeepAlive(KeepAlive {})),
+			Message::Generation(GenerationMessage::KeysDissemination(KeysDissemination {
+				session: Default::default(),
+				session_nonce: 42,
+				secret1: secret(),
+				secret2: secret(),
+				publics: vec![public(), public(), public()],
+			})),
+			Message::Encryption(EncryptionMessage::InitializeEncryptionSession(InitializeEncryptionSession {
+				session: Default::default(),
+				session_nonce: 42,
+				requester: primitives::requester::Requester::Public(Random.generate().public().clone()).into(),
+				common_point: public(),
+				encrypted_point: public(),
+			})),
+			Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(DecryptionConsensusMessage {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				origin: Some(node()),
+				message: ConsensusMessage::ConfirmConsensusInitialization(ConfirmConsensusInitialization {
+					is_confirmed: true,
+				}),
+			})),
+			Message::Decryption(DecryptionMessage::PartialDecryption(PartialDecryption {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				request_id: secret(),
+				shadow_point: public(),
+				decrypt_shadow: Some(vec![1, 2, 3]),
+			})),
+			Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningGenerationMessage(SchnorrSigningGenerationMessage {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				message: GenerationMessage::KeysDissemination(KeysDissemination {
+					session: Default::default(),
+					session_nonce: 42,
+					secret1: secret(),
+					secret2: secret(),
+					publics: vec![public()],
+				}),
+			})),
+			Message::SchnorrSigning(SchnorrSigningMessage::SchnorrPartialSignature(SchnorrPartialSignature {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				request_id: secret(),
+				partial_signature: secret(),
+			})),
+			Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionError(EcdsaSigningSessionError {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				error: Error::InvalidNodeId(Random.generate().address()),
+			})),
+			Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegationCompleted(EcdsaSigningSessionDelegationCompleted {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				signature: signature(),
+			})),
+			Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersionsError(KeyVersionsError {
+				session: Default::default(),
+				sub_session: secret(),
+				session_nonce: 42,
+				error: Error::Internal("error".into()),
+				continue_with: None,
+			})),
+		]
+	}
+
+	fn serialize_and_deserialize(message: Message, version: u64) -> (Vec<u8>, Vec<u8>) {
+		let serialized: Vec<u8> = serialize_message(message, version).unwrap().into();
+		let header = deserialize_header(&serialized[..MESSAGE_HEADER_SIZE]).unwrap();
+		assert_eq!(header.version, version);
+		assert_eq!(header.size as usize, serialized.len() - MESSAGE_HEADER_SIZE);
+
+		let deserialized = deserialize_message(&header, serialized[MESSAGE_HEADER_SIZE..].to_vec()).unwrap();
+		let reserialized = serialize_message(deserialized, version).unwrap().into();
+		(serialized, reserialized)
+	}
+
+	#[test]
+	fn messages_serialization_works_with_json_codec() {
+		for message in test_messages() {
+			let (serialized, reserialized) = serialize_and_deserialize(message, JSON_HEADER_VERSION);
+			assert_eq!(serialized, reserialized);
+		}
+	}
+
+	#[test]
+	fn messages_serialization_works_with_binary_codec() {
+		for message in test_messages() {
+			let (serialized, reserialized) = serialize_and_deserialize(message.clone(), BINARY_HEADER_VERSION);
+			assert_eq!(serialized, reserialized);
+
+			let (serialized, reserialized) = serialize_and_deserialize(message.clone(), SESSION_KEYS_HEADER_VERSION);
+			assert_eq!(serialized, reserialized);
+
+			let (serialized, reserialized) = serialize_and_deserialize(message, VERIFIABLE_SHARES_HEADER_VERSION);
+			assert_eq!(serialized, reserialized);
+		}
+	}
+
+	#[test]
+	fn ecdsa_partial_signature_proof_is_only_sent_to_nodes_supporting_verifiable_shares() {
+		let message = Message::EcdsaSigning(EcdsaSigningMessage::EcdsaPartialSignature(EcdsaPartialSignature {
+			session: Default::default(),
+			sub_session: Random.generate().secret().clone().into(),
+			session_nonce: 42,
+			request_id: Random.generate().secret().clone().into(),
+			partial_signature_s: Random.generate().secret().clone().into(),
+			partial_signature_s_proof: Some(EcdsaPartialSignatureProof {
+				inv_nonce_mul_secret_public: Random.generate().public().clone().into(),
+				challenge: Random.generate().secret().clone().into(),
+				response: Random.generate().secret().clone().into(),
+			}),
+		}));
+
+		let has_proof = |version| {
+			let serialized: Vec<u8> = serialize_message(message.clone(), version).unwrap().into();
+			let header = deserialize_header(&serialized[..MESSAGE_HEADER_SIZE]).unwrap();
+			match deserialize_message(&header, serialized[MESSAGE_HEADER_SIZE..].to_vec()).unwrap() {
+				Message::EcdsaSigning(EcdsaSigningMessage::EcdsaPartialSignature(message)) =>
+					message.partial_signature_s_proof.is_some(),
+				_ => unreachable!("serialized ECDSA partial signature; qed"),
+			}
+		};
+
+		assert!(!has_proof(JSON_HEADER_VERSION));
+		assert!(!has_proof(BINARY_HEADER_VERSION));
+		assert!(!has_proof(SESSION_KEYS_HEADER_VERSION));
+		assert!(has_proof(VERIFIABLE_SHARES_HEADER_VERSION));
+	}
+
+	#[test]
+	fn public_key_shares_are_only_sent_to_nodes_supporting_verifiable_shares() {
+		let message = Message::ShareAdd(ShareAddMessage::KeyShareCommon(KeyShareCommon {
+			session: Default::default(),
+			session_nonce: 42,
+			key_common: CommonKeyData {
+				threshold: 1,
+				author: Default::default(),
+				public: Random.generate().public().clone().into(),
+			},
+			common_point: Some(Random.generate().public().clone().into()),
+			encrypted_point: Some(Random.generate().public().clone().into()),
+			id_numbers: vec![(Random.generate().address().into(), Random.generate().secret().clone().into())]
+				.into_iter().collect(),
+			public_shares: vec![(Random.generate().address().into(), Random.generate().public().clone().into())]
+				.into_iter().collect(),
+		}));
+
+		let has_public_shares = |version| {
+			let (serialized, reserialized) = serialize_and_deserialize(message.clone(), version);
+			assert_eq!(serialized, reserialized);
+
+			let header = deserialize_header(&serialized[..MESSAGE_HEADER_SIZE]).unwrap();
+			match deserialize_message(&header, serialized[MESSAGE_HEADER_SIZE..].to_vec()).unwrap() {
+				Message::ShareAdd(ShareAddMessage::KeyShareCommon(message)) => !message.public_shares.is_empty(),
+				_ => unreachable!("serialized key share common data; qed"),
+			}
+		};
+
+		assert!(!has_public_shares(JSON_HEADER_VERSION));
+		assert!(!has_public_shares(BINARY_HEADER_VERSION));
+		assert!(!has_public_shares(SESSION_KEYS_HEADER_VERSION));
+		assert!(has_public_shares(VERIFIABLE_SHARES_HEADER_VERSION));
+	}
+
+	#[test]
+	fn refreshed_key_shares_are_never_sent_to_nodes_not_supporting_verifiable_shares() {
+		let messages = vec![
+			Message::ShareAdd(ShareAddMessage::NewKeysDissemination(NewKeysDissemination {
+				session: Default::default(),
+				session_nonce: 42,
+				secret_subshare: Random.generate().secret().clone().into(),
+				share_proof: vec![Random.generate().public().clone().into()],
+			})),
+			Message::ShareAdd(ShareAddMessage::NewKeysProofsFootprint(NewKeysProofsFootprint {
+				session: Default::default(),
+				session_nonce: 42,
+				share_proofs_footprint: Default::default(),
+			})),
+		];
+
+		for message in messages {
+			for version in vec![JSON_HEADER_VERSION, BINARY_HEADER_VERSION, SESSION_KEYS_HEADER_VERSION] {
+				assert_eq!(serialize_message(message.clone(), version).unwrap_err(), Error::InvalidMessageVersion);
+
+				let serialized: Vec<u8> = serialize_message(message.clone(), VERIFIABLE_SHARES_HEADER_VERSION).unwrap().into();
+				let mut header = deserialize_header(&serialized[..MESSAGE_HEADER_SIZE]).unwrap();
+				header.version = version;
+				assert_eq!(deserialize_message(&header, serialized[MESSAGE_HEADER_SIZE..].to_vec()).unwrap_err(),
+					Error::InvalidMessageVersion);
+			}
+
+			let (serialized, reserialized) = serialize_and_deserialize(message, VERIFIABLE_SHARES_HEADER_VERSION);
+			assert_eq!(serialized, reserialized);
+		}
+	}
+
+	#[test]
+	fn servers_set_change_consensus_initialization_is_sent_to_nodes_not_supporting_verifiable_shares() {
+		let message = |refresh_key_shares| Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeConsensusMessage(
+			ServersSetChangeConsensusMessage {
+				session: Default::default(),
+				session_nonce: 42,
+				message: ConsensusMessageWithServersSet::InitializeConsensusSession(InitializeConsensusSessionWithServersSet {
+					migration_id: Some(Default::default()),
+					refresh_key_shares,
+					refreshed_key_id: if refresh_key_shares { Some(Default::default()) } else { None },
+					old_nodes_set: vec![Random.generate().address().into()].into_iter().collect(),
+					new_nodes_set: vec![Random.generate().address().into()].into_iter().collect(),
+					old_set_signature: parity_crypto::publickey::sign(Random.generate().secret(), &Default::default()).unwrap().into(),
+					new_set_signature: parity_crypto::publickey::sign(Random.generate().secret(), &Default::default()).unwrap().into(),
+				}),
+			}
+		));
+
+		for version in vec![JSON_HEADER_VERSION, BINARY_HEADER_VERSION, SESSION_KEYS_HEADER_VERSION] {
+			let (serialized, reserialized) = serialize_and_deserialize(message(false), version);
+			assert_eq!(serialized, reserialized);
+
+			assert_eq!(serialize_message(message(true), version).unwrap_err(), Error::InvalidMessageVersion);
+		}
+
+		let (serialized, reserialized) = serialize_and_deserialize(message(true), VERIFIABLE_SHARES_HEADER_VERSION);
+		assert_eq!(serialized, reserialized);
+	}
+
+	#[test]
+	fn binary_codec_is_more_compact_than_json_codec() {
+		for message in test_messages() {
+			let json_size = serialize_message(message.clone(), JSON_HEADER_VERSION).unwrap().len();
+			let binary_size = serialize_message(message, BINARY_HEADER_VERSION).unwrap().len();
+			assert!(binary_size <= json_size);
+		}
+	}
+
+	#[test]
+	fn message_serialized_with_one_codec_is_not_deserialized_with_other() {
+		let message = test_messages().remove(2);
+		let serialized: Vec<u8> = serialize_message(message, BINARY_HEADER_VERSION).unwrap().into();
+		let mut header = deserialize_header(&serialized[..MESSAGE_HEADER_SIZE]).unwrap();
+		header.version = JSON_HEADER_VERSION;
+		assert!(deserialize_message(&header, serialized[MESSAGE_HEADER_SIZE..].to_vec()).is_err());
+	}
+
+	#[test]
+	fn header_version_is_negotiated() {
+		assert_eq!(negotiate_header_version(None), JSON_HEADER_VERSION);
+		assert_eq!(negotiate_header_version(Some(JSON_HEADER_VERSION)), JSON_HEADER_VERSION);
+		assert_eq!(negotiate_header_version(Some(BINARY_HEADER_VERSION)), BINARY_HEADER_VERSION);
+		assert_eq!(negotiate_header_version(Some(SESSION_KEYS_HEADER_VERSION)), SESSION_KEYS_HEADER_VERSION);
+		assert_eq!(negotiate_header_version(Some(VERIFIABLE_SHARES_HEADER_VERSION)), VERIFIABLE_SHARES_HEADER_VERSION);
+		assert_eq!(negotiate_header_version(Some(CURRENT_HEADER_VERSION + 1)), CURRENT_HEADER_VERSION);
+	}
 }
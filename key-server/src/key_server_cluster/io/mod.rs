@@ -20,15 +20,18 @@ mod message;
 mod read_header;
 mod read_payload;
 mod read_message;
+mod session_keys;
 mod shared_tcp_stream;
 mod write_message;
 
 pub use self::deadline::{deadline, Deadline, DeadlineStatus};
 pub use self::handshake::{handshake, accept_handshake, Handshake, HandshakeResult};
 pub use self::message::{MessageHeader, SerializedMessage, serialize_message, deserialize_message,
-	deserialize_header, encrypt_message, fix_shared_key, encrypt_data, decrypt_data};
+	deserialize_header, encrypt_message, fix_shared_key, encrypt_data, decrypt_data, negotiate_header_version,
+	CURRENT_HEADER_VERSION, HANDSHAKE_HEADER_VERSION, SESSION_KEYS_HEADER_VERSION, VERIFIABLE_SHARES_HEADER_VERSION};
 pub use self::read_header::{read_header, ReadHeader};
 pub use self::read_payload::{read_payload, read_encrypted_payload, ReadPayload};
 pub use self::read_message::{read_message, read_encrypted_message, ReadMessage};
+pub use self::session_keys::{ConnectionKey, SessionKeys, SessionCipher};
 pub use self::shared_tcp_stream::SharedTcpStream;
 pub use self::write_message::{write_message, write_encrypted_message, WriteMessage};
@@ -17,10 +17,9 @@
 use std::io;
 use futures::{try_ready, Poll, Future, Async};
 use tokio_io::AsyncRead;
-use parity_crypto::publickey::KeyPair;
 use crate::key_server_cluster::Error;
 use crate::key_server_cluster::message::Message;
-use crate::key_server_cluster::io::{read_header, ReadHeader, read_payload, read_encrypted_payload, ReadPayload};
+use crate::key_server_cluster::io::{ConnectionKey, read_header, ReadHeader, read_payload, read_encrypted_payload, ReadPayload};
 
 /// Create future for read single message from the stream.
 pub fn read_message<A>(a: A) -> ReadMessage<A> where A: AsyncRead {
@@ -31,7 +30,7 @@ pub fn read_message<A>(a: A) -> ReadMessage<A> where A: AsyncRead {
 }
 
 /// Create future for read single encrypted message from the stream.
-pub fn read_encrypted_message<A>(a: A, key: KeyPair) -> ReadMessage<A> where A: AsyncRead {
+pub fn read_encrypted_message<A>(a: A, key: ConnectionKey) -> ReadMessage<A> where A: AsyncRead {
 	ReadMessage {
 		key: Some(key),
 		state: ReadMessageState::ReadHeader(read_header(a)),
@@ -46,7 +45,7 @@ enum ReadMessageState<A> {
 
 /// Future for read single message from the stream.
 pub struct ReadMessage<A> {
-	key: Option<KeyPair>,
+	key: Option<ConnectionKey>,
 	state: ReadMessageState<A>,
 }
 
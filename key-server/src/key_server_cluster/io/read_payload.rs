@@ -18,10 +18,10 @@ use std::io;
 use futures::{try_ready, Poll, Future};
 use tokio_io::AsyncRead;
 use tokio_io::io::{read_exact, ReadExact};
-use parity_crypto::publickey::KeyPair;
 use crate::key_server_cluster::Error;
 use crate::key_server_cluster::message::Message;
-use crate::key_server_cluster::io::message::{MessageHeader, deserialize_message, decrypt_message};
+use crate::key_server_cluster::io::ConnectionKey;
+use crate::key_server_cluster::io::message::{MessageHeader, deserialize_message};
 
 /// Create future for read single message payload from the stream.
 pub fn read_payload<A>(a: A, header: MessageHeader) -> ReadPayload<A> where A: AsyncRead {
@@ -33,7 +33,7 @@ pub fn read_payload<A>(a: A, header: MessageHeader) -> ReadPayload<A> where A: A
 }
 
 /// Create future for read single encrypted message payload from the stream.
-pub fn read_encrypted_payload<A>(a: A, header: MessageHeader, key: KeyPair) -> ReadPayload<A> where A: AsyncRead {
+pub fn read_encrypted_payload<A>(a: A, header: MessageHeader, key: ConnectionKey) -> ReadPayload<A> where A: AsyncRead {
 	ReadPayload {
 		reader: read_exact(a, vec![0; header.size as usize]),
 		header: header,
@@ -45,7 +45,7 @@ pub fn read_encrypted_payload<A>(a: A, header: MessageHeader, key: KeyPair) -> R
 pub struct ReadPayload<A> {
 	reader: ReadExact<A, Vec<u8>>,
 	header: MessageHeader,
-	key: Option<KeyPair>,
+	key: Option<ConnectionKey>,
 }
 
 impl<A> Future for ReadPayload<A> where A: AsyncRead {
@@ -55,7 +55,7 @@ impl<A> Future for ReadPayload<A> where A: AsyncRead {
 	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
 		let (read, data) = try_ready!(self.reader.poll());
 		let payload = if let Some(key) = self.key.take() {
-			decrypt_message(&key, data)
+			key.decrypt_message(&self.header, data)
 				.and_then(|data| deserialize_message(&self.header, data))
 		} else {
 			deserialize_message(&self.header, data)
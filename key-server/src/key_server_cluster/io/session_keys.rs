@@ -0,0 +1,369 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use byteorder::{ByteOrder, LittleEndian, BigEndian};
+use ethereum_types::{H128, H256};
+use keccak_hash::keccak;
+use parking_lot::Mutex;
+use parity_crypto::{aes, hmac};
+use parity_crypto::publickey::KeyPair;
+use crate::key_server_cluster::{Error, NodeId};
+use crate::key_server_cluster::io::message::{MESSAGE_HEADER_SIZE, MessageHeader, SerializedMessage,
+	serialize_header, deserialize_header, build_serialized_message, encrypt_message, decrypt_message};
+
+/// Size of message nonce, prepended to the encrypted payload.
+const NONCE_SIZE: usize = 8;
+/// Size of message authentication tag, appended to the encrypted payload.
+const TAG_SIZE: usize = 32;
+/// Number of recent nonces that are tracked by the receiver. Messages of single connection
+/// could be written to the stream in order that differs from the order of nonces, so we can't
+/// just require every nonce to be larger than the previous one.
+const REPLAY_WINDOW_SIZE: u64 = 64;
+/// Label that is used to derive session encryption keys.
+const ENCRYPTION_KEY_LABEL: &[u8] = b"parity-secretstore-session-encryption-key";
+/// Label that is used to derive session authentication keys.
+const AUTHENTICATION_KEY_LABEL: &[u8] = b"parity-secretstore-session-authentication-key";
+
+/// Key that is used to protect messages of the established connection.
+#[derive(Clone)]
+pub enum ConnectionKey {
+	/// Every message is encrypted using ECIES with the shared key pair. Used to talk
+	/// to nodes that do not support session keys.
+	SharedKeyPair(KeyPair),
+	/// Messages are encrypted and authenticated with symmetric session keys.
+	SessionKeys(Arc<SessionCipher>),
+}
+
+/// Symmetric keys of the connection, derived from the shared key during handshake.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionKeys {
+	/// Keys that are used to protect messages, sent to the peer.
+	send: DirectionKeys,
+	/// Keys that are used to check messages, received from the peer.
+	receive: DirectionKeys,
+}
+
+/// Keys that are used to protect messages, flowing in single direction.
+#[derive(Clone, Debug, PartialEq)]
+struct DirectionKeys {
+	/// AES-128-CTR encryption key.
+	encryption_key: H128,
+	/// HMAC-SHA256 authentication key.
+	authentication_key: H256,
+}
+
+/// Stateful cipher of the connection: session keys + nonces of sent and received messages.
+pub struct SessionCipher {
+	/// Session keys.
+	keys: SessionKeys,
+	/// Nonce of the next sent message.
+	send_nonce: Mutex<u64>,
+	/// Nonces of recently received messages.
+	replay_window: Mutex<ReplayWindow>,
+}
+
+/// Sliding window of nonces of recently received messages.
+#[derive(Default)]
+struct ReplayWindow {
+	/// Maximal nonce that has been received so far.
+	max_nonce: Option<u64>,
+	/// Bitmap of received nonces. Bit N is set if `max_nonce - N` has been received.
+	received: u64,
+}
+
+impl ConnectionKey {
+	/// Create connection key from session keys.
+	pub fn session(keys: SessionKeys) -> Self {
+		ConnectionKey::SessionKeys(Arc::new(SessionCipher::new(keys)))
+	}
+
+	/// Encrypt serialized message.
+	pub fn encrypt_message(&self, message: SerializedMessage) -> Result<SerializedMessage, Error> {
+		match *self {
+			ConnectionKey::SharedKeyPair(ref key) => encrypt_message(key, message),
+			ConnectionKey::SessionKeys(ref cipher) => cipher.encrypt_message(message),
+		}
+	}
+
+	/// Decrypt payload of the message with given header.
+	pub fn decrypt_message(&self, header: &MessageHeader, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+		match *self {
+			ConnectionKey::SharedKeyPair(ref key) => decrypt_message(key, payload),
+			ConnectionKey::SessionKeys(ref cipher) => cipher.decrypt_message(header, payload),
+		}
+	}
+}
+
+impl SessionKeys {
+	/// Derive session keys from the shared key. Every direction has its own keys, so
+	/// nonces of both nodes could start from zero.
+	pub fn derive(shared_key: &KeyPair, self_node_id: &NodeId, peer_node_id: &NodeId) -> Self {
+		SessionKeys {
+			send: DirectionKeys::derive(shared_key, self_node_id),
+			receive: DirectionKeys::derive(shared_key, peer_node_id),
+		}
+	}
+}
+
+impl DirectionKeys {
+	/// Derive keys that are used to protect messages of given sender.
+	fn derive(shared_key: &KeyPair, sender: &NodeId) -> Self {
+		let derive_key = |label: &[u8]| {
+			let mut data = Vec::with_capacity(32 + label.len() + 20);
+			data.extend_from_slice(shared_key.secret().as_bytes());
+			data.extend_from_slice(label);
+			data.extend_from_slice(sender.as_bytes());
+			keccak(&data)
+		};
+
+		DirectionKeys {
+			encryption_key: H128::from_slice(&derive_key(ENCRYPTION_KEY_LABEL)[..16]),
+			authentication_key: derive_key(AUTHENTICATION_KEY_LABEL),
+		}
+	}
+
+	/// Compute authentication tag of the message.
+	fn authentication_tag(&self, header: &[u8], nonce_and_ciphertext: &[u8]) -> Vec<u8> {
+		let key = hmac::SigKey::sha256(self.authentication_key.as_bytes());
+		let mut signer = hmac::Signer::with(&key);
+		signer.update(header);
+		signer.update(nonce_and_ciphertext);
+		signer.sign().to_vec()
+	}
+}
+
+impl SessionCipher {
+	/// Create new cipher.
+	pub fn new(keys: SessionKeys) -> Self {
+		SessionCipher {
+			keys,
+			send_nonce: Mutex::new(0),
+			replay_window: Mutex::new(ReplayWindow::default()),
+		}
+	}
+
+	/// Encrypt serialized message. Encrypted payload is: nonce || ciphertext || tag.
+	pub fn encrypt_message(&self, message: SerializedMessage) -> Result<SerializedMessage, Error> {
+		let mut message: Vec<_> = message.into();
+		let payload = message.split_off(MESSAGE_HEADER_SIZE);
+		let mut header = deserialize_header(&message)?;
+		let encrypted_size = NONCE_SIZE + payload.len() + TAG_SIZE;
+		if encrypted_size > u16::max_value() as usize {
+			return Err(Error::InvalidMessage);
+		}
+		header.size = encrypted_size as u16;
+
+		let nonce = {
+			let mut send_nonce = self.send_nonce.lock();
+			let nonce = *send_nonce;
+			*send_nonce = nonce.checked_add(1)
+				.ok_or_else(|| Error::Internal("Session nonces are exhausted".into()))?;
+			nonce
+		};
+
+		let mut encrypted_payload = vec![0; NONCE_SIZE + payload.len()];
+		LittleEndian::write_u64(&mut encrypted_payload[..NONCE_SIZE], nonce);
+		aes::encrypt_128_ctr(
+			self.keys.send.encryption_key.as_bytes(),
+			&make_iv(nonce),
+			&payload,
+			&mut encrypted_payload[NONCE_SIZE..],
+		).map_err(|err| Error::EthKey(err.to_string()))?;
+
+		let tag = self.keys.send.authentication_tag(&serialize_header(&header)?, &encrypted_payload);
+		encrypted_payload.extend_from_slice(&tag);
+		build_serialized_message(header, encrypted_payload)
+	}
+
+	/// Decrypt payload of the message with given header. Fails if message has been modified
+	/// or if message with the same nonce has already been received.
+	pub fn decrypt_message(&self, header: &MessageHeader, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+		if payload.len() < NONCE_SIZE + TAG_SIZE {
+			return Err(Error::InvalidMessage);
+		}
+
+		let (nonce_and_ciphertext, tag) = payload.split_at(payload.len() - TAG_SIZE);
+		let expected_tag = self.keys.receive.authentication_tag(&serialize_header(header)?, nonce_and_ciphertext);
+		if !parity_crypto::is_equal(&expected_tag, tag) {
+			return Err(Error::InvalidMessage);
+		}
+
+		let nonce = LittleEndian::read_u64(&nonce_and_ciphertext[..NONCE_SIZE]);
+		if !self.replay_window.lock().insert(nonce) {
+			return Err(Error::ReplayProtection);
+		}
+
+		let ciphertext = &nonce_and_ciphertext[NONCE_SIZE..];
+		let mut plain = vec![0; ciphertext.len()];
+		aes::decrypt_128_ctr(
+			self.keys.receive.encryption_key.as_bytes(),
+			&make_iv(nonce),
+			ciphertext,
+			&mut plain,
+		).map_err(|err| Error::EthKey(err.to_string()))?;
+		Ok(plain)
+	}
+}
+
+impl ReplayWindow {
+	/// Remember received nonce. Returns false if nonce has already been received or if it is too old.
+	fn insert(&mut self, nonce: u64) -> bool {
+		let max_nonce = match self.max_nonce {
+			Some(max_nonce) => max_nonce,
+			None => {
+				self.max_nonce = Some(nonce);
+				self.received = 1;
+				return true;
+			},
+		};
+
+		if nonce > max_nonce {
+			let shift = nonce - max_nonce;
+			self.received = if shift >= REPLAY_WINDOW_SIZE { 0 } else { self.received << shift };
+			self.received |= 1;
+			self.max_nonce = Some(nonce);
+			return true;
+		}
+
+		let offset = max_nonce - nonce;
+		if offset >= REPLAY_WINDOW_SIZE {
+			return false;
+		}
+
+		let mask = 1u64 << offset;
+		if self.received & mask != 0 {
+			return false;
+		}
+
+		self.received |= mask;
+		true
+	}
+}
+
+/// Make AES-CTR IV for the message with given nonce. Nonce occupies upper half of the counter block
+/// and lower half is incremented for every block of the message, so counter blocks of different
+/// messages never overlap.
+fn make_iv(nonce: u64) -> [u8; 16] {
+	let mut iv = [0u8; 16];
+	BigEndian::write_u64(&mut iv[..8], nonce);
+	iv
+}
+
+#[cfg(test)]
+mod tests {
+	use parity_crypto::publickey::{Random, Generator};
+	use crate::key_server_cluster::Error;
+	use crate::key_server_cluster::message::{Message, ClusterMessage, KeepAliveResponse};
+	use crate::key_server_cluster::io::message::{MESSAGE_HEADER_SIZE, SESSION_KEYS_HEADER_VERSION,
+		serialize_message, deserialize_header, deserialize_message};
+	use super::{SessionKeys, SessionCipher, ReplayWindow};
+
+	fn ciphers() -> (SessionCipher, SessionCipher) {
+		let shared_key = Random.generate();
+		let node1 = Random.generate().address();
+		let node2 = Random.generate().address();
+		(
+			SessionCipher::new(SessionKeys::derive(&shared_key, &node1, &node2)),
+			SessionCipher::new(SessionKeys::derive(&shared_key, &node2, &node1)),
+		)
+	}
+
+	fn encrypted_message(cipher: &SessionCipher) -> Vec<u8> {
+		let message = Message::Cluster(ClusterMessage::KeepAliveResponse(KeepAliveResponse {
+			session_id: Some(Default::default()),
+		}));
+		let message = serialize_message(message, SESSION_KEYS_HEADER_VERSION).unwrap();
+		cipher.encrypt_message(message).unwrap().into()
+	}
+
+	fn decrypt_message(cipher: &SessionCipher, message: Vec<u8>) -> Result<Message, Error> {
+		let header = deserialize_header(&message[..MESSAGE_HEADER_SIZE])?;
+		assert_eq!(header.size as usize, message.len() - MESSAGE_HEADER_SIZE);
+		cipher.decrypt_message(&header, message[MESSAGE_HEADER_SIZE..].to_vec())
+			.and_then(|payload| deserialize_message(&header, payload))
+	}
+
+	#[test]
+	fn session_keys_of_peers_are_matching() {
+		let shared_key = Random.generate();
+		let node1 = Random.generate().address();
+		let node2 = Random.generate().address();
+		let keys1 = SessionKeys::derive(&shared_key, &node1, &node2);
+		let keys2 = SessionKeys::derive(&shared_key, &node2, &node1);
+		assert_eq!(keys1.send, keys2.receive);
+		assert_eq!(keys1.receive, keys2.send);
+		assert!(keys1.send != keys1.receive);
+	}
+
+	#[test]
+	fn messages_are_encrypted_and_decrypted_in_both_directions() {
+		let (cipher1, cipher2) = ciphers();
+		assert!(decrypt_message(&cipher2, encrypted_message(&cipher1)).is_ok());
+		assert!(decrypt_message(&cipher1, encrypted_message(&cipher2)).is_ok());
+		assert!(decrypt_message(&cipher2, encrypted_message(&cipher1)).is_ok());
+	}
+
+	#[test]
+	fn own_messages_are_not_accepted() {
+		let (cipher1, _) = ciphers();
+		assert_eq!(decrypt_message(&cipher1, encrypted_message(&cipher1)).unwrap_err(), Error::InvalidMessage);
+	}
+
+	#[test]
+	fn modified_messages_are_rejected() {
+		let (cipher1, cipher2) = ciphers();
+		let message = encrypted_message(&cipher1);
+		for index in &[8, MESSAGE_HEADER_SIZE, MESSAGE_HEADER_SIZE + 8, message.len() - 1] {
+			let mut modified_message = message.clone();
+			modified_message[*index] ^= 1;
+			assert_eq!(decrypt_message(&cipher2, modified_message).unwrap_err(), Error::InvalidMessage);
+		}
+		assert!(decrypt_message(&cipher2, message).is_ok());
+	}
+
+	#[test]
+	fn replayed_messages_are_rejected() {
+		let (cipher1, cipher2) = ciphers();
+		let message = encrypted_message(&cipher1);
+		assert!(decrypt_message(&cipher2, message.clone()).is_ok());
+		assert_eq!(decrypt_message(&cipher2, message).unwrap_err(), Error::ReplayProtection);
+	}
+
+	#[test]
+	fn reordered_messages_are_accepted() {
+		let (cipher1, cipher2) = ciphers();
+		let message1 = encrypted_message(&cipher1);
+		let message2 = encrypted_message(&cipher1);
+		assert!(decrypt_message(&cipher2, message2).is_ok());
+		assert!(decrypt_message(&cipher2, message1).is_ok());
+	}
+
+	#[test]
+	fn replay_window_works() {
+		let mut window = ReplayWindow::default();
+		assert!(window.insert(10));
+		assert!(!window.insert(10));
+		assert!(window.insert(5));
+		assert!(!window.insert(5));
+		assert!(window.insert(80));
+		assert!(!window.insert(16));
+		assert!(window.insert(17));
+		assert!(!window.insert(17));
+		assert!(window.insert(1000));
+		assert!(!window.insert(80));
+	}
+}
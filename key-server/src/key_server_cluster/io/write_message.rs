@@ -18,13 +18,13 @@ use std::io;
 use futures::{Future, Poll};
 use tokio_io::AsyncWrite;
 use tokio_io::io::{WriteAll, write_all};
-use parity_crypto::publickey::KeyPair;
 use crate::key_server_cluster::message::Message;
-use crate::key_server_cluster::io::{serialize_message, encrypt_message};
+use crate::key_server_cluster::io::{ConnectionKey, serialize_message};
+use crate::key_server_cluster::io::message::HANDSHAKE_HEADER_VERSION;
 
-/// Write plain message to the channel.
+/// Write plain (handshake) message to the channel.
 pub fn write_message<A>(a: A, message: Message) -> WriteMessage<A> where A: AsyncWrite {
-	let (error, future) = match serialize_message(message)
+	let (error, future) = match serialize_message(message, HANDSHAKE_HEADER_VERSION)
 		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())) {
 		Ok(message) => (None, write_all(a, message.into())),
 		Err(error) => (Some(error), write_all(a, Vec::new())),
@@ -35,10 +35,10 @@ pub fn write_message<A>(a: A, message: Message) -> WriteMessage<A> where A: Asyn
 	}
 }
 
-/// Write encrypted message to the channel.
-pub fn write_encrypted_message<A>(a: A, key: &KeyPair, message: Message) -> WriteMessage<A> where A: AsyncWrite {
-	let (error, future) = match serialize_message(message)
-		.and_then(|message| encrypt_message(key, message))
+/// Write encrypted message to the channel, using codec that corresponds to given header version.
+pub fn write_encrypted_message<A>(a: A, key: &ConnectionKey, version: u64, message: Message) -> WriteMessage<A> where A: AsyncWrite {
+	let (error, future) = match serialize_message(message, version)
+		.and_then(|message| key.encrypt_message(message))
 		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())) {
 		Ok(message) => (None, write_all(a, message.into())),
 		Err(error) => (Some(error), write_all(a, Vec::new())),
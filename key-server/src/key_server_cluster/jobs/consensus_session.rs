@@ -366,9 +366,8 @@ impl<ConsensusExecutor, ConsensusTransport, ComputationExecutor, ComputationTran
 
 #[cfg(test)]
 mod tests {
-	use std::sync::Arc;
 	use parity_crypto::publickey::{KeyPair, Random, Generator, sign, public_to_address};
-	use primitives::acl_storage::InMemoryPermissiveAclStorage;
+	use primitives::acl_storage::{AclStorage, InMemoryPermissiveAclStorage};
 	use crate::key_server_cluster::{Error, NodeId, SessionId, Requester};
 	use crate::key_server_cluster::message::{ConsensusMessage, InitializeConsensusSession, ConfirmConsensusInitialization};
 	use crate::key_server_cluster::jobs::job_session::tests::{make_master_session_meta, make_slave_session_meta, SquaredSumJobExecutor, DummyJobTransport};
@@ -378,25 +377,29 @@ mod tests {
 	type SquaredSumConsensusSession = ConsensusSession<KeyAccessJob, DummyJobTransport<Requester, bool>, SquaredSumJobExecutor, DummyJobTransport<u32, u32>>;
 
 	fn make_master_consensus_session(threshold: usize, requester: Option<KeyPair>, acl_storage: Option<InMemoryPermissiveAclStorage>) -> SquaredSumConsensusSession {
-		let secret = requester.map(|kp| kp.secret().clone()).unwrap_or(Random.generate().secret().clone());
+		let requester = requester.unwrap_or_else(|| Random.generate());
+		let requester_address = public_to_address(requester.public());
+		let acl_storage = acl_storage.unwrap_or_default();
+		let mut consensus_executor = KeyAccessJob::new_on_master(
+			SessionId::from([1u8; 32]),
+			sign(requester.secret(), &SessionId::from([1u8; 32])).unwrap().into(),
+		);
+		consensus_executor.set_access_check_result(requester_address, acl_storage.check(requester_address, &SessionId::from([1u8; 32])));
 		SquaredSumConsensusSession::new(ConsensusSessionParams {
 			meta: make_master_session_meta(threshold),
-			consensus_executor: KeyAccessJob::new_on_master(
-				SessionId::from([1u8; 32]),
-				Arc::new(acl_storage.unwrap_or(InMemoryPermissiveAclStorage::default())),
-				sign(&secret, &SessionId::from([1u8; 32])).unwrap().into()
-			),
+			consensus_executor,
 			consensus_transport: DummyJobTransport::default(),
 		}).unwrap()
 	}
 
-	fn make_slave_consensus_session(threshold: usize, acl_storage: Option<InMemoryPermissiveAclStorage>) -> SquaredSumConsensusSession {
+	fn make_slave_consensus_session(threshold: usize, requester: Option<&KeyPair>) -> SquaredSumConsensusSession {
+		let mut consensus_executor = KeyAccessJob::new_on_slave(SessionId::from([1u8; 32]));
+		if let Some(requester) = requester {
+			consensus_executor.set_access_check_result(public_to_address(requester.public()), Ok(true));
+		}
 		SquaredSumConsensusSession::new(ConsensusSessionParams {
 			meta: make_slave_session_meta(threshold),
-			consensus_executor: KeyAccessJob::new_on_slave(
-				SessionId::from([1u8; 32]),
-				Arc::new(acl_storage.unwrap_or(InMemoryPermissiveAclStorage::default())),
-			),
+			consensus_executor,
 			consensus_transport: DummyJobTransport::default(),
 		}).unwrap()
 	}
@@ -511,11 +514,30 @@ mod tests {
 	}
 
 	#[test]
-	fn consensus_session_computation_request_is_ignored_when_wrong() {
+	fn consensus_session_initialization_fails_when_acl_check_is_not_completed() {
 		let mut session = make_slave_consensus_session(0, None);
+		assert!(session.on_consensus_message(&NodeId::from_low_u64_be(1), &ConsensusMessage::InitializeConsensusSession(InitializeConsensusSession {
+			requester: Requester::Signature(sign(Random.generate().secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
+			version: Default::default(),
+		})).is_err());
+	}
+
+	#[test]
+	fn consensus_session_initialization_fails_when_acl_check_is_completed_for_other_requester() {
+		let mut session = make_slave_consensus_session(0, Some(&Random.generate()));
+		assert!(session.on_consensus_message(&NodeId::from_low_u64_be(1), &ConsensusMessage::InitializeConsensusSession(InitializeConsensusSession {
+			requester: Requester::Signature(sign(Random.generate().secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
+			version: Default::default(),
+		})).is_err());
+	}
+
+	#[test]
+	fn consensus_session_computation_request_is_ignored_when_wrong() {
+		let requester = Random.generate();
+		let mut session = make_slave_consensus_session(0, Some(&requester));
 		assert_eq!(session.state(), ConsensusSessionState::WaitingForInitialization);
 		session.on_consensus_message(&NodeId::from_low_u64_be(1), &ConsensusMessage::InitializeConsensusSession(InitializeConsensusSession {
-			requester: Requester::Signature(sign(Random.generate().secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
+			requester: Requester::Signature(sign(requester.secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
 			version: Default::default(),
 		})).unwrap();
 		assert_eq!(session.state(), ConsensusSessionState::ConsensusEstablished);
@@ -525,10 +547,11 @@ mod tests {
 
 	#[test]
 	fn consensus_session_computation_request_is_processed_when_correct() {
-		let mut session = make_slave_consensus_session(0, None);
+		let requester = Random.generate();
+		let mut session = make_slave_consensus_session(0, Some(&requester));
 		assert_eq!(session.state(), ConsensusSessionState::WaitingForInitialization);
 		session.on_consensus_message(&NodeId::from_low_u64_be(1), &ConsensusMessage::InitializeConsensusSession(InitializeConsensusSession {
-			requester: Requester::Signature(sign(Random.generate().secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
+			requester: Requester::Signature(sign(requester.secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
 			version: Default::default(),
 		})).unwrap();
 		assert_eq!(session.state(), ConsensusSessionState::ConsensusEstablished);
@@ -556,9 +579,10 @@ mod tests {
 
 	#[test]
 	fn consessus_session_completion_is_accepted() {
-		let mut session = make_slave_consensus_session(0, None);
+		let requester = Random.generate();
+		let mut session = make_slave_consensus_session(0, Some(&requester));
 		session.on_consensus_message(&NodeId::from_low_u64_be(1), &ConsensusMessage::InitializeConsensusSession(InitializeConsensusSession {
-			requester: Requester::Signature(sign(Random.generate().secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
+			requester: Requester::Signature(sign(requester.secret(), &SessionId::from([1u8; 32])).unwrap()).into(),
 			version: Default::default(),
 		})).unwrap();
 		session.on_session_completed(&NodeId::from_low_u64_be(1)).unwrap();
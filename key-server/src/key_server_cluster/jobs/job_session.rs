@@ -292,11 +292,18 @@ impl<Executor, Transport> JobSession<Executor, Transport> where Executor: JobExe
 
 		let active_data = self.data.active_data.as_mut()
 			.expect("on_partial_response is only called on master nodes; on master nodes active_data is filled during initialization; qed");
-		if !active_data.requests.remove(node) {
+		if !active_data.requests.contains(node) {
 			return Err(Error::InvalidNodeForRequest);
 		}
 
-		match self.executor.check_partial_response(node, &response)? {
+		// node stays in requests until its response is checked, so that invalid response could be
+		// reported using on_node_error() && response to the other request doesn't cancel current request
+		let response_action = self.executor.check_partial_response(node, &response)?;
+		if response_action != JobPartialResponseAction::Ignore {
+			active_data.requests.remove(node);
+		}
+
+		match response_action {
 			JobPartialResponseAction::Ignore => Ok(()),
 			JobPartialResponseAction::Reject => {
 				// direct reject is always considered as fatal
@@ -405,7 +412,7 @@ pub mod tests {
 
 		fn prepare_partial_request(&self, _n: &NodeId, _nodes: &BTreeSet<NodeId>) -> Result<u32, Error> { Ok(2) }
 		fn process_partial_request(&mut self, r: u32) -> Result<JobPartialRequestAction<u32>, Error> { if r <= 10 { Ok(JobPartialRequestAction::Respond(r * r)) } else { Err(Error::InvalidMessage) } }
-		fn check_partial_response(&mut self, _s: &NodeId, r: &u32) -> Result<JobPartialResponseAction, Error> { if r % 2 == 0 { Ok(JobPartialResponseAction::Accept) } else { Ok(JobPartialResponseAction::Reject) } }
+		fn check_partial_response(&mut self, _s: &NodeId, r: &u32) -> Result<JobPartialResponseAction, Error> { if *r > 100 { Err(Error::InvalidMessage) } else if r % 2 == 0 { Ok(JobPartialResponseAction::Accept) } else { Ok(JobPartialResponseAction::Reject) } }
 		fn compute_response(&self, r: &BTreeMap<NodeId, u32>) -> Result<u32, Error> { Ok(r.values().fold(0, |v1, v2| v1 + v2)) }
 	}
 
@@ -568,6 +575,17 @@ pub mod tests {
 		assert_eq!(job.state(), JobSessionState::Finished);
 	}
 
+	#[test]
+	fn job_invalid_response_is_reported_with_node_error() {
+		let mut job = JobSession::new(make_master_session_meta(1), SquaredSumJobExecutor, DummyJobTransport::default());
+		job.initialize(vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2), Address::from_low_u64_be(3)].into_iter().collect(), None, false).unwrap();
+		assert_eq!(job.on_partial_response(&NodeId::from_low_u64_be(2), 102).unwrap_err(), Error::InvalidMessage);
+		assert!(job.requests().contains(&NodeId::from_low_u64_be(2)));
+		job.on_node_error(&NodeId::from_low_u64_be(2), Error::InvalidMessage).unwrap();
+		assert!(!job.requests().contains(&NodeId::from_low_u64_be(2)));
+		assert_eq!(job.state(), JobSessionState::Active);
+	}
+
 	#[test]
 	fn job_node_error_ignored_when_slave_disconnects_from_slave() {
 		let mut job = JobSession::new(make_slave_session_meta(1), SquaredSumJobExecutor, DummyJobTransport::default());
@@ -14,9 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
 use std::collections::{BTreeSet, BTreeMap};
-use primitives::acl_storage::AclStorage;
+use ethereum_types::Address;
 use crate::key_server_cluster::{Error, NodeId, SessionId, Requester};
 use crate::key_server_cluster::jobs::job_session::{JobPartialResponseAction, JobPartialRequestAction, JobExecutor};
 
@@ -26,31 +25,37 @@ pub struct KeyAccessJob {
 	id: SessionId,
 	/// Has key share?
 	has_key_share: bool,
-	/// ACL storage.
-	acl_storage: Arc<dyn AclStorage>,
+	/// Result of requester access check, awaited before the job has been started.
+	access_check_result: Option<(Address, Result<bool, Error>)>,
 	/// Requester data.
 	requester: Option<Requester>,
 }
 
 impl KeyAccessJob {
-	pub fn new_on_slave(id: SessionId, acl_storage: Arc<dyn AclStorage>) -> Self {
+	pub fn new_on_slave(id: SessionId) -> Self {
 		KeyAccessJob {
 			id: id,
 			has_key_share: true,
-			acl_storage: acl_storage,
+			access_check_result: None,
 			requester: None,
 		}
 	}
 
-	pub fn new_on_master(id: SessionId, acl_storage: Arc<dyn AclStorage>, requester: Requester) -> Self {
+	pub fn new_on_master(id: SessionId, requester: Requester) -> Self {
 		KeyAccessJob {
 			id: id,
 			has_key_share: true,
-			acl_storage: acl_storage,
+			access_check_result: None,
 			requester: Some(requester),
 		}
 	}
 
+	/// Set result of the requester access check. ACL checks may require waiting, so they're
+	/// awaited by the caller, before the request reaches the job.
+	pub fn set_access_check_result(&mut self, requester_address: Address, result: Result<bool, Error>) {
+		self.access_check_result = Some((requester_address, result));
+	}
+
 	pub fn set_has_key_share(&mut self, has_key_share: bool) {
 		self.has_key_share = has_key_share;
 	}
@@ -79,8 +84,11 @@ impl JobExecutor for KeyAccessJob {
 		}
 
 		self.requester = Some(partial_request.clone());
-		self.acl_storage.check(partial_request.address(&self.id)?, &self.id)
-			.map(|is_confirmed| if is_confirmed { JobPartialRequestAction::Respond(true) } else { JobPartialRequestAction::Reject(false) })
+		let requester_address = partial_request.address(&self.id)?;
+		match self.access_check_result {
+			Some((ref checked_address, ref result)) if *checked_address == requester_address => result.clone(),
+			_ => Err(Error::Internal("ACL check has not been completed for the requester".into())),
+		}.map(|is_confirmed| if is_confirmed { JobPartialRequestAction::Respond(true) } else { JobPartialRequestAction::Reject(false) })
 	}
 
 	fn check_partial_response(&mut self, _sender: &NodeId, partial_response: &bool) -> Result<JobPartialResponseAction, Error> {
@@ -32,6 +32,8 @@ pub struct EcdsaSigningJob {
 	inv_nonce_share: Secret,
 	/// Nonce public.
 	nonce_public: Public,
+	/// Publics of inv(nonce) shares of all signing nodes (on master node).
+	inv_nonce_public_shares: BTreeMap<NodeId, Public>,
 	/// Request id.
 	request_id: Option<Secret>,
 	/// ECDSA reversed-nonce coefficient
@@ -57,6 +59,9 @@ pub struct EcdsaPartialSigningResponse {
 	pub request_id: Secret,
 	/// Partial signature' s share.
 	pub partial_signature_s: Secret,
+	/// Proof that s share is computed using correct secret share. None if share is computed
+	/// by the node that doesn't support verifiable signature shares.
+	pub partial_signature_s_proof: Option<math::EcdsaSignatureShareProof>,
 }
 
 impl EcdsaSigningJob {
@@ -66,18 +71,21 @@ impl EcdsaSigningJob {
 			key_version: key_version,
 			nonce_public: nonce_public,
 			inv_nonce_share: inv_nonce_share,
+			inv_nonce_public_shares: BTreeMap::new(),
 			request_id: None,
 			inversed_nonce_coeff: None,
 			message_hash: None,
 		})
 	}
 
-	pub fn new_on_master(key_share: KeyShare, key_version: H256, nonce_public: Public, inv_nonce_share: Secret, inversed_nonce_coeff: Secret, message_hash: H256) -> Result<Self, Error> {
+	pub fn new_on_master(key_share: KeyShare, key_version: H256, nonce_public: Public, inv_nonce_share: Secret,
+		inv_nonce_public_shares: BTreeMap<NodeId, Public>, inversed_nonce_coeff: Secret, message_hash: H256) -> Result<Self, Error> {
 		Ok(EcdsaSigningJob {
 			key_share: key_share,
 			key_version: key_version,
 			nonce_public: nonce_public,
 			inv_nonce_share: inv_nonce_share,
+			inv_nonce_public_shares: inv_nonce_public_shares,
 			request_id: Some(math::generate_random_scalar()?),
 			inversed_nonce_coeff: Some(inversed_nonce_coeff),
 			message_hash: Some(message_hash),
@@ -118,18 +126,53 @@ impl JobExecutor for EcdsaSigningJob {
 			&signature_r,
 			&math::to_scalar(partial_request.message_hash)?,
 		)?;
+		let partial_signature_s_proof = math::prove_ecdsa_signature_share(
+			&inversed_nonce_coeff_mul_nonce,
+			&math::compute_public_share(&key_version.secret_share)?,
+		)?;
 
 		Ok(JobPartialRequestAction::Respond(EcdsaPartialSigningResponse {
 			request_id: partial_request.id,
 			partial_signature_s: partial_signature_s,
+			partial_signature_s_proof: Some(partial_signature_s_proof),
 		}))
 	}
 
-	fn check_partial_response(&mut self, _sender: &NodeId, partial_response: &EcdsaPartialSigningResponse) -> Result<JobPartialResponseAction, Error> {
+	fn check_partial_response(&mut self, sender: &NodeId, partial_response: &EcdsaPartialSigningResponse) -> Result<JobPartialResponseAction, Error> {
 		if Some(&partial_response.request_id) != self.request_id.as_ref() {
 			return Ok(JobPartialResponseAction::Ignore);
 		}
-		// TODO [Trust]: check_ecdsa_signature_share()
+
+		// public key shares are unknown for versions, created by ShareAdd session => can't check signature share
+		let key_version = self.key_share.version(&self.key_version)?;
+		if key_version.public_shares.is_empty() {
+			return Ok(JobPartialResponseAction::Accept);
+		}
+
+		// nodes that don't support verifiable signature shares are not sending proofs => can't check signature share
+		let partial_signature_s_proof = match partial_response.partial_signature_s_proof.as_ref() {
+			Some(partial_signature_s_proof) => partial_signature_s_proof,
+			None => return Ok(JobPartialResponseAction::Accept),
+		};
+
+		let inversed_nonce_coeff = self.inversed_nonce_coeff.as_ref()
+			.expect("check_partial_response is only called on master nodes; inversed_nonce_coeff is filed in constructor on master nodes; qed");
+		let message_hash = self.message_hash.as_ref()
+			.expect("check_partial_response is only called on master nodes; message_hash is filed in constructor on master nodes; qed");
+		let public_key_share = key_version.public_shares.get(sender).ok_or(Error::InvalidMessage)?;
+		let inv_nonce_public_share = self.inv_nonce_public_shares.get(sender).ok_or(Error::InvalidNodeForRequest)?;
+		let is_signature_share_valid = math::check_ecdsa_signature_share(
+			&partial_response.partial_signature_s,
+			&math::compute_ecdsa_r(&self.nonce_public)?,
+			&math::to_scalar(message_hash.clone())?,
+			inversed_nonce_coeff,
+			inv_nonce_public_share,
+			public_key_share,
+			partial_signature_s_proof,
+		)?;
+		if !is_signature_share_valid {
+			return Err(Error::InvalidSignatureShare(sender.clone()));
+		}
 
 		Ok(JobPartialResponseAction::Accept)
 	}
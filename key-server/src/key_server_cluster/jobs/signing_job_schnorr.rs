@@ -34,6 +34,8 @@ pub struct SchnorrSigningJob {
 	session_public: Public,
 	/// Session secret coefficient.
 	session_secret_coeff: Secret,
+	/// Publics of session secret coefficients of all signing nodes (on master node).
+	session_public_shares: BTreeMap<NodeId, Public>,
 	/// Request id.
 	request_id: Option<Secret>,
 	/// Message hash.
@@ -67,18 +69,21 @@ impl SchnorrSigningJob {
 			key_version: key_version,
 			session_public: session_public,
 			session_secret_coeff: session_secret_coeff,
+			session_public_shares: BTreeMap::new(),
 			request_id: None,
 			message_hash: None,
 		})
 	}
 
-	pub fn new_on_master(self_node_id: NodeId, key_share: KeyShare, key_version: H256, session_public: Public, session_secret_coeff: Secret, message_hash: H256) -> Result<Self, Error> {
+	pub fn new_on_master(self_node_id: NodeId, key_share: KeyShare, key_version: H256, session_public: Public, session_secret_coeff: Secret,
+		session_public_shares: BTreeMap<NodeId, Public>, message_hash: H256) -> Result<Self, Error> {
 		Ok(SchnorrSigningJob {
 			self_node_id: self_node_id,
 			key_share: key_share,
 			key_version: key_version,
 			session_public: session_public,
 			session_secret_coeff: session_secret_coeff,
+			session_public_shares: session_public_shares,
 			request_id: Some(math::generate_random_scalar()?),
 			message_hash: Some(message_hash),
 		})
@@ -131,11 +136,39 @@ impl JobExecutor for SchnorrSigningJob {
 		}))
 	}
 
-	fn check_partial_response(&mut self, _sender: &NodeId, partial_response: &SchnorrPartialSigningResponse) -> Result<JobPartialResponseAction, Error> {
+	fn check_partial_response(&mut self, sender: &NodeId, partial_response: &SchnorrPartialSigningResponse) -> Result<JobPartialResponseAction, Error> {
 		if Some(&partial_response.request_id) != self.request_id.as_ref() {
 			return Ok(JobPartialResponseAction::Ignore);
 		}
-		// TODO [Trust]: check_schnorr_signature_share()
+
+		// public key shares are unknown for versions, created by ShareAdd session => can't check signature share
+		let key_version = self.key_share.version(&self.key_version)?;
+		if key_version.public_shares.is_empty() {
+			return Ok(JobPartialResponseAction::Accept);
+		}
+
+		let message_hash = self.message_hash.as_ref()
+			.expect("check_partial_response is only called on master nodes; message_hash is filed in constructor on master nodes; qed");
+		let combined_hash = math::combine_message_hash_with_public(message_hash, &self.session_public)?;
+		let public_key_share = key_version.public_shares.get(sender).ok_or(Error::InvalidMessage)?;
+		let session_public_share = self.session_public_shares.get(sender).ok_or(Error::InvalidNodeForRequest)?;
+		let sender_id_number = key_version.id_numbers.get(sender).ok_or(Error::InvalidMessage)?;
+		let other_id_numbers = self.session_public_shares.keys()
+			.filter(|n| *n != sender)
+			.map(|n| key_version.id_numbers.get(n).ok_or(Error::InvalidMessage))
+			.collect::<Result<Vec<_>, _>>()?;
+		let is_signature_share_valid = math::check_schnorr_signature_share(
+			self.key_share.threshold,
+			&combined_hash,
+			&partial_response.partial_signature,
+			public_key_share,
+			session_public_share,
+			sender_id_number,
+			other_id_numbers.into_iter(),
+		)?;
+		if !is_signature_share_valid {
+			return Err(Error::InvalidSignatureShare(sender.clone()));
+		}
 
 		Ok(JobPartialResponseAction::Accept)
 	}
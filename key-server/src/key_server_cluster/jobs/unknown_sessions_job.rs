@@ -54,10 +54,15 @@ impl JobExecutor for UnknownSessionsJob {
 	}
 
 	fn process_partial_request(&mut self, partial_request: NodeId) -> Result<JobPartialRequestAction<BTreeSet<SessionId>>, Error> {
-		Ok(JobPartialRequestAction::Respond(self.key_storage.iter()
-			.filter(|&(_, ref key_share)| !key_share.versions.last().map(|v| v.id_numbers.contains_key(&partial_request)).unwrap_or(true))
-			.map(|(id, _)| id.clone())
-			.collect()))
+		let mut unknown_sessions = BTreeSet::new();
+		for key in self.key_storage.iter() {
+			let (id, key_share) = key?;
+			if !key_share.versions.last().map(|v| v.id_numbers.contains_key(&partial_request)).unwrap_or(true) {
+				unknown_sessions.insert(id);
+			}
+		}
+
+		Ok(JobPartialRequestAction::Respond(unknown_sessions))
 	}
 
 	fn check_partial_response(&mut self, _sender: &NodeId, _partial_response: &BTreeSet<SessionId>) -> Result<JobPartialResponseAction, Error> {
@@ -30,6 +30,17 @@ pub struct EncryptedSecret {
 	pub encrypted_point: Public,
 }
 
+/// Proof that ECDSA signature S-share is computed using inv(nonce) share and the node' secret share.
+#[derive(Debug, Clone)]
+pub struct EcdsaSignatureShareProof {
+	/// Public of inv(nonce) share, multiplied by secret share.
+	pub inv_nonce_mul_secret_public: Public,
+	/// Proof challenge.
+	pub challenge: Secret,
+	/// Proof response.
+	pub response: Secret,
+}
+
 /// Calculate the inversion of a Secret key (in place) using the `libsecp256k1` crate.
 fn invert_secret(s: &mut Secret) -> Result<(), Error> {
 	*s = secp256k1::SecretKey::parse(&s.0)
@@ -240,6 +251,37 @@ pub fn prepare_share_proof(polynom1: &[Secret]) -> Result<Vec<Public>, Error> {
 	Ok(share_proof)
 }
 
+/// Compute public key share of the node (i.e. `node_secret_share * G`), using public share proofs of all nodes.
+pub fn compute_public_key_share<'a, I>(node_number: &Secret, mut share_proofs: I) -> Result<Public, Error> where I: Iterator<Item=&'a [Public]> {
+	let mut public_key_share = compute_public_polynom(
+		share_proofs.next().expect("compute_public_key_share is called when there's at least one share proof; qed"),
+		node_number,
+	)?;
+	while let Some(share_proof) = share_proofs.next() {
+		ec_math_utils::public_add(&mut public_key_share, &compute_public_polynom(share_proof, node_number)?)?;
+	}
+
+	Ok(public_key_share)
+}
+
+/// Compute value of polynom, 'hidden' in the public share proof, using `node_number` as argument.
+fn compute_public_polynom(share_proof: &[Public], node_number: &Secret) -> Result<Public, Error> {
+	debug_assert!(!share_proof.is_empty());
+
+	let mut result = share_proof[0].clone();
+	for i in 1..share_proof.len() {
+		let mut secret_pow = node_number.clone();
+		secret_pow.pow(i)?;
+
+		let mut public_k = share_proof[i].clone();
+		ec_math_utils::public_mul_secret(&mut public_k, &secret_pow)?;
+
+		ec_math_utils::public_add(&mut result, &public_k)?;
+	}
+
+	Ok(result)
+}
+
 /// Compute secret subshare from passed secret value.
 pub fn compute_secret_subshare<'a, I>(threshold: usize, secret_value: &Secret, sender_id_number: &Secret, other_id_numbers: I) -> Result<Secret, Error> where I: Iterator<Item=&'a Secret> {
 	let mut subshare = compute_shadow_mul(secret_value, sender_id_number, other_id_numbers)?;
@@ -250,6 +292,20 @@ pub fn compute_secret_subshare<'a, I>(threshold: usize, secret_value: &Secret, s
 	Ok(subshare)
 }
 
+/// Compute public share proof of secret subshare, i.e. share proof with every commitment multiplied
+/// by the same coefficient that is used in `compute_secret_subshare`.
+pub fn compute_subshare_proof<'a, I>(threshold: usize, share_proof: &[Public], sender_id_number: &Secret, other_id_numbers: I) -> Result<Vec<Public>, Error> where I: Iterator<Item=&'a Secret> {
+	let mut one = [0u8; 32];
+	one[31] = 1;
+	let coeff = compute_secret_subshare(threshold, &Secret::from(one), sender_id_number, other_id_numbers)?;
+
+	let mut subshare_proof = share_proof.to_vec();
+	for public_k in subshare_proof.iter_mut() {
+		ec_math_utils::public_mul_secret(public_k, &coeff)?;
+	}
+	Ok(subshare_proof)
+}
+
 /// Compute secret share.
 pub fn compute_secret_share<'a, I>(secret_values: I) -> Result<Secret, Error> where I: Iterator<Item=&'a Secret> {
 	compute_secret_sum(secret_values)
@@ -433,24 +489,26 @@ pub fn compute_schnorr_signature_share<'a, I>(threshold: usize, combined_hash: &
 	Ok(sum)
 }
 
-/// Check Schnorr signature share.
-pub fn _check_schnorr_signature_share<'a, I>(_combined_hash: &Secret, _signature_share: &Secret, _public_share: &Public, _one_time_public_share: &Public, _node_numbers: I)
-	-> Result<bool, Error> where I: Iterator<Item=&'a Secret> {
-	// TODO [Trust]: in paper partial signature is checked using comparison:
-	//    sig[i] * T                                  = r[i] - c * lagrange_coeff(i) * y[i]
-	// => (k[i] - c * lagrange_coeff(i) * s[i]) * T   = r[i] - c * lagrange_coeff(i) * y[i]
-	// => k[i] * T - c * lagrange_coeff(i) * s[i] * T = k[i] * T - c * lagrange_coeff(i) * y[i]
-	// => this means that y[i] = s[i] * T
-	// but when verifying signature (for t = 1), nonce public (r) is restored using following expression:
-	// r = (sig[0] + sig[1]) * T - c * y
-	// r = (k[0] - c * lagrange_coeff(0) * s[0] + k[1] - c * lagrange_coeff(1) * s[1]) * T - c * y
-	// r = (k[0] + k[1]) * T - c * (lagrange_coeff(0) * s[0] + lagrange_coeff(1) * s[1]) * T - c * y
-	// r = r - c * (lagrange_coeff(0) * s[0] + lagrange_coeff(1) * s[1]) * T - c * y
-	// => -c * y = c * (lagrange_coeff(0) * s[0] + lagrange_coeff(1) * s[1]) * T
-	// => -y = (lagrange_coeff(0) * s[0] + lagrange_coeff(1) * s[1]) * T
-	// => y[i] != s[i] * T
-	// => some other way is required
-	Ok(true)
+/// Check Schnorr signature share, computed by the node with given number.
+/// Share is valid when `signature_share * G = one_time_public_share - c * lagrange_coeff(i) * public_key_share`, where
+/// `one_time_public_share` is the public of node' one-time secret coefficient and `public_key_share` is `node_secret_share * G`.
+pub fn check_schnorr_signature_share<'a, I>(threshold: usize, combined_hash: &Secret, signature_share: &Secret, public_key_share: &Public,
+	one_time_public_share: &Public, node_number: &Secret, other_nodes_numbers: I) -> Result<bool, Error> where I: Iterator<Item=&'a Secret> {
+	// calculate left part
+	let left = compute_public_share(signature_share)?;
+
+	// calculate right part
+	let mut right = one_time_public_share.clone();
+	let subtrahend_coeff = compute_shadow_mul(combined_hash, node_number, other_nodes_numbers)?;
+	let mut subtrahend = public_key_share.clone();
+	ec_math_utils::public_mul_secret(&mut subtrahend, &subtrahend_coeff)?;
+	if threshold % 2 == 0 {
+		ec_math_utils::public_sub(&mut right, &subtrahend)?;
+	} else {
+		ec_math_utils::public_add(&mut right, &subtrahend)?;
+	}
+
+	Ok(left == right)
 }
 
 /// Compute Schnorr signature.
@@ -506,6 +564,93 @@ pub fn compute_ecdsa_s_share(inv_nonce_share: &Secret, inv_nonce_mul_secret: &Se
 	Ok(signature_s_share)
 }
 
+/// Prove that `inv_nonce_share * public_key_share` is computed using the same inv(nonce) share as
+/// the `inv_nonce_share * G`. This is the Chaum-Pedersen proof of equality of discrete logarithms.
+pub fn prove_ecdsa_signature_share(inv_nonce_share: &Secret, public_key_share: &Public) -> Result<EcdsaSignatureShareProof, Error> {
+	let inv_nonce_public_share = compute_public_share(inv_nonce_share)?;
+	let mut inv_nonce_mul_secret_public = public_key_share.clone();
+	ec_math_utils::public_mul_secret(&mut inv_nonce_mul_secret_public, inv_nonce_share)?;
+
+	let blinding_factor = generate_random_scalar()?;
+	let blinded_generator = compute_public_share(&blinding_factor)?;
+	let mut blinded_public_key_share = public_key_share.clone();
+	ec_math_utils::public_mul_secret(&mut blinded_public_key_share, &blinding_factor)?;
+
+	let challenge = compute_ecdsa_signature_share_challenge(&[
+		&inv_nonce_public_share,
+		public_key_share,
+		&inv_nonce_mul_secret_public,
+		&blinded_generator,
+		&blinded_public_key_share,
+	])?;
+	let mut response = blinding_factor;
+	response.sub(&compute_secret_mul(&challenge, inv_nonce_share)?)?;
+
+	Ok(EcdsaSignatureShareProof {
+		inv_nonce_mul_secret_public,
+		challenge,
+		response,
+	})
+}
+
+/// Check ECDSA signature S-share, computed by the node. Share is valid when the proof is valid and
+/// `signature_s_share * G = message_hash * inv_nonce_public_share + signature_r * inv_nonce_mul_secret_public`, where
+/// `inv_nonce_public_share` is `inversed_nonce_coeff * inversion_nonce_share * G` and `public_key_share` is `node_secret_share * G`.
+pub fn check_ecdsa_signature_share(signature_s_share: &Secret, signature_r: &Secret, message_hash: &Secret, inversed_nonce_coeff: &Secret,
+	inversion_nonce_public_share: &Public, public_key_share: &Public, proof: &EcdsaSignatureShareProof) -> Result<bool, Error> {
+	let mut inv_nonce_public_share = inversion_nonce_public_share.clone();
+	ec_math_utils::public_mul_secret(&mut inv_nonce_public_share, inversed_nonce_coeff)?;
+	let inv_nonce_public_share = &inv_nonce_public_share;
+
+	// restore blinded values from the response
+	let mut blinded_generator = compute_public_share(&proof.response)?;
+	let mut addendum = inv_nonce_public_share.clone();
+	ec_math_utils::public_mul_secret(&mut addendum, &proof.challenge)?;
+	ec_math_utils::public_add(&mut blinded_generator, &addendum)?;
+
+	let mut blinded_public_key_share = public_key_share.clone();
+	ec_math_utils::public_mul_secret(&mut blinded_public_key_share, &proof.response)?;
+	let mut addendum = proof.inv_nonce_mul_secret_public.clone();
+	ec_math_utils::public_mul_secret(&mut addendum, &proof.challenge)?;
+	ec_math_utils::public_add(&mut blinded_public_key_share, &addendum)?;
+
+	let challenge = compute_ecdsa_signature_share_challenge(&[
+		inv_nonce_public_share,
+		public_key_share,
+		&proof.inv_nonce_mul_secret_public,
+		&blinded_generator,
+		&blinded_public_key_share,
+	])?;
+	if challenge != proof.challenge {
+		return Ok(false);
+	}
+
+	// calculate left part
+	let left = compute_public_share(signature_s_share)?;
+
+	// calculate right part
+	let mut right = inv_nonce_public_share.clone();
+	ec_math_utils::public_mul_secret(&mut right, message_hash)?;
+	let mut addendum = proof.inv_nonce_mul_secret_public.clone();
+	ec_math_utils::public_mul_secret(&mut addendum, signature_r)?;
+	ec_math_utils::public_add(&mut right, &addendum)?;
+
+	Ok(left == right)
+}
+
+/// Compute challenge of ECDSA signature share proof.
+fn compute_ecdsa_signature_share_challenge(publics: &[&Public]) -> Result<Secret, Error> {
+	let mut challenge_keccak = Keccak::new_keccak256();
+	for public in publics {
+		challenge_keccak.update(public.as_bytes());
+	}
+
+	let mut challenge = [0u8; 32];
+	challenge_keccak.finalize(&mut challenge);
+
+	to_scalar(challenge.into())
+}
+
 /// Compute S part of ECDSA signature from shares.
 pub fn compute_ecdsa_s(t: usize, signature_s_shares: &[Secret], id_numbers: &[Secret]) -> Result<Secret, Error> {
 	let double_t = t * 2;
@@ -910,16 +1055,26 @@ pub mod tests {
 				.collect();
 
 			// step 4: receive and verify signatures shares from other nodes
+			// (public key shares of nodes are computed from share proofs, published during DKG)
+			let share_proofs: Vec<_> = artifacts.polynoms1.iter().map(|p| prepare_share_proof(p).unwrap()).collect();
 			let received_signatures: Vec<Vec<_>> = (0..n)
 				.map(|i| (0..n)
 					.filter(|j| i != *j)
 					.map(|j| {
 						let signature_share = partial_signatures[j].clone();
-						assert!(_check_schnorr_signature_share(&combined_hash,
+						let public_key_share = compute_public_key_share(&artifacts.id_numbers[j],
+							share_proofs.iter().map(|p| &p[..])).unwrap();
+						assert!(check_schnorr_signature_share(t,
+							&combined_hash,
 							&signature_share,
-							&artifacts.public_shares[j],
+							&public_key_share,
 							&one_time_artifacts.public_shares[j],
-							artifacts.id_numbers.iter().take(t)).unwrap_or(false));
+							&artifacts.id_numbers[j],
+							artifacts.id_numbers.iter()
+								.enumerate()
+								.filter(|&(k, _)| j != k)
+								.map(|(_, n)| n)
+								.take(t)).unwrap());
 						signature_share
 					})
 					.collect())
@@ -941,6 +1096,115 @@ pub mod tests {
 		}
 	}
 
+	#[test]
+	fn public_key_share_is_computed_from_share_proofs() {
+		let artifacts = run_key_generation(2, 5, None, None);
+		let share_proofs: Vec<_> = artifacts.polynoms1.iter().map(|p| prepare_share_proof(p).unwrap()).collect();
+		for i in 0..5 {
+			assert_eq!(
+				compute_public_key_share(&artifacts.id_numbers[i], share_proofs.iter().map(|p| &p[..])).unwrap(),
+				compute_public_share(&artifacts.secret_shares[i]).unwrap(),
+			);
+		}
+	}
+
+	#[test]
+	fn refreshed_key_shares_are_verified_using_subshare_proofs() {
+		let (t, n, new_n) = (1, 3, 4);
+		let artifacts = run_key_generation(t, n, None, None);
+		let id_numbers: Vec<_> = artifacts.id_numbers.iter().cloned()
+			.chain((n..new_n).map(|_| generate_random_scalar().unwrap()))
+			.collect();
+
+		// every node of the consensus group shares its secret share
+		let polynoms: Vec<_> = (0..t + 1).map(|i| {
+			let mut polynom = generate_random_polynom(t).unwrap();
+			polynom[0] = artifacts.secret_shares[i].clone();
+			polynom
+		}).collect();
+		let share_proofs: Vec<_> = polynoms.iter().map(|p| prepare_share_proof(p).unwrap()).collect();
+		let subshare_proofs: Vec<_> = (0..t + 1).map(|i| compute_subshare_proof(t, &share_proofs[i], &id_numbers[i],
+			(0..t + 1).filter(|j| *j != i).map(|j| &id_numbers[j])).unwrap()).collect();
+
+		// free terms of subshare proofs are summed up to the joint public
+		assert_eq!(
+			compute_public_sum(subshare_proofs.iter().map(|p| &p[0])).unwrap(),
+			artifacts.joint_public,
+		);
+
+		for j in 0..new_n {
+			// every received subshare matches the share proof
+			let subshares: Vec<_> = (0..t + 1).map(|i| compute_polynom(&polynoms[i], &id_numbers[j]).unwrap()).collect();
+			for i in 0..t + 1 {
+				assert!(share_proof_verification(t, &id_numbers[j], &subshares[i], &share_proofs[i]).unwrap());
+			}
+
+			// ...and public of refreshed key share is computed from subshare proofs
+			let secret_share = compute_secret_share((0..t + 1).map(|i| compute_secret_subshare(t, &subshares[i], &id_numbers[i],
+				(0..t + 1).filter(|k| *k != i).map(|k| &id_numbers[k])).unwrap()).collect::<Vec<_>>().iter()).unwrap();
+			assert_eq!(
+				compute_public_key_share(&id_numbers[j], subshare_proofs.iter().map(|p| &p[..])).unwrap(),
+				compute_public_share(&secret_share).unwrap(),
+			);
+		}
+
+		// corrupted subshare doesn't match the share proof
+		let mut corrupted_subshare = compute_polynom(&polynoms[0], &id_numbers[n]).unwrap();
+		corrupted_subshare.add(&generate_random_scalar().unwrap()).unwrap();
+		assert!(!share_proof_verification(t, &id_numbers[n], &corrupted_subshare, &share_proofs[0]).unwrap());
+	}
+
+	#[test]
+	fn corrupted_schnorr_signature_share_is_detected() {
+		let t = 1;
+		let artifacts = run_key_generation(t, t + 1, None, None);
+		let one_time_artifacts = run_key_generation(t, t + 1, Some(artifacts.id_numbers.clone()), None);
+		let combined_hash = combine_message_hash_with_public(&H256::random(), &one_time_artifacts.joint_public).unwrap();
+
+		let mut signature_share = compute_schnorr_signature_share(t, &combined_hash, &one_time_artifacts.polynoms1[0][0],
+			&artifacts.secret_shares[0], &artifacts.id_numbers[0], once(&artifacts.id_numbers[1])).unwrap();
+		let public_key_share = compute_public_share(&artifacts.secret_shares[0]).unwrap();
+		assert!(check_schnorr_signature_share(t, &combined_hash, &signature_share, &public_key_share,
+			&one_time_artifacts.public_shares[0], &artifacts.id_numbers[0], once(&artifacts.id_numbers[1])).unwrap());
+
+		signature_share.add(&generate_random_scalar().unwrap()).unwrap();
+		assert!(!check_schnorr_signature_share(t, &combined_hash, &signature_share, &public_key_share,
+			&one_time_artifacts.public_shares[0], &artifacts.id_numbers[0], once(&artifacts.id_numbers[1])).unwrap());
+	}
+
+	#[test]
+	fn corrupted_ecdsa_signature_share_is_detected() {
+		let secret_share = generate_random_scalar().unwrap();
+		let public_key_share = compute_public_share(&secret_share).unwrap();
+		let inversed_nonce_coeff = generate_random_scalar().unwrap();
+		let inversion_nonce_share = generate_random_scalar().unwrap();
+		let inversion_nonce_public_share = compute_public_share(&inversion_nonce_share).unwrap();
+		let inv_nonce_share = compute_secret_mul(&inversed_nonce_coeff, &inversion_nonce_share).unwrap();
+		let signature_r = generate_random_scalar().unwrap();
+		let message_hash = to_scalar(H256::random()).unwrap();
+
+		let compute_share = |secret_share: &Secret| compute_ecdsa_s_share(&inv_nonce_share,
+			&compute_secret_mul(&inv_nonce_share, secret_share).unwrap(), &signature_r, &message_hash).unwrap();
+
+		// valid share is accepted
+		let mut signature_s_share = compute_share(&secret_share);
+		let proof = prove_ecdsa_signature_share(&inv_nonce_share, &public_key_share).unwrap();
+		assert!(check_ecdsa_signature_share(&signature_s_share, &signature_r, &message_hash,
+			&inversed_nonce_coeff, &inversion_nonce_public_share, &public_key_share, &proof).unwrap());
+
+		// share, computed using wrong secret share is rejected
+		let wrong_secret_share = generate_random_scalar().unwrap();
+		let wrong_public_key_share = compute_public_share(&wrong_secret_share).unwrap();
+		let wrong_proof = prove_ecdsa_signature_share(&inv_nonce_share, &wrong_public_key_share).unwrap();
+		assert!(!check_ecdsa_signature_share(&compute_share(&wrong_secret_share), &signature_r, &message_hash,
+			&inversed_nonce_coeff, &inversion_nonce_public_share, &public_key_share, &wrong_proof).unwrap());
+
+		// corrupted share is rejected
+		signature_s_share.add(&generate_random_scalar().unwrap()).unwrap();
+		assert!(!check_ecdsa_signature_share(&signature_s_share, &signature_r, &message_hash,
+			&inversed_nonce_coeff, &inversion_nonce_public_share, &public_key_share, &proof).unwrap());
+	}
+
 	#[test]
 	fn full_ecdsa_signature_math_session() {
 		let test_cases = [(2, 5), (2, 6), (3, 11), (4, 11)];
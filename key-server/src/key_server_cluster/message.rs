@@ -18,7 +18,7 @@ use std::fmt;
 use std::collections::{BTreeSet, BTreeMap};
 use parity_crypto::publickey::Secret;
 use serde::{Serialize, Deserialize};
-use crate::key_server_cluster::SessionId;
+use crate::key_server_cluster::{SessionId, Requester};
 use super::{Error, SerializableH256, SerializablePublic, SerializableSecret,
 	SerializableSignature, SerializableMessageHash, SerializableRequester, SerializableAddress};
 
@@ -34,6 +34,8 @@ pub enum Message {
 	Generation(GenerationMessage),
 	/// Encryption message.
 	Encryption(EncryptionMessage),
+	/// Key deletion message.
+	KeyDeletion(KeyDeletionMessage),
 	/// Decryption message.
 	Decryption(DecryptionMessage),
 	/// Schnorr signing message.
@@ -46,6 +48,8 @@ pub enum Message {
 	ShareAdd(ShareAddMessage),
 	/// Servers set change message.
 	ServersSetChange(ServersSetChangeMessage),
+	/// Key versions pruning message.
+	KeyVersionsPruning(KeyVersionsPruningMessage),
 }
 
 /// All possible cluster-level messages.
@@ -98,10 +102,27 @@ pub enum EncryptionMessage {
 	InitializeEncryptionSession(InitializeEncryptionSession),
 	/// Confirm/reject encryption session initialization.
 	ConfirmEncryptionInitialization(ConfirmEncryptionInitialization),
+	/// Commit prepared encryption data.
+	CommitEncryptionSession(CommitEncryptionSession),
+	/// Confirm that prepared encryption data is committed.
+	ConfirmEncryptionCommit(ConfirmEncryptionCommit),
 	/// When encryption session error has occured.
 	EncryptionSessionError(EncryptionSessionError),
 }
 
+/// All possible messages that can be sent during key deletion session.
+#[derive(Clone, Debug)]
+pub enum KeyDeletionMessage {
+	/// Initialize key deletion session.
+	InitializeKeyDeletionSession(InitializeKeyDeletionSession),
+	/// Confirm key deletion session initialization.
+	ConfirmKeyDeletionInitialization(ConfirmKeyDeletionInitialization),
+	/// Commit key deletion.
+	CommitKeyDeletionSession(CommitKeyDeletionSession),
+	/// When key deletion session error has occured.
+	KeyDeletionSessionError(KeyDeletionSessionError),
+}
+
 /// All possible messages that can be sent during consensus establishing.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ConsensusMessage {
@@ -120,6 +141,16 @@ pub enum ConsensusMessageWithServersSet {
 	ConfirmConsensusInitialization(ConfirmConsensusInitialization),
 }
 
+/// All possible messages that can be sent during servers-set consensus establishing, as they are
+/// serialized for nodes that do not support verifiable shares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LegacyConsensusMessageWithServersSet {
+	/// Initialize consensus session.
+	InitializeConsensusSession(LegacyInitializeConsensusSessionWithServersSet),
+	/// Confirm/reject consensus session initialization.
+	ConfirmConsensusInitialization(ConfirmConsensusInitialization),
+}
+
 /// All possible messages that can be sent during share add consensus establishing.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ConsensusMessageOfShareAdd {
@@ -234,6 +265,8 @@ pub enum ShareAddMessage {
 	NewKeysDissemination(NewKeysDissemination),
 	/// When session error has occured.
 	ShareAddError(ShareAddError),
+	/// Footprint of all share proofs, received by the node.
+	NewKeysProofsFootprint(NewKeysProofsFootprint),
 }
 
 /// All possible messages that can be sent during key version negotiation message.
@@ -247,6 +280,21 @@ pub enum KeyVersionNegotiationMessage {
 	KeyVersionsError(KeyVersionsError),
 }
 
+/// All possible messages that can be sent during key versions pruning session.
+#[derive(Clone, Debug)]
+pub enum KeyVersionsPruningMessage {
+	/// Initialize key versions pruning session.
+	InitializeKeyVersionsPruning(InitializeKeyVersionsPruning),
+	/// Versions of the key, held by the node.
+	HeldKeyVersions(HeldKeyVersions),
+	/// Versions of the key that must be pruned.
+	PruneKeyVersions(PruneKeyVersions),
+	/// Confirm that versions have been pruned.
+	ConfirmKeyVersionsPruning(ConfirmKeyVersionsPruning),
+	/// When session error has occured.
+	KeyVersionsPruningError(KeyVersionsPruningError),
+}
+
 /// Introduce node public key.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NodePublicKey {
@@ -256,6 +304,9 @@ pub struct NodePublicKey {
 	pub confirmation_plain: SerializableH256,
 	/// The same random `confirmation_plain`, signed with one-time session key.
 	pub confirmation_signed_session: SerializableSignature,
+	/// Maximal message header version, supported by the node. None if node only supports JSON codec.
+	#[serde(default)]
+	pub max_header_version: Option<u64>,
 }
 
 /// Confirm that node owns the private key of previously passed public key (aka node id).
@@ -263,6 +314,10 @@ pub struct NodePublicKey {
 pub struct NodePrivateKeySignature {
 	/// Previously passed `confirmation_plain`, signed with node private key.
 	pub confirmation_signed: SerializableSignature,
+	/// Previously passed `confirmation_plain` and maximal header versions of both nodes, signed with
+	/// node private key. None if node only supports JSON codec.
+	#[serde(default)]
+	pub header_versions_signed: Option<SerializableSignature>,
 }
 
 /// Ask if the node is still alive.
@@ -401,6 +456,24 @@ pub struct ConfirmEncryptionInitialization {
 	pub session_nonce: u64,
 }
 
+/// Node is requested to commit prepared encryption data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitEncryptionSession {
+	/// Encryption session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+}
+
+/// Node confirms that prepared encryption data is committed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmEncryptionCommit {
+	/// Encryption session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+}
+
 /// When encryption session error has occured.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EncryptionSessionError {
@@ -412,6 +485,48 @@ pub struct EncryptionSessionError {
 	pub error: Error,
 }
 
+/// Node is requested to prepare for key deletion.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InitializeKeyDeletionSession {
+	/// Key deletion session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Session origin address (if any).
+	pub origin: Option<SerializableAddress>,
+	/// Requester.
+	pub requester: SerializableRequester,
+}
+
+/// Node is responding to key deletion initialization request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmKeyDeletionInitialization {
+	/// Key deletion session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+}
+
+/// Node is requested to delete the key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitKeyDeletionSession {
+	/// Key deletion session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+}
+
+/// When key deletion session error has occured.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyDeletionSessionError {
+	/// Key deletion session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Error message.
+	pub error: Error,
+}
+
 /// Node is asked to be part of consensus group.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InitializeConsensusSession {
@@ -431,6 +546,26 @@ pub struct ConfirmConsensusInitialization {
 /// Node is asked to be part of servers-set consensus group.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InitializeConsensusSessionWithServersSet {
+	/// Migration id (if any).
+	pub migration_id: Option<SerializableH256>,
+	/// Refresh key shares without changing servers set.
+	pub refresh_key_shares: bool,
+	/// If key shares are refreshed, only refresh shares of this key (if specified).
+	pub refreshed_key_id: Option<MessageSessionId>,
+	/// Old nodes set.
+	pub old_nodes_set: BTreeSet<MessageNodeId>,
+	/// New nodes set.
+	pub new_nodes_set: BTreeSet<MessageNodeId>,
+	/// Old server set, signed by requester.
+	pub old_set_signature: SerializableSignature,
+	/// New server set, signed by requester.
+	pub new_set_signature: SerializableSignature,
+}
+
+/// Node is asked to be part of servers-set consensus group, as it is serialized for nodes that do not
+/// support verifiable shares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegacyInitializeConsensusSessionWithServersSet {
 	/// Migration id (if any).
 	pub migration_id: Option<SerializableH256>,
 	/// Old nodes set.
@@ -671,6 +806,35 @@ pub struct EcdsaPartialSignature {
 	pub request_id: SerializableSecret,
 	/// Partial S part of signature.
 	pub partial_signature_s: SerializableSecret,
+	/// Proof of partial S part of signature. None if message is received from the node that
+	/// doesn't support verifiable signature shares.
+	pub partial_signature_s_proof: Option<EcdsaPartialSignatureProof>,
+}
+
+/// Proof that partial ECDSA signature is computed using correct secret share.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EcdsaPartialSignatureProof {
+	/// Public of inv(nonce) share, multiplied by secret share.
+	pub inv_nonce_mul_secret_public: SerializablePublic,
+	/// Challenge of partial signature proof.
+	pub challenge: SerializableSecret,
+	/// Response of partial signature proof.
+	pub response: SerializableSecret,
+}
+
+/// Partial ECDSA signature, as it is serialized for nodes that do not support verifiable signature shares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegacyEcdsaPartialSignature {
+	/// Generation session Id.
+	pub session: MessageSessionId,
+	/// Signing session Id.
+	pub sub_session: SerializableSecret,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Request id.
+	pub request_id: SerializableSecret,
+	/// Partial S part of signature.
+	pub partial_signature_s: SerializableSecret,
 }
 
 /// When ECDSA signing session error has occured.
@@ -855,6 +1019,18 @@ pub struct ServersSetChangeConsensusMessage {
 	pub message: ConsensusMessageWithServersSet,
 }
 
+/// Consensus-related servers set change message, as it is serialized for nodes that do not support
+/// verifiable shares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegacyServersSetChangeConsensusMessage {
+	/// Servers set change session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Consensus message.
+	pub message: LegacyConsensusMessageWithServersSet,
+}
+
 /// Unknown sessions ids request.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UnknownSessionsRequest {
@@ -997,6 +1173,25 @@ pub struct KeyShareCommon {
 	pub encrypted_point: Option<SerializablePublic>,
 	/// Selected version id numbers.
 	pub id_numbers: BTreeMap<MessageNodeId, SerializableSecret>,
+	/// Selected version public key shares. Empty if public key shares are unknown.
+	pub public_shares: BTreeMap<MessageNodeId, SerializablePublic>,
+}
+
+/// Key share common data, as it is serialized for nodes that do not support verifiable shares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegacyKeyShareCommon {
+	/// Generation session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Common key data.
+	pub key_common: CommonKeyData,
+	/// Common (shared) encryption point.
+	pub common_point: Option<SerializablePublic>,
+	/// Encrypted point.
+	pub encrypted_point: Option<SerializablePublic>,
+	/// Selected version id numbers.
+	pub id_numbers: BTreeMap<MessageNodeId, SerializableSecret>,
 }
 
 /// Generated keys are sent to every node.
@@ -1008,6 +1203,8 @@ pub struct NewKeysDissemination {
 	pub session_nonce: u64,
 	/// Sub share of rcevier' secret share.
 	pub secret_subshare: SerializableSecret,
+	/// Public commitments to the coefficients of sender' refreshing polynom.
+	pub share_proof: Vec<SerializablePublic>,
 }
 
 /// When share add session error has occured.
@@ -1021,6 +1218,18 @@ pub struct ShareAddError {
 	pub error: Error,
 }
 
+/// Footprint of all share proofs, received by the node. It is used to check that every node
+/// has received the same share proofs from every sender.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewKeysProofsFootprint {
+	/// Generation session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Share proofs footprint.
+	pub share_proofs_footprint: SerializableH256,
+}
+
 /// Key versions are requested.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RequestKeyVersions {
@@ -1074,6 +1283,59 @@ pub struct KeyVersionsError {
 	pub continue_with: Option<FailedKeyVersionContinueAction>,
 }
 
+/// Initialize key versions pruning session.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InitializeKeyVersionsPruning {
+	/// Key versions pruning session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Key versions pruning hash, signed by administrator.
+	pub admin_signature: SerializableSignature,
+}
+
+/// Versions of the key, held by the node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeldKeyVersions {
+	/// Key versions pruning session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Key versions (in the order they have been created).
+	pub versions: Vec<SerializableH256>,
+}
+
+/// Versions of the key, held by every node. Every node computes versions to prune on its own.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PruneKeyVersions {
+	/// Key versions pruning session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Key versions (in the order they have been created), held by every node.
+	pub held_versions: BTreeMap<MessageNodeId, Vec<SerializableH256>>,
+}
+
+/// Confirm that versions have been pruned.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmKeyVersionsPruning {
+	/// Key versions pruning session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+}
+
+/// When key versions pruning session error has occured.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyVersionsPruningError {
+	/// Key versions pruning session Id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Error message.
+	pub error: Error,
+}
+
 /// Key version continue action from failed node.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum FailedKeyVersionContinueAction {
@@ -1100,6 +1362,7 @@ impl Message {
 		match *self {
 			Message::Generation(GenerationMessage::InitializeSession(_)) => true,
 			Message::Encryption(EncryptionMessage::InitializeEncryptionSession(_)) => true,
+			Message::KeyDeletion(KeyDeletionMessage::InitializeKeyDeletionSession(_)) => true,
 			Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(ref msg)) => match msg.message {
 				ConsensusMessage::InitializeConsensusSession(_) => true,
 				_ => false
@@ -1122,6 +1385,7 @@ impl Message {
 				ConsensusMessageWithServersSet::InitializeConsensusSession(_) => true,
 				_ => false
 			},
+			Message::KeyVersionsPruning(KeyVersionsPruningMessage::InitializeKeyVersionsPruning(_)) => true,
 			_ => false,
 		}
 	}
@@ -1135,16 +1399,44 @@ impl Message {
 		}
 	}
 
+	/// Returns key id and requester, which access to the key must be checked before
+	/// message is processed by the session.
+	pub fn key_access_request(&self) -> Option<(SessionId, Requester)> {
+		match *self {
+			Message::Decryption(DecryptionMessage::DecryptionConsensusMessage(ref msg)) => match msg.message {
+				ConsensusMessage::InitializeConsensusSession(ref init) => Some((*msg.session, init.requester.clone().into())),
+				_ => None,
+			},
+			Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningConsensusMessage(ref msg)) => match msg.message {
+				ConsensusMessage::InitializeConsensusSession(ref init) => Some((*msg.session, init.requester.clone().into())),
+				_ => None,
+			},
+			Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningConsensusMessage(ref msg)) => match msg.message {
+				ConsensusMessage::InitializeConsensusSession(ref init) => Some((*msg.session, init.requester.clone().into())),
+				_ => None,
+			},
+			Message::Decryption(DecryptionMessage::DecryptionSessionDelegation(ref msg)) =>
+				Some((*msg.session, msg.requester.clone().into())),
+			Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionDelegation(ref msg)) =>
+				Some((*msg.session, msg.requester.clone().into())),
+			Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(ref msg)) =>
+				Some((*msg.session, msg.requester.clone().into())),
+			_ => None,
+		}
+	}
+
 	pub fn is_error_message(&self) -> bool {
 		match *self {
 			Message::Generation(GenerationMessage::SessionError(_)) => true,
 			Message::Encryption(EncryptionMessage::EncryptionSessionError(_)) => true,
+			Message::KeyDeletion(KeyDeletionMessage::KeyDeletionSessionError(_)) => true,
 			Message::Decryption(DecryptionMessage::DecryptionSessionError(_)) => true,
 			Message::SchnorrSigning(SchnorrSigningMessage::SchnorrSigningSessionError(_)) => true,
 			Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionError(_)) => true,
 			Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersionsError(_)) => true,
 			Message::ShareAdd(ShareAddMessage::ShareAddError(_)) => true,
 			Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(_)) => true,
+			Message::KeyVersionsPruning(KeyVersionsPruningMessage::KeyVersionsPruningError(_)) => true,
 			_ => false,
 		}
 	}
@@ -1161,12 +1453,14 @@ impl Message {
 			Message::Cluster(_) => None,
 			Message::Generation(ref message) => Some(message.session_nonce()),
 			Message::Encryption(ref message) => Some(message.session_nonce()),
+			Message::KeyDeletion(ref message) => Some(message.session_nonce()),
 			Message::Decryption(ref message) => Some(message.session_nonce()),
 			Message::SchnorrSigning(ref message) => Some(message.session_nonce()),
 			Message::EcdsaSigning(ref message) => Some(message.session_nonce()),
 			Message::ShareAdd(ref message) => Some(message.session_nonce()),
 			Message::ServersSetChange(ref message) => Some(message.session_nonce()),
 			Message::KeyVersionNegotiation(ref message) => Some(message.session_nonce()),
+			Message::KeyVersionsPruning(ref message) => Some(message.session_nonce()),
 		}
 	}
 }
@@ -1204,6 +1498,8 @@ impl EncryptionMessage {
 		match *self {
 			EncryptionMessage::InitializeEncryptionSession(ref msg) => &msg.session,
 			EncryptionMessage::ConfirmEncryptionInitialization(ref msg) => &msg.session,
+			EncryptionMessage::CommitEncryptionSession(ref msg) => &msg.session,
+			EncryptionMessage::ConfirmEncryptionCommit(ref msg) => &msg.session,
 			EncryptionMessage::EncryptionSessionError(ref msg) => &msg.session,
 		}
 	}
@@ -1212,11 +1508,33 @@ impl EncryptionMessage {
 		match *self {
 			EncryptionMessage::InitializeEncryptionSession(ref msg) => msg.session_nonce,
 			EncryptionMessage::ConfirmEncryptionInitialization(ref msg) => msg.session_nonce,
+			EncryptionMessage::CommitEncryptionSession(ref msg) => msg.session_nonce,
+			EncryptionMessage::ConfirmEncryptionCommit(ref msg) => msg.session_nonce,
 			EncryptionMessage::EncryptionSessionError(ref msg) => msg.session_nonce,
 		}
 	}
 }
 
+impl KeyDeletionMessage {
+	pub fn session_id(&self) -> &SessionId {
+		match *self {
+			KeyDeletionMessage::InitializeKeyDeletionSession(ref msg) => &msg.session,
+			KeyDeletionMessage::ConfirmKeyDeletionInitialization(ref msg) => &msg.session,
+			KeyDeletionMessage::CommitKeyDeletionSession(ref msg) => &msg.session,
+			KeyDeletionMessage::KeyDeletionSessionError(ref msg) => &msg.session,
+		}
+	}
+
+	pub fn session_nonce(&self) -> u64 {
+		match *self {
+			KeyDeletionMessage::InitializeKeyDeletionSession(ref msg) => msg.session_nonce,
+			KeyDeletionMessage::ConfirmKeyDeletionInitialization(ref msg) => msg.session_nonce,
+			KeyDeletionMessage::CommitKeyDeletionSession(ref msg) => msg.session_nonce,
+			KeyDeletionMessage::KeyDeletionSessionError(ref msg) => msg.session_nonce,
+		}
+	}
+}
+
 impl DecryptionMessage {
 	pub fn session_id(&self) -> &SessionId {
 		match *self {
@@ -1387,6 +1705,7 @@ impl ShareAddMessage {
 			ShareAddMessage::KeyShareCommon(ref msg) => &msg.session,
 			ShareAddMessage::NewKeysDissemination(ref msg) => &msg.session,
 			ShareAddMessage::ShareAddError(ref msg) => &msg.session,
+			ShareAddMessage::NewKeysProofsFootprint(ref msg) => &msg.session,
 		}
 	}
 
@@ -1396,6 +1715,29 @@ impl ShareAddMessage {
 			ShareAddMessage::KeyShareCommon(ref msg) => msg.session_nonce,
 			ShareAddMessage::NewKeysDissemination(ref msg) => msg.session_nonce,
 			ShareAddMessage::ShareAddError(ref msg) => msg.session_nonce,
+			ShareAddMessage::NewKeysProofsFootprint(ref msg) => msg.session_nonce,
+		}
+	}
+}
+
+impl KeyVersionsPruningMessage {
+	pub fn session_id(&self) -> &SessionId {
+		match *self {
+			KeyVersionsPruningMessage::InitializeKeyVersionsPruning(ref msg) => &msg.session,
+			KeyVersionsPruningMessage::HeldKeyVersions(ref msg) => &msg.session,
+			KeyVersionsPruningMessage::PruneKeyVersions(ref msg) => &msg.session,
+			KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(ref msg) => &msg.session,
+			KeyVersionsPruningMessage::KeyVersionsPruningError(ref msg) => &msg.session,
+		}
+	}
+
+	pub fn session_nonce(&self) -> u64 {
+		match *self {
+			KeyVersionsPruningMessage::InitializeKeyVersionsPruning(ref msg) => msg.session_nonce,
+			KeyVersionsPruningMessage::HeldKeyVersions(ref msg) => msg.session_nonce,
+			KeyVersionsPruningMessage::PruneKeyVersions(ref msg) => msg.session_nonce,
+			KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(ref msg) => msg.session_nonce,
+			KeyVersionsPruningMessage::KeyVersionsPruningError(ref msg) => msg.session_nonce,
 		}
 	}
 }
@@ -1432,12 +1774,14 @@ impl fmt::Display for Message {
 			Message::Cluster(ref message) => write!(f, "Cluster.{}", message),
 			Message::Generation(ref message) => write!(f, "Generation.{}", message),
 			Message::Encryption(ref message) => write!(f, "Encryption.{}", message),
+			Message::KeyDeletion(ref message) => write!(f, "KeyDeletion.{}", message),
 			Message::Decryption(ref message) => write!(f, "Decryption.{}", message),
 			Message::SchnorrSigning(ref message) => write!(f, "SchnorrSigning.{}", message),
 			Message::EcdsaSigning(ref message) => write!(f, "EcdsaSigning.{}", message),
 			Message::ServersSetChange(ref message) => write!(f, "ServersSetChange.{}", message),
 			Message::ShareAdd(ref message) => write!(f, "ShareAdd.{}", message),
 			Message::KeyVersionNegotiation(ref message) => write!(f, "KeyVersionNegotiation.{}", message),
+			Message::KeyVersionsPruning(ref message) => write!(f, "KeyVersionsPruning.{}", message),
 		}
 	}
 }
@@ -1473,11 +1817,24 @@ impl fmt::Display for EncryptionMessage {
 		match *self {
 			EncryptionMessage::InitializeEncryptionSession(_) => write!(f, "InitializeEncryptionSession"),
 			EncryptionMessage::ConfirmEncryptionInitialization(_) => write!(f, "ConfirmEncryptionInitialization"),
+			EncryptionMessage::CommitEncryptionSession(_) => write!(f, "CommitEncryptionSession"),
+			EncryptionMessage::ConfirmEncryptionCommit(_) => write!(f, "ConfirmEncryptionCommit"),
 			EncryptionMessage::EncryptionSessionError(ref msg) => write!(f, "EncryptionSessionError({})", msg.error),
 		}
 	}
 }
 
+impl fmt::Display for KeyDeletionMessage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			KeyDeletionMessage::InitializeKeyDeletionSession(_) => write!(f, "InitializeKeyDeletionSession"),
+			KeyDeletionMessage::ConfirmKeyDeletionInitialization(_) => write!(f, "ConfirmKeyDeletionInitialization"),
+			KeyDeletionMessage::CommitKeyDeletionSession(_) => write!(f, "CommitKeyDeletionSession"),
+			KeyDeletionMessage::KeyDeletionSessionError(ref msg) => write!(f, "KeyDeletionSessionError({})", msg.error),
+		}
+	}
+}
+
 impl fmt::Display for ConsensusMessage {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match *self {
@@ -1577,6 +1934,7 @@ impl fmt::Display for ShareAddMessage {
 			ShareAddMessage::KeyShareCommon(ref m) => write!(f, "KeyShareCommon({})", m.session.0),
 			ShareAddMessage::NewKeysDissemination(ref m) => write!(f, "NewKeysDissemination({})", m.session.0),
 			ShareAddMessage::ShareAddError(ref m) => write!(f, "ShareAddError({})", m.session.0),
+			ShareAddMessage::NewKeysProofsFootprint(ref m) => write!(f, "NewKeysProofsFootprint({})", m.session.0),
 
 		}
 	}
@@ -1592,6 +1950,18 @@ impl fmt::Display for KeyVersionNegotiationMessage {
 	}
 }
 
+impl fmt::Display for KeyVersionsPruningMessage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			KeyVersionsPruningMessage::InitializeKeyVersionsPruning(_) => write!(f, "InitializeKeyVersionsPruning"),
+			KeyVersionsPruningMessage::HeldKeyVersions(_) => write!(f, "HeldKeyVersions"),
+			KeyVersionsPruningMessage::PruneKeyVersions(_) => write!(f, "PruneKeyVersions"),
+			KeyVersionsPruningMessage::ConfirmKeyVersionsPruning(_) => write!(f, "ConfirmKeyVersionsPruning"),
+			KeyVersionsPruningMessage::KeyVersionsPruningError(_) => write!(f, "KeyVersionsPruningError"),
+		}
+	}
+}
+
 impl fmt::Display for RandomPointGenerationMessage {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match *self {
@@ -47,6 +47,7 @@ mod admin_sessions;
 mod client_sessions;
 
 pub use self::admin_sessions::key_version_negotiation_session;
+pub use self::admin_sessions::key_versions_pruning_session;
 pub use self::admin_sessions::servers_set_change_session;
 pub use self::admin_sessions::share_add_session;
 pub use self::admin_sessions::share_change_session;
@@ -54,6 +55,7 @@ pub use self::admin_sessions::share_change_session;
 pub use self::client_sessions::decryption_session;
 pub use self::client_sessions::encryption_session;
 pub use self::client_sessions::generation_session;
+pub use self::client_sessions::key_deletion_session;
 pub use self::client_sessions::random_point_generation_session;
 pub use self::client_sessions::signing_session_ecdsa;
 pub use self::client_sessions::signing_session_schnorr;
@@ -38,7 +38,7 @@ pub use crate::key_server::KeyServerImpl;
 pub use crate::traits::KeyServer;
 pub use key_server_cluster::{math, message::Message};
 use primitives::{
-	acl_storage::AclStorage,
+	acl_storage::{AclStorage, AsyncAclStorage, CachedAclStorage, SyncAclStorageAdapter},
 	executor::TokioHandle,
 	key_server_set::KeyServerSet,
 	key_storage::KeyStorage,
@@ -48,7 +48,7 @@ use primitives::{
 /// 
 pub struct Builder {
 	self_key_pair: Option<Arc<dyn KeyServerKeyPair>>,
-	acl_storage: Option<Arc<dyn AclStorage>>,
+	acl_storage: Option<Arc<dyn AsyncAclStorage>>,
 	key_storage: Option<Arc<dyn KeyStorage>>,
 	config: Option<ClusterConfiguration>,
 }
@@ -69,6 +69,11 @@ impl Builder {
 	}
 	
 	pub fn with_acl_storage(mut self, acl_storage: Arc<dyn AclStorage>) -> Self {
+		self.acl_storage = Some(Arc::new(SyncAclStorageAdapter::new(acl_storage)));
+		self
+	}
+
+	pub fn with_async_acl_storage(mut self, acl_storage: Arc<dyn AsyncAclStorage>) -> Self {
 		self.acl_storage = Some(acl_storage);
 		self
 	}
@@ -93,6 +98,7 @@ impl Builder {
 		let acl_storage = self.acl_storage.ok_or_else(|| Error::Internal("Invalid initialization".into()))?;
 		let key_storage = self.key_storage.ok_or_else(|| Error::Internal("Invalid initialization".into()))?;
 		let config = self.config.ok_or_else(|| Error::Internal("Invalid initialization".into()))?;
+		let acl_storage: Arc<dyn AsyncAclStorage> = Arc::new(CachedAclStorage::new(acl_storage, config.acl_cache_ttl));
 
 		let connection_trigger: Box<dyn crate::key_server_cluster::connection_trigger::ConnectionTrigger<std::net::SocketAddr>> = match config.auto_migrate_enabled {
 			false => Box::new(crate::key_server_cluster::connection_trigger::SimpleConnectionTrigger::new(
@@ -119,6 +125,10 @@ impl Builder {
 			acl_storage.clone(),
 			servers_set_change_creator_connector,
 			connection_provider.clone(),
+			Arc::new(executor.clone()),
+			config.session_timeout_interval,
+			config.session_keep_alive_interval,
+			config.key_versions_to_keep,
 			move |message_processor| {
 				let connections_manager = Arc::new(NetConnectionsManager::new(
 					executor,
@@ -128,6 +138,9 @@ impl Builder {
 					listen_address,
 					self_key_pair,
 					false,
+					config.maintain_interval,
+					config.keep_alive_send_interval,
+					config.keep_alive_disconnect_interval,
 				)?);
 				connections_manager.start()?;
 				Ok(connections_manager)
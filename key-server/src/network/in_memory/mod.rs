@@ -18,6 +18,7 @@ use std::collections::{BTreeSet, VecDeque};
 use std::sync::Arc;
 use parking_lot::Mutex;
 use crate::key_server_cluster::{Error, NodeId};
+use crate::key_server_cluster::io::CURRENT_HEADER_VERSION;
 use crate::key_server_cluster::message::Message;
 use crate::network::{ConnectionProvider, ConnectionManager, Connection};
 
@@ -38,6 +39,7 @@ pub struct InMemoryConnectionsData {
 	is_isolated: bool,
 	connected_nodes: BTreeSet<NodeId>,
 	disconnected_nodes: BTreeSet<NodeId>,
+	header_version: u64,
 	messages: InMemoryMessagesQueue,
 }
 
@@ -45,6 +47,7 @@ pub struct InMemoryConnectionsData {
 pub struct InMemoryConnection {
 	from: NodeId,
 	to: NodeId,
+	header_version: u64,
 	messages: InMemoryMessagesQueue,
 }
 
@@ -75,6 +78,10 @@ impl InMemoryConnectionsManager {
 	pub fn include(&self, node: NodeId) {
 		self.core.lock().connected_nodes.insert(node);
 	}
+
+	pub fn set_header_version(&self, header_version: u64) {
+		self.core.lock().header_version = header_version;
+	}
 }
 
 impl ConnectionManager for InMemoryConnectionsManager {
@@ -104,6 +111,7 @@ impl ConnectionProvider for InMemoryConnections {
 			true => Some(Arc::new(InMemoryConnection {
 				from: core.node,
 				to: *node,
+				header_version: core.header_version,
 				messages: core.messages.clone(),
 			})),
 			false => None,
@@ -124,6 +132,10 @@ impl Connection for InMemoryConnection {
 		format!("{}", self.to)
 	}
 
+	fn header_version(&self) -> u64 {
+		self.header_version
+	}
+
 	fn send_message(&self, message: Message) {
 		self.messages.lock().push_back((self.from, self.to, message))
 	}
@@ -141,6 +153,7 @@ pub fn new_in_memory_connections(
 			is_isolated,
 			connected_nodes: nodes,
 			disconnected_nodes: Default::default(),
+			header_version: CURRENT_HEADER_VERSION,
 			messages,
 		})),
 	})
@@ -35,6 +35,8 @@ pub trait Connection: Send + Sync {
 	fn node_id(&self) -> &KeyServerId;
 	/// Returns 'address' of the node to use in traces.
 	fn node_address(&self) -> String;
+	/// Returns message header version, negotiated with the connected node.
+	fn header_version(&self) -> u64;
 	/// Send message to the connected node.
 	fn send_message(&self, message: Message);
 }
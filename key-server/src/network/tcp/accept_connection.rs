@@ -59,7 +59,8 @@ impl Future for AcceptConnection {
 			stream: stream.into(),
 			address: self.address,
 			node_id: result.node_id,
-			key: result.shared_key,
+			key: result.connection_key(),
+			header_version: result.header_version,
 		};
 		Ok(Ok(connection).into())
 	}
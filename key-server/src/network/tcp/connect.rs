@@ -73,7 +73,8 @@ impl Future for Connect {
 					stream: stream.into(),
 					address: self.address,
 					node_id: result.node_id,
-					key: result.shared_key,
+					key: result.connection_key(),
+					header_version: result.header_version,
 				};
 				(ConnectState::Connected, Async::Ready(Ok(connection)))
 			},
@@ -15,9 +15,8 @@
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::net;
-use parity_crypto::publickey::KeyPair;
 use crate::key_server_cluster::NodeId;
-use crate::key_server_cluster::io::SharedTcpStream;
+use crate::key_server_cluster::io::{ConnectionKey, SharedTcpStream};
 
 /// Established connection data
 pub struct Connection {
@@ -28,5 +27,7 @@ pub struct Connection {
 	/// Peer node id.
 	pub node_id: NodeId,
 	/// Encryption key.
-	pub key: KeyPair,
+	pub key: ConnectionKey,
+	/// Header version that is used to serialize messages.
+	pub header_version: u64,
 }
@@ -33,7 +33,6 @@ use parking_lot::{Mutex, RwLock};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::timer::{Interval, timeout::Error as TimeoutError};
 use tokio_io::IoFuture;
-use parity_crypto::publickey::KeyPair;
 use log::{trace, warn};
 use primitives::executor::TokioHandle;
 use primitives::key_server_key_pair::KeyServerKeyPair;
@@ -41,7 +40,7 @@ use crate::network::{ConnectionProvider, ConnectionManager, Connection};
 use crate::key_server_cluster::{Error, NodeId};
 use crate::key_server_cluster::connection_trigger::{Maintain, ConnectionTrigger};
 use crate::key_server_cluster::cluster_message_processor::MessageProcessor;
-use crate::key_server_cluster::io::{DeadlineStatus, ReadMessage, SharedTcpStream,
+use crate::key_server_cluster::io::{ConnectionKey, DeadlineStatus, ReadMessage, SharedTcpStream,
 	read_encrypted_message, WriteMessage, write_encrypted_message};
 use crate::key_server_cluster::message::{self, ClusterMessage, Message};
 use self::accept_connection::accept_connection as io_accept_connection;
@@ -51,18 +50,18 @@ use crate::network::tcp::connection::Connection as IoConnection;
 /// Empty future.
 pub type BoxedEmptyFuture = Box<dyn Future<Item = (), Error = ()> + Send>;
 
-/// Maintain interval (seconds). Every MAINTAIN_INTERVAL seconds node:
+/// Default maintain interval. Every maintain interval node:
 /// 1) checks if connected nodes are responding to KeepAlive messages
 /// 2) tries to connect to disconnected nodes
 /// 3) checks if enc/dec sessions are time-outed
-const MAINTAIN_INTERVAL: u64 = 10;
+pub const DEFAULT_MAINTAIN_INTERVAL: Duration = Duration::from_secs(10);
 
-/// When no messages have been received from node within KEEP_ALIVE_SEND_INTERVAL seconds,
+/// Default keep alive send interval. When no messages have been received from node within this interval,
 /// we must send KeepAlive message to the node to check if it still responds to messages.
-const KEEP_ALIVE_SEND_INTERVAL: Duration = Duration::from_secs(30);
-/// When no messages have been received from node within KEEP_ALIVE_DISCONNECT_INTERVAL seconds,
+pub const DEFAULT_KEEP_ALIVE_SEND_INTERVAL: Duration = Duration::from_secs(30);
+/// Default keep alive disconnect interval. When no messages have been received from node within this interval,
 /// we must treat this node as non-responding && disconnect from it.
-const KEEP_ALIVE_DISCONNECT_INTERVAL: Duration = Duration::from_secs(60);
+pub const DEFAULT_KEEP_ALIVE_DISCONNECT_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Network connections manager.
 pub struct NetConnectionsManager {
@@ -76,6 +75,12 @@ pub struct NetConnectionsManager {
 struct NetConnectionsData {
 	/// Allow connecting to 'higher' nodes.
 	allow_connecting_to_higher_nodes: bool,
+	/// Connections maintain interval.
+	maintain_interval: Duration,
+	/// KeepAlive message is sent to the node if no messages have been received within this interval.
+	keep_alive_send_interval: Duration,
+	/// Node is disconnected if no messages have been received within this interval.
+	keep_alive_disconnect_interval: Duration,
 	/// Reference to tokio task executor.
 	executor: TokioHandle,
 	/// Key pair of this node.
@@ -114,8 +119,10 @@ pub struct NetConnection {
 	node_address: SocketAddr,
 	/// Is this inbound (true) or outbound (false) connection?
 	is_inbound: bool,
-	/// Key pair that is used to encrypt connection' messages.
-	key: KeyPair,
+	/// Key that is used to encrypt connection' messages.
+	key: ConnectionKey,
+	/// Header version that is used to serialize connection' messages.
+	header_version: u64,
 	/// Last message time.
 	last_message_time: RwLock<Instant>,
 	/// Underlying TCP stream.
@@ -156,6 +163,9 @@ impl NetConnectionsManager {
 		listen_address: NodeAddress,
 		self_key_pair: Arc<dyn KeyServerKeyPair>,
 		allow_connecting_to_higher_nodes: bool,
+		maintain_interval: Duration,
+		keep_alive_send_interval: Duration,
+		keep_alive_disconnect_interval: Duration,
 	) -> Result<Self, Error> {
 		let listen_address = make_socket_address(
 			&listen_address.address,
@@ -166,6 +176,9 @@ impl NetConnectionsManager {
 			listen_address,
 			data: Arc::new(NetConnectionsData {
 				allow_connecting_to_higher_nodes,
+				maintain_interval,
+				keep_alive_send_interval,
+				keep_alive_disconnect_interval,
 				executor,
 				message_processor,
 				self_key_pair,
@@ -229,6 +242,7 @@ impl NetConnection {
 			is_inbound: is_inbound,
 			stream: connection.stream,
 			key: connection.key,
+			header_version: connection.header_version,
 			last_message_time: RwLock::new(Instant::now()),
 		}
 	}
@@ -245,7 +259,7 @@ impl NetConnection {
 
 	/// Returns future that sends encrypted message over this connection.
 	pub fn send_message_future(&self, message: Message) -> WriteMessage<SharedTcpStream> {
-		write_encrypted_message(self.stream.clone(), &self.key, message)
+		write_encrypted_message(self.stream.clone(), &self.key, self.header_version, message)
 	}
 
 	/// Returns future that reads encrypted message from this connection.
@@ -267,6 +281,10 @@ impl Connection for NetConnection {
 		format!("{}", self.node_address)
 	}
 
+	fn header_version(&self) -> u64 {
+		self.header_version
+	}
+
 	fn send_message(&self, message: Message) {
 		execute(&self.executor, self.send_message_future(message).then(|_| Ok(())));
 	}
@@ -480,7 +498,7 @@ fn net_process_connection_messages(
 /// Schedule connections. maintain.
 fn net_schedule_maintain(data: Arc<NetConnectionsData>) {
 	let closure_data = data.clone();
-	execute(&data.executor, Interval::new_interval(Duration::new(MAINTAIN_INTERVAL, 0))
+	execute(&data.executor, Interval::new_interval(data.maintain_interval)
 		.and_then(move |_| Ok(net_maintain(closure_data.clone())))
 		.for_each(|_| Ok(()))
 		.then(|_| future::ok(())));
@@ -505,7 +523,7 @@ fn net_keep_alive(data: Arc<NetConnectionsData>) {
 		let last_message_time = connection.last_message_time();
 		let now = Instant::now();
 		let last_message_diff = now - last_message_time;
-		if last_message_diff > KEEP_ALIVE_DISCONNECT_INTERVAL {
+		if last_message_diff > data.keep_alive_disconnect_interval {
 			warn!(target: "secretstore_net", "{}: keep alive timeout for node {}",
 				data.self_key_pair.address(), connection.node_id());
 
@@ -516,7 +534,7 @@ fn net_keep_alive(data: Arc<NetConnectionsData>) {
 			}
 			data.message_processor.process_disconnect(&node_id);
 		}
-		else if last_message_diff > KEEP_ALIVE_SEND_INTERVAL {
+		else if last_message_diff > data.keep_alive_send_interval {
 			connection.send_message(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive {})));
 		}
 	}
@@ -14,7 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
 use parity_crypto::publickey::Address;
+use crate::key_server_cluster::cluster_sessions::{DEFAULT_SESSION_TIMEOUT_INTERVAL, DEFAULT_SESSION_KEEP_ALIVE_INTERVAL,
+	DEFAULT_KEY_VERSIONS_TO_KEEP};
+use crate::network::tcp::{DEFAULT_MAINTAIN_INTERVAL, DEFAULT_KEEP_ALIVE_SEND_INTERVAL, DEFAULT_KEEP_ALIVE_DISCONNECT_INTERVAL};
 
 /// Node id.
 pub type NodeId = primitives::KeyServerId;
@@ -34,6 +38,9 @@ pub struct NodeAddress {
 	pub port: u16,
 }
 
+/// Default time during which result of the ACL check is reused by the key server.
+pub const DEFAULT_ACL_CACHE_TTL: Duration = Duration::from_secs(10);
+
 /// Key server cluster configuration
 #[derive(Debug)]
 pub struct ClusterConfiguration {
@@ -42,6 +49,39 @@ pub struct ClusterConfiguration {
 	/// Should key servers set change session should be started when servers set changes.
 	/// This will only work when servers set is configured using KeyServerSet contract.
 	pub auto_migrate_enabled: bool,
+	/// Session is treated as stalled && finished with an error if there are no session-related
+	/// messages within this interval.
+	pub session_timeout_interval: Duration,
+	/// Interval to send session-level KeepAlive-messages.
+	pub session_keep_alive_interval: Duration,
+	/// Interval to check connections to other key servers and to stop stalled sessions.
+	pub maintain_interval: Duration,
+	/// KeepAlive message is sent to the key server if no messages have been received within this interval.
+	pub keep_alive_send_interval: Duration,
+	/// Key server is disconnected if no messages have been received within this interval.
+	pub keep_alive_disconnect_interval: Duration,
+	/// Result of the ACL check is reused for repeated checks of the same (requester, key) pair
+	/// within this interval.
+	pub acl_cache_ttl: Duration,
+	/// Number of most recent restorable versions of the key, that are kept when administrator
+	/// asks to prune obsolete key versions. The latest version of the key is always kept.
+	pub key_versions_to_keep: usize,
+}
+
+impl Default for ClusterConfiguration {
+	fn default() -> Self {
+		ClusterConfiguration {
+			admin_address: None,
+			auto_migrate_enabled: false,
+			session_timeout_interval: DEFAULT_SESSION_TIMEOUT_INTERVAL,
+			session_keep_alive_interval: DEFAULT_SESSION_KEEP_ALIVE_INTERVAL,
+			maintain_interval: DEFAULT_MAINTAIN_INTERVAL,
+			keep_alive_send_interval: DEFAULT_KEEP_ALIVE_SEND_INTERVAL,
+			keep_alive_disconnect_interval: DEFAULT_KEEP_ALIVE_DISCONNECT_INTERVAL,
+			acl_cache_ttl: DEFAULT_ACL_CACHE_TTL,
+			key_versions_to_keep: DEFAULT_KEY_VERSIONS_TO_KEEP,
+		}
+	}
 }
 
 /// Shadow decryption result.
@@ -15,10 +15,24 @@
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use futures::{FutureExt, future::{BoxFuture, ready}};
 use parking_lot::RwLock;
 use ethereum_types::Address;
 use crate::{ServerKeyId, error::Error};
 
+/// Level of access to the key that is required by the operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAccessLevel {
+	/// Access to operations that are not revealing private data associated with
+	/// the key (like server key retrieval).
+	Read,
+	/// Access to all operations that are involving the key, including document
+	/// key retrieval and message signing.
+	Decrypt,
+}
+
 /// ACL storage of Secret Store.
 pub trait AclStorage: Send + Sync {
 	/// Check if owner of `requester_address` can run any operations that are
@@ -27,6 +41,133 @@ pub trait AclStorage: Send + Sync {
 	/// The private data is either private portion of server key, or document
 	/// key associated with this server key.
 	fn check(&self, requester_address: Address, key_id: &ServerKeyId) -> Result<bool, Error>;
+
+	/// Check if owner of `requester_address` has given level of access to the key.
+	///
+	/// Storages that are not distinguishing access levels are using result of `check`
+	/// for all levels.
+	fn check_access(
+		&self,
+		requester_address: Address,
+		key_id: &ServerKeyId,
+		_level: KeyAccessLevel,
+	) -> Result<bool, Error> {
+		self.check(requester_address, key_id)
+	}
+}
+
+/// ACL storage of Secret Store, which is able to check permissions without blocking.
+pub trait AsyncAclStorage: Send + Sync {
+	/// Check if owner of `requester_address` can run any operations that are
+	/// touching private data associated with given server key.
+	///
+	/// See `AclStorage::check` for details.
+	fn check(&self, requester_address: Address, key_id: &ServerKeyId) -> BoxFuture<'static, Result<bool, Error>>;
+
+	/// Check if owner of `requester_address` has given level of access to the key.
+	///
+	/// See `AclStorage::check_access` for details.
+	fn check_access(
+		&self,
+		requester_address: Address,
+		key_id: &ServerKeyId,
+		_level: KeyAccessLevel,
+	) -> BoxFuture<'static, Result<bool, Error>> {
+		self.check(requester_address, key_id)
+	}
+}
+
+/// Adapter that allows using synchronous ACL storage where `AsyncAclStorage` is expected.
+///
+/// The check is performed when `check` is called and returned future is always ready.
+/// So the adapter should only wrap storages that are not blocking (i.e. are not making
+/// any network requests).
+pub struct SyncAclStorageAdapter {
+	acl_storage: Arc<dyn AclStorage>,
+}
+
+impl SyncAclStorageAdapter {
+	/// Create new adapter for given synchronous ACL storage.
+	pub fn new(acl_storage: Arc<dyn AclStorage>) -> Self {
+		SyncAclStorageAdapter {
+			acl_storage,
+		}
+	}
+}
+
+impl AsyncAclStorage for SyncAclStorageAdapter {
+	fn check(&self, requester_address: Address, key_id: &ServerKeyId) -> BoxFuture<'static, Result<bool, Error>> {
+		ready(self.acl_storage.check(requester_address, key_id)).boxed()
+	}
+
+	fn check_access(
+		&self,
+		requester_address: Address,
+		key_id: &ServerKeyId,
+		level: KeyAccessLevel,
+	) -> BoxFuture<'static, Result<bool, Error>> {
+		ready(self.acl_storage.check_access(requester_address, key_id, level)).boxed()
+	}
+}
+
+/// ACL storage that caches results of checks, made by inner storage.
+///
+/// Only successful checks are cached. Cached result is used for `ttl` after
+/// it has been received from the inner storage. While result is cached, future
+/// that is returned by `check` is always ready.
+pub struct CachedAclStorage {
+	/// Inner ACL storage.
+	acl_storage: Arc<dyn AsyncAclStorage>,
+	/// Time during which cached check result is valid.
+	ttl: Duration,
+	/// Cached check results along with time when they have been received.
+	cache: Arc<RwLock<HashMap<(Address, ServerKeyId, KeyAccessLevel), (Instant, bool)>>>,
+}
+
+impl CachedAclStorage {
+	/// Create new cached ACL storage.
+	pub fn new(acl_storage: Arc<dyn AsyncAclStorage>, ttl: Duration) -> Self {
+		CachedAclStorage {
+			acl_storage,
+			ttl,
+			cache: Arc::new(RwLock::new(HashMap::new())),
+		}
+	}
+}
+
+impl AsyncAclStorage for CachedAclStorage {
+	fn check(&self, requester_address: Address, key_id: &ServerKeyId) -> BoxFuture<'static, Result<bool, Error>> {
+		self.check_access(requester_address, key_id, KeyAccessLevel::Decrypt)
+	}
+
+	fn check_access(
+		&self,
+		requester_address: Address,
+		key_id: &ServerKeyId,
+		level: KeyAccessLevel,
+	) -> BoxFuture<'static, Result<bool, Error>> {
+		let cache_key = (requester_address, *key_id, level);
+		let cached_result = self.cache.read()
+			.get(&cache_key)
+			.filter(|(checked_at, _)| checked_at.elapsed() < self.ttl)
+			.map(|(_, is_allowed)| *is_allowed);
+		if let Some(is_allowed) = cached_result {
+			return ready(Ok(is_allowed)).boxed();
+		}
+
+		let ttl = self.ttl;
+		let cache = self.cache.clone();
+		let check = self.acl_storage.check_access(requester_address, key_id, level);
+		async move {
+			let is_allowed = check.await?;
+
+			let mut cache = cache.write();
+			cache.retain(|_, (checked_at, _)| checked_at.elapsed() < ttl);
+			cache.insert(cache_key, (Instant::now(), is_allowed));
+
+			Ok(is_allowed)
+		}.boxed()
+	}
 }
 
 /// In-memory ACL storage implementation.
@@ -55,3 +196,102 @@ impl AclStorage for InMemoryPermissiveAclStorage {
 			.unwrap_or(true))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+	use futures::executor::block_on;
+	use super::*;
+
+	#[derive(Default)]
+	struct CountingAclStorage {
+		acl_storage: InMemoryPermissiveAclStorage,
+		read_only: RwLock<HashSet<Address>>,
+		checks: AtomicUsize,
+		fail: AtomicBool,
+	}
+
+	impl AclStorage for CountingAclStorage {
+		fn check(&self, requester: Address, document: &ServerKeyId) -> Result<bool, Error> {
+			self.checks.fetch_add(1, Ordering::SeqCst);
+			if self.fail.load(Ordering::SeqCst) {
+				return Err(Error::Internal("test".into()));
+			}
+
+			self.acl_storage.check(requester, document)
+		}
+
+		fn check_access(&self, requester: Address, document: &ServerKeyId, level: KeyAccessLevel) -> Result<bool, Error> {
+			let is_allowed = self.check(requester, document)?;
+			Ok(is_allowed && (level == KeyAccessLevel::Read || !self.read_only.read().contains(&requester)))
+		}
+	}
+
+	fn cached_acl_storage(ttl: Duration) -> (Arc<CountingAclStorage>, CachedAclStorage) {
+		let acl_storage = Arc::new(CountingAclStorage::default());
+		let cached_acl_storage = CachedAclStorage::new(
+			Arc::new(SyncAclStorageAdapter::new(acl_storage.clone())),
+			ttl,
+		);
+		(acl_storage, cached_acl_storage)
+	}
+
+	#[test]
+	fn sync_adapter_returns_ready_result() {
+		let acl_storage = Arc::new(InMemoryPermissiveAclStorage::default());
+		acl_storage.forbid(Address::from_low_u64_be(1), ServerKeyId::from_low_u64_be(1));
+
+		let adapter = SyncAclStorageAdapter::new(acl_storage);
+		assert_eq!(adapter.check(Address::from_low_u64_be(1), &ServerKeyId::from_low_u64_be(1)).now_or_never(), Some(Ok(false)));
+		assert_eq!(adapter.check(Address::from_low_u64_be(2), &ServerKeyId::from_low_u64_be(1)).now_or_never(), Some(Ok(true)));
+	}
+
+	#[test]
+	fn cached_storage_reuses_result_of_repeated_check() {
+		let (acl_storage, cached_acl_storage) = cached_acl_storage(Duration::from_secs(60));
+		acl_storage.acl_storage.forbid(Address::from_low_u64_be(1), ServerKeyId::from_low_u64_be(1));
+
+		let requester = Address::from_low_u64_be(1);
+		assert_eq!(block_on(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(1))), Ok(false));
+		assert_eq!(block_on(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(2))), Ok(true));
+		assert_eq!(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(1)).now_or_never(), Some(Ok(false)));
+		assert_eq!(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(2)).now_or_never(), Some(Ok(true)));
+		assert_eq!(acl_storage.checks.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn cached_storage_checks_again_when_result_is_expired() {
+		let (acl_storage, cached_acl_storage) = cached_acl_storage(Duration::from_secs(0));
+
+		let requester = Address::from_low_u64_be(1);
+		assert_eq!(block_on(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(1))), Ok(true));
+		assert_eq!(block_on(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(1))), Ok(true));
+		assert_eq!(acl_storage.checks.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn cached_storage_does_not_cache_errors() {
+		let (acl_storage, cached_acl_storage) = cached_acl_storage(Duration::from_secs(60));
+		acl_storage.fail.store(true, Ordering::SeqCst);
+
+		let requester = Address::from_low_u64_be(1);
+		assert!(block_on(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(1))).is_err());
+
+		acl_storage.fail.store(false, Ordering::SeqCst);
+		assert_eq!(block_on(cached_acl_storage.check(requester, &ServerKeyId::from_low_u64_be(1))), Ok(true));
+		assert_eq!(acl_storage.checks.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn cached_storage_caches_access_levels_separately() {
+		let (acl_storage, cached_acl_storage) = cached_acl_storage(Duration::from_secs(60));
+		acl_storage.read_only.write().insert(Address::from_low_u64_be(1));
+
+		let requester = Address::from_low_u64_be(1);
+		let key_id = ServerKeyId::from_low_u64_be(1);
+		assert_eq!(block_on(cached_acl_storage.check_access(requester, &key_id, KeyAccessLevel::Read)), Ok(true));
+		assert_eq!(block_on(cached_acl_storage.check_access(requester, &key_id, KeyAccessLevel::Decrypt)), Ok(false));
+		assert_eq!(cached_acl_storage.check(requester, &key_id).now_or_never(), Some(Ok(false)));
+		assert_eq!(acl_storage.checks.load(Ordering::SeqCst), 2);
+	}
+}
@@ -88,6 +88,17 @@ pub enum Error {
 	Database(String),
 	/// Internal error.
 	Internal(String),
+
+	// variants are encoded by their index by the binary codec => new variants must only be appended
+
+	/// Partial signature, computed by given node, is invalid.
+	/// This means that node is misbehaving/cheating.
+	InvalidSignatureShare(KeyServerId),
+	/// Secret subshare, sent by given node, does not match its commitments.
+	/// This means that node is misbehaving/cheating.
+	InvalidSecretSubshare(KeyServerId),
+	/// Server key with this ID has been deleted. Key with the same ID can't be generated again.
+	ServerKeyIsDeleted,
 }
 
 impl Error {
@@ -115,8 +126,10 @@ impl Error {
 
 			// config-related errors
 			Error::InvalidNodeAddress | Error::InvalidNodeId(_) |
+			// misbehaving node errors => node must be excluded from all further computations
+			Error::InvalidSignatureShare(_) | Error::InvalidSecretSubshare(_) |
 			// wrong session input params errors
-			Error::NotEnoughNodesForThreshold | Error::ServerKeyAlreadyGenerated | Error::ServerKeyIsNotFound |
+			Error::NotEnoughNodesForThreshold | Error::ServerKeyAlreadyGenerated | Error::ServerKeyIsNotFound | Error::ServerKeyIsDeleted |
 				Error::DocumentKeyAlreadyStored | Error::DocumentKeyIsNotFound | Error::InsufficientRequesterData(_) |
 			// access denied/consensus error
 			Error::AccessDenied | Error::ConsensusUnreachable |
@@ -140,10 +153,13 @@ impl fmt::Display for Error {
 			Error::InvalidNodeForRequest => write!(f, "invalid node for this request"),
 			Error::InvalidMessage => write!(f, "invalid message is received"),
 			Error::InvalidMessageVersion => write!(f, "unsupported message is received"),
+			Error::InvalidSignatureShare(ref id) => write!(f, "invalid signature share is received from node {}", id),
+			Error::InvalidSecretSubshare(ref id) => write!(f, "invalid secret subshare is received from node {}", id),
 			Error::ReplayProtection => write!(f, "replay message is received"),
 			Error::NodeDisconnected => write!(f, "node required for this operation is currently disconnected"),
 			Error::ServerKeyAlreadyGenerated => write!(f, "Server key with this ID is already generated"),
 			Error::ServerKeyIsNotFound => write!(f, "Server key with this ID is not found"),
+			Error::ServerKeyIsDeleted => write!(f, "Server key with this ID has been deleted"),
 			Error::DocumentKeyAlreadyStored => write!(f, "Document key with this ID is already stored"),
 			Error::DocumentKeyIsNotFound => write!(f, "Document key with this ID is not found"),
 			Error::ConsensusUnreachable => write!(f, "Consensus unreachable"),
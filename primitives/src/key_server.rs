@@ -79,6 +79,20 @@ pub struct ServerKeyRetrievalArtifacts {
 /// Result of server key retrieval session.
 pub type ServerKeyRetrievalResult = SessionResult<ServerKeyRetrievalParams, ServerKeyRetrievalArtifacts>;
 
+/// Essential server key deletion params.
+#[derive(Clone)]
+pub struct ServerKeyDeletionParams {
+	/// Key id.
+	pub key_id: ServerKeyId,
+}
+
+/// Server key deletion artifacts.
+#[derive(Clone)]
+pub struct ServerKeyDeletionArtifacts;
+
+/// Result of server key deletion session.
+pub type ServerKeyDeletionResult = SessionResult<ServerKeyDeletionParams, ServerKeyDeletionArtifacts>;
+
 /// Server key (SK) generator.
 pub trait ServerKeyGenerator {
 	/// SK generation future.
@@ -110,6 +124,23 @@ pub trait ServerKeyGenerator {
 	) -> Self::RestoreKeyFuture;
 }
 
+/// Server key (SK) deleter.
+pub trait ServerKeyDeleter: ServerKeyGenerator {
+	/// SK deletion future.
+	type DeleteKeyFuture: Future<Output = ServerKeyDeletionResult> + Send;
+
+	/// Delete previously generated SK from all key servers.
+	/// `key_id` is identifier of previously generated SK.
+	/// `author` is the same author, that has created the server key.
+	/// Once deleted, SK with the same `key_id` can't be generated again.
+	fn delete_key(
+		&self,
+		origin: Option<Origin>,
+		key_id: ServerKeyId,
+		author: Requester,
+	) -> Self::DeleteKeyFuture;
+}
+
 /// Essential document key store params.
 #[derive(Clone)]
 pub struct DocumentKeyStoreParams {
@@ -312,6 +343,8 @@ pub struct SchnorrSigningArtifacts {
 	pub signature_c: H256,
 	/// S portion of Schnorr signature. UNENCRYPTED.
 	pub signature_s: H256,
+	/// Threshold that has been used to generate associated server key.
+	pub threshold: usize,
 }
 
 /// Result of Schnorr signing session.
@@ -331,6 +364,8 @@ pub struct EcdsaSigningParams {
 pub struct EcdsaSigningArtifacts {
 	/// ECDSA signature. UNENCRYPTED.
 	pub signature: Signature,
+	/// Threshold that has been used to generate associated server key.
+	pub threshold: usize,
 }
 
 /// Result of ECDSA signing session.
@@ -386,10 +421,35 @@ pub trait AdminSessionsServer {
 		new_set_signature: Signature,
 		new_servers_set: BTreeSet<KeyServerId>,
 	) -> Self::ChangeServersSetFuture;
+
+	/// Refresh key shares future.
+	type RefreshKeySharesFuture: Future<Output = SessionResult<(), ()>> + Send;
+
+	/// Re-randomize shares of given key (or of all keys if key_id is None) without changing
+	/// servers set and the joint public key. Old versions of key shares are removed once
+	/// all cluster nodes have received new shares.
+	fn refresh_key_shares(
+		&self,
+		origin: Option<Origin>,
+		key_id: Option<ServerKeyId>,
+		servers_set_signature: Signature,
+	) -> Self::RefreshKeySharesFuture;
+
+	/// Prune key versions future.
+	type PruneKeyVersionsFuture: Future<Output = SessionResult<(), ()>> + Send;
+
+	/// Remove obsolete versions of given key from all key servers. Every key server keeps the latest
+	/// version and a configured number of most recent restorable versions of the key.
+	fn prune_key_versions(
+		&self,
+		origin: Option<Origin>,
+		key_id: ServerKeyId,
+		admin_signature: Signature,
+	) -> Self::PruneKeyVersionsFuture;
 }
 
 /// Key server.
-pub trait KeyServer: AdminSessionsServer + DocumentKeyServer + MessageSigner + Send + Sync + 'static {
+pub trait KeyServer: AdminSessionsServer + ServerKeyDeleter + DocumentKeyServer + MessageSigner + Send + Sync + 'static {
 }
 
 impl<P, R> SessionResult<P, R> {
@@ -479,6 +539,29 @@ mod accumulating_key_server {
 		}
 	}
 
+	impl ServerKeyDeleter for AccumulatingKeyServer {
+		type DeleteKeyFuture = Ready<ServerKeyDeletionResult>;
+
+		fn delete_key(
+			&self,
+			origin: Option<Origin>,
+			key_id: ServerKeyId,
+			author: Requester,
+		) -> Self::DeleteKeyFuture {
+			self.accumulated_tasks.lock().push(ServiceTask::DeleteServerKey(
+				key_id,
+				author,
+			));
+			ready(SessionResult {
+				origin,
+				params: ServerKeyDeletionParams {
+					key_id,
+				},
+				result: Err(Error::Internal("Test-Error".into())),
+			})
+		}
+	}
+
 	impl DocumentKeyServer for AccumulatingKeyServer {
 		type StoreDocumentKeyFuture = Ready<DocumentKeyStoreResult>;
 		type GenerateDocumentKeyFuture = Ready<DocumentKeyGenerationResult>;
@@ -656,6 +739,44 @@ mod accumulating_key_server {
 				result: Err(Error::Internal("Test-Error".into())),
 			})
 		}
+
+		type RefreshKeySharesFuture = Ready<SessionResult<(), ()>>;
+
+		fn refresh_key_shares(
+			&self,
+			origin: Option<Origin>,
+			key_id: Option<ServerKeyId>,
+			servers_set_signature: Signature,
+		) -> Self::RefreshKeySharesFuture {
+			self.accumulated_tasks.lock().push(ServiceTask::RefreshKeyShares(
+				key_id,
+				servers_set_signature,
+			));
+			ready(SessionResult {
+				origin,
+				params: (),
+				result: Err(Error::Internal("Test-Error".into())),
+			})
+		}
+
+		type PruneKeyVersionsFuture = Ready<SessionResult<(), ()>>;
+
+		fn prune_key_versions(
+			&self,
+			origin: Option<Origin>,
+			key_id: ServerKeyId,
+			admin_signature: Signature,
+		) -> Self::PruneKeyVersionsFuture {
+			self.accumulated_tasks.lock().push(ServiceTask::PruneKeyVersions(
+				key_id,
+				admin_signature,
+			));
+			ready(SessionResult {
+				origin,
+				params: (),
+				result: Err(Error::Internal("Test-Error".into())),
+			})
+		}
 	}
 
 	impl KeyServer for AccumulatingKeyServer {
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use parking_lot::RwLock;
 use tiny_keccak::{Hasher, Keccak};
 use ethereum_types::H256;
@@ -47,12 +47,16 @@ pub struct KeyShareVersion {
 	pub id_numbers: BTreeMap<KeyServerId, Secret>,
 	/// Secret share of secret portion of server key, valid within this version.
 	pub secret_share: Secret,
+	/// Public key shares (secret_share * G) of all nodes, valid within this version.
+	/// Is empty if public key shares are unknown (i.e. for versions, created by share add session
+	/// before key shares verification has been introduced).
+	pub public_shares: BTreeMap<KeyServerId, Public>,
 }
 
 
 /// Secret Store key storage.
 pub trait KeyStorage: Send + Sync + 'static {
-	/// Insert new key share.
+	/// Insert new key share. Fails if the key with the same id has been deleted.
 	fn insert(&self, key_id: ServerKeyId, key: KeyShare) -> Result<(), Error>;
 	/// Update existing key share.
 	fn update(&self, key_id: ServerKeyId, key: KeyShare) -> Result<(), Error>;
@@ -60,22 +64,38 @@ pub trait KeyStorage: Send + Sync + 'static {
 	fn get(&self, key_id: &ServerKeyId) -> Result<Option<KeyShare>, Error>;
 	/// Remove key share.
 	fn remove(&self, key_id: &ServerKeyId) -> Result<(), Error>;
+	/// Remove key share and leave the tombstone, so that the key with the same id can't be inserted again.
+	fn delete(&self, key_id: &ServerKeyId) -> Result<(), Error>;
+	/// Check if the key with given id has been deleted.
+	fn is_deleted(&self, key_id: &ServerKeyId) -> Result<bool, Error>;
+	/// Durably stage new key share. Staged key share isn't returned by `get` until it is committed.
+	fn prepare(&self, key_id: ServerKeyId, key: KeyShare) -> Result<(), Error>;
+	/// Replace key share with the staged one. Fails if there's no staged key share.
+	fn commit_prepared(&self, key_id: &ServerKeyId) -> Result<(), Error>;
+	/// Drop staged key share (if any).
+	fn discard_prepared(&self, key_id: &ServerKeyId) -> Result<(), Error>;
 	/// Clears the database.
 	fn clear(&self) -> Result<(), Error>;
 	/// Check if storage contains encryption key
 	fn contains(&self, key_id: &ServerKeyId) -> bool;
-	/// Iterate through storage.
-	fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=(ServerKeyId, KeyShare)> + 'a>;
+	/// Iterate through storage. Key shares that can't be read are returned as errors.
+	fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Result<(ServerKeyId, KeyShare), Error>> + 'a>;
 }
 
 /// In-memory key storage implementation.
 #[derive(Debug, Default)]
 pub struct InMemoryKeyStorage {
 	keys: RwLock<HashMap<ServerKeyId, KeyShare>>,
+	deleted_keys: RwLock<HashSet<ServerKeyId>>,
+	prepared_keys: RwLock<HashMap<ServerKeyId, KeyShare>>,
 }
 
 impl KeyStorage for InMemoryKeyStorage {
 	fn insert(&self, key_id: ServerKeyId, key: KeyShare) -> Result<(), Error> {
+		if self.deleted_keys.read().contains(&key_id) {
+			return Err(Error::ServerKeyIsDeleted);
+		}
+
 		self.keys.write().insert(key_id, key);
 		Ok(())
 	}
@@ -94,6 +114,36 @@ impl KeyStorage for InMemoryKeyStorage {
 		Ok(())
 	}
 
+	fn delete(&self, key_id: &ServerKeyId) -> Result<(), Error> {
+		self.deleted_keys.write().insert(*key_id);
+		self.keys.write().remove(key_id);
+		Ok(())
+	}
+
+	fn is_deleted(&self, key_id: &ServerKeyId) -> Result<bool, Error> {
+		Ok(self.deleted_keys.read().contains(key_id))
+	}
+
+	fn prepare(&self, key_id: ServerKeyId, key: KeyShare) -> Result<(), Error> {
+		self.prepared_keys.write().insert(key_id, key);
+		Ok(())
+	}
+
+	fn commit_prepared(&self, key_id: &ServerKeyId) -> Result<(), Error> {
+		if self.deleted_keys.read().contains(key_id) {
+			return Err(Error::ServerKeyIsDeleted);
+		}
+
+		let key = self.prepared_keys.write().remove(key_id).ok_or(Error::InvalidStateForRequest)?;
+		self.keys.write().insert(*key_id, key);
+		Ok(())
+	}
+
+	fn discard_prepared(&self, key_id: &ServerKeyId) -> Result<(), Error> {
+		self.prepared_keys.write().remove(key_id);
+		Ok(())
+	}
+
 	fn clear(&self) -> Result<(), Error> {
 		self.keys.write().clear();
 		Ok(())
@@ -103,8 +153,8 @@ impl KeyStorage for InMemoryKeyStorage {
 		self.keys.read().contains_key(key_id)
 	}
 
-	fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=(ServerKeyId, KeyShare)> + 'a> {
-		Box::new(self.keys.read().clone().into_iter())
+	fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=Result<(ServerKeyId, KeyShare), Error>> + 'a> {
+		Box::new(self.keys.read().clone().into_iter().map(Ok))
 	}
 }
 
@@ -128,14 +178,25 @@ impl KeyShare {
 
 impl KeyShareVersion {
 	/// Create new version.
-	pub fn new(id_numbers: BTreeMap<KeyServerId, Secret>, secret_share: Secret) -> Self {
+	pub fn new(
+		id_numbers: BTreeMap<KeyServerId, Secret>,
+		secret_share: Secret,
+		public_shares: BTreeMap<KeyServerId, Public>,
+	) -> Self {
 		KeyShareVersion {
 			hash: Self::data_hash(id_numbers.iter().map(|(k, v)| (k.as_bytes(), v.as_bytes()))),
 			id_numbers: id_numbers,
 			secret_share: secret_share,
+			public_shares: public_shares,
 		}
 	}
 
+	/// Calculate hash of version, that is created when shares of given version are refreshed
+	/// (id numbers of the refreshed version are the same, so its hash must be computed differently).
+	pub fn refreshed_hash(version: &H256) -> H256 {
+		Self::data_hash(std::iter::once((version.as_bytes(), &[][..])))
+	}
+
 	/// Calculate hash of given version data.
 	pub fn data_hash<'a, I>(id_numbers: I) -> H256 where I: Iterator<Item=(&'a [u8], &'a [u8])> {
 		let mut nodes_keccak = Keccak::v256();
@@ -14,8 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
+use tiny_keccak::{Hasher, Keccak};
 use parity_crypto::publickey::{Address, Public, Signature, public_to_address, recover};
-use crate::{error::Error, ServerKeyId};
+use crate::{error::Error, ServerKeyId, H256};
+
+/// Prefix of the message that the key author signs to delete the key.
+const KEY_DELETION_MESSAGE_PREFIX: &[u8] = b"delete";
 
 /// Requester identification data.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -48,6 +52,19 @@ impl Requester {
 	}
 }
 
+/// Compute hash of the message that must be signed by the key author to delete the key. It differs
+/// from the key id, so signatures that are used to generate or to retrieve the key can't be reused
+/// to delete it.
+pub fn key_deletion_message_hash(server_key_id: &ServerKeyId) -> H256 {
+	let mut keccak = Keccak::v256();
+	keccak.update(KEY_DELETION_MESSAGE_PREFIX);
+	keccak.update(server_key_id.as_bytes());
+
+	let mut hash = [0u8; 32];
+	keccak.finalize(&mut hash);
+	hash.into()
+}
+
 impl From<Signature> for Requester {
 	fn from(signature: Signature) -> Requester {
 		Requester::Signature(signature)
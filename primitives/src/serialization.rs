@@ -46,6 +46,73 @@ impl ToHex for Secret {
 	}
 }
 
+/// Conversion to/from raw bytes. Used when value is serialized by non human-readable
+/// serializer (like bincode), where hex-encoding only doubles the size of data.
+trait RawBytes: Sized {
+	fn to_raw_bytes(&self) -> &[u8];
+	fn from_raw_bytes(bytes: &[u8]) -> Result<Self, String>;
+}
+
+impl RawBytes for Bytes {
+	fn to_raw_bytes(&self) -> &[u8] {
+		&self[..]
+	}
+
+	fn from_raw_bytes(bytes: &[u8]) -> Result<Self, String> {
+		Ok(bytes.to_vec())
+	}
+}
+
+impl RawBytes for Secret {
+	fn to_raw_bytes(&self) -> &[u8] {
+		self.as_bytes()
+	}
+
+	fn from_raw_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() != 32 {
+			return Err(format!("invalid secret length: {}", bytes.len()));
+		}
+
+		let mut secret = [0u8; 32];
+		secret.copy_from_slice(bytes);
+		Ok(secret.into())
+	}
+}
+
+impl RawBytes for Signature {
+	fn to_raw_bytes(&self) -> &[u8] {
+		&self[..]
+	}
+
+	fn from_raw_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() != 65 {
+			return Err(format!("invalid signature length: {}", bytes.len()));
+		}
+
+		let mut signature = [0u8; 65];
+		signature.copy_from_slice(bytes);
+		Ok(signature.into())
+	}
+}
+
+macro_rules! impl_raw_bytes {
+	($name: ident, $len: expr) => (
+		impl RawBytes for $name {
+			fn to_raw_bytes(&self) -> &[u8] {
+				self.as_bytes()
+			}
+
+			fn from_raw_bytes(bytes: &[u8]) -> Result<Self, String> {
+				if bytes.len() != $len {
+					return Err(format!("invalid {} length: {}", stringify!($name), bytes.len()));
+				}
+
+				Ok($name::from_slice(bytes))
+			}
+		}
+	);
+}
+
 macro_rules! impl_to_hex {
 	($name: ident) => (
 		impl ToHex for $name {
@@ -92,7 +159,11 @@ macro_rules! impl_bytes {
 
 		impl Serialize for $name {
 			fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-				serializer.serialize_str(<$other as ToHex>::to_hex(&self.0).as_ref())
+				if serializer.is_human_readable() {
+					serializer.serialize_str(<$other as ToHex>::to_hex(&self.0).as_ref())
+				} else {
+					serializer.serialize_bytes(<$other as RawBytes>::to_raw_bytes(&self.0))
+				}
 			}
 		}
 
@@ -120,7 +191,25 @@ macro_rules! impl_bytes {
 					}
 				}
 
-				deserializer.deserialize_any(HexBytesVisitor)
+				struct RawBytesVisitor;
+
+				impl<'b> Visitor<'b> for RawBytesVisitor {
+					type Value = $name;
+
+					fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+						write!(formatter, "a raw bytes array")
+					}
+
+					fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E> where E: SerdeError {
+						<$other as RawBytes>::from_raw_bytes(value).map($name).map_err(SerdeError::custom)
+					}
+				}
+
+				if deserializer.is_human_readable() {
+					deserializer.deserialize_any(HexBytesVisitor)
+				} else {
+					deserializer.deserialize_bytes(RawBytesVisitor)
+				}
 			}
 		}
 	}
@@ -135,6 +224,10 @@ impl_to_hex!(H256);
 impl_to_hex!(H160);
 impl_to_hex!(Public);
 
+impl_raw_bytes!(H256, 32);
+impl_raw_bytes!(H160, 20);
+impl_raw_bytes!(Public, 64);
+
 impl_bytes!(SerializableBytes, Bytes, true, (Default));
 impl_bytes!(SerializableH256, H256, false, (Default, PartialOrd, Ord));
 impl_bytes!(SerializableH160, H160, false, (Default, PartialOrd, Ord));
@@ -199,6 +292,32 @@ mod tests {
 		);
 	}
 
+	macro_rules! do_binary_test {
+		($value: expr, $expected_len: expr, $expected_type: ident) => (
+			let serialized = bincode::serialize(&$value).unwrap();
+			// bincode prefixes byte arrays with u64 length
+			assert_eq!(serialized.len(), 8 + $expected_len);
+			let deserialized: $expected_type = bincode::deserialize(&serialized).unwrap();
+			assert_eq!(deserialized, $value);
+		);
+	}
+
+	#[test]
+	fn serialize_and_deserialize_binary() {
+		do_binary_test!(SerializableBytes(vec![1, 2, 3, 4]), 4, SerializableBytes);
+		do_binary_test!(SerializableH256(H256::from_low_u64_be(42)), 32, SerializableH256);
+		do_binary_test!(SerializableH160(H160::from_low_u64_be(42)), 20, SerializableH160);
+		do_binary_test!(SerializablePublic(Public::from_low_u64_be(42)), 64, SerializablePublic);
+		do_binary_test!(SerializableSecret(Secret::from([42u8; 32])), 32, SerializableSecret);
+		do_binary_test!(SerializableSignature(Signature::from([42u8; 65])), 65, SerializableSignature);
+	}
+
+	#[test]
+	fn deserializing_binary_of_wrong_length_fails() {
+		let serialized = bincode::serialize(&SerializableH160(H160::from_low_u64_be(42))).unwrap();
+		assert!(bincode::deserialize::<SerializableH256>(&serialized).is_err());
+	}
+
 	#[test]
 	fn serialize_and_deserialize_bytes() {
 		do_test!(SerializableBytes(vec![1, 2, 3, 4]), "\"0x01020304\"".to_owned(), SerializableBytes);
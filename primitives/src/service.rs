@@ -19,7 +19,7 @@ use ethereum_types::H256;
 use parity_crypto::publickey::{Public, Signature};
 use crate::{
 	KeyServerId, ServerKeyId,
-	key_server::{ServerKeyGenerationResult, DocumentKeyShadowRetrievalResult},
+	key_server::{ServerKeyGenerationResult, ServerKeyDeletionResult, DocumentKeyShadowRetrievalResult},
 	requester::Requester,
 };
 
@@ -37,6 +37,8 @@ pub trait ServiceTasksListenerRegistrar: Send + Sync {
 pub trait ServiceTasksListener: Send + Sync {
 	/// Called when server key generation session is completed.
 	fn server_key_generated(&self, _: ServerKeyGenerationResult) {}
+	/// Called when server key deletion session is completed.
+	fn server_key_deleted(&self, _: ServerKeyDeletionResult) {}
 	/// Called when document key shadow is retrieved.
 	fn document_key_shadow_retrieved(&self, _: DocumentKeyShadowRetrievalResult) {}
 }
@@ -50,6 +52,8 @@ pub enum ServiceTask {
 	GenerateServerKey(ServerKeyId, Requester, usize),
 	/// Retrieve server key (server_key_id, requester).
 	RetrieveServerKey(ServerKeyId, Option<Requester>),
+	/// Delete server key (server_key_id, author).
+	DeleteServerKey(ServerKeyId, Requester),
 
 	// === Document key store tasks ===
 
@@ -76,4 +80,8 @@ pub enum ServiceTask {
 
 	/// Change servers set (old_set_signature, new_set_signature, new_set).
 	ChangeServersSet(Signature, Signature, BTreeSet<KeyServerId>),
+	/// Refresh key shares of given key or of all keys (key_id, servers_set_signature).
+	RefreshKeyShares(Option<ServerKeyId>, Signature),
+	/// Prune obsolete versions of the key (key_id, admin_signature).
+	PruneKeyVersions(ServerKeyId, Signature),
 }
@@ -0,0 +1,25 @@
+#!/usr/bin/env bash
+# Runs all benchmarks of the secret store runtime module. The output is used to update
+# default weights in `src/weights.rs`.
+#
+# Usage: benchmark.sh [STEPS] [REPEAT]
+
+set -e
+
+STEPS=${1:-10}
+REPEAT=${2:-20}
+
+MODULE_DIR=$(cd "$(dirname "$0")" && pwd)
+ROOT_DIR=$(cd "$MODULE_DIR/../.." && pwd)
+NODE="$ROOT_DIR/target/release/parity-secretstore-substrate-node"
+
+cargo build --release --manifest-path "$ROOT_DIR/bin/substrate-node/Cargo.toml" --features runtime-benchmarks
+
+for extrinsic in $(sed -n 's/^\t\([a-z][a-z_0-9]*\) {$/\1/p' "$MODULE_DIR/src/benchmarking.rs"); do
+	"$NODE" benchmark \
+		--chain dev \
+		--pallet secretstore \
+		--extrinsic "$extrinsic" \
+		--steps "$STEPS" \
+		--repeat "$REPEAT"
+done
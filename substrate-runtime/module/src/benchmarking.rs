@@ -0,0 +1,756 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Benchmarks of module dispatchables. Every benchmark is executed in the worst
+//! possible conditions, i.e. responses from key servers are benchmarked when they
+//! complete the request and the service fee is paid to all key servers.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use frame_benchmarking::{benchmarks, account};
+use frame_support::{StorageValue, StorageMap};
+use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+use sp_std::{collections::btree_map::BTreeMap, vec::Vec};
+use crate::key_server_set_storage::{KeyServer, RuntimeStorageWithMigration, Storage, StorageWithMigration};
+use super::*;
+
+const SEED: u32 = 0;
+/// Maximal number of key servers in the set that we're benchmarking.
+const MAX_KEY_SERVERS: u32 = 255;
+/// Maximal size of key server network address that we're benchmarking.
+const MAX_ADDRESS_SIZE: u32 = 256;
+/// Maximal number of key approvers that we're benchmarking.
+const MAX_KEY_APPROVERS: u32 = crate::key_access::MAX_KEY_APPROVERS as u32;
+/// Maximal size of document key shadow that we're benchmarking.
+const MAX_SHADOW_SIZE: u32 = 4096;
+/// Maximal number of both active and expired requests in the queue that we're benchmarking.
+const MAX_EXPIRING_REQUESTS: u32 = 2;
+
+/// Returns id of benchmark key server with given index.
+fn key_server_id(index: u32) -> KeyServerId {
+	KeyServerId::from_low_u64_be(1_000_000 + index as u64)
+}
+
+/// Returns set of `k` benchmark key servers.
+fn key_servers(k: u32) -> BTreeMap<KeyServerId, KeyServer> {
+	(0..k)
+		.map(|index| (key_server_id(index), KeyServer {
+			index: index as u8,
+			id: key_server_id(index),
+			address: key_server_id(index).as_bytes().to_vec(),
+		}))
+		.collect()
+}
+
+/// Claim given entity id by given account.
+fn claim<T: Trait>(account: &T::AccountId, id: EntityId) {
+	ClaimedId::<T>::insert(account, id);
+	ClaimedBy::<T>::insert(id, account.clone());
+}
+
+/// Replace current and new key server sets with the set of `k` benchmark key servers.
+/// Returns accounts of these key servers.
+fn fill_key_servers<T: Trait>(k: u32) -> Vec<T::AccountId> {
+	let mut storage = RuntimeStorageWithMigration::<T>::default();
+	storage.current_mut().fill_from(key_servers(k));
+	storage.new_mut().fill_from(key_servers(k));
+	storage.migration_mut().clear();
+	storage.set_migration_id(None);
+	storage.initialized();
+
+	(0..k)
+		.map(|index| {
+			let key_server: T::AccountId = account("key_server", index, SEED);
+			claim::<T>(&key_server, key_server_id(index));
+			key_server
+		})
+		.collect()
+}
+
+/// Make given account the owner of the module.
+fn owner<T: Trait>() -> T::AccountId {
+	let owner: T::AccountId = account("owner", 0, SEED);
+	Owner::<T>::put(owner.clone());
+	owner
+}
+
+/// Set non-zero fees for all services.
+fn set_fees<T: Trait>() {
+	let fee: BalanceOf<T> = 1_000_000u32.into();
+	ServerKeyGenerationFee::<T>::put(fee);
+	ServerKeyRetrievalFee::<T>::put(fee);
+	ServerKeyDeletionFee::<T>::put(fee);
+	DocumentKeyStoreFee::<T>::put(fee);
+	DocumentKeyShadowRetrievalFee::<T>::put(fee);
+	SchnorrSigningFee::<T>::put(fee);
+	EcdsaSigningFee::<T>::put(fee);
+}
+
+/// Create account with given claimed id and enough funds to pay for any service.
+fn funded_account<T: Trait>(name: &'static str, index: u32, id: EntityId) -> T::AccountId {
+	let account: T::AccountId = account(name, index, SEED);
+	claim::<T>(&account, id);
+	T::Currency::make_free_balance_be(&account, 1_000_000_000u32.into());
+	account
+}
+
+/// Create requester account.
+fn requester<T: Trait>(index: u32) -> T::AccountId {
+	funded_account::<T>("requester", index, requester_id(index))
+}
+
+/// Returns id of requester with given index.
+fn requester_id(index: u32) -> EntityId {
+	EntityId::from_low_u64_be(2_000_000 + index as u64)
+}
+
+/// Returns public key of the document key shadow requester.
+fn shadow_requester_public() -> sp_core::H512 {
+	sp_core::H512::repeat_byte(1)
+}
+
+/// Returns id of the document key shadow requester.
+fn shadow_requester_id() -> EntityId {
+	let public_hash = sp_io::hashing::keccak_256(shadow_requester_public().as_fixed_bytes());
+	let mut id = EntityId::zero();
+	id.as_bytes_mut().copy_from_slice(&public_hash[12..]);
+	id
+}
+
+/// Create document key shadow requester account.
+fn shadow_requester<T: Trait>() -> T::AccountId {
+	funded_account::<T>("shadow_requester", 0, shadow_requester_id())
+}
+
+/// Returns ids of `n` key approvers.
+fn approver_ids(n: u32) -> Vec<EntityId> {
+	(0..n).map(|index| EntityId::from_low_u64_be(3_000_000 + index as u64)).collect()
+}
+
+/// Create `n` key approvers accounts.
+fn approver_accounts<T: Trait>(n: u32) -> Vec<T::AccountId> {
+	approver_ids(n)
+		.into_iter()
+		.enumerate()
+		.map(|(index, id)| funded_account::<T>("approver", index as u32, id))
+		.collect()
+}
+
+fn server_key_id(index: u32) -> ServerKeyId {
+	ServerKeyId::from_low_u64_be(index as u64)
+}
+
+fn server_key_public() -> sp_core::H512 {
+	sp_core::H512::repeat_byte(2)
+}
+
+fn migration_id() -> MigrationIdT {
+	MigrationIdT::repeat_byte(3)
+}
+
+fn message() -> sp_core::H256 {
+	sp_core::H256::repeat_byte(4)
+}
+
+fn signed<T: Trait>(account: &T::AccountId) -> T::Origin {
+	RawOrigin::Signed(account.clone()).into()
+}
+
+benchmarks! {
+	_ {
+		let u in 0 .. 1000 => ();
+		let k in 1 .. MAX_KEY_SERVERS => ();
+		let a in 1 .. MAX_ADDRESS_SIZE => ();
+		let n in 1 .. MAX_KEY_APPROVERS => ();
+		let s in 1 .. MAX_SHADOW_SIZE => ();
+	}
+
+	change_owner {
+		let u in ...;
+		let caller = owner::<T>();
+		let new_owner: T::AccountId = account("new_owner", u, SEED);
+	}: _(RawOrigin::Signed(caller), new_owner)
+
+	claim_id {
+		let u in ...;
+		let caller: T::AccountId = account("caller", u, SEED);
+	}: _(RawOrigin::Signed(caller), requester_id(u))
+
+	complete_initialization {
+		let u in ...;
+		let caller = owner::<T>();
+		IsInitialized::put(false);
+	}: _(RawOrigin::Signed(caller))
+
+	add_key_server {
+		let k in ...;
+		let a in ...;
+		fill_key_servers::<T>(k);
+		let caller = owner::<T>();
+	}: _(RawOrigin::Signed(caller), key_server_id(k), vec![0u8; a as usize])
+
+	remove_key_server {
+		let u in ...;
+		fill_key_servers::<T>(2);
+		let caller = owner::<T>();
+	}: _(RawOrigin::Signed(caller), key_server_id(1))
+
+	start_migration {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		// the new set has one more key server
+		let mut storage = RuntimeStorageWithMigration::<T>::default();
+		storage.new_mut().append(key_server_id(k), key_server_id(k).as_bytes().to_vec())?;
+	}: _(RawOrigin::Signed(key_servers[0].clone()), migration_id())
+
+	confirm_migration {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		// all key servers, except the last one, have already confirmed migration
+		let mut storage = RuntimeStorageWithMigration::<T>::default();
+		storage.migration_mut().fill_from(self::key_servers(k));
+		storage.set_migration_id(Some((migration_id(), key_server_id(0))));
+		for index in 0..k - 1 {
+			storage.confirm_migration(&key_server_id(index));
+		}
+	}: _(RawOrigin::Signed(key_servers[k as usize - 1].clone()), migration_id())
+
+	set_request_lifetime {
+		let u in ...;
+	}: _(RawOrigin::Root, (u + 1).into())
+
+	claim_key {
+		let u in ...;
+		let caller = requester::<T>(u);
+	}: _(RawOrigin::Signed(caller), server_key_id(u))
+
+	transfer_key {
+		let u in ...;
+		let caller = requester::<T>(u);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(u))?;
+	}: _(RawOrigin::Signed(caller), server_key_id(u), requester_id(u + 1))
+
+	grant_key_access {
+		let u in ...;
+		let caller = requester::<T>(u);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(u))?;
+	}: _(
+		RawOrigin::Signed(caller),
+		server_key_id(u),
+		requester_id(u + 1),
+		KeyAccessLevel::Decrypt,
+		Some(T::BlockNumber::max_value())
+	)
+
+	revoke_key_access {
+		let u in ...;
+		let caller = requester::<T>(u);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(u))?;
+		Module::<T>::grant_key_access(
+			signed::<T>(&caller),
+			server_key_id(u),
+			requester_id(u + 1),
+			KeyAccessLevel::Decrypt,
+			None,
+		)?;
+	}: _(RawOrigin::Signed(caller), server_key_id(u), requester_id(u + 1))
+
+	set_key_access_policy {
+		let u in ...;
+		let caller = requester::<T>(u);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(u))?;
+	}: _(RawOrigin::Signed(caller), server_key_id(u), KeyAccessPolicyKind::MultiApproval)
+
+	set_key_approvers {
+		let n in ...;
+		let caller = requester::<T>(0);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(0))?;
+	}: _(RawOrigin::Signed(caller), server_key_id(0), approver_ids(n), n as u8)
+
+	approve_key_access {
+		let u in ...;
+		let caller = requester::<T>(0);
+		let approvers = approver_accounts::<T>(MAX_KEY_APPROVERS);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(0))?;
+		// all approvers, except the last one, have already approved access
+		Module::<T>::set_key_approvers(
+			signed::<T>(&caller),
+			server_key_id(0),
+			approver_ids(MAX_KEY_APPROVERS),
+			MAX_KEY_APPROVERS as u8,
+		)?;
+		for approver in &approvers[..approvers.len() - 1] {
+			Module::<T>::approve_key_access(signed::<T>(approver), server_key_id(0), requester_id(u + 1))?;
+		}
+	}: _(RawOrigin::Signed(approvers[approvers.len() - 1].clone()), server_key_id(0), requester_id(u + 1))
+
+	revoke_key_access_approval {
+		let u in ...;
+		let caller = requester::<T>(0);
+		let approvers = approver_accounts::<T>(MAX_KEY_APPROVERS);
+		Module::<T>::claim_key(signed::<T>(&caller), server_key_id(0))?;
+		// all approvers have approved access and the last one is revoking approval
+		Module::<T>::set_key_approvers(
+			signed::<T>(&caller),
+			server_key_id(0),
+			approver_ids(MAX_KEY_APPROVERS),
+			MAX_KEY_APPROVERS as u8,
+		)?;
+		for approver in &approvers {
+			Module::<T>::approve_key_access(signed::<T>(approver), server_key_id(0), requester_id(u + 1))?;
+		}
+	}: _(RawOrigin::Signed(approvers[approvers.len() - 1].clone()), server_key_id(0), requester_id(u + 1))
+
+	generate_server_key {
+		let k in ...;
+		fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+	}: _(RawOrigin::Signed(caller), server_key_id(0), (k - 1) as u8)
+
+	server_key_generated {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// all key servers must agree on generated key && all key servers, except the last one,
+		// have already reported generated key
+		ServerKeyGenerationService::<T>::generate(signed::<T>(&caller), server_key_id(0), (k - 1) as u8)?;
+		for key_server in &key_servers[..k as usize - 1] {
+			ServerKeyGenerationService::<T>::on_generated(
+				signed::<T>(key_server),
+				server_key_id(0),
+				server_key_public(),
+			)?;
+		}
+	}: _(RawOrigin::Signed(key_servers[k as usize - 1].clone()), server_key_id(0), server_key_public())
+
+	server_key_generation_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		ServerKeyGenerationService::<T>::generate(signed::<T>(&caller), server_key_id(0), (k - 1) as u8)?;
+	}: _(RawOrigin::Signed(key_servers[0].clone()), server_key_id(0))
+
+	retrieve_server_key {
+		let u in ...;
+		set_fees::<T>();
+		let caller = requester::<T>(u);
+	}: _(RawOrigin::Signed(caller), server_key_id(u))
+
+	server_key_retrieved {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// all key servers are reporting the key with max threshold && all key servers, except
+		// the last one, have already reported the key
+		ServerKeyRetrievalService::<T>::retrieve(signed::<T>(&caller), server_key_id(0))?;
+		for key_server in &key_servers[..k as usize - 1] {
+			ServerKeyRetrievalService::<T>::on_retrieved(
+				signed::<T>(key_server),
+				server_key_id(0),
+				server_key_public(),
+				(k - 1) as u8,
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[k as usize - 1].clone()),
+		server_key_id(0),
+		server_key_public(),
+		(k - 1) as u8
+	)
+
+	server_key_retrieval_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// half of key servers have already reported error
+		ServerKeyRetrievalService::<T>::retrieve(signed::<T>(&caller), server_key_id(0))?;
+		for key_server in &key_servers[..k as usize / 2] {
+			ServerKeyRetrievalService::<T>::on_retrieval_error(signed::<T>(key_server), server_key_id(0))?;
+		}
+	}: _(RawOrigin::Signed(key_servers[k as usize / 2].clone()), server_key_id(0))
+
+	delete_server_key {
+		let u in ...;
+		set_fees::<T>();
+		let caller = requester::<T>(u);
+	}: _(RawOrigin::Signed(caller), server_key_id(u))
+
+	server_key_deleted {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// all key servers, except the last one, have already confirmed deletion
+		ServerKeyDeletionService::<T>::delete(signed::<T>(&caller), server_key_id(0))?;
+		for key_server in &key_servers[..k as usize - 1] {
+			ServerKeyDeletionService::<T>::on_deleted(signed::<T>(key_server), server_key_id(0))?;
+		}
+	}: _(RawOrigin::Signed(key_servers[k as usize - 1].clone()), server_key_id(0))
+
+	server_key_deletion_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		ServerKeyDeletionService::<T>::delete(signed::<T>(&caller), server_key_id(0))?;
+	}: _(RawOrigin::Signed(key_servers[0].clone()), server_key_id(0))
+
+	store_document_key {
+		let u in ...;
+		set_fees::<T>();
+		let caller = requester::<T>(u);
+	}: _(RawOrigin::Signed(caller), server_key_id(u), server_key_public(), server_key_public())
+
+	document_key_stored {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// all key servers, except the last one, have already confirmed store
+		DocumentKeyStoreService::<T>::store(
+			signed::<T>(&caller),
+			server_key_id(0),
+			server_key_public(),
+			server_key_public(),
+		)?;
+		for key_server in &key_servers[..k as usize - 1] {
+			DocumentKeyStoreService::<T>::on_stored(signed::<T>(key_server), server_key_id(0))?;
+		}
+	}: _(RawOrigin::Signed(key_servers[k as usize - 1].clone()), server_key_id(0))
+
+	document_key_store_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		DocumentKeyStoreService::<T>::store(
+			signed::<T>(&caller),
+			server_key_id(0),
+			server_key_public(),
+			server_key_public(),
+		)?;
+	}: _(RawOrigin::Signed(key_servers[0].clone()), server_key_id(0))
+
+	retrieve_document_key_shadow {
+		let u in ...;
+		set_fees::<T>();
+		let caller = shadow_requester::<T>();
+	}: _(RawOrigin::Signed(caller), server_key_id(u), shadow_requester_public())
+
+	document_key_common_retrieved {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = shadow_requester::<T>();
+		// half of key servers have already reported common data
+		DocumentKeyShadowRetrievalService::<T>::retrieve(
+			signed::<T>(&caller),
+			server_key_id(0),
+			shadow_requester_public(),
+		)?;
+		for key_server in &key_servers[..k as usize / 2] {
+			DocumentKeyShadowRetrievalService::<T>::on_common_retrieved(
+				signed::<T>(key_server),
+				server_key_id(0),
+				shadow_requester_id(),
+				server_key_public(),
+				0,
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[k as usize / 2].clone()),
+		server_key_id(0),
+		shadow_requester_id(),
+		server_key_public(),
+		0
+	)
+
+	document_key_personal_retrieved {
+		let k in ...;
+		let s in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = shadow_requester::<T>();
+		// common data is confirmed by the majority of key servers
+		DocumentKeyShadowRetrievalService::<T>::retrieve(
+			signed::<T>(&caller),
+			server_key_id(0),
+			shadow_requester_public(),
+		)?;
+		for key_server in &key_servers[..k as usize / 2 + 1] {
+			DocumentKeyShadowRetrievalService::<T>::on_common_retrieved(
+				signed::<T>(key_server),
+				server_key_id(0),
+				shadow_requester_id(),
+				server_key_public(),
+				0,
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[0].clone()),
+		server_key_id(0),
+		shadow_requester_id(),
+		KeyServersMask::from_index(0),
+		server_key_public(),
+		vec![0u8; s as usize]
+	)
+
+	document_key_shadow_retrieval_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = shadow_requester::<T>();
+		// half of key servers have already reported error
+		DocumentKeyShadowRetrievalService::<T>::retrieve(
+			signed::<T>(&caller),
+			server_key_id(0),
+			shadow_requester_public(),
+		)?;
+		for key_server in &key_servers[..k as usize / 2] {
+			DocumentKeyShadowRetrievalService::<T>::on_retrieval_error(
+				signed::<T>(key_server),
+				server_key_id(0),
+				shadow_requester_id(),
+			)?;
+		}
+	}: _(RawOrigin::Signed(key_servers[k as usize / 2].clone()), server_key_id(0), shadow_requester_id())
+
+	sign_message_schnorr {
+		let u in ...;
+		set_fees::<T>();
+		let caller = requester::<T>(u);
+	}: _(RawOrigin::Signed(caller), server_key_id(u), message())
+
+	schnorr_signature_generated {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// all key servers are reporting signature with max threshold && all key servers, except
+		// the last one, have already reported signature
+		SchnorrSigningService::<T>::sign(signed::<T>(&caller), server_key_id(0), message())?;
+		for key_server in &key_servers[..k as usize - 1] {
+			SchnorrSigningService::<T>::on_signed(
+				signed::<T>(key_server),
+				server_key_id(0),
+				requester_id(0),
+				message(),
+				message(),
+				message(),
+				(k - 1) as u8,
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[k as usize - 1].clone()),
+		server_key_id(0),
+		requester_id(0),
+		message(),
+		message(),
+		message(),
+		(k - 1) as u8
+	)
+
+	schnorr_signing_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// half of key servers have already reported error
+		SchnorrSigningService::<T>::sign(signed::<T>(&caller), server_key_id(0), message())?;
+		for key_server in &key_servers[..k as usize / 2] {
+			SchnorrSigningService::<T>::on_signing_error(
+				signed::<T>(key_server),
+				server_key_id(0),
+				requester_id(0),
+				message(),
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[k as usize / 2].clone()),
+		server_key_id(0),
+		requester_id(0),
+		message()
+	)
+
+	sign_message_ecdsa {
+		let u in ...;
+		set_fees::<T>();
+		let caller = requester::<T>(u);
+	}: _(RawOrigin::Signed(caller), server_key_id(u), message())
+
+	ecdsa_signature_generated {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// all key servers are reporting signature with max threshold && all key servers, except
+		// the last one, have already reported signature
+		EcdsaSigningService::<T>::sign(signed::<T>(&caller), server_key_id(0), message())?;
+		for key_server in &key_servers[..k as usize - 1] {
+			EcdsaSigningService::<T>::on_signed(
+				signed::<T>(key_server),
+				server_key_id(0),
+				requester_id(0),
+				message(),
+				sp_core::ecdsa::Signature::from_raw([0u8; 65]),
+				(k - 1) as u8,
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[k as usize - 1].clone()),
+		server_key_id(0),
+		requester_id(0),
+		message(),
+		sp_core::ecdsa::Signature::from_raw([0u8; 65]),
+		(k - 1) as u8
+	)
+
+	ecdsa_signing_error {
+		let k in ...;
+		let key_servers = fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// half of key servers have already reported error
+		EcdsaSigningService::<T>::sign(signed::<T>(&caller), server_key_id(0), message())?;
+		for key_server in &key_servers[..k as usize / 2] {
+			EcdsaSigningService::<T>::on_signing_error(
+				signed::<T>(key_server),
+				server_key_id(0),
+				requester_id(0),
+				message(),
+			)?;
+		}
+	}: _(
+		RawOrigin::Signed(key_servers[k as usize / 2].clone()),
+		server_key_id(0),
+		requester_id(0),
+		message()
+	)
+
+	expire_requests {
+		let k in ...;
+		// every service has its own queue && every queue holds a few requests only
+		let r in 0 .. MAX_EXPIRING_REQUESTS;
+		let e in 0 .. MAX_EXPIRING_REQUESTS;
+		fill_key_servers::<T>(k);
+		set_fees::<T>();
+		let caller = requester::<T>(0);
+		// first `e` requests have been created `RequestLifetime` blocks ago and have expired by now
+		for index in 0..e {
+			ServerKeyGenerationService::<T>::generate(signed::<T>(&caller), server_key_id(index), 0)?;
+		}
+		let now = frame_system::Module::<T>::block_number() + RequestLifetime::<T>::get();
+		frame_system::Module::<T>::set_block_number(now);
+		for index in e..e + r {
+			ServerKeyGenerationService::<T>::generate(signed::<T>(&caller), server_key_id(index), 0)?;
+		}
+	}: {
+		ServerKeyGenerationService::<T>::expire_requests(now);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use frame_benchmarking::BenchmarkingSetup;
+	use crate::mock::*;
+	use super::*;
+
+	/// Run benchmark with all components set to their lowest values and then with all
+	/// components set to their highest values.
+	fn run_benchmark(benchmark: SelectedBenchmark) {
+		let components = <SelectedBenchmark as BenchmarkingSetup<TestRuntime>>::components(&benchmark);
+		let lowest = components.iter().map(|(name, low, _)| (*name, *low)).collect::<Vec<_>>();
+		let highest = components.iter().map(|(name, _, high)| (*name, *high)).collect::<Vec<_>>();
+		for components in vec![lowest, highest] {
+			default_initialization().execute_with(|| {
+				let call = <SelectedBenchmark as BenchmarkingSetup<TestRuntime>>::instance(
+					&benchmark,
+					&components,
+				).unwrap();
+				call().unwrap();
+			});
+		}
+	}
+
+	#[test]
+	fn should_run_key_server_set_benchmarks() {
+		run_benchmark(SelectedBenchmark::change_owner);
+		run_benchmark(SelectedBenchmark::claim_id);
+		run_benchmark(SelectedBenchmark::complete_initialization);
+		run_benchmark(SelectedBenchmark::add_key_server);
+		run_benchmark(SelectedBenchmark::remove_key_server);
+		run_benchmark(SelectedBenchmark::start_migration);
+		run_benchmark(SelectedBenchmark::confirm_migration);
+		run_benchmark(SelectedBenchmark::set_request_lifetime);
+	}
+
+	#[test]
+	fn should_run_key_access_benchmarks() {
+		run_benchmark(SelectedBenchmark::claim_key);
+		run_benchmark(SelectedBenchmark::transfer_key);
+		run_benchmark(SelectedBenchmark::grant_key_access);
+		run_benchmark(SelectedBenchmark::revoke_key_access);
+		run_benchmark(SelectedBenchmark::set_key_access_policy);
+		run_benchmark(SelectedBenchmark::set_key_approvers);
+		run_benchmark(SelectedBenchmark::approve_key_access);
+		run_benchmark(SelectedBenchmark::revoke_key_access_approval);
+	}
+
+	#[test]
+	fn should_run_server_key_benchmarks() {
+		run_benchmark(SelectedBenchmark::generate_server_key);
+		run_benchmark(SelectedBenchmark::server_key_generated);
+		run_benchmark(SelectedBenchmark::server_key_generation_error);
+		run_benchmark(SelectedBenchmark::retrieve_server_key);
+		run_benchmark(SelectedBenchmark::server_key_retrieved);
+		run_benchmark(SelectedBenchmark::server_key_retrieval_error);
+		run_benchmark(SelectedBenchmark::delete_server_key);
+		run_benchmark(SelectedBenchmark::server_key_deleted);
+		run_benchmark(SelectedBenchmark::server_key_deletion_error);
+	}
+
+	#[test]
+	fn should_run_document_key_benchmarks() {
+		run_benchmark(SelectedBenchmark::store_document_key);
+		run_benchmark(SelectedBenchmark::document_key_stored);
+		run_benchmark(SelectedBenchmark::document_key_store_error);
+		run_benchmark(SelectedBenchmark::retrieve_document_key_shadow);
+		run_benchmark(SelectedBenchmark::document_key_common_retrieved);
+		run_benchmark(SelectedBenchmark::document_key_personal_retrieved);
+		run_benchmark(SelectedBenchmark::document_key_shadow_retrieval_error);
+	}
+
+	#[test]
+	fn should_run_signing_benchmarks() {
+		run_benchmark(SelectedBenchmark::sign_message_schnorr);
+		run_benchmark(SelectedBenchmark::schnorr_signature_generated);
+		run_benchmark(SelectedBenchmark::schnorr_signing_error);
+		run_benchmark(SelectedBenchmark::sign_message_ecdsa);
+		run_benchmark(SelectedBenchmark::ecdsa_signature_generated);
+		run_benchmark(SelectedBenchmark::ecdsa_signing_error);
+	}
+
+	#[test]
+	fn should_run_expire_requests_benchmark() {
+		run_benchmark(SelectedBenchmark::expire_requests);
+	}
+}
@@ -18,13 +18,14 @@
 //! for retrieving document keys shadows.
 
 use codec::{Encode, Decode};
-use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure};
+use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure, weights::Weight};
 use primitives::{EntityId, ServerKeyId, KeyServerId, KeyServersMask};
 use sp_std::vec::Vec;
 use frame_system::ensure_signed;
-use crate::service::{Responses, ResponseSupport, SecretStoreService};
+use crate::service::{Responses, ResponseSupport, RequestEscrow, SecretStoreService};
+use crate::weights::current_key_servers_count;
 use super::{
-	Trait, Module, Event,
+	Trait, Module, Event, WeightInfo,
 	DocumentKeyShadowRetrievalFee,
 	DocumentKeyShadowRetrievalRequestsKeys, DocumentKeyShadowRetrievalRequests,
 	DocumentKeyShadowRetrievalCommonResponses, DocumentKeyShadowRetrievalPersonalResponses,
@@ -36,7 +37,7 @@ const MAX_REQUESTS: u64 = 4;
 
 /// Structure that describes document key shadow retrieval request with responses meta.
 #[derive(Decode, Encode)]
-pub struct DocumentKeyShadowRetrievalRequest<Number> {
+pub struct DocumentKeyShadowRetrievalRequest<AccountId, Balance, Number> {
 	/// The author of this request. It must be the same author as in the
 	/// server key generation request.
 	pub requester: EntityId,
@@ -52,6 +53,8 @@ pub struct DocumentKeyShadowRetrievalRequest<Number> {
 	pub personal_retrieval_errors_mask: KeyServersMask,
 	/// Personal data: retrieval errors count.
 	pub personal_retrieval_errors_count: u8,
+	/// Reserved service fee.
+	pub escrow: RequestEscrow<AccountId, Balance, Number>,
 }
 
 /// Response from single key server from single decryption session.
@@ -103,9 +106,9 @@ impl<T: Trait> DocumentKeyShadowRetrievalService<T> {
 			"The same request is already queued",
 		);
 
-		// collect service fee
+		// reserve service fee
 		let fee = DocumentKeyShadowRetrievalFee::<T>::get();
-		SecretStoreService::<T>::collect_service_fee(&origin, fee)?;
+		let escrow = SecretStoreService::<T>::reserve_service_fee(&origin, fee)?;
 
 		// we do not know exact threshold value here && we can not blindly trust the first response
 		// => we should agree upon two values: threshold && document key itself
@@ -129,6 +132,7 @@ impl<T: Trait> DocumentKeyShadowRetrievalService<T> {
 			threshold: None,
 			personal_retrieval_errors_mask: Default::default(),
 			personal_retrieval_errors_count: 0,
+			escrow,
 		};
 		DocumentKeyShadowRetrievalRequests::<T>::insert(retrieval_id, request);
 		DocumentKeyShadowRetrievalRequestsKeys::append(sp_std::iter::once(&retrieval_id))?;
@@ -189,6 +193,7 @@ impl<T: Trait> DocumentKeyShadowRetrievalService<T> {
 			},
 			ResponseSupport::Impossible => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&(id, requester));
 
 				// emit event
@@ -243,25 +248,26 @@ impl<T: Trait> DocumentKeyShadowRetrievalService<T> {
 		personal_data.reported_count = personal_data.reported_count + 1;
 		personal_data.participants = personal_data_updated_participants;
 
-		// publish personal portion
-		Module::<T>::deposit_event(Event::DocumentKeyPersonalRetrieved(
-			id,
-			requester,
-			decrypted_secret,
-			shadow,
-		));
-
 		// check if we have published enough portions
 		if request.threshold != Some(personal_data.reported_count - 1) {
 			DocumentKeyShadowRetrievalPersonalResponses::insert(
 				&retrieval_id,
-				&(participants, decrypted_secret),
+				&(participants, decrypted_secret.clone()),
 				&personal_data,
 			);
 		} else {
+			SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 			delete_request::<T>(&retrieval_id);
 		}
 
+		// publish personal portion
+		Module::<T>::deposit_event(Event::DocumentKeyPersonalRetrieved(
+			id,
+			requester,
+			decrypted_secret,
+			shadow,
+		));
+
 		Ok(())
 	}
 
@@ -299,6 +305,7 @@ impl<T: Trait> DocumentKeyShadowRetrievalService<T> {
 			}
 
 			// delete request and fire event
+			SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 			delete_request::<T>(&retrieval_id);
 			Module::<T>::deposit_event(Event::DocumentKeyShadowRetrievalError(id, requester));
 			return Ok(());
@@ -331,11 +338,37 @@ impl<T: Trait> DocumentKeyShadowRetrievalService<T> {
 			return Ok(());
 		}
 
+		SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 		delete_request::<T>(&retrieval_id);
 		Module::<T>::deposit_event(Event::DocumentKeyShadowRetrievalError(id, requester));
 		Ok(())
 	}
 
+	/// Expire all requests that have not been completed until given block. Returns weight
+	/// of the operation.
+	pub fn expire_requests(now: <T as frame_system::Trait>::BlockNumber) -> Weight {
+		let mut active_requests = 0;
+		let mut expired_requests = 0;
+		for retrieval_id in DocumentKeyShadowRetrievalRequestsKeys::get() {
+			let request = match DocumentKeyShadowRetrievalRequests::<T>::get(&retrieval_id) {
+				Some(request) if request.escrow.expires_at <= now => request,
+				_ => {
+					active_requests += 1;
+					continue;
+				},
+			};
+			expired_requests += 1;
+
+			// return fee to the requester and fire event
+			SecretStoreService::<T>::refund_service_fee(&request.escrow, &request.common_responses);
+			delete_request::<T>(&retrieval_id);
+
+			Module::<T>::deposit_event(Event::DocumentKeyShadowRetrievalExpired(retrieval_id.0, retrieval_id.1));
+		}
+
+		T::WeightInfo::expire_requests(active_requests, expired_requests, current_key_servers_count::<T>())
+	}
+
 	/// Returs true if response from given keyServer is required.
 	pub fn is_response_required(
 		key_server: KeyServerId,
@@ -1434,4 +1467,38 @@ mod tests {
 			ensure_clean_storage([32; 32].into(), REAL_REQUESTER1_ADDRESS.into());
 		});
 	}
+
+	#[test]
+	fn should_expire_document_key_shadow_retrieval_request() {
+		default_initialization_with_five_servers().execute_with(|| {
+			// ask to retrieve document key shadow
+			DocumentKeyShadowRetrievalService::<TestRuntime>::retrieve(
+				Origin::signed(REAL_REQUESTER1),
+				[32; 32].into(),
+				REAL_REQUESTER1_PUBLIC.into(),
+			).unwrap();
+			let expires_at = frame_system::Module::<TestRuntime>::block_number() + REQUEST_LIFETIME;
+
+			// request is not expired before deadline
+			DocumentKeyShadowRetrievalService::<TestRuntime>::expire_requests(expires_at - 1);
+			assert_eq!(DocumentKeyShadowRetrievalRequestsKeys::get().len(), 1);
+
+			// request is expired at deadline
+			DocumentKeyShadowRetrievalService::<TestRuntime>::expire_requests(expires_at);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::DocumentKeyShadowRetrievalExpired(
+						[32; 32].into(),
+						REAL_REQUESTER1_ADDRESS.into(),
+					).into())
+					.is_some(),
+			);
+
+			// => nobody has responded, so the whole fee is returned to requester
+			assert_eq!(free_balance(REAL_REQUESTER1), 10_000_000);
+			assert_eq!(reserved_balance(REAL_REQUESTER1), 0);
+
+			ensure_clean_storage([32; 32].into(), REAL_REQUESTER1_ADDRESS.into());
+		});
+	}
 }
@@ -18,12 +18,13 @@
 //! for storing document keys.
 
 use codec::{Encode, Decode};
-use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure};
+use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure, weights::Weight};
 use primitives::{EntityId, ServerKeyId, KeyServerId};
 use frame_system::ensure_signed;
-use crate::service::{Responses, ResponseSupport, SecretStoreService};
+use crate::service::{Responses, ResponseSupport, RequestEscrow, SecretStoreService};
+use crate::weights::current_key_servers_count;
 use super::{
-	Trait, Module, Event,
+	Trait, Module, Event, WeightInfo,
 	DocumentKeyStoreFee,
 	DocumentKeyStoreRequests, DocumentKeyStoreRequestsKeys,
 	DocumentKeyStoreResponses,
@@ -35,7 +36,7 @@ const MAX_REQUESTS: u64 = 8;
 
 /// Structure that describes document key store request with responses meta.
 #[derive(Decode, Encode)]
-pub struct DocumentKeyStoreRequest<Number> {
+pub struct DocumentKeyStoreRequest<AccountId, Balance, Number> {
 	/// The author of this request. It must be the same author as in the
 	/// server key generation request.
 	pub author: EntityId,
@@ -45,6 +46,8 @@ pub struct DocumentKeyStoreRequest<Number> {
 	pub encrypted_point: sp_core::H512,
 	/// Responses metadata.
 	pub responses: Responses<Number>,
+	/// Reserved service fee.
+	pub escrow: RequestEscrow<AccountId, Balance, Number>,
 }
 
 /// Implementation of document key storing service.
@@ -70,18 +73,19 @@ impl<T: Trait> DocumentKeyStoreService<T> {
 			"The same request is already queued",
 		);
 
-		// collect service fee
+		// reserve service fee
 		let origin = ensure_signed(origin)?;
+		let author = resolve_entity_id::<T>(&origin)?;
 		let fee = DocumentKeyStoreFee::<T>::get();
-		SecretStoreService::<T>::collect_service_fee(&origin, fee)?;
+		let escrow = SecretStoreService::<T>::reserve_service_fee(&origin, fee)?;
 
 		// insert request to the queue
-		let author = resolve_entity_id::<T>(&origin)?;
 		let request = DocumentKeyStoreRequest {
 			author: author.clone(),
 			common_point: common_point.clone(),
 			encrypted_point: encrypted_point.clone(),
 			responses: SecretStoreService::<T>::new_responses(),
+			escrow,
 		};
 		DocumentKeyStoreRequests::<T>::insert(id, request);
 		DocumentKeyStoreRequestsKeys::append(sp_std::iter::once(&id))?;
@@ -121,6 +125,7 @@ impl<T: Trait> DocumentKeyStoreService<T> {
 			},
 			ResponseSupport::Confirmed => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&id);
 
 				// emit event
@@ -141,19 +146,45 @@ impl<T: Trait> DocumentKeyStoreService<T> {
 		let _ = SecretStoreService::<T>::key_server_index_from_origin(origin)?;
 
 		// check if this request is active (the tx could arrive when request is already inactive)
-		let _request = match DocumentKeyStoreRequests::<T>::get(id) {
+		let request = match DocumentKeyStoreRequests::<T>::get(id) {
 			Some(request) => request,
 			None => return Ok(()),
 		};
 
 		// any error in key generation is fatal, because we need all key servers to participate in generation
 		// => delete request and fire event
+		SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 		delete_request::<T>(&id);
 
 		Module::<T>::deposit_event(Event::DocumentKeyStoreError(id));
 		Ok(())
 	}
 
+	/// Expire all requests that have not been completed until given block. Returns weight
+	/// of the operation.
+	pub fn expire_requests(now: <T as frame_system::Trait>::BlockNumber) -> Weight {
+		let mut active_requests = 0;
+		let mut expired_requests = 0;
+		for id in DocumentKeyStoreRequestsKeys::get() {
+			let request = match DocumentKeyStoreRequests::<T>::get(&id) {
+				Some(request) if request.escrow.expires_at <= now => request,
+				_ => {
+					active_requests += 1;
+					continue;
+				},
+			};
+			expired_requests += 1;
+
+			// return fee to the requester and fire event
+			SecretStoreService::<T>::refund_service_fee(&request.escrow, &request.responses);
+			delete_request::<T>(&id);
+
+			Module::<T>::deposit_event(Event::DocumentKeyStoreExpired(id));
+		}
+
+		T::WeightInfo::expire_requests(active_requests, expired_requests, current_key_servers_count::<T>())
+	}
+
 	/// Returns true if response from given key server is required to complete request.
 	pub fn is_response_required(
 		key_server: KeyServerId,
@@ -547,4 +578,32 @@ mod tests {
 			));
 		});
 	}
+
+	#[test]
+	fn should_expire_document_key_store_request() {
+		default_initialization().execute_with(|| {
+			// ask to store document key
+			DocumentKeyStoreService::<TestRuntime>::store(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				[21; 64].into(),
+				[42; 64].into(),
+			).unwrap();
+			let expires_at = frame_system::Module::<TestRuntime>::block_number() + REQUEST_LIFETIME;
+
+			// request is expired at deadline
+			DocumentKeyStoreService::<TestRuntime>::expire_requests(expires_at);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::DocumentKeyStoreExpired([32; 32].into()).into())
+					.is_some(),
+			);
+
+			// => nobody has responded, so the whole fee is returned to requester
+			assert_eq!(free_balance(REQUESTER1), 10_000_000);
+			assert_eq!(reserved_balance(REQUESTER1), 0);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
 }
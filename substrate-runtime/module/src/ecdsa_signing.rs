@@ -0,0 +1,702 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Contains actual implementation of all public/private module methods
+//! for ECDSA signing of messages.
+
+use codec::{Encode, Decode};
+use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure, weights::Weight};
+use primitives::{EntityId, ServerKeyId, KeyServerId, KeyServersMask};
+use frame_system::ensure_signed;
+use crate::service::{Responses, ResponseSupport, RequestEscrow, SecretStoreService};
+use crate::weights::current_key_servers_count;
+use super::{
+	Trait, Module, Event, WeightInfo,
+	EcdsaSigningFee,
+	EcdsaSigningRequests, EcdsaSigningRequestsKeys,
+	EcdsaSigningResponses,
+	BalanceOf, resolve_entity_id,
+};
+
+/// Maximal number of active requests in the queue.
+const MAX_REQUESTS: u64 = 4;
+
+/// Invalid threshold.
+const INVALID_THRESHOLD: u8 = 0xFF;
+
+/// Structure that describes ECDSA signing request with responses meta.
+#[derive(Decode, Encode)]
+pub struct EcdsaSigningRequest<AccountId, Balance, Number> {
+	/// Key threshold responses metadata.
+	pub responses: Responses<Number>,
+	/// Key threshold that key servers have agreed upon. If it is None, then
+	/// key servers have not yet agreed upon threshold.
+	pub threshold: Option<u8>,
+	/// Number of key servers that have reported signature.
+	pub signatures_count: u8,
+	/// Number of key servers that have reported error.
+	pub errors_count: u8,
+	/// Reserved service fee.
+	pub escrow: RequestEscrow<AccountId, Balance, Number>,
+}
+
+/// Implementation of ECDSA signing service.
+pub struct EcdsaSigningService<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Trait> EcdsaSigningService<T> {
+	/// Request new ECDSA signature of the message. Signature will be published via
+	/// EcdsaSigned event when available.
+	pub fn sign(
+		origin: T::Origin,
+		id: ServerKeyId,
+		message: sp_core::H256,
+	) -> Result<(), &'static str> {
+		// limit number of requests in the queue
+		ensure!(
+			(EcdsaSigningRequestsKeys::decode_len()? as u64) < MAX_REQUESTS,
+			"Too many active requests. Try later",
+		);
+
+		// the request ID here is the concat of key id, requester id and message hash
+		let origin = ensure_signed(origin)?;
+		let requester = resolve_entity_id::<T>(&origin)?;
+		let signing_id = (id, requester, message);
+
+		// check if there are no pending request for the same message
+		ensure!(
+			!EcdsaSigningRequests::<T>::contains_key(signing_id),
+			"The same request is already queued",
+		);
+
+		// reserve service fee
+		let fee = EcdsaSigningFee::<T>::get();
+		let escrow = SecretStoreService::<T>::reserve_service_fee(&origin, fee)?;
+
+		// insert request to the queue
+		let request = EcdsaSigningRequest {
+			responses: SecretStoreService::<T>::new_responses(),
+			threshold: None,
+			signatures_count: 0,
+			errors_count: 0,
+			escrow,
+		};
+		EcdsaSigningRequests::<T>::insert(signing_id, request);
+		EcdsaSigningRequestsKeys::append(sp_std::iter::once(&signing_id))?;
+
+		// emit event
+		Module::<T>::deposit_event(Event::EcdsaSigningRequested(id, requester, message));
+
+		Ok(())
+	}
+
+	/// Called when signature is reported by key server.
+	pub fn on_signed(
+		origin: T::Origin,
+		id: ServerKeyId,
+		requester: EntityId,
+		message: sp_core::H256,
+		signature: sp_core::ecdsa::Signature,
+		threshold: u8,
+	) -> Result<(), &'static str> {
+		// check if this request is active (the tx could arrive when request is already inactive)
+		let signing_id = (id, requester, message);
+		let request = match EcdsaSigningRequests::<T>::get(signing_id) {
+			Some(request) => request,
+			None => return Ok(()),
+		};
+
+		// every key server is running its own signing session and signatures, computed in
+		// different sessions, are different (but valid). We can't trust the single key server, so:
+		// 1) at least 50% + 1 authorities must agree on the same key threshold value
+		// 2) every reported signature is published, so that requester could verify it
+		// 3) request is completed when threshold + 1 signatures are published
+		let key_servers_count = SecretStoreService::<T>::key_servers_count()?;
+		let mut request = match Self::insert_response(origin, &signing_id, request, threshold)? {
+			Some(request) => request,
+			None => return Ok(()),
+		};
+		request.signatures_count = request.signatures_count + 1;
+
+		// emit event
+		Module::<T>::deposit_event(Event::EcdsaSigned(id, requester, message, signature));
+
+		Self::complete_request(signing_id, request, key_servers_count)
+	}
+
+	/// Called when error occurs during ECDSA signing.
+	pub fn on_signing_error(
+		origin: T::Origin,
+		id: ServerKeyId,
+		requester: EntityId,
+		message: sp_core::H256,
+	) -> Result<(), &'static str> {
+		// check if this request is active (the tx could arrive when request is already inactive)
+		let signing_id = (id, requester, message);
+		let request = match EcdsaSigningRequests::<T>::get(signing_id) {
+			Some(request) => request,
+			None => return Ok(()),
+		};
+
+		// error is treated like a voting for invalid key threshold
+		// => insert invalid response and check if there are enough errors
+		let key_servers_count = SecretStoreService::<T>::key_servers_count()?;
+		let mut request = match Self::insert_response(origin, &signing_id, request, INVALID_THRESHOLD)? {
+			Some(request) => request,
+			None => return Ok(()),
+		};
+		request.errors_count = request.errors_count + 1;
+
+		Self::complete_request(signing_id, request, key_servers_count)
+	}
+
+	/// Expire all requests that have not been completed until given block. Returns weight
+	/// of the operation.
+	pub fn expire_requests(now: <T as frame_system::Trait>::BlockNumber) -> Weight {
+		let mut active_requests = 0;
+		let mut expired_requests = 0;
+		for signing_id in EcdsaSigningRequestsKeys::get() {
+			let request = match EcdsaSigningRequests::<T>::get(&signing_id) {
+				Some(request) if request.escrow.expires_at <= now => request,
+				_ => {
+					active_requests += 1;
+					continue;
+				},
+			};
+			expired_requests += 1;
+
+			// return fee to the requester and fire event
+			SecretStoreService::<T>::refund_service_fee(&request.escrow, &request.responses);
+			delete_request::<T>(&signing_id);
+
+			Module::<T>::deposit_event(Event::EcdsaSigningExpired(signing_id.0, signing_id.1, signing_id.2));
+		}
+
+		T::WeightInfo::expire_requests(active_requests, expired_requests, current_key_servers_count::<T>())
+	}
+
+	/// Returns true if response from given key server is required to complete request.
+	pub fn is_response_required(
+		key_server: KeyServerId,
+		id: ServerKeyId,
+		requester: EntityId,
+		message: sp_core::H256,
+	) -> bool {
+		EcdsaSigningRequests::<T>::get(&(id, requester, message))
+			.map(|request| SecretStoreService::<T>::is_response_required(
+				key_server,
+				&request.responses,
+			))
+			.unwrap_or(false)
+	}
+
+	/// Insert key threshold response of the key server. Returns None if key server has
+	/// already responded.
+	fn insert_response(
+		origin: T::Origin,
+		signing_id: &(ServerKeyId, EntityId, sp_core::H256),
+		mut request: EcdsaSigningRequestOf<T>,
+		threshold: u8,
+	) -> Result<Option<EcdsaSigningRequestOf<T>>, &'static str> {
+		// every key server could respond only once
+		let key_server_index = SecretStoreService::<T>::key_server_index_from_origin(origin)?;
+		let key_server_mask = KeyServersMask::from_index(key_server_index);
+		let responded_key_servers_mask = request.responses.responded_key_servers_mask;
+		if responded_key_servers_mask.union(key_server_mask) == responded_key_servers_mask {
+			return Ok(None);
+		}
+
+		// insert threshold response
+		let key_servers_count = SecretStoreService::<T>::key_servers_count()?;
+		let threshold_support = SecretStoreService::<T>::insert_response::<_, _, EcdsaSigningResponses>(
+			key_server_index,
+			key_servers_count / 2,
+			&mut request.responses,
+			signing_id,
+			&threshold,
+		)?;
+		match threshold_support {
+			ResponseSupport::Confirmed if request.threshold.is_none() =>
+				request.threshold = Some(threshold),
+			ResponseSupport::Impossible =>
+				request.threshold = Some(INVALID_THRESHOLD),
+			_ => (),
+		}
+
+		Ok(Some(request))
+	}
+
+	/// Completes request if there are enough signatures or errors. Otherwise saves updated request.
+	fn complete_request(
+		signing_id: (ServerKeyId, EntityId, sp_core::H256),
+		request: EcdsaSigningRequestOf<T>,
+		key_servers_count: u8,
+	) -> Result<(), &'static str> {
+		let is_failed = match request.threshold {
+			// key servers have not yet agreed upon key threshold
+			None => {
+				EcdsaSigningRequests::<T>::insert(signing_id, request);
+				return Ok(());
+			},
+			// key servers have agreed that key can't be used for signing
+			Some(INVALID_THRESHOLD) => true,
+			// threshold + 1 signatures have been published
+			Some(threshold) if request.signatures_count > threshold => false,
+			// it is impossible to receive threshold + 1 signatures
+			Some(threshold) if request.errors_count > key_servers_count.saturating_sub(threshold + 1) => true,
+			// waiting for more responses
+			Some(_) => {
+				EcdsaSigningRequests::<T>::insert(signing_id, request);
+				return Ok(());
+			},
+		};
+
+		// we do not need this request anymore
+		SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
+		delete_request::<T>(&signing_id);
+
+		// emit event
+		if is_failed {
+			Module::<T>::deposit_event(Event::EcdsaSigningError(signing_id.0, signing_id.1, signing_id.2));
+		}
+
+		Ok(())
+	}
+}
+
+/// ECDSA signing request of given runtime.
+type EcdsaSigningRequestOf<T> = EcdsaSigningRequest<
+	<T as frame_system::Trait>::AccountId,
+	BalanceOf<T>,
+	<T as frame_system::Trait>::BlockNumber,
+>;
+
+/// Deletes request and all associated data.
+fn delete_request<T: Trait>(request: &(ServerKeyId, EntityId, sp_core::H256)) {
+	EcdsaSigningResponses::remove_prefix(request);
+	EcdsaSigningRequests::<T>::remove(request);
+	EcdsaSigningRequestsKeys::mutate(|list| {
+		let index = list.iter().position(|lrequest| lrequest == request);
+		if let Some(index) = index {
+			list.swap_remove(index);
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::mock::*;
+	use super::*;
+
+	const MESSAGE: [u8; 32] = [77; 32];
+
+	fn requester() -> EntityId {
+		[REQUESTER1 as u8; 20].into()
+	}
+
+	fn signature() -> sp_core::ecdsa::Signature {
+		sp_core::ecdsa::Signature::from_raw([42; 65])
+	}
+
+	fn other_signature() -> sp_core::ecdsa::Signature {
+		sp_core::ecdsa::Signature::from_raw([44; 65])
+	}
+
+	fn ensure_clean_storage(key: ServerKeyId) {
+		let signing_id = (key, requester(), MESSAGE.into());
+		assert_eq!(EcdsaSigningRequestsKeys::get(), vec![]);
+		assert!(!EcdsaSigningRequests::<TestRuntime>::contains_key(signing_id));
+		assert_eq!(
+			EcdsaSigningResponses::iter_prefix(signing_id).collect::<Vec<_>>(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn should_accept_ecdsa_signing_request() {
+		default_initialization().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+
+			// check that event has been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::EcdsaSigningRequested(
+						[32; 32].into(),
+						requester(),
+						MESSAGE.into(),
+					).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_reject_ecdsa_signing_request_when_fee_is_not_paid() {
+		default_initialization().execute_with(|| {
+			// REQUESTER2 has no enough funds
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER2),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap_err();
+
+			assert_eq!(EcdsaSigningRequestsKeys::get(), vec![]);
+		});
+	}
+
+	#[test]
+	fn should_reject_ecdsa_signing_request_when_limit_reached() {
+		default_initialization().execute_with(|| {
+			// make MAX_REQUESTS requests
+			for i in 0..MAX_REQUESTS {
+				EcdsaSigningService::<TestRuntime>::sign(
+					Origin::signed(REQUESTER1),
+					[i as u8; 32].into(),
+					MESSAGE.into(),
+				).unwrap();
+			}
+
+			// and now try to push new request so that there will be more than a limit requests
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[MAX_REQUESTS as u8; 32].into(),
+				MESSAGE.into(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_reject_duplicated_ecdsa_signing_request() {
+		default_initialization().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+
+			// ask to sign the same message again
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_publish_ecdsa_signature() {
+		default_initialization().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+			let events_count = frame_system::Module::<TestRuntime>::events().len();
+
+			// signature from key server 1 is received
+			EcdsaSigningService::<TestRuntime>::on_signed(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+				signature(),
+				1,
+			).unwrap();
+
+			// => signature is published, but request is still active
+			assert_eq!(
+				events_count + 1,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::EcdsaSigned(
+						[32; 32].into(),
+						requester(),
+						MESSAGE.into(),
+						signature(),
+					).into())
+					.is_some(),
+			);
+			assert!(EcdsaSigningRequests::<TestRuntime>::contains_key((
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			)));
+
+			// the same signature from key server 1 is ignored
+			EcdsaSigningService::<TestRuntime>::on_signed(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+				signature(),
+				1,
+			).unwrap();
+			assert_eq!(
+				events_count + 1,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+
+			// and then signature from key server 2 is received
+			EcdsaSigningService::<TestRuntime>::on_signed(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+				other_signature(),
+				1,
+			).unwrap();
+
+			// => threshold + 1 signatures are published and request is completed
+			assert_eq!(
+				events_count + 2,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::EcdsaSigned(
+						[32; 32].into(),
+						requester(),
+						MESSAGE.into(),
+						other_signature(),
+					).into())
+					.is_some(),
+			);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_not_accept_ecdsa_signature_from_non_key_server() {
+		default_initialization().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+
+			// response from non key server is received
+			EcdsaSigningService::<TestRuntime>::on_signed(
+				Origin::signed(KEY_SERVER3),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+				signature(),
+				1,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_raise_ecdsa_signing_error_when_key_server_reports_error() {
+		default_initialization_with_three_servers().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+			let events_count = frame_system::Module::<TestRuntime>::events().len();
+
+			// error from key server 1 is received
+			EcdsaSigningService::<TestRuntime>::on_signing_error(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			).unwrap();
+
+			// => single key server can't reject request
+			assert_eq!(
+				events_count,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+
+			// error from key server 2 is received
+			EcdsaSigningService::<TestRuntime>::on_signing_error(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			).unwrap();
+
+			// check that signing error is published
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::EcdsaSigningError(
+						[32; 32].into(),
+						requester(),
+						MESSAGE.into(),
+					).into())
+					.is_some(),
+			);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_raise_ecdsa_signing_error_when_threshold_signatures_are_impossible() {
+		default_initialization_with_five_servers().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+
+			// 3 key servers agree that key threshold is 3
+			for key_server in &[KEY_SERVER0, KEY_SERVER1, KEY_SERVER2] {
+				EcdsaSigningService::<TestRuntime>::on_signed(
+					Origin::signed(*key_server),
+					[32; 32].into(),
+					requester(),
+					MESSAGE.into(),
+					sp_core::ecdsa::Signature::from_raw([*key_server as u8; 65]),
+					3,
+				).unwrap();
+			}
+
+			// error from key server 4 is received => 4th signature is still possible
+			EcdsaSigningService::<TestRuntime>::on_signing_error(
+				Origin::signed(KEY_SERVER3),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			).unwrap();
+			assert!(EcdsaSigningRequests::<TestRuntime>::contains_key((
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			)));
+
+			// error from key server 5 is received => 4th signature is impossible
+			EcdsaSigningService::<TestRuntime>::on_signing_error(
+				Origin::signed(KEY_SERVER4),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			).unwrap();
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::EcdsaSigningError(
+						[32; 32].into(),
+						requester(),
+						MESSAGE.into(),
+					).into())
+					.is_some(),
+			);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_require_response_until_ecdsa_signature_is_published() {
+		default_initialization().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+			assert!(EcdsaSigningService::<TestRuntime>::is_response_required(
+				KEY_SERVER0_ID.into(),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			));
+
+			// signature from key server 1 is received
+			EcdsaSigningService::<TestRuntime>::on_signed(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+				signature(),
+				1,
+			).unwrap();
+			assert!(!EcdsaSigningService::<TestRuntime>::is_response_required(
+				KEY_SERVER0_ID.into(),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			));
+			assert!(EcdsaSigningService::<TestRuntime>::is_response_required(
+				KEY_SERVER1_ID.into(),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			));
+
+			// signature from key server 2 is received
+			EcdsaSigningService::<TestRuntime>::on_signed(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+				other_signature(),
+				1,
+			).unwrap();
+			assert!(!EcdsaSigningService::<TestRuntime>::is_response_required(
+				KEY_SERVER1_ID.into(),
+				[32; 32].into(),
+				requester(),
+				MESSAGE.into(),
+			));
+		});
+	}
+
+	#[test]
+	fn should_expire_ecdsa_signing_request() {
+		default_initialization().execute_with(|| {
+			// ask to sign message
+			EcdsaSigningService::<TestRuntime>::sign(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				MESSAGE.into(),
+			).unwrap();
+			let expires_at = frame_system::Module::<TestRuntime>::block_number() + REQUEST_LIFETIME;
+
+			// request is expired at deadline
+			EcdsaSigningService::<TestRuntime>::expire_requests(expires_at);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::EcdsaSigningExpired(
+						[32; 32].into(),
+						requester(),
+						MESSAGE.into(),
+					).into())
+					.is_some(),
+			);
+
+			// => nobody has responded, so the whole fee is returned to requester
+			assert_eq!(free_balance(REQUESTER1), 10_000_000);
+			assert_eq!(reserved_balance(REQUESTER1), 0);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+}
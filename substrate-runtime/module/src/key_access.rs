@@ -0,0 +1,767 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Contains actual implementation of all public/private module methods
+//! for managing access to keys.
+//!
+//! Access to every key is checked by one of key access policies. By default,
+//! the policy that is configured in the runtime (`Trait::KeyAccessPolicy`) is
+//! used. Key owner may select one of stock policies for the key instead.
+
+use codec::{Encode, Decode};
+use frame_support::{StorageMap, StorageDoubleMap, ensure};
+use primitives::{EntityId, ServerKeyId, acl_storage::KeyAccessLevel};
+use frame_system::ensure_signed;
+use sp_runtime::RuntimeDebug;
+use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
+use super::{
+	Trait, Module, Event,
+	KeyOwners, KeyAccessGrants, KeyAccessPolicies, KeyApprovers, KeyAccessApprovals,
+	resolve_entity_id,
+};
+
+/// Maximal number of entities that are allowed to approve access to the single key.
+pub const MAX_KEY_APPROVERS: usize = 255;
+
+/// Access to the key, granted by one of key owners.
+#[derive(Decode, Encode)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct KeyAccessGrant<Number> {
+	/// Granted access level.
+	pub level: KeyAccessLevel,
+	/// Number of block when this grant expires. If None, grant never expires.
+	pub expires_at: Option<Number>,
+}
+
+/// Policy that is used to check access to the key.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub enum KeyAccessPolicyKind {
+	/// The policy that is configured in the runtime (see `Trait::KeyAccessPolicy`).
+	Runtime,
+	/// Only key owners have access to the key (see `OwnerOnlyPolicy`).
+	OwnerOnly,
+	/// Key owners and grantees have access to the key (see `AllowListPolicy`).
+	AllowList,
+	/// Key owners and entities that are approved by key approvers have access
+	/// to the key (see `MultiApprovalPolicy`).
+	MultiApproval,
+}
+
+impl Default for KeyAccessPolicyKind {
+	fn default() -> Self {
+		KeyAccessPolicyKind::Runtime
+	}
+}
+
+/// Entities that are approving access to the key.
+#[derive(Decode, Encode)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct KeyApproversSet {
+	/// Entities that are allowed to approve access to the key.
+	pub approvers: Vec<EntityId>,
+	/// Number of approvals that is required to access the key.
+	pub threshold: u8,
+}
+
+/// Key access policy.
+pub trait KeyAccessPolicy<T: Trait> {
+	/// Check if requester has given level of access to the key.
+	fn check(id: ServerKeyId, requester: EntityId, level: KeyAccessLevel) -> bool;
+}
+
+/// Policy that only gives access to key owners.
+pub struct OwnerOnlyPolicy;
+
+impl<T: Trait> KeyAccessPolicy<T> for OwnerOnlyPolicy {
+	fn check(id: ServerKeyId, requester: EntityId, _level: KeyAccessLevel) -> bool {
+		KeyOwners::get(&id).contains(&requester)
+	}
+}
+
+/// Policy that gives full access to key owners and access of granted level to
+/// entities that have active grants (see `KeyAccessGrants`).
+pub struct AllowListPolicy;
+
+impl<T: Trait> KeyAccessPolicy<T> for AllowListPolicy {
+	fn check(id: ServerKeyId, requester: EntityId, level: KeyAccessLevel) -> bool {
+		if KeyOwners::get(&id).contains(&requester) {
+			return true;
+		}
+
+		match KeyAccessGrants::<T>::get(&id, &requester) {
+			Some(grant) => grant.level.allows(level) && grant.expires_at
+				.map(|expires_at| expires_at > frame_system::Module::<T>::block_number())
+				.unwrap_or(true),
+			None => false,
+		}
+	}
+}
+
+/// Policy that gives full access to key owners and to entities that have been
+/// approved by at least `threshold` of key approvers (see `KeyApprovers`).
+pub struct MultiApprovalPolicy;
+
+impl<T: Trait> KeyAccessPolicy<T> for MultiApprovalPolicy {
+	fn check(id: ServerKeyId, requester: EntityId, _level: KeyAccessLevel) -> bool {
+		if KeyOwners::get(&id).contains(&requester) {
+			return true;
+		}
+
+		// approvals are cleared when approvers set is changed => we only need to count them
+		match KeyApprovers::get(&id) {
+			Some(approvers) => KeyAccessApprovals::get(&id, &requester).len() >= approvers.threshold as usize,
+			None => false,
+		}
+	}
+}
+
+/// Implementation of key access management.
+pub struct KeyAccessService<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Trait> KeyAccessService<T> {
+	/// Grant access to the key to given entity. Existing grant of the same entity
+	/// is replaced.
+	pub fn grant(
+		origin: T::Origin,
+		id: ServerKeyId,
+		grantee: EntityId,
+		level: KeyAccessLevel,
+		expires_at: Option<<T as frame_system::Trait>::BlockNumber>,
+	) -> Result<(), &'static str> {
+		let origin = ensure_signed(origin)?;
+		let origin = resolve_entity_id::<T>(&origin)?;
+		let owners = KeyOwners::get(&id);
+		ensure!(
+			owners.contains(&origin),
+			"You're not owner of the key",
+		);
+		ensure!(
+			!owners.contains(&grantee),
+			"Grantee is already owner of the key",
+		);
+		if let Some(expires_at) = expires_at {
+			ensure!(
+				expires_at > frame_system::Module::<T>::block_number(),
+				"Grant expiration block is in the past",
+			);
+		}
+
+		KeyAccessGrants::<T>::insert(id, grantee, KeyAccessGrant {
+			level,
+			expires_at,
+		});
+
+		Module::<T>::deposit_event(Event::KeyAccessGranted(id, grantee, level));
+
+		Ok(())
+	}
+
+	/// Revoke access to the key from given entity.
+	pub fn revoke(
+		origin: T::Origin,
+		id: ServerKeyId,
+		grantee: EntityId,
+	) -> Result<(), &'static str> {
+		let origin = ensure_signed(origin)?;
+		let origin = resolve_entity_id::<T>(&origin)?;
+		ensure!(
+			KeyOwners::get(&id).contains(&origin),
+			"You're not owner of the key",
+		);
+		ensure!(
+			KeyAccessGrants::<T>::contains_key(&id, &grantee),
+			"Access to the key has not been granted",
+		);
+
+		KeyAccessGrants::<T>::remove(id, grantee);
+
+		Module::<T>::deposit_event(Event::KeyAccessRevoked(id, grantee));
+
+		Ok(())
+	}
+
+	/// Select policy that will be used to check access to the key.
+	pub fn set_policy(
+		origin: T::Origin,
+		id: ServerKeyId,
+		policy: KeyAccessPolicyKind,
+	) -> Result<(), &'static str> {
+		let origin = ensure_signed(origin)?;
+		let origin = resolve_entity_id::<T>(&origin)?;
+		ensure!(
+			KeyOwners::get(&id).contains(&origin),
+			"You're not owner of the key",
+		);
+
+		KeyAccessPolicies::insert(id, policy);
+
+		Module::<T>::deposit_event(Event::KeyAccessPolicySelected(id, policy));
+
+		Ok(())
+	}
+
+	/// Set entities that are approving access to the key. All existing approvals
+	/// are discarded.
+	pub fn set_approvers(
+		origin: T::Origin,
+		id: ServerKeyId,
+		approvers: Vec<EntityId>,
+		threshold: u8,
+	) -> Result<(), &'static str> {
+		let origin = ensure_signed(origin)?;
+		let origin = resolve_entity_id::<T>(&origin)?;
+		ensure!(
+			KeyOwners::get(&id).contains(&origin),
+			"You're not owner of the key",
+		);
+		ensure!(
+			threshold != 0,
+			"Approval threshold must be positive",
+		);
+		ensure!(
+			approvers.len() >= threshold as usize,
+			"Not enough approvers to reach the threshold",
+		);
+		ensure!(
+			approvers.len() <= MAX_KEY_APPROVERS,
+			"Too many approvers",
+		);
+		ensure!(
+			approvers.iter().collect::<BTreeSet<_>>().len() == approvers.len(),
+			"Duplicate approvers",
+		);
+
+		KeyApprovers::insert(id, KeyApproversSet {
+			approvers: approvers.clone(),
+			threshold,
+		});
+		KeyAccessApprovals::remove_prefix(id);
+
+		Module::<T>::deposit_event(Event::KeyApproversSet(id, approvers, threshold));
+
+		Ok(())
+	}
+
+	/// Approve access to the key to given entity.
+	pub fn approve(
+		origin: T::Origin,
+		id: ServerKeyId,
+		requester: EntityId,
+	) -> Result<(), &'static str> {
+		let origin = ensure_signed(origin)?;
+		let origin = resolve_entity_id::<T>(&origin)?;
+		let approvers = KeyApprovers::get(&id).ok_or("Key has no approvers")?;
+		ensure!(
+			approvers.approvers.contains(&origin),
+			"You're not approver of the key",
+		);
+
+		let mut approvals = KeyAccessApprovals::get(&id, &requester);
+		ensure!(
+			!approvals.contains(&origin),
+			"Access to the key has already been approved",
+		);
+
+		approvals.push(origin);
+		KeyAccessApprovals::insert(id, requester, approvals);
+
+		Module::<T>::deposit_event(Event::KeyAccessApproved(id, requester, origin));
+
+		Ok(())
+	}
+
+	/// Revoke previously given approval of access to the key.
+	pub fn revoke_approval(
+		origin: T::Origin,
+		id: ServerKeyId,
+		requester: EntityId,
+	) -> Result<(), &'static str> {
+		let origin = ensure_signed(origin)?;
+		let origin = resolve_entity_id::<T>(&origin)?;
+		let mut approvals = KeyAccessApprovals::get(&id, &requester);
+		ensure!(
+			approvals.contains(&origin),
+			"Access to the key has not been approved",
+		);
+
+		approvals.retain(|approver| *approver != origin);
+		if approvals.is_empty() {
+			KeyAccessApprovals::remove(id, requester);
+		} else {
+			KeyAccessApprovals::insert(id, requester, approvals);
+		}
+
+		Module::<T>::deposit_event(Event::KeyAccessApprovalRevoked(id, requester, origin));
+
+		Ok(())
+	}
+
+	/// Check if requester has given level of access to the key, using policy
+	/// that is selected for this key.
+	pub fn check(
+		id: ServerKeyId,
+		requester: EntityId,
+		level: KeyAccessLevel,
+	) -> bool {
+		match KeyAccessPolicies::get(&id) {
+			KeyAccessPolicyKind::Runtime =>
+				<T::KeyAccessPolicy as KeyAccessPolicy<T>>::check(id, requester, level),
+			KeyAccessPolicyKind::OwnerOnly =>
+				<OwnerOnlyPolicy as KeyAccessPolicy<T>>::check(id, requester, level),
+			KeyAccessPolicyKind::AllowList =>
+				<AllowListPolicy as KeyAccessPolicy<T>>::check(id, requester, level),
+			KeyAccessPolicyKind::MultiApproval =>
+				<MultiApprovalPolicy as KeyAccessPolicy<T>>::check(id, requester, level),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::mock::*;
+	use crate::Module;
+	use super::*;
+
+	const KEY: [u8; 32] = [1u8; 32];
+
+	fn owner() -> EntityId {
+		[REQUESTER1 as u8; 20].into()
+	}
+
+	fn grantee() -> EntityId {
+		REAL_REQUESTER1_ADDRESS.into()
+	}
+
+	fn claim_key() {
+		Module::<TestRuntime>::claim_key(
+			Origin::signed(REQUESTER1),
+			KEY.into(),
+		).unwrap();
+	}
+
+	fn grant(level: KeyAccessLevel, expires_at: Option<u64>) -> Result<(), &'static str> {
+		KeyAccessService::<TestRuntime>::grant(
+			Origin::signed(REQUESTER1),
+			KEY.into(),
+			grantee(),
+			level,
+			expires_at,
+		)
+	}
+
+	fn check(requester: EntityId, level: KeyAccessLevel) -> bool {
+		KeyAccessService::<TestRuntime>::check(KEY.into(), requester, level)
+	}
+
+	#[test]
+	fn should_grant_full_access_to_key_owner() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+
+			assert!(check(owner(), KeyAccessLevel::Read));
+			assert!(check(owner(), KeyAccessLevel::Decrypt));
+			assert!(!check(grantee(), KeyAccessLevel::Read));
+		});
+	}
+
+	#[test]
+	fn should_allow_grant_read_access() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Read, None).unwrap();
+
+			assert!(check(grantee(), KeyAccessLevel::Read));
+			assert!(!check(grantee(), KeyAccessLevel::Decrypt));
+
+			// check that event has been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::KeyAccessGranted(
+						KEY.into(),
+						grantee(),
+						KeyAccessLevel::Read,
+					).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_allow_grant_decrypt_access() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, None).unwrap();
+
+			assert!(check(grantee(), KeyAccessLevel::Read));
+			assert!(check(grantee(), KeyAccessLevel::Decrypt));
+		});
+	}
+
+	#[test]
+	fn should_replace_existing_grant() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, None).unwrap();
+			grant(KeyAccessLevel::Read, None).unwrap();
+
+			assert_eq!(
+				KeyAccessGrants::<TestRuntime>::get(ServerKeyId::from(KEY), grantee()),
+				Some(KeyAccessGrant { level: KeyAccessLevel::Read, expires_at: None }),
+			);
+			assert!(!check(grantee(), KeyAccessLevel::Decrypt));
+		});
+	}
+
+	#[test]
+	fn should_forbid_grant_access_by_non_owner() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+
+			KeyAccessService::<TestRuntime>::grant(
+				Origin::signed(REAL_REQUESTER1),
+				KEY.into(),
+				REAL_REQUESTER2_ADDRESS.into(),
+				KeyAccessLevel::Decrypt,
+				None,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_forbid_grant_access_by_grantee() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, None).unwrap();
+
+			KeyAccessService::<TestRuntime>::grant(
+				Origin::signed(REAL_REQUESTER1),
+				KEY.into(),
+				REAL_REQUESTER2_ADDRESS.into(),
+				KeyAccessLevel::Decrypt,
+				None,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_forbid_grant_access_to_owner() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+
+			KeyAccessService::<TestRuntime>::grant(
+				Origin::signed(REQUESTER1),
+				KEY.into(),
+				owner(),
+				KeyAccessLevel::Read,
+				None,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_forbid_grant_access_that_has_already_expired() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			frame_system::Module::<TestRuntime>::set_block_number(10);
+
+			grant(KeyAccessLevel::Decrypt, Some(10)).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_expire_access_grant() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, Some(10)).unwrap();
+
+			frame_system::Module::<TestRuntime>::set_block_number(9);
+			assert!(check(grantee(), KeyAccessLevel::Decrypt));
+
+			frame_system::Module::<TestRuntime>::set_block_number(10);
+			assert!(!check(grantee(), KeyAccessLevel::Read));
+			assert!(!check(grantee(), KeyAccessLevel::Decrypt));
+		});
+	}
+
+	#[test]
+	fn should_allow_revoke_access() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, None).unwrap();
+
+			KeyAccessService::<TestRuntime>::revoke(
+				Origin::signed(REQUESTER1),
+				KEY.into(),
+				grantee(),
+			).unwrap();
+
+			assert!(!check(grantee(), KeyAccessLevel::Read));
+			assert!(!KeyAccessGrants::<TestRuntime>::contains_key(ServerKeyId::from(KEY), grantee()));
+
+			// check that event has been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::KeyAccessRevoked(KEY.into(), grantee()).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_forbid_revoke_access_by_non_owner() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, None).unwrap();
+
+			KeyAccessService::<TestRuntime>::revoke(
+				Origin::signed(REAL_REQUESTER1),
+				KEY.into(),
+				grantee(),
+			).unwrap_err();
+
+			assert!(check(grantee(), KeyAccessLevel::Decrypt));
+		});
+	}
+
+	#[test]
+	fn should_forbid_revoke_access_that_has_not_been_granted() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+
+			KeyAccessService::<TestRuntime>::revoke(
+				Origin::signed(REQUESTER1),
+				KEY.into(),
+				grantee(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_keep_grants_when_key_ownership_is_transferred() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Read, None).unwrap();
+
+			Module::<TestRuntime>::transfer_key(
+				Origin::signed(REQUESTER1),
+				KEY.into(),
+				REAL_REQUESTER2_ADDRESS.into(),
+			).unwrap();
+
+			// previous owner has lost access and can't manage grants anymore
+			assert!(!check(owner(), KeyAccessLevel::Read));
+			KeyAccessService::<TestRuntime>::revoke(
+				Origin::signed(REQUESTER1),
+				KEY.into(),
+				grantee(),
+			).unwrap_err();
+
+			// while grants are still active and new owner could manage them
+			assert!(check(grantee(), KeyAccessLevel::Read));
+			KeyAccessService::<TestRuntime>::revoke(
+				Origin::signed(REAL_REQUESTER2),
+				KEY.into(),
+				grantee(),
+			).unwrap();
+		});
+	}
+
+	fn approver() -> EntityId {
+		REAL_REQUESTER2_ADDRESS.into()
+	}
+
+	fn set_policy(policy: KeyAccessPolicyKind) {
+		KeyAccessService::<TestRuntime>::set_policy(
+			Origin::signed(REQUESTER1),
+			KEY.into(),
+			policy,
+		).unwrap();
+	}
+
+	fn set_approvers(approvers: Vec<EntityId>, threshold: u8) -> Result<(), &'static str> {
+		KeyAccessService::<TestRuntime>::set_approvers(
+			Origin::signed(REQUESTER1),
+			KEY.into(),
+			approvers,
+			threshold,
+		)
+	}
+
+	fn approve(approver: u64) -> Result<(), &'static str> {
+		KeyAccessService::<TestRuntime>::approve(
+			Origin::signed(approver),
+			KEY.into(),
+			grantee(),
+		)
+	}
+
+	#[test]
+	fn should_use_runtime_policy_by_default() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Read, None).unwrap();
+
+			assert_eq!(KeyAccessPolicies::get(ServerKeyId::from(KEY)), KeyAccessPolicyKind::Runtime);
+			assert!(check(grantee(), KeyAccessLevel::Read));
+		});
+	}
+
+	#[test]
+	fn should_ignore_grants_when_owner_only_policy_is_selected() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			grant(KeyAccessLevel::Decrypt, None).unwrap();
+			set_policy(KeyAccessPolicyKind::OwnerOnly);
+
+			assert!(check(owner(), KeyAccessLevel::Decrypt));
+			assert!(!check(grantee(), KeyAccessLevel::Read));
+
+			// check that event has been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::KeyAccessPolicySelected(
+						KEY.into(),
+						KeyAccessPolicyKind::OwnerOnly,
+					).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_forbid_select_policy_by_non_owner() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+
+			KeyAccessService::<TestRuntime>::set_policy(
+				Origin::signed(REAL_REQUESTER1),
+				KEY.into(),
+				KeyAccessPolicyKind::OwnerOnly,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_grant_access_when_enough_approvals_are_received() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			set_policy(KeyAccessPolicyKind::MultiApproval);
+			set_approvers(vec![[OWNER as u8; 20].into(), approver()], 2).unwrap();
+
+			approve(OWNER).unwrap();
+			assert!(!check(grantee(), KeyAccessLevel::Read));
+
+			approve(REAL_REQUESTER2).unwrap();
+			assert!(check(grantee(), KeyAccessLevel::Read));
+			assert!(check(grantee(), KeyAccessLevel::Decrypt));
+			assert!(check(owner(), KeyAccessLevel::Decrypt));
+
+			// check that events have been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::KeyApproversSet(
+						KEY.into(),
+						vec![[OWNER as u8; 20].into(), approver()],
+						2,
+					).into())
+					.is_some(),
+			);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::KeyAccessApproved(KEY.into(), grantee(), approver()).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_forbid_invalid_approvers() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+
+			set_approvers(vec![approver()], 0).unwrap_err();
+			set_approvers(vec![approver()], 2).unwrap_err();
+			set_approvers(vec![approver(), approver()], 1).unwrap_err();
+			KeyAccessService::<TestRuntime>::set_approvers(
+				Origin::signed(REAL_REQUESTER1),
+				KEY.into(),
+				vec![approver()],
+				1,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_forbid_approve_by_non_approver() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			set_approvers(vec![approver()], 1).unwrap();
+
+			approve(OWNER).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_forbid_approve_twice() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			set_approvers(vec![approver()], 1).unwrap();
+
+			approve(REAL_REQUESTER2).unwrap();
+			approve(REAL_REQUESTER2).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_allow_revoke_approval() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			set_policy(KeyAccessPolicyKind::MultiApproval);
+			set_approvers(vec![approver()], 1).unwrap();
+			approve(REAL_REQUESTER2).unwrap();
+
+			KeyAccessService::<TestRuntime>::revoke_approval(
+				Origin::signed(REAL_REQUESTER2),
+				KEY.into(),
+				grantee(),
+			).unwrap();
+
+			assert!(!check(grantee(), KeyAccessLevel::Read));
+			assert!(!KeyAccessApprovals::contains_key(ServerKeyId::from(KEY), grantee()));
+
+			// check that event has been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::KeyAccessApprovalRevoked(KEY.into(), grantee(), approver()).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_discard_approvals_when_approvers_are_changed() {
+		basic_initialization().execute_with(|| {
+			claim_key();
+			set_policy(KeyAccessPolicyKind::MultiApproval);
+			set_approvers(vec![approver()], 1).unwrap();
+			approve(REAL_REQUESTER2).unwrap();
+			assert!(check(grantee(), KeyAccessLevel::Decrypt));
+
+			set_approvers(vec![approver()], 1).unwrap();
+			assert!(!check(grantee(), KeyAccessLevel::Decrypt));
+		});
+	}
+}
@@ -236,7 +236,7 @@ impl<BS, ES, SS> KeyServerSetWithMigration<BS, ES, SS> where
 #[cfg(test)]
 mod tests {
 	use frame_support::{StorageMap, StorageValue};
-	use crate::{CurrentSetChangeBlock, MigrationConfirmations};
+	use crate::{CurrentSetChangeBlock, CurrentKeyServersCount, MigrationConfirmations};
 	use crate::mock::*;
 	use super::*;
 
@@ -282,6 +282,7 @@ mod tests {
 				ordered_set(key_server_set().snapshot(KEY_SERVER1_ID.into()).current_set),
 				default_key_server_set(),
 			);
+			assert_eq!(CurrentKeyServersCount::get(), 2);
 		});
 	}
 
@@ -597,6 +598,7 @@ mod tests {
 			assert_eq!(snapshot.migration, None);
 			assert_eq!(ordered_set(snapshot.current_set), new_set);
 			assert_eq!(ordered_set(snapshot.new_set), new_set);
+			assert_eq!(CurrentKeyServersCount::get(), 3);
 			assert_eq!(CurrentSetChangeBlock::<TestRuntime>::get(), 42);
 			assert!(!MigrationConfirmations::contains_key(KeyServerId::from(KEY_SERVER0_ID)));
 			assert!(!MigrationConfirmations::contains_key(KeyServerId::from(KEY_SERVER1_ID)));
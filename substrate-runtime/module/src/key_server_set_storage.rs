@@ -27,7 +27,7 @@ use crate::{
 	Trait,
 	Owner, IsInitialized,
 	MigrationId, MigrationConfirmations, CurrentSetChangeBlock,
-	CurrentKeyServers, MigrationKeyServers, NewKeyServers,
+	CurrentKeyServers, CurrentKeyServersCount, MigrationKeyServers, NewKeyServers,
 };
 
 /// Single key server data.
@@ -254,9 +254,62 @@ impl<M> Storage for RuntimeStorage<M> where
 	}
 }
 
+/// The storage of single key server set that also maintains number of servers in the set, so
+/// that it could be read without iterating the set.
+pub(crate) struct CountedRuntimeStorage<M, C>(RuntimeStorage<M>, PhantomData<C>);
+
+impl<M, C> Default for CountedRuntimeStorage<M, C> {
+	fn default() -> Self {
+		CountedRuntimeStorage(Default::default(), Default::default())
+	}
+}
+
+impl<M, C> Storage for CountedRuntimeStorage<M, C> where
+	M: IterableStorageMap<KeyServerId, KeyServer, Query=Option<KeyServer>>,
+	C: StorageValue<u32, Query=u32>,
+{
+	fn get(&self) -> Vec<KeyServer> {
+		self.0.get()
+	}
+
+	fn get_as_map(&self) -> BTreeMap<KeyServerId, KeyServer> {
+		self.0.get_as_map()
+	}
+
+	fn contains(&self, id: &KeyServerId) -> bool {
+		self.0.contains(id)
+	}
+
+	fn append(&mut self, id: KeyServerId, address: KeyServerNetworkAddress) -> Result<(), &'static str> {
+		self.0.append(id, address)?;
+		C::mutate(|count| *count += 1);
+		Ok(())
+	}
+
+	fn remove(&mut self, id: &KeyServerId) -> Result<(), &'static str> {
+		self.0.remove(id)?;
+		C::mutate(|count| *count = count.saturating_sub(1));
+		Ok(())
+	}
+
+	fn update(&mut self, id: &KeyServerId, address: KeyServerNetworkAddress) -> Result<(), &'static str> {
+		self.0.update(id, address)
+	}
+
+	fn fill_from(&mut self, key_servers: BTreeMap<KeyServerId, KeyServer>) {
+		C::put(key_servers.len() as u32);
+		self.0.fill_from(key_servers);
+	}
+
+	fn clear(&mut self) {
+		self.0.clear();
+		C::kill();
+	}
+}
+
 /// The storage of 'migratable' key server set that supports migration.
 pub(crate) struct RuntimeStorageWithMigration<T> {
-	current: RuntimeStorage<CurrentKeyServers>,
+	current: CountedRuntimeStorage<CurrentKeyServers, CurrentKeyServersCount>,
 	migration: RuntimeStorage<MigrationKeyServers>,
 	new: RuntimeStorage<NewKeyServers>,
 	_phantom: PhantomData<T>,
@@ -18,24 +18,38 @@
 
 use sp_std::prelude::*;
 
+mod benchmarking;
 mod blockchain_storage;
 mod entity_id_storage;
 mod document_key_shadow_retrieval;
 mod document_key_store;
+mod ecdsa_signing;
+mod key_access;
 mod key_server_set;
 mod key_server_set_storage;
+mod migration;
 mod mock;
+mod schnorr_signing;
+mod server_key_deletion;
 mod server_key_generation;
 mod server_key_retrieval;
 mod service;
+mod weights;
 
-use frame_support::{StorageMap, traits::Currency, decl_module, decl_event, decl_storage, ensure};
-use frame_system::{self as system, ensure_signed};
+use frame_support::{
+	StorageMap,
+	traits::{Currency, ReservableCurrency},
+	weights::{DispatchClass, FunctionOf, SimpleDispatchInfo, Weight},
+	decl_module, decl_event, decl_storage, ensure,
+};
+use frame_system::{self as system, ensure_root, ensure_signed};
+use sp_runtime::traits::Zero;
 use primitives::{
 	EntityId,
 	KeyServerId,
 	ServerKeyId,
 	KeyServersMask,
+	acl_storage::KeyAccessLevel,
 	key_server_set::{KeyServerSetSnapshot, KeyServerNetworkAddress, MigrationId as MigrationIdT},
 };
 use document_key_shadow_retrieval::{
@@ -44,9 +58,27 @@ use document_key_shadow_retrieval::{
 	DocumentKeyShadowRetrievalService,
 };
 use document_key_store::{DocumentKeyStoreRequest, DocumentKeyStoreService};
+use ecdsa_signing::{EcdsaSigningRequest, EcdsaSigningService};
+use key_access::{KeyAccessGrant, KeyAccessService, KeyApproversSet};
+use schnorr_signing::{SchnorrSigningRequest, SchnorrSigningService};
+use server_key_deletion::{ServerKeyDeletionRequest, ServerKeyDeletionService};
 use server_key_generation::{ServerKeyGenerationRequest, ServerKeyGenerationService};
 use server_key_retrieval::{ServerKeyRetrievalRequest, ServerKeyRetrievalService};
 use key_server_set_storage::KeyServer;
+use weights::{KeyServersWeight, current_key_servers_count};
+
+pub use key_access::{
+	KeyAccessPolicy,
+	KeyAccessPolicyKind,
+	OwnerOnlyPolicy,
+	AllowListPolicy,
+	MultiApprovalPolicy,
+};
+pub use weights::WeightInfo;
+
+/// Number of blocks after which active service request expires, unless other value is
+/// set in genesis or by root. This is one hour with 6 seconds blocks.
+pub const DEFAULT_REQUEST_LIFETIME: u32 = 600;
 
 pub type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::Balance;
 
@@ -55,17 +87,42 @@ pub trait Trait: frame_system::Trait {
 	/// They overarching event type.
 	type Event: From<Event> + Into<<Self as frame_system::Trait>::Event>;
 
-	/// The currency type used for paying services.
-	type Currency: Currency<Self::AccountId>;
+	/// The currency type used for paying services. Service fee is reserved when request
+	/// is created and is paid to key servers when request is completed.
+	type Currency: ReservableCurrency<Self::AccountId>;
+
+	/// The policy that is used to check access to keys, unless other policy has been
+	/// selected by the key owner.
+	type KeyAccessPolicy: KeyAccessPolicy<Self>;
+
+	/// Weights of module dispatchables.
+	type WeightInfo: WeightInfo;
 }
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event() = default;
 
+		/// Migrate module storage to the current version.
+		fn on_runtime_upgrade() {
+			migration::migrate::<T>();
+		}
+
+		/// Expire all requests that have not been completed in time.
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			ServerKeyGenerationService::<T>::expire_requests(now)
+				.saturating_add(ServerKeyRetrievalService::<T>::expire_requests(now))
+				.saturating_add(ServerKeyDeletionService::<T>::expire_requests(now))
+				.saturating_add(DocumentKeyStoreService::<T>::expire_requests(now))
+				.saturating_add(DocumentKeyShadowRetrievalService::<T>::expire_requests(now))
+				.saturating_add(SchnorrSigningService::<T>::expire_requests(now))
+				.saturating_add(EcdsaSigningService::<T>::expire_requests(now))
+		}
+
 		/// Change key server set owner.
 		///
 		/// Can only be called by current owner.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::change_owner())]
 		pub fn change_owner(origin, new_owner: T::AccountId) {
 			let origin = ensure_signed(origin)?;
 			ensure!(
@@ -80,6 +137,7 @@ decl_module! {
 		///
 		/// Any account may claim single entity id.
 		/// Entity id may only be claimed by at most one account.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::claim_id())]
 		pub fn claim_id(origin, id: EntityId) {
 			ensure!(
 				!<ClaimedBy<T>>::contains_key(&id),
@@ -99,6 +157,7 @@ decl_module! {
 		/// Complete key servers set initialization.
 		///
 		/// Can only be called by owner.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::complete_initialization())]
 		pub fn complete_initialization(origin) {
 			key_server_set::<T>().complete_initialization(origin)?;
 		}
@@ -106,6 +165,14 @@ decl_module! {
 		/// Add key server to the set.
 		///
 		/// Can only be called by owner.
+		#[weight = FunctionOf(
+			|args: (&KeyServerId, &KeyServerNetworkAddress)| T::WeightInfo::add_key_server(
+				current_key_servers_count::<T>(),
+				args.1.len() as u32,
+			),
+			|_: (&KeyServerId, &KeyServerNetworkAddress)| DispatchClass::Normal,
+			true
+		)]
 		pub fn add_key_server(origin, id: KeyServerId, network_address: KeyServerNetworkAddress) {
 			key_server_set::<T>().add_key_server(origin, id, network_address)?;
 		}
@@ -113,6 +180,7 @@ decl_module! {
 		/// Remove key server from the set.
 		///
 		/// Can only be called by owner.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::remove_key_server())]
 		pub fn remove_key_server(origin, id: KeyServerId) {
 			key_server_set::<T>().remove_key_server(origin, id)?;
 		}
@@ -121,6 +189,7 @@ decl_module! {
 		///
 		/// Can only be called by one of key servers from UNION(current, new) set.
 		/// Can only be called when migration is required.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::start_migration)]
 		pub fn start_migration(origin, migration_id: MigrationIdT) {
 			key_server_set::<T>().start_migration(origin, migration_id)?;
 		}
@@ -129,10 +198,25 @@ decl_module! {
 		///
 		/// Can only be called by one of key servers from migration set.
 		/// Can only be called when migration is active.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::confirm_migration)]
 		pub fn confirm_migration(origin, migration_id: MigrationIdT) {
 			key_server_set::<T>().confirm_migration(origin, migration_id)?;
 		}
 
+		/// Change number of blocks after which active service request expires.
+		///
+		/// Can only be called by root. Lifetime of already active requests is not changed.
+		#[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_request_lifetime())]
+		pub fn set_request_lifetime(origin, lifetime: T::BlockNumber) {
+			ensure_root(origin)?;
+			ensure!(
+				lifetime != Zero::zero(),
+				"Request lifetime must be positive",
+			);
+
+			RequestLifetime::<T>::put(lifetime);
+		}
+
 		/// Claim key ownership.
 		///
 		/// This is an example of how key access could be managed - we allow at most
@@ -141,6 +225,7 @@ decl_module! {
 		///
 		/// **IMPORTANT**: it is a good idea to claim ownership **before** key generation.
 		/// Otherwise anyone could claim (i.e. steal) your key.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::claim_key())]
 		pub fn claim_key(origin, id: ServerKeyId) {
 			ensure!(
 				!KeyOwners::contains_key(&id),
@@ -157,6 +242,7 @@ decl_module! {
 		/// This is an example of how key access could be managed - we allow at most
 		/// 1 key 'owner' at a time. By calling this method you're transferring access
 		/// to private portion of key with given id from yourself to given account.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::transfer_key())]
 		pub fn transfer_key(origin, id: ServerKeyId, new_claimant: EntityId) {
 			let origin = ensure_signed(origin)?;
 			let origin = resolve_entity_id::<T>(&origin)?;
@@ -168,12 +254,75 @@ decl_module! {
 			KeyOwners::insert(id, vec![new_claimant]);
 		}
 
+		/// Grant access to the key.
+		///
+		/// Can only be called by key owner. Grantee will have given level of access
+		/// to the key until `expires_at` block (if specified) or until access is revoked.
+		/// Existing grant of the same entity is replaced.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::grant_key_access())]
+		pub fn grant_key_access(
+			origin,
+			id: ServerKeyId,
+			grantee: EntityId,
+			level: KeyAccessLevel,
+			expires_at: Option<T::BlockNumber>,
+		) {
+			KeyAccessService::<T>::grant(origin, id, grantee, level, expires_at)?;
+		}
+
+		/// Revoke previously granted access to the key.
+		///
+		/// Can only be called by key owner.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::revoke_key_access())]
+		pub fn revoke_key_access(origin, id: ServerKeyId, grantee: EntityId) {
+			KeyAccessService::<T>::revoke(origin, id, grantee)?;
+		}
+
+		/// Select policy that is used to check access to the key.
+		///
+		/// Can only be called by key owner.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_key_access_policy())]
+		pub fn set_key_access_policy(origin, id: ServerKeyId, policy: KeyAccessPolicyKind) {
+			KeyAccessService::<T>::set_policy(origin, id, policy)?;
+		}
+
+		/// Set entities that are approving access to the key. At least `threshold` of
+		/// them must approve access before it is given by the multi-approval policy.
+		/// All existing approvals are discarded.
+		///
+		/// Can only be called by key owner.
+		#[weight = FunctionOf(
+			|args: (&ServerKeyId, &Vec<EntityId>, &u8)| T::WeightInfo::set_key_approvers(args.1.len() as u32),
+			|_: (&ServerKeyId, &Vec<EntityId>, &u8)| DispatchClass::Normal,
+			true
+		)]
+		pub fn set_key_approvers(origin, id: ServerKeyId, approvers: Vec<EntityId>, threshold: u8) {
+			KeyAccessService::<T>::set_approvers(origin, id, approvers, threshold)?;
+		}
+
+		/// Approve access to the key.
+		///
+		/// Can only be called by one of key approvers.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::approve_key_access())]
+		pub fn approve_key_access(origin, id: ServerKeyId, requester: EntityId) {
+			KeyAccessService::<T>::approve(origin, id, requester)?;
+		}
+
+		/// Revoke previously given approval of access to the key.
+		///
+		/// Can only be called by the approver.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::revoke_key_access_approval())]
+		pub fn revoke_key_access_approval(origin, id: ServerKeyId, requester: EntityId) {
+			KeyAccessService::<T>::revoke_approval(origin, id, requester)?;
+		}
+
 		/// Generate server key.
 		///
 		/// The caller should be able to pay ServerKeyGenerationFee.
 		/// Generated server key will be published using ServerKeyGenerated event.
 		/// If SecretStore will be unable to generate server key, then it will emit
 		/// ServerKeyGenerationError event.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::generate_server_key)]
 		pub fn generate_server_key(origin, id: ServerKeyId, threshold: u8) {
 			ServerKeyGenerationService::<T>::generate(origin, id, threshold)?;
 		}
@@ -181,6 +330,7 @@ decl_module! {
 		/// Called when generation is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::server_key_generated)]
 		pub fn server_key_generated(origin, id: ServerKeyId, server_key_public: sp_core::H512) {
 			ServerKeyGenerationService::<T>::on_generated(origin, id, server_key_public)?;
 		}
@@ -188,6 +338,7 @@ decl_module! {
 		/// Called when generation error is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::server_key_generation_error)]
 		pub fn server_key_generation_error(origin, id: ServerKeyId) {
 			ServerKeyGenerationService::<T>::on_generation_error(origin, id)?;
 		}
@@ -198,6 +349,7 @@ decl_module! {
 		/// Retrieved server key will be published using ServerKeyRetrieved event.
 		/// If SecretStore will be unable to retrieve server key, then it will emit
 		/// ServerKeyRetrievalError event.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::retrieve_server_key())]
 		pub fn retrieve_server_key(origin, id: ServerKeyId) {
 			ServerKeyRetrievalService::<T>::retrieve(origin, id)?;
 		}
@@ -205,6 +357,7 @@ decl_module! {
 		/// Called when generation is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::server_key_retrieved)]
 		pub fn server_key_retrieved(origin, id: ServerKeyId, server_key_public: sp_core::H512, threshold: u8) {
 			ServerKeyRetrievalService::<T>::on_retrieved(origin, id, server_key_public, threshold)?;
 		}
@@ -212,16 +365,47 @@ decl_module! {
 		/// Called when generation error is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::server_key_retrieval_error)]
 		pub fn server_key_retrieval_error(origin, id: ServerKeyId) {
 			ServerKeyRetrievalService::<T>::on_retrieval_error(origin, id)?;
 		}
 
+		/// Delete server key.
+		///
+		/// The caller should be able to pay ServerKeyDeletionFee. Key servers will only
+		/// delete the key if the caller is the author of the key.
+		/// Deletion confirmation will be published using ServerKeyDeleted event.
+		/// If SecretStore will be unable to delete server key, then it will emit
+		/// ServerKeyDeletionError event. Once deleted, the key with the same id can't
+		/// be generated again.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::delete_server_key())]
+		pub fn delete_server_key(origin, id: ServerKeyId) {
+			ServerKeyDeletionService::<T>::delete(origin, id)?;
+		}
+
+		/// Called when deletion is reported by key server.
+		///
+		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::server_key_deleted)]
+		pub fn server_key_deleted(origin, id: ServerKeyId) {
+			ServerKeyDeletionService::<T>::on_deleted(origin, id)?;
+		}
+
+		/// Called when deletion error is reported by key server.
+		///
+		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::server_key_deletion_error)]
+		pub fn server_key_deletion_error(origin, id: ServerKeyId) {
+			ServerKeyDeletionService::<T>::on_deletion_error(origin, id)?;
+		}
+
 		/// Store document key.
 		///
 		/// The caller should be able to pay DocumentKeyStoreFee.
 		/// Store confirmation will be published using DocumentKeyStored event.
 		/// If SecretStore will be unable to store document key, then it will emit
 		/// DocumentKeyStoreError event.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::store_document_key())]
 		pub fn store_document_key(origin, id: ServerKeyId, common_point: sp_core::H512, encrypted_point: sp_core::H512) {
 			DocumentKeyStoreService::<T>::store(origin, id, common_point, encrypted_point)?;
 		}
@@ -229,6 +413,7 @@ decl_module! {
 		/// Called when store is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::document_key_stored)]
 		pub fn document_key_stored(origin, id: ServerKeyId) {
 			DocumentKeyStoreService::<T>::on_stored(origin, id)?;
 		}
@@ -236,6 +421,7 @@ decl_module! {
 		/// Called when store error is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::document_key_store_error)]
 		pub fn document_key_store_error(origin, id: ServerKeyId) {
 			DocumentKeyStoreService::<T>::on_store_error(origin, id)?;
 		}
@@ -247,6 +433,7 @@ decl_module! {
 		/// series of DocumentKeyPersonalRetrieved event (see DocumentKeyCommonRetrieved
 		/// description for details). If SecretStore will be unable to retrieve document
 		/// key shadow, then it will emit DocumentKeyShadowRetrievalError event.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::retrieve_document_key_shadow())]
 		pub fn retrieve_document_key_shadow(origin, id: ServerKeyId, requester_public: sp_core::H512) {
 			DocumentKeyShadowRetrievalService::<T>::retrieve(origin, id, requester_public)?;
 		}
@@ -254,6 +441,7 @@ decl_module! {
 		/// Called when document key common part is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::document_key_common_retrieved)]
 		pub fn document_key_common_retrieved(
 			origin,
 			id: ServerKeyId,
@@ -273,6 +461,15 @@ decl_module! {
 		/// Called when document key personal part is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = FunctionOf(
+			|args: (&ServerKeyId, &EntityId, &KeyServersMask, &sp_core::H512, &Vec<u8>)|
+				T::WeightInfo::document_key_personal_retrieved(
+					current_key_servers_count::<T>(),
+					args.4.len() as u32,
+				),
+			|_: (&ServerKeyId, &EntityId, &KeyServersMask, &sp_core::H512, &Vec<u8>)| DispatchClass::Normal,
+			true
+		)]
 		pub fn document_key_personal_retrieved(
 			origin,
 			id: ServerKeyId,
@@ -294,9 +491,87 @@ decl_module! {
 		/// Called when document key shadow retrieval error is reported by key server.
 		///
 		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::document_key_shadow_retrieval_error)]
 		pub fn document_key_shadow_retrieval_error(origin, id: ServerKeyId, requester: EntityId) {
 			DocumentKeyShadowRetrievalService::<T>::on_retrieval_error(origin, id, requester)?;
 		}
+
+		/// Sign message using Schnorr signature scheme.
+		///
+		/// The caller should be able to pay SchnorrSigningFee.
+		/// Signature will be published using SchnorrSigned event. If SecretStore
+		/// will be unable to sign message, then it will emit SchnorrSigningError event.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::sign_message_schnorr())]
+		pub fn sign_message_schnorr(origin, id: ServerKeyId, message: sp_core::H256) {
+			SchnorrSigningService::<T>::sign(origin, id, message)?;
+		}
+
+		/// Called when Schnorr signature is reported by key server.
+		///
+		/// Can only be called by key servers from the current set. Every key server
+		/// reports its own signature along with threshold of the key.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::schnorr_signature_generated)]
+		pub fn schnorr_signature_generated(
+			origin,
+			id: ServerKeyId,
+			requester: EntityId,
+			message: sp_core::H256,
+			signature_c: sp_core::H256,
+			signature_s: sp_core::H256,
+			threshold: u8,
+		) {
+			SchnorrSigningService::<T>::on_signed(
+				origin,
+				id,
+				requester,
+				message,
+				signature_c,
+				signature_s,
+				threshold,
+			)?;
+		}
+
+		/// Called when Schnorr signing error is reported by key server.
+		///
+		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::schnorr_signing_error)]
+		pub fn schnorr_signing_error(origin, id: ServerKeyId, requester: EntityId, message: sp_core::H256) {
+			SchnorrSigningService::<T>::on_signing_error(origin, id, requester, message)?;
+		}
+
+		/// Sign message using ECDSA signature scheme.
+		///
+		/// The caller should be able to pay EcdsaSigningFee.
+		/// Signature will be published using EcdsaSigned event. If SecretStore
+		/// will be unable to sign message, then it will emit EcdsaSigningError event.
+		#[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::sign_message_ecdsa())]
+		pub fn sign_message_ecdsa(origin, id: ServerKeyId, message: sp_core::H256) {
+			EcdsaSigningService::<T>::sign(origin, id, message)?;
+		}
+
+		/// Called when ECDSA signature is reported by key server.
+		///
+		/// Can only be called by key servers from the current set. Every key server
+		/// reports its own signature along with threshold of the key.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::ecdsa_signature_generated)]
+		pub fn ecdsa_signature_generated(
+			origin,
+			id: ServerKeyId,
+			requester: EntityId,
+			message: sp_core::H256,
+			signature: sp_core::ecdsa::Signature,
+			threshold: u8,
+		) {
+			EcdsaSigningService::<T>::on_signed(origin, id, requester, message, signature, threshold)?;
+		}
+
+		/// Called when ECDSA signing error is reported by key server.
+		///
+		/// Can only be called by key servers from the current set.
+		#[weight = KeyServersWeight::<T>::new(T::WeightInfo::ecdsa_signing_error)]
+		pub fn ecdsa_signing_error(origin, id: ServerKeyId, requester: EntityId, message: sp_core::H256) {
+			EcdsaSigningService::<T>::on_signing_error(origin, id, requester, message)?;
+		}
 	}
 }
 
@@ -317,12 +592,32 @@ decl_event!(
 		/// (KeyServer) Key server set: migration has completed.
 		MigrationCompleted,
 
+		/// (Client) Key access: access of given level has been granted to the entity
+		/// by one of key owners.
+		KeyAccessGranted(ServerKeyId, EntityId, KeyAccessLevel),
+		/// (Client) Key access: previously granted access has been revoked from the entity.
+		KeyAccessRevoked(ServerKeyId, EntityId),
+		/// (Client) Key access: policy that is used to check access to the key has been
+		/// selected by one of key owners.
+		KeyAccessPolicySelected(ServerKeyId, KeyAccessPolicyKind),
+		/// (Client) Key access: key approvers and approval threshold have been set by one of
+		/// key owners. All previous approvals are discarded.
+		KeyApproversSet(ServerKeyId, Vec<EntityId>, u8),
+		/// (Client) Key access: access of the entity (second) has been approved by one of
+		/// key approvers (third).
+		KeyAccessApproved(ServerKeyId, EntityId, EntityId),
+		/// (Client) Key access: previously given approval of access of the entity (second)
+		/// has been revoked by the approver (third).
+		KeyAccessApprovalRevoked(ServerKeyId, EntityId, EntityId),
+
 		/// (KeyServer) Server key generation: new key generation requested.
 		ServerKeyGenerationRequested(ServerKeyId, EntityId, u8),
 		/// (Client) Server key generation: key is generated.
 		ServerKeyGenerated(ServerKeyId, sp_core::H512),
 		/// (Client) Server key generation: key generation has failed.
 		ServerKeyGenerationError(ServerKeyId),
+		/// (Client) Server key generation: request has expired.
+		ServerKeyGenerationExpired(ServerKeyId),
 
 		/// (KeyServer) Server key retrieval: new key retrieval requested.
 		ServerKeyRetrievalRequested(ServerKeyId),
@@ -330,6 +625,17 @@ decl_event!(
 		ServerKeyRetrieved(ServerKeyId, sp_core::H512),
 		/// (Client) Server key retrieval: key retrieval has failed.
 		ServerKeyRetrievalError(ServerKeyId),
+		/// (Client) Server key retrieval: request has expired.
+		ServerKeyRetrievalExpired(ServerKeyId),
+
+		/// (KeyServer) Server key deletion: new key deletion requested.
+		ServerKeyDeletionRequested(ServerKeyId, EntityId),
+		/// (Client) Server key deletion: key is deleted.
+		ServerKeyDeleted(ServerKeyId),
+		/// (Client) Server key deletion: key deletion has failed.
+		ServerKeyDeletionError(ServerKeyId),
+		/// (Client) Server key deletion: request has expired.
+		ServerKeyDeletionExpired(ServerKeyId),
 
 		/// (KeyServer) Document key store: new document key store requested.
 		DocumentKeyStoreRequested(ServerKeyId, EntityId, sp_core::H512, sp_core::H512),
@@ -337,6 +643,8 @@ decl_event!(
 		DocumentKeyStored(ServerKeyId),
 		/// (Client) Document key store: key store has failed.
 		DocumentKeyStoreError(ServerKeyId),
+		/// (Client) Document key store: request has expired.
+		DocumentKeyStoreExpired(ServerKeyId),
 
 		/// (KeyServer) Document key shadow retrieval: new retrieval requested.
 		DocumentKeyShadowRetrievalRequested(ServerKeyId, EntityId),
@@ -354,6 +662,29 @@ decl_event!(
 		/// (Client) Document key shadow retrieval: personal portion has been reported
 		/// by one of key servers.
 		DocumentKeyPersonalRetrieved(ServerKeyId, EntityId, sp_core::H512, Vec<u8>),
+		/// (Client) Document key shadow retrieval: request has expired.
+		DocumentKeyShadowRetrievalExpired(ServerKeyId, EntityId),
+
+		/// (KeyServer) Schnorr signing: new message signing requested.
+		SchnorrSigningRequested(ServerKeyId, EntityId, sp_core::H256),
+		/// (Client) Schnorr signing: message is signed by one of key servers. The signature
+		/// is (c, s) pair. The requester should wait for `threshold + 1` signatures and pick
+		/// the valid one.
+		SchnorrSigned(ServerKeyId, EntityId, sp_core::H256, sp_core::H256, sp_core::H256),
+		/// (Client) Schnorr signing: message signing has failed.
+		SchnorrSigningError(ServerKeyId, EntityId, sp_core::H256),
+		/// (Client) Schnorr signing: request has expired.
+		SchnorrSigningExpired(ServerKeyId, EntityId, sp_core::H256),
+
+		/// (KeyServer) ECDSA signing: new message signing requested.
+		EcdsaSigningRequested(ServerKeyId, EntityId, sp_core::H256),
+		/// (Client) ECDSA signing: message is signed by one of key servers. The requester
+		/// should wait for `threshold + 1` signatures and pick the valid one.
+		EcdsaSigned(ServerKeyId, EntityId, sp_core::H256, sp_core::ecdsa::Signature),
+		/// (Client) ECDSA signing: message signing has failed.
+		EcdsaSigningError(ServerKeyId, EntityId, sp_core::H256),
+		/// (Client) ECDSA signing: request has expired.
+		EcdsaSigningExpired(ServerKeyId, EntityId, sp_core::H256),
 	}
 );
 
@@ -362,6 +693,8 @@ decl_storage! {
 		/// Owner can perform some actions that are unavailable to regular users.
 		/// https://github.com/paritytech/secret-store/issues/30
 		pub Owner get(owner) config(): T::AccountId;
+		/// Version of the module storage. It is used to migrate storage on runtime upgrade.
+		StorageVersion build(|_| migration::CURRENT_STORAGE_VERSION): u32;
 
 		/// Claimed entity ID by account ID.
 		ClaimedId get(claimed_address): map hasher(blake2_128_concat) T::AccountId => Option<EntityId>;
@@ -379,6 +712,8 @@ decl_storage! {
 		/// Current key servers set. This is the set of key servers that are running key server
 		/// operations at this moment.
 		CurrentKeyServers: map hasher(blake2_128_concat) KeyServerId => Option<KeyServer>;
+		/// Number of key servers in the current set.
+		CurrentKeyServersCount: u32;
 		/// Migration key servers set. That is the set of key servers we are currently migrating
 		/// to from current set.
 		MigrationKeyServers: map hasher(blake2_128_concat) KeyServerId => Option<KeyServer>;
@@ -396,8 +731,27 @@ decl_storage! {
 		/// of server key (StoreDocumentKey, RetrieveDocumentKey, RetrieveDocumentKeyShadow, ...)
 		/// are only available for entities that 'own' this key.
 		///
-		/// In current (example) implementation we have at most 1 key owner.
+		/// In current (example) implementation we have at most 1 key owner. Other entities
+		/// may be given access to the key by the owner (see KeyAccessGrants).
 		KeyOwners: map hasher(blake2_128_concat) ServerKeyId => Vec<EntityId>;
+		/// Access to keys, granted by key owners to other entities.
+		KeyAccessGrants: double_map
+			hasher(blake2_128_concat) ServerKeyId,
+			hasher(blake2_128_concat) EntityId => Option<KeyAccessGrant<<T as frame_system::Trait>::BlockNumber>>;
+		/// Access policies, selected by key owners. Access to keys without selected policy
+		/// is checked by the policy that is configured in the runtime.
+		KeyAccessPolicies: map hasher(blake2_128_concat) ServerKeyId => KeyAccessPolicyKind;
+		/// Entities that are approving access to keys (see MultiApprovalPolicy).
+		KeyApprovers: map hasher(blake2_128_concat) ServerKeyId => Option<KeyApproversSet>;
+		/// Approvals of access to keys, given by key approvers.
+		KeyAccessApprovals: double_map
+			hasher(blake2_128_concat) ServerKeyId,
+			hasher(blake2_128_concat) EntityId => Vec<EntityId>;
+
+		/// Number of blocks after which active service request expires. Fee of expired request
+		/// is returned to the requester, except for shares of key servers that have already responded.
+		pub RequestLifetime get(request_lifetime) config(): <T as frame_system::Trait>::BlockNumber
+			= DEFAULT_REQUEST_LIFETIME.into();
 
 		/// Current server key generation fee. Splitted among all key servers from current set.
 		pub ServerKeyGenerationFee get(server_key_generation_fee) config(): BalanceOf<T>;
@@ -406,7 +760,7 @@ decl_storage! {
 		ServerKeyGenerationRequestsKeys: Vec<ServerKeyId>;
 		/// All active server key generation requests.
 		ServerKeyGenerationRequests: map hasher(blake2_128_concat) ServerKeyId
-			=> Option<ServerKeyGenerationRequest<<T as frame_system::Trait>::BlockNumber>>;
+			=> Option<ServerKeyGenerationRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
 		/// Reported server keys.
 		ServerKeyGenerationResponses: double_map
 			hasher(blake2_128_concat) ServerKeyId,
@@ -420,7 +774,7 @@ decl_storage! {
 		/// All active server key retrieval requests.
 		ServerKeyRetrievalRequests: map
 			hasher(blake2_128_concat) ServerKeyId
-			=> Option<ServerKeyRetrievalRequest<<T as frame_system::Trait>::BlockNumber>>;
+			=> Option<ServerKeyRetrievalRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
 		/// Reported server keys.
 		ServerKeyRetrievalResponses: double_map
 			hasher(blake2_128_concat) ServerKeyId,
@@ -430,6 +784,21 @@ decl_storage! {
 			hasher(blake2_128_concat) ServerKeyId,
 			hasher(twox_64_concat) u8 => u8;
 
+		/// Current server key deletion fee. Splitted among all key servers from current set.
+		pub ServerKeyDeletionFee get(server_key_deletion_fee) config(): BalanceOf<T>;
+		/// IDs of server keys that we're deleting/going to delete. Every key has its
+		/// entry in ServerKeyDeletionRequests.
+		ServerKeyDeletionRequestsKeys: Vec<ServerKeyId>;
+		/// All active server key deletion requests.
+		ServerKeyDeletionRequests: map hasher(blake2_128_concat) ServerKeyId
+			=> Option<ServerKeyDeletionRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
+		/// Server key deletion confirmations.
+		ServerKeyDeletionResponses: double_map
+			hasher(blake2_128_concat) ServerKeyId,
+			hasher(twox_64_concat) () => u8;
+		/// IDs of deleted server keys. Keys with these IDs can't be generated again.
+		DeletedServerKeys: map hasher(blake2_128_concat) ServerKeyId => bool;
+
 		/// Current document key store fee. Splitted among all key servers from current set.
 		pub DocumentKeyStoreFee get(document_key_store_fee) config(): BalanceOf<T>;
 		/// IDs of server keys that we're binding with document keys. Every key has its
@@ -437,7 +806,7 @@ decl_storage! {
 		DocumentKeyStoreRequestsKeys: Vec<ServerKeyId>;
 		/// All active document key store requests.
 		DocumentKeyStoreRequests: map hasher(blake2_128_concat) ServerKeyId
-			=> Option<DocumentKeyStoreRequest<<T as frame_system::Trait>::BlockNumber>>;
+			=> Option<DocumentKeyStoreRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
 		/// Document key store confirmations.
 		DocumentKeyStoreResponses: double_map
 			hasher(blake2_128_concat) ServerKeyId,
@@ -450,7 +819,7 @@ decl_storage! {
 		DocumentKeyShadowRetrievalRequestsKeys: Vec<(ServerKeyId, EntityId)>;
 		/// All active document key shadow retrieval requests
 		DocumentKeyShadowRetrievalRequests: map hasher(blake2_128_concat) (ServerKeyId, EntityId)
-			=> Option<DocumentKeyShadowRetrievalRequest<<T as frame_system::Trait>::BlockNumber>>;
+			=> Option<DocumentKeyShadowRetrievalRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
 		/// Reported common portions of document keys.
 		DocumentKeyShadowRetrievalCommonResponses: double_map
 			hasher(blake2_128_concat) (ServerKeyId, EntityId),
@@ -459,6 +828,32 @@ decl_storage! {
 		DocumentKeyShadowRetrievalPersonalResponses: double_map
 			hasher(blake2_128_concat) (ServerKeyId, EntityId),
 			hasher(blake2_128_concat) (KeyServersMask, sp_core::H512) => DocumentKeyShadowRetrievalPersonalData;
+
+		/// Current Schnorr signing fee. Splitted among all key servers from current set.
+		pub SchnorrSigningFee get(schnorr_signing_fee) config(): BalanceOf<T>;
+		/// IDs of (server key, requester, message) that we're signing/going to sign. Every key has
+		/// its entry in SchnorrSigningRequests.
+		SchnorrSigningRequestsKeys: Vec<(ServerKeyId, EntityId, sp_core::H256)>;
+		/// All active Schnorr signing requests.
+		SchnorrSigningRequests: map hasher(blake2_128_concat) (ServerKeyId, EntityId, sp_core::H256)
+			=> Option<SchnorrSigningRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
+		/// Key thresholds, reported by key servers along with Schnorr signatures.
+		SchnorrSigningResponses: double_map
+			hasher(blake2_128_concat) (ServerKeyId, EntityId, sp_core::H256),
+			hasher(twox_64_concat) u8 => u8;
+
+		/// Current ECDSA signing fee. Splitted among all key servers from current set.
+		pub EcdsaSigningFee get(ecdsa_signing_fee) config(): BalanceOf<T>;
+		/// IDs of (server key, requester, message) that we're signing/going to sign. Every key has
+		/// its entry in EcdsaSigningRequests.
+		EcdsaSigningRequestsKeys: Vec<(ServerKeyId, EntityId, sp_core::H256)>;
+		/// All active ECDSA signing requests.
+		EcdsaSigningRequests: map hasher(blake2_128_concat) (ServerKeyId, EntityId, sp_core::H256)
+			=> Option<EcdsaSigningRequest<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>>;
+		/// Key thresholds, reported by key servers along with ECDSA signatures.
+		EcdsaSigningResponses: double_map
+			hasher(blake2_128_concat) (ServerKeyId, EntityId, sp_core::H256),
+			hasher(twox_64_concat) u8 => u8;
 	}
 	add_extra_genesis {
 		config(is_initialization_completed): bool;
@@ -499,9 +894,15 @@ impl<T: Trait> Module<T> {
 		key_server_set::<T>().current_set_with_indices()
 	}
 
-	/// Check if requester has access to private portion of server key.
+	/// Check if requester has access to private portion of server key. Access is checked
+	/// by the policy that is selected for the key.
 	pub fn check_key_access(key: ServerKeyId, requester: EntityId) -> bool {
-		KeyOwners::get(key).contains(&requester)
+		KeyAccessService::<T>::check(key, requester, KeyAccessLevel::Decrypt)
+	}
+
+	/// Check if requester has given level of access to server key.
+	pub fn check_key_access_level(key: ServerKeyId, requester: EntityId, level: KeyAccessLevel) -> bool {
+		KeyAccessService::<T>::check(key, requester, level)
 	}
 
 	/// Get pending server key generation tasks.
@@ -549,6 +950,30 @@ impl<T: Trait> Module<T> {
 		ServerKeyRetrievalService::<T>::is_response_required(key_server, key_id)
 	}
 
+	/// Get pending server key deletion tasks.
+	pub fn server_key_deletion_tasks(begin: u32, end: u32) -> Vec<primitives::service::ServiceTask> {
+		ServerKeyDeletionRequestsKeys::get()
+			.into_iter()
+			.skip(begin as usize)
+			.take(end.saturating_sub(begin) as usize)
+			.map(|key_id| {
+				let request = ServerKeyDeletionRequests::<T>::get(&key_id)
+					.expect("every key from ServerKeyDeletionRequestsKeys has corresponding
+						entry in ServerKeyDeletionRequests; qed");
+				primitives::service::ServiceTask::DeleteServerKey(
+					key_id,
+					request.author,
+				)
+			})
+			.collect()
+	}
+
+	/// Returns true if given key server should submit its response to given server key
+	/// deletion request.
+	pub fn is_server_key_deletion_response_required(key_server: KeyServerId, key_id: ServerKeyId) -> bool {
+		ServerKeyDeletionService::<T>::is_response_required(key_server, key_id)
+	}
+
 	/// Get pending document key store tasks.
 	pub fn document_key_store_tasks(begin: u32, end: u32) -> Vec<primitives::service::ServiceTask> {
 		DocumentKeyStoreRequestsKeys::get()
@@ -608,6 +1033,60 @@ impl<T: Trait> Module<T> {
 	) -> bool {
 		DocumentKeyShadowRetrievalService::<T>::is_response_required(key_server, key_id, requester)
 	}
+
+	/// Get pending Schnorr signing tasks.
+	pub fn schnorr_signing_tasks(begin: u32, end: u32) -> Vec<primitives::service::ServiceTask> {
+		SchnorrSigningRequestsKeys::get()
+			.into_iter()
+			.skip(begin as usize)
+			.take(end.saturating_sub(begin) as usize)
+			.map(|(key_id, requester, message)| {
+				primitives::service::ServiceTask::SchnorrSignMessage(
+					key_id,
+					requester,
+					message,
+				)
+			})
+			.collect()
+	}
+
+	/// Returns true if given key server should submit its response to given Schnorr
+	/// signing request.
+	pub fn is_schnorr_signing_response_required(
+		key_server: KeyServerId,
+		key_id: ServerKeyId,
+		requester: EntityId,
+		message: sp_core::H256,
+	) -> bool {
+		SchnorrSigningService::<T>::is_response_required(key_server, key_id, requester, message)
+	}
+
+	/// Get pending ECDSA signing tasks.
+	pub fn ecdsa_signing_tasks(begin: u32, end: u32) -> Vec<primitives::service::ServiceTask> {
+		EcdsaSigningRequestsKeys::get()
+			.into_iter()
+			.skip(begin as usize)
+			.take(end.saturating_sub(begin) as usize)
+			.map(|(key_id, requester, message)| {
+				primitives::service::ServiceTask::EcdsaSignMessage(
+					key_id,
+					requester,
+					message,
+				)
+			})
+			.collect()
+	}
+
+	/// Returns true if given key server should submit its response to given ECDSA
+	/// signing request.
+	pub fn is_ecdsa_signing_response_required(
+		key_server: KeyServerId,
+		key_id: ServerKeyId,
+		requester: EntityId,
+		message: sp_core::H256,
+	) -> bool {
+		EcdsaSigningService::<T>::is_response_required(key_server, key_id, requester, message)
+	}
 }
 
 /// Type alias of used KeyServerSet implementation.
@@ -665,6 +1144,41 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn should_allow_set_request_lifetime_by_root() {
+		basic_initialization().execute_with(|| {
+			Module::<TestRuntime>::set_request_lifetime(
+				frame_system::RawOrigin::Root.into(),
+				100,
+			).unwrap();
+
+			assert_eq!(
+				RequestLifetime::<TestRuntime>::get(),
+				100,
+			);
+		});
+	}
+
+	#[test]
+	fn should_forbid_set_request_lifetime_by_non_root() {
+		basic_initialization().execute_with(|| {
+			Module::<TestRuntime>::set_request_lifetime(
+				Origin::signed(OWNER),
+				100,
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_forbid_zero_request_lifetime() {
+		basic_initialization().execute_with(|| {
+			Module::<TestRuntime>::set_request_lifetime(
+				frame_system::RawOrigin::Root.into(),
+				0,
+			).unwrap_err();
+		});
+	}
+
 	#[test]
 	fn should_allow_claim_id() {
 		basic_initialization().execute_with(|| {
@@ -0,0 +1,215 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations that are applied on runtime upgrade.
+//!
+//! Requests that have been created by the first version of the module are not
+//! holding reserved fee (the fee has been paid to key servers when the request
+//! was created) and can't be decoded using current request types. So instead of
+//! converting these requests, we're removing them from the queues and notify
+//! clients that requests have expired. The first version also had no request
+//! lifetime, so it is initialized with the default value.
+//!
+//! Versions before the second were not maintaining number of key servers in the
+//! current set, so it is computed from the set itself.
+
+use frame_support::{IterableStorageMap, StorageValue, StorageMap, StorageDoubleMap};
+use sp_runtime::traits::Zero;
+use super::{
+	DEFAULT_REQUEST_LIFETIME,
+	Trait, Module, Event, StorageVersion, RequestLifetime, CurrentKeyServers, CurrentKeyServersCount,
+	ServerKeyGenerationRequestsKeys, ServerKeyGenerationRequests, ServerKeyGenerationResponses,
+	ServerKeyRetrievalRequestsKeys, ServerKeyRetrievalRequests,
+	ServerKeyRetrievalResponses, ServerKeyRetrievalThresholdResponses,
+	DocumentKeyStoreRequestsKeys, DocumentKeyStoreRequests, DocumentKeyStoreResponses,
+	DocumentKeyShadowRetrievalRequestsKeys, DocumentKeyShadowRetrievalRequests,
+	DocumentKeyShadowRetrievalCommonResponses, DocumentKeyShadowRetrievalPersonalResponses,
+};
+
+/// Current version of the module storage.
+pub const CURRENT_STORAGE_VERSION: u32 = 2;
+
+/// Migrate module storage to the current version.
+pub fn migrate<T: Trait>() {
+	if StorageVersion::get() == 0 {
+		drain_unescrowed_requests::<T>();
+	}
+
+	// requests would expire at the block they're created at
+	if RequestLifetime::<T>::get().is_zero() {
+		RequestLifetime::<T>::put(<T as frame_system::Trait>::BlockNumber::from(DEFAULT_REQUEST_LIFETIME));
+	}
+
+	if StorageVersion::get() < 2 {
+		CurrentKeyServersCount::put(CurrentKeyServers::iter().count() as u32);
+	}
+
+	StorageVersion::put(CURRENT_STORAGE_VERSION);
+}
+
+/// Remove all requests that have been created before service fees have been reserved.
+fn drain_unescrowed_requests<T: Trait>() {
+	for id in ServerKeyGenerationRequestsKeys::take() {
+		ServerKeyGenerationResponses::remove_prefix(&id);
+		ServerKeyGenerationRequests::<T>::remove(&id);
+		Module::<T>::deposit_event(Event::ServerKeyGenerationExpired(id));
+	}
+
+	for id in ServerKeyRetrievalRequestsKeys::take() {
+		ServerKeyRetrievalResponses::remove_prefix(&id);
+		ServerKeyRetrievalThresholdResponses::remove_prefix(&id);
+		ServerKeyRetrievalRequests::<T>::remove(&id);
+		Module::<T>::deposit_event(Event::ServerKeyRetrievalExpired(id));
+	}
+
+	for id in DocumentKeyStoreRequestsKeys::take() {
+		DocumentKeyStoreResponses::remove_prefix(&id);
+		DocumentKeyStoreRequests::<T>::remove(&id);
+		Module::<T>::deposit_event(Event::DocumentKeyStoreExpired(id));
+	}
+
+	for (id, requester) in DocumentKeyShadowRetrievalRequestsKeys::take() {
+		DocumentKeyShadowRetrievalCommonResponses::remove_prefix(&(id, requester));
+		DocumentKeyShadowRetrievalPersonalResponses::remove_prefix(&(id, requester));
+		DocumentKeyShadowRetrievalRequests::<T>::remove(&(id, requester));
+		Module::<T>::deposit_event(Event::DocumentKeyShadowRetrievalExpired(id, requester));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use primitives::{EntityId, ServerKeyId};
+	use crate::mock::*;
+	use super::*;
+
+	const KEY: [u8; 32] = [32; 32];
+
+	fn requester() -> EntityId {
+		REAL_REQUESTER1_ADDRESS.into()
+	}
+
+	fn insert_legacy_requests() {
+		// legacy requests can't be decoded using current types, so we're inserting raw values
+		let key: ServerKeyId = KEY.into();
+		let legacy_request = vec![42u8; 16];
+		ServerKeyGenerationRequestsKeys::put(vec![key]);
+		sp_io::storage::set(&ServerKeyGenerationRequests::<TestRuntime>::hashed_key_for(&key), &legacy_request);
+		ServerKeyGenerationResponses::insert(key, sp_core::H512::from_low_u64_be(1), 1);
+		ServerKeyRetrievalRequestsKeys::put(vec![key]);
+		sp_io::storage::set(&ServerKeyRetrievalRequests::<TestRuntime>::hashed_key_for(&key), &legacy_request);
+		ServerKeyRetrievalThresholdResponses::insert(key, 1, 1);
+		DocumentKeyStoreRequestsKeys::put(vec![key]);
+		sp_io::storage::set(&DocumentKeyStoreRequests::<TestRuntime>::hashed_key_for(&key), &legacy_request);
+		DocumentKeyStoreResponses::insert(key, (), 1);
+		DocumentKeyShadowRetrievalRequestsKeys::put(vec![(key, requester())]);
+		sp_io::storage::set(
+			&DocumentKeyShadowRetrievalRequests::<TestRuntime>::hashed_key_for(&(key, requester())),
+			&legacy_request,
+		);
+		DocumentKeyShadowRetrievalCommonResponses::insert((key, requester()), (sp_core::H512::from_low_u64_be(1), 1), 1);
+	}
+
+	#[test]
+	fn should_set_current_storage_version_at_genesis() {
+		basic_initialization().execute_with(|| {
+			assert_eq!(StorageVersion::get(), CURRENT_STORAGE_VERSION);
+		});
+	}
+
+	#[test]
+	fn should_drain_legacy_requests() {
+		basic_initialization().execute_with(|| {
+			StorageVersion::put(0);
+			insert_legacy_requests();
+
+			migrate::<TestRuntime>();
+
+			let key: ServerKeyId = KEY.into();
+			assert_eq!(StorageVersion::get(), CURRENT_STORAGE_VERSION);
+			assert_eq!(ServerKeyGenerationRequestsKeys::get(), vec![]);
+			assert!(!ServerKeyGenerationRequests::<TestRuntime>::contains_key(&key));
+			assert_eq!(ServerKeyGenerationResponses::iter_prefix(&key).collect::<Vec<_>>(), vec![]);
+			assert_eq!(ServerKeyRetrievalRequestsKeys::get(), vec![]);
+			assert!(!ServerKeyRetrievalRequests::<TestRuntime>::contains_key(&key));
+			assert_eq!(ServerKeyRetrievalThresholdResponses::iter_prefix(&key).collect::<Vec<_>>(), vec![]);
+			assert_eq!(DocumentKeyStoreRequestsKeys::get(), vec![]);
+			assert!(!DocumentKeyStoreRequests::<TestRuntime>::contains_key(&key));
+			assert_eq!(DocumentKeyStoreResponses::iter_prefix(&key).collect::<Vec<_>>(), vec![]);
+			assert_eq!(DocumentKeyShadowRetrievalRequestsKeys::get(), vec![]);
+			assert!(!DocumentKeyShadowRetrievalRequests::<TestRuntime>::contains_key(&(key, requester())));
+			assert_eq!(
+				DocumentKeyShadowRetrievalCommonResponses::iter_prefix(&(key, requester())).collect::<Vec<_>>(),
+				vec![],
+			);
+
+			// check that clients have been notified
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::DocumentKeyShadowRetrievalExpired(key, requester()).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_initialize_request_lifetime() {
+		default_initialization().execute_with(|| {
+			// the first version of the module had no request lifetime
+			StorageVersion::put(0);
+			RequestLifetime::<TestRuntime>::put(0);
+
+			migrate::<TestRuntime>();
+			assert_eq!(RequestLifetime::<TestRuntime>::get(), DEFAULT_REQUEST_LIFETIME as u64);
+
+			// request that is created after migration is not expired in the next block
+			let key: ServerKeyId = KEY.into();
+			crate::ServerKeyGenerationService::<TestRuntime>::generate(
+				Origin::signed(REQUESTER1),
+				key,
+				1,
+			).unwrap();
+			let next_block = frame_system::Module::<TestRuntime>::block_number() + 1;
+			crate::ServerKeyGenerationService::<TestRuntime>::expire_requests(next_block);
+			assert_eq!(ServerKeyGenerationRequestsKeys::get(), vec![key]);
+			assert!(ServerKeyGenerationRequests::<TestRuntime>::contains_key(&key));
+		});
+	}
+
+	#[test]
+	fn should_initialize_current_key_servers_count() {
+		default_initialization().execute_with(|| {
+			// versions before the second had no key servers count
+			StorageVersion::put(1);
+			CurrentKeyServersCount::kill();
+
+			migrate::<TestRuntime>();
+			assert_eq!(CurrentKeyServersCount::get(), 2);
+			assert_eq!(crate::weights::current_key_servers_count::<TestRuntime>(), 2);
+		});
+	}
+
+	#[test]
+	fn should_not_drain_requests_when_storage_is_up_to_date() {
+		basic_initialization().execute_with(|| {
+			let key: ServerKeyId = KEY.into();
+			ServerKeyGenerationRequestsKeys::put(vec![key]);
+
+			migrate::<TestRuntime>();
+
+			assert_eq!(ServerKeyGenerationRequestsKeys::get(), vec![key]);
+		});
+	}
+}
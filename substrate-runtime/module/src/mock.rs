@@ -94,8 +94,12 @@ impl pallet_balances::Trait for TestRuntime {
 impl Trait for TestRuntime {
 	type Event = TestEvent;
 	type Currency = pallet_balances::Module<Self>;
+	type KeyAccessPolicy = AllowListPolicy;
+	type WeightInfo = ();
 }
 
+pub const REQUEST_LIFETIME: u64 = 10;
+
 pub const OWNER: u64 = 1;
 pub const REQUESTER1: u64 = 2;
 pub const REQUESTER2: u64 = 3;
@@ -182,8 +186,12 @@ fn initialize(
 		],
 		server_key_generation_fee: 1_000_000,
 		server_key_retrieval_fee: 1_000_000,
+		server_key_deletion_fee: 1_000_000,
 		document_key_store_fee: 1_000_000,
 		document_key_shadow_retrieval_fee: 1_000_000,
+		schnorr_signing_fee: 1_000_000,
+		ecdsa_signing_fee: 1_000_000,
+		request_lifetime: REQUEST_LIFETIME,
 	};
 	config.assimilate_storage(&mut t).unwrap();
 	let config = pallet_balances::GenesisConfig::<TestRuntime> {
@@ -198,6 +206,14 @@ fn initialize(
 	t.into()
 }
 
+pub fn free_balance(account: u64) -> u64 {
+	<pallet_balances::Module<TestRuntime> as frame_support::traits::Currency<u64>>::free_balance(&account)
+}
+
+pub fn reserved_balance(account: u64) -> u64 {
+	<pallet_balances::Module<TestRuntime> as frame_support::traits::ReservableCurrency<u64>>::reserved_balance(&account)
+}
+
 pub fn basic_initialization() -> sp_io::TestExternalities {
 	initialize(false, default_key_server_set())
 }
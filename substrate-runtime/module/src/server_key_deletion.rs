@@ -0,0 +1,513 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Contains actual implementation of all public/private module methods
+//! for deleting server keys.
+
+use codec::{Encode, Decode};
+use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure, weights::Weight};
+use primitives::{EntityId, ServerKeyId, KeyServerId};
+use frame_system::ensure_signed;
+use crate::service::{Responses, ResponseSupport, RequestEscrow, SecretStoreService};
+use crate::weights::current_key_servers_count;
+use super::{
+	Trait, Module, Event, WeightInfo,
+	KeyOwners, KeyAccessGrants, KeyAccessPolicies, KeyApprovers, KeyAccessApprovals, DeletedServerKeys,
+	ServerKeyDeletionFee,
+	ServerKeyDeletionRequests, ServerKeyDeletionRequestsKeys,
+	ServerKeyDeletionResponses,
+	resolve_entity_id,
+};
+
+/// Maximal number of active requests in the queue.
+const MAX_REQUESTS: u64 = 8;
+
+/// Structure that describes server key deletion request with responses meta.
+#[derive(Decode, Encode)]
+pub struct ServerKeyDeletionRequest<AccountId, Balance, Number> {
+	/// The author of this request. It must be the same author as in the
+	/// server key generation request.
+	pub author: EntityId,
+	/// Responses metadata.
+	pub responses: Responses<Number>,
+	/// Reserved service fee.
+	pub escrow: RequestEscrow<AccountId, Balance, Number>,
+}
+
+/// Implementation of server key deletion service.
+pub struct ServerKeyDeletionService<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Trait> ServerKeyDeletionService<T> {
+	/// Request deletion of server key.
+	pub fn delete(
+		origin: T::Origin,
+		id: ServerKeyId,
+	) -> Result<(), &'static str> {
+		// limit number of requests in the queue
+		ensure!(
+			(ServerKeyDeletionRequestsKeys::decode_len()? as u64) < MAX_REQUESTS,
+			"Too many active requests. Try later",
+		);
+
+		// check if the key is not deleted yet
+		ensure!(
+			!DeletedServerKeys::contains_key(id),
+			"The server key has been deleted",
+		);
+
+		// check if there are no pending request for the same key
+		ensure!(
+			!ServerKeyDeletionRequests::<T>::contains_key(id),
+			"The same request is already queued",
+		);
+
+		// reserve service fee
+		let origin = ensure_signed(origin)?;
+		let author = resolve_entity_id::<T>(&origin)?;
+		let fee = ServerKeyDeletionFee::<T>::get();
+		let escrow = SecretStoreService::<T>::reserve_service_fee(&origin, fee)?;
+
+		// insert request to the queue
+		let request = ServerKeyDeletionRequest {
+			author: author.clone(),
+			responses: SecretStoreService::<T>::new_responses(),
+			escrow,
+		};
+		ServerKeyDeletionRequests::<T>::insert(id, request);
+		ServerKeyDeletionRequestsKeys::append(sp_std::iter::once(&id))?;
+
+		// emit event
+		Module::<T>::deposit_event(Event::ServerKeyDeletionRequested(id, author));
+
+		Ok(())
+	}
+
+	/// Called when deletion is reported by key server.
+	pub fn on_deleted(
+		origin: T::Origin,
+		id: ServerKeyId,
+	) -> Result<(), &'static str> {
+		// check if this request is active (the tx could arrive when request is already inactive)
+		let mut request = match ServerKeyDeletionRequests::<T>::get(id) {
+			Some(request) => request,
+			None => return Ok(()),
+		};
+
+		// insert response (we're waiting for responses from all authorities here)
+		let key_servers_count = SecretStoreService::<T>::key_servers_count()?;
+		let key_server_index = SecretStoreService::<T>::key_server_index_from_origin(origin)?;
+		let response_support = SecretStoreService::<T>::insert_response::<_, _, ServerKeyDeletionResponses>(
+			key_server_index,
+			key_servers_count - 1,
+			&mut request.responses,
+			&id,
+			&(),
+		)?;
+
+		// check if response is confirmed
+		match response_support {
+			ResponseSupport::Unconfirmed => {
+				ServerKeyDeletionRequests::<T>::insert(id, request);
+			},
+			ResponseSupport::Confirmed => {
+				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
+				delete_request::<T>(&id);
+
+				// remember that the key has been deleted and forget everything about it
+				DeletedServerKeys::insert(id, true);
+				KeyOwners::remove(id);
+				KeyAccessGrants::<T>::remove_prefix(id);
+				KeyAccessPolicies::remove(id);
+				KeyApprovers::remove(id);
+				KeyAccessApprovals::remove_prefix(id);
+
+				// emit event
+				Module::<T>::deposit_event(Event::ServerKeyDeleted(id));
+			},
+			ResponseSupport::Impossible => unreachable!("we're receiving the same response from all servers; qed"),
+		}
+
+		Ok(())
+	}
+
+	/// Called when error occurs during server key deletion.
+	pub fn on_deletion_error(
+		origin: T::Origin,
+		id: ServerKeyId,
+	) -> Result<(), &'static str> {
+		// check that it is reported by the key server
+		let _ = SecretStoreService::<T>::key_server_index_from_origin(origin)?;
+
+		// check if this request is active (the tx could arrive when request is already inactive)
+		let request = match ServerKeyDeletionRequests::<T>::get(id) {
+			Some(request) => request,
+			None => return Ok(()),
+		};
+
+		// any error in key deletion is fatal, because we need all key servers to participate in deletion
+		// => delete request and fire event
+		SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
+		delete_request::<T>(&id);
+
+		Module::<T>::deposit_event(Event::ServerKeyDeletionError(id));
+		Ok(())
+	}
+
+	/// Expire all requests that have not been completed until given block. Returns weight
+	/// of the operation.
+	pub fn expire_requests(now: <T as frame_system::Trait>::BlockNumber) -> Weight {
+		let mut active_requests = 0;
+		let mut expired_requests = 0;
+		for id in ServerKeyDeletionRequestsKeys::get() {
+			let request = match ServerKeyDeletionRequests::<T>::get(&id) {
+				Some(request) if request.escrow.expires_at <= now => request,
+				_ => {
+					active_requests += 1;
+					continue;
+				},
+			};
+			expired_requests += 1;
+
+			// return fee to the requester and fire event
+			SecretStoreService::<T>::refund_service_fee(&request.escrow, &request.responses);
+			delete_request::<T>(&id);
+
+			Module::<T>::deposit_event(Event::ServerKeyDeletionExpired(id));
+		}
+
+		T::WeightInfo::expire_requests(active_requests, expired_requests, current_key_servers_count::<T>())
+	}
+
+	/// Returns true if response from given key server is required to complete request.
+	pub fn is_response_required(
+		key_server: KeyServerId,
+		id: ServerKeyId,
+	) -> bool {
+		ServerKeyDeletionRequests::<T>::get(&id)
+			.map(|request| SecretStoreService::<T>::is_response_required(
+				key_server,
+				&request.responses,
+			))
+			.unwrap_or(false)
+	}
+}
+
+/// Deletes request and all associated data.
+fn delete_request<T: Trait>(request: &ServerKeyId) {
+	ServerKeyDeletionResponses::remove_prefix(request);
+	ServerKeyDeletionRequests::<T>::remove(request);
+	ServerKeyDeletionRequestsKeys::mutate(|list| {
+		let index = list.iter().position(|lrequest| lrequest == request);
+		if let Some(index) = index {
+			list.swap_remove(index);
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::mock::*;
+	use super::*;
+
+	fn ensure_clean_storage(key: ServerKeyId) {
+		assert_eq!(ServerKeyDeletionRequestsKeys::get(), vec![]);
+		assert!(!ServerKeyDeletionRequests::<TestRuntime>::contains_key(key));
+		assert_eq!(
+			ServerKeyDeletionResponses::iter_prefix(key).collect::<Vec<_>>(),
+			vec![],
+		);
+	}
+
+	#[test]
+	fn should_accept_server_key_deletion_request() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+
+			// check that event has been emitted
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::ServerKeyDeletionRequested(
+						[32; 32].into(),
+						[REQUESTER1 as u8; 20].into(),
+					).into())
+					.is_some(),
+			);
+		});
+	}
+
+	#[test]
+	fn should_reject_server_key_deletion_request_when_fee_is_not_paid() {
+		default_initialization().execute_with(|| {
+			// REQUESTER2 has no enough funds
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER2),
+				[32; 32].into(),
+			).unwrap_err();
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_reject_server_key_deletion_request_when_limit_reached() {
+		default_initialization().execute_with(|| {
+			// make MAX_REQUESTS requests
+			for i in 0..MAX_REQUESTS {
+				ServerKeyDeletionService::<TestRuntime>::delete(
+					Origin::signed(REQUESTER1),
+					[i as u8; 32].into(),
+				).unwrap();
+			}
+
+			// and now try to push new request so that there will be more than a limit requests
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[MAX_REQUESTS as u8; 32].into(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_reject_duplicated_server_key_deletion_request() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+
+			// ask to delete server key again
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_publish_server_key_deletion_confirmation() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+			let events_count = frame_system::Module::<TestRuntime>::events().len();
+
+			// response from key server 1 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deleted(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+			).unwrap();
+			// => no new events generated
+			assert_eq!(
+				events_count,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+			assert!(!DeletedServerKeys::contains_key(ServerKeyId::from([32; 32])));
+
+			// response from key server 2 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deleted(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+			).unwrap();
+			// => new event is generated
+			assert_eq!(
+				events_count + 1,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::ServerKeyDeleted([32; 32].into()).into())
+					.is_some(),
+			);
+			assert!(DeletedServerKeys::contains_key(ServerKeyId::from([32; 32])));
+
+			// and then another response from key server 2 is received (and ignored without error)
+			ServerKeyDeletionService::<TestRuntime>::on_deleted(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+			).unwrap();
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_reject_deletion_request_for_deleted_key() {
+		default_initialization().execute_with(|| {
+			DeletedServerKeys::insert(ServerKeyId::from([32; 32]), true);
+
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap_err();
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_not_accept_deletion_confirmation_from_non_key_server() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+
+			// response from key server 3 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deleted(
+				Origin::signed(KEY_SERVER3),
+				[32; 32].into(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_raise_deletion_error_when_at_least_one_server_has_responded_with_error() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+			let events_count = frame_system::Module::<TestRuntime>::events().len();
+
+			// error from key server 1 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deletion_error(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+			).unwrap();
+
+			// check that deletion error is published
+			assert_eq!(
+				events_count + 1,
+				frame_system::Module::<TestRuntime>::events().len(),
+			);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::ServerKeyDeletionError([32; 32].into()).into())
+					.is_some(),
+			);
+			assert!(!DeletedServerKeys::contains_key(ServerKeyId::from([32; 32])));
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+
+	#[test]
+	fn should_fail_if_deletion_error_is_reported_by_non_key_server() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+
+			// error from REQUESTER1 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deletion_error(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap_err();
+		});
+	}
+
+	#[test]
+	fn should_return_if_deletion_response_is_required() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+
+			// response from all key servers is required
+			assert!(ServerKeyDeletionService::<TestRuntime>::is_response_required(
+				KEY_SERVER0_ID.into(),
+				[32; 32].into(),
+			));
+			assert!(ServerKeyDeletionService::<TestRuntime>::is_response_required(
+				KEY_SERVER1_ID.into(),
+				[32; 32].into(),
+			));
+
+			// response from key server 1 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deleted(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+			).unwrap();
+
+			// response from key server 2 is required
+			assert!(!ServerKeyDeletionService::<TestRuntime>::is_response_required(
+				KEY_SERVER0_ID.into(),
+				[32; 32].into(),
+			));
+			assert!(ServerKeyDeletionService::<TestRuntime>::is_response_required(
+				KEY_SERVER1_ID.into(),
+				[32; 32].into(),
+			));
+
+			// response from key server 2 is received
+			ServerKeyDeletionService::<TestRuntime>::on_deleted(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+			).unwrap();
+
+			// no responses are required
+			assert!(!ServerKeyDeletionService::<TestRuntime>::is_response_required(
+				KEY_SERVER0_ID.into(),
+				[32; 32].into(),
+			));
+			assert!(!ServerKeyDeletionService::<TestRuntime>::is_response_required(
+				KEY_SERVER1_ID.into(),
+				[32; 32].into(),
+			));
+		});
+	}
+
+	#[test]
+	fn should_expire_server_key_deletion_request() {
+		default_initialization().execute_with(|| {
+			// ask to delete server key
+			ServerKeyDeletionService::<TestRuntime>::delete(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+			let expires_at = frame_system::Module::<TestRuntime>::block_number() + REQUEST_LIFETIME;
+
+			// request is expired at deadline
+			ServerKeyDeletionService::<TestRuntime>::expire_requests(expires_at);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::ServerKeyDeletionExpired([32; 32].into()).into())
+					.is_some(),
+			);
+
+			// => nobody has responded, so the whole fee is returned to requester
+			assert_eq!(free_balance(REQUESTER1), 10_000_000);
+			assert_eq!(reserved_balance(REQUESTER1), 0);
+			assert!(!DeletedServerKeys::contains_key(ServerKeyId::from([32; 32])));
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
+}
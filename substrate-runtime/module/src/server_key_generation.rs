@@ -18,12 +18,14 @@
 //! for server key generation.
 
 use codec::{Encode, Decode};
-use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure};
+use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure, weights::Weight};
 use primitives::{EntityId, ServerKeyId, KeyServerId};
 use frame_system::ensure_signed;
-use crate::service::{Responses, ResponseSupport, SecretStoreService};
+use crate::service::{Responses, ResponseSupport, RequestEscrow, SecretStoreService};
+use crate::weights::current_key_servers_count;
 use super::{
-	Trait, Module, Event,
+	Trait, Module, Event, WeightInfo,
+	DeletedServerKeys,
 	ServerKeyGenerationFee,
 	ServerKeyGenerationRequests, ServerKeyGenerationRequestsKeys,
 	ServerKeyGenerationResponses,
@@ -35,13 +37,15 @@ const MAX_REQUESTS: u64 = 4;
 
 /// Structure that describes server key generation request with responses meta.
 #[derive(Decode, Encode)]
-pub struct ServerKeyGenerationRequest<Number> {
+pub struct ServerKeyGenerationRequest<AccountId, Balance, Number> {
 	/// The author of this request.
 	pub author: EntityId,
 	/// The threshold of the key we're generating.
 	pub threshold: u8,
 	/// Responses metadata.
 	pub responses: Responses<Number>,
+	/// Reserved service fee.
+	pub escrow: RequestEscrow<AccountId, Balance, Number>,
 }
 
 /// Implementation of server key generation service.
@@ -74,17 +78,24 @@ impl<T: Trait> ServerKeyGenerationService<T> {
 			"The same request is already queued",
 		);
 
-		// collect service fee
+		// deleted keys can't be generated again
+		ensure!(
+			!DeletedServerKeys::contains_key(id),
+			"The server key has been deleted",
+		);
+
+		// reserve service fee
 		let origin = ensure_signed(origin)?;
+		let author = resolve_entity_id::<T>(&origin)?;
 		let fee = ServerKeyGenerationFee::<T>::get();
-		SecretStoreService::<T>::collect_service_fee(&origin, fee)?;
+		let escrow = SecretStoreService::<T>::reserve_service_fee(&origin, fee)?;
 
 		// insert request to the queue
-		let author = resolve_entity_id::<T>(&origin)?;
 		let request = ServerKeyGenerationRequest {
 			author,
 			threshold,
 			responses: SecretStoreService::<T>::new_responses(),
+			escrow,
 		};
 		ServerKeyGenerationRequests::<T>::insert(id, request);
 		ServerKeyGenerationRequestsKeys::append(sp_std::iter::once(&id))?;
@@ -125,6 +136,7 @@ impl<T: Trait> ServerKeyGenerationService<T> {
 			},
 			ResponseSupport::Confirmed => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&id);
 
 				// emit event
@@ -132,6 +144,7 @@ impl<T: Trait> ServerKeyGenerationService<T> {
 			},
 			ResponseSupport::Impossible => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&id);
 
 				// emit event
@@ -151,19 +164,45 @@ impl<T: Trait> ServerKeyGenerationService<T> {
 		let _ = SecretStoreService::<T>::key_server_index_from_origin(origin)?;
 
 		// check if this request is active (the tx could arrive when request is already inactive)
-		let _request = match ServerKeyGenerationRequests::<T>::get(id) {
+		let request = match ServerKeyGenerationRequests::<T>::get(id) {
 			Some(request) => request,
 			None => return Ok(()),
 		};
 
 		// any error in key generation is fatal, because we need all key servers to participate in generation
 		// => delete request and fire event
+		SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 		delete_request::<T>(&id);
 
 		Module::<T>::deposit_event(Event::ServerKeyGenerationError(id));
 		Ok(())
 	}
 
+	/// Expire all requests that have not been completed until given block. Returns weight
+	/// of the operation.
+	pub fn expire_requests(now: <T as frame_system::Trait>::BlockNumber) -> Weight {
+		let mut active_requests = 0;
+		let mut expired_requests = 0;
+		for id in ServerKeyGenerationRequestsKeys::get() {
+			let request = match ServerKeyGenerationRequests::<T>::get(&id) {
+				Some(request) if request.escrow.expires_at <= now => request,
+				_ => {
+					active_requests += 1;
+					continue;
+				},
+			};
+			expired_requests += 1;
+
+			// return fee to the requester and fire event
+			SecretStoreService::<T>::refund_service_fee(&request.escrow, &request.responses);
+			delete_request::<T>(&id);
+
+			Module::<T>::deposit_event(Event::ServerKeyGenerationExpired(id));
+		}
+
+		T::WeightInfo::expire_requests(active_requests, expired_requests, current_key_servers_count::<T>())
+	}
+
 	/// Returns true if response from given key server is required to complete request.
 	pub fn is_response_required(
 		key_server: KeyServerId,
@@ -293,6 +332,22 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn should_reject_server_key_generation_request_for_deleted_key() {
+		default_initialization().execute_with(|| {
+			DeletedServerKeys::insert(ServerKeyId::from([32; 32]), true);
+
+			// ask to generate server key
+			ServerKeyGenerationService::<TestRuntime>::generate(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				1,
+			).unwrap_err();
+
+			assert_eq!(ServerKeyGenerationRequestsKeys::get(), vec![]);
+		});
+	}
+
 	#[test]
 	fn should_publish_generated_server_key() {
 		default_initialization().execute_with(|| {
@@ -536,4 +591,115 @@ mod tests {
 			ensure_clean_storage([32; 32].into());
 		});
 	}
+
+	#[test]
+	fn should_pay_reserved_fee_when_server_key_is_generated() {
+		default_initialization().execute_with(|| {
+			// ask to generate server key
+			ServerKeyGenerationService::<TestRuntime>::generate(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				1,
+			).unwrap();
+
+			// fee is reserved until key is generated
+			assert_eq!(free_balance(REQUESTER1), 9_000_000);
+			assert_eq!(reserved_balance(REQUESTER1), 1_000_000);
+
+			// responses from both key servers are received
+			ServerKeyGenerationService::<TestRuntime>::on_generated(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				[42; 64].into(),
+			).unwrap();
+			ServerKeyGenerationService::<TestRuntime>::on_generated(
+				Origin::signed(KEY_SERVER1),
+				[32; 32].into(),
+				[42; 64].into(),
+			).unwrap();
+
+			// => fee is splitted among key servers
+			assert_eq!(free_balance(REQUESTER1), 9_000_000);
+			assert_eq!(reserved_balance(REQUESTER1), 0);
+			assert_eq!(free_balance(KEY_SERVER0), 500_000);
+			assert_eq!(free_balance(KEY_SERVER1), 500_000);
+		});
+	}
+
+	#[test]
+	fn should_keep_server_key_generation_request_when_fee_can_not_be_paid() {
+		default_initialization().execute_with(|| {
+			// ask to generate server key
+			ServerKeyGenerationService::<TestRuntime>::generate(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				1,
+			).unwrap();
+
+			// key server 1 has lost its claim => fee can't be paid
+			let key_server1: EntityId = KEY_SERVER1_ID.into();
+			crate::ClaimedBy::<TestRuntime>::remove(&key_server1);
+
+			// => response from key server 0 is rejected
+			assert!(ServerKeyGenerationService::<TestRuntime>::on_generated(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				[42; 64].into(),
+			).is_err());
+			assert!(ServerKeyGenerationService::<TestRuntime>::on_generation_error(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+			).is_err());
+
+			// => request and reserved fee are kept
+			assert_eq!(ServerKeyGenerationRequestsKeys::get(), vec![[32; 32].into()]);
+			let key: ServerKeyId = [32; 32].into();
+			assert_eq!(
+				ServerKeyGenerationResponses::iter_prefix(key).collect::<Vec<_>>(),
+				vec![],
+			);
+			assert_eq!(reserved_balance(REQUESTER1), 1_000_000);
+			assert_eq!(free_balance(KEY_SERVER0), 0);
+		});
+	}
+
+	#[test]
+	fn should_expire_server_key_generation_request() {
+		default_initialization().execute_with(|| {
+			// ask to generate server key
+			ServerKeyGenerationService::<TestRuntime>::generate(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+				1,
+			).unwrap();
+			let expires_at = frame_system::Module::<TestRuntime>::block_number() + REQUEST_LIFETIME;
+
+			// response from key server 1 is received
+			ServerKeyGenerationService::<TestRuntime>::on_generated(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				[42; 64].into(),
+			).unwrap();
+
+			// request is not expired before deadline
+			ServerKeyGenerationService::<TestRuntime>::expire_requests(expires_at - 1);
+			assert_eq!(ServerKeyGenerationRequestsKeys::get(), vec![[32; 32].into()]);
+
+			// request is expired at deadline
+			ServerKeyGenerationService::<TestRuntime>::expire_requests(expires_at);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::ServerKeyGenerationExpired([32; 32].into()).into())
+					.is_some(),
+			);
+
+			// => key server that has responded receives its share and the rest is returned to requester
+			assert_eq!(free_balance(REQUESTER1), 9_500_000);
+			assert_eq!(reserved_balance(REQUESTER1), 0);
+			assert_eq!(free_balance(KEY_SERVER0), 500_000);
+			assert_eq!(free_balance(KEY_SERVER1), 0);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
 }
@@ -18,12 +18,13 @@
 //! for server key retrieval.
 
 use codec::{Encode, Decode};
-use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure};
+use frame_support::{StorageValue, StorageMap, StorageDoubleMap, ensure, weights::Weight};
 use primitives::{KeyServerId, ServerKeyId};
 use frame_system::ensure_signed;
-use crate::service::{Responses, ResponseSupport, SecretStoreService};
+use crate::service::{Responses, ResponseSupport, RequestEscrow, SecretStoreService};
+use crate::weights::current_key_servers_count;
 use super::{
-	Trait, Module, Event,
+	Trait, Module, Event, WeightInfo, BalanceOf,
 	ServerKeyRetrievalFee,
 	ServerKeyRetrievalRequests, ServerKeyRetrievalRequestsKeys,
 	ServerKeyRetrievalResponses, ServerKeyRetrievalThresholdResponses,
@@ -37,13 +38,15 @@ const INVALID_THRESHOLD: u8 = 0xFF;
 
 /// Structure that describes server key retrieval request with responses meta.
 #[derive(Decode, Encode)]
-pub struct ServerKeyRetrievalRequest<Number> {
+pub struct ServerKeyRetrievalRequest<AccountId, Balance, Number> {
 	/// Threshold responses metadata.
 	pub threshold_responses: Responses<Number>,
 	/// Responses metadata.
 	pub responses: Responses<Number>,
 	/// Retrieved server key public with max support.
 	pub server_key_with_max_threshold: sp_core::H512,
+	/// Reserved service fee.
+	pub escrow: RequestEscrow<AccountId, Balance, Number>,
 }
 
 /// Implementation of server key retrieval service.
@@ -67,10 +70,10 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 			"The same request is already queued",
 		);
 
-		// collect service fee
+		// reserve service fee
 		let origin = ensure_signed(origin)?;
 		let fee = ServerKeyRetrievalFee::<T>::get();
-		SecretStoreService::<T>::collect_service_fee(&origin, fee)?;
+		let escrow = SecretStoreService::<T>::reserve_service_fee(&origin, fee)?;
 
 		// we do not know exact threshold value here && we can not blindly trust the first response
 		// => we should agree upon two values: threshold && server key itself
@@ -82,6 +85,7 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 			threshold_responses: SecretStoreService::<T>::new_responses(),
 			responses: SecretStoreService::<T>::new_responses(),
 			server_key_with_max_threshold: Default::default(),
+			escrow,
 		};
 		ServerKeyRetrievalRequests::<T>::insert(id, request);
 		ServerKeyRetrievalRequestsKeys::append(sp_std::iter::once(&id))?;
@@ -121,6 +125,7 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 			},
 			ResponseSupport::Confirmed => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&id);
 
 				// emit event
@@ -128,6 +133,7 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 			},
 			ResponseSupport::Impossible => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&id);
 
 				// emit event
@@ -167,6 +173,7 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 			},
 			ResponseSupport::Confirmed | ResponseSupport::Impossible => {
 				// we do not need this request anymore
+				SecretStoreService::<T>::pay_service_fee(&request.escrow)?;
 				delete_request::<T>(&id);
 
 				// emit event
@@ -177,6 +184,31 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 		Ok(())
 	}
 
+	/// Expire all requests that have not been completed until given block. Returns weight
+	/// of the operation.
+	pub fn expire_requests(now: <T as frame_system::Trait>::BlockNumber) -> Weight {
+		let mut active_requests = 0;
+		let mut expired_requests = 0;
+		for id in ServerKeyRetrievalRequestsKeys::get() {
+			let request = match ServerKeyRetrievalRequests::<T>::get(&id) {
+				Some(request) if request.escrow.expires_at <= now => request,
+				_ => {
+					active_requests += 1;
+					continue;
+				},
+			};
+			expired_requests += 1;
+
+			// return fee to the requester and fire event
+			SecretStoreService::<T>::refund_service_fee(&request.escrow, &request.threshold_responses);
+			delete_request::<T>(&id);
+
+			Module::<T>::deposit_event(Event::ServerKeyRetrievalExpired(id));
+		}
+
+		T::WeightInfo::expire_requests(active_requests, expired_requests, current_key_servers_count::<T>())
+	}
+
 	/// Returns true if response from given key server is required to complete request.
 	pub fn is_response_required(
 		key_server: KeyServerId,
@@ -194,11 +226,11 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 	fn insert_response(
 		origin: T::Origin,
 		id: ServerKeyId,
-		mut request: ServerKeyRetrievalRequest<<T as frame_system::Trait>::BlockNumber>,
+		mut request: ServerKeyRetrievalRequestOf<T>,
 		server_key_public: sp_core::H512,
 		threshold: u8,
 	) -> Result<(
-		ServerKeyRetrievalRequest<<T as frame_system::Trait>::BlockNumber>,
+		ServerKeyRetrievalRequestOf<T>,
 		ResponseSupport,
 		sp_core::H512
 	), &'static str> {
@@ -247,6 +279,13 @@ impl<T: Trait> ServerKeyRetrievalService<T> {
 	}
 }
 
+/// Server key retrieval request of given runtime.
+type ServerKeyRetrievalRequestOf<T> = ServerKeyRetrievalRequest<
+	<T as frame_system::Trait>::AccountId,
+	BalanceOf<T>,
+	<T as frame_system::Trait>::BlockNumber,
+>;
+
 /// Deletes request and all associated data.
 fn delete_request<T: Trait>(request: &ServerKeyId) {
 	ServerKeyRetrievalResponses::remove_prefix(request);
@@ -1003,4 +1042,39 @@ mod tests {
 			ensure_clean_storage([32; 32].into());
 		});
 	}
+
+	#[test]
+	fn should_expire_server_key_retrieval_request() {
+		default_initialization_with_five_servers().execute_with(|| {
+			// ask to retrieve server key
+			ServerKeyRetrievalService::<TestRuntime>::retrieve(
+				Origin::signed(REQUESTER1),
+				[32; 32].into(),
+			).unwrap();
+			let expires_at = frame_system::Module::<TestRuntime>::block_number() + REQUEST_LIFETIME;
+
+			// response from key server 1 is received
+			ServerKeyRetrievalService::<TestRuntime>::on_retrieved(
+				Origin::signed(KEY_SERVER0),
+				[32; 32].into(),
+				[42; 64].into(),
+				1,
+			).unwrap();
+
+			// request is expired at deadline
+			ServerKeyRetrievalService::<TestRuntime>::expire_requests(expires_at);
+			assert!(
+				frame_system::Module::<TestRuntime>::events().into_iter()
+					.find(|e| e.event == Event::ServerKeyRetrievalExpired([32; 32].into()).into())
+					.is_some(),
+			);
+
+			// => key server that has responded receives its share and the rest is returned to requester
+			assert_eq!(free_balance(REQUESTER1), 9_800_000);
+			assert_eq!(reserved_balance(REQUESTER1), 0);
+			assert_eq!(free_balance(KEY_SERVER0), 200_000);
+
+			ensure_clean_storage([32; 32].into());
+		});
+	}
 }
\ No newline at end of file
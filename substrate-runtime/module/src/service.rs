@@ -18,8 +18,8 @@
 
 use frame_system::ensure_signed;
 use frame_support::{
-	traits::{Currency, ExistenceRequirement},
-	IterableStorageMap, StorageValue, StorageMap, StorageDoubleMap,
+	traits::{Currency, ExistenceRequirement, ReservableCurrency},
+	IterableStorageMap, StorageValue, StorageMap, StorageDoubleMap, ensure,
 };
 use primitives::{KeyServerId, KeyServersMask};
 use sp_std::vec::Vec;
@@ -27,7 +27,8 @@ use super::{
 	BalanceOf,
 	Trait,
 	ClaimedId, ClaimedBy,
-	CurrentKeyServers, CurrentSetChangeBlock,
+	CurrentKeyServers, CurrentKeyServersCount, CurrentSetChangeBlock,
+	RequestLifetime,
 };
 use codec::{Decode, Encode, EncodeLike};
 
@@ -70,6 +71,19 @@ pub struct Responses<BlockNumber> {
 	pub max_response_support: u8,
 }
 
+/// The fee that has been reserved from requester account when request has been created.
+/// It is paid to key servers once request is served, or returned to requester (partially)
+/// if request expires.
+#[derive(Decode, Encode)]
+pub struct RequestEscrow<AccountId, Balance, BlockNumber> {
+	/// The account that has paid for this request.
+	pub payer: AccountId,
+	/// Reserved fee.
+	pub fee: Balance,
+	/// Number of block when request expires if it is still active.
+	pub expires_at: BlockNumber,
+}
+
 /// How's response is supported by the current key server set.
 #[derive(Debug, PartialEq)]
 pub enum ResponseSupport {
@@ -98,7 +112,7 @@ impl<T: Trait> SecretStoreService<T> {
 
 	/// Return number of key servers in the current set.
 	pub fn key_servers_count() -> Result<u8, &'static str> {
-		Ok(CurrentKeyServers::iter().count() as u8) // TODO: optimize?
+		Ok(CurrentKeyServersCount::get() as u8)
 	}
 
 	/// Get key server index from call origin.
@@ -115,6 +129,21 @@ impl<T: Trait> SecretStoreService<T> {
 			.ok_or("the caller is not a key server")
 	}
 
+	/// Ensure that service fee could be paid to key servers of the current set.
+	///
+	/// Module storage isn't transactional, so this must be checked before any changes are
+	/// made by the call that is going to pay the fee.
+	pub fn ensure_service_fee_payable() -> Result<(), &'static str> {
+		for (id, _) in CurrentKeyServers::iter() {
+			ensure!(
+				ClaimedBy::<T>::contains_key(&id),
+				"key server has not claimed id",
+			);
+		}
+
+		Ok(())
+	}
+
 	/// Deposit equal share of amount to each of key servers.
 	pub fn collect_service_fee(origin: &T::AccountId, fee: BalanceOf<T>) -> Result<(), &'static str> {
 		let key_servers_accounts = CurrentKeyServers::iter()
@@ -145,6 +174,69 @@ impl<T: Trait> SecretStoreService<T> {
 		Ok(())
 	}
 
+	/// Reserve service fee at requester account until request is completed or expired.
+	pub fn reserve_service_fee(
+		origin: &T::AccountId,
+		fee: BalanceOf<T>,
+	) -> Result<RequestEscrow<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>, &'static str> {
+		T::Currency::reserve(origin, fee)?;
+
+		Ok(RequestEscrow {
+			payer: origin.clone(),
+			fee,
+			expires_at: frame_system::Module::<T>::block_number() + RequestLifetime::<T>::get(),
+		})
+	}
+
+	/// Deposit reserved fee to key servers once request is completed.
+	///
+	/// Fails without touching the reserve if the fee can't be paid (e.g. some key server has
+	/// not claimed its id), so the request stays in the queue until it is expired or fee
+	/// becomes payable.
+	pub fn pay_service_fee(
+		escrow: &RequestEscrow<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>,
+	) -> Result<(), &'static str> {
+		Self::ensure_service_fee_payable()?;
+
+		T::Currency::unreserve(&escrow.payer, escrow.fee);
+		Self::collect_service_fee(&escrow.payer, escrow.fee)
+	}
+
+	/// Return reserved fee to requester once request is expired. Key servers from the current
+	/// set, that have already responded to the request, are still receiving their share.
+	pub fn refund_service_fee(
+		escrow: &RequestEscrow<T::AccountId, BalanceOf<T>, <T as frame_system::Trait>::BlockNumber>,
+		responses: &Responses<<T as frame_system::Trait>::BlockNumber>,
+	) {
+		T::Currency::unreserve(&escrow.payer, escrow.fee);
+
+		// responses that have been received from previous set are not counted
+		if responses.key_servers_change_block != CurrentSetChangeBlock::<T>::get() {
+			return;
+		}
+
+		let key_servers = CurrentKeyServers::iter().collect::<Vec<_>>();
+		if key_servers.is_empty() {
+			return;
+		}
+
+		let key_server_fee_share = escrow.fee / (key_servers.len() as u8).into();
+		for (id, key_server) in key_servers {
+			if !responses.responded_key_servers_mask.is_set(key_server.index) {
+				continue;
+			}
+
+			if let Some(key_server_account) = ClaimedBy::<T>::get(&id) {
+				let _ = T::Currency::transfer(
+					&escrow.payer,
+					&key_server_account,
+					key_server_fee_share,
+					ExistenceRequirement::AllowDeath,
+				);
+			}
+		}
+	}
+
 	/// Inserts key server response into Responses.
 	pub fn insert_response<RequestKey, Response, Map>(
 		key_server_index: u8,
@@ -157,8 +249,11 @@ impl<T: Trait> SecretStoreService<T> {
 		Response: EncodeLike,
 		Map: StorageDoubleMap<RequestKey, Response, u8, Query=u8>,
 	{
-		// early return (this is the only fn that can fail here)
+		// early return (these are the only fns that can fail here)
 		let key_servers_count = Self::key_servers_count()?;
+		// response may complete the request => fail before changing anything if we're unable to
+		// pay the service fee
+		Self::ensure_service_fee_payable()?;
 
 		// check that servers set is still the same (and all previous responses are valid)
 		let key_servers_change_block = CurrentSetChangeBlock::<T>::get();
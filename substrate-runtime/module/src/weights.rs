@@ -0,0 +1,245 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of Parity Secret Store.
+
+// Parity Secret Store is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Secret Store is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Weights of module dispatchables.
+//!
+//! Every function of `WeightInfo` corresponds to the benchmark with the same name
+//! from `benchmarking.rs`. Arguments of functions are benchmark components:
+//! `k` is the number of key servers in the current set, `a` is the size of key
+//! server network address, `n` is the number of key approvers, `s` is the
+//! size of document key shadow, `r` and `e` are the numbers of active and expired
+//! requests in the service queue.
+//!
+//! Default weights are estimated from the number of storage reads and writes
+//! performed by every dispatchable. They have not been produced by benchmarks
+//! yet and must be replaced with benchmark results, collected on the reference
+//! hardware. To collect them, run `benchmark.sh` from the module directory. It
+//! builds the node with `runtime-benchmarks` feature and runs every benchmark,
+//! defined in `benchmarking.rs`, e.g.:
+//!
+//! ```text
+//! parity-secretstore-substrate-node benchmark --chain dev --pallet secretstore \
+//!     --extrinsic generate_server_key --steps 10 --repeat 20
+//! ```
+
+use frame_support::{
+	StorageValue,
+	weights::{ClassifyDispatch, DispatchClass, PaysFee, WeighData, Weight},
+};
+use crate::{Trait, CurrentKeyServersCount};
+
+/// Weight functions of module dispatchables.
+pub trait WeightInfo {
+	fn change_owner() -> Weight;
+	fn claim_id() -> Weight;
+	fn complete_initialization() -> Weight;
+	fn add_key_server(k: u32, a: u32) -> Weight;
+	fn remove_key_server() -> Weight;
+	fn start_migration(k: u32) -> Weight;
+	fn confirm_migration(k: u32) -> Weight;
+	fn set_request_lifetime() -> Weight;
+	fn claim_key() -> Weight;
+	fn transfer_key() -> Weight;
+	fn grant_key_access() -> Weight;
+	fn revoke_key_access() -> Weight;
+	fn set_key_access_policy() -> Weight;
+	fn set_key_approvers(n: u32) -> Weight;
+	fn approve_key_access() -> Weight;
+	fn revoke_key_access_approval() -> Weight;
+	fn generate_server_key(k: u32) -> Weight;
+	fn server_key_generated(k: u32) -> Weight;
+	fn server_key_generation_error(k: u32) -> Weight;
+	fn retrieve_server_key() -> Weight;
+	fn server_key_retrieved(k: u32) -> Weight;
+	fn server_key_retrieval_error(k: u32) -> Weight;
+	fn delete_server_key() -> Weight;
+	fn server_key_deleted(k: u32) -> Weight;
+	fn server_key_deletion_error(k: u32) -> Weight;
+	fn store_document_key() -> Weight;
+	fn document_key_stored(k: u32) -> Weight;
+	fn document_key_store_error(k: u32) -> Weight;
+	fn retrieve_document_key_shadow() -> Weight;
+	fn document_key_common_retrieved(k: u32) -> Weight;
+	fn document_key_personal_retrieved(k: u32, s: u32) -> Weight;
+	fn document_key_shadow_retrieval_error(k: u32) -> Weight;
+	fn sign_message_schnorr() -> Weight;
+	fn schnorr_signature_generated(k: u32) -> Weight;
+	fn schnorr_signing_error(k: u32) -> Weight;
+	fn sign_message_ecdsa() -> Weight;
+	fn ecdsa_signature_generated(k: u32) -> Weight;
+	fn ecdsa_signing_error(k: u32) -> Weight;
+	fn expire_requests(r: u32, e: u32, k: u32) -> Weight;
+}
+
+/// Weight of storage read.
+const READ: Weight = 250;
+/// Weight of storage write.
+const WRITE: Weight = 1_000;
+/// Weight of service fee transfer to single key server.
+const FEE_TRANSFER: Weight = 2 * READ + 2 * WRITE;
+
+impl WeightInfo for () {
+	fn change_owner() -> Weight {
+		5_000 + READ + WRITE
+	}
+	fn claim_id() -> Weight {
+		5_000 + 2 * READ + 2 * WRITE
+	}
+	fn complete_initialization() -> Weight {
+		5_000 + 2 * READ + WRITE
+	}
+	fn add_key_server(k: u32, a: u32) -> Weight {
+		10_000 + 3 * READ + 2 * WRITE + k * 2 * READ + a * 2
+	}
+	fn remove_key_server() -> Weight {
+		10_000 + 3 * READ + 2 * WRITE
+	}
+	fn start_migration(k: u32) -> Weight {
+		10_000 + 3 * READ + 2 * WRITE + k * (2 * READ + WRITE)
+	}
+	fn confirm_migration(k: u32) -> Weight {
+		10_000 + 4 * READ + 4 * WRITE + k * (3 * READ + 4 * WRITE)
+	}
+	fn set_request_lifetime() -> Weight {
+		5_000 + WRITE
+	}
+	fn claim_key() -> Weight {
+		5_000 + 2 * READ + WRITE
+	}
+	fn transfer_key() -> Weight {
+		5_000 + 2 * READ + WRITE
+	}
+	fn grant_key_access() -> Weight {
+		5_000 + 2 * READ + WRITE
+	}
+	fn revoke_key_access() -> Weight {
+		5_000 + 3 * READ + WRITE
+	}
+	fn set_key_access_policy() -> Weight {
+		5_000 + 2 * READ + WRITE
+	}
+	fn set_key_approvers(n: u32) -> Weight {
+		10_000 + 2 * READ + 2 * WRITE + n * 100
+	}
+	fn approve_key_access() -> Weight {
+		10_000 + 3 * READ + WRITE
+	}
+	fn revoke_key_access_approval() -> Weight {
+		10_000 + 2 * READ + WRITE
+	}
+	fn generate_server_key(k: u32) -> Weight {
+		20_000 + 6 * READ + 4 * WRITE + k * READ
+	}
+	fn server_key_generated(k: u32) -> Weight {
+		20_000 + 6 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn server_key_generation_error(k: u32) -> Weight {
+		15_000 + 5 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn retrieve_server_key() -> Weight {
+		15_000 + 5 * READ + 4 * WRITE
+	}
+	fn server_key_retrieved(k: u32) -> Weight {
+		25_000 + 8 * READ + 6 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn server_key_retrieval_error(k: u32) -> Weight {
+		25_000 + 8 * READ + 6 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn delete_server_key() -> Weight {
+		15_000 + 6 * READ + 4 * WRITE
+	}
+	fn server_key_deleted(k: u32) -> Weight {
+		25_000 + 6 * READ + 7 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn server_key_deletion_error(k: u32) -> Weight {
+		15_000 + 5 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn store_document_key() -> Weight {
+		15_000 + 5 * READ + 4 * WRITE
+	}
+	fn document_key_stored(k: u32) -> Weight {
+		20_000 + 6 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn document_key_store_error(k: u32) -> Weight {
+		15_000 + 5 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn retrieve_document_key_shadow() -> Weight {
+		25_000 + 5 * READ + 4 * WRITE
+	}
+	fn document_key_common_retrieved(k: u32) -> Weight {
+		20_000 + 6 * READ + 3 * WRITE + k * READ
+	}
+	fn document_key_personal_retrieved(k: u32, s: u32) -> Weight {
+		25_000 + 7 * READ + 5 * WRITE + k * (2 * READ + FEE_TRANSFER) + s * 2
+	}
+	fn document_key_shadow_retrieval_error(k: u32) -> Weight {
+		20_000 + 6 * READ + 5 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn sign_message_schnorr() -> Weight {
+		15_000 + 5 * READ + 4 * WRITE
+	}
+	fn schnorr_signature_generated(k: u32) -> Weight {
+		20_000 + 6 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn schnorr_signing_error(k: u32) -> Weight {
+		15_000 + 5 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn sign_message_ecdsa() -> Weight {
+		15_000 + 5 * READ + 4 * WRITE
+	}
+	fn ecdsa_signature_generated(k: u32) -> Weight {
+		20_000 + 6 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn ecdsa_signing_error(k: u32) -> Weight {
+		15_000 + 5 * READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER)
+	}
+	fn expire_requests(r: u32, e: u32, k: u32) -> Weight {
+		1_000 + READ + r * READ + e * (10_000 + READ + 4 * WRITE + k * (2 * READ + FEE_TRANSFER))
+	}
+}
+
+/// Weight of dispatchable that depends on the number of key servers in the current set.
+pub struct KeyServersWeight<T>(fn(u32) -> Weight, sp_std::marker::PhantomData<T>);
+
+impl<T> KeyServersWeight<T> {
+	/// Create weight from weight function.
+	pub fn new(weight: fn(u32) -> Weight) -> Self {
+		KeyServersWeight(weight, Default::default())
+	}
+}
+
+impl<T: Trait, Args> WeighData<Args> for KeyServersWeight<T> {
+	fn weigh_data(&self, _: Args) -> Weight {
+		(self.0)(current_key_servers_count::<T>())
+	}
+}
+
+impl<T, Args> ClassifyDispatch<Args> for KeyServersWeight<T> {
+	fn classify_dispatch(&self, _: Args) -> DispatchClass {
+		DispatchClass::Normal
+	}
+}
+
+impl<T, Args> PaysFee<Args> for KeyServersWeight<T> {
+	fn pays_fee(&self, _: Args) -> bool {
+		true
+	}
+}
+
+/// Returns number of key servers in the current set.
+pub fn current_key_servers_count<T: Trait>() -> u32 {
+	CurrentKeyServersCount::get()
+}
@@ -14,13 +14,39 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Secret Store.  If not, see <http://www.gnu.org/licenses/>.
 
+use codec::{Encode, Decode};
 use crate::{EntityId, ServerKeyId};
 
+/// Level of access to the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum KeyAccessLevel {
+	/// Access to operations that are not revealing private portion of the key
+	/// (like server key retrieval).
+	Read,
+	/// Access to all operations that are involving the key, including document
+	/// key retrieval and message signing.
+	Decrypt,
+}
+
+impl KeyAccessLevel {
+	/// Returns true if this access level allows operations of given access level.
+	pub fn allows(&self, level: KeyAccessLevel) -> bool {
+		match (*self, level) {
+			(KeyAccessLevel::Decrypt, _) => true,
+			(KeyAccessLevel::Read, KeyAccessLevel::Read) => true,
+			(KeyAccessLevel::Read, KeyAccessLevel::Decrypt) => false,
+		}
+	}
+}
+
 sp_api::decl_runtime_apis! {
 	/// API for checking key access rights.
+	#[api_version(2)]
 	pub trait SecretStoreAclApi {
 		/// Check if requestor can perform operations that are involving key
 		/// with the given ID.
 		fn check(requester: EntityId, key: ServerKeyId) -> bool;
+		/// Check if requestor has given level of access to the key with the given ID.
+		fn check_access(requester: EntityId, key: ServerKeyId, level: KeyAccessLevel) -> bool;
 	}
 }
@@ -27,12 +27,18 @@ pub enum ServiceTask {
 	GenerateServerKey(ServerKeyId, EntityId, u8),
 	/// Retrieve server key (server_key_id).
 	RetrieveServerKey(ServerKeyId),
+	/// Delete server key (server_key_id, author).
+	DeleteServerKey(ServerKeyId, EntityId),
 	/// Store document key (server_key_id, author, common_point, encrypted_point).
 	StoreDocumentKey(ServerKeyId, EntityId, sp_core::H512, sp_core::H512),
 	/// Retrieve common data of document key (server_key_id, requester).
 	RetrieveShadowDocumentKeyCommon(ServerKeyId, EntityId),
 	/// Retrieve personal data of document key (server_key_id, requester).
 	RetrieveShadowDocumentKeyPersonal(ServerKeyId, sp_core::H512),
+	/// Sign message with Schnorr signature (server_key_id, requester, message_hash).
+	SchnorrSignMessage(ServerKeyId, EntityId, sp_core::H256),
+	/// Sign message with ECDSA signature (server_key_id, requester, message_hash).
+	EcdsaSignMessage(ServerKeyId, EntityId, sp_core::H256),
 }
 
 sp_api::decl_runtime_apis! {
@@ -48,6 +54,11 @@ sp_api::decl_runtime_apis! {
 		/// Check if server key retrieval response is required from given key server.
 		fn is_server_key_retrieval_response_required(key_server: KeyServerId, key: ServerKeyId) -> bool;
 
+		/// Get pending server key deletion tasks.
+		fn server_key_deletion_tasks(begin: u32, end: u32) -> Vec<ServiceTask>;
+		/// Check if server key deletion response is required from given key server.
+		fn is_server_key_deletion_response_required(key_server: KeyServerId, key: ServerKeyId) -> bool;
+
 		///
 		fn document_key_store_tasks(begin: u32, end: u32) -> Vec<ServiceTask>;
 		/// Check if server key retrieval response is required from given key server.
@@ -57,5 +68,25 @@ sp_api::decl_runtime_apis! {
 		fn document_key_shadow_retrieval_tasks(begin: u32, end: u32) -> Vec<ServiceTask>;
 		///
 		fn is_document_key_shadow_retrieval_response_required(key_server: KeyServerId, key: ServerKeyId, requester: EntityId) -> bool;
+
+		/// Get pending Schnorr signing tasks.
+		fn schnorr_signing_tasks(begin: u32, end: u32) -> Vec<ServiceTask>;
+		/// Check if Schnorr signing response is required from given key server.
+		fn is_schnorr_signing_response_required(
+			key_server: KeyServerId,
+			key: ServerKeyId,
+			requester: EntityId,
+			message: sp_core::H256,
+		) -> bool;
+
+		/// Get pending ECDSA signing tasks.
+		fn ecdsa_signing_tasks(begin: u32, end: u32) -> Vec<ServiceTask>;
+		/// Check if ECDSA signing response is required from given key server.
+		fn is_ecdsa_signing_response_required(
+			key_server: KeyServerId,
+			key: ServerKeyId,
+			requester: EntityId,
+			message: sp_core::H256,
+		) -> bool;
 	}
 }
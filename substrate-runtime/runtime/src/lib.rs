@@ -109,7 +109,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("bridge-node"),
 	impl_name: create_runtime_str!("bridge-node"),
 	authoring_version: 1,
-	spec_version: 1,
+	spec_version: 2,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 };
@@ -238,6 +238,8 @@ impl pallet_sudo::Trait for Runtime {
 impl secretstore_runtime_module::Trait for Runtime {
 	type Event = Event;
 	type Currency = Balances;
+	type KeyAccessPolicy = secretstore_runtime_module::AllowListPolicy;
+	type WeightInfo = ();
 }
 
 construct_runtime!(
@@ -385,6 +387,14 @@ impl_runtime_apis! {
 		) -> bool {
 			SecretStore::check_key_access(key, requester)
 		}
+
+		fn check_access(
+			requester: secretstore_runtime_primitives::EntityId,
+			key: secretstore_runtime_primitives::ServerKeyId,
+			level: secretstore_runtime_primitives::acl_storage::KeyAccessLevel,
+		) -> bool {
+			SecretStore::check_key_access_level(key, requester, level)
+		}
 	}
 
 	impl secretstore_runtime_primitives::key_server_set::SecretStoreKeyServerSetApi<Block> for Runtime {
@@ -428,6 +438,20 @@ impl_runtime_apis! {
 			SecretStore::is_server_key_retrieval_response_required(key_server, key_id)
 		}
 
+		fn server_key_deletion_tasks(
+			begin: u32,
+			end: u32,
+		) -> Vec<secretstore_runtime_primitives::service::ServiceTask> {
+			SecretStore::server_key_deletion_tasks(begin, end)
+		}
+
+		fn is_server_key_deletion_response_required(
+			key_server: secretstore_runtime_primitives::KeyServerId,
+			key_id: secretstore_runtime_primitives::ServerKeyId,
+		) -> bool {
+			SecretStore::is_server_key_deletion_response_required(key_server, key_id)
+		}
+
 		fn document_key_store_tasks(
 			begin: u32,
 			end: u32,
@@ -456,5 +480,64 @@ impl_runtime_apis! {
 		) -> bool {
 			SecretStore::is_document_key_shadow_retrieval_response_required(key_server, key_id, requester)
 		}
+
+		fn schnorr_signing_tasks(
+			begin: u32,
+			end: u32,
+		) -> Vec<secretstore_runtime_primitives::service::ServiceTask> {
+			SecretStore::schnorr_signing_tasks(begin, end)
+		}
+
+		fn is_schnorr_signing_response_required(
+			key_server: secretstore_runtime_primitives::KeyServerId,
+			key_id: secretstore_runtime_primitives::ServerKeyId,
+			requester: secretstore_runtime_primitives::EntityId,
+			message: sp_core::H256,
+		) -> bool {
+			SecretStore::is_schnorr_signing_response_required(key_server, key_id, requester, message)
+		}
+
+		fn ecdsa_signing_tasks(
+			begin: u32,
+			end: u32,
+		) -> Vec<secretstore_runtime_primitives::service::ServiceTask> {
+			SecretStore::ecdsa_signing_tasks(begin, end)
+		}
+
+		fn is_ecdsa_signing_response_required(
+			key_server: secretstore_runtime_primitives::KeyServerId,
+			key_id: secretstore_runtime_primitives::ServerKeyId,
+			requester: secretstore_runtime_primitives::EntityId,
+			message: sp_core::H256,
+		) -> bool {
+			SecretStore::is_ecdsa_signing_response_required(key_server, key_id, requester, message)
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	impl frame_benchmarking::Benchmark<Block> for Runtime {
+		fn dispatch_benchmark(
+			module: Vec<u8>,
+			extrinsic: Vec<u8>,
+			lowest_range_values: Vec<u32>,
+			highest_range_values: Vec<u32>,
+			steps: Vec<u32>,
+			repeat: u32,
+		) -> Result<Vec<frame_benchmarking::BenchmarkResults>, sp_runtime::RuntimeString> {
+			use frame_benchmarking::Benchmarking;
+
+			let result = match module.as_slice() {
+				b"secretstore" | b"secret-store" => SecretStore::run_benchmark(
+					extrinsic,
+					lowest_range_values,
+					highest_range_values,
+					steps,
+					repeat,
+				),
+				_ => Err("Benchmark not found for this pallet."),
+			};
+
+			result.map_err(|e| e.into())
+		}
 	}
 }
@@ -24,7 +24,7 @@ use std::{
 use futures::{FutureExt, Stream, StreamExt, future::ready};
 use log::trace;
 use primitives::{
-	Address, KeyServerId, Public, ServerKeyId,
+	Address, H256, KeyServerId, Public, ServerKeyId, Signature,
 	error::Error,
 	executor::Executor,
 	key_server::KeyServer,
@@ -67,6 +67,10 @@ pub enum SecretStoreCall {
 	ServerKeyRetrieved(ServerKeyId, Public, u8),
 	/// Called when server key retrieval error happens.
 	ServerKeyRetrievalError(ServerKeyId),
+	/// Called when server key is deleted.
+	ServerKeyDeleted(ServerKeyId),
+	/// Called when server key deletion error happens.
+	ServerKeyDeletionError(ServerKeyId),
 	/// Called when document key is stored.
 	DocumentKeyStored(ServerKeyId),
 	/// Called when document key store error happens.
@@ -77,6 +81,14 @@ pub enum SecretStoreCall {
 	DocumentKeyPersonalRetrieved(ServerKeyId, Address, Vec<Address>, Public, Vec<u8>),
 	/// Called when document key shadow retireval error happens.
 	DocumentKeyShadowRetrievalError(ServerKeyId, Address),
+	/// Called when Schnorr signature is generated.
+	SchnorrSignatureGenerated(ServerKeyId, Address, H256, H256, H256, u8),
+	/// Called when Schnorr signing error happens.
+	SchnorrSigningError(ServerKeyId, Address, H256),
+	/// Called when ECDSA signature is generated.
+	EcdsaSignatureGenerated(ServerKeyId, Address, H256, Signature, u8),
+	/// Called when ECDSA signing error happens.
+	EcdsaSigningError(ServerKeyId, Address, H256),
 }
 
 /// Substrate blockchain.
@@ -128,6 +140,19 @@ pub trait Blockchain: 'static + Send + Sync {
 		key_server_id: KeyServerId,
 	) -> Self::ResponseRequiredFuture;
 
+	/// Get pending server key deletion tasks range at given block.
+	fn server_key_deletion_tasks(
+		&self,
+		block_hash: Self::BlockHash,
+		range: Range<usize>,
+	) -> Self::PendingEventsStream;
+	/// Is server key deletion request response required?
+	fn is_server_key_deletion_response_required(
+		&self,
+		key_id: ServerKeyId,
+		key_server_id: KeyServerId,
+	) -> Self::ResponseRequiredFuture;
+
 	/// Get pending document key store tasks range at given block.
 	fn document_key_store_tasks(
 		&self,
@@ -154,6 +179,36 @@ pub trait Blockchain: 'static + Send + Sync {
 		requester: Address,
 		key_server_id: KeyServerId,
 	) -> Self::ResponseRequiredFuture;
+
+	/// Get pending Schnorr signing tasks range at given block.
+	fn schnorr_signing_tasks(
+		&self,
+		block_hash: Self::BlockHash,
+		range: Range<usize>,
+	) -> Self::PendingEventsStream;
+	/// Is Schnorr signing request response required?
+	fn is_schnorr_signing_response_required(
+		&self,
+		key_id: ServerKeyId,
+		requester: Address,
+		message: H256,
+		key_server_id: KeyServerId,
+	) -> Self::ResponseRequiredFuture;
+
+	/// Get pending ECDSA signing tasks range at given block.
+	fn ecdsa_signing_tasks(
+		&self,
+		block_hash: Self::BlockHash,
+		range: Range<usize>,
+	) -> Self::PendingEventsStream;
+	/// Is ECDSA signing request response required?
+	fn is_ecdsa_signing_response_required(
+		&self,
+		key_id: ServerKeyId,
+		requester: Address,
+		message: H256,
+		key_server_id: KeyServerId,
+	) -> Self::ResponseRequiredFuture;
 }
 
 /// Transaction pool API.
@@ -259,6 +314,13 @@ impl<B: Blockchain> blockchain_service::Block for SubstrateBlock<B> {
 				block_hash,
 				range,
 			),
+		)).chain(pending_tasks_stream(
+			self.blockchain.clone(),
+			self.block_hash.clone(),
+			|blockchain, block_hash, range| blockchain.server_key_deletion_tasks(
+				block_hash,
+				range,
+			),
 		)).chain(pending_tasks_stream(
 			self.blockchain.clone(),
 			self.block_hash.clone(),
@@ -273,6 +335,20 @@ impl<B: Blockchain> blockchain_service::Block for SubstrateBlock<B> {
 				block_hash,
 				range,
 			),
+		)).chain(pending_tasks_stream(
+			self.blockchain.clone(),
+			self.block_hash.clone(),
+			|blockchain, block_hash, range| blockchain.schnorr_signing_tasks(
+				block_hash,
+				range,
+			),
+		)).chain(pending_tasks_stream(
+			self.blockchain.clone(),
+			self.block_hash.clone(),
+			|blockchain, block_hash, range| blockchain.ecdsa_signing_tasks(
+				block_hash,
+				range,
+			),
 		)).boxed()
 	}
 
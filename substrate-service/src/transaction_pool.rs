@@ -21,11 +21,12 @@ use std::{
 use futures::future::{FutureExt, TryFutureExt, ready};
 use log::{error, trace};
 use primitives::{
-	Address, ServerKeyId,
+	Address, H256, ServerKeyId,
 	executor::Executor,
 	key_server::{
 		ServerKeyGenerationArtifacts, ServerKeyRetrievalArtifacts,
 		DocumentKeyCommonRetrievalArtifacts, DocumentKeyShadowRetrievalArtifacts,
+		SchnorrSigningArtifacts, EcdsaSigningArtifacts,
 	},
 	requester::Requester,
 };
@@ -168,6 +169,22 @@ impl<E, B, P> blockchain_service::TransactionPool
 		)
 	}
 
+	fn publish_deleted_server_key(&self, _origin: Address, key_id: ServerKeyId) {
+		self.submit_response_transaction(
+			format!("ServerKeyDeletionSuccess({})", key_id),
+			self.blockchain.is_server_key_deletion_response_required(key_id, self.key_server_address),
+			move || Ok(SecretStoreCall::ServerKeyDeleted(key_id)),
+		)
+	}
+
+	fn publish_server_key_deletion_error(&self, _origin: Address, key_id: ServerKeyId) {
+		self.submit_response_transaction(
+			format!("ServerKeyDeletionFailure({})", key_id),
+			self.blockchain.is_server_key_deletion_response_required(key_id, self.key_server_address),
+			move || Ok(SecretStoreCall::ServerKeyDeletionError(key_id)),
+		)
+	}
+
 	fn publish_stored_document_key(&self, _origin: Address, key_id: ServerKeyId) {
 		self.submit_response_transaction(
 			format!("DocumentKeyStoreSuccess({})", key_id),
@@ -320,6 +337,149 @@ impl<E, B, P> blockchain_service::TransactionPool
 				)),
 		)
 	}
+
+	fn publish_schnorr_signature(
+		&self,
+		_origin: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+		artifacts: SchnorrSigningArtifacts,
+	) {
+		let blockchain = self.blockchain.clone();
+		let key_server_address = self.key_server_address;
+		self.submit_response_transaction(
+			format!("SchnorrSigningSuccess({}, {}, {:?})", key_id, requester, message),
+			ready(requester.address(&key_id).map_err(Into::into))
+				.and_then(move |requester|
+					blockchain
+						.is_schnorr_signing_response_required(
+							key_id,
+							requester,
+							message,
+							key_server_address,
+						)
+				),
+			move || serialize_threshold(artifacts.threshold)
+				.and_then(|threshold|
+					requester
+						.address(&key_id)
+						.map_err(Into::into)
+						.map(|requester| (threshold, requester))
+				)
+				.map(|(threshold, requester)| SecretStoreCall::SchnorrSignatureGenerated(
+					key_id,
+					requester,
+					message,
+					artifacts.signature_c,
+					artifacts.signature_s,
+					threshold,
+				)),
+		)
+	}
+
+	fn publish_schnorr_signing_error(
+		&self,
+		_origin: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+	) {
+		let blockchain = self.blockchain.clone();
+		let key_server_address = self.key_server_address;
+		self.submit_response_transaction(
+			format!("SchnorrSigningFailure({}, {}, {:?})", key_id, requester, message),
+			ready(requester.address(&key_id).map_err(Into::into))
+				.and_then(move |requester|
+					blockchain
+						.is_schnorr_signing_response_required(
+							key_id,
+							requester,
+							message,
+							key_server_address,
+						)
+				),
+			move || requester
+				.address(&key_id)
+				.map_err(Into::into)
+				.map(|requester| SecretStoreCall::SchnorrSigningError(
+					key_id,
+					requester,
+					message,
+				)),
+		)
+	}
+
+	fn publish_ecdsa_signature(
+		&self,
+		_origin: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+		artifacts: EcdsaSigningArtifacts,
+	) {
+		let blockchain = self.blockchain.clone();
+		let key_server_address = self.key_server_address;
+		self.submit_response_transaction(
+			format!("EcdsaSigningSuccess({}, {}, {:?})", key_id, requester, message),
+			ready(requester.address(&key_id).map_err(Into::into))
+				.and_then(move |requester|
+					blockchain
+						.is_ecdsa_signing_response_required(
+							key_id,
+							requester,
+							message,
+							key_server_address,
+						)
+				),
+			move || serialize_threshold(artifacts.threshold)
+				.and_then(|threshold|
+					requester
+						.address(&key_id)
+						.map_err(Into::into)
+						.map(|requester| (threshold, requester))
+				)
+				.map(|(threshold, requester)| SecretStoreCall::EcdsaSignatureGenerated(
+					key_id,
+					requester,
+					message,
+					artifacts.signature,
+					threshold,
+				)),
+		)
+	}
+
+	fn publish_ecdsa_signing_error(
+		&self,
+		_origin: Address,
+		key_id: ServerKeyId,
+		requester: Requester,
+		message: H256,
+	) {
+		let blockchain = self.blockchain.clone();
+		let key_server_address = self.key_server_address;
+		self.submit_response_transaction(
+			format!("EcdsaSigningFailure({}, {}, {:?})", key_id, requester, message),
+			ready(requester.address(&key_id).map_err(Into::into))
+				.and_then(move |requester|
+					blockchain
+						.is_ecdsa_signing_response_required(
+							key_id,
+							requester,
+							message,
+							key_server_address,
+						)
+				),
+			move || requester
+				.address(&key_id)
+				.map_err(Into::into)
+				.map(|requester| SecretStoreCall::EcdsaSigningError(
+					key_id,
+					requester,
+					message,
+				)),
+		)
+	}
 }
 
 /// Serialize threshold (we only support 256 KS at max).